@@ -0,0 +1,388 @@
+//! Offline prompt QA. Runs a YAML suite of conversation scenarios through the prompt
+//! builder and a mock or real provider, then scores the replies.
+//!
+//! `cargo run --bin eval -- evals/basic.yaml [--provider mock|gemini] [--model <name>]`
+
+use anyhow::{Context, Result};
+use chloe::services::gemini_types::{self, GeminiRequest, GeminiResponse};
+use chloe::services::llm_service::{
+    ConversationContext, MessageContext, UserInfo, default_tool_executor, model_endpoint,
+};
+use chloe::services::model_router::{ModelRouter, TaskType};
+use chloe::services::prompt_builder::PromptBuilder;
+use chloe::tools::ToolName;
+use chloe::utils::Paginator;
+use chloe::utils::reaction_tracker::ReactionTracker;
+use serde::Deserialize;
+use serde_json::Value;
+use std::sync::Arc;
+
+const DEFAULT_SYSTEM_PROMPT: &str = "You're Chloe, a discord bot.";
+
+/// Discord's message limit, used when a scenario doesn't set its own
+const DEFAULT_MAX_LENGTH: usize = 2000;
+
+#[derive(Debug, Deserialize)]
+struct Suite {
+    system_prompt: Option<String>,
+    scenarios: Vec<Scenario>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Scenario {
+    name: String,
+    #[serde(default = "default_user")]
+    user: String,
+    message: String,
+    #[serde(default)]
+    history: Vec<HistoryMessage>,
+    /// Canned provider reply for `--provider mock`
+    mock: Option<MockReply>,
+    #[serde(default)]
+    expect: Expectations,
+}
+
+#[derive(Debug, Deserialize)]
+struct HistoryMessage {
+    user: String,
+    content: String,
+    #[serde(default)]
+    bot: bool,
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct MockReply {
+    #[serde(default)]
+    text: String,
+    tool: Option<String>,
+    #[serde(default)]
+    args: Value,
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct Expectations {
+    /// Tool the reply should call, `none` for a plain text reply
+    tool: Option<String>,
+    max_length: Option<usize>,
+    #[serde(default)]
+    banned: Vec<String>,
+    #[serde(default)]
+    contains: Vec<String>,
+}
+
+fn default_user() -> String {
+    "tester".to_string()
+}
+
+/// What the provider answered, with the user-visible text pulled out of send_message calls
+#[derive(Debug, Default)]
+struct Reply {
+    tool: Option<String>,
+    content: String,
+}
+
+impl Reply {
+    fn from_parts(text: &str, tool: Option<&str>, args: &Value) -> Self {
+        let content = match tool {
+            Some(name) if name == ToolName::DiscordSendMessage.as_str() => args
+                .get("content")
+                .and_then(|content| content.as_str())
+                .unwrap_or_default()
+                .to_string(),
+            _ => text.to_string(),
+        };
+        Self {
+            tool: tool.map(|name| name.to_string()),
+            content,
+        }
+    }
+}
+
+enum Provider {
+    Mock,
+    Gemini {
+        client: reqwest::Client,
+        api_key: String,
+        model: String,
+    },
+}
+
+impl Provider {
+    async fn respond(
+        &self,
+        scenario: &Scenario,
+        prompt: &str,
+        tool_definitions: Vec<Value>,
+    ) -> Result<Reply> {
+        match self {
+            Provider::Mock => {
+                let mock = scenario
+                    .mock
+                    .as_ref()
+                    .with_context(|| format!("Scenario '{}' has no mock reply", scenario.name))?;
+                Ok(Reply::from_parts(
+                    &mock.text,
+                    mock.tool.as_deref(),
+                    &mock.args,
+                ))
+            }
+            Provider::Gemini {
+                client,
+                api_key,
+                model,
+            } => {
+                let request = GeminiRequest::new(prompt)
+                    .with_tools(tool_definitions)
+                    .with_safety_settings(gemini_types::default_safety_settings());
+                let response: GeminiResponse = client
+                    .post(model_endpoint(model, api_key))
+                    .json(&request)
+                    .send()
+                    .await?
+                    .error_for_status()?
+                    .json()
+                    .await?;
+
+                let function_call = response.get_function_call();
+                Ok(Reply::from_parts(
+                    response.get_text().unwrap_or_default(),
+                    function_call.map(|call| call.name.as_str()),
+                    function_call.map(|call| &call.args).unwrap_or(&Value::Null),
+                ))
+            }
+        }
+    }
+}
+
+fn build_context(scenario: &Scenario) -> ConversationContext {
+    let mut user_info: Vec<UserInfo> = Vec::new();
+    let mut user_id =
+        |name: &str, is_bot: bool| match user_info.iter().find(|user| user.display_name == name) {
+            Some(user) => user.user_id,
+            None => {
+                let user_id = 100_000_000_000_000_000 + user_info.len() as u64;
+                user_info.push(UserInfo {
+                    display_name: name.to_string(),
+                    user_id,
+                    is_bot,
+                });
+                user_id
+            }
+        };
+
+    user_id(&scenario.user, false);
+    let recent_messages = scenario
+        .history
+        .iter()
+        .map(|message| MessageContext {
+            user_display_name: message.user.clone(),
+            user_id: user_id(&message.user, message.bot),
+            content: message.content.clone(),
+            is_bot: message.bot,
+            is_self: message.bot,
+            channel_id: 0,
+            images: Vec::new(),
+        })
+        .collect();
+
+    ConversationContext {
+        current_user: scenario.user.clone(),
+        current_message: scenario.message.clone(),
+        current_images: Vec::new(),
+        recent_messages,
+        user_info,
+        referenced_message: None,
+        is_random_reply: false,
+        instructions: None,
+        relevant_memories: Vec::new(),
+        author_activity: None,
+        linked_messages: Vec::new(),
+        persona: None,
+        guild_prompt: None,
+        memory_guild_id: None,
+    }
+}
+
+/// Every expectation the reply misses, empty when it passes
+fn score(expect: &Expectations, reply: &Reply) -> Vec<String> {
+    let mut failures = Vec::new();
+
+    match expect.tool.as_deref() {
+        Some("none") if reply.tool.is_some() => failures.push(format!(
+            "expected no tool call, got {}",
+            reply.tool.as_deref().unwrap_or_default()
+        )),
+        Some(tool) if tool != "none" && reply.tool.as_deref() != Some(tool) => {
+            failures.push(format!(
+                "expected tool {}, got {}",
+                tool,
+                reply.tool.as_deref().unwrap_or("none")
+            ))
+        }
+        _ => {}
+    }
+
+    let max_length = expect.max_length.unwrap_or(DEFAULT_MAX_LENGTH);
+    let length = reply.content.chars().count();
+    if length > max_length {
+        failures.push(format!(
+            "reply is {} chars, limit is {}",
+            length, max_length
+        ));
+    }
+
+    let content = reply.content.to_lowercase();
+    for banned in &expect.banned {
+        if content.contains(&banned.to_lowercase()) {
+            failures.push(format!("reply contains banned text '{}'", banned));
+        }
+    }
+    for expected in &expect.contains {
+        if !content.contains(&expected.to_lowercase()) {
+            failures.push(format!("reply is missing '{}'", expected));
+        }
+    }
+
+    failures
+}
+
+struct Options {
+    suite_path: String,
+    provider: String,
+    model: Option<String>,
+}
+
+fn parse_args(args: &[String]) -> Result<Options> {
+    let mut suite_path = None;
+    let mut provider = "mock".to_string();
+    let mut model = None;
+
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--provider" => provider = args.next().context("Missing value for --provider")?.clone(),
+            "--model" => model = Some(args.next().context("Missing value for --model")?.clone()),
+            _ if suite_path.is_none() => suite_path = Some(arg.clone()),
+            _ => anyhow::bail!("Unexpected argument {}", arg),
+        }
+    }
+
+    Ok(Options {
+        suite_path: suite_path
+            .context("Usage: eval <suite.yaml> [--provider mock|gemini] [--model <name>]")?,
+        provider,
+        model,
+    })
+}
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let options = parse_args(&args)?;
+
+    let suite: Suite = serde_yaml::from_str(
+        &std::fs::read_to_string(&options.suite_path)
+            .with_context(|| format!("Failed to read {}", options.suite_path))?,
+    )
+    .with_context(|| format!("Failed to parse {}", options.suite_path))?;
+
+    let provider = match options.provider.as_str() {
+        "mock" => Provider::Mock,
+        "gemini" => Provider::Gemini {
+            client: reqwest::Client::new(),
+            api_key: std::env::var("GEMINI_API_KEY")
+                .context("GEMINI_API_KEY environment variable not set")?,
+            model: options
+                .model
+                .unwrap_or_else(|| ModelRouter::from_env().select(TaskType::Chat, None)),
+        },
+        other => anyhow::bail!("Unknown provider {}, expected mock or gemini", other),
+    };
+
+    // the client only connects when used, and the eval never executes tools
+    let redis_client = redis::Client::open("redis://127.0.0.1")?;
+    let paginator = Arc::new(Paginator::new(redis_client.clone()));
+    let reactions = Arc::new(ReactionTracker::new(redis_client));
+    let tool_definitions = default_tool_executor(paginator, reactions).get_tool_definitions();
+    let system_prompt = suite
+        .system_prompt
+        .clone()
+        .unwrap_or_else(|| DEFAULT_SYSTEM_PROMPT.to_string());
+
+    let mut failed = 0;
+    for scenario in &suite.scenarios {
+        let context = build_context(scenario);
+        let prompt = PromptBuilder::new(system_prompt.clone(), tool_definitions.clone())
+            .build_enriched_prompt(&context, None)
+            .await;
+
+        let failures = match provider
+            .respond(scenario, &prompt, tool_definitions.clone())
+            .await
+        {
+            Ok(reply) => score(&scenario.expect, &reply),
+            Err(e) => vec![format!("provider error: {:#}", e)],
+        };
+
+        if failures.is_empty() {
+            println!("PASS {}", scenario.name);
+        } else {
+            failed += 1;
+            println!("FAIL {}: {}", scenario.name, failures.join("; "));
+        }
+    }
+
+    println!(
+        "{}/{} scenarios passed",
+        suite.scenarios.len() - failed,
+        suite.scenarios.len()
+    );
+    if failed > 0 {
+        std::process::exit(1);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_score_checks_tool_length_and_content() {
+        let reply = Reply::from_parts(
+            "",
+            Some("discord_send_message"),
+            &json!({"content": "As an AI I can't, bestie"}),
+        );
+
+        let expect = Expectations {
+            tool: Some("discord_send_message".to_string()),
+            max_length: Some(10),
+            banned: vec!["as an ai".to_string()],
+            contains: vec!["bestie".to_string()],
+        };
+        let failures = score(&expect, &reply);
+        assert_eq!(failures.len(), 2);
+
+        let expect = Expectations {
+            tool: Some("none".to_string()),
+            ..Default::default()
+        };
+        assert_eq!(score(&expect, &reply).len(), 1);
+    }
+
+    #[test]
+    fn test_build_context_assigns_stable_user_ids() {
+        let scenario: Scenario = serde_yaml::from_str(
+            "name: ids\nuser: alice\nmessage: hi\nhistory:\n  - {user: bob, content: yo}\n  - {user: alice, content: hey}\n",
+        )
+        .unwrap();
+        let context = build_context(&scenario);
+        assert_eq!(context.user_info.len(), 2);
+        assert_eq!(
+            context.recent_messages[1].user_id,
+            context.user_info[0].user_id
+        );
+    }
+}
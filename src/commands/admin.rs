@@ -0,0 +1,105 @@
+use super::reply;
+use crate::{Context, Error};
+use chloe::services::reply_visibility::ReplyKind;
+use poise::serenity_prelude as serenity;
+use tracing::info;
+
+/// Manage who can run chloe's global commands
+#[poise::command(slash_command, subcommands("promote", "demote"), subcommand_required)]
+pub async fn admin(_ctx: Context<'_>) -> Result<(), Error> {
+    Ok(())
+}
+
+/// Make someone a superadmin
+#[poise::command(slash_command, check = "super::permissions::require_superadmin")]
+pub async fn promote(
+    ctx: Context<'_>,
+    #[description = "User to make a superadmin"] user: serenity::User,
+) -> Result<(), Error> {
+    if user.bot {
+        return reply::say(ctx, ReplyKind::Admin, "bots can't be superadmins").await;
+    }
+
+    let changed = ctx
+        .data()
+        .user_service
+        .set_superadmin(
+            user.id.get() as i64,
+            true,
+            Some(ctx.author().id.get() as i64),
+        )
+        .await?;
+    info!(
+        event = "superadmin_promoted",
+        user_id = %user.id,
+        promoted_by = %ctx.author().id,
+        changed = changed,
+        "Superadmin promote command used"
+    );
+
+    if changed {
+        reply::say(
+            ctx,
+            ReplyKind::Admin,
+            format!("👑 <@{}> is a superadmin now", user.id),
+        )
+        .await
+    } else {
+        reply::say(
+            ctx,
+            ReplyKind::Admin,
+            format!("<@{}> is already a superadmin", user.id),
+        )
+        .await
+    }
+}
+
+/// Take superadmin away from someone
+#[poise::command(slash_command, check = "super::permissions::require_superadmin")]
+pub async fn demote(
+    ctx: Context<'_>,
+    #[description = "Superadmin to demote"] user: serenity::User,
+) -> Result<(), Error> {
+    // keeps the last superadmin from locking everyone out by accident
+    if user.id == ctx.author().id {
+        return reply::say(
+            ctx,
+            ReplyKind::Admin,
+            "you can't demote yourself, ask another superadmin",
+        )
+        .await;
+    }
+
+    let changed = ctx
+        .data()
+        .user_service
+        .set_superadmin(
+            user.id.get() as i64,
+            false,
+            Some(ctx.author().id.get() as i64),
+        )
+        .await?;
+    info!(
+        event = "superadmin_demoted",
+        user_id = %user.id,
+        demoted_by = %ctx.author().id,
+        changed = changed,
+        "Superadmin demote command used"
+    );
+
+    if changed {
+        reply::say(
+            ctx,
+            ReplyKind::Admin,
+            format!("<@{}> isn't a superadmin anymore", user.id),
+        )
+        .await
+    } else {
+        reply::say(
+            ctx,
+            ReplyKind::Admin,
+            format!("<@{}> wasn't a superadmin", user.id),
+        )
+        .await
+    }
+}
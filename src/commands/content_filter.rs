@@ -0,0 +1,292 @@
+use super::reply;
+use crate::{Context, Error};
+use chloe::services::content_incidents::{CONTENT_REPORTS_SETTING, RECENT_INCIDENTS_LIMIT};
+use chloe::services::content_safety::{
+    BlockThreshold, CONTENT_SAFETY_SETTING, HarmCategory, SafetyThresholds,
+};
+use chloe::services::reply_visibility::ReplyKind;
+use poise::serenity_prelude as serenity;
+use serde_json::{Value, json};
+use tracing::info;
+
+#[derive(Debug, Clone, Copy, poise::ChoiceParameter)]
+pub enum Category {
+    #[name = "harassment"]
+    Harassment,
+    #[name = "hate speech"]
+    HateSpeech,
+    #[name = "sexually explicit"]
+    SexuallyExplicit,
+    #[name = "dangerous content"]
+    DangerousContent,
+}
+
+impl From<Category> for HarmCategory {
+    fn from(category: Category) -> Self {
+        match category {
+            Category::Harassment => HarmCategory::Harassment,
+            Category::HateSpeech => HarmCategory::HateSpeech,
+            Category::SexuallyExplicit => HarmCategory::SexuallyExplicit,
+            Category::DangerousContent => HarmCategory::DangerousContent,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, poise::ChoiceParameter)]
+pub enum Threshold {
+    #[name = "block nothing"]
+    None,
+    #[name = "block only high risk"]
+    OnlyHigh,
+    #[name = "block medium risk and above"]
+    MediumAndAbove,
+    #[name = "block low risk and above"]
+    LowAndAbove,
+}
+
+impl From<Threshold> for BlockThreshold {
+    fn from(threshold: Threshold) -> Self {
+        match threshold {
+            Threshold::None => BlockThreshold::None,
+            Threshold::OnlyHigh => BlockThreshold::OnlyHigh,
+            Threshold::MediumAndAbove => BlockThreshold::MediumAndAbove,
+            Threshold::LowAndAbove => BlockThreshold::LowAndAbove,
+        }
+    }
+}
+
+/// Choose what the model's content filter blocks
+#[poise::command(
+    slash_command,
+    subcommands("show", "set", "reset", "floor", "reports", "incidents"),
+    subcommand_required
+)]
+pub async fn contentfilter(_ctx: Context<'_>) -> Result<(), Error> {
+    Ok(())
+}
+
+/// Show what's blocked in this server
+#[poise::command(slash_command, guild_only, check = "super::permissions::require_admin")]
+pub async fn show(ctx: Context<'_>) -> Result<(), Error> {
+    let Some(guild_id) = ctx.guild_id() else {
+        return Ok(());
+    };
+    let guild = guild_thresholds(ctx, guild_id.get() as i64).await;
+    let floor = ctx.data().settings.safety_floor().await;
+    reply::say(ctx, ReplyKind::Admin, describe(&guild, &floor)).await
+}
+
+/// Change what's blocked for one category in this server
+#[poise::command(slash_command, guild_only, check = "super::permissions::require_admin")]
+pub async fn set(
+    ctx: Context<'_>,
+    #[description = "Kind of harm"] category: Category,
+    #[description = "How likely it has to be before the reply is blocked"] threshold: Threshold,
+) -> Result<(), Error> {
+    let Some(guild_id) = ctx.guild_id() else {
+        return Ok(());
+    };
+    let guild_id = guild_id.get() as i64;
+    let mut guild = guild_thresholds(ctx, guild_id).await;
+    guild.set(category.into(), threshold.into());
+
+    let setting = guild.to_setting();
+    ctx.data()
+        .guild_service
+        .update_guild_settings(guild_id, &json!({ CONTENT_SAFETY_SETTING: setting }))
+        .await?;
+    info!(
+        event = "guild_content_safety_set",
+        guild_id = guild_id,
+        thresholds = %setting,
+        "Guild content safety thresholds updated"
+    );
+
+    let floor = ctx.data().settings.safety_floor().await;
+    reply::say(
+        ctx,
+        ReplyKind::Admin,
+        format!("✅ {}", describe(&guild, &floor)),
+    )
+    .await
+}
+
+/// Go back to chloe's default content filter in this server
+#[poise::command(slash_command, guild_only, check = "super::permissions::require_admin")]
+pub async fn reset(ctx: Context<'_>) -> Result<(), Error> {
+    let Some(guild_id) = ctx.guild_id() else {
+        return Ok(());
+    };
+    let guild_id = guild_id.get() as i64;
+    ctx.data()
+        .guild_service
+        .update_guild_settings(guild_id, &json!({ CONTENT_SAFETY_SETTING: Value::Null }))
+        .await?;
+    info!(
+        event = "guild_content_safety_reset",
+        guild_id = guild_id,
+        "Guild content safety thresholds reset"
+    );
+
+    let floor = ctx.data().settings.safety_floor().await;
+    reply::say(
+        ctx,
+        ReplyKind::Admin,
+        format!("🔄 {}", describe(&SafetyThresholds::default(), &floor)),
+    )
+    .await
+}
+
+/// Set the least every server blocks for one category
+#[poise::command(slash_command, check = "super::permissions::require_superadmin")]
+pub async fn floor(
+    ctx: Context<'_>,
+    #[description = "Kind of harm"] category: Category,
+    #[description = "The weakest filter any server may use"] threshold: Threshold,
+) -> Result<(), Error> {
+    let data = ctx.data();
+    let mut floor = data.settings.safety_floor().await;
+    floor.set(category.into(), threshold.into());
+    data.settings.set_safety_floor(&data.db_pool, floor).await?;
+    info!(
+        event = "safety_floor_command_used",
+        user_id = %ctx.author().id,
+        "Safety floor command used"
+    );
+
+    let lines: Vec<String> = HarmCategory::ALL
+        .into_iter()
+        .map(|category| {
+            format!(
+                "**{}:** {}",
+                category.label(),
+                threshold_name(floor.get(category))
+            )
+        })
+        .collect();
+    reply::say(
+        ctx,
+        ReplyKind::Admin,
+        format!("✅ every server blocks at least\n{}", lines.join("\n")),
+    )
+    .await
+}
+
+/// Tell moderators when the filter blocks a reply here, or stop telling them
+#[poise::command(slash_command, guild_only, check = "super::permissions::require_admin")]
+pub async fn reports(
+    ctx: Context<'_>,
+    #[description = "Private channel to report blocked replies in, leave empty to stop"]
+    channel: Option<serenity::GuildChannel>,
+) -> Result<(), Error> {
+    let Some(guild_id) = ctx.guild_id() else {
+        return Ok(());
+    };
+    let setting = match &channel {
+        Some(channel) => json!({ "logChannel": channel.id.to_string() }),
+        None => Value::Null,
+    };
+    ctx.data()
+        .guild_service
+        .update_guild_settings(
+            guild_id.get() as i64,
+            &json!({ CONTENT_REPORTS_SETTING: setting }),
+        )
+        .await?;
+    info!(
+        event = "content_reports_channel_set",
+        guild_id = %guild_id,
+        channel_id = ?channel.as_ref().map(|channel| channel.id),
+        "Content filter report channel updated"
+    );
+
+    let message = match channel {
+        Some(channel) => format!("🚫 blocked replies are now reported in <#{}>", channel.id),
+        None => "🔕 blocked replies are no longer reported, they're still recorded".to_string(),
+    };
+    reply::say(ctx, ReplyKind::Admin, message).await
+}
+
+/// Show the latest replies the filter blocked in this server
+#[poise::command(slash_command, guild_only, check = "super::permissions::require_admin")]
+pub async fn incidents(ctx: Context<'_>) -> Result<(), Error> {
+    let Some(guild_id) = ctx.guild_id() else {
+        return Ok(());
+    };
+    let incidents = ctx
+        .data()
+        .content_incidents
+        .recent(guild_id.get(), RECENT_INCIDENTS_LIMIT)
+        .await?;
+    if incidents.is_empty() {
+        return reply::say(ctx, ReplyKind::Admin, "nothing blocked here yet ✨").await;
+    }
+
+    let lines: Vec<String> = incidents
+        .iter()
+        .map(|incident| {
+            let when = incident
+                .created_at
+                .map(|created_at| format!("<t:{}:R>", created_at.timestamp()))
+                .unwrap_or_default();
+            let channel = incident
+                .channel_id
+                .map(|id| format!(" in <#{}>", id))
+                .unwrap_or_default();
+            format!(
+                "• {}{}: {}, prompt `{}`",
+                when,
+                channel,
+                incident.describe(),
+                incident.short_hash()
+            )
+        })
+        .collect();
+    reply::say(
+        ctx,
+        ReplyKind::Admin,
+        format!("🚫 latest blocked replies\n{}", lines.join("\n")),
+    )
+    .await
+}
+
+async fn guild_thresholds(ctx: Context<'_>, guild_id: i64) -> SafetyThresholds {
+    let setting = ctx
+        .data()
+        .guild_service
+        .get_guild_setting(guild_id, CONTENT_SAFETY_SETTING)
+        .await;
+    SafetyThresholds::from_setting(setting.as_ref(), SafetyThresholds::default())
+}
+
+/// What's blocked per category, pointing out where the global floor overrides the server
+fn describe(guild: &SafetyThresholds, floor: &SafetyThresholds) -> String {
+    let applied = guild.at_least(floor);
+    let lines: Vec<String> = HarmCategory::ALL
+        .into_iter()
+        .map(|category| {
+            let mut line = format!(
+                "**{}:** {}",
+                category.label(),
+                threshold_name(applied.get(category))
+            );
+            if applied.get(category) != guild.get(category) {
+                line.push_str(" (raised by the global floor)");
+            }
+            line
+        })
+        .collect();
+    format!(
+        "content filter in this server, gemini models only\n{}",
+        lines.join("\n")
+    )
+}
+
+fn threshold_name(threshold: BlockThreshold) -> &'static str {
+    match threshold {
+        BlockThreshold::None => "nothing blocked",
+        BlockThreshold::OnlyHigh => "high risk blocked",
+        BlockThreshold::MediumAndAbove => "medium risk and above blocked",
+        BlockThreshold::LowAndAbove => "low risk and above blocked",
+    }
+}
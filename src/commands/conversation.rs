@@ -0,0 +1,201 @@
+use super::reply;
+use crate::{Context, Error};
+use chloe::services::reply_visibility::ReplyKind;
+use chloe::services::saved_conversations::{
+    MAX_SAVED_CONVERSATIONS, SAVED_TURNS_LIMIT, normalize_name,
+};
+use tracing::info;
+
+/// Save conversations with chloe and pick them up again somewhere else
+#[poise::command(
+    slash_command,
+    subcommands("save", "resume", "list", "delete"),
+    subcommand_required
+)]
+pub async fn conversation(_ctx: Context<'_>) -> Result<(), Error> {
+    Ok(())
+}
+
+/// Save the conversation in this channel under a name
+#[poise::command(slash_command)]
+pub async fn save(
+    ctx: Context<'_>,
+    #[description = "Name to save it under, like project-x"] name: String,
+) -> Result<(), Error> {
+    let name = match normalize_name(&name) {
+        Ok(name) => name,
+        Err(e) => return reply::say(ctx, ReplyKind::Private, e).await,
+    };
+    let data = ctx.data();
+    let channel_id = ctx.channel_id().get();
+
+    let mut turns = data
+        .conversations
+        .recent_turns(channel_id, ctx.id(), SAVED_TURNS_LIMIT)
+        .await?;
+    // a channel that picked up a saved conversation keeps it in the new save
+    if let Some(resumed) = data.saved_conversations.resumed_in(channel_id).await? {
+        let room = SAVED_TURNS_LIMIT.saturating_sub(turns.len());
+        turns.extend(resumed.turns.into_iter().take(room));
+    }
+    if turns.is_empty() {
+        return reply::say(
+            ctx,
+            ReplyKind::Private,
+            "there's no conversation to save here yet",
+        )
+        .await;
+    }
+
+    let saved = data
+        .saved_conversations
+        .save(
+            ctx.author().id.get(),
+            &name,
+            ctx.guild_id().map(|id| id.get()),
+            channel_id,
+            &turns,
+        )
+        .await?;
+    let message = if saved {
+        format!(
+            "💾 saved {} turns as `{}`, pick it up anywhere with `/conversation resume`",
+            turns.len(),
+            name
+        )
+    } else {
+        format!(
+            "you already have {} saved conversations, delete one first",
+            MAX_SAVED_CONVERSATIONS
+        )
+    };
+    reply::say(ctx, ReplyKind::Private, message).await
+}
+
+/// Continue a saved conversation in this channel
+#[poise::command(slash_command)]
+pub async fn resume(
+    ctx: Context<'_>,
+    #[description = "Name it was saved under"] name: String,
+) -> Result<(), Error> {
+    let Ok(name) = normalize_name(&name) else {
+        return reply::say(ctx, ReplyKind::Private, "that's not a conversation name").await;
+    };
+    let data = ctx.data();
+    let user_id = ctx.author().id.get();
+
+    let Some(saved) = data.saved_conversations.find(user_id, &name).await? else {
+        return reply::say(
+            ctx,
+            ReplyKind::Private,
+            format!("you don't have a conversation called `{}`", name),
+        )
+        .await;
+    };
+    // its turns may come from a channel the people here can't read
+    let thread_parent_id = ctx
+        .guild_channel()
+        .await
+        .filter(|channel| channel.thread_metadata.is_some())
+        .and_then(|channel| channel.parent_id)
+        .map(|id| id.get());
+    if !saved.can_resume_in(
+        ctx.guild_id().map(|id| id.get()),
+        ctx.channel_id().get(),
+        thread_parent_id,
+    ) {
+        return reply::say(
+            ctx,
+            ReplyKind::Private,
+            format!(
+                "that conversation is from <#{}>, resume it there, in one of its threads or in DMs",
+                saved.source_channel_id
+            ),
+        )
+        .await;
+    }
+
+    data.saved_conversations
+        .resume(user_id, &name, ctx.channel_id().get())
+        .await?;
+    info!(
+        event = "conversation_resume_command_used",
+        user_id = user_id,
+        channel_id = %ctx.channel_id(),
+        name = %name,
+        "Conversation resume command used"
+    );
+    reply::say(
+        ctx,
+        ReplyKind::Status,
+        format!(
+            "▶️ picking up `{}` from <#{}>, {} turns back",
+            name,
+            saved.source_channel_id,
+            saved.turns.len()
+        ),
+    )
+    .await
+}
+
+/// List the conversations you saved
+#[poise::command(slash_command)]
+pub async fn list(ctx: Context<'_>) -> Result<(), Error> {
+    let saved = ctx
+        .data()
+        .saved_conversations
+        .list(ctx.author().id.get())
+        .await?;
+    if saved.is_empty() {
+        return reply::say(
+            ctx,
+            ReplyKind::Private,
+            "you haven't saved any conversations yet, use `/conversation save`",
+        )
+        .await;
+    }
+
+    let lines: Vec<String> = saved
+        .iter()
+        .map(|conversation| {
+            let resumed = conversation
+                .resumed_in
+                .map(|channel_id| format!(", continued in <#{}>", channel_id))
+                .unwrap_or_default();
+            format!(
+                "• `{}`: {} turns from <#{}>, saved <t:{}:R>{}",
+                conversation.name,
+                conversation.turns.len(),
+                conversation.source_channel_id,
+                conversation.created_at.timestamp(),
+                resumed
+            )
+        })
+        .collect();
+    reply::say(
+        ctx,
+        ReplyKind::Private,
+        format!("💾 your saved conversations\n{}", lines.join("\n")),
+    )
+    .await
+}
+
+/// Delete a saved conversation, channels continuing it forget it too
+#[poise::command(slash_command)]
+pub async fn delete(
+    ctx: Context<'_>,
+    #[description = "Name it was saved under"] name: String,
+) -> Result<(), Error> {
+    let name = normalize_name(&name).unwrap_or(name);
+    let deleted = ctx
+        .data()
+        .saved_conversations
+        .delete(ctx.author().id.get(), &name)
+        .await?;
+    let message = if deleted {
+        format!("🗑️ deleted `{}`", name)
+    } else {
+        format!("you don't have a conversation called `{}`", name)
+    };
+    reply::say(ctx, ReplyKind::Private, message).await
+}
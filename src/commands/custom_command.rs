@@ -0,0 +1,198 @@
+use super::reply;
+use crate::{Context, Error};
+use chloe::services::custom_commands::{
+    self, CustomCommand, MAX_COMMANDS_PER_GUILD, MAX_DESCRIPTION_LENGTH, MAX_RESPONSE_LENGTH,
+};
+use chloe::services::reply_visibility::ReplyKind;
+use poise::serenity_prelude as serenity;
+use tracing::warn;
+
+/// Manage this server's own slash commands
+#[poise::command(
+    slash_command,
+    guild_only,
+    rename = "commands",
+    subcommands("add", "remove", "list"),
+    subcommand_required
+)]
+pub async fn custom_commands(_ctx: Context<'_>) -> Result<(), Error> {
+    Ok(())
+}
+
+/// Add a slash command that answers with fixed text, or replace the one with that name
+#[poise::command(slash_command, guild_only, check = "super::permissions::require_admin")]
+pub async fn add(
+    ctx: Context<'_>,
+    #[description = "Name of the command, e.g. rules for /rules"] name: String,
+    #[description = "What the command answers"] response: String,
+    #[description = "Shown in the command picker, defaults to the start of the response"]
+    description: Option<String>,
+    #[description = "Answer with an embed instead of plain text"] embed: Option<bool>,
+) -> Result<(), Error> {
+    let Some(guild_id) = ctx.guild_id() else {
+        return Ok(());
+    };
+
+    let Some(name) = custom_commands::normalize_name(&name) else {
+        return reply::say(
+            ctx,
+            ReplyKind::Admin,
+            "names need 1 to 32 letters, numbers, - or _, without spaces",
+        )
+        .await;
+    };
+    // chloe's own commands are global, a guild command with the same name would shadow one
+    if ctx
+        .framework()
+        .options()
+        .commands
+        .iter()
+        .any(|command| command.name == name)
+    {
+        return reply::say(
+            ctx,
+            ReplyKind::Admin,
+            format!("`/{}` is one of chloe's commands already", name),
+        )
+        .await;
+    }
+    let response = response.trim().to_string();
+    if response.is_empty() || response.len() > MAX_RESPONSE_LENGTH {
+        return reply::say(
+            ctx,
+            ReplyKind::Admin,
+            format!("responses need 1 to {} characters", MAX_RESPONSE_LENGTH),
+        )
+        .await;
+    }
+    let description = description
+        .map(|description| description.trim().to_string())
+        .filter(|description| !description.is_empty())
+        .unwrap_or_else(|| CustomCommand::default_description(&response));
+    if description.chars().count() > MAX_DESCRIPTION_LENGTH {
+        return reply::say(
+            ctx,
+            ReplyKind::Admin,
+            format!(
+                "descriptions can be at most {} characters",
+                MAX_DESCRIPTION_LENGTH
+            ),
+        )
+        .await;
+    }
+    let command = CustomCommand {
+        name,
+        description,
+        response,
+        embed: embed.unwrap_or(false),
+    };
+
+    let existing = ctx
+        .data()
+        .custom_commands
+        .list(guild_id.get() as i64)
+        .await?;
+    if existing.len() >= MAX_COMMANDS_PER_GUILD
+        && !existing.iter().any(|other| other.name == command.name)
+    {
+        return reply::say(
+            ctx,
+            ReplyKind::Admin,
+            format!(
+                "this server has {} commands already, remove one first",
+                MAX_COMMANDS_PER_GUILD
+            ),
+        )
+        .await;
+    }
+
+    // registering a name that exists replaces that command on discord's side too
+    let registered = guild_id
+        .create_command(
+            ctx.http(),
+            serenity::CreateCommand::new(&command.name).description(&command.description),
+        )
+        .await?;
+    if let Err(e) = ctx
+        .data()
+        .custom_commands
+        .add(
+            guild_id.get() as i64,
+            &command,
+            registered.id.get(),
+            &ctx.author().id.to_string(),
+        )
+        .await
+    {
+        if let Err(delete_error) = guild_id.delete_command(ctx.http(), registered.id).await {
+            warn!(
+                event = "custom_command_unregister_failed",
+                guild_id = %guild_id,
+                name = %command.name,
+                error = ?delete_error,
+                "Failed to unregister a custom command that wasn't saved"
+            );
+        }
+        return Err(e.into());
+    }
+
+    reply::say(
+        ctx,
+        ReplyKind::Admin,
+        format!("✅ `/{}` is ready to use", command.name),
+    )
+    .await
+}
+
+/// Remove one of this server's slash commands
+#[poise::command(slash_command, guild_only, check = "super::permissions::require_admin")]
+pub async fn remove(
+    ctx: Context<'_>,
+    #[description = "Name of the command to remove"] name: String,
+) -> Result<(), Error> {
+    let Some(guild_id) = ctx.guild_id() else {
+        return Ok(());
+    };
+
+    let name = custom_commands::normalize_name(&name).unwrap_or_default();
+    let removed = ctx
+        .data()
+        .custom_commands
+        .remove(guild_id.get() as i64, &name)
+        .await?;
+    let Some(command_id) = removed else {
+        return reply::say(ctx, ReplyKind::Admin, "there's no command with that name").await;
+    };
+    if command_id > 0 {
+        guild_id
+            .delete_command(ctx.http(), serenity::CommandId::new(command_id))
+            .await?;
+    }
+    reply::say(ctx, ReplyKind::Admin, format!("🗑️ removed `/{}`", name)).await
+}
+
+/// List this server's slash commands
+#[poise::command(slash_command, guild_only, check = "super::permissions::require_admin")]
+pub async fn list(ctx: Context<'_>) -> Result<(), Error> {
+    let Some(guild_id) = ctx.guild_id() else {
+        return Ok(());
+    };
+
+    let commands = ctx
+        .data()
+        .custom_commands
+        .list(guild_id.get() as i64)
+        .await?;
+    if commands.is_empty() {
+        return reply::say(ctx, ReplyKind::Admin, "no custom commands yet").await;
+    }
+
+    let content: String = commands
+        .iter()
+        .map(|command| {
+            let kind = if command.embed { " (embed)" } else { "" };
+            format!("• `/{}`{}: {}\n", command.name, kind, command.description)
+        })
+        .collect();
+    reply::paginate(ctx, ReplyKind::Admin, "✨ custom commands", &content).await
+}
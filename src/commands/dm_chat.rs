@@ -0,0 +1,21 @@
+use super::reply;
+use crate::{Context, Error};
+use chloe::services::reply_visibility::ReplyKind;
+
+/// Chat with chloe in DMs, or stop
+#[poise::command(slash_command)]
+pub async fn dmchat(
+    ctx: Context<'_>,
+    #[description = "Whether chloe answers your DMs"] enabled: bool,
+) -> Result<(), Error> {
+    ctx.data()
+        .user_service
+        .set_dm_chat(ctx.author().id.get() as i64, enabled)
+        .await?;
+    let content = if enabled {
+        "✅ DM me anytime 💬 while this is on, DMs come to me instead of opening modmail"
+    } else {
+        "👋 I'll stop answering your DMs"
+    };
+    reply::say(ctx, ReplyKind::Private, content).await
+}
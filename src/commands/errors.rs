@@ -0,0 +1,108 @@
+use super::permissions::PermissionDenied;
+use super::reply;
+use crate::{Context, Data, Error};
+use chloe::services::reply_visibility::ReplyKind;
+use tracing::{error, info, warn};
+
+/// Tell the user a command failed instead of leaving the interaction to time out. Each
+/// failure gets a short reference that's in both the reply and the log, so a report
+/// from a user can be matched to the full error.
+pub async fn on_error(error: poise::FrameworkError<'_, Data, Error>) {
+    match error {
+        poise::FrameworkError::Command { error, ctx, .. } => {
+            let reference = error_reference();
+            error!(
+                event = "command_failed",
+                reference = %reference,
+                command = %ctx.command().qualified_name,
+                user_id = %ctx.author().id,
+                guild_id = ?ctx.guild_id(),
+                error = ?error,
+                "Command returned an error"
+            );
+            reply_with_reference(ctx, &reference).await;
+        }
+        poise::FrameworkError::CommandPanic { payload, ctx, .. } => {
+            let reference = error_reference();
+            error!(
+                event = "command_panicked",
+                reference = %reference,
+                command = %ctx.command().qualified_name,
+                user_id = %ctx.author().id,
+                guild_id = ?ctx.guild_id(),
+                payload = ?payload,
+                "Command panicked"
+            );
+            reply_with_reference(ctx, &reference).await;
+        }
+        poise::FrameworkError::ArgumentParse {
+            error, input, ctx, ..
+        } => {
+            warn!(
+                event = "command_argument_invalid",
+                command = %ctx.command().qualified_name,
+                input = ?input,
+                error = %error,
+                "Couldn't parse command arguments"
+            );
+            let message = match input {
+                Some(input) => format!("I couldn't make sense of `{}` 😵‍💫", input),
+                None => "I couldn't make sense of those options 😵‍💫".to_string(),
+            };
+            send_private(ctx, message).await;
+        }
+        poise::FrameworkError::CommandCheckFailed {
+            error: Some(error),
+            ctx,
+            ..
+        } if error.is::<PermissionDenied>() => {
+            info!(
+                event = "command_permission_denied",
+                command = %ctx.command().qualified_name,
+                user_id = %ctx.author().id,
+                guild_id = ?ctx.guild_id(),
+                "User lacks the role for a command"
+            );
+            send_private(ctx, error.to_string()).await;
+        }
+        // the guild commands admins define are answered by CustomCommandHandler
+        poise::FrameworkError::UnknownInteraction { .. } => {}
+        // discord permission checks, cooldowns and guild only commands already explain themselves
+        other => {
+            if let Err(e) = poise::builtins::on_error(other).await {
+                error!(
+                    event = "command_error_handler_failed",
+                    error = ?e,
+                    "Failed to report a command error"
+                );
+            }
+        }
+    }
+}
+
+async fn reply_with_reference(ctx: Context<'_>, reference: &str) {
+    send_private(
+        ctx,
+        format!(
+            "oops, something broke on my end 💔 try again in a bit, and if it keeps happening tell the admins the error code `{}`",
+            reference
+        ),
+    )
+    .await;
+}
+
+async fn send_private(ctx: Context<'_>, content: String) {
+    if let Err(e) = reply::say(ctx, ReplyKind::Private, content).await {
+        warn!(
+            event = "command_error_reply_failed",
+            command = %ctx.command().qualified_name,
+            error = ?e,
+            "Failed to tell the user a command failed"
+        );
+    }
+}
+
+/// Eight hex characters, enough to find one failure in the logs
+fn error_reference() -> String {
+    format!("{:08X}", rand::random::<u32>())
+}
@@ -0,0 +1,208 @@
+use super::reply;
+use crate::{Context, Error};
+use chloe::services::leveling::{LEVELING_SETTING, LevelingSettings};
+use chloe::services::reply_visibility::ReplyKind;
+use poise::serenity_prelude as serenity;
+
+const LEVELING_COLOR: u32 = 0xff69b4;
+
+/// Whether the guild turned leveling on, telling the caller when it didn't
+async fn leveling_enabled(ctx: Context<'_>, guild_id: serenity::GuildId) -> Result<bool, Error> {
+    let setting = ctx
+        .data()
+        .guild_service
+        .get_guild_setting(guild_id.get() as i64, LEVELING_SETTING)
+        .await;
+    if LevelingSettings::from_setting(setting.as_ref()).is_some() {
+        return Ok(true);
+    }
+    reply::say(ctx, ReplyKind::Private, "leveling is off in this server").await?;
+    Ok(false)
+}
+
+/// Show your level and xp, or someone else's
+#[poise::command(slash_command, guild_only)]
+pub async fn rank(
+    ctx: Context<'_>,
+    #[description = "Whose rank to show, you by default"] user: Option<serenity::User>,
+) -> Result<(), Error> {
+    let Some(guild_id) = ctx.guild_id() else {
+        return Ok(());
+    };
+    if !leveling_enabled(ctx, guild_id).await? {
+        return Ok(());
+    }
+
+    let user = user.as_ref().unwrap_or_else(|| ctx.author());
+    let Some(rank) = ctx
+        .data()
+        .leveling
+        .rank(guild_id.get(), user.id.get())
+        .await?
+    else {
+        return reply::say(
+            ctx,
+            ReplyKind::Status,
+            format!("{} hasn't earned any xp yet", user.name),
+        )
+        .await;
+    };
+
+    let embed = serenity::CreateEmbed::new()
+        .color(LEVELING_COLOR)
+        .author(serenity::CreateEmbedAuthor::new(&user.name).icon_url(user.face()))
+        .field("level", rank.progress.level.to_string(), true)
+        .field("rank", format!("#{}", rank.position), true)
+        .field("xp", rank.xp.to_string(), true)
+        .field(
+            "next level",
+            format!(
+                "{} {}/{}",
+                progress_bar(rank.progress.xp_into_level, rank.progress.xp_for_next),
+                rank.progress.xp_into_level,
+                rank.progress.xp_for_next
+            ),
+            false,
+        )
+        .footer(serenity::CreateEmbedFooter::new(format!(
+            "{} messages counted",
+            rank.message_count
+        )));
+    reply::send(
+        ctx,
+        ReplyKind::Status,
+        poise::CreateReply::default().embed(embed),
+    )
+    .await
+}
+
+/// Show the members with the most xp
+#[poise::command(slash_command, guild_only)]
+pub async fn leaderboard(ctx: Context<'_>) -> Result<(), Error> {
+    let Some(guild_id) = ctx.guild_id() else {
+        return Ok(());
+    };
+    if !leveling_enabled(ctx, guild_id).await? {
+        return Ok(());
+    }
+
+    let entries = ctx.data().leveling.leaderboard(guild_id.get()).await?;
+    if entries.is_empty() {
+        return reply::say(ctx, ReplyKind::Status, "nobody has earned any xp yet").await;
+    }
+    let lines: Vec<String> = entries
+        .iter()
+        .enumerate()
+        .map(|(position, entry)| {
+            format!(
+                "**{}.** <@{}> · level {} · {} xp",
+                position + 1,
+                entry.user_id,
+                entry.level,
+                entry.xp
+            )
+        })
+        .collect();
+    let embed = serenity::CreateEmbed::new()
+        .color(LEVELING_COLOR)
+        .title("🏆 leaderboard")
+        .description(lines.join("\n"));
+    reply::send(
+        ctx,
+        ReplyKind::Status,
+        poise::CreateReply::default().embed(embed),
+    )
+    .await
+}
+
+/// Give members a role when they reach a level
+#[poise::command(
+    slash_command,
+    guild_only,
+    subcommands("add", "remove", "list"),
+    subcommand_required
+)]
+pub async fn levelreward(_ctx: Context<'_>) -> Result<(), Error> {
+    Ok(())
+}
+
+/// Hand out a role at a level, replacing the role set for that level
+#[poise::command(slash_command, guild_only, check = "super::permissions::require_admin")]
+pub async fn add(
+    ctx: Context<'_>,
+    #[description = "Level that earns the role"]
+    #[min = 1]
+    #[max = 500]
+    level: i32,
+    #[description = "Role they get"] role: serenity::Role,
+) -> Result<(), Error> {
+    let Some(guild_id) = ctx.guild_id() else {
+        return Ok(());
+    };
+    if role.managed || role.id.get() == guild_id.get() {
+        return reply::say(ctx, ReplyKind::Admin, "that role can't be handed out").await;
+    }
+
+    ctx.data()
+        .leveling
+        .set_reward(guild_id.get(), level, role.id.get())
+        .await?;
+    reply::say(
+        ctx,
+        ReplyKind::Admin,
+        format!("✅ level {} gets <@&{}>", level, role.id),
+    )
+    .await
+}
+
+/// Stop handing out the role for a level
+#[poise::command(slash_command, guild_only, check = "super::permissions::require_admin")]
+pub async fn remove(
+    ctx: Context<'_>,
+    #[description = "Level of the reward to remove"] level: i32,
+) -> Result<(), Error> {
+    let Some(guild_id) = ctx.guild_id() else {
+        return Ok(());
+    };
+
+    let removed = ctx
+        .data()
+        .leveling
+        .remove_reward(guild_id.get(), level)
+        .await?;
+    let content = if removed {
+        "🗑️ removed"
+    } else {
+        "there's no reward for that level"
+    };
+    reply::say(ctx, ReplyKind::Admin, content).await
+}
+
+/// List the roles handed out for levels
+#[poise::command(slash_command, guild_only, check = "super::permissions::require_admin")]
+pub async fn list(ctx: Context<'_>) -> Result<(), Error> {
+    let Some(guild_id) = ctx.guild_id() else {
+        return Ok(());
+    };
+
+    let rewards = ctx.data().leveling.rewards(guild_id.get()).await?;
+    if rewards.is_empty() {
+        return reply::say(ctx, ReplyKind::Admin, "no level rewards set up yet").await;
+    }
+    let content = rewards
+        .iter()
+        .map(|reward| format!("• level {}: <@&{}>", reward.level, reward.role_id))
+        .collect::<Vec<_>>()
+        .join("\n");
+    reply::say(ctx, ReplyKind::Admin, content).await
+}
+
+/// Ten blocks filled by how far along the level is
+fn progress_bar(done: i64, total: i64) -> String {
+    let filled = if total > 0 {
+        (done * 10 / total).clamp(0, 10) as usize
+    } else {
+        0
+    };
+    format!("{}{}", "▰".repeat(filled), "▱".repeat(10 - filled))
+}
@@ -1,2 +1,22 @@
+pub mod admin;
+pub mod content_filter;
+pub mod conversation;
+pub mod custom_command;
+pub mod dm_chat;
+pub mod errors;
+pub mod leveling;
+pub mod model;
+pub mod modmail;
+pub mod permissions;
+pub mod persona;
 pub mod ping;
+pub mod prompt;
+pub mod reaction_role;
+pub mod remind;
+pub mod reply;
+pub mod rewind;
+pub mod schedule;
 pub mod status;
+pub mod summarize;
+pub mod trigger;
+pub mod usage;
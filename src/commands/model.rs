@@ -0,0 +1,165 @@
+use super::reply;
+use crate::{Context, Error};
+use chloe::services::model_router::{GuildModelConfig, MAX_TEMPERATURE, qualified_model};
+use chloe::services::ollama_provider;
+use chloe::services::reply_visibility::ReplyKind;
+use serde_json::{Value, json};
+use tracing::info;
+
+#[derive(Debug, Clone, Copy, poise::ChoiceParameter)]
+pub enum Provider {
+    #[name = "gemini"]
+    Gemini,
+    #[name = "ollama"]
+    Ollama,
+}
+
+impl Provider {
+    fn as_str(&self) -> &'static str {
+        match self {
+            Provider::Gemini => "gemini",
+            Provider::Ollama => "ollama",
+        }
+    }
+}
+
+/// Pin the model chloe uses in this server, or show the current one
+#[poise::command(slash_command, guild_only, check = "super::permissions::require_admin")]
+pub async fn model(
+    ctx: Context<'_>,
+    #[description = "Who serves the model, gemini by default"] provider: Option<Provider>,
+    #[description = "Model name, e.g. gemini-2.5-pro or llama3.1:8b"] model: Option<String>,
+    #[description = "Sampling temperature from 0 to 2"]
+    #[min = 0.0]
+    #[max = 2.0]
+    temperature: Option<f64>,
+    #[description = "Go back to automatic model routing"] reset: Option<bool>,
+) -> Result<(), Error> {
+    let Some(guild_id) = ctx.guild_id() else {
+        return Ok(());
+    };
+    let guild_id = guild_id.get() as i64;
+    let data = ctx.data();
+
+    let current = data
+        .guild_service
+        .get_guild_setting(guild_id, "llmConfig")
+        .await;
+
+    if reset.unwrap_or(false) {
+        data.guild_service
+            .update_guild_settings(guild_id, &json!({ "llmConfig": Value::Null }))
+            .await?;
+        info!(
+            event = "guild_model_reset",
+            guild_id = guild_id,
+            "Guild model pin removed"
+        );
+        reply::send(
+            ctx,
+            ReplyKind::Admin,
+            poise::CreateReply::default().content("🔄 back to picking models automatically"),
+        )
+        .await?;
+        return Ok(());
+    }
+
+    if model.is_none() && temperature.is_none() {
+        reply::send(
+            ctx,
+            ReplyKind::Admin,
+            poise::CreateReply::default()
+                .content(describe(&GuildModelConfig::from_setting(current.as_ref()))),
+        )
+        .await?;
+        return Ok(());
+    }
+
+    // keep whatever isn't being changed
+    let mut config = current
+        .filter(|current| current.is_object())
+        .unwrap_or_else(|| json!({}));
+
+    if let Some(model) = model {
+        let provider = provider.unwrap_or(Provider::Gemini);
+        let Some(qualified) = qualified_model(provider.as_str(), &model) else {
+            reply::send(
+                ctx,
+                ReplyKind::Admin,
+                poise::CreateReply::default()
+                    .content(format!("`{}` isn't a model name I can use", model.trim())),
+            )
+            .await?;
+            return Ok(());
+        };
+        if let Err(reason) = check_available(ctx, &qualified).await {
+            reply::send(
+                ctx,
+                ReplyKind::Admin,
+                poise::CreateReply::default().content(reason),
+            )
+            .await?;
+            return Ok(());
+        }
+        config["provider"] = json!(provider.as_str());
+        config["model"] = json!(model.trim());
+    }
+    if let Some(temperature) = temperature {
+        config["temperature"] = json!(temperature.clamp(0.0, MAX_TEMPERATURE as f64));
+    }
+
+    data.guild_service
+        .update_guild_settings(guild_id, &json!({ "llmConfig": config }))
+        .await?;
+    info!(
+        event = "guild_model_pinned",
+        guild_id = guild_id,
+        config = %config,
+        "Guild model configuration updated"
+    );
+
+    reply::send(
+        ctx,
+        ReplyKind::Admin,
+        poise::CreateReply::default().content(format!(
+            "✅ {}",
+            describe(&GuildModelConfig::from_setting(Some(&config)))
+        )),
+    )
+    .await?;
+    Ok(())
+}
+
+/// Ollama models have to be pulled before they can answer
+async fn check_available(ctx: Context<'_>, model: &str) -> Result<(), String> {
+    let Some(name) = ollama_provider::model_name(model) else {
+        return Ok(());
+    };
+    match ctx.data().llm_service.ollama_models().await {
+        None => Err("ollama isn't set up for this bot".to_string()),
+        Some(Err(e)) => Err(format!("couldn't reach ollama: {}", e)),
+        Some(Ok(models))
+            if models
+                .iter()
+                .any(|pulled| pulled == name || pulled.strip_suffix(":latest") == Some(name)) =>
+        {
+            Ok(())
+        }
+        Some(Ok(models)) => Err(format!(
+            "`{}` isn't pulled on ollama, available: {}",
+            name,
+            models.join(", ")
+        )),
+    }
+}
+
+fn describe(config: &GuildModelConfig) -> String {
+    let model = match &config.model {
+        Some(model) => format!("using `{}` in this server", model),
+        None => "picking models automatically in this server".to_string(),
+    };
+    match config.temperature {
+        Some(temperature) => format!("{}, temperature {}", model, temperature),
+        None => model,
+    }
+}
@@ -0,0 +1,217 @@
+use super::reply;
+use crate::{Context, Error};
+use chloe::services::modmail::{INTERNAL_NOTE_PREFIX, ModmailThread};
+use chloe::services::reply_visibility::ReplyKind;
+use poise::serenity_prelude as serenity;
+use serde_json::{Value, json};
+use tracing::{error, info};
+
+/// Messages of the thread the draft is based on
+const DRAFT_HISTORY: u8 = 30;
+
+const DRAFT_PROMPT: &str = "You help the moderators of a Discord server answer a member who contacted them privately through modmail. \
+Below is the conversation so far, the member's messages are prefixed with their name and bold, the moderators' with \"Mod\". \
+Lines marked as notes are moderators talking among themselves, the member never saw them. \
+Write the reply the moderators could send next: kind, clear and short, in the language the member writes in. \
+Output only the reply text.";
+
+/// Let members DM chloe to reach this server's mods
+#[poise::command(
+    slash_command,
+    guild_only,
+    subcommands("setup", "disable", "close", "draft"),
+    subcommand_required
+)]
+pub async fn modmail(_ctx: Context<'_>) -> Result<(), Error> {
+    Ok(())
+}
+
+/// Relay members' DMs into threads in a mod channel
+#[poise::command(slash_command, guild_only, check = "super::permissions::require_admin")]
+pub async fn setup(
+    ctx: Context<'_>,
+    #[description = "Private channel modmail threads are opened in"]
+    channel: serenity::GuildChannel,
+) -> Result<(), Error> {
+    let Some(guild_id) = ctx.guild_id() else {
+        return Ok(());
+    };
+    if channel.kind != serenity::ChannelType::Text {
+        return reply::say(
+            ctx,
+            ReplyKind::Admin,
+            "modmail needs a text channel to open threads in",
+        )
+        .await;
+    }
+
+    ctx.data()
+        .guild_service
+        .update_guild_settings(
+            guild_id.get() as i64,
+            &json!({ "modmail": { "channelId": channel.id.to_string() } }),
+        )
+        .await?;
+    info!(
+        event = "modmail_enabled",
+        guild_id = %guild_id,
+        channel_id = %channel.id,
+        "Modmail enabled"
+    );
+
+    reply::say(
+        ctx,
+        ReplyKind::Admin,
+        format!(
+            "📬 DMs from members now open threads in <#{}>. keep it private, members are only told what you reply. start a message with `{}` to keep it between mods",
+            channel.id, INTERNAL_NOTE_PREFIX
+        ),
+    )
+    .await
+}
+
+/// Stop relaying DMs, open threads stay as they are
+#[poise::command(slash_command, guild_only, check = "super::permissions::require_admin")]
+pub async fn disable(ctx: Context<'_>) -> Result<(), Error> {
+    let Some(guild_id) = ctx.guild_id() else {
+        return Ok(());
+    };
+
+    ctx.data()
+        .guild_service
+        .update_guild_settings(guild_id.get() as i64, &json!({ "modmail": Value::Null }))
+        .await?;
+    info!(
+        event = "modmail_disabled",
+        guild_id = %guild_id,
+        "Modmail disabled"
+    );
+    reply::say(ctx, ReplyKind::Admin, "📪 modmail is off").await
+}
+
+/// Close the modmail conversation in this thread
+#[poise::command(slash_command, guild_only)]
+pub async fn close(ctx: Context<'_>) -> Result<(), Error> {
+    let Some(thread) = current_thread(ctx).await else {
+        return reply::say(ctx, ReplyKind::Admin, "this isn't an open modmail thread").await;
+    };
+    ctx.data().modmail.close(thread.thread_id).await?;
+
+    let guild_name = ctx
+        .guild()
+        .map(|guild| guild.name.clone())
+        .unwrap_or_else(|| "the server".to_string());
+    let notified = serenity::UserId::new(thread.user_id)
+        .direct_message(
+            ctx.http(),
+            serenity::CreateMessage::new().content(format!(
+                "🔒 the mods of **{}** closed this conversation, message me again if you need them",
+                guild_name
+            )),
+        )
+        .await
+        .is_ok();
+
+    ctx.send(poise::CreateReply::default().content(if notified {
+        "🔒 closed, they've been told"
+    } else {
+        "🔒 closed, couldn't DM them about it"
+    }))
+    .await?;
+    let _ = ctx
+        .channel_id()
+        .edit_thread(
+            ctx.http(),
+            serenity::EditThread::new().archived(true).locked(true),
+        )
+        .await;
+    Ok(())
+}
+
+/// Have chloe suggest a reply to the member, only you see it
+#[poise::command(slash_command, guild_only)]
+pub async fn draft(ctx: Context<'_>) -> Result<(), Error> {
+    let Some(thread) = current_thread(ctx).await else {
+        return reply::say(ctx, ReplyKind::Private, "this isn't an open modmail thread").await;
+    };
+    ctx.defer_ephemeral().await?;
+
+    let mut messages = ctx
+        .channel_id()
+        .messages(
+            ctx.http(),
+            serenity::GetMessages::new().limit(DRAFT_HISTORY),
+        )
+        .await?;
+    messages.reverse();
+
+    let bot_id = ctx.framework().bot_id;
+    let transcript: Vec<String> = messages
+        .iter()
+        .filter(|message| !message.content.trim().is_empty())
+        .filter_map(|message| {
+            if message.author.id == bot_id {
+                // chloe's own messages in the thread are the member's relayed DMs
+                return message
+                    .content
+                    .starts_with("**")
+                    .then(|| message.content.clone());
+            }
+            if message.author.bot {
+                return None;
+            }
+            let content = message.content.trim();
+            Some(match content.strip_prefix(INTERNAL_NOTE_PREFIX) {
+                Some(note) => format!("(note) Mod {}: {}", message.author.name, note.trim()),
+                None => format!("Mod {}: {}", message.author.name, content),
+            })
+        })
+        .collect();
+    if transcript.is_empty() {
+        return reply::say(ctx, ReplyKind::Private, "nothing to answer yet").await;
+    }
+
+    match ctx
+        .data()
+        .llm_service
+        .complete_text(DRAFT_PROMPT, &transcript.join("\n"))
+        .await
+    {
+        Ok(draft) => {
+            info!(
+                event = "modmail_draft_generated",
+                thread_id = thread.thread_id,
+                "Drafted modmail reply"
+            );
+            let draft: String = draft.chars().take(1900).collect();
+            reply::say(
+                ctx,
+                ReplyKind::Private,
+                format!("✨ suggested reply:\n>>> {}", draft),
+            )
+            .await
+        }
+        Err(e) => {
+            error!(
+                event = "modmail_draft_failed",
+                thread_id = thread.thread_id,
+                error = ?e,
+                "Failed to draft modmail reply"
+            );
+            reply::say(
+                ctx,
+                ReplyKind::Private,
+                "couldn't come up with a draft right now",
+            )
+            .await
+        }
+    }
+}
+
+async fn current_thread(ctx: Context<'_>) -> Option<ModmailThread> {
+    ctx.data()
+        .modmail
+        .thread(ctx.channel_id().get())
+        .await
+        .filter(|thread| ctx.guild_id().map(|id| id.get()) == Some(thread.guild_id))
+}
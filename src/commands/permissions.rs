@@ -0,0 +1,82 @@
+use crate::{Context, Error};
+use std::fmt;
+
+/// What a user may do with chloe's commands, each role includes the ones before it
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum ChloeRole {
+    Member,
+    /// A chloe admin of the guild the command runs in
+    Admin,
+    /// An admin of every guild and of chloe's global settings
+    Superadmin,
+}
+
+/// A command check failed, `on_error` tells the user which role they're missing
+#[derive(Debug)]
+pub struct PermissionDenied {
+    pub required: ChloeRole,
+    pub command: String,
+}
+
+impl fmt::Display for PermissionDenied {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let who = match self.required {
+            ChloeRole::Member => "members",
+            ChloeRole::Admin => "server admins",
+            ChloeRole::Superadmin => "superadmins",
+        };
+        write!(f, "only {} can use `/{}` 💅", who, self.command)
+    }
+}
+
+impl std::error::Error for PermissionDenied {}
+
+/// The invoking user's role where the command runs
+pub async fn role_of(ctx: Context<'_>) -> Result<ChloeRole, Error> {
+    if is_superadmin(ctx).await? {
+        return Ok(ChloeRole::Superadmin);
+    }
+    let is_admin = match ctx.guild_id() {
+        Some(guild_id) => {
+            ctx.data()
+                .guild_service
+                .is_user_admin(guild_id.get() as i64, ctx.author().id.get() as i64)
+                .await
+        }
+        None => false,
+    };
+    Ok(if is_admin {
+        ChloeRole::Admin
+    } else {
+        ChloeRole::Member
+    })
+}
+
+async fn require(ctx: Context<'_>, required: ChloeRole) -> Result<bool, Error> {
+    if role_of(ctx).await? >= required {
+        return Ok(true);
+    }
+    Err(Box::new(PermissionDenied {
+        required,
+        command: ctx.command().qualified_name.clone(),
+    }))
+}
+
+/// Poise check for commands limited to guild admins and superadmins
+pub async fn require_admin(ctx: Context<'_>) -> Result<bool, Error> {
+    require(ctx, ChloeRole::Admin).await
+}
+
+/// Poise check for commands limited to superadmins
+pub async fn require_superadmin(ctx: Context<'_>) -> Result<bool, Error> {
+    require(ctx, ChloeRole::Superadmin).await
+}
+
+async fn is_superadmin(ctx: Context<'_>) -> Result<bool, Error> {
+    let user = ctx
+        .data()
+        .user_service
+        .get_user(ctx.author().id.get() as i64)
+        .await?;
+    Ok(user.map(|user| user.superadmin).unwrap_or(false))
+}
@@ -0,0 +1,343 @@
+use super::reply;
+use crate::{ApplicationContext, Context, Error};
+use chloe::services::personas::{
+    MAX_DESCRIPTION_LENGTH, MAX_NAME_LENGTH, MAX_OVERLAY_LENGTH, PERSONA_SETTING, Persona,
+    PersonaError, normalize_key,
+};
+use chloe::services::reply_visibility::ReplyKind;
+use poise::serenity_prelude as serenity;
+use serde_json::{Value, json};
+use std::time::Duration;
+use tracing::{info, warn};
+
+const MODAL_TIMEOUT: Duration = Duration::from_secs(60 * 15);
+const PREVIEW_TIMEOUT: Duration = Duration::from_secs(60 * 5);
+
+const PUBLISH_ID: &str = "chloe_persona_publish";
+const DISCARD_ID: &str = "chloe_persona_discard";
+
+#[derive(Debug, poise::Modal)]
+#[name = "publish a persona"]
+struct PersonaModal {
+    #[name = "name"]
+    #[placeholder = "Pirate"]
+    #[min_length = 1]
+    #[max_length = 100]
+    name: String,
+    #[name = "description"]
+    #[placeholder = "Talks like a pirate and calls everyone matey"]
+    #[min_length = 1]
+    #[max_length = 200]
+    description: String,
+    #[name = "overlay"]
+    #[placeholder = "Talk like a pirate in every reply."]
+    #[paragraph]
+    #[min_length = 1]
+    #[max_length = 2000]
+    overlay: String,
+}
+
+/// Pick how chloe comes across in this server
+#[poise::command(
+    slash_command,
+    guild_only,
+    subcommands("preset", "list", "clear", "publish", "unpublish"),
+    subcommand_required
+)]
+pub async fn persona(_ctx: Context<'_>) -> Result<(), Error> {
+    Ok(())
+}
+
+async fn autocomplete_preset(ctx: Context<'_>, partial: &str) -> Vec<serenity::AutocompleteChoice> {
+    let partial = partial.to_lowercase();
+    ctx.data()
+        .personas
+        .list()
+        .await
+        .unwrap_or_default()
+        .into_iter()
+        .filter(|persona| persona.key.contains(&partial) || persona.name.contains(&partial))
+        .take(25)
+        .map(|persona| {
+            serenity::AutocompleteChoice::new(
+                format!("{} ({})", persona.name, persona.key),
+                persona.key,
+            )
+        })
+        .collect()
+}
+
+/// Use one of the persona presets in this server
+#[poise::command(slash_command, guild_only, check = "super::permissions::require_admin")]
+pub async fn preset(
+    ctx: Context<'_>,
+    #[description = "Preset to use, see /persona list"]
+    #[autocomplete = "autocomplete_preset"]
+    name: String,
+) -> Result<(), Error> {
+    let Some(guild_id) = ctx.guild_id() else {
+        return Ok(());
+    };
+    let Some(persona) = ctx.data().personas.find(&name).await? else {
+        return reply::say(
+            ctx,
+            ReplyKind::Admin,
+            format!(
+                "there's no preset called `{}`, see /persona list",
+                name.trim()
+            ),
+        )
+        .await;
+    };
+
+    ctx.data()
+        .guild_service
+        .update_guild_settings(
+            guild_id.get() as i64,
+            &json!({ PERSONA_SETTING: persona.key }),
+        )
+        .await?;
+    info!(
+        event = "guild_persona_set",
+        guild_id = %guild_id,
+        persona = %persona.key,
+        "Guild persona changed"
+    );
+    reply::say(
+        ctx,
+        ReplyKind::Admin,
+        format!("🎭 i'm your {} now", persona.name),
+    )
+    .await
+}
+
+/// Show the persona presets and the one this server uses
+#[poise::command(slash_command, guild_only)]
+pub async fn list(ctx: Context<'_>) -> Result<(), Error> {
+    let Some(guild_id) = ctx.guild_id() else {
+        return Ok(());
+    };
+    let current = ctx
+        .data()
+        .guild_service
+        .get_guild_setting(guild_id.get() as i64, PERSONA_SETTING)
+        .await;
+    let current = current.as_ref().and_then(Value::as_str);
+    let personas = ctx.data().personas.list().await?;
+
+    let mut content: String = personas
+        .iter()
+        .map(|persona| describe(persona, current == Some(persona.key.as_str())))
+        .collect();
+    if current.is_none() {
+        content.push_str("\nno persona picked, i'm just me");
+    }
+    reply::paginate(ctx, ReplyKind::Admin, "🎭 personas", &content).await
+}
+
+/// Stop using a persona in this server
+#[poise::command(slash_command, guild_only, check = "super::permissions::require_admin")]
+pub async fn clear(ctx: Context<'_>) -> Result<(), Error> {
+    let Some(guild_id) = ctx.guild_id() else {
+        return Ok(());
+    };
+    ctx.data()
+        .guild_service
+        .update_guild_settings(
+            guild_id.get() as i64,
+            &json!({ PERSONA_SETTING: Value::Null }),
+        )
+        .await?;
+    info!(
+        event = "guild_persona_cleared",
+        guild_id = %guild_id,
+        "Guild persona removed"
+    );
+    reply::say(ctx, ReplyKind::Admin, "🔄 back to being just me").await
+}
+
+/// Publish a persona preset every server can pick, or update a published one
+#[poise::command(slash_command, check = "super::permissions::require_superadmin")]
+pub async fn publish(
+    ctx: ApplicationContext<'_>,
+    #[description = "Short key servers pick it by, like pirate"] key: String,
+) -> Result<(), Error> {
+    // the modal has to be the first response, so no deferring before this
+    let Some(key) = normalize_key(&key) else {
+        return reply::say(
+            ctx.into(),
+            ReplyKind::Admin,
+            format!("{} 💅", PersonaError::InvalidKey),
+        )
+        .await;
+    };
+    let existing = ctx.data().personas.find(&key).await?;
+    if existing.as_ref().is_some_and(|persona| persona.built_in) {
+        return reply::say(
+            ctx.into(),
+            ReplyKind::Admin,
+            format!("{} 💅", PersonaError::BuiltIn(key)),
+        )
+        .await;
+    }
+    let defaults = existing.map(|persona| PersonaModal {
+        name: persona.name,
+        description: persona.description,
+        overlay: persona.overlay,
+    });
+
+    let Some(PersonaModal {
+        name,
+        description,
+        overlay,
+    }) = poise::execute_modal(ctx, defaults, Some(MODAL_TIMEOUT)).await?
+    else {
+        return Ok(());
+    };
+    let (name, description, overlay) = (name.trim(), description.trim(), overlay.trim());
+    if name.is_empty()
+        || description.is_empty()
+        || overlay.is_empty()
+        || name.chars().count() > MAX_NAME_LENGTH
+        || description.chars().count() > MAX_DESCRIPTION_LENGTH
+        || overlay.chars().count() > MAX_OVERLAY_LENGTH
+    {
+        return reply::say(
+            ctx.into(),
+            ReplyKind::Admin,
+            format!(
+                "presets need a name (up to {}), a description (up to {}) and an overlay (up to {} characters)",
+                MAX_NAME_LENGTH, MAX_DESCRIPTION_LENGTH, MAX_OVERLAY_LENGTH
+            ),
+        )
+        .await;
+    }
+
+    let preview = serenity::CreateEmbed::new()
+        .title(format!("persona preview: {} `{}`", name, key))
+        .description(overlay)
+        .field("description", description, false)
+        .color(0xff69b4)
+        .footer(serenity::CreateEmbedFooter::new(
+            "not published until you publish it",
+        ));
+    let buttons = serenity::CreateActionRow::Buttons(vec![
+        serenity::CreateButton::new(PUBLISH_ID)
+            .label("publish")
+            .style(serenity::ButtonStyle::Success),
+        serenity::CreateButton::new(DISCARD_ID)
+            .label("discard")
+            .style(serenity::ButtonStyle::Danger),
+    ]);
+    let reply = ctx
+        .send(
+            poise::CreateReply::default()
+                .embed(preview)
+                .components(vec![buttons])
+                .ephemeral(true),
+        )
+        .await?;
+    let message = reply.message().await?;
+
+    let Some(interaction) = message
+        .await_component_interaction(ctx.serenity_context())
+        .author_id(ctx.author().id)
+        .timeout(PREVIEW_TIMEOUT)
+        .await
+    else {
+        reply
+            .edit(
+                ctx.into(),
+                poise::CreateReply::default()
+                    .content("⌛ preview expired, nothing published")
+                    .components(vec![]),
+            )
+            .await?;
+        return Ok(());
+    };
+
+    let status = if interaction.data.custom_id == PUBLISH_ID {
+        let published = ctx
+            .data()
+            .personas
+            .publish(&key, name, description, overlay, ctx.author().id.get())
+            .await;
+        match published {
+            Ok(persona) => format!(
+                "🎭 published `{}`, every server can pick it now",
+                persona.key
+            ),
+            Err(PersonaError::Database(e)) => {
+                warn!(
+                    event = "persona_publish_failed",
+                    persona = %key,
+                    error = ?e,
+                    "Failed to publish persona preset"
+                );
+                "🔴 failed to publish the preset, nothing changed".to_string()
+            }
+            Err(e) => format!("{} 💅", e),
+        }
+    } else {
+        "❌ discarded, nothing published".to_string()
+    };
+    if let Err(e) = interaction
+        .create_response(
+            ctx.http(),
+            serenity::CreateInteractionResponse::UpdateMessage(
+                serenity::CreateInteractionResponseMessage::new()
+                    .content(status)
+                    .components(vec![]),
+            ),
+        )
+        .await
+    {
+        warn!(
+            event = "persona_preview_update_failed",
+            error = ?e,
+            "Failed to update persona preview"
+        );
+    }
+    Ok(())
+}
+
+/// Remove a published persona preset
+#[poise::command(slash_command, check = "super::permissions::require_superadmin")]
+pub async fn unpublish(
+    ctx: Context<'_>,
+    #[description = "Key of the preset"]
+    #[autocomplete = "autocomplete_preset"]
+    key: String,
+) -> Result<(), Error> {
+    match ctx.data().personas.unpublish(&key).await {
+        Ok(true) => {
+            reply::say(
+                ctx,
+                ReplyKind::Admin,
+                format!("🗑️ unpublished `{}`", key.trim()),
+            )
+            .await
+        }
+        Ok(false) => {
+            reply::say(
+                ctx,
+                ReplyKind::Admin,
+                "there's no published preset with that key",
+            )
+            .await
+        }
+        Err(PersonaError::Database(e)) => Err(e.into()),
+        Err(e) => reply::say(ctx, ReplyKind::Admin, format!("{} 💅", e)).await,
+    }
+}
+
+fn describe(persona: &Persona, current: bool) -> String {
+    format!(
+        "{} **{}** `{}`{}: {}\n",
+        if current { "✅" } else { "•" },
+        persona.name,
+        persona.key,
+        if persona.built_in { "" } else { " (published)" },
+        persona.description
+    )
+}
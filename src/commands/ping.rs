@@ -1,7 +1,8 @@
+use super::reply;
 use crate::{Context, Error};
+use chloe::services::reply_visibility::ReplyKind;
 
 #[poise::command(slash_command)]
 pub async fn ping(ctx: Context<'_>) -> Result<(), Error> {
-    ctx.say("Pong!").await?;
-    Ok(())
+    reply::say(ctx, ReplyKind::Status, "Pong!").await
 }
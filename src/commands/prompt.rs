@@ -0,0 +1,535 @@
+use super::permissions::{ChloeRole, role_of};
+use super::reply;
+use crate::services::scheduler::{ScheduledJob, parse_schedule_time};
+use crate::{ApplicationContext, Context, Error};
+use chloe::services::guild_prompts::PROMPT_HISTORY_LIMIT;
+use chloe::services::prompt_builder::{PROMPT_VARIABLES, unknown_placeholders};
+use chloe::services::reply_visibility::ReplyKind;
+use chrono::Utc;
+use poise::serenity_prelude as serenity;
+use std::time::Duration;
+use tracing::{info, warn};
+
+/// Discord caps modal text inputs at 4000 characters
+const MAX_PROMPT_CHARS: usize = 4000;
+/// How much of each version `/prompt history` shows
+const HISTORY_PREVIEW_CHARS: usize = 80;
+const MODAL_TIMEOUT: Duration = Duration::from_secs(60 * 15);
+const PREVIEW_TIMEOUT: Duration = Duration::from_secs(60 * 5);
+
+const ACTIVATE_ID: &str = "chloe_prompt_activate";
+const DISCARD_ID: &str = "chloe_prompt_discard";
+
+#[derive(Debug, poise::Modal)]
+#[name = "edit chloe's prompt"]
+struct PromptModal {
+    #[name = "system prompt"]
+    #[placeholder = "You're Chloe, a discord bot."]
+    #[paragraph]
+    #[min_length = 1]
+    #[max_length = 4000]
+    prompt: String,
+}
+
+/// Manage chloe's system prompt, globally or for this server
+#[poise::command(
+    slash_command,
+    subcommands(
+        "edit",
+        "schedule",
+        "schedules",
+        "unschedule",
+        "set",
+        "show",
+        "history",
+        "reset"
+    ),
+    subcommand_required
+)]
+pub async fn prompt(_ctx: Context<'_>) -> Result<(), Error> {
+    Ok(())
+}
+
+/// Edit the system prompt in a text box, preview it, then activate it as a new version
+#[poise::command(slash_command, check = "super::permissions::require_superadmin")]
+pub async fn edit(ctx: ApplicationContext<'_>) -> Result<(), Error> {
+    // the modal has to be the first response, so no deferring before this
+    let current = ctx.data().settings.get_global_settings().await.prompt;
+    let defaults =
+        (current.chars().count() <= MAX_PROMPT_CHARS).then_some(PromptModal { prompt: current });
+
+    let Some(PromptModal { prompt }) =
+        poise::execute_modal(ctx, defaults, Some(MODAL_TIMEOUT)).await?
+    else {
+        return Ok(());
+    };
+
+    let prompt = prompt.trim().to_string();
+    let Some(interaction) = preview_prompt(ctx, &prompt).await? else {
+        return Ok(());
+    };
+    let status = activate_prompt(ctx, &prompt).await;
+    finish_preview(ctx, &interaction, status).await;
+    Ok(())
+}
+
+/// Schedule a prompt version to be active for a while (times in UTC, e.g. 2025-10-25 18:00)
+#[poise::command(slash_command, check = "super::permissions::require_superadmin")]
+pub async fn schedule(
+    ctx: Context<'_>,
+    #[description = "Prompt version to activate"] version: i32,
+    #[description = "When to activate it (UTC)"] start: String,
+    #[description = "When to switch back to the previous prompt (UTC)"] end: Option<String>,
+) -> Result<(), Error> {
+    let Some(start_at) = parse_schedule_time(&start) else {
+        reply::send(
+            ctx,
+            ReplyKind::Admin,
+            poise::CreateReply::default()
+                .content("couldn't read the start time, use `YYYY-MM-DD HH:MM` (UTC)"),
+        )
+        .await?;
+        return Ok(());
+    };
+
+    let end_at = match end.as_deref().map(parse_schedule_time) {
+        Some(None) => {
+            reply::send(
+                ctx,
+                ReplyKind::Admin,
+                poise::CreateReply::default()
+                    .content("couldn't read the end time, use `YYYY-MM-DD HH:MM` (UTC)"),
+            )
+            .await?;
+            return Ok(());
+        }
+        Some(Some(end_at)) if end_at <= start_at.max(Utc::now()) => {
+            reply::send(
+                ctx,
+                ReplyKind::Admin,
+                poise::CreateReply::default()
+                    .content("the end time has to be after the start time and in the future"),
+            )
+            .await?;
+            return Ok(());
+        }
+        end_at => end_at.flatten(),
+    };
+
+    let data = ctx.data();
+    let Some(prompt_id) = data
+        .settings
+        .find_prompt_by_version(&data.db_pool, version)
+        .await?
+    else {
+        reply::send(
+            ctx,
+            ReplyKind::Admin,
+            poise::CreateReply::default().content(format!("there's no prompt version {}", version)),
+        )
+        .await?;
+        return Ok(());
+    };
+
+    let job_id = data
+        .scheduler
+        .schedule(
+            ScheduledJob::ActivatePrompt {
+                prompt_id,
+                version,
+                revert_at: end_at,
+            },
+            start_at,
+            Some(&ctx.author().name),
+        )
+        .await?;
+
+    let revert = end_at
+        .map(|end_at| format!(", switching back <t:{}:R>", end_at.timestamp()))
+        .unwrap_or_default();
+    reply::send(
+        ctx,
+        ReplyKind::Admin,
+        poise::CreateReply::default().content(format!(
+            "🗓️ prompt v{} goes live <t:{}:f>{}\n-# job `{}`",
+            version,
+            start_at.timestamp(),
+            revert,
+            job_id
+        )),
+    )
+    .await?;
+    Ok(())
+}
+
+/// List upcoming scheduled prompt activations
+#[poise::command(slash_command, check = "super::permissions::require_superadmin")]
+pub async fn schedules(ctx: Context<'_>) -> Result<(), Error> {
+    let jobs = ctx
+        .data()
+        .scheduler
+        .pending_jobs(ScheduledJob::ACTIVATE_PROMPT)
+        .await?;
+
+    let lines: Vec<String> = jobs
+        .iter()
+        .filter_map(|record| {
+            let ScheduledJob::ActivatePrompt {
+                version, revert_at, ..
+            } = &record.job
+            else {
+                return None;
+            };
+            let revert = revert_at
+                .map(|end_at| format!(" until <t:{}:f>", end_at.timestamp()))
+                .unwrap_or_default();
+            Some(format!(
+                "• v{} <t:{}:f>{} by {} — `{}`",
+                version,
+                record.run_at.timestamp(),
+                revert,
+                record.created_by.as_deref().unwrap_or("unknown"),
+                record.id
+            ))
+        })
+        .collect();
+
+    if lines.is_empty() {
+        return reply::say(ctx, ReplyKind::Admin, "nothing scheduled ✨").await;
+    }
+    reply::paginate(
+        ctx,
+        ReplyKind::Admin,
+        "scheduled prompts",
+        &lines.join("\n"),
+    )
+    .await
+}
+
+/// Cancel a scheduled prompt activation
+#[poise::command(slash_command, check = "super::permissions::require_superadmin")]
+pub async fn unschedule(
+    ctx: Context<'_>,
+    #[description = "Job id from /prompt schedules"] job_id: String,
+) -> Result<(), Error> {
+    let content = if ctx.data().scheduler.cancel(job_id.trim()).await? {
+        "🗑️ cancelled"
+    } else {
+        "no pending job with that id"
+    };
+
+    reply::send(
+        ctx,
+        ReplyKind::Admin,
+        poise::CreateReply::default().content(content),
+    )
+    .await?;
+    Ok(())
+}
+
+/// Give chloe a prompt of her own in this server, preview it, then activate it as a new version
+#[poise::command(slash_command, guild_only, check = "super::permissions::require_admin")]
+pub async fn set(ctx: ApplicationContext<'_>) -> Result<(), Error> {
+    let Some(guild_id) = ctx.guild_id() else {
+        return Ok(());
+    };
+    // the modal has to be the first response, so no deferring before this
+    let data = ctx.data();
+    // the global prompt stays with superadmins, a guild without its own starts blank
+    let current = match data.guild_prompts.active(guild_id.get()).await? {
+        Some(guild_prompt) => Some(guild_prompt.content),
+        None if role_of(ctx.into()).await? == ChloeRole::Superadmin => {
+            Some(data.settings.get_global_settings().await.prompt)
+        }
+        None => None,
+    };
+    let defaults = current
+        .filter(|current| current.chars().count() <= MAX_PROMPT_CHARS)
+        .map(|prompt| PromptModal { prompt });
+
+    let Some(PromptModal { prompt }) =
+        poise::execute_modal(ctx, defaults, Some(MODAL_TIMEOUT)).await?
+    else {
+        return Ok(());
+    };
+
+    let prompt = prompt.trim().to_string();
+    let Some(interaction) = preview_prompt(ctx, &prompt).await? else {
+        return Ok(());
+    };
+    let status = activate_guild_prompt(ctx, guild_id, &prompt).await;
+    finish_preview(ctx, &interaction, status).await;
+    Ok(())
+}
+
+/// Show the system prompt chloe uses in this server
+#[poise::command(slash_command, guild_only, check = "super::permissions::require_admin")]
+pub async fn show(ctx: Context<'_>) -> Result<(), Error> {
+    let Some(guild_id) = ctx.guild_id() else {
+        return Ok(());
+    };
+    let data = ctx.data();
+    let (title, prompt) = match data.guild_prompts.active(guild_id.get()).await? {
+        Some(guild_prompt) => (
+            format!("this server's prompt, v{}", guild_prompt.version),
+            guild_prompt.content,
+        ),
+        None => {
+            let global_settings = data.settings.get_global_settings().await;
+            let prompt = if role_of(ctx).await? == ChloeRole::Superadmin {
+                global_settings.prompt
+            } else {
+                "this server uses chloe's global prompt, give her one of its own with `/prompt set`"
+                    .to_string()
+            };
+            (
+                format!("global prompt, v{}", global_settings.prompt_version),
+                prompt,
+            )
+        }
+    };
+    reply::paginate(ctx, ReplyKind::Admin, &title, &prompt).await
+}
+
+/// List this server's prompt versions
+#[poise::command(slash_command, guild_only, check = "super::permissions::require_admin")]
+pub async fn history(ctx: Context<'_>) -> Result<(), Error> {
+    let Some(guild_id) = ctx.guild_id() else {
+        return Ok(());
+    };
+    let versions = ctx
+        .data()
+        .guild_prompts
+        .history(guild_id.get(), PROMPT_HISTORY_LIMIT)
+        .await?;
+
+    let lines: Vec<String> = versions
+        .iter()
+        .map(|version| {
+            let mut preview: String = version
+                .content
+                .chars()
+                .take(HISTORY_PREVIEW_CHARS)
+                .collect();
+            if version.content.chars().count() > HISTORY_PREVIEW_CHARS {
+                preview.push('…');
+            }
+            format!(
+                "• v{}{} <t:{}:R> by {}\n> {}",
+                version.version,
+                if version.is_active { " (active)" } else { "" },
+                version.created_at.timestamp(),
+                version.created_by.as_deref().unwrap_or("unknown"),
+                preview.replace('\n', " ")
+            )
+        })
+        .collect();
+
+    if lines.is_empty() {
+        return reply::say(
+            ctx,
+            ReplyKind::Admin,
+            "no prompts of its own yet, this server uses the global one ✨",
+        )
+        .await;
+    }
+    reply::paginate(ctx, ReplyKind::Admin, "prompt history", &lines.join("\n")).await
+}
+
+/// Go back to the global prompt in this server, its versions are kept
+#[poise::command(slash_command, guild_only, check = "super::permissions::require_admin")]
+pub async fn reset(ctx: Context<'_>) -> Result<(), Error> {
+    let Some(guild_id) = ctx.guild_id() else {
+        return Ok(());
+    };
+    let content = if ctx.data().guild_prompts.deactivate(guild_id.get()).await? {
+        "🔄 back to the global prompt"
+    } else {
+        "this server already uses the global prompt"
+    };
+    reply::say(ctx, ReplyKind::Admin, content).await
+}
+
+/// Show the prompt with activate and discard buttons. Returns the press of activate, to
+/// answer with how activating went, None once the preview was discarded or expired.
+async fn preview_prompt(
+    ctx: ApplicationContext<'_>,
+    prompt: &str,
+) -> Result<Option<serenity::ComponentInteraction>, Error> {
+    let mut preview = serenity::CreateEmbed::new()
+        .title("prompt preview")
+        .description(prompt)
+        .color(0xff69b4)
+        .footer(serenity::CreateEmbedFooter::new(format!(
+            "{} characters • not active until you activate it",
+            prompt.chars().count()
+        )));
+    if let Some(warning) = placeholder_warning(prompt) {
+        preview = preview.field("placeholders", warning, false);
+    }
+    let buttons = serenity::CreateActionRow::Buttons(vec![
+        serenity::CreateButton::new(ACTIVATE_ID)
+            .label("activate")
+            .style(serenity::ButtonStyle::Success),
+        serenity::CreateButton::new(DISCARD_ID)
+            .label("discard")
+            .style(serenity::ButtonStyle::Danger),
+    ]);
+
+    let reply = ctx
+        .send(
+            poise::CreateReply::default()
+                .embed(preview)
+                .components(vec![buttons])
+                .ephemeral(true),
+        )
+        .await?;
+    let message = reply.message().await?;
+
+    let Some(interaction) = message
+        .await_component_interaction(ctx.serenity_context())
+        .author_id(ctx.author().id)
+        .timeout(PREVIEW_TIMEOUT)
+        .await
+    else {
+        reply
+            .edit(
+                ctx.into(),
+                poise::CreateReply::default()
+                    .content("⌛ preview expired, prompt not changed")
+                    .components(vec![]),
+            )
+            .await?;
+        return Ok(None);
+    };
+
+    if interaction.data.custom_id != ACTIVATE_ID {
+        finish_preview(
+            ctx,
+            &interaction,
+            "❌ discarded, prompt not changed".to_string(),
+        )
+        .await;
+        return Ok(None);
+    }
+    Ok(Some(interaction))
+}
+
+/// Replace the preview with how it ended
+async fn finish_preview(
+    ctx: ApplicationContext<'_>,
+    interaction: &serenity::ComponentInteraction,
+    status: String,
+) {
+    if let Err(e) = interaction
+        .create_response(
+            ctx.http(),
+            serenity::CreateInteractionResponse::UpdateMessage(
+                serenity::CreateInteractionResponseMessage::new()
+                    .content(status)
+                    .components(vec![]),
+            ),
+        )
+        .await
+    {
+        warn!(
+            event = "prompt_preview_update_failed",
+            error = ?e,
+            "Failed to update prompt preview"
+        );
+    }
+}
+
+async fn activate_guild_prompt(
+    ctx: ApplicationContext<'_>,
+    guild_id: serenity::GuildId,
+    prompt: &str,
+) -> String {
+    let created_by = ctx.author().name.clone();
+    match ctx
+        .data()
+        .guild_prompts
+        .create_version(guild_id.get(), prompt, Some(&created_by))
+        .await
+    {
+        Ok(Some(version)) => {
+            info!(
+                event = "guild_prompt_set",
+                guild_id = %guild_id,
+                version = version,
+                user_id = ctx.author().id.get(),
+                "Guild prompt set from discord"
+            );
+            format!("✅ prompt v{} is now active in this server", version)
+        }
+        Ok(None) => "this server isn't registered yet, try again in a bit".to_string(),
+        Err(e) => {
+            warn!(
+                event = "guild_prompt_set_failed",
+                guild_id = %guild_id,
+                error = ?e,
+                "Failed to save guild prompt from modal"
+            );
+            "🔴 failed to save the prompt, nothing changed".to_string()
+        }
+    }
+}
+
+async fn activate_prompt(ctx: ApplicationContext<'_>, prompt: &str) -> String {
+    let data = ctx.data();
+    let created_by = ctx.author().name.clone();
+
+    let result = async {
+        let prompt_id = data
+            .settings
+            .create_new_prompt_version(&data.db_pool, prompt, Some(&created_by))
+            .await?;
+        data.settings
+            .activate_prompt_version(&data.db_pool, &prompt_id)
+            .await?;
+        Ok::<_, sqlx::Error>(prompt_id)
+    }
+    .await;
+
+    match result {
+        Ok(prompt_id) => {
+            info!(
+                event = "prompt_edited_via_modal",
+                prompt_id = %prompt_id,
+                user_id = ctx.author().id.get(),
+                "Prompt edited and activated from discord"
+            );
+            "✅ new prompt version activated".to_string()
+        }
+        Err(e) => {
+            warn!(
+                event = "prompt_activation_failed",
+                error = ?e,
+                "Failed to save prompt from modal"
+            );
+            "🔴 failed to save the prompt, nothing changed".to_string()
+        }
+    }
+}
+
+/// Point out placeholders chloe won't fill in, they'd reach the model as written
+fn placeholder_warning(prompt: &str) -> Option<String> {
+    let unknown = unknown_placeholders(prompt);
+    if unknown.is_empty() {
+        return None;
+    }
+    let unknown: Vec<&str> = unknown.iter().map(String::as_str).collect();
+    Some(format!(
+        "⚠️ {} won't be filled in, chloe only knows {}",
+        placeholder_list(&unknown),
+        placeholder_list(&PROMPT_VARIABLES)
+    ))
+}
+
+fn placeholder_list(names: &[&str]) -> String {
+    names
+        .iter()
+        .map(|name| format!("`{{{{{}}}}}`", name))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
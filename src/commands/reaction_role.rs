@@ -0,0 +1,162 @@
+use super::reply;
+use crate::{Context, Error};
+use chloe::services::reaction_roles::{ReactionRole, emoji_key, parse_message_ref};
+use chloe::services::reply_visibility::ReplyKind;
+use poise::serenity_prelude as serenity;
+
+/// Give members a role when they react on a message
+#[poise::command(
+    slash_command,
+    guild_only,
+    subcommands("add", "remove", "list"),
+    subcommand_required
+)]
+pub async fn reactionrole(_ctx: Context<'_>) -> Result<(), Error> {
+    Ok(())
+}
+
+/// Map an emoji on a message to a role
+#[poise::command(slash_command, guild_only, check = "super::permissions::require_admin")]
+pub async fn add(
+    ctx: Context<'_>,
+    #[description = "Message link, or the id of a message in this channel"] message: String,
+    #[description = "Emoji members react with"] emoji: String,
+    #[description = "Role they get"] role: serenity::Role,
+) -> Result<(), Error> {
+    let Some(guild_id) = ctx.guild_id() else {
+        return Ok(());
+    };
+
+    let Some((channel_id, message_id)) = parse_message_ref(&message, ctx.channel_id().get()) else {
+        return reply::say(ctx, ReplyKind::Admin, "that's not a message link or id").await;
+    };
+    let in_guild = ctx.guild().is_some_and(|guild| {
+        let channel_id = serenity::ChannelId::new(channel_id);
+        guild.channels.contains_key(&channel_id)
+            || guild.threads.iter().any(|thread| thread.id == channel_id)
+    });
+    if !in_guild {
+        return reply::say(ctx, ReplyKind::Admin, "that message isn't in this server").await;
+    }
+    if role.managed || role.id.get() == guild_id.get() {
+        return reply::say(ctx, ReplyKind::Admin, "that role can't be handed out").await;
+    }
+    let Ok(emoji) = serenity::ReactionType::try_from(emoji.trim()) else {
+        return reply::say(ctx, ReplyKind::Admin, "that's not an emoji I can use").await;
+    };
+
+    let channel_id = serenity::ChannelId::new(channel_id);
+    let Ok(target) = channel_id
+        .message(ctx.http(), serenity::MessageId::new(message_id))
+        .await
+    else {
+        return reply::say(
+            ctx,
+            ReplyKind::Admin,
+            "couldn't find that message, can I see the channel?",
+        )
+        .await;
+    };
+
+    // chloe's own reaction shows members what to click, and fails for unusable emojis
+    if target.react(ctx.http(), emoji.clone()).await.is_err() {
+        return reply::say(
+            ctx,
+            ReplyKind::Admin,
+            "couldn't react with that emoji, is it from this server?",
+        )
+        .await;
+    }
+
+    let mapping = ReactionRole {
+        channel_id: channel_id.get(),
+        message_id,
+        emoji: emoji_key(&emoji),
+        role_id: role.id.get(),
+    };
+    ctx.data()
+        .reaction_roles
+        .add(
+            guild_id.get() as i64,
+            &mapping,
+            &ctx.author().id.to_string(),
+        )
+        .await?;
+
+    reply::say(
+        ctx,
+        ReplyKind::Admin,
+        format!(
+            "✅ reacting with {} on {} now gives <@&{}>. my role has to be above it and I need manage roles",
+            emoji,
+            target.link(),
+            role.id
+        ),
+    )
+    .await
+}
+
+/// Stop an emoji on a message from giving a role
+#[poise::command(slash_command, guild_only, check = "super::permissions::require_admin")]
+pub async fn remove(
+    ctx: Context<'_>,
+    #[description = "Message link, or the id of a message in this channel"] message: String,
+    #[description = "Emoji to stop handing out a role for"] emoji: String,
+) -> Result<(), Error> {
+    let (Some((_, message_id)), Ok(emoji)) = (
+        parse_message_ref(&message, ctx.channel_id().get()),
+        serenity::ReactionType::try_from(emoji.trim()),
+    ) else {
+        return reply::say(
+            ctx,
+            ReplyKind::Admin,
+            "I need a message link or id and an emoji",
+        )
+        .await;
+    };
+
+    let removed = ctx
+        .data()
+        .reaction_roles
+        .remove(message_id, &emoji_key(&emoji))
+        .await?;
+    let content = if removed {
+        "🗑️ removed"
+    } else {
+        "that emoji isn't set up on that message"
+    };
+    reply::say(ctx, ReplyKind::Admin, content).await
+}
+
+/// List this server's reaction roles
+#[poise::command(slash_command, guild_only, check = "super::permissions::require_admin")]
+pub async fn list(ctx: Context<'_>) -> Result<(), Error> {
+    let Some(guild_id) = ctx.guild_id() else {
+        return Ok(());
+    };
+
+    let mappings = ctx
+        .data()
+        .reaction_roles
+        .list(guild_id.get() as i64)
+        .await?;
+    if mappings.is_empty() {
+        return reply::say(ctx, ReplyKind::Admin, "no reaction roles set up yet").await;
+    }
+
+    let lines: Vec<String> = mappings
+        .iter()
+        .map(|mapping| {
+            // custom emojis are stored by id
+            let emoji = match mapping.emoji.parse::<u64>() {
+                Ok(id) => format!("<:emoji:{}>", id),
+                Err(_) => mapping.emoji.clone(),
+            };
+            format!(
+                "• {} → <@&{}> on https://discord.com/channels/{}/{}/{}",
+                emoji, mapping.role_id, guild_id, mapping.channel_id, mapping.message_id
+            )
+        })
+        .collect();
+    reply::say(ctx, ReplyKind::Admin, &lines.join("\n")).await
+}
@@ -0,0 +1,111 @@
+use super::reply;
+use crate::{Context, Error};
+use chloe::services::reminders::{MAX_MESSAGE_LENGTH, Reminder, ReminderError, resolve_remind_at};
+use chloe::services::reply_visibility::ReplyKind;
+use chrono::Utc;
+
+/// Get pinged about something later
+#[poise::command(
+    slash_command,
+    subcommands("me", "list", "cancel"),
+    subcommand_required
+)]
+pub async fn remind(_ctx: Context<'_>) -> Result<(), Error> {
+    Ok(())
+}
+
+/// Set a reminder, chloe pings you in this channel when it's due
+#[poise::command(slash_command)]
+pub async fn me(
+    ctx: Context<'_>,
+    #[description = "In how long, like 2h or 1d 30m, or a UTC time like 2025-10-25 18:30"]
+    when: String,
+    #[description = "What to remind you of"] message: String,
+) -> Result<(), Error> {
+    let message = message.trim();
+    if message.is_empty() || message.chars().count() > MAX_MESSAGE_LENGTH {
+        return reply::say(
+            ctx,
+            ReplyKind::Private,
+            format!("reminders need 1 to {} characters", MAX_MESSAGE_LENGTH),
+        )
+        .await;
+    }
+    let remind_at = match resolve_remind_at(&when, Utc::now()) {
+        Ok(remind_at) => remind_at,
+        Err(e) => return reply::say(ctx, ReplyKind::Private, &e).await,
+    };
+
+    let created = ctx
+        .data()
+        .reminders
+        .create(
+            ctx.guild_id().map(|id| id.get()),
+            ctx.channel_id().get(),
+            ctx.author().id.get(),
+            message,
+            remind_at,
+        )
+        .await;
+    match created {
+        Ok(reminder) => {
+            reply::say(
+                ctx,
+                ReplyKind::Private,
+                format!("⏰ {}", describe(&reminder)),
+            )
+            .await
+        }
+        Err(e @ ReminderError::TooManyPending) => {
+            reply::say(ctx, ReplyKind::Private, format!("{} 💅", e)).await
+        }
+        Err(ReminderError::Database(e)) => Err(e.into()),
+    }
+}
+
+/// List your pending reminders
+#[poise::command(slash_command)]
+pub async fn list(ctx: Context<'_>) -> Result<(), Error> {
+    let reminders = ctx
+        .data()
+        .reminders
+        .pending_for_user(ctx.author().id.get())
+        .await?;
+    if reminders.is_empty() {
+        return reply::say(ctx, ReplyKind::Private, "no reminders pending").await;
+    }
+
+    let content: String = reminders
+        .iter()
+        .map(|reminder| format!("• {}\n", describe(reminder)))
+        .collect();
+    reply::paginate(ctx, ReplyKind::Private, "⏰ your reminders", &content).await
+}
+
+/// Cancel one of your pending reminders
+#[poise::command(slash_command)]
+pub async fn cancel(
+    ctx: Context<'_>,
+    #[description = "Id shown by /remind list"] id: String,
+) -> Result<(), Error> {
+    let cancelled = ctx
+        .data()
+        .reminders
+        .cancel(ctx.author().id.get(), &id)
+        .await?;
+    let content = match cancelled {
+        Some(reminder) => format!("🗑️ cancelled {}", describe(&reminder)),
+        None => "you have no pending reminder with that id".to_string(),
+    };
+    reply::say(ctx, ReplyKind::Private, &content).await
+}
+
+fn describe(reminder: &Reminder) -> String {
+    let message: String = reminder.message.chars().take(100).collect();
+    format!(
+        "`{}` <t:{}:R>: \"{}\"",
+        reminder.short_id(),
+        reminder.remind_at.timestamp(),
+        message
+    )
+}
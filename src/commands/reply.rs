@@ -0,0 +1,72 @@
+use crate::{Context, Error};
+use chloe::services::reply_visibility::{ReplyKind, ReplyVisibility};
+use chloe::utils::Paginator;
+use chloe::utils::pagination::MAX_PAGE_CHARS;
+use poise::serenity_prelude as serenity;
+
+/// Whether a reply of `kind` is ephemeral where the command ran, DMs use the defaults
+pub async fn is_ephemeral(ctx: Context<'_>, kind: ReplyKind) -> bool {
+    match ctx.guild_id() {
+        Some(guild_id) => {
+            ctx.data()
+                .guild_service
+                .is_reply_ephemeral(guild_id.get() as i64, kind)
+                .await
+        }
+        None => ReplyVisibility::default().is_ephemeral(kind),
+    }
+}
+
+/// Send `reply` as the guild's `replyVisibility` wants replies of `kind` sent. Mentions
+/// never ping, a public admin reply shouldn't notify whoever it names.
+pub async fn send(
+    ctx: Context<'_>,
+    kind: ReplyKind,
+    reply: poise::CreateReply,
+) -> Result<(), Error> {
+    let ephemeral = is_ephemeral(ctx, kind).await;
+    ctx.send(
+        reply
+            .allowed_mentions(serenity::CreateAllowedMentions::new())
+            .ephemeral(ephemeral),
+    )
+    .await?;
+    Ok(())
+}
+
+pub async fn say(
+    ctx: Context<'_>,
+    kind: ReplyKind,
+    content: impl Into<String>,
+) -> Result<(), Error> {
+    send(ctx, kind, poise::CreateReply::default().content(content)).await
+}
+
+/// Send a list or other long text as embed pages with ◀ ▶ buttons, however long it gets
+pub async fn paginate(
+    ctx: Context<'_>,
+    kind: ReplyKind,
+    title: &str,
+    text: &str,
+) -> Result<(), Error> {
+    let pages = Paginator::split_pages(text, MAX_PAGE_CHARS);
+    let (embed, components, _) = ctx.data().paginator.first_page(Some(title), pages).await?;
+    send(
+        ctx,
+        kind,
+        poise::CreateReply::default()
+            .embed(embed)
+            .components(components),
+    )
+    .await
+}
+
+/// Defer for a slow command so the eventual reply has the right visibility
+pub async fn defer(ctx: Context<'_>, kind: ReplyKind) -> Result<(), Error> {
+    if is_ephemeral(ctx, kind).await {
+        ctx.defer_ephemeral().await?;
+    } else {
+        ctx.defer().await?;
+    }
+    Ok(())
+}
@@ -0,0 +1,57 @@
+use super::reply;
+use crate::{Context, Error};
+use chloe::services::reply_visibility::ReplyKind;
+use tracing::info;
+
+/// Turns dropped when no count is given, the last message and chloe's answer to it
+const DEFAULT_REWIND_TURNS: u8 = 2;
+
+/// DMs are the member's own to rewind, a server channel's turns are everyone's
+async fn require_dm_or_admin(ctx: Context<'_>) -> Result<bool, Error> {
+    if ctx.guild_id().is_none() {
+        return Ok(true);
+    }
+    super::permissions::require_admin(ctx).await
+}
+
+/// Make chloe forget the latest turns of the conversation in this channel
+#[poise::command(slash_command, check = "require_dm_or_admin")]
+pub async fn rewind(
+    ctx: Context<'_>,
+    #[description = "How many of the latest turns to forget, 2 by default"]
+    #[min = 1]
+    #[max = 20]
+    turns: Option<u8>,
+) -> Result<(), Error> {
+    let turns = turns.unwrap_or(DEFAULT_REWIND_TURNS);
+    let channel_id = ctx.channel_id().get();
+    let data = ctx.data();
+
+    let forgotten = data
+        .conversations
+        .rewind(channel_id, turns as usize)
+        .await?;
+    if forgotten.is_empty() {
+        return reply::say(ctx, ReplyKind::Private, "there's nothing to rewind here").await;
+    }
+    data.message_cache.forget(channel_id, &forgotten).await;
+    data.saved_conversations.forget_turns(&forgotten).await?;
+
+    info!(
+        event = "conversation_rewound",
+        channel_id = channel_id,
+        user_id = %ctx.author().id,
+        turns = forgotten.len(),
+        "Conversation rewound"
+    );
+
+    let message = if forgotten.len() == 1 {
+        "⏪ forgot the last turn, pretend it never happened".to_string()
+    } else {
+        format!(
+            "⏪ forgot the last {} turns, pretend they never happened",
+            forgotten.len()
+        )
+    };
+    reply::say(ctx, ReplyKind::Status, message).await
+}
@@ -0,0 +1,120 @@
+use super::reply;
+use crate::{Context, Error};
+use chloe::services::reply_visibility::ReplyKind;
+use chloe::services::scheduled_messages::{MAX_CONTENT_LENGTH, ScheduleError, ScheduledMessage};
+use poise::serenity_prelude as serenity;
+
+/// Have chloe post announcements on a schedule
+#[poise::command(
+    slash_command,
+    guild_only,
+    subcommands("add", "list", "remove"),
+    subcommand_required
+)]
+pub async fn schedule(_ctx: Context<'_>) -> Result<(), Error> {
+    Ok(())
+}
+
+/// Post a message on a cron schedule, e.g. 0 9 * * 1-5 for 9:00 on weekdays
+#[poise::command(slash_command, guild_only, check = "super::permissions::require_admin")]
+pub async fn add(
+    ctx: Context<'_>,
+    #[description = "Channel to post in"] channel: serenity::GuildChannel,
+    #[description = "When, as minute hour day month weekday, e.g. 0 9 * * mon or @daily"]
+    cron: String,
+    #[description = "What to post"] message: String,
+    #[description = "Timezone the schedule is in, e.g. Europe/Berlin, UTC by default"]
+    timezone: Option<String>,
+) -> Result<(), Error> {
+    let Some(guild_id) = ctx.guild_id() else {
+        return Ok(());
+    };
+    let message = message.trim();
+    if message.is_empty() || message.chars().count() > MAX_CONTENT_LENGTH {
+        return reply::say(
+            ctx,
+            ReplyKind::Admin,
+            format!("messages need 1 to {} characters", MAX_CONTENT_LENGTH),
+        )
+        .await;
+    }
+
+    let created = ctx
+        .data()
+        .scheduled_messages
+        .create(
+            guild_id.get(),
+            channel.id.get(),
+            &cron,
+            timezone.as_deref(),
+            message,
+            ctx.author().id.get(),
+        )
+        .await;
+    match created {
+        Ok(schedule) => {
+            reply::say(ctx, ReplyKind::Admin, format!("🗓️ {}", describe(&schedule))).await
+        }
+        Err(ScheduleError::Database(e)) => Err(e.into()),
+        Err(e) => reply::say(ctx, ReplyKind::Admin, format!("{} 💅", e)).await,
+    }
+}
+
+/// List this server's scheduled messages
+#[poise::command(slash_command, guild_only, check = "super::permissions::require_admin")]
+pub async fn list(ctx: Context<'_>) -> Result<(), Error> {
+    let Some(guild_id) = ctx.guild_id() else {
+        return Ok(());
+    };
+
+    let schedules = ctx.data().scheduled_messages.list(guild_id.get()).await?;
+    if schedules.is_empty() {
+        return reply::say(ctx, ReplyKind::Admin, "nothing scheduled ✨").await;
+    }
+
+    let content: String = schedules
+        .iter()
+        .map(|schedule| format!("• {}\n", describe(schedule)))
+        .collect();
+    reply::paginate(ctx, ReplyKind::Admin, "🗓️ scheduled messages", &content).await
+}
+
+/// Stop posting a scheduled message
+#[poise::command(slash_command, guild_only, check = "super::permissions::require_admin")]
+pub async fn remove(
+    ctx: Context<'_>,
+    #[description = "Id shown by /schedule list"] id: String,
+) -> Result<(), Error> {
+    let Some(guild_id) = ctx.guild_id() else {
+        return Ok(());
+    };
+
+    let removed = ctx
+        .data()
+        .scheduled_messages
+        .remove(guild_id.get(), &id)
+        .await?;
+    let content = match removed {
+        Some(schedule) => format!("🗑️ removed {}", describe(&schedule)),
+        None => "there's no scheduled message with that id".to_string(),
+    };
+    reply::say(ctx, ReplyKind::Admin, &content).await
+}
+
+fn describe(schedule: &ScheduledMessage) -> String {
+    let message: String = schedule.content.chars().take(100).collect();
+    let next = if schedule.enabled {
+        format!("next <t:{}:R>", schedule.next_run_at.timestamp())
+    } else {
+        "paused, i can't post in that channel".to_string()
+    };
+    format!(
+        "`{}` `{}` ({}) in <#{}>, {}: \"{}\"",
+        schedule.short_id(),
+        schedule.cron,
+        schedule.timezone.name(),
+        schedule.channel_id,
+        next,
+        message
+    )
+}
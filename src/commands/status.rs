@@ -1,4 +1,11 @@
+use super::reply;
 use crate::{Context, Error};
+use chloe::queue::listener::queue_stats;
+use chloe::services::model_router::GuildModelConfig;
+use chloe::services::reply_visibility::ReplyKind;
+use chloe::utils::build_info;
+use chloe::utils::cache_stats::CacheSnapshot;
+use chloe::utils::rate_limiter::RateLimiterSnapshot;
 use poise::serenity_prelude as serenity;
 use sqlx::Row;
 use std::time::{Duration, SystemTime};
@@ -10,14 +17,17 @@ pub async fn status(ctx: Context<'_>) -> Result<(), Error> {
     let start_time = SystemTime::now();
 
     // Defer the response since we'll be collecting a lot of metrics
-    ctx.defer().await?;
+    reply::defer(ctx, ReplyKind::Status).await?;
 
     // Collect all metrics concurrently
-    let (runtime_metrics, system_info, db_health, redis_health) = tokio::join!(
+    let (runtime_metrics, system_info, db_health, redis_health, llm_info, queue_info, rate_limits) = tokio::join!(
         collect_runtime_metrics(),
         collect_system_metrics(),
         check_database_health(&ctx.data().db_pool),
-        check_redis_health(&ctx.data().redis_client)
+        check_redis_health(&ctx.data().redis_client),
+        collect_llm_info(ctx),
+        check_queue(&ctx.data().redis_client),
+        collect_rate_limits(ctx)
     );
 
     let collection_time = start_time.elapsed().unwrap_or(Duration::ZERO);
@@ -32,17 +42,28 @@ pub async fn status(ctx: Context<'_>) -> Result<(), Error> {
         .field("database", db_health, true)
         .field("cache", redis_health, true)
         .field("guild info", format_guild_info(ctx), true)
+        .field("build", format_build_info(), true)
+        .field("llm", llm_info, true)
+        .field("providers", format_provider_health(ctx), true)
+        .field("queue", queue_info, true)
+        .field("cache hits", format_cache_hits(ctx), true)
+        .field("rate limits", rate_limits, true)
         .field(
             "collection time",
             format!("{}ms", collection_time.as_millis()),
             true,
         )
-        .footer(serenity::CreateEmbedFooter::new(
-            "chloe v0.1.0 • made with sparkle and good vibes! ✨",
-        ));
+        .footer(serenity::CreateEmbedFooter::new(format!(
+            "chloe {} • made with sparkle and good vibes! ✨",
+            build_info::version_line()
+        )));
 
-    ctx.send(poise::CreateReply::default().embed(embed)).await?;
-    Ok(())
+    reply::send(
+        ctx,
+        ReplyKind::Status,
+        poise::CreateReply::default().embed(embed),
+    )
+    .await
 }
 
 async fn collect_runtime_metrics() -> String {
@@ -51,6 +72,20 @@ async fn collect_runtime_metrics() -> String {
     format!("**workers:** {}\n**runtime:** tokio", metrics.num_workers())
 }
 
+fn format_build_info() -> String {
+    let built_at = build_info::built_at()
+        .map(|at| format!("<t:{}:R>", at.timestamp()))
+        .unwrap_or_else(|| "unknown".to_string());
+    format!(
+        "**version:** {}\n**commit:** `{}`\n**built:** {}\n**uptime:** {} (since <t:{}:f>)",
+        build_info::VERSION,
+        build_info::GIT_SHA,
+        built_at,
+        build_info::format_uptime(build_info::uptime()),
+        build_info::started_at().timestamp()
+    )
+}
+
 async fn collect_system_metrics() -> String {
     let mut system = System::new_all();
     system.refresh_all();
@@ -142,6 +177,149 @@ async fn check_redis_health(redis_client: &redis::Client) -> String {
     }
 }
 
+async fn collect_llm_info(ctx: Context<'_>) -> String {
+    let llm_service = &ctx.data().llm_service;
+    let mut lines = vec![format!(
+        "**default model:** {}",
+        llm_service.default_model()
+    )];
+
+    if let Some(guild_id) = ctx.guild_id() {
+        let setting = ctx
+            .data()
+            .guild_service
+            .get_guild_setting(guild_id.get() as i64, "llmConfig")
+            .await;
+        if let Some(model) = GuildModelConfig::from_setting(setting.as_ref()).model {
+            lines.push(format!("**pinned here:** {}", model));
+        }
+    }
+
+    let empty_responses = llm_service.empty_response_counts();
+    if empty_responses.is_empty() {
+        lines.push("**empty replies:** none".to_string());
+    }
+    lines.extend(empty_responses.iter().map(|(model, count)| {
+        format!(
+            "**{}:** {} empty, {} recovered",
+            model, count.empty, count.recovered
+        )
+    }));
+    lines.join("\n")
+}
+
+fn format_provider_health(ctx: Context<'_>) -> String {
+    let health = ctx.data().llm_service.provider_health();
+    if health.is_empty() {
+        return "no requests yet".to_string();
+    }
+    let lines: Vec<String> = health
+        .iter()
+        .map(|provider| {
+            let status = match provider.error_rate() {
+                rate if rate >= 0.25 => "🔴",
+                rate if rate > 0.0 => "🟡",
+                _ => "🟢",
+            };
+            format!(
+                "{} **{}:** {:.0}% errors of last {}\np50 {}ms, p95 {}ms",
+                status,
+                provider.provider,
+                provider.error_rate() * 100.0,
+                provider.requests,
+                provider.median_latency.as_millis(),
+                provider.p95_latency.as_millis()
+            )
+        })
+        .collect();
+    lines.join("\n")
+}
+
+async fn check_queue(redis_client: &redis::Client) -> String {
+    match queue_stats(redis_client).await {
+        Ok(stats) => {
+            let last_processed = stats
+                .last_processed
+                .map(|at| format!("<t:{}:R>", at.timestamp()))
+                .unwrap_or_else(|| "never".to_string());
+            format!(
+                "**depth:** {}\n**last processed:** {}",
+                stats.depth, last_processed
+            )
+        }
+        Err(e) => format!(
+            "**status:** 🔴 error\n**error:** {}",
+            e.to_string().chars().take(50).collect::<String>()
+        ),
+    }
+}
+
+fn format_cache_hits(ctx: Context<'_>) -> String {
+    let data = ctx.data();
+    let tool_results = data
+        .llm_service
+        .tool_cache_stats()
+        .map(|stats| ("tool results", stats));
+    let lines: Vec<String> = data
+        .guild_service
+        .cache_stats()
+        .into_iter()
+        .chain(tool_results)
+        .map(|(cache, stats)| format!("**{}:** {}", cache, format_hit_rate(&stats)))
+        .collect();
+    lines.join("\n")
+}
+
+async fn collect_rate_limits(ctx: Context<'_>) -> String {
+    let data = ctx.data();
+    let llm = data.llm_service.rate_limiter_stats().await;
+    let dm_chat = data.dm_limiter.snapshot().await;
+    [
+        format_limiter("llm", &llm),
+        format_limiter("dm chat", &dm_chat),
+    ]
+    .join("\n")
+}
+
+/// Slots in use, waits and rejections, and the busiest key when one is close to its limit
+fn format_limiter(name: &str, snapshot: &RateLimiterSnapshot) -> String {
+    let totals = &snapshot.totals;
+    let mut line = format!(
+        "**{}:** {}/{} busy",
+        name, snapshot.in_flight, snapshot.max_concurrent
+    );
+    if let Some(wait) = totals.average_wait() {
+        line.push_str(&format!(
+            ", {} waited ~{}ms",
+            totals.waited,
+            wait.as_millis()
+        ));
+    }
+    if totals.rejected() > 0 {
+        line.push_str(&format!(
+            ", {} turned away ({} busy, {} too fast)",
+            totals.rejected(),
+            totals.rejected_concurrency,
+            totals.rejected_interval
+        ));
+    }
+    if let Some(busiest) = snapshot.keys.first().filter(|key| key.saturation >= 0.5) {
+        line.push_str(&format!(
+            "\nbusiest: `{}` {:.0}%",
+            busiest.key,
+            busiest.saturation * 100.0
+        ));
+    }
+    line
+}
+
+fn format_hit_rate(stats: &CacheSnapshot) -> String {
+    match stats.hit_rate() {
+        Some(rate) => format!("{:.0}% of {}", rate * 100.0, stats.hits + stats.misses),
+        None => "unused".to_string(),
+    }
+}
+
 fn format_guild_info(ctx: Context<'_>) -> String {
     let member_count = ctx
         .guild()
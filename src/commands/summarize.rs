@@ -0,0 +1,79 @@
+use super::reply;
+use crate::{Context, Error};
+use chloe::services::reply_visibility::ReplyKind;
+use chloe::services::summarizer::{self, HistoryRange};
+use poise::serenity_prelude as serenity;
+use tracing::{error, info};
+
+/// Discord's limit on an embed description
+const MAX_EMBED_DESCRIPTION: usize = 4096;
+
+/// Recap what was said in this channel, with key decisions and action items
+#[poise::command(slash_command)]
+pub async fn summarize(
+    ctx: Context<'_>,
+    #[description = "How many of the latest messages to read, 100 by default"]
+    #[min = 1]
+    #[max = 500]
+    count: Option<u32>,
+    #[description = "Read everything from this long ago instead, like 2h or 1d 12h"] since: Option<
+        String,
+    >,
+) -> Result<(), Error> {
+    let range = match HistoryRange::from_options(
+        count.map(|count| count as usize),
+        since.as_deref(),
+        chrono::Utc::now(),
+    ) {
+        Ok(range) => range,
+        Err(e) => return reply::say(ctx, ReplyKind::Private, e).await,
+    };
+    reply::defer(ctx, ReplyKind::Private).await?;
+
+    let messages = summarizer::fetch_history(ctx.http(), ctx.channel_id(), None, range).await?;
+    let lines = summarizer::transcript(&messages);
+    if lines.is_empty() {
+        return reply::say(ctx, ReplyKind::Private, "there's nothing to summarize here").await;
+    }
+
+    let recap = match summarizer::summarize(&ctx.data().llm_service, &lines).await {
+        Ok(recap) => recap,
+        Err(e) => {
+            error!(
+                event = "summary_failed",
+                channel_id = %ctx.channel_id(),
+                error = ?e,
+                "Failed to summarize channel"
+            );
+            return reply::say(
+                ctx,
+                ReplyKind::Private,
+                "couldn't write the recap right now, try again in a bit",
+            )
+            .await;
+        }
+    };
+    info!(
+        event = "channel_summarized",
+        channel_id = %ctx.channel_id(),
+        user_id = %ctx.author().id,
+        messages = lines.len(),
+        "Summarized channel"
+    );
+
+    let recap: String = recap.chars().take(MAX_EMBED_DESCRIPTION).collect();
+    let embed = serenity::CreateEmbed::new()
+        .color(0xff69b4)
+        .title("📝 recap")
+        .description(recap)
+        .footer(serenity::CreateEmbedFooter::new(format!(
+            "{} messages read",
+            lines.len()
+        )));
+    reply::send(
+        ctx,
+        ReplyKind::Private,
+        poise::CreateReply::default().embed(embed),
+    )
+    .await
+}
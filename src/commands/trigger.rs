@@ -0,0 +1,135 @@
+use super::reply;
+use crate::{Context, Error};
+use chloe::services::reply_visibility::ReplyKind;
+use chloe::services::triggers::{MAX_PATTERN_LENGTH, MAX_RESPONSE_LENGTH, Trigger};
+use poise::serenity_prelude as serenity;
+
+/// Answer messages matching a keyword or pattern automatically
+#[poise::command(
+    slash_command,
+    guild_only,
+    subcommands("add", "remove", "list"),
+    subcommand_required
+)]
+pub async fn trigger(_ctx: Context<'_>) -> Result<(), Error> {
+    Ok(())
+}
+
+/// Add an auto-response, or replace the one with the same pattern
+#[poise::command(slash_command, guild_only, check = "super::permissions::require_admin")]
+pub async fn add(
+    ctx: Context<'_>,
+    #[description = "Keyword that sets it off, or a regex with regex:true"] pattern: String,
+    #[description = "What to reply, or instructions for chloe with llm:true"] response: String,
+    #[description = "Treat the pattern as a regular expression"] regex: Option<bool>,
+    #[description = "Let chloe write the answer following the response as instructions"]
+    llm: Option<bool>,
+    #[description = "Only answer in this channel"] channel: Option<serenity::GuildChannel>,
+) -> Result<(), Error> {
+    let Some(guild_id) = ctx.guild_id() else {
+        return Ok(());
+    };
+
+    let trigger = Trigger {
+        pattern: pattern.trim().to_string(),
+        is_regex: regex.unwrap_or(false),
+        response: response.trim().to_string(),
+        use_llm: llm.unwrap_or(false),
+        channel_id: channel.map(|channel| channel.id.get()),
+    };
+    if trigger.pattern.is_empty() || trigger.pattern.len() > MAX_PATTERN_LENGTH {
+        return reply::say(
+            ctx,
+            ReplyKind::Admin,
+            format!("patterns need 1 to {} characters", MAX_PATTERN_LENGTH),
+        )
+        .await;
+    }
+    if trigger.response.is_empty() || trigger.response.len() > MAX_RESPONSE_LENGTH {
+        return reply::say(
+            ctx,
+            ReplyKind::Admin,
+            format!("responses need 1 to {} characters", MAX_RESPONSE_LENGTH),
+        )
+        .await;
+    }
+    if let Err(e) = trigger.matcher() {
+        return reply::say(
+            ctx,
+            ReplyKind::Admin,
+            format!("that regex doesn't work: {}", e),
+        )
+        .await;
+    }
+
+    ctx.data()
+        .triggers
+        .add(
+            guild_id.get() as i64,
+            &trigger,
+            &ctx.author().id.to_string(),
+        )
+        .await?;
+
+    reply::say(ctx, ReplyKind::Admin, format!("✅ {}", describe(&trigger))).await
+}
+
+/// Remove an auto-response
+#[poise::command(slash_command, guild_only, check = "super::permissions::require_admin")]
+pub async fn remove(
+    ctx: Context<'_>,
+    #[description = "Pattern of the trigger to remove"] pattern: String,
+) -> Result<(), Error> {
+    let Some(guild_id) = ctx.guild_id() else {
+        return Ok(());
+    };
+
+    let removed = ctx
+        .data()
+        .triggers
+        .remove(guild_id.get() as i64, pattern.trim())
+        .await?;
+    let content = if removed {
+        "🗑️ removed"
+    } else {
+        "there's no trigger with that pattern"
+    };
+    reply::say(ctx, ReplyKind::Admin, content).await
+}
+
+/// List this server's auto-responses
+#[poise::command(slash_command, guild_only, check = "super::permissions::require_admin")]
+pub async fn list(ctx: Context<'_>) -> Result<(), Error> {
+    let Some(guild_id) = ctx.guild_id() else {
+        return Ok(());
+    };
+
+    let triggers = ctx.data().triggers.list(guild_id.get() as i64).await?;
+    if triggers.is_empty() {
+        return reply::say(ctx, ReplyKind::Admin, "no triggers set up yet").await;
+    }
+
+    let content: String = triggers
+        .iter()
+        .map(|trigger| format!("• {}\n", describe(trigger)))
+        .collect();
+    reply::paginate(ctx, ReplyKind::Admin, "⚡ triggers", &content).await
+}
+
+fn describe(trigger: &Trigger) -> String {
+    let kind = if trigger.is_regex { "regex" } else { "keyword" };
+    let answer = if trigger.use_llm {
+        "chloe answers following"
+    } else {
+        "replies with"
+    };
+    let channel = trigger
+        .channel_id
+        .map(|id| format!(" in <#{}>", id))
+        .unwrap_or_default();
+    let response: String = trigger.response.chars().take(100).collect();
+    format!(
+        "{} `{}`{}: {} \"{}\"",
+        kind, trigger.pattern, channel, answer, response
+    )
+}
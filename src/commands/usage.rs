@@ -0,0 +1,92 @@
+use super::reply;
+use crate::{Context, Error};
+use chloe::services::reply_visibility::ReplyKind;
+use chloe::services::usage_service::{ExportFormat, parse_date_range};
+use chrono::{Duration, Utc};
+use poise::serenity_prelude as serenity;
+use tracing::info;
+
+/// Days exported when no range is given, today included
+const DEFAULT_EXPORT_DAYS: i64 = 30;
+
+#[derive(Debug, Clone, Copy, poise::ChoiceParameter)]
+pub enum Format {
+    #[name = "csv"]
+    Csv,
+    #[name = "json"]
+    Json,
+}
+
+impl From<Format> for ExportFormat {
+    fn from(format: Format) -> Self {
+        match format {
+            Format::Csv => ExportFormat::Csv,
+            Format::Json => ExportFormat::Json,
+        }
+    }
+}
+
+/// See what chloe costs in this server
+#[poise::command(slash_command, subcommands("export"), subcommand_required)]
+pub async fn usage(_ctx: Context<'_>) -> Result<(), Error> {
+    Ok(())
+}
+
+/// Download this server's token usage per day, member and model
+#[poise::command(slash_command, guild_only, check = "super::permissions::require_admin")]
+pub async fn export(
+    ctx: Context<'_>,
+    #[description = "First day, like 2025-10-01, 30 days ago by default"] from: Option<String>,
+    #[description = "Last day, like 2025-10-31, today by default (UTC)"] to: Option<String>,
+    #[description = "File format, csv by default"] format: Option<Format>,
+) -> Result<(), Error> {
+    let Some(guild_id) = ctx.guild_id() else {
+        return Ok(());
+    };
+    let today = Utc::now().date_naive();
+    let from =
+        from.unwrap_or_else(|| (today - Duration::days(DEFAULT_EXPORT_DAYS - 1)).to_string());
+    let to = to.unwrap_or_else(|| today.to_string());
+    let (from, to) = match parse_date_range(&from, &to) {
+        Ok(range) => range,
+        Err(e) => return reply::say(ctx, ReplyKind::Admin, e).await,
+    };
+    let format: ExportFormat = format.unwrap_or(Format::Csv).into();
+    reply::defer(ctx, ReplyKind::Admin).await?;
+
+    let rows = ctx
+        .data()
+        .usage_service
+        .guild_rows(guild_id.get(), from, to)
+        .await?;
+    info!(
+        event = "usage_export_command_used",
+        guild_id = %guild_id,
+        user_id = %ctx.author().id,
+        rows = rows.len(),
+        "Usage export command used"
+    );
+    if rows.is_empty() {
+        return reply::say(
+            ctx,
+            ReplyKind::Admin,
+            format!("no usage recorded here from {} to {}", from, to),
+        )
+        .await;
+    }
+
+    let cost: f64 = rows.iter().map(|row| row.cost_usd()).sum();
+    let filename = format!("usage-{}-{}-{}.{}", guild_id, from, to, format.extension());
+    let attachment = serenity::CreateAttachment::bytes(format.render(&rows), filename);
+    reply::send(
+        ctx,
+        ReplyKind::Admin,
+        poise::CreateReply::default()
+            .content(format!(
+                "📊 usage from {} to {}, about ${:.2} at list prices",
+                from, to, cost
+            ))
+            .attachment(attachment),
+    )
+    .await
+}
@@ -1,3 +1,4 @@
+use std::time::Duration;
 use thiserror::Error;
 
 #[derive(Error, Debug)]
@@ -44,6 +45,25 @@ pub enum BotError {
 
 pub type Result<T> = std::result::Result<T, BotError>;
 
+/// Model provider failures the message handler reacts to, carried inside `anyhow::Error`
+#[derive(Error, Debug)]
+pub enum LlmError {
+    #[error("{provider} request timed out after {}s", after.as_secs_f64())]
+    Timeout {
+        provider: &'static str,
+        after: Duration,
+    },
+}
+
+impl LlmError {
+    pub fn is_timeout(error: &anyhow::Error) -> bool {
+        matches!(
+            error.downcast_ref::<LlmError>(),
+            Some(LlmError::Timeout { .. })
+        )
+    }
+}
+
 // Conversion utilities
 impl BotError {
     pub fn env_var(var: &str) -> Self {
@@ -0,0 +1,13 @@
+// the library exists so the eval binary can share chloe's code with the bot itself
+#![allow(clippy::new_without_default, clippy::should_implement_trait)]
+
+pub mod database;
+pub mod error;
+pub mod queue;
+pub mod reactions;
+pub mod redis_client;
+pub mod schema;
+pub mod services;
+pub mod settings;
+pub mod tools;
+pub mod utils;
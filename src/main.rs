@@ -6,38 +6,55 @@ use std::sync::Arc;
 use std::time::Duration;
 use tracing::{error, info};
 
+use chloe::{queue, reactions, schema, services, settings, tools, utils};
+
 mod commands;
-mod database;
-mod error;
-mod queue;
-mod reactions;
-mod redis_client;
-mod schema;
-mod services;
-mod settings;
-mod tools;
-mod utils;
 
 type Error = Box<dyn std::error::Error + Send + Sync>;
 type Context<'a> = poise::Context<'a, Data, Error>;
+type ApplicationContext<'a> = poise::ApplicationContext<'a, Data, Error>;
 
 pub struct Data {
     redis_client: redis::Client,
     db_pool: PgPool,
     settings: settings::Settings,
     guild_service: Arc<services::guild_service::GuildService>,
+    user_service: Arc<services::user_service::UserService>,
+    scheduler: Arc<services::scheduler::Scheduler>,
     llm_service: Arc<services::llm_service::LlmService>,
+    reaction_roles: Arc<services::reaction_roles::ReactionRoleService>,
+    triggers: Arc<services::triggers::TriggerService>,
+    modmail: Arc<services::modmail::ModmailService>,
+    reminders: Arc<services::reminders::ReminderService>,
+    scheduled_messages: Arc<services::scheduled_messages::ScheduledMessageService>,
+    personas: Arc<services::personas::PersonaService>,
+    custom_commands: Arc<services::custom_commands::CustomCommandService>,
+    leveling: Arc<services::leveling::LevelingService>,
+    dm_limiter: Arc<utils::RateLimiter>,
+    content_incidents: Arc<services::content_incidents::ContentIncidentService>,
+    guild_prompts: Arc<services::guild_prompts::GuildPromptService>,
+    usage_service: Arc<services::usage_service::UsageService>,
+    conversations: Arc<services::conversation_service::ConversationService>,
+    saved_conversations: Arc<services::saved_conversations::SavedConversationService>,
+    message_cache: Arc<utils::message_cache::MessageCache>,
+    paginator: Arc<utils::Paginator>,
 }
 
 #[tokio::main]
 async fn main() -> Result<()> {
+    utils::build_info::mark_started();
     tracing_subscriber::fmt()
         .with_env_filter(
             tracing_subscriber::EnvFilter::from_default_env().add_directive("chloe=info".parse()?),
         )
         .init();
 
-    info!(event = "bot_startup", "Starting chloe 💅💄");
+    info!(
+        event = "bot_startup",
+        version = utils::build_info::VERSION,
+        git_sha = utils::build_info::GIT_SHA,
+        "Starting chloe 💅💄"
+    );
 
     let redis_url = std::env::var("REDIS_URL").expect("Expected REDIS_URL in environment");
     let redis_client = redis::Client::open(redis_url)?;
@@ -66,18 +83,133 @@ async fn main() -> Result<()> {
         "Connected to redis"
     );
 
-    let app_settings = settings::Settings::new();
-    let guild_service = Arc::new(services::guild_service::GuildService::new(db_pool.clone()));
+    let change_events = Arc::new(services::change_events::ChangeEvents::from_env(
+        redis_client.clone(),
+    ));
+    let app_settings = settings::Settings::new().with_change_events(Arc::clone(&change_events));
+
+    // `chloe replay [options]` re-runs recorded provider traffic offline instead of starting the bot
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if args.first().map(String::as_str) == Some("replay") {
+        schema::initialize_database(&db_pool).await?;
+        let options = services::provider_recorder::ReplayOptions::from_args(&args[1..])?;
+        return services::provider_recorder::replay(&db_pool, &app_settings, options).await;
+    }
+
+    let guild_service = Arc::new(
+        services::guild_service::GuildService::new(db_pool.clone())
+            .with_change_events(Arc::clone(&change_events)),
+    );
     let user_service = Arc::new(services::user_service::UserService::new(db_pool.clone()));
-    let llm_service = Arc::new(services::llm_service::LlmService::new(Arc::new(
+    let paginator = Arc::new(utils::Paginator::new(redis_client.clone()));
+    let usage_service = Arc::new(services::usage_service::UsageService::new(db_pool.clone()));
+    // pgvector is checked for in setup, facts and turns are only embedded once it's found
+    let semantic_memory = services::embeddings::configured_embedder(&reqwest::Client::new())
+        .map(|embedder| {
+            Arc::new(services::semantic_memory::SemanticMemory::new(
+                db_pool.clone(),
+                embedder,
+            ))
+        });
+    let user_memories = Arc::new(
+        services::user_memories::UserMemoryService::new(db_pool.clone())
+            .with_semantic_memory(semantic_memory.clone()),
+    );
+    let reminders = Arc::new(services::reminders::ReminderService::new(db_pool.clone()));
+    let alerts = services::alerting::AlertMonitor::from_env().map(Arc::new);
+    let content_incidents = Arc::new(
+        services::content_incidents::ContentIncidentService::new(db_pool.clone()),
+    );
+    let message_cache = Arc::new(utils::message_cache::MessageCache::new(redis_client.clone()));
+    let llm_service = Arc::new(services::llm_service::LlmService::new(
+        Arc::new(app_settings.clone()),
+        Arc::clone(&paginator),
+        Arc::clone(&guild_service),
+        services::provider_recorder::ProviderRecorder::from_env(db_pool.clone()),
+        Some(Arc::clone(&usage_service)),
+        Some(user_memories),
+        Arc::new(utils::reaction_tracker::ReactionTracker::new(
+            redis_client.clone(),
+        )),
+    )?
+    .with_tool(Arc::new(tools::SetReminderTool::new(Arc::clone(&reminders))))
+    .with_tool(Arc::new(tools::ListRemindersTool::new(Arc::clone(&reminders))))
+    .with_tool(Arc::new(tools::CancelReminderTool::new(Arc::clone(&reminders))))
+    .with_tool(Arc::new(tools::DiscordTimeoutUserTool::new(Arc::clone(&guild_service))))
+    .with_tool(Arc::new(tools::DiscordDeleteMessageTool::new(Arc::clone(&guild_service))))
+    .with_tool(Arc::new(tools::DiscordKickUserTool::new(Arc::clone(&guild_service))))
+    .with_tool(Arc::new(tools::ReadFullMessageTool::new(Arc::clone(&message_cache))))
+    .with_tool(Arc::new(tools::SummarizeChannelTool::new()))
+    .with_tool_cache(Arc::new(tools::result_cache::ToolResultCache::from_env(
+        redis_client.clone(),
+    )))
+    .with_alerts(alerts.clone())
+    .with_content_incidents(Arc::clone(&content_incidents)));
+
+    let reaction_roles = Arc::new(services::reaction_roles::ReactionRoleService::new(
+        db_pool.clone(),
+    ));
+
+    let triggers = Arc::new(services::triggers::TriggerService::new(db_pool.clone()));
+    let modmail = Arc::new(services::modmail::ModmailService::new(db_pool.clone()));
+    let starboard = Arc::new(services::starboard::StarboardService::new(db_pool.clone()));
+    let custom_commands = Arc::new(services::custom_commands::CustomCommandService::new(
+        db_pool.clone(),
+    ));
+    let personas = Arc::new(services::personas::PersonaService::new(db_pool.clone()));
+    let guild_prompts = Arc::new(
+        services::guild_prompts::GuildPromptService::new(db_pool.clone())
+            .with_change_events(change_events),
+    );
+    let leveling = Arc::new(services::leveling::LevelingService::new(
+        db_pool.clone(),
+        redis_client.clone(),
+    ));
+    let scheduled_messages = Arc::new(
+        services::scheduled_messages::ScheduledMessageService::new(db_pool.clone()),
+    );
+    let saved_conversations = Arc::new(
+        services::saved_conversations::SavedConversationService::new(db_pool.clone()),
+    );
+    let regenerations = Arc::new(services::regenerations::RegenerationStore::new(
+        redis_client.clone(),
+    ));
+    let dm_limiter = Arc::new(utils::create_dm_rate_limiter());
+    let conversations = Arc::new(
+        services::conversation_service::ConversationService::new(db_pool.clone())
+            .with_semantic_memory(semantic_memory.clone()),
+    );
+
+    let scheduler = Arc::new(services::scheduler::Scheduler::new(
+        db_pool.clone(),
         app_settings.clone(),
-    ))?);
+    ));
 
     let redis_client_for_framework = redis_client.clone();
     let db_pool_for_framework = db_pool.clone();
     let settings_for_framework = app_settings.clone();
     let guild_service_for_framework = Arc::clone(&guild_service);
+    let user_service_for_framework = Arc::clone(&user_service);
+    let scheduler_for_framework = Arc::clone(&scheduler);
     let llm_service_for_framework = Arc::clone(&llm_service);
+    let reaction_roles_for_framework = Arc::clone(&reaction_roles);
+    let triggers_for_framework = Arc::clone(&triggers);
+    let modmail_for_framework = Arc::clone(&modmail);
+    let reminders_for_framework = Arc::clone(&reminders);
+    let scheduled_messages_for_framework = Arc::clone(&scheduled_messages);
+    let alerts_for_framework = alerts.clone();
+    let personas_for_framework = Arc::clone(&personas);
+    let custom_commands_for_framework = Arc::clone(&custom_commands);
+    let leveling_for_framework = Arc::clone(&leveling);
+    let dm_limiter_for_framework = Arc::clone(&dm_limiter);
+    let content_incidents_for_framework = Arc::clone(&content_incidents);
+    let guild_prompts_for_framework = Arc::clone(&guild_prompts);
+    let usage_service_for_framework = Arc::clone(&usage_service);
+    let conversations_for_framework = Arc::clone(&conversations);
+    let saved_conversations_for_framework = Arc::clone(&saved_conversations);
+    let message_cache_for_framework = Arc::clone(&message_cache);
+    let paginator_for_framework = Arc::clone(&paginator);
+    let semantic_memory_for_framework = semantic_memory.clone();
 
     let queue_listener = queue::QueueListener::new(
         redis_client.clone(),
@@ -85,6 +217,7 @@ async fn main() -> Result<()> {
         app_settings.clone(),
         Arc::clone(&guild_service),
         Arc::clone(&user_service),
+        Arc::clone(&usage_service),
     );
     tokio::spawn(async move {
         queue_listener.start_listening().await;
@@ -92,7 +225,30 @@ async fn main() -> Result<()> {
 
     let framework = poise::Framework::builder()
         .options(poise::FrameworkOptions {
-            commands: vec![commands::ping::ping(), commands::status::status()],
+            commands: vec![
+                commands::ping::ping(),
+                commands::status::status(),
+                commands::admin::admin(),
+                commands::custom_command::custom_commands(),
+                commands::prompt::prompt(),
+                commands::leveling::rank(),
+                commands::leveling::leaderboard(),
+                commands::leveling::levelreward(),
+                commands::summarize::summarize(),
+                commands::rewind::rewind(),
+                commands::conversation::conversation(),
+                commands::usage::usage(),
+                commands::model::model(),
+                commands::content_filter::contentfilter(),
+                commands::modmail::modmail(),
+                commands::persona::persona(),
+                commands::reaction_role::reactionrole(),
+                commands::remind::remind(),
+                commands::dm_chat::dmchat(),
+                commands::schedule::schedule(),
+                commands::trigger::trigger(),
+            ],
+            on_error: |error| Box::pin(commands::errors::on_error(error)),
             ..Default::default()
         })
         .setup(move |ctx, _ready, framework| {
@@ -100,7 +256,27 @@ async fn main() -> Result<()> {
             let db_pool = db_pool_for_framework;
             let settings = settings_for_framework;
             let guild_service = guild_service_for_framework;
+            let user_service = user_service_for_framework;
+            let scheduler = scheduler_for_framework;
             let llm_service = llm_service_for_framework;
+            let reaction_roles = reaction_roles_for_framework;
+            let triggers = triggers_for_framework;
+            let modmail = modmail_for_framework;
+            let reminders = reminders_for_framework;
+            let scheduled_messages = scheduled_messages_for_framework;
+            let alerts = alerts_for_framework;
+            let personas = personas_for_framework;
+            let custom_commands = custom_commands_for_framework;
+            let leveling = leveling_for_framework;
+            let dm_limiter = dm_limiter_for_framework;
+            let content_incidents = content_incidents_for_framework;
+            let guild_prompts = guild_prompts_for_framework;
+            let usage_service = usage_service_for_framework;
+            let conversations = conversations_for_framework;
+            let saved_conversations = saved_conversations_for_framework;
+            let message_cache = message_cache_for_framework;
+            let paginator = paginator_for_framework;
+            let semantic_memory = semantic_memory_for_framework;
 
             Box::pin(async move {
                 if let Err(e) = schema::initialize_database(&db_pool).await {
@@ -111,6 +287,10 @@ async fn main() -> Result<()> {
                     );
                 }
 
+                if let Some(semantic_memory) = &semantic_memory {
+                    semantic_memory.initialize().await;
+                }
+
                 if let Err(e) = schema::ensure_global_settings(&db_pool).await {
                     error!(
                         event = "global_settings_ensure_failed",
@@ -119,20 +299,34 @@ async fn main() -> Result<()> {
                     );
                 }
 
+                match user_service.bootstrap_superadmins().await {
+                    Ok(promoted) if promoted > 0 => info!(
+                        event = "superadmins_bootstrapped",
+                        promoted = promoted,
+                        "Promoted superadmins from SUPERADMIN_IDS"
+                    ),
+                    Ok(_) => {}
+                    Err(e) => error!(
+                        event = "superadmin_bootstrap_failed",
+                        error = ?e,
+                        "Failed to bootstrap superadmins"
+                    ),
+                }
+
                 let current_guilds: Vec<_> = ctx.cache.guilds().iter().cloned().collect();
-                if let Err(e) = schema::sync_guilds(&db_pool, &current_guilds, ctx).await {
-                    error!(
+                match schema::sync_missing_guilds(&db_pool, &current_guilds, ctx).await {
+                    Ok(missing) => info!(
+                        event = "guilds_synced",
+                        guild_count = current_guilds.len(),
+                        missing_count = missing,
+                        "Checked guilds against the database"
+                    ),
+                    Err(e) => error!(
                         event = "guild_sync_failed",
                         error = ?e,
                         guild_count = current_guilds.len(),
                         "Failed to sync guilds"
-                    );
-                } else {
-                    info!(
-                        event = "guilds_synced",
-                        guild_count = current_guilds.len(),
-                        "Successfully synced guilds to database"
-                    );
+                    ),
                 }
 
                 if let Err(e) = settings.load_from_database(&db_pool).await {
@@ -143,6 +337,63 @@ async fn main() -> Result<()> {
                     );
                 }
 
+                // the first message after a deploy shouldn't pay for cold connections and caches
+                let warmup_llm = Arc::clone(&llm_service);
+                let warmup_guilds = Arc::clone(&guild_service);
+                tokio::spawn(async move {
+                    match warmup_guilds.preload_settings().await {
+                        Ok(guild_count) => info!(
+                            event = "guild_settings_preloaded",
+                            guild_count = guild_count,
+                            "Preloaded guild settings"
+                        ),
+                        Err(e) => error!(
+                            event = "guild_settings_preload_failed",
+                            error = ?e,
+                            "Failed to preload guild settings"
+                        ),
+                    }
+                    warmup_llm.warm_up().await;
+                });
+
+                let prompt_watcher = settings.clone();
+                let watcher_pool = db_pool.clone();
+                tokio::spawn(async move {
+                    prompt_watcher.watch_active_prompt(watcher_pool).await;
+                });
+
+                // started here rather than in main so the jobs table exists
+                let scheduler_worker = Arc::clone(&scheduler);
+                tokio::spawn(async move {
+                    scheduler_worker.start().await;
+                });
+
+                let reminder_worker = Arc::clone(&reminders);
+                let reminder_http = ctx.http.clone();
+                tokio::spawn(async move {
+                    reminder_worker.start(reminder_http).await;
+                });
+
+                let scheduled_message_worker = Arc::clone(&scheduled_messages);
+                let scheduled_message_http = ctx.http.clone();
+                tokio::spawn(async move {
+                    scheduled_message_worker
+                        .start(scheduled_message_http)
+                        .await;
+                });
+
+                if let Some(alerts) = alerts {
+                    let alert_http = ctx.http.clone();
+                    tokio::spawn(async move {
+                        alerts.start(alert_http).await;
+                    });
+                }
+
+                let conversation_pruner = Arc::clone(&conversations);
+                tokio::spawn(async move {
+                    conversation_pruner.prune_periodically().await;
+                });
+
                 poise::builtins::register_globally(ctx, &framework.options().commands).await?;
                 info!(
                     event = "commands_registered",
@@ -153,7 +404,25 @@ async fn main() -> Result<()> {
                     db_pool,
                     settings,
                     guild_service,
+                    user_service,
+                    scheduler,
                     llm_service,
+                    reaction_roles,
+                    triggers,
+                    modmail,
+                    reminders,
+                    scheduled_messages,
+                    personas,
+                    custom_commands,
+                    leveling,
+                    dm_limiter,
+                    content_incidents,
+                    guild_prompts,
+                    usage_service,
+                    conversations,
+                    saved_conversations,
+                    message_cache,
+                    paginator,
                 })
             })
         })
@@ -161,13 +430,73 @@ async fn main() -> Result<()> {
 
     let token = std::env::var("DISCORD_TOKEN").expect("Expected DISCORD_TOKEN in environment");
 
-    let intents = GatewayIntents::non_privileged() | GatewayIntents::MESSAGE_CONTENT;
+    let mut intents = GatewayIntents::non_privileged() | GatewayIntents::MESSAGE_CONTENT;
+    if utils::presence::presences_enabled() {
+        intents |= GatewayIntents::GUILD_PRESENCES;
+    }
+    if services::member_greetings::member_events_enabled() {
+        intents |= GatewayIntents::GUILD_MEMBERS;
+    }
+
+    let llm_handler = Arc::new(reactions::llm_handler::LLMHandler::new(
+        Arc::clone(&guild_service),
+        Arc::clone(&llm_service),
+        message_cache,
+        Arc::clone(&triggers),
+        Arc::clone(&usage_service),
+        Arc::clone(&conversations),
+        Arc::clone(&personas),
+        Arc::clone(&guild_prompts),
+        saved_conversations,
+        regenerations,
+        Arc::clone(&user_service),
+        dm_limiter,
+    ));
+    // offered each message in this order, the first to claim it answers
+    let message_router = reactions::message_router::MessageRouter::new()
+        .subscribe(Arc::new(reactions::modmail_handler::ModmailHandler::new(
+            Arc::clone(&guild_service),
+            Arc::clone(&modmail),
+            Arc::clone(&user_service),
+        )))
+        .subscribe(Arc::new(reactions::ping_handler::PingHandler::new(
+            Arc::clone(&guild_service),
+        )))
+        .subscribe(llm_handler.clone())
+        .subscribe(Arc::new(
+            reactions::leveling_handler::LevelingHandler::new(
+                Arc::clone(&guild_service),
+                leveling,
+            ),
+        ));
 
     let client = ClientBuilder::new(token, intents)
         .framework(framework)
-        .event_handler(reactions::llm_handler::LLMHandler::new(
+        .event_handler(message_router)
+        .event_handler_arc(llm_handler)
+        .event_handler(reactions::guild_handler::GuildHandler::new(
+            db_pool.clone(),
+            Arc::clone(&guild_service),
+            Arc::clone(&scheduler),
+            Arc::clone(&personas),
+        ))
+        .event_handler(reactions::interaction_handler::InteractionHandler::new(
+            Arc::clone(&paginator),
+        ))
+        .event_handler(reactions::custom_command_handler::CustomCommandHandler::new(
+            custom_commands,
+        ))
+        .event_handler(reactions::reaction_role_handler::ReactionRoleHandler::new(
+            Arc::clone(&reaction_roles),
+        ))
+        .event_handler(reactions::member_handler::MemberHandler::new(
             Arc::clone(&guild_service),
             Arc::clone(&llm_service),
+            Arc::clone(&personas),
+        ))
+        .event_handler(reactions::starboard_handler::StarboardHandler::new(
+            Arc::clone(&guild_service),
+            starboard,
         ))
         .await;
 
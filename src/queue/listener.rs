@@ -1,19 +1,45 @@
-use super::{settings_update, update_prompt, user_operations};
+use super::{settings_update, update_prompt, usage_export, user_operations};
 use crate::services::guild_service::GuildService;
+use crate::services::usage_service::UsageService;
 use crate::services::user_service::UserService;
 use crate::settings::Settings;
+use chrono::{DateTime, Utc};
 use redis::{AsyncCommands, Client, RedisResult};
 use sqlx::PgPool;
 use std::sync::Arc;
 use tokio::time::{Duration, sleep};
 use tracing::{error, info, warn};
 
+/// Redis list other services push work for chloe onto
+pub const QUEUE_NAME: &str = "chloe";
+
+/// Unix time the listener last finished a message, for /status
+const LAST_PROCESSED_KEY: &str = "chloe:queue:last_processed";
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct QueueStats {
+    pub depth: usize,
+    pub last_processed: Option<DateTime<Utc>>,
+}
+
+/// How many messages are waiting and when the last one was handled, by any instance
+pub async fn queue_stats(client: &Client) -> RedisResult<QueueStats> {
+    let mut conn = client.get_multiplexed_async_connection().await?;
+    let depth: usize = conn.llen(QUEUE_NAME).await?;
+    let last_processed: Option<i64> = conn.get(LAST_PROCESSED_KEY).await?;
+    Ok(QueueStats {
+        depth,
+        last_processed: last_processed.and_then(|at| DateTime::from_timestamp(at, 0)),
+    })
+}
+
 pub struct QueueListener {
     client: Client,
     db_pool: PgPool,
     settings: Settings,
     guild_service: Arc<GuildService>,
     user_service: Arc<UserService>,
+    usage: Arc<UsageService>,
 }
 
 impl QueueListener {
@@ -23,6 +49,7 @@ impl QueueListener {
         settings: Settings,
         guild_service: Arc<GuildService>,
         user_service: Arc<UserService>,
+        usage: Arc<UsageService>,
     ) -> Self {
         Self {
             client,
@@ -30,6 +57,7 @@ impl QueueListener {
             settings,
             guild_service,
             user_service,
+            usage,
         }
     }
 
@@ -57,7 +85,7 @@ impl QueueListener {
         let mut conn = self.client.get_multiplexed_async_connection().await?;
 
         // lower this later
-        let result: Option<Vec<String>> = conn.brpop("chloe", 300.0).await?;
+        let result: Option<Vec<String>> = conn.brpop(QUEUE_NAME, 300.0).await?;
 
         if let Some(values) = result {
             if values.len() >= 2 {
@@ -112,6 +140,14 @@ impl QueueListener {
                                 )
                                 .await;
                             }
+                            "export_usage" => {
+                                usage_export::handle_export_usage(
+                                    message,
+                                    &self.usage,
+                                    &self.client,
+                                )
+                                .await;
+                            }
                             _ => {
                                 warn!(
                                     event = "unknown_json_action",
@@ -154,6 +190,17 @@ impl QueueListener {
                         }
                     }
                 }
+
+                if let Err(e) = conn
+                    .set::<_, _, ()>(LAST_PROCESSED_KEY, Utc::now().timestamp())
+                    .await
+                {
+                    warn!(
+                        event = "queue_last_processed_store_failed",
+                        error = %e,
+                        "Failed to store when the queue was last processed"
+                    );
+                }
             }
         }
 
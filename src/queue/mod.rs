@@ -1,6 +1,7 @@
 pub mod listener;
 pub mod settings_update;
 pub mod update_prompt;
+pub mod usage_export;
 pub mod user_operations;
 
 pub use listener::QueueListener;
@@ -0,0 +1,91 @@
+use super::user_operations::send_response;
+use crate::services::usage_service::{ExportFormat, UsageService, parse_date_range};
+use redis::Client;
+use serde_json::{Value, json};
+use tracing::{error, info};
+
+/// Answer an `export_usage` message, e.g.
+/// `{"action": "export_usage", "request_id": "abc", "guild_snowflake": "123",
+/// "from": "2025-10-01", "to": "2025-10-31", "format": "csv"}`, with the guild's usage
+/// rows for the range as a file's content. The format defaults to csv.
+pub async fn handle_export_usage(message: &str, usage: &UsageService, redis_client: &Client) {
+    let parsed: Value = match serde_json::from_str(message) {
+        Ok(parsed) => parsed,
+        Err(e) => {
+            error!(
+                event = "usage_export_parse_failed",
+                error = ?e,
+                "Failed to parse export_usage message as JSON"
+            );
+            return;
+        }
+    };
+    let request_id = parsed
+        .get("request_id")
+        .and_then(|v| v.as_str())
+        .unwrap_or("unknown");
+
+    let response = match export(&parsed, usage).await {
+        Ok((filename, format, content)) => {
+            info!(
+                event = "usage_export_completed",
+                request_id = %request_id,
+                filename = %filename,
+                "Usage export sent"
+            );
+            json!({
+                "success": true,
+                "request_id": request_id,
+                "data": {
+                    "filename": filename,
+                    "format": format.extension(),
+                    "content": content
+                }
+            })
+        }
+        Err(e) => {
+            error!(
+                event = "usage_export_failed",
+                request_id = %request_id,
+                error = %e,
+                "Usage export failed"
+            );
+            json!({
+                "success": false,
+                "request_id": request_id,
+                "error": e
+            })
+        }
+    };
+
+    send_response(redis_client, &response).await;
+}
+
+async fn export(
+    parsed: &Value,
+    usage: &UsageService,
+) -> Result<(String, ExportFormat, String), String> {
+    let field = |key: &str| {
+        parsed
+            .get(key)
+            .and_then(|v| v.as_str())
+            .ok_or_else(|| format!("Missing '{}' field for export_usage action", key))
+    };
+    let guild_id: u64 = field("guild_snowflake")?
+        .parse()
+        .map_err(|_| "Invalid guild_snowflake format".to_string())?;
+    let (from, to) = parse_date_range(field("from")?, field("to")?)?;
+    let format = match parsed.get("format").and_then(|v| v.as_str()) {
+        Some(format) => {
+            ExportFormat::from_str(format).ok_or_else(|| format!("Unknown format: {}", format))?
+        }
+        None => ExportFormat::Csv,
+    };
+
+    let rows = usage
+        .guild_rows(guild_id, from, to)
+        .await
+        .map_err(|e| format!("Usage lookup failed: {:?}", e))?;
+    let filename = format!("usage-{}-{}-{}.{}", guild_id, from, to, format.extension());
+    Ok((filename, format, format.render(&rows)))
+}
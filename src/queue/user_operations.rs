@@ -445,7 +445,7 @@ async fn handle_get_users_by_internal_ids(
     send_response(redis_client, &response).await;
 }
 
-async fn send_response(redis_client: &Client, response: &Value) {
+pub(super) async fn send_response(redis_client: &Client, response: &Value) {
     match redis_client.get_multiplexed_async_connection().await {
         Ok(mut conn) => {
             let response_str = response.to_string();
@@ -0,0 +1,94 @@
+use crate::services::custom_commands::CustomCommandService;
+use serenity::builder::{
+    CreateAllowedMentions, CreateEmbed, CreateInteractionResponse, CreateInteractionResponseMessage,
+};
+use serenity::model::application::{CommandInteraction, Interaction};
+use serenity::{async_trait, prelude::*};
+use std::sync::Arc;
+use tracing::{error, info, warn};
+
+const CUSTOM_COMMAND_COLOR: u32 = 0xff69b4;
+
+/// Answers the slash commands guild admins define with `/commands`. They're registered
+/// as guild commands, chloe's own are global and go through poise.
+pub struct CustomCommandHandler {
+    pub custom_commands: Arc<CustomCommandService>,
+}
+
+impl CustomCommandHandler {
+    pub fn new(custom_commands: Arc<CustomCommandService>) -> Self {
+        Self { custom_commands }
+    }
+
+    async fn respond(&self, ctx: &Context, interaction: &CommandInteraction) {
+        let Some(guild_id) = interaction.guild_id else {
+            return;
+        };
+        let name = &interaction.data.name;
+        let message = match self.custom_commands.find(guild_id.get() as i64, name).await {
+            Ok(Some(command)) if command.embed => CreateInteractionResponseMessage::new().embed(
+                CreateEmbed::new()
+                    .description(&command.response)
+                    .color(CUSTOM_COMMAND_COLOR),
+            ),
+            Ok(Some(command)) => CreateInteractionResponseMessage::new().content(&command.response),
+            Ok(None) => {
+                // removed while discord still offered it
+                warn!(
+                    event = "custom_command_missing",
+                    guild_id = %guild_id,
+                    name = %name,
+                    "Custom command isn't in the database"
+                );
+                CreateInteractionResponseMessage::new()
+                    .content("this command doesn't exist anymore 💔")
+                    .ephemeral(true)
+            }
+            Err(e) => {
+                error!(
+                    event = "custom_command_load_failed",
+                    guild_id = %guild_id,
+                    name = %name,
+                    error = ?e,
+                    "Failed to load custom command"
+                );
+                CreateInteractionResponseMessage::new()
+                    .content("oops, something broke on my end 💔 try again in a bit")
+                    .ephemeral(true)
+            }
+        };
+
+        // the text is the admin's, it shouldn't ping whoever it names
+        let response = CreateInteractionResponse::Message(
+            message.allowed_mentions(CreateAllowedMentions::new()),
+        );
+        match interaction.create_response(&ctx.http, response).await {
+            Ok(()) => info!(
+                event = "custom_command_used",
+                guild_id = %guild_id,
+                user_id = %interaction.user.id,
+                name = %name,
+                "Answered custom command"
+            ),
+            Err(e) => warn!(
+                event = "custom_command_response_failed",
+                guild_id = %guild_id,
+                name = %name,
+                error = ?e,
+                "Failed to answer custom command"
+            ),
+        }
+    }
+}
+
+#[async_trait]
+impl EventHandler for CustomCommandHandler {
+    async fn interaction_create(&self, ctx: Context, interaction: Interaction) {
+        match interaction {
+            Interaction::Command(command) if command.data.guild_id.is_some() => {
+                self.respond(&ctx, &command).await;
+            }
+            _ => {}
+        }
+    }
+}
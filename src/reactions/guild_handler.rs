@@ -0,0 +1,438 @@
+use crate::schema::{self, GuildSnapshot};
+use crate::services::guild_retention;
+use crate::services::guild_service::GuildService;
+use crate::services::personas::{PERSONA_SETTING, PersonaService};
+use crate::services::scheduler::{ScheduledJob, Scheduler};
+use serde_json::json;
+use serenity::builder::{
+    CreateActionRow, CreateButton, CreateEmbed, CreateInteractionResponse,
+    CreateInteractionResponseMessage, CreateMessage, CreateSelectMenu, CreateSelectMenuKind,
+    CreateSelectMenuOption, EditMessage,
+};
+use serenity::model::Permissions;
+use serenity::model::application::{
+    ButtonStyle, ComponentInteraction, ComponentInteractionDataKind,
+};
+use serenity::model::channel::{ChannelType, Message};
+use serenity::model::guild::{Guild, PartialGuild, UnavailableGuild};
+use serenity::model::id::ChannelId;
+use serenity::{async_trait, prelude::*};
+use sqlx::PgPool;
+use std::sync::Arc;
+use tokio::time::{Duration, Instant};
+use tracing::{error, info, warn};
+
+/// How long the setup message stays interactive
+const ONBOARDING_TIMEOUT: Duration = Duration::from_secs(60 * 30);
+
+/// Discord allows at most 25 options in a select menu
+const MAX_CHANNEL_OPTIONS: usize = 25;
+
+/// Discord cuts select option descriptions at 100 characters
+const MAX_OPTION_DESCRIPTION_CHARS: usize = 100;
+
+const CHANNELS_ID: &str = "chloe_onboard_channels";
+const PERSONA_ID: &str = "chloe_onboard_persona";
+const ENABLE_ID: &str = "chloe_onboard_enable";
+const SKIP_ID: &str = "chloe_onboard_skip";
+
+/// Registers newly joined guilds and walks their admins through initial setup
+pub struct GuildHandler {
+    pub db_pool: PgPool,
+    pub guild_service: Arc<GuildService>,
+    pub scheduler: Arc<Scheduler>,
+    pub personas: Arc<PersonaService>,
+}
+
+impl GuildHandler {
+    pub fn new(
+        db_pool: PgPool,
+        guild_service: Arc<GuildService>,
+        scheduler: Arc<Scheduler>,
+        personas: Arc<PersonaService>,
+    ) -> Self {
+        Self {
+            db_pool,
+            guild_service,
+            scheduler,
+            personas,
+        }
+    }
+}
+
+#[async_trait]
+impl EventHandler for GuildHandler {
+    async fn guild_create(&self, ctx: Context, guild: Guild, is_new: Option<bool>) {
+        // guilds that were already joined also fire this on startup, which is when
+        // renames and ownership transfers made while chloe was offline get picked up
+        if is_new != Some(true) {
+            self.resync(&GuildSnapshot::from(&guild)).await;
+            return;
+        }
+
+        info!(
+            event = "guild_joined",
+            guild_id = %guild.id,
+            guild_name = %guild.name,
+            "Joined new guild"
+        );
+
+        if let Err(e) = schema::sync_guilds(&self.db_pool, &[guild.id], &ctx).await {
+            error!(
+                event = "guild_join_sync_failed",
+                guild_id = %guild.id,
+                error = ?e,
+                "Failed to sync newly joined guild"
+            );
+            return;
+        }
+        if let Err(e) = guild_retention::mark_rejoined(&self.db_pool, guild.id.get()).await {
+            error!(
+                event = "guild_rejoin_failed",
+                guild_id = %guild.id,
+                error = ?e,
+                "Failed to mark rejoined guild active"
+            );
+        }
+
+        // rejoining a guild that was already set up shouldn't ask again
+        let already_enabled = self
+            .guild_service
+            .get_guild_setting(guild.id.get() as i64, "llm")
+            .await
+            .and_then(|setting| setting.as_bool())
+            .unwrap_or(false);
+        if already_enabled {
+            return;
+        }
+
+        if let Err(e) = self.run_onboarding(&ctx, &guild).await {
+            warn!(
+                event = "guild_onboarding_failed",
+                guild_id = %guild.id,
+                error = %e,
+                "Failed to run guild onboarding"
+            );
+        }
+    }
+
+    async fn guild_update(&self, _ctx: Context, _old: Option<Guild>, new_data: PartialGuild) {
+        self.resync(&GuildSnapshot::from(&new_data)).await;
+    }
+
+    async fn guild_delete(
+        &self,
+        _ctx: Context,
+        incomplete: UnavailableGuild,
+        _full: Option<Guild>,
+    ) {
+        // an outage on discord's side, chloe is still in the guild
+        if incomplete.unavailable {
+            return;
+        }
+        let guild_id = incomplete.id;
+        info!(event = "guild_left", guild_id = %guild_id, "Removed from guild");
+        self.guild_service.forget_guild(guild_id.get() as i64).await;
+
+        let left_at = match guild_retention::mark_left(&self.db_pool, guild_id.get()).await {
+            Ok(Some(left_at)) => left_at,
+            Ok(None) => return,
+            Err(e) => {
+                error!(
+                    event = "guild_leave_failed",
+                    guild_id = %guild_id,
+                    error = ?e,
+                    "Failed to mark guild inactive"
+                );
+                return;
+            }
+        };
+        let purge_at = left_at + guild_retention::retention_period();
+        if let Err(e) = self
+            .scheduler
+            .schedule(
+                ScheduledJob::PurgeGuild {
+                    guild_id: guild_id.get(),
+                },
+                purge_at,
+                Some("guild_leave"),
+            )
+            .await
+        {
+            error!(
+                event = "guild_purge_schedule_failed",
+                guild_id = %guild_id,
+                error = ?e,
+                "Failed to schedule purge of guild data"
+            );
+        }
+    }
+}
+
+impl GuildHandler {
+    /// Store the guild's name and owner if either changed since the last sync
+    async fn resync(&self, guild: &GuildSnapshot) {
+        match schema::upsert_guilds(&self.db_pool, std::slice::from_ref(guild)).await {
+            Ok(0) => {}
+            Ok(_) => info!(
+                event = "guild_resynced",
+                guild_id = %guild.id,
+                guild_name = %guild.name,
+                "Synced changed guild"
+            ),
+            Err(e) => error!(
+                event = "guild_resync_failed",
+                guild_id = %guild.id,
+                error = ?e,
+                "Failed to sync changed guild"
+            ),
+        }
+    }
+
+    async fn run_onboarding(&self, ctx: &Context, guild: &Guild) -> Result<(), String> {
+        let mut text_channels: Vec<_> = guild
+            .channels
+            .values()
+            .filter(|channel| channel.kind == ChannelType::Text)
+            .collect();
+        text_channels.sort_by_key(|channel| channel.position);
+        text_channels.truncate(MAX_CHANNEL_OPTIONS);
+
+        let channel_options = text_channels
+            .iter()
+            .map(|channel| {
+                CreateSelectMenuOption::new(format!("#{}", channel.name), channel.id.to_string())
+            })
+            .collect::<Vec<_>>();
+        let channel_count = channel_options.len() as u8;
+
+        let mut components = Vec::new();
+        if !channel_options.is_empty() {
+            components.push(CreateActionRow::SelectMenu(
+                CreateSelectMenu::new(
+                    CHANNELS_ID,
+                    CreateSelectMenuKind::String {
+                        options: channel_options,
+                    },
+                )
+                .placeholder("channels I can chat in (none = everywhere)")
+                .min_values(0)
+                .max_values(channel_count),
+            ));
+        }
+        // a persona list that won't load just leaves the step out
+        let persona_options = match self.personas.list().await {
+            Ok(personas) => personas
+                .into_iter()
+                .take(MAX_CHANNEL_OPTIONS)
+                .map(|persona| {
+                    let option = CreateSelectMenuOption::new(persona.name, persona.key);
+                    if persona.description.is_empty() {
+                        return option;
+                    }
+                    let description: String = persona
+                        .description
+                        .chars()
+                        .take(MAX_OPTION_DESCRIPTION_CHARS)
+                        .collect();
+                    option.description(description)
+                })
+                .collect::<Vec<_>>(),
+            Err(e) => {
+                warn!(
+                    event = "guild_onboarding_personas_failed",
+                    guild_id = %guild.id,
+                    error = ?e,
+                    "Failed to list personas for onboarding"
+                );
+                Vec::new()
+            }
+        };
+        if !persona_options.is_empty() {
+            components.push(CreateActionRow::SelectMenu(
+                CreateSelectMenu::new(
+                    PERSONA_ID,
+                    CreateSelectMenuKind::String {
+                        options: persona_options,
+                    },
+                )
+                .placeholder("pick a persona (none = just me)")
+                .min_values(0)
+                .max_values(1),
+            ));
+        }
+        components.push(CreateActionRow::Buttons(vec![
+            CreateButton::new(ENABLE_ID)
+                .label("enable chloe")
+                .style(ButtonStyle::Success),
+            CreateButton::new(SKIP_ID)
+                .label("not now")
+                .style(ButtonStyle::Secondary),
+        ]));
+
+        let embed = CreateEmbed::new()
+            .title(format!("hiii {} 💅", guild.name))
+            .description(
+                "thanks for inviting me! everything starts switched off, so here's a quick setup:\n\
+                 1. pick the channels I'm allowed to chat in (or leave it empty for everywhere)\n\
+                 2. pick a persona if you want me to play one\n\
+                 3. hit **enable chloe**\n\n\
+                 server managers can change this later from the dashboard ✨",
+            )
+            .color(0xff69b4);
+        let setup_message = CreateMessage::new().embed(embed).components(components);
+
+        let mut message = match Self::setup_channel(ctx, guild) {
+            Some(channel_id) => channel_id.send_message(&ctx.http, setup_message).await,
+            None => guild.owner_id.dm(&ctx.http, setup_message).await,
+        }
+        .map_err(|e| format!("Failed to send onboarding message: {}", e))?;
+
+        info!(
+            event = "guild_onboarding_started",
+            guild_id = %guild.id,
+            channel_id = %message.channel_id,
+            "Sent onboarding message"
+        );
+
+        self.await_setup(ctx, guild, &mut message).await
+    }
+
+    /// The system channel when chloe can post there, otherwise None to fall back to a DM
+    fn setup_channel(ctx: &Context, guild: &Guild) -> Option<ChannelId> {
+        let channel = guild.channels.get(&guild.system_channel_id?)?;
+        let member = guild.members.get(&ctx.cache.current_user().id)?;
+        guild
+            .user_permissions_in(channel, member)
+            .contains(Permissions::SEND_MESSAGES | Permissions::VIEW_CHANNEL)
+            .then_some(channel.id)
+    }
+
+    async fn await_setup(
+        &self,
+        ctx: &Context,
+        guild: &Guild,
+        message: &mut Message,
+    ) -> Result<(), String> {
+        let deadline = Instant::now() + ONBOARDING_TIMEOUT;
+        let mut selected_channels: Vec<String> = Vec::new();
+        let mut selected_persona: Option<String> = None;
+
+        loop {
+            let remaining = deadline.saturating_duration_since(Instant::now());
+            let Some(interaction) = message
+                .await_component_interaction(&ctx.shard)
+                .timeout(remaining)
+                .await
+            else {
+                let _ = message
+                    .edit(&ctx.http, EditMessage::new().components(vec![]))
+                    .await;
+                info!(
+                    event = "guild_onboarding_expired",
+                    guild_id = %guild.id,
+                    "Onboarding message expired without a choice"
+                );
+                return Ok(());
+            };
+
+            if !Self::is_authorized(guild, &interaction) {
+                let _ = interaction
+                    .create_response(
+                        &ctx.http,
+                        CreateInteractionResponse::Message(
+                            CreateInteractionResponseMessage::new()
+                                .content("only server managers can set me up")
+                                .ephemeral(true),
+                        ),
+                    )
+                    .await;
+                continue;
+            }
+
+            let summary = match (interaction.data.custom_id.as_str(), &interaction.data.kind) {
+                (CHANNELS_ID, ComponentInteractionDataKind::StringSelect { values }) => {
+                    selected_channels = values.clone();
+                    let _ = interaction
+                        .create_response(&ctx.http, CreateInteractionResponse::Acknowledge)
+                        .await;
+                    continue;
+                }
+                (PERSONA_ID, ComponentInteractionDataKind::StringSelect { values }) => {
+                    selected_persona = values.first().cloned();
+                    let _ = interaction
+                        .create_response(&ctx.http, CreateInteractionResponse::Acknowledge)
+                        .await;
+                    continue;
+                }
+                (ENABLE_ID, _) => {
+                    let mut settings = json!({ "llm": true, "llmChannels": selected_channels });
+                    if let Some(persona) = &selected_persona {
+                        settings[PERSONA_SETTING] = json!(persona);
+                    }
+                    self.guild_service
+                        .update_guild_settings(guild.id.get() as i64, &settings)
+                        .await
+                        .map_err(|e| format!("Failed to save onboarding settings: {}", e))?;
+
+                    let mut summary = if selected_channels.is_empty() {
+                        "✅ all set! mention me or say my name anywhere 💬".to_string()
+                    } else {
+                        let channels = selected_channels
+                            .iter()
+                            .map(|id| format!("<#{}>", id))
+                            .collect::<Vec<_>>()
+                            .join(", ");
+                        format!("✅ all set! I'll chat in {}", channels)
+                    };
+                    if let Some(persona) = &selected_persona {
+                        summary.push_str(&format!("\nplaying the `{}` persona 🎭", persona));
+                    }
+                    summary
+                }
+                _ => "no worries, I'll stay quiet until someone enables me from the dashboard 🤐"
+                    .to_string(),
+            };
+
+            info!(
+                event = "guild_onboarding_completed",
+                guild_id = %guild.id,
+                enabled = interaction.data.custom_id == ENABLE_ID,
+                channel_count = selected_channels.len(),
+                persona = ?selected_persona,
+                completed_by = interaction.user.id.get(),
+                "Guild onboarding completed"
+            );
+
+            if let Err(e) = interaction
+                .create_response(
+                    &ctx.http,
+                    CreateInteractionResponse::UpdateMessage(
+                        CreateInteractionResponseMessage::new()
+                            .content(summary)
+                            .components(vec![]),
+                    ),
+                )
+                .await
+            {
+                warn!(
+                    event = "guild_onboarding_update_failed",
+                    error = ?e,
+                    "Failed to update onboarding message"
+                );
+            }
+
+            return Ok(());
+        }
+    }
+
+    fn is_authorized(guild: &Guild, interaction: &ComponentInteraction) -> bool {
+        interaction.user.id == guild.owner_id
+            || interaction
+                .member
+                .as_ref()
+                .and_then(|member| member.permissions)
+                .map(|permissions| permissions.contains(Permissions::MANAGE_GUILD))
+                .unwrap_or(false)
+    }
+}
@@ -0,0 +1,26 @@
+use crate::utils::Paginator;
+use serenity::{async_trait, model::application::Interaction, prelude::*};
+use std::sync::Arc;
+
+/// Handles component interactions that outlive a single request, like page buttons
+pub struct InteractionHandler {
+    pub paginator: Arc<Paginator>,
+}
+
+impl InteractionHandler {
+    pub fn new(paginator: Arc<Paginator>) -> Self {
+        Self { paginator }
+    }
+}
+
+#[async_trait]
+impl EventHandler for InteractionHandler {
+    async fn interaction_create(&self, ctx: Context, interaction: Interaction) {
+        match interaction {
+            Interaction::Component(component) if Paginator::owns_interaction(&component) => {
+                self.paginator.handle_interaction(&ctx.http, &component).await;
+            }
+            _ => {}
+        }
+    }
+}
@@ -0,0 +1,164 @@
+use super::message_router::{Handled, MessageSubscriber};
+use crate::services::guild_service::GuildService;
+use crate::services::leveling::{LEVELING_SETTING, LevelingService, LevelingSettings};
+use serenity::builder::{CreateAllowedMentions, CreateMessage};
+use serenity::model::channel::Message;
+use serenity::model::id::{ChannelId, GuildId, RoleId};
+use serenity::{async_trait, prelude::*};
+use std::sync::Arc;
+use tracing::{error, info, warn};
+
+/// Gives members xp for chatting in guilds with leveling on, announcing level ups and
+/// handing out the role rewards they reached
+#[derive(Clone)]
+pub struct LevelingHandler {
+    pub guild_service: Arc<GuildService>,
+    pub leveling: Arc<LevelingService>,
+}
+
+impl LevelingHandler {
+    pub fn new(guild_service: Arc<GuildService>, leveling: Arc<LevelingService>) -> Self {
+        Self {
+            guild_service,
+            leveling,
+        }
+    }
+
+    /// Award the message's xp, then hand out rewards and announce a level up
+    async fn award(
+        &self,
+        ctx: &Context,
+        guild_id: GuildId,
+        msg: &Message,
+        settings: LevelingSettings,
+    ) {
+        let level = match self
+            .leveling
+            .award(guild_id.get(), msg.author.id.get())
+            .await
+        {
+            Ok(Some(level)) => level,
+            Ok(None) => return,
+            Err(e) => {
+                error!(
+                    event = "xp_award_failed",
+                    guild_id = %guild_id,
+                    user_id = %msg.author.id,
+                    error = ?e,
+                    "Failed to award xp"
+                );
+                return;
+            }
+        };
+
+        self.grant_rewards(ctx, guild_id, msg, level).await;
+        if !settings.announce {
+            return;
+        }
+        let channel_id = settings
+            .announce_channel_id
+            .map(ChannelId::new)
+            .unwrap_or(msg.channel_id);
+        let announcement = CreateMessage::new()
+            .content(format!(
+                "<@{}> just reached level {} ✨",
+                msg.author.id, level
+            ))
+            .allowed_mentions(CreateAllowedMentions::new().users([msg.author.id]));
+        if let Err(e) = channel_id.send_message(&ctx.http, announcement).await {
+            warn!(
+                event = "level_up_announce_failed",
+                guild_id = %guild_id,
+                channel_id = %channel_id,
+                error = ?e,
+                "Failed to announce level up"
+            );
+        }
+    }
+
+    /// Every reward up to `level` the member doesn't have yet, so one missed while the
+    /// role was misconfigured is caught up on the next level
+    async fn grant_rewards(&self, ctx: &Context, guild_id: GuildId, msg: &Message, level: i32) {
+        let rewards = match self.leveling.rewards(guild_id.get()).await {
+            Ok(rewards) => rewards,
+            Err(e) => {
+                error!(
+                    event = "level_rewards_load_failed",
+                    guild_id = %guild_id,
+                    error = ?e,
+                    "Failed to load level rewards"
+                );
+                return;
+            }
+        };
+        let held = msg
+            .member
+            .as_ref()
+            .map(|member| member.roles.clone())
+            .unwrap_or_default();
+        for reward in rewards.iter().filter(|reward| reward.level <= level) {
+            let role_id = RoleId::new(reward.role_id);
+            if held.contains(&role_id) {
+                continue;
+            }
+            match ctx
+                .http
+                .add_member_role(guild_id, msg.author.id, role_id, Some("level reward"))
+                .await
+            {
+                Ok(()) => info!(
+                    event = "level_reward_granted",
+                    guild_id = %guild_id,
+                    user_id = %msg.author.id,
+                    role_id = %role_id,
+                    level = reward.level,
+                    "Granted level reward"
+                ),
+                // usually the role sits above chloe's or was deleted
+                Err(e) => warn!(
+                    event = "level_reward_failed",
+                    guild_id = %guild_id,
+                    role_id = %role_id,
+                    error = ?e,
+                    "Failed to grant level reward"
+                ),
+            }
+        }
+    }
+}
+
+#[async_trait]
+impl MessageSubscriber for LevelingHandler {
+    fn name(&self) -> &'static str {
+        "leveling"
+    }
+
+    /// Xp is counted for every message, whoever ends up answering it
+    async fn observe(&self, ctx: &Context, msg: &Message) {
+        let Some(guild_id) = msg.guild_id else {
+            return;
+        };
+        if msg.author.bot || msg.webhook_id.is_some() {
+            return;
+        }
+        let setting = self
+            .guild_service
+            .get_guild_setting(guild_id.get() as i64, LEVELING_SETTING)
+            .await;
+        let Some(settings) = LevelingSettings::from_setting(setting.as_ref()) else {
+            return;
+        };
+
+        // the cooldown, database and discord calls shouldn't hold up whoever answers
+        let handler = self.clone();
+        let ctx = ctx.clone();
+        let msg = msg.clone();
+        tokio::spawn(async move {
+            handler.award(&ctx, guild_id, &msg, settings).await;
+        });
+    }
+
+    async fn handle(&self, _ctx: &Context, _msg: &Message) -> Handled {
+        Handled::Passed
+    }
+}
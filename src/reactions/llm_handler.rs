@@ -1,22 +1,157 @@
+use crate::error::LlmError;
 use crate::services::{
+    context_builder::{ContextBuilder, ContextLimits, ContextOptions},
+    conversation_service::{ConversationService, TurnRole},
+    degraded_mode::{
+        DEGRADED_MODE_SETTING, DegradedMode, DegradedPlan, DegradedPolicy, DegradedReason,
+    },
+    guild_prompts::GuildPromptService,
     guild_service::GuildService,
-    llm_service::{ConversationContext, LlmService, MessageContext, UserInfo},
+    intent_router::IntentRouter,
+    llm_service::{ConversationContext, LlmService},
+    model_router::GuildModelConfig,
+    personas::{PERSONA_SETTING, PersonaService},
+    regenerations::{RegenerationRequest, RegenerationStore, is_regenerate_reaction},
+    safety::{self, SAFETY_SETTING, SafetySettings},
+    saved_conversations::{SavedConversation, SavedConversationService},
+    triggers::TriggerService,
+    usage_service::{BudgetScope, TokenBudget, UsageService},
+    user_service::UserService,
 };
-use crate::utils::{ImageProcessor, MessageSanitizer};
+use super::message_router::{Handled, MessageSubscriber};
+use crate::utils::in_flight::InFlight;
+use crate::utils::message_cache::{CachedMessage, MessageCache};
+use crate::utils::nickname_cache::NicknameCache;
+use crate::utils::presence::describe_activities;
+use crate::utils::RateLimiter;
+use crate::utils::rate_limiter::RateLimitRejection;
+use serenity::model::channel::Reaction;
+use serenity::model::event::{GuildMemberUpdateEvent, MessageUpdateEvent};
+use serenity::model::guild::Member;
+use serenity::model::user::User;
+use serenity::model::id::{ChannelId, GuildId, MessageId};
 use serenity::{async_trait, model::channel::Message, prelude::*};
-use std::{collections::HashSet, sync::Arc};
+use std::sync::Arc;
 use tracing::{error, info};
 
 pub struct LLMHandler {
     pub guild_service: Arc<GuildService>,
     pub llm_service: Arc<LlmService>,
+    pub message_cache: Arc<MessageCache>,
+    pub nicknames: Arc<NicknameCache>,
+    pub context_builder: Arc<ContextBuilder>,
+    pub triggers: Arc<TriggerService>,
+    pub usage: Arc<UsageService>,
+    pub conversations: Arc<ConversationService>,
+    pub personas: Arc<PersonaService>,
+    pub guild_prompts: Arc<GuildPromptService>,
+    pub saved_conversations: Arc<SavedConversationService>,
+    pub regenerations: Arc<RegenerationStore>,
+    pub user_service: Arc<UserService>,
+    /// Stands in for the guild token budget when users chat with chloe in DMs
+    pub dm_limiter: Arc<RateLimiter>,
+    pub in_flight: Arc<InFlight>,
 }
 
 #[async_trait]
-impl EventHandler for LLMHandler {
-    async fn message(&self, ctx: Context, msg: Message) {
+impl MessageSubscriber for LLMHandler {
+    fn name(&self) -> &'static str {
+        "llm"
+    }
+
+    async fn observe(&self, ctx: &Context, msg: &Message) {
+        let bot_id = ctx.cache.current_user().id;
+        // chloe's reply takes over the context it was answered from, for 🔁
+        if msg.author.id == bot_id
+            && let Some(asked) = msg.message_reference.as_ref().and_then(|r| r.message_id)
+        {
+            self.regenerations.claim(asked.get(), msg.id.get()).await;
+        }
+
+        // cache everything chloe may later need as context, including her own replies
+        if msg.guild_id.is_some()
+            && (!msg.author.bot || msg.author.id == bot_id)
+        {
+            let author_name = if msg.author.bot {
+                "Chloe".to_string()
+            } else {
+                self.nicknames.display_name(&ctx.http, None, msg).await
+            };
+            let cached = CachedMessage::from_message(msg, author_name);
+            self.message_cache.store(&cached).await;
+            if msg.author.bot {
+                self.conversations
+                    .record(&cached, msg.guild_id.map(|id| id.get()), TurnRole::Assistant);
+            }
+        }
+    }
+
+    async fn handle(&self, ctx: &Context, msg: &Message) -> Handled {
         if msg.author.bot {
-            return;
+            return Handled::Passed;
+        }
+
+        if msg.guild_id.is_none() {
+            let opted_in = match self
+                .user_service
+                .dm_chat_enabled(msg.author.id.get() as i64)
+                .await
+            {
+                Ok(opted_in) => opted_in,
+                Err(e) => {
+                    error!(
+                        event = "dm_chat_lookup_failed",
+                        user = %msg.author.name,
+                        error = ?e,
+                        "Couldn't check DM chat opt-in"
+                    );
+                    false
+                }
+            };
+            if !opted_in {
+                return Handled::Passed;
+            }
+            self.process_dm_message(ctx.clone(), msg.clone(), None).await;
+            return Handled::Claimed;
+        }
+
+        // admin defined auto-responses answer before anything reaches the model
+        let trigger = match msg.guild_id {
+            Some(guild_id) => {
+                self.triggers
+                    .find_match(guild_id.get() as i64, msg.channel_id.get(), &msg.content)
+                    .await
+            }
+            None => None,
+        };
+        if let Some(trigger) = trigger {
+            info!(
+                event = "trigger_matched",
+                user = %msg.author.name,
+                channel_id = %msg.channel_id,
+                pattern = %trigger.pattern,
+                use_llm = trigger.use_llm,
+                "Message matched a guild trigger"
+            );
+            if trigger.use_llm {
+                self.process_llm_message_with_error_handling(
+                    ctx.clone(),
+                    msg.clone(),
+                    true,
+                    false,
+                    Some(trigger.response),
+                    None,
+                )
+                .await;
+            } else if let Err(why) = msg.reply(&ctx.http, &trigger.response).await {
+                error!(
+                    event = "trigger_reply_send_failed",
+                    user = %msg.author.name,
+                    error = ?why,
+                    "Error sending trigger reply"
+                );
+            }
+            return Handled::Claimed;
         }
 
         // Check for random reply first
@@ -31,6 +166,15 @@ impl EventHandler for LLMHandler {
                         if let Some(channel_str) = channel_value.as_str() {
                             if let Ok(target_channel_id) = channel_str.parse::<u64>() {
                                 if msg.channel_id.get() == target_channel_id {
+                                    // no chiming in unprompted during the guild's quiet hours
+                                    if self
+                                        .guild_service
+                                        .is_quiet_hours(guild_id.get() as i64)
+                                        .await
+                                    {
+                                        break;
+                                    }
+
                                     // 1 in 100 chance to respond
                                     let random_number: u32 = rand::random::<u32>() % 100 + 1;
 
@@ -55,7 +199,7 @@ impl EventHandler for LLMHandler {
                                                     msg.clone(),
                                                 )
                                                 .await;
-                                                return;
+                                                return Handled::Claimed;
                                             }
                                         }
                                     }
@@ -76,43 +220,321 @@ impl EventHandler for LLMHandler {
                 .unwrap_or(false));
 
         if should_respond {
-            self.process_llm_message(ctx, msg).await;
+            self.process_llm_message(ctx.clone(), msg.clone()).await;
+            return Handled::Claimed;
+        }
+        Handled::Passed
+    }
+}
+
+#[async_trait]
+impl EventHandler for LLMHandler {
+
+    async fn message_update(
+        &self,
+        ctx: Context,
+        old_if_available: Option<Message>,
+        new: Option<Message>,
+        event: MessageUpdateEvent,
+    ) {
+        // embeds unfurling, pins and the like come through as updates too, only an edit
+        // stamps the message and changes its text
+        let Some(content) = &event.content else {
+            return;
+        };
+        if event.edited_timestamp.is_none() {
+            return;
         }
+        let previous = match old_if_available {
+            Some(old) => Some(old.content),
+            None => self
+                .message_cache
+                .get(event.id.get())
+                .await
+                .map(|cached| cached.content),
+        };
+        if previous.as_deref() == Some(content.as_str()) {
+            return;
+        }
+
+        self.message_cache
+            .update_content(event.id.get(), content)
+            .await;
+        self.conversations
+            .update_content(event.id.get(), content)
+            .await;
+        self.follow_edit(ctx, new, &event).await;
+    }
+
+    // member events only arrive with the GUILD_MEMBERS intent, until then entries expire
+    async fn guild_member_update(
+        &self,
+        _ctx: Context,
+        _old_if_available: Option<Member>,
+        _new: Option<Member>,
+        event: GuildMemberUpdateEvent,
+    ) {
+        let name = event
+            .nick
+            .unwrap_or_else(|| event.user.display_name().to_string());
+        self.nicknames
+            .update(event.guild_id.get(), event.user.id.get(), name)
+            .await;
+    }
+
+    async fn guild_member_removal(
+        &self,
+        _ctx: Context,
+        guild_id: GuildId,
+        user: User,
+        _member_data_if_available: Option<Member>,
+    ) {
+        self.nicknames
+            .invalidate(guild_id.get(), user.id.get())
+            .await;
+    }
+
+    async fn reaction_add(&self, ctx: Context, reaction: Reaction) {
+        if !is_regenerate_reaction(&reaction.emoji) {
+            return;
+        }
+        let Some(user_id) = reaction.user_id else {
+            return;
+        };
+        if user_id == ctx.cache.current_user().id {
+            return;
+        }
+        // only chloe's replies have a stored request
+        let Some(request) = self.regenerations.find(reaction.message_id.get()).await else {
+            return;
+        };
+        let is_admin = match reaction.guild_id {
+            Some(guild_id) => {
+                self.guild_service
+                    .is_user_admin(guild_id.get() as i64, user_id.get() as i64)
+                    .await
+            }
+            None => false,
+        };
+        if !request.allows(user_id.get(), is_admin) {
+            info!(
+                event = "regeneration_not_allowed",
+                user_id = %user_id,
+                message_id = %reaction.message_id,
+                "Only the asker or an admin can regenerate a reply"
+            );
+            return;
+        }
+        self.regenerate(ctx, reaction, request).await;
+    }
+
+    async fn message_delete(
+        &self,
+        _ctx: Context,
+        channel_id: ChannelId,
+        deleted_message_id: MessageId,
+        _guild_id: Option<GuildId>,
+    ) {
+        self.message_cache
+            .remove(channel_id.get(), deleted_message_id.get())
+            .await;
+        self.conversations.remove(deleted_message_id.get()).await;
     }
 }
 
 impl LLMHandler {
-    pub fn new(guild_service: Arc<GuildService>, llm_service: Arc<LlmService>) -> Self {
+    #[allow(clippy::too_many_arguments)]
+    pub fn new(
+        guild_service: Arc<GuildService>,
+        llm_service: Arc<LlmService>,
+        message_cache: Arc<MessageCache>,
+        triggers: Arc<TriggerService>,
+        usage: Arc<UsageService>,
+        conversations: Arc<ConversationService>,
+        personas: Arc<PersonaService>,
+        guild_prompts: Arc<GuildPromptService>,
+        saved_conversations: Arc<SavedConversationService>,
+        regenerations: Arc<RegenerationStore>,
+        user_service: Arc<UserService>,
+        dm_limiter: Arc<RateLimiter>,
+    ) -> Self {
+        let nicknames = Arc::new(NicknameCache::new());
         Self {
             guild_service,
             llm_service,
+            context_builder: Arc::new(ContextBuilder::new(
+                Arc::clone(&message_cache),
+                Arc::clone(&nicknames),
+                Arc::clone(&conversations),
+            )),
+            message_cache,
+            nicknames,
+            triggers,
+            usage,
+            conversations,
+            personas,
+            guild_prompts,
+            saved_conversations,
+            regenerations,
+            user_service,
+            dm_limiter,
+            in_flight: Arc::new(InFlight::new()),
         }
     }
 
     async fn process_llm_message(&self, ctx: Context, msg: Message) {
-        self.process_llm_message_with_error_handling(ctx, msg, true, false)
+        self.process_llm_message_with_error_handling(ctx, msg, true, false, None, None)
             .await;
     }
 
     async fn process_llm_message_silent(&self, ctx: Context, msg: Message) {
-        self.process_llm_message_with_error_handling(ctx, msg, false, true)
+        self.process_llm_message_with_error_handling(ctx, msg, false, true, None, None)
             .await;
     }
 
+    /// Answer a DM from a user who opted in with `/dmchat`. There are no guild settings in
+    /// DMs, so chloe's defaults apply and the DM limiter turns away anyone going too fast.
+    async fn process_dm_message(
+        &self,
+        ctx: Context,
+        msg: Message,
+        replacing: Option<MessageId>,
+    ) {
+        let permit = match self
+            .dm_limiter
+            .try_acquire(format!("dm_{}", msg.author.id))
+            .await
+        {
+            Ok(permit) => permit,
+            Err(rejection) => {
+                info!(
+                    event = "dm_chat_rate_limited",
+                    user = %msg.author.name,
+                    rejection = ?rejection,
+                    "DM chat rate limited, not calling the model"
+                );
+                if let Err(why) = msg.reply(&ctx.http, dm_refusal(rejection)).await {
+                    error!(
+                        event = "dm_rate_limit_reply_send_failed",
+                        user = %msg.author.name,
+                        error = ?why,
+                        "Error sending DM rate limit reply"
+                    );
+                }
+                return;
+            }
+        };
+
+        let guild_service = Arc::clone(&self.guild_service);
+        let llm_service = Arc::clone(&self.llm_service);
+        let context_builder = Arc::clone(&self.context_builder);
+        let conversations = Arc::clone(&self.conversations);
+        let saved_conversations = Arc::clone(&self.saved_conversations);
+        let regenerations = Arc::clone(&self.regenerations);
+        let message_id = msg.id.get();
+
+        let task = tokio::spawn(async move {
+            let _permit = permit;
+            info!(
+                event = "llm_dm_response_triggered",
+                user = %msg.author.name,
+                "Responding to DM from opted-in user"
+            );
+
+            let safety_settings = SafetySettings::from_setting(None);
+            if safety_settings.enabled && safety::is_crisis_message(&msg.content) {
+                safety::respond_to_crisis(&ctx.http, &msg, &safety_settings).await;
+                return;
+            }
+
+            // DMs have no budget to spend, only a failing provider degrades them
+            let degraded_policy = DegradedPolicy::default();
+            let mut degraded = None;
+            if llm_service.provider_failing(&degraded_policy, None) {
+                match degraded_policy.plan(
+                    DegradedReason::ProviderFailing,
+                    &msg.content,
+                    !msg.attachments.is_empty(),
+                    &llm_service.cheapest_model(),
+                ) {
+                    DegradedPlan::Canned(reply) => {
+                        reply_canned(&ctx.http, &msg, &reply).await;
+                        return;
+                    }
+                    DegradedPlan::Limited(mode) => degraded = Some(mode),
+                    DegradedPlan::Unchanged => {}
+                }
+            }
+
+            let _typing = msg.channel_id.start_typing(&ctx.http);
+            let resumed = resumed_conversation(&saved_conversations, msg.channel_id.get()).await;
+            let options = ContextOptions {
+                bot_user_id: ctx.cache.current_user().id.get(),
+                is_random_reply: false,
+                limits: ContextLimits::default(),
+                resumed,
+            };
+            let context = context_builder.build_context(&ctx.http, &msg, options).await;
+            conversations.record(
+                &CachedMessage::from_message(&msg, context.current_user.clone()),
+                None,
+                TurnRole::User,
+            );
+            respond(
+                &ctx,
+                &msg,
+                &guild_service,
+                &llm_service,
+                &regenerations,
+                context,
+                ReplyOptions {
+                    send_error_response: true,
+                    replacing,
+                    degraded,
+                },
+            )
+            .await;
+        });
+        self.in_flight.track(message_id, task.abort_handle());
+    }
+
     async fn process_llm_message_with_error_handling(
         &self,
         ctx: Context,
         msg: Message,
         send_error_response: bool,
         is_random_reply: bool,
+        instructions: Option<String>,
+        replacing: Option<MessageId>,
     ) {
         if let Some(guild_id) = msg.guild_id {
             let guild_service = Arc::clone(&self.guild_service);
             let llm_service = Arc::clone(&self.llm_service);
+            let context_builder = Arc::clone(&self.context_builder);
+            let usage = Arc::clone(&self.usage);
+            let conversations = Arc::clone(&self.conversations);
+            let personas = Arc::clone(&self.personas);
+            let guild_prompts = Arc::clone(&self.guild_prompts);
+            let saved_conversations = Arc::clone(&self.saved_conversations);
+            let regenerations = Arc::clone(&self.regenerations);
             let http = Arc::clone(&ctx.http);
+            let message_id = msg.id.get();
             let msg_clone = msg;
 
-            tokio::spawn(async move {
+            let task = tokio::spawn(async move {
+                if !guild_service
+                    .is_llm_channel_allowed(guild_id.get() as i64, msg_clone.channel_id.get())
+                    .await
+                {
+                    info!(
+                        event = "llm_channel_not_allowed",
+                        guild_id = %guild_id,
+                        channel_id = %msg_clone.channel_id,
+                        "LLM not enabled in this channel - skipping"
+                    );
+                    return;
+                }
+
                 if let Some(llm_setting) = guild_service
                     .get_guild_setting(guild_id.get() as i64, "llm")
                     .await
@@ -126,183 +548,228 @@ impl LLMHandler {
                             "LLM enabled - responding to message from user"
                         );
 
-                        let _typing = msg_clone.channel_id.start_typing(&http);
-                        info!(
-                            event = "typing_indicator_started",
-                            user = %msg_clone.author.name,
-                            channel_id = %msg_clone.channel_id,
-                            "Started typing indicator"
+                        // crisis messages get vetted resources, never the persona or the model
+                        let safety_settings = SafetySettings::from_setting(
+                            guild_service
+                                .get_guild_setting(guild_id.get() as i64, SAFETY_SETTING)
+                                .await
+                                .as_ref(),
                         );
+                        if safety_settings.enabled && safety::is_crisis_message(&msg_clone.content)
+                        {
+                            safety::respond_to_crisis(&http, &msg_clone, &safety_settings).await;
+                            return;
+                        }
 
-                        // create a helper to handle image processing in the async closure
-                        let image_processor = ImageProcessor::new();
-
-                        let reply_chain_messages = image_processor
-                            .get_reply_chain_context(&http, &msg_clone)
-                            .await;
-
-                        info!(
-                            event = "reply_chain_context_gathered",
-                            user = %msg_clone.author.name,
-                            channel_id = %msg_clone.channel_id,
-                            message_count = reply_chain_messages.len(),
-                            messages = ?reply_chain_messages.iter().map(|m| format!("{}: {}", m.user_display_name, m.content)).collect::<Vec<_>>(),
-                            "Gathered reply chain context"
-                        );
-                        let user_display_name = msg_clone
-                            .author_nick(&http)
+                        // answer trivial messages without a model call unless the guild opted out
+                        let router_enabled = guild_service
+                            .get_guild_setting(guild_id.get() as i64, "intentRouter")
                             .await
-                            .unwrap_or_else(|| msg_clone.author.display_name().to_string());
-
-                        // process images from the current message
-                        let current_images =
-                            image_processor.process_message_images(&msg_clone).await;
-
-                        let bot_user_id = ctx.cache.current_user().id.get();
-                        let user_info = LLMHandler::gather_user_info(
-                            &reply_chain_messages,
-                            &msg_clone,
-                            &user_display_name,
-                            bot_user_id,
-                        )
-                        .await;
-
-                        let referenced_message =
-                            if let Some(ref ref_msg) = msg_clone.referenced_message {
-                                let ref_user_display_name = if ref_msg.author.bot {
-                                    "Chloe".to_string()
-                                } else {
-                                    ref_msg.author_nick(&http).await.unwrap_or_else(|| {
-                                        ref_msg.author.display_name().to_string()
-                                    })
-                                };
-
-                                let ref_images =
-                                    image_processor.process_message_images(ref_msg).await;
-
-                                // Sanitize referenced message content
-                                let ref_sanitized_content = MessageSanitizer::sanitize_message(
-                                    &ref_msg.content,
-                                    &ref_user_display_name
+                            .and_then(|setting| setting.as_bool())
+                            .unwrap_or(true);
+                        if router_enabled
+                            && !is_random_reply
+                            && instructions.is_none()
+                            && replacing.is_none()
+                            && msg_clone.attachments.is_empty()
+                        {
+                            let intent = IntentRouter::classify(&msg_clone.content);
+                            if let Some(reply) = IntentRouter::canned_response(&intent) {
+                                info!(
+                                    event = "intent_routed",
+                                    user = %msg_clone.author.name,
+                                    intent = ?intent,
+                                    "Answered trivial intent without the model"
                                 );
-
-                                Some(MessageContext {
-                                    user_display_name: ref_user_display_name,
-                                    user_id: ref_msg.author.id.get(),
-                                    content: ref_sanitized_content,
-                                    is_bot: ref_msg.author.bot,
-                                    channel_id: ref_msg.channel_id.get(),
-                                    images: ref_images,
-                                })
-                            } else {
-                                None
-                            };
-
-                        // Sanitize the current message to prevent impersonation
-                        let sanitized_message = MessageSanitizer::sanitize_message(
-                            &msg_clone.content,
-                            &user_display_name
-                        );
-
-                        let context = ConversationContext {
-                            current_user: user_display_name,
-                            current_message: sanitized_message,
-                            current_images,
-                            recent_messages: reply_chain_messages,
-                            user_info,
-                            referenced_message,
-                            is_random_reply,
-                        };
-
-                        // create a sender for immediate responses (two-part tool calls)
-                        let http_clone = Arc::clone(&http);
-                        let msg_clone_for_sender = msg_clone.clone();
-                        let _sender = move |initial_text: String| {
-                            let http = Arc::clone(&http_clone);
-                            let msg = msg_clone_for_sender.clone();
-                            async move {
-                                if let Err(why) = msg.reply(&http, initial_text).await {
+                                if let Err(why) = msg_clone.reply(&http, reply).await {
                                     error!(
-                                        event = "initial_response_send_failed",
-                                        user = %msg.author.name,
+                                        event = "intent_reply_send_failed",
+                                        user = %msg_clone.author.name,
                                         error = ?why,
-                                        "Error sending initial LLM response"
+                                        "Error sending routed reply"
                                     );
                                 }
+                                return;
                             }
-                        };
+                        }
 
-                        // create a typing starter for tool execution
-                        let msg_clone_for_typing = msg_clone.clone();
-                        let http_clone_for_typing = Arc::clone(&http);
-                        let typing_starter = move || {
-                            let msg = msg_clone_for_typing.clone();
-                            let http = http_clone_for_typing.clone();
-                            async move {
-                                let _typing = msg.channel_id.start_typing(&http);
-                                info!(
-                                    event = "tool_execution_typing_started",
-                                    user = %msg.author.name,
-                                    channel_id = %msg.channel_id,
-                                    "Started typing indicator for tool execution"
-                                );
+                        // a spent daily budget refuses before the provider is called, unless
+                        // the guild opted into answering degraded past it
+                        let mut over_budget = None;
+                        let budget = TokenBudget::from_setting(
+                            guild_service
+                                .get_guild_setting(guild_id.get() as i64, "tokenBudget")
+                                .await
+                                .as_ref(),
+                        );
+                        if !budget.is_unlimited() {
+                            match usage
+                                .tokens_today(guild_id.get(), msg_clone.author.id.get())
+                                .await
+                            {
+                                Ok((guild_tokens, user_tokens)) => {
+                                    if let Some(scope) = budget.exceeded(guild_tokens, user_tokens) {
+                                        info!(
+                                            event = "token_budget_exhausted",
+                                            user = %msg_clone.author.name,
+                                            guild_id = %guild_id,
+                                            scope = ?scope,
+                                            guild_tokens = guild_tokens,
+                                            user_tokens = user_tokens,
+                                            "Daily token budget used up"
+                                        );
+                                        over_budget = Some(match scope {
+                                            BudgetScope::Guild => "I've talked way too much in this server today 😮‍💨 I'll be back after midnight UTC",
+                                            BudgetScope::User => "you've used up all your chloe time for today 💅 try again after midnight UTC",
+                                        });
+                                    }
+                                }
+                                // a usage lookup failing shouldn't take chloe down with it
+                                Err(e) => error!(
+                                    event = "token_budget_check_failed",
+                                    guild_id = %guild_id,
+                                    error = ?e,
+                                    "Couldn't check token budget, answering anyway"
+                                ),
                             }
-                        };
-
-                        // Create Discord context for tool execution
-                        let discord_context = crate::tools::DiscordContext {
-                            http: Arc::clone(&http),
-                            channel_id: msg_clone.channel_id,
-                            message_id: msg_clone.id,
-                            guild_id: msg_clone.guild_id,
-                        };
+                        }
 
-                        match llm_service
-                            .prompt_with_context_and_sender_with_discord(
-                                context,
-                                None::<fn(String) -> std::future::Ready<()>>,
-                                Some(typing_starter),
-                                Some(&discord_context),
-                            )
-                            .await
+                        // a failing provider gets a cheaper answer, not silence
+                        let degraded_policy = DegradedPolicy::from_setting(
+                            guild_service
+                                .get_guild_setting(guild_id.get() as i64, DEGRADED_MODE_SETTING)
+                                .await
+                                .as_ref(),
+                        );
+                        let llm_config = GuildModelConfig::from_setting(
+                            guild_service
+                                .get_guild_setting(guild_id.get() as i64, "llmConfig")
+                                .await
+                                .as_ref(),
+                        );
+                        let reason = if over_budget.is_some() {
+                            Some(DegradedReason::BudgetExhausted)
+                        } else if llm_service
+                            .provider_failing(&degraded_policy, llm_config.model.as_deref())
                         {
-                            Ok(llm_response) => {
-                                info!(
-                                    event = "llm_response_received",
-                                    user = %msg_clone.author.name,
-                                    response_length = llm_response.raw_text.len(),
-                                    "Received LLM response, Discord tools should have been executed automatically"
-                                );
-
-                                // With direct tool execution, all Discord actions should already be complete
-                                // No additional processing needed - tools handled everything directly
+                            Some(DegradedReason::ProviderFailing)
+                        } else {
+                            None
+                        };
+                        let plan = match reason {
+                            // nobody asked for a random reply, it can wait until chloe is back
+                            Some(_) if is_random_reply => return,
+                            Some(reason) => degraded_policy.plan(
+                                reason,
+                                &msg_clone.content,
+                                !msg_clone.attachments.is_empty(),
+                                &llm_service.cheapest_model(),
+                            ),
+                            None => DegradedPlan::Unchanged,
+                        };
+                        let degraded = match plan {
+                            DegradedPlan::Canned(reply) => {
+                                reply_canned(&http, &msg_clone, &reply).await;
+                                return;
                             }
-                            Err(err) => {
-                                error!(
-                                    event = "llm_processing_failed",
-                                    user = %msg_clone.author.name,
-                                    error = ?err,
-                                    send_error_response = send_error_response,
-                                    "Error getting LLM response"
-                                );
-                                if send_error_response {
-                                    if let Err(why) = msg_clone.reply(&http, "Sorry, I'm having trouble processing your message right now.").await {
-                                        error!(
-                                            event = "fallback_response_send_failed",
-                                            user = %msg_clone.author.name,
-                                            error = ?why,
-                                            "Error sending fallback response"
-                                        );
+                            DegradedPlan::Limited(mode) => Some(mode),
+                            DegradedPlan::Unchanged => {
+                                if let Some(refusal) = over_budget {
+                                    if send_error_response {
+                                        let sent = msg_clone.reply(&http, refusal).await;
+                                        if let Err(why) = sent {
+                                            error!(
+                                                event = "budget_reply_send_failed",
+                                                user = %msg_clone.author.name,
+                                                error = ?why,
+                                                "Error sending budget refusal"
+                                            );
+                                        }
                                     }
-                                } else {
-                                    info!(
-                                        event = "llm_processing_failed_silent",
-                                        user = %msg_clone.author.name,
-                                        "LLM processing failed for random reply, staying silent"
-                                    );
+                                    return;
+                                }
+                                None
+                            }
+                        };
+
+                        let _typing = msg_clone.channel_id.start_typing(&http);
+                        info!(
+                            event = "typing_indicator_started",
+                            user = %msg_clone.author.name,
+                            channel_id = %msg_clone.channel_id,
+                            "Started typing indicator"
+                        );
+
+                        let context_window = guild_service
+                            .get_guild_setting(guild_id.get() as i64, "contextWindow")
+                            .await;
+                        let resumed = resumed_conversation(
+                            &saved_conversations,
+                            msg_clone.channel_id.get(),
+                        )
+                        .await;
+                        let options = ContextOptions {
+                            bot_user_id: ctx.cache.current_user().id.get(),
+                            is_random_reply,
+                            limits: ContextLimits::from_setting(context_window.as_ref()),
+                            resumed,
+                        };
+                        let mut context = context_builder
+                            .build_context(&http, &msg_clone, options)
+                            .await;
+                        context.instructions = instructions;
+                        context.author_activity = ctx.cache.guild(guild_id).and_then(|guild| {
+                            guild
+                                .presences
+                                .get(&msg_clone.author.id)
+                                .and_then(|presence| describe_activities(&presence.activities))
+                        });
+                        let persona_key = guild_service
+                            .get_guild_setting(guild_id.get() as i64, PERSONA_SETTING)
+                            .await;
+                        if let Some(key) = persona_key.as_ref().and_then(|key| key.as_str()) {
+                            match personas.find(key).await {
+                                Ok(persona) => {
+                                    context.persona = persona.map(|persona| persona.overlay)
                                 }
+                                Err(e) => error!(
+                                    event = "persona_lookup_failed",
+                                    guild_id = %guild_id,
+                                    persona = key,
+                                    error = ?e,
+                                    "Couldn't load the server's persona, answering without it"
+                                ),
                             }
                         }
+                        match guild_prompts.active(guild_id.get()).await {
+                            Ok(guild_prompt) => context.guild_prompt = guild_prompt,
+                            Err(e) => error!(
+                                event = "guild_prompt_lookup_failed",
+                                guild_id = %guild_id,
+                                error = ?e,
+                                "Couldn't load the server's prompt, answering with the global one"
+                            ),
+                        }
+                        conversations.record(
+                            &CachedMessage::from_message(&msg_clone, context.current_user.clone()),
+                            Some(guild_id.get()),
+                            TurnRole::User,
+                        );
+
+                        respond(
+                            &ctx,
+                            &msg_clone,
+                            &guild_service,
+                            &llm_service,
+                            &regenerations,
+                            context,
+                            ReplyOptions {
+                                send_error_response,
+                                replacing,
+                                degraded,
+                            },
+                        )
+                        .await;
                     } else {
                         info!(
                             event = "llm_disabled_for_guild",
@@ -320,44 +787,423 @@ impl LLMHandler {
                     );
                 }
             });
+            self.in_flight.track(message_id, task.abort_handle());
         }
     }
 
-    async fn gather_user_info(
-        recent_messages: &[MessageContext],
-        current_msg: &Message,
-        current_user_display: &str,
-        bot_user_id: u64,
-    ) -> Vec<UserInfo> {
-        let mut user_info = Vec::new();
-        let mut seen_users = HashSet::new();
-
-        if seen_users.insert(current_msg.author.id.get()) {
-            user_info.push(UserInfo {
-                display_name: current_user_display.to_string(),
-                user_id: current_msg.author.id.get(),
-                is_bot: current_msg.author.bot,
-            });
+    /// An edit to a message chloe is still answering restarts the answer from the new
+    /// text. When she answered it in the last few minutes her reply is updated instead,
+    /// unless the guild set `followEdits` to false.
+    async fn follow_edit(
+        &self,
+        ctx: Context,
+        edited: Option<Message>,
+        event: &MessageUpdateEvent,
+    ) {
+        if event.author.as_ref().is_some_and(|author| author.bot) {
+            return;
         }
+        let restarted = self.in_flight.cancel(event.id.get());
+        let reply = self
+            .regenerations
+            .recent_reply(event.id.get())
+            .await
+            .map(MessageId::new);
+        if !restarted && (reply.is_none() || !self.follows_edits(event.guild_id).await) {
+            return;
+        }
+
+        let mut msg = match edited {
+            Some(msg) => msg,
+            None => match event.channel_id.message(&ctx.http, event.id).await {
+                Ok(msg) => msg,
+                Err(e) => {
+                    error!(
+                        event = "edited_message_fetch_failed",
+                        message_id = %event.id,
+                        error = %e,
+                        "Couldn't fetch the edited message, not answering it again"
+                    );
+                    return;
+                }
+            },
+        };
+        // messages fetched over http don't say which guild they're in
+        msg.guild_id = msg.guild_id.or(event.guild_id);
+        info!(
+            event = "llm_edit_followed",
+            user = %msg.author.name,
+            channel_id = %msg.channel_id,
+            restarted = restarted,
+            replacing = ?reply,
+            "Answering an edited message again"
+        );
 
-        for msg in recent_messages {
-            if seen_users.insert(msg.user_id) {
-                user_info.push(UserInfo {
-                    display_name: msg.user_display_name.clone(),
-                    user_id: msg.user_id,
-                    is_bot: msg.is_bot,
-                });
+        match reply {
+            // nothing was posted yet, the edit is looked at like a new message
+            None => {
+                self.handle(&ctx, &msg).await;
+            }
+            Some(reply) if msg.guild_id.is_some() => {
+                self.process_llm_message_with_error_handling(
+                    ctx,
+                    msg,
+                    true,
+                    false,
+                    None,
+                    Some(reply),
+                )
+                .await
             }
+            Some(reply) => self.process_dm_message(ctx, msg, Some(reply)).await,
         }
+    }
 
-        if seen_users.insert(bot_user_id) {
-            user_info.push(UserInfo {
-                display_name: "Chloe".to_string(),
-                user_id: bot_user_id,
-                is_bot: true,
-            });
+    async fn follows_edits(&self, guild_id: Option<GuildId>) -> bool {
+        let Some(guild_id) = guild_id else {
+            return true;
+        };
+        self.guild_service
+            .get_guild_setting(guild_id.get() as i64, "followEdits")
+            .await
+            .and_then(|setting| setting.as_bool())
+            .unwrap_or(true)
+    }
+
+    /// Answer the message behind a reply again from the context it had, the new answer
+    /// replaces the reply. Turned away where chloe no longer answers, once the guild's
+    /// budget is spent or by the DM limiter, like any answer.
+    async fn regenerate(
+        &self,
+        ctx: Context,
+        reaction: Reaction,
+        mut request: RegenerationRequest,
+    ) {
+        let channel_id = ChannelId::new(request.channel_id);
+        // chloe can't take reactions off in DMs, so un-reacting and reacting again is free
+        let permit = match request.guild_id {
+            Some(_) => None,
+            None => match self
+                .dm_limiter
+                .try_acquire(format!("dm_{}", request.author_id))
+                .await
+            {
+                Ok(permit) => Some(permit),
+                Err(rejection) => {
+                    info!(
+                        event = "dm_regeneration_rate_limited",
+                        user_id = request.author_id,
+                        rejection = ?rejection,
+                        "DM regeneration rate limited, not calling the model"
+                    );
+                    if let Err(why) = channel_id.say(&ctx.http, dm_refusal(rejection)).await {
+                        error!(
+                            event = "dm_rate_limit_reply_send_failed",
+                            user_id = request.author_id,
+                            error = ?why,
+                            "Error sending DM rate limit reply"
+                        );
+                    }
+                    return;
+                }
+            },
+        };
+
+        let guild_service = Arc::clone(&self.guild_service);
+        let llm_service = Arc::clone(&self.llm_service);
+        let context_builder = Arc::clone(&self.context_builder);
+        let usage = Arc::clone(&self.usage);
+        let regenerations = Arc::clone(&self.regenerations);
+
+        tokio::spawn(async move {
+            let _permit = permit;
+            if let Some(guild_id) = request.guild_id {
+                // the channel or the whole guild may have been turned off since the answer
+                let llm_enabled = guild_service
+                    .get_guild_setting(guild_id as i64, "llm")
+                    .await
+                    .and_then(|setting| setting.as_bool())
+                    .unwrap_or(false);
+                if !llm_enabled
+                    || !guild_service
+                        .is_llm_channel_allowed(guild_id as i64, request.channel_id)
+                        .await
+                {
+                    info!(
+                        event = "regeneration_llm_disabled",
+                        guild_id = guild_id,
+                        channel_id = %channel_id,
+                        "LLM not enabled here anymore, not regenerating"
+                    );
+                    return;
+                }
+
+                let budget = TokenBudget::from_setting(
+                    guild_service
+                        .get_guild_setting(guild_id as i64, "tokenBudget")
+                        .await
+                        .as_ref(),
+                );
+                if !budget.is_unlimited()
+                    && let Ok((guild_tokens, user_tokens)) =
+                        usage.tokens_today(guild_id, request.author_id).await
+                    && budget.exceeded(guild_tokens, user_tokens).is_some()
+                {
+                    info!(
+                        event = "regeneration_budget_exhausted",
+                        guild_id = guild_id,
+                        message_id = %reaction.message_id,
+                        "Daily token budget used up, not regenerating"
+                    );
+                    return;
+                }
+            }
+
+            let mut msg = match channel_id
+                .message(&ctx.http, MessageId::new(request.message_id))
+                .await
+            {
+                Ok(msg) => msg,
+                Err(e) => {
+                    info!(
+                        event = "regeneration_source_missing",
+                        message_id = request.message_id,
+                        error = %e,
+                        "The message chloe answered is gone, not regenerating"
+                    );
+                    return;
+                }
+            };
+            // messages fetched over http don't say which guild they're in
+            msg.guild_id = request.guild_id.map(GuildId::new);
+            // images weren't stored with the context, the asked message still has them
+            request.context.current_images = context_builder.message_images(&msg).await;
+            if let (Some(referenced), Some(source)) = (
+                request.context.referenced_message.as_mut(),
+                msg.referenced_message.as_deref(),
+            ) {
+                referenced.images = context_builder.message_images(source).await;
+            }
+
+            // take the 🔁 off again so it can be used another time, needs manage messages
+            let _ = reaction.delete(&ctx.http).await;
+            info!(
+                event = "llm_regeneration_triggered",
+                user_id = ?reaction.user_id,
+                channel_id = %channel_id,
+                reply_id = %reaction.message_id,
+                "Regenerating reply"
+            );
+            let _typing = channel_id.start_typing(&ctx.http);
+            respond(
+                &ctx,
+                &msg,
+                &guild_service,
+                &llm_service,
+                &regenerations,
+                request.context,
+                ReplyOptions {
+                    send_error_response: true,
+                    replacing: Some(reaction.message_id),
+                    degraded: None,
+                },
+            )
+            .await;
+        });
+    }
+}
+
+/// What a DM user turned away by the DM limiter is told
+fn dm_refusal(rejection: RateLimitRejection) -> String {
+    match rejection {
+        RateLimitRejection::Concurrency => {
+            "I'm talking to way too many people right now 😵‍💫 try me again in a moment"
+                .to_string()
+        }
+        RateLimitRejection::Interval { retry_after } => format!(
+            "slow down a little 😮‍💨 try again in {}s",
+            retry_after.as_secs_f64().ceil().max(1.0)
+        ),
+    }
+}
+
+/// Answer without the model while degraded
+async fn reply_canned(http: &serenity::http::Http, msg: &Message, reply: &str) {
+    info!(
+        event = "degraded_canned_reply",
+        user = %msg.author.name,
+        channel_id = %msg.channel_id,
+        "Answered with a canned reply in degraded mode"
+    );
+    if let Err(why) = msg.reply(http, reply).await {
+        error!(
+            event = "degraded_reply_send_failed",
+            user = %msg.author.name,
+            error = ?why,
+            "Error sending canned degraded reply"
+        );
+    }
+}
+
+/// The saved conversation the channel follows on from, None when it can't be loaded
+async fn resumed_conversation(
+    saved_conversations: &SavedConversationService,
+    channel_id: u64,
+) -> Option<SavedConversation> {
+    match saved_conversations.resumed_in(channel_id).await {
+        Ok(resumed) => resumed,
+        Err(e) => {
+            error!(
+                event = "resumed_conversation_lookup_failed",
+                channel_id = channel_id,
+                error = ?e,
+                "Couldn't load the resumed conversation, answering without it"
+            );
+            None
+        }
+    }
+}
+
+/// How `respond` answers, besides what it answers from
+struct ReplyOptions {
+    /// Tell the author when answering failed
+    send_error_response: bool,
+    /// chloe's earlier reply the answer is edited into
+    replacing: Option<MessageId>,
+    /// Limits while the provider is failing or the budget is spent
+    degraded: Option<DegradedMode>,
+}
+
+/// Run the model over the built context, its tools send the reply
+async fn respond(
+    ctx: &Context,
+    msg: &Message,
+    guild_service: &GuildService,
+    llm_service: &LlmService,
+    regenerations: &RegenerationStore,
+    context: ConversationContext,
+    options: ReplyOptions,
+) {
+    let ReplyOptions {
+        send_error_response,
+        replacing,
+        degraded,
+    } = options;
+    let http = Arc::clone(&ctx.http);
+    regenerations
+        .remember(RegenerationRequest {
+            guild_id: msg.guild_id.map(|id| id.get()),
+            channel_id: msg.channel_id.get(),
+            message_id: msg.id.get(),
+            author_id: msg.author.id.get(),
+            context: context.clone(),
+        })
+        .await;
+
+    // create a sender for immediate responses (two-part tool calls)
+    let http_clone = Arc::clone(&http);
+    let msg_for_sender = msg.clone();
+    let _sender = move |initial_text: String| {
+        let http = Arc::clone(&http_clone);
+        let msg = msg_for_sender.clone();
+        async move {
+            if let Err(why) = msg.reply(&http, initial_text).await {
+                error!(
+                    event = "initial_response_send_failed",
+                    user = %msg.author.name,
+                    error = ?why,
+                    "Error sending initial LLM response"
+                );
+            }
         }
+    };
+
+    // create a typing starter for tool execution
+    let msg_for_typing = msg.clone();
+    let http_clone_for_typing = Arc::clone(&http);
+    let typing_starter = move || {
+        let msg = msg_for_typing.clone();
+        let http = http_clone_for_typing.clone();
+        async move {
+            let _typing = msg.channel_id.start_typing(&http);
+            info!(
+                event = "tool_execution_typing_started",
+                user = %msg.author.name,
+                channel_id = %msg.channel_id,
+                "Started typing indicator for tool execution"
+            );
+        }
+    };
+
+    // Create Discord context for tool execution
+    let mut discord_context =
+        crate::tools::DiscordContext::from_message(ctx, msg, guild_service).await;
+    if replacing.is_some() {
+        // streaming would post the new answer as a new message
+        discord_context.edit_message = replacing;
+        discord_context.stream_replies = false;
+    }
+    if let Some(degraded) = &degraded {
+        info!(
+            event = "llm_answering_degraded",
+            user = %msg.author.name,
+            reason = ?degraded.reason,
+            model = ?degraded.model,
+            tools = degraded.tools,
+            "Answering in degraded mode"
+        );
+        degraded.apply(&mut discord_context);
+    }
+
+    match llm_service
+        .prompt_with_context_and_sender_with_discord(
+            context,
+            None::<fn(String) -> std::future::Ready<()>>,
+            Some(typing_starter),
+            Some(&discord_context),
+        )
+        .await
+    {
+        Ok(llm_response) => {
+            info!(
+                event = "llm_response_received",
+                user = %msg.author.name,
+                response_length = llm_response.raw_text.len(),
+                "Received LLM response, Discord tools should have been executed automatically"
+            );
 
-        user_info
+            // With direct tool execution, all Discord actions should already be complete
+            // No additional processing needed - tools handled everything directly
+        }
+        Err(err) => {
+            error!(
+                event = "llm_processing_failed",
+                user = %msg.author.name,
+                error = ?err,
+                send_error_response = send_error_response,
+                "Error getting LLM response"
+            );
+            if send_error_response {
+                let apology = if LlmError::is_timeout(&err) {
+                    "Sorry, I'm thinking too slowly right now, try me again in a moment."
+                } else {
+                    "Sorry, I'm having trouble processing your message right now."
+                };
+                if let Err(why) = msg.reply(&http, apology).await {
+                    error!(
+                        event = "fallback_response_send_failed",
+                        user = %msg.author.name,
+                        error = ?why,
+                        "Error sending fallback response"
+                    );
+                }
+            } else {
+                info!(
+                    event = "llm_processing_failed_silent",
+                    user = %msg.author.name,
+                    "LLM processing failed for random reply, staying silent"
+                );
+            }
+        }
     }
 }
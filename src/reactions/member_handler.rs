@@ -0,0 +1,168 @@
+use crate::services::guild_service::GuildService;
+use crate::services::llm_service::LlmService;
+use crate::services::member_greetings::{
+    self, GREETING_PROMPT, GreetingKind, GreetingSettings, GreetingVars,
+};
+use crate::services::personas::{PERSONA_SETTING, PersonaService};
+use serenity::builder::{CreateAllowedMentions, CreateMessage};
+use serenity::model::guild::Member;
+use serenity::model::id::{ChannelId, GuildId};
+use serenity::model::user::User;
+use serenity::{async_trait, prelude::*};
+use std::sync::Arc;
+use tracing::{info, warn};
+
+/// Posts the guild's welcome and farewell messages as members join and leave
+pub struct MemberHandler {
+    pub guild_service: Arc<GuildService>,
+    pub llm_service: Arc<LlmService>,
+    pub personas: Arc<PersonaService>,
+}
+
+impl MemberHandler {
+    pub fn new(
+        guild_service: Arc<GuildService>,
+        llm_service: Arc<LlmService>,
+        personas: Arc<PersonaService>,
+    ) -> Self {
+        Self {
+            guild_service,
+            llm_service,
+            personas,
+        }
+    }
+
+    async fn greet(&self, ctx: &Context, kind: GreetingKind, guild_id: GuildId, user: &User) {
+        let setting = self
+            .guild_service
+            .get_guild_setting(guild_id.get() as i64, kind.setting())
+            .await;
+        let Some(settings) = GreetingSettings::from_setting(kind, setting.as_ref()) else {
+            return;
+        };
+
+        let (guild, member_count) = ctx
+            .cache
+            .guild(guild_id)
+            .map(|guild| (guild.name.clone(), guild.member_count))
+            .unwrap_or_default();
+        let vars = GreetingVars {
+            user_id: user.id.get(),
+            username: user.display_name().to_string(),
+            guild,
+            member_count,
+        };
+        let generated = if settings.generate {
+            self.generate(kind, guild_id, &settings.template, &vars)
+                .await
+        } else {
+            None
+        };
+        let content =
+            generated.unwrap_or_else(|| member_greetings::render(&settings.template, &vars));
+
+        // only a newcomer is pinged, someone who left can't see it anyway
+        let mentions = match kind {
+            GreetingKind::Welcome => CreateAllowedMentions::new().users([user.id]),
+            GreetingKind::Farewell => CreateAllowedMentions::new(),
+        };
+        let sent = ChannelId::new(settings.channel_id)
+            .send_message(
+                &ctx.http,
+                CreateMessage::new()
+                    .content(content.chars().take(2000).collect::<String>())
+                    .allowed_mentions(mentions),
+            )
+            .await;
+        match sent {
+            Ok(_) => info!(
+                event = "member_greeting_sent",
+                guild_id = %guild_id,
+                user_id = %user.id,
+                kind = ?kind,
+                generated = settings.generate,
+                "Sent member greeting"
+            ),
+            Err(e) => warn!(
+                event = "member_greeting_failed",
+                guild_id = %guild_id,
+                channel_id = settings.channel_id,
+                kind = ?kind,
+                error = ?e,
+                "Failed to send member greeting"
+            ),
+        }
+    }
+
+    /// A greeting written by the model in the guild's persona, None when it fails
+    async fn generate(
+        &self,
+        kind: GreetingKind,
+        guild_id: GuildId,
+        template: &str,
+        vars: &GreetingVars,
+    ) -> Option<String> {
+        let mut system_prompt = GREETING_PROMPT.to_string();
+        let persona_key = self
+            .guild_service
+            .get_guild_setting(guild_id.get() as i64, PERSONA_SETTING)
+            .await;
+        if let Some(key) = persona_key.as_ref().and_then(|key| key.as_str())
+            && let Ok(Some(persona)) = self.personas.find(key).await
+        {
+            system_prompt.push_str(&format!(
+                "\n\nThis server picked a persona for you, play it:\n{}",
+                persona.overlay
+            ));
+        }
+
+        let prompt = member_greetings::generation_prompt(kind, template, vars);
+        match self
+            .llm_service
+            .complete_text(&system_prompt, &prompt)
+            .await
+        {
+            Ok(text) => Some(member_greetings::render(&text, vars)),
+            Err(e) => {
+                warn!(
+                    event = "member_greeting_generation_failed",
+                    guild_id = %guild_id,
+                    kind = ?kind,
+                    error = ?e,
+                    "Failed to generate member greeting, using the template"
+                );
+                None
+            }
+        }
+    }
+}
+
+#[async_trait]
+impl EventHandler for MemberHandler {
+    async fn guild_member_addition(&self, ctx: Context, new_member: Member) {
+        if new_member.user.bot {
+            return;
+        }
+        self.greet(
+            &ctx,
+            GreetingKind::Welcome,
+            new_member.guild_id,
+            &new_member.user,
+        )
+        .await;
+    }
+
+    async fn guild_member_removal(
+        &self,
+        ctx: Context,
+        guild_id: GuildId,
+        user: User,
+        _member_data_if_available: Option<Member>,
+    ) {
+        if user.bot {
+            return;
+        }
+        self.greet(&ctx, GreetingKind::Farewell, guild_id, &user)
+            .await;
+    }
+}
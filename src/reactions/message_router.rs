@@ -0,0 +1,155 @@
+use serenity::model::channel::Message;
+use serenity::{async_trait, prelude::*};
+use std::sync::Arc;
+use tracing::debug;
+
+/// Whether a subscriber answered a message
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Handled {
+    /// Later subscribers don't get to handle the message
+    Claimed,
+    Passed,
+}
+
+/// Something that reacts to messages through the `MessageRouter`. Generic over the context
+/// only so routing can be tested without a gateway connection.
+#[async_trait]
+pub trait MessageSubscriber<C: Sync = Context>: Send + Sync {
+    /// Name used in logs
+    fn name(&self) -> &'static str;
+
+    /// Sees every message, bots' included, before any subscriber handles it. For
+    /// bookkeeping like caching that shouldn't depend on who answers.
+    async fn observe(&self, _ctx: &C, _msg: &Message) {}
+
+    async fn handle(&self, ctx: &C, msg: &Message) -> Handled;
+}
+
+/// Serenity runs every registered handler for an event at once and in no set order. That
+/// suits reactions and member updates, but only one subsystem should answer a message, so
+/// message subscribers are offered each message here in the order they subscribed until
+/// one claims it.
+pub struct MessageRouter<C: Sync = Context> {
+    subscribers: Vec<Arc<dyn MessageSubscriber<C>>>,
+}
+
+impl MessageRouter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl<C: Sync> Default for MessageRouter<C> {
+    fn default() -> Self {
+        Self {
+            subscribers: Vec::new(),
+        }
+    }
+}
+
+impl<C: Sync> MessageRouter<C> {
+    pub fn subscribe(mut self, subscriber: Arc<dyn MessageSubscriber<C>>) -> Self {
+        self.subscribers.push(subscriber);
+        self
+    }
+
+    /// Observe then route the message, returning who claimed it
+    pub async fn route(&self, ctx: &C, msg: &Message) -> Option<&'static str> {
+        for subscriber in &self.subscribers {
+            subscriber.observe(ctx, msg).await;
+        }
+        for subscriber in &self.subscribers {
+            if subscriber.handle(ctx, msg).await == Handled::Claimed {
+                return Some(subscriber.name());
+            }
+        }
+        None
+    }
+}
+
+#[async_trait]
+impl EventHandler for MessageRouter {
+    async fn message(&self, ctx: Context, msg: Message) {
+        if let Some(subscriber) = self.route(&ctx, &msg).await {
+            debug!(
+                event = "message_claimed",
+                subscriber = subscriber,
+                message_id = %msg.id,
+                channel_id = %msg.channel_id,
+                "Message handled by subscriber"
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Mutex;
+
+    /// Calls made to the stubs, in order
+    type Calls = Mutex<Vec<String>>;
+
+    struct Stub {
+        name: &'static str,
+        handled: Handled,
+    }
+
+    #[async_trait]
+    impl MessageSubscriber<Calls> for Stub {
+        fn name(&self) -> &'static str {
+            self.name
+        }
+
+        async fn observe(&self, calls: &Calls, _msg: &Message) {
+            calls.lock().unwrap().push(format!("observe {}", self.name));
+        }
+
+        async fn handle(&self, calls: &Calls, _msg: &Message) -> Handled {
+            calls.lock().unwrap().push(format!("handle {}", self.name));
+            self.handled
+        }
+    }
+
+    fn stubs(handled: [Handled; 3]) -> MessageRouter<Calls> {
+        let [first, second, third] = handled;
+        MessageRouter::default()
+            .subscribe(Arc::new(Stub {
+                name: "first",
+                handled: first,
+            }))
+            .subscribe(Arc::new(Stub {
+                name: "second",
+                handled: second,
+            }))
+            .subscribe(Arc::new(Stub {
+                name: "third",
+                handled: third,
+            }))
+    }
+
+    #[tokio::test]
+    async fn test_route() {
+        let calls = Calls::default();
+        let router = stubs([Handled::Passed, Handled::Claimed, Handled::Claimed]);
+        assert_eq!(
+            router.route(&calls, &Message::default()).await,
+            Some("second")
+        );
+        assert_eq!(
+            *calls.lock().unwrap(),
+            [
+                "observe first",
+                "observe second",
+                "observe third",
+                "handle first",
+                "handle second",
+            ]
+        );
+
+        let calls = Calls::default();
+        let router = stubs([Handled::Passed; 3]);
+        assert_eq!(router.route(&calls, &Message::default()).await, None);
+        assert_eq!(calls.lock().unwrap().len(), 6);
+    }
+}
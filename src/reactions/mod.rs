@@ -1 +1,11 @@
+pub mod custom_command_handler;
+pub mod guild_handler;
+pub mod interaction_handler;
+pub mod leveling_handler;
 pub mod llm_handler;
+pub mod member_handler;
+pub mod message_router;
+pub mod modmail_handler;
+pub mod ping_handler;
+pub mod reaction_role_handler;
+pub mod starboard_handler;
@@ -0,0 +1,385 @@
+use super::message_router::{Handled, MessageSubscriber};
+use crate::services::guild_service::GuildService;
+use crate::services::modmail::{ModmailService, ModmailThread, is_relayed, modmail_channel};
+use crate::services::user_service::UserService;
+use serenity::builder::{
+    CreateActionRow, CreateAllowedMentions, CreateMessage, CreateSelectMenu, CreateSelectMenuKind,
+    CreateSelectMenuOption, CreateThread, EditMessage,
+};
+use serenity::model::application::ComponentInteractionDataKind;
+use serenity::model::channel::{Message, ReactionType};
+use serenity::model::id::{ChannelId, GuildId, UserId};
+use serenity::{async_trait, prelude::*};
+use std::sync::Arc;
+use tokio::time::Duration;
+use tracing::{error, info, warn};
+
+/// How long a member has to pick which server's mods a DM is for
+const GUILD_CHOICE_TIMEOUT: Duration = Duration::from_secs(120);
+
+/// Discord allows at most 25 options in a select menu
+const MAX_GUILD_OPTIONS: usize = 25;
+
+const GUILD_CHOICE_ID: &str = "chloe_modmail_guild";
+
+/// Relays members' DMs into threads in their guild's modmail channel, and mod replies in
+/// those threads back to the member. Users chatting with chloe in DMs only reach the mods
+/// through a thread that's already open.
+pub struct ModmailHandler {
+    pub guild_service: Arc<GuildService>,
+    pub modmail: Arc<ModmailService>,
+    pub user_service: Arc<UserService>,
+}
+
+impl ModmailHandler {
+    pub fn new(
+        guild_service: Arc<GuildService>,
+        modmail: Arc<ModmailService>,
+        user_service: Arc<UserService>,
+    ) -> Self {
+        Self {
+            guild_service,
+            modmail,
+            user_service,
+        }
+    }
+
+    async fn relay_from_member(&self, ctx: &Context, msg: &Message) {
+        let open = self.modmail.threads_for_user(msg.author.id.get()).await;
+        let thread = if open.len() == 1 {
+            open[0].clone()
+        } else if !open.is_empty() {
+            let guilds = open
+                .iter()
+                .map(|thread| GuildId::new(thread.guild_id))
+                .collect();
+            let Some(guild_id) = self.choose_guild(ctx, msg, guilds).await else {
+                return;
+            };
+            match open
+                .into_iter()
+                .find(|thread| thread.guild_id == guild_id.get())
+            {
+                Some(thread) => thread,
+                None => return,
+            }
+        } else {
+            let guilds = self.modmail_guilds(ctx, msg.author.id).await;
+            if guilds.is_empty() {
+                // no modmail anywhere, DMs are just DMs
+                return;
+            }
+            let guild_id = if guilds.len() == 1 {
+                guilds[0]
+            } else {
+                match self.choose_guild(ctx, msg, guilds).await {
+                    Some(guild_id) => guild_id,
+                    None => return,
+                }
+            };
+            match self.open_thread(ctx, msg, guild_id).await {
+                Some(thread) => thread,
+                None => {
+                    let _ = msg
+                        .reply(
+                            &ctx.http,
+                            "couldn't reach the mods right now, try again later",
+                        )
+                        .await;
+                    return;
+                }
+            }
+        };
+
+        let content = relay_content(&format!("**{}:**", msg.author.name), msg);
+        let sent = ChannelId::new(thread.thread_id)
+            .send_message(
+                &ctx.http,
+                CreateMessage::new()
+                    .content(content)
+                    .allowed_mentions(CreateAllowedMentions::new()),
+            )
+            .await;
+        match sent {
+            Ok(_) => {
+                let _ = msg
+                    .react(&ctx.http, ReactionType::Unicode("📨".to_string()))
+                    .await;
+            }
+            Err(e) => {
+                warn!(
+                    event = "modmail_relay_to_mods_failed",
+                    user_id = %msg.author.id,
+                    thread_id = thread.thread_id,
+                    error = ?e,
+                    "Failed to relay DM into modmail thread"
+                );
+                // a deleted thread would otherwise swallow every DM, the next one opens a new thread
+                if is_not_found(&e) {
+                    let _ = self.modmail.close(thread.thread_id).await;
+                }
+                let _ = msg
+                    .reply(
+                        &ctx.http,
+                        "couldn't pass that on to the mods, try again later",
+                    )
+                    .await;
+            }
+        }
+    }
+
+    async fn relay_to_member(&self, ctx: &Context, msg: &Message, thread: &ModmailThread) {
+        if !is_relayed(&msg.content) {
+            return;
+        }
+        let guild_name = GuildId::new(thread.guild_id)
+            .name(&ctx.cache)
+            .unwrap_or_else(|| "the server".to_string());
+        // mods answer as a team, their names stay in the thread
+        let content = relay_content(&format!("**{} mods:**", guild_name), msg);
+        let sent = UserId::new(thread.user_id)
+            .direct_message(
+                &ctx.http,
+                CreateMessage::new()
+                    .content(content)
+                    .allowed_mentions(CreateAllowedMentions::new()),
+            )
+            .await;
+
+        let reaction = match sent {
+            Ok(_) => {
+                info!(
+                    event = "modmail_reply_relayed",
+                    user_id = thread.user_id,
+                    thread_id = thread.thread_id,
+                    "Relayed mod reply to member"
+                );
+                "✅"
+            }
+            Err(e) => {
+                warn!(
+                    event = "modmail_relay_to_member_failed",
+                    user_id = thread.user_id,
+                    thread_id = thread.thread_id,
+                    error = ?e,
+                    "Failed to DM modmail reply, the member may have DMs closed"
+                );
+                "❌"
+            }
+        };
+        let _ = msg
+            .react(&ctx.http, ReactionType::Unicode(reaction.to_string()))
+            .await;
+    }
+
+    /// Guilds with modmail turned on that the member belongs to
+    async fn modmail_guilds(&self, ctx: &Context, user_id: UserId) -> Vec<GuildId> {
+        let mut guilds = Vec::new();
+        for guild_id in ctx.cache.guilds() {
+            let setting = self
+                .guild_service
+                .get_guild_setting(guild_id.get() as i64, "modmail")
+                .await;
+            if modmail_channel(setting.as_ref()).is_some()
+                && guild_id.member(ctx, user_id).await.is_ok()
+            {
+                guilds.push(guild_id);
+            }
+        }
+        guilds
+    }
+
+    /// Ask a member in several modmail guilds which one a DM is for
+    async fn choose_guild(
+        &self,
+        ctx: &Context,
+        msg: &Message,
+        guilds: Vec<GuildId>,
+    ) -> Option<GuildId> {
+        let options: Vec<CreateSelectMenuOption> = guilds
+            .iter()
+            .take(MAX_GUILD_OPTIONS)
+            .map(|guild_id| {
+                let name = guild_id
+                    .name(&ctx.cache)
+                    .unwrap_or_else(|| guild_id.to_string());
+                CreateSelectMenuOption::new(name, guild_id.to_string())
+            })
+            .collect();
+        let menu = CreateSelectMenu::new(GUILD_CHOICE_ID, CreateSelectMenuKind::String { options })
+            .placeholder("pick a server");
+
+        let mut prompt = msg
+            .channel_id
+            .send_message(
+                &ctx.http,
+                CreateMessage::new()
+                    .content("which server's mods is this for? I'll pass it on once you pick")
+                    .components(vec![CreateActionRow::SelectMenu(menu)]),
+            )
+            .await
+            .ok()?;
+
+        let interaction = prompt
+            .await_component_interaction(&ctx.shard)
+            .timeout(GUILD_CHOICE_TIMEOUT)
+            .await;
+        let choice = interaction
+            .as_ref()
+            .and_then(|interaction| match &interaction.data.kind {
+                ComponentInteractionDataKind::StringSelect { values } => values
+                    .first()
+                    .and_then(|value| value.parse::<u64>().ok())
+                    .map(GuildId::new),
+                _ => None,
+            });
+
+        let content = match choice.and_then(|guild_id| guild_id.name(&ctx.cache)) {
+            Some(name) => format!("sending it to the mods of **{}**", name),
+            None => "never mind then, message me again whenever".to_string(),
+        };
+        if let Some(interaction) = interaction {
+            let _ = interaction.defer(&ctx.http).await;
+        }
+        let _ = prompt
+            .edit(
+                &ctx.http,
+                EditMessage::new().content(content).components(vec![]),
+            )
+            .await;
+        choice.filter(|guild_id| guilds.contains(guild_id))
+    }
+
+    async fn open_thread(
+        &self,
+        ctx: &Context,
+        msg: &Message,
+        guild_id: GuildId,
+    ) -> Option<ModmailThread> {
+        let setting = self
+            .guild_service
+            .get_guild_setting(guild_id.get() as i64, "modmail")
+            .await;
+        let channel_id = ChannelId::new(modmail_channel(setting.as_ref())?);
+
+        let result = async {
+            let starter = channel_id
+                .send_message(
+                    &ctx.http,
+                    CreateMessage::new()
+                        .content(format!(
+                            "📬 modmail from <@{}> ({}). replies here go to them, start a message with `//` to keep it between mods",
+                            msg.author.id, msg.author.name
+                        ))
+                        .allowed_mentions(CreateAllowedMentions::new()),
+                )
+                .await?;
+            let name: String = format!("modmail {}", msg.author.name)
+                .chars()
+                .take(100)
+                .collect();
+            channel_id
+                .create_thread_from_message(&ctx.http, starter.id, CreateThread::new(name))
+                .await
+        }
+        .await;
+
+        let thread_channel = match result {
+            Ok(thread_channel) => thread_channel,
+            Err(e) => {
+                error!(
+                    event = "modmail_thread_create_failed",
+                    guild_id = %guild_id,
+                    channel_id = %channel_id,
+                    error = ?e,
+                    "Failed to open modmail thread"
+                );
+                return None;
+            }
+        };
+
+        let thread = ModmailThread {
+            guild_id: guild_id.get(),
+            user_id: msg.author.id.get(),
+            thread_id: thread_channel.id.get(),
+        };
+        if let Err(e) = self.modmail.open(&thread).await {
+            error!(
+                event = "modmail_thread_save_failed",
+                guild_id = %guild_id,
+                error = ?e,
+                "Failed to save modmail thread"
+            );
+            return None;
+        }
+
+        let guild_name = guild_id
+            .name(&ctx.cache)
+            .unwrap_or_else(|| "the server".to_string());
+        let _ = msg
+            .channel_id
+            .say(
+                &ctx.http,
+                format!(
+                    "📨 you're talking to the mods of **{}** now, their replies will show up here",
+                    guild_name
+                ),
+            )
+            .await;
+        Some(thread)
+    }
+}
+
+/// A relayed message: who it's from, what they said and links to anything attached,
+/// cut to discord's 2000 character limit
+fn relay_content(header: &str, msg: &Message) -> String {
+    let mut content = format!("{} {}", header, msg.content);
+    for attachment in &msg.attachments {
+        content.push('\n');
+        content.push_str(&attachment.url);
+    }
+    content.chars().take(2000).collect()
+}
+
+fn is_not_found(error: &serenity::Error) -> bool {
+    matches!(
+        error,
+        serenity::Error::Http(serenity::http::HttpError::UnsuccessfulRequest(response))
+            if response.status_code.as_u16() == 404
+    )
+}
+
+#[async_trait]
+impl MessageSubscriber for ModmailHandler {
+    fn name(&self) -> &'static str {
+        "modmail"
+    }
+
+    async fn handle(&self, ctx: &Context, msg: &Message) -> Handled {
+        if msg.author.bot {
+            return Handled::Passed;
+        }
+
+        if msg.guild_id.is_none() {
+            let user_id = msg.author.id.get();
+            if self.modmail.threads_for_user(user_id).await.is_empty()
+                && self
+                    .user_service
+                    .dm_chat_enabled(user_id as i64)
+                    .await
+                    .unwrap_or(false)
+            {
+                return Handled::Passed;
+            }
+            self.relay_from_member(ctx, msg).await;
+            return Handled::Claimed;
+        }
+
+        // mods talking in a modmail thread are talking to the member, not to chloe
+        if let Some(thread) = self.modmail.thread(msg.channel_id.get()).await {
+            self.relay_to_member(ctx, msg, &thread).await;
+            return Handled::Claimed;
+        }
+        Handled::Passed
+    }
+}
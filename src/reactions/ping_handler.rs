@@ -0,0 +1,94 @@
+use super::message_router::{Handled, MessageSubscriber};
+use crate::services::guild_service::GuildService;
+use crate::services::ping_reply;
+use serenity::builder::CreateAllowedMentions;
+use serenity::model::channel::Message;
+use serenity::{async_trait, prelude::*};
+use std::sync::Arc;
+use tracing::{info, warn};
+
+/// Answers bare pings with the guild's ping reply, for guilds with `ping_reply` on. Runs
+/// ahead of the LLM handler so a bare ping gets the template rather than the model.
+pub struct PingHandler {
+    pub guild_service: Arc<GuildService>,
+}
+
+impl PingHandler {
+    pub fn new(guild_service: Arc<GuildService>) -> Self {
+        Self { guild_service }
+    }
+}
+
+#[async_trait]
+impl MessageSubscriber for PingHandler {
+    fn name(&self) -> &'static str {
+        "ping_reply"
+    }
+
+    async fn handle(&self, ctx: &Context, msg: &Message) -> Handled {
+        let Some(guild_id) = msg.guild_id else {
+            return Handled::Passed;
+        };
+        if msg.author.bot
+            || !ping_reply::is_bare_ping(&msg.content, ctx.cache.current_user().id.get())
+        {
+            return Handled::Passed;
+        }
+
+        // highest role first, so the most specific template wins
+        let (roles, server) = match ctx.cache.guild(guild_id) {
+            Some(guild) => {
+                let mut roles = msg
+                    .member
+                    .as_ref()
+                    .map(|member| member.roles.clone())
+                    .unwrap_or_default();
+                roles.sort_by_key(|role| {
+                    std::cmp::Reverse(guild.roles.get(role).map(|role| role.position))
+                });
+                let roles: Vec<u64> = roles.iter().map(|role| role.get()).collect();
+                (roles, guild.name.clone())
+            }
+            None => (Vec::new(), String::new()),
+        };
+
+        let Some(template) = self
+            .guild_service
+            .get_ping_response(guild_id.get() as i64, &roles)
+            .await
+        else {
+            return Handled::Passed;
+        };
+
+        let reply = ping_reply::render(
+            &template,
+            msg.author.id.get(),
+            &server,
+            msg.channel_id.get(),
+        );
+        // templates are written by admins, the reply may ping the author but nobody else
+        let allowed_mentions = CreateAllowedMentions::new().users(vec![msg.author.id]);
+        let builder = serenity::builder::CreateMessage::new()
+            .content(reply)
+            .reference_message(msg)
+            .allowed_mentions(allowed_mentions);
+
+        match msg.channel_id.send_message(&ctx.http, builder).await {
+            Ok(_) => info!(
+                event = "ping_reply_sent",
+                guild_id = %guild_id,
+                channel_id = %msg.channel_id,
+                user = %msg.author.name,
+                "Replied to a bare ping"
+            ),
+            Err(e) => warn!(
+                event = "ping_reply_send_failed",
+                guild_id = %guild_id,
+                channel_id = %msg.channel_id,
+                error = ?e,
+                "Failed to send ping reply"
+            ),
+        }
+        Handled::Claimed
+    }
+}
@@ -0,0 +1,77 @@
+use crate::services::reaction_roles::ReactionRoleService;
+use serenity::model::channel::Reaction;
+use serenity::model::id::RoleId;
+use serenity::{async_trait, prelude::*};
+use std::sync::Arc;
+use tracing::{info, warn};
+
+/// Grants and removes roles as members react on messages set up with /reactionrole
+pub struct ReactionRoleHandler {
+    pub reaction_roles: Arc<ReactionRoleService>,
+}
+
+impl ReactionRoleHandler {
+    pub fn new(reaction_roles: Arc<ReactionRoleService>) -> Self {
+        Self { reaction_roles }
+    }
+
+    async fn apply(&self, ctx: &Context, reaction: &Reaction, added: bool) {
+        let (Some(guild_id), Some(user_id)) = (reaction.guild_id, reaction.user_id) else {
+            return;
+        };
+        // chloe's own reaction marks the message, it isn't a request for the role
+        if user_id == ctx.cache.current_user().id {
+            return;
+        }
+        let Some(role_id) = self
+            .reaction_roles
+            .role_for(reaction.message_id.get(), &reaction.emoji)
+            .await
+        else {
+            return;
+        };
+
+        let role_id = RoleId::new(role_id);
+        let result = if added {
+            ctx.http
+                .add_member_role(guild_id, user_id, role_id, Some("reaction role"))
+                .await
+        } else {
+            ctx.http
+                .remove_member_role(guild_id, user_id, role_id, Some("reaction role"))
+                .await
+        };
+
+        match result {
+            Ok(()) => info!(
+                event = "reaction_role_applied",
+                guild_id = %guild_id,
+                user_id = %user_id,
+                role_id = %role_id,
+                added = added,
+                "Updated member role from reaction"
+            ),
+            // usually missing manage roles, or the role sits above chloe's
+            Err(e) => warn!(
+                event = "reaction_role_failed",
+                guild_id = %guild_id,
+                user_id = %user_id,
+                role_id = %role_id,
+                added = added,
+                error = ?e,
+                "Failed to update member role from reaction"
+            ),
+        }
+    }
+}
+
+#[async_trait]
+impl EventHandler for ReactionRoleHandler {
+    async fn reaction_add(&self, ctx: Context, reaction: Reaction) {
+        self.apply(&ctx, &reaction, true).await;
+    }
+
+    async fn reaction_remove(&self, ctx: Context, reaction: Reaction) {
+        self.apply(&ctx, &reaction, false).await;
+    }
+}
@@ -0,0 +1,160 @@
+use crate::services::guild_service::GuildService;
+use crate::services::starboard::{STARBOARD_SETTING, StarboardService, StarboardSettings};
+use serenity::builder::{
+    CreateAllowedMentions, CreateEmbed, CreateEmbedAuthor, CreateEmbedFooter, CreateMessage,
+};
+use serenity::model::channel::{Message, Reaction, ReactionType};
+use serenity::model::id::{ChannelId, GuildId};
+use serenity::{async_trait, prelude::*};
+use std::sync::Arc;
+use tracing::{error, warn};
+
+const STARBOARD_COLOR: u32 = 0xFFAC33;
+
+/// Reposts messages to a guild's starboard channel once enough members react with its emoji
+pub struct StarboardHandler {
+    pub guild_service: Arc<GuildService>,
+    pub starboard: Arc<StarboardService>,
+}
+
+impl StarboardHandler {
+    pub fn new(guild_service: Arc<GuildService>, starboard: Arc<StarboardService>) -> Self {
+        Self {
+            guild_service,
+            starboard,
+        }
+    }
+
+    async fn repost(
+        &self,
+        ctx: &Context,
+        guild_id: GuildId,
+        settings: &StarboardSettings,
+        msg: &Message,
+        emoji: &ReactionType,
+        count: u64,
+    ) {
+        match self
+            .starboard
+            .claim(guild_id.get(), msg.channel_id.get(), msg.id.get())
+            .await
+        {
+            Ok(true) => {}
+            Ok(false) => return,
+            Err(e) => {
+                error!(
+                    event = "starboard_claim_failed",
+                    guild_id = %guild_id,
+                    message_id = %msg.id,
+                    error = ?e,
+                    "Failed to claim message for starboard"
+                );
+                return;
+            }
+        }
+
+        let repost = CreateMessage::new()
+            .content(format!("{} **{}** in <#{}>", emoji, count, msg.channel_id))
+            .embed(starboard_embed(guild_id, msg))
+            .allowed_mentions(CreateAllowedMentions::new());
+        let result = match ChannelId::new(settings.channel_id)
+            .send_message(&ctx.http, repost)
+            .await
+        {
+            Ok(posted) => self.starboard.posted(msg.id.get(), posted.id.get()).await,
+            Err(e) => {
+                // usually a deleted channel or missing send permissions
+                warn!(
+                    event = "starboard_post_failed",
+                    guild_id = %guild_id,
+                    channel_id = settings.channel_id,
+                    message_id = %msg.id,
+                    error = ?e,
+                    "Failed to repost message to starboard"
+                );
+                self.starboard.release(msg.id.get()).await
+            }
+        };
+        if let Err(e) = result {
+            error!(
+                event = "starboard_save_failed",
+                guild_id = %guild_id,
+                message_id = %msg.id,
+                error = ?e,
+                "Failed to save starboard post"
+            );
+        }
+    }
+}
+
+#[async_trait]
+impl EventHandler for StarboardHandler {
+    async fn reaction_add(&self, ctx: Context, reaction: Reaction) {
+        let Some(guild_id) = reaction.guild_id else {
+            return;
+        };
+        let setting = self
+            .guild_service
+            .get_guild_setting(guild_id.get() as i64, STARBOARD_SETTING)
+            .await;
+        let Some(settings) = StarboardSettings::from_setting(setting.as_ref()) else {
+            return;
+        };
+        // starring the starboard's own posts would repost them again
+        if !settings.matches(&reaction.emoji) || reaction.channel_id.get() == settings.channel_id {
+            return;
+        }
+
+        let msg = match reaction.message(&ctx.http).await {
+            Ok(msg) => msg,
+            Err(e) => {
+                warn!(
+                    event = "starboard_message_fetch_failed",
+                    guild_id = %guild_id,
+                    message_id = %reaction.message_id,
+                    error = ?e,
+                    "Failed to fetch starred message"
+                );
+                return;
+            }
+        };
+        let count = msg
+            .reactions
+            .iter()
+            .find(|existing| settings.matches(&existing.reaction_type))
+            .map_or(0, |existing| existing.count);
+        if count >= settings.threshold {
+            self.repost(&ctx, guild_id, &settings, &msg, &reaction.emoji, count)
+                .await;
+        }
+    }
+}
+
+fn starboard_embed(guild_id: GuildId, msg: &Message) -> CreateEmbed {
+    let mut embed = CreateEmbed::new()
+        .color(STARBOARD_COLOR)
+        .author(CreateEmbedAuthor::new(&msg.author.name).icon_url(msg.author.face()))
+        .field(
+            "source",
+            format!(
+                "[jump to message]({})",
+                msg.id.link(msg.channel_id, Some(guild_id))
+            ),
+            false,
+        )
+        .footer(CreateEmbedFooter::new(msg.id.to_string()))
+        .timestamp(msg.timestamp);
+    if !msg.content.is_empty() {
+        embed = embed.description(&msg.content);
+    }
+    let image = msg.attachments.iter().find(|attachment| {
+        attachment
+            .content_type
+            .as_deref()
+            .is_some_and(|content_type| content_type.starts_with("image/"))
+    });
+    if let Some(image) = image {
+        embed = embed.image(&image.url);
+    }
+    embed
+}
@@ -1,3 +1,4 @@
+use futures::stream::{self, StreamExt};
 use serde_json::json;
 use serenity::model::prelude::*;
 use sqlx::{PgPool, Row};
@@ -16,6 +17,7 @@ pub async fn initialize_database(db_pool: &PgPool) -> Result<(), sqlx::Error> {
             avatar VARCHAR(255),
             banner VARCHAR(255),
             superadmin BOOLEAN NOT NULL DEFAULT false,
+            dm_chat BOOLEAN NOT NULL DEFAULT false,
             created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
             modified_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP
         )
@@ -28,6 +30,7 @@ pub async fn initialize_database(db_pool: &PgPool) -> Result<(), sqlx::Error> {
             snowflake_id BIGINT UNIQUE NOT NULL,
             name VARCHAR(255) NOT NULL,
             owner_id VARCHAR(255) REFERENCES chloe_users(id),
+            synced_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
             created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
             modified_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP
         )
@@ -82,6 +85,272 @@ pub async fn initialize_database(db_pool: &PgPool) -> Result<(), sqlx::Error> {
         )
     "#;
 
+    // create chloe_scheduled_jobs table for the background scheduler
+    let create_scheduled_jobs_table = r#"
+        CREATE TABLE IF NOT EXISTS chloe_scheduled_jobs (
+            id VARCHAR(255) PRIMARY KEY DEFAULT gen_random_uuid()::text,
+            job_type VARCHAR(64) NOT NULL,
+            payload JSON NOT NULL,
+            run_at TIMESTAMPTZ NOT NULL,
+            status VARCHAR(32) NOT NULL DEFAULT 'pending',
+            created_by VARCHAR(255),
+            last_error TEXT,
+            created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
+            modified_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP
+        )
+    "#;
+
+    // create chloe_provider_recordings table for the opt-in replay corpus
+    let create_provider_recordings_table = r#"
+        CREATE TABLE IF NOT EXISTS chloe_provider_recordings (
+            id VARCHAR(255) PRIMARY KEY DEFAULT gen_random_uuid()::text,
+            model VARCHAR(255) NOT NULL,
+            prompt_hash VARCHAR(64) NOT NULL,
+            prompt_version INTEGER NOT NULL,
+            request JSON NOT NULL,
+            response JSON NOT NULL,
+            created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP
+        )
+    "#;
+
+    // create chloe_reaction_roles table mapping emojis on a message to roles
+    let create_reaction_roles_table = r#"
+        CREATE TABLE IF NOT EXISTS chloe_reaction_roles (
+            id VARCHAR(255) PRIMARY KEY DEFAULT gen_random_uuid()::text,
+            guild_id VARCHAR(255) NOT NULL REFERENCES chloe_guilds(id),
+            channel_id BIGINT NOT NULL,
+            message_id BIGINT NOT NULL,
+            emoji VARCHAR(255) NOT NULL,
+            role_id BIGINT NOT NULL,
+            created_by VARCHAR(255),
+            created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
+            UNIQUE(message_id, emoji)
+        )
+    "#;
+
+    // create chloe_triggers table for per guild auto-responses
+    let create_triggers_table = r#"
+        CREATE TABLE IF NOT EXISTS chloe_triggers (
+            id VARCHAR(255) PRIMARY KEY DEFAULT gen_random_uuid()::text,
+            guild_id VARCHAR(255) NOT NULL REFERENCES chloe_guilds(id),
+            pattern VARCHAR(255) NOT NULL,
+            is_regex BOOLEAN NOT NULL DEFAULT false,
+            response TEXT NOT NULL,
+            use_llm BOOLEAN NOT NULL DEFAULT false,
+            channel_id BIGINT,
+            created_by VARCHAR(255),
+            created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
+            UNIQUE(guild_id, pattern)
+        )
+    "#;
+
+    // create chloe_usage table with daily token counts per guild, user and model
+    let create_usage_table = r#"
+        CREATE TABLE IF NOT EXISTS chloe_usage (
+            guild_id BIGINT NOT NULL,
+            user_id BIGINT NOT NULL,
+            model VARCHAR(255) NOT NULL,
+            day DATE NOT NULL DEFAULT CURRENT_DATE,
+            prompt_tokens BIGINT NOT NULL DEFAULT 0,
+            completion_tokens BIGINT NOT NULL DEFAULT 0,
+            requests BIGINT NOT NULL DEFAULT 0,
+            PRIMARY KEY (guild_id, user_id, model, day)
+        )
+    "#;
+
+    // create chloe_modmail_threads table linking members' DMs to threads in a mod channel
+    let create_modmail_threads_table = r#"
+        CREATE TABLE IF NOT EXISTS chloe_modmail_threads (
+            id VARCHAR(255) PRIMARY KEY DEFAULT gen_random_uuid()::text,
+            guild_id VARCHAR(255) NOT NULL REFERENCES chloe_guilds(id),
+            user_id BIGINT NOT NULL,
+            thread_id BIGINT NOT NULL UNIQUE,
+            status VARCHAR(20) NOT NULL DEFAULT 'open',
+            created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
+            closed_at TIMESTAMP
+        )
+    "#;
+
+    // create chloe_messages table keeping conversation turns per channel
+    let create_messages_table = r#"
+        CREATE TABLE IF NOT EXISTS chloe_messages (
+            message_id BIGINT PRIMARY KEY,
+            channel_id BIGINT NOT NULL,
+            guild_id BIGINT,
+            author_id BIGINT NOT NULL,
+            author_name VARCHAR(255) NOT NULL,
+            role VARCHAR(16) NOT NULL,
+            content TEXT NOT NULL,
+            created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP
+        )
+    "#;
+
+    // create chloe_user_memories table keeping facts members told chloe about themselves
+    let create_user_memories_table = r#"
+        CREATE TABLE IF NOT EXISTS chloe_user_memories (
+            id VARCHAR(255) PRIMARY KEY DEFAULT gen_random_uuid()::text,
+            guild_id VARCHAR(255) NOT NULL REFERENCES chloe_guilds(id),
+            user_id BIGINT NOT NULL,
+            fact TEXT NOT NULL,
+            created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
+            UNIQUE(guild_id, user_id, fact)
+        )
+    "#;
+
+    // create chloe_reminders table for reminders members set with /remind or by asking chloe
+    let create_reminders_table = r#"
+        CREATE TABLE IF NOT EXISTS chloe_reminders (
+            id VARCHAR(255) PRIMARY KEY DEFAULT gen_random_uuid()::text,
+            guild_id BIGINT,
+            channel_id BIGINT NOT NULL,
+            user_id BIGINT NOT NULL,
+            message TEXT NOT NULL,
+            remind_at TIMESTAMPTZ NOT NULL,
+            status VARCHAR(32) NOT NULL DEFAULT 'pending',
+            created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
+            sent_at TIMESTAMP
+        )
+    "#;
+
+    // create chloe_persona_presets table for personas superadmins publish next to the built-in ones
+    let create_persona_presets_table = r#"
+        CREATE TABLE IF NOT EXISTS chloe_persona_presets (
+            id VARCHAR(255) PRIMARY KEY DEFAULT gen_random_uuid()::text,
+            key VARCHAR(64) NOT NULL UNIQUE,
+            name VARCHAR(100) NOT NULL,
+            description TEXT NOT NULL,
+            overlay TEXT NOT NULL,
+            created_by BIGINT NOT NULL,
+            created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
+            modified_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP
+        )
+    "#;
+
+    // create chloe_superadmin_audit table, every promote and demote with who did it
+    let create_superadmin_audit_table = r#"
+        CREATE TABLE IF NOT EXISTS chloe_superadmin_audit (
+            id VARCHAR(255) PRIMARY KEY DEFAULT gen_random_uuid()::text,
+            user_id BIGINT NOT NULL,
+            actor_id BIGINT,
+            action VARCHAR(16) NOT NULL,
+            created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP
+        )
+    "#;
+
+    // create chloe_scheduled_messages table for recurring announcements admins set with /schedule
+    let create_scheduled_messages_table = r#"
+        CREATE TABLE IF NOT EXISTS chloe_scheduled_messages (
+            id VARCHAR(255) PRIMARY KEY DEFAULT gen_random_uuid()::text,
+            guild_id VARCHAR(255) NOT NULL REFERENCES chloe_guilds(id),
+            channel_id BIGINT NOT NULL,
+            cron VARCHAR(100) NOT NULL,
+            timezone VARCHAR(64) NOT NULL DEFAULT 'UTC',
+            content TEXT NOT NULL,
+            enabled BOOLEAN NOT NULL DEFAULT true,
+            next_run_at TIMESTAMPTZ NOT NULL,
+            last_run_at TIMESTAMPTZ,
+            created_by BIGINT NOT NULL,
+            created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP
+        )
+    "#;
+
+    // create chloe_starboard table, one row per message reposted to a guild's starboard
+    let create_starboard_table = r#"
+        CREATE TABLE IF NOT EXISTS chloe_starboard (
+            id VARCHAR(255) PRIMARY KEY DEFAULT gen_random_uuid()::text,
+            guild_id VARCHAR(255) NOT NULL REFERENCES chloe_guilds(id),
+            channel_id BIGINT NOT NULL,
+            message_id BIGINT NOT NULL UNIQUE,
+            starboard_message_id BIGINT,
+            created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP
+        )
+    "#;
+
+    // create chloe_custom_commands table for slash commands admins define per guild
+    let create_custom_commands_table = r#"
+        CREATE TABLE IF NOT EXISTS chloe_custom_commands (
+            id VARCHAR(255) PRIMARY KEY DEFAULT gen_random_uuid()::text,
+            guild_id VARCHAR(255) NOT NULL REFERENCES chloe_guilds(id),
+            name VARCHAR(32) NOT NULL,
+            description VARCHAR(100) NOT NULL,
+            response TEXT NOT NULL,
+            embed BOOLEAN NOT NULL DEFAULT false,
+            command_id BIGINT,
+            created_by VARCHAR(255),
+            created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
+            UNIQUE(guild_id, name)
+        )
+    "#;
+
+    // create chloe_levels table with each member's xp per guild
+    let create_levels_table = r#"
+        CREATE TABLE IF NOT EXISTS chloe_levels (
+            id VARCHAR(255) PRIMARY KEY DEFAULT gen_random_uuid()::text,
+            guild_id VARCHAR(255) NOT NULL REFERENCES chloe_guilds(id),
+            user_id BIGINT NOT NULL,
+            xp BIGINT NOT NULL DEFAULT 0,
+            level INTEGER NOT NULL DEFAULT 0,
+            message_count BIGINT NOT NULL DEFAULT 0,
+            last_xp_at TIMESTAMPTZ,
+            UNIQUE(guild_id, user_id)
+        )
+    "#;
+
+    // create chloe_level_rewards table, the role a guild hands out at a level
+    let create_level_rewards_table = r#"
+        CREATE TABLE IF NOT EXISTS chloe_level_rewards (
+            id VARCHAR(255) PRIMARY KEY DEFAULT gen_random_uuid()::text,
+            guild_id VARCHAR(255) NOT NULL REFERENCES chloe_guilds(id),
+            level INTEGER NOT NULL,
+            role_id BIGINT NOT NULL,
+            created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
+            UNIQUE(guild_id, level)
+        )
+    "#;
+
+    // create chloe_guild_prompts table for guilds' own versioned prompts
+    let create_guild_prompts_table = r#"
+        CREATE TABLE IF NOT EXISTS chloe_guild_prompts (
+            id VARCHAR(255) PRIMARY KEY DEFAULT gen_random_uuid()::text,
+            guild_id VARCHAR(255) NOT NULL REFERENCES chloe_guilds(id),
+            version INTEGER NOT NULL,
+            content TEXT NOT NULL,
+            created_by VARCHAR(255),
+            is_active BOOLEAN NOT NULL DEFAULT false,
+            created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+            UNIQUE(guild_id, version)
+        )
+    "#;
+
+    // create chloe_content_incidents table with replies the provider's content filter blocked
+    let create_content_incidents_table = r#"
+        CREATE TABLE IF NOT EXISTS chloe_content_incidents (
+            id VARCHAR(255) PRIMARY KEY DEFAULT gen_random_uuid()::text,
+            guild_id BIGINT,
+            channel_id BIGINT,
+            model VARCHAR(255) NOT NULL,
+            block_reason VARCHAR(64) NOT NULL,
+            category VARCHAR(64),
+            prompt_hash VARCHAR(64) NOT NULL,
+            created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+        )
+    "#;
+
+    // create chloe_saved_conversations table with conversations members saved under a name
+    let create_saved_conversations_table = r#"
+        CREATE TABLE IF NOT EXISTS chloe_saved_conversations (
+            id VARCHAR(255) PRIMARY KEY DEFAULT gen_random_uuid()::text,
+            user_id BIGINT NOT NULL,
+            name VARCHAR(64) NOT NULL,
+            guild_id BIGINT,
+            source_channel_id BIGINT NOT NULL,
+            turns JSONB NOT NULL,
+            resumed_in BIGINT,
+            created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+            UNIQUE(user_id, name)
+        )
+    "#;
+
     // execute table creation
     sqlx::query(create_users_table).execute(db_pool).await?;
     info!("created/verified chloe_users table");
@@ -93,7 +362,8 @@ pub async fn initialize_database(db_pool: &PgPool) -> Result<(), sqlx::Error> {
         ADD COLUMN IF NOT EXISTS global_name VARCHAR(255),
         ADD COLUMN IF NOT EXISTS avatar VARCHAR(255),
         ADD COLUMN IF NOT EXISTS banner VARCHAR(255),
-        ADD COLUMN IF NOT EXISTS superadmin BOOLEAN NOT NULL DEFAULT false
+        ADD COLUMN IF NOT EXISTS superadmin BOOLEAN NOT NULL DEFAULT false,
+        ADD COLUMN IF NOT EXISTS dm_chat BOOLEAN NOT NULL DEFAULT false
     "#;
     sqlx::query(add_user_columns).execute(db_pool).await?;
     info!("ensured user profile columns exist in chloe_users table");
@@ -101,6 +371,17 @@ pub async fn initialize_database(db_pool: &PgPool) -> Result<(), sqlx::Error> {
     sqlx::query(create_guilds_table).execute(db_pool).await?;
     info!("created/verified chloe_guilds table");
 
+    sqlx::query("ALTER TABLE chloe_guilds ADD COLUMN IF NOT EXISTS synced_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP")
+        .execute(db_pool)
+        .await?;
+    info!("ensured synced_at column exists in chloe_guilds table");
+
+    // set while chloe isn't in the guild, its data is purged once the retention period passes
+    sqlx::query("ALTER TABLE chloe_guilds ADD COLUMN IF NOT EXISTS left_at TIMESTAMPTZ")
+        .execute(db_pool)
+        .await?;
+    info!("ensured left_at column exists in chloe_guilds table");
+
     sqlx::query(create_settings_table).execute(db_pool).await?;
     info!("created/verified chloe_guilds_settings table");
 
@@ -117,6 +398,101 @@ pub async fn initialize_database(db_pool: &PgPool) -> Result<(), sqlx::Error> {
         .await?;
     info!("created/verified chloe_settings table");
 
+    // per-category block thresholds no guild can go below, null for the default floor
+    sqlx::query("ALTER TABLE chloe_settings ADD COLUMN IF NOT EXISTS safety_floor JSONB")
+        .execute(db_pool)
+        .await?;
+    info!("ensured safety_floor column exists in chloe_settings table");
+
+    sqlx::query(create_scheduled_jobs_table)
+        .execute(db_pool)
+        .await?;
+    info!("created/verified chloe_scheduled_jobs table");
+
+    sqlx::query(create_provider_recordings_table)
+        .execute(db_pool)
+        .await?;
+    info!("created/verified chloe_provider_recordings table");
+
+    // prompt_version counts a guild's own prompt versions when set
+    sqlx::query("ALTER TABLE chloe_provider_recordings ADD COLUMN IF NOT EXISTS guild_prompt BOOLEAN NOT NULL DEFAULT false")
+        .execute(db_pool)
+        .await?;
+    info!("ensured guild_prompt column exists in chloe_provider_recordings table");
+
+    sqlx::query(create_reaction_roles_table)
+        .execute(db_pool)
+        .await?;
+    info!("created/verified chloe_reaction_roles table");
+
+    sqlx::query(create_triggers_table).execute(db_pool).await?;
+    info!("created/verified chloe_triggers table");
+
+    sqlx::query(create_usage_table).execute(db_pool).await?;
+    info!("created/verified chloe_usage table");
+
+    sqlx::query(create_modmail_threads_table)
+        .execute(db_pool)
+        .await?;
+    info!("created/verified chloe_modmail_threads table");
+
+    sqlx::query(create_messages_table).execute(db_pool).await?;
+    info!("created/verified chloe_messages table");
+
+    sqlx::query(create_user_memories_table)
+        .execute(db_pool)
+        .await?;
+    info!("created/verified chloe_user_memories table");
+
+    sqlx::query(create_reminders_table).execute(db_pool).await?;
+    info!("created/verified chloe_reminders table");
+
+    sqlx::query(create_persona_presets_table)
+        .execute(db_pool)
+        .await?;
+    info!("created/verified chloe_persona_presets table");
+
+    sqlx::query(create_superadmin_audit_table)
+        .execute(db_pool)
+        .await?;
+    info!("created/verified chloe_superadmin_audit table");
+
+    sqlx::query(create_scheduled_messages_table)
+        .execute(db_pool)
+        .await?;
+    info!("created/verified chloe_scheduled_messages table");
+
+    sqlx::query(create_starboard_table).execute(db_pool).await?;
+    info!("created/verified chloe_starboard table");
+
+    sqlx::query(create_custom_commands_table)
+        .execute(db_pool)
+        .await?;
+    info!("created/verified chloe_custom_commands table");
+
+    sqlx::query(create_levels_table).execute(db_pool).await?;
+    info!("created/verified chloe_levels table");
+
+    sqlx::query(create_level_rewards_table)
+        .execute(db_pool)
+        .await?;
+    info!("created/verified chloe_level_rewards table");
+
+    sqlx::query(create_guild_prompts_table)
+        .execute(db_pool)
+        .await?;
+    info!("created/verified chloe_guild_prompts table");
+
+    sqlx::query(create_content_incidents_table)
+        .execute(db_pool)
+        .await?;
+    info!("created/verified chloe_content_incidents table");
+
+    sqlx::query(create_saved_conversations_table)
+        .execute(db_pool)
+        .await?;
+    info!("created/verified chloe_saved_conversations table");
+
     // create performance indexes
     create_performance_indexes(db_pool).await?;
 
@@ -124,6 +500,44 @@ pub async fn initialize_database(db_pool: &PgPool) -> Result<(), sqlx::Error> {
     Ok(())
 }
 
+/// Create the pgvector extension and the embeddings table. Kept apart from the other tables
+/// because it fails on postgres servers without pgvector installed, chloe then runs without
+/// semantic memory.
+pub async fn initialize_semantic_memory(db_pool: &PgPool) -> Result<(), sqlx::Error> {
+    sqlx::query("CREATE EXTENSION IF NOT EXISTS vector")
+        .execute(db_pool)
+        .await?;
+
+    // create chloe_memory_embeddings table, rows go away with the turn or fact they embed
+    let create_memory_embeddings_table = format!(
+        r#"
+        CREATE TABLE IF NOT EXISTS chloe_memory_embeddings (
+            id VARCHAR(255) PRIMARY KEY DEFAULT gen_random_uuid()::text,
+            message_id BIGINT UNIQUE REFERENCES chloe_messages(message_id) ON DELETE CASCADE,
+            memory_id VARCHAR(255) UNIQUE REFERENCES chloe_user_memories(id) ON DELETE CASCADE,
+            channel_id BIGINT,
+            guild_id BIGINT,
+            user_id BIGINT NOT NULL,
+            author_name VARCHAR(255) NOT NULL,
+            content TEXT NOT NULL,
+            embedding vector({}) NOT NULL,
+            created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP
+        )
+    "#,
+        crate::services::embeddings::EMBEDDING_DIMENSIONS
+    );
+    sqlx::query(&create_memory_embeddings_table)
+        .execute(db_pool)
+        .await?;
+    info!("created/verified chloe_memory_embeddings table");
+
+    sqlx::query("CREATE INDEX CONCURRENTLY IF NOT EXISTS idx_memory_embeddings_vector ON chloe_memory_embeddings USING hnsw (embedding vector_cosine_ops)")
+        .execute(db_pool).await?;
+    sqlx::query("CREATE INDEX CONCURRENTLY IF NOT EXISTS idx_memory_embeddings_channel ON chloe_memory_embeddings(channel_id, message_id)")
+        .execute(db_pool).await?;
+    Ok(())
+}
+
 async fn create_performance_indexes(db_pool: &PgPool) -> Result<(), sqlx::Error> {
     info!("creating performance indexes...");
     sqlx::query("CREATE INDEX CONCURRENTLY IF NOT EXISTS idx_guilds_snowflake ON chloe_guilds(snowflake_id)")
@@ -139,10 +553,65 @@ async fn create_performance_indexes(db_pool: &PgPool) -> Result<(), sqlx::Error>
         .execute(db_pool).await?;
     sqlx::query("CREATE INDEX CONCURRENTLY IF NOT EXISTS idx_guilds_settings_covering ON chloe_guilds_settings(guild_id) INCLUDE (settings)")
         .execute(db_pool).await?;
+    sqlx::query("CREATE INDEX CONCURRENTLY IF NOT EXISTS idx_scheduled_jobs_due ON chloe_scheduled_jobs(status, run_at)")
+        .execute(db_pool).await?;
+    sqlx::query("CREATE INDEX CONCURRENTLY IF NOT EXISTS idx_provider_recordings_created ON chloe_provider_recordings(created_at)")
+        .execute(db_pool).await?;
+    sqlx::query(
+        "CREATE INDEX CONCURRENTLY IF NOT EXISTS idx_usage_user ON chloe_usage(user_id, day)",
+    )
+    .execute(db_pool)
+    .await?;
+    sqlx::query("CREATE INDEX CONCURRENTLY IF NOT EXISTS idx_messages_channel ON chloe_messages(channel_id, message_id DESC)")
+        .execute(db_pool).await?;
+    sqlx::query("CREATE INDEX CONCURRENTLY IF NOT EXISTS idx_reminders_due ON chloe_reminders(status, remind_at)")
+        .execute(db_pool).await?;
+    sqlx::query("CREATE INDEX CONCURRENTLY IF NOT EXISTS idx_reminders_user ON chloe_reminders(user_id, status)")
+        .execute(db_pool).await?;
+    sqlx::query("CREATE INDEX CONCURRENTLY IF NOT EXISTS idx_scheduled_messages_due ON chloe_scheduled_messages(enabled, next_run_at)")
+        .execute(db_pool).await?;
+    sqlx::query("CREATE INDEX CONCURRENTLY IF NOT EXISTS idx_levels_leaderboard ON chloe_levels(guild_id, xp DESC)")
+        .execute(db_pool).await?;
+    sqlx::query("CREATE INDEX CONCURRENTLY IF NOT EXISTS idx_content_incidents_guild ON chloe_content_incidents(guild_id, created_at DESC)")
+        .execute(db_pool).await?;
+    sqlx::query("CREATE INDEX CONCURRENTLY IF NOT EXISTS idx_saved_conversations_resumed ON chloe_saved_conversations(resumed_in)")
+        .execute(db_pool).await?;
     info!("Performance indexes created successfully");
     Ok(())
 }
 
+/// How many guilds are fetched from discord at once while syncing
+const GUILD_FETCH_CONCURRENCY: usize = 8;
+
+/// The parts of a guild chloe keeps in the database
+#[derive(Debug, Clone)]
+pub struct GuildSnapshot {
+    pub id: GuildId,
+    pub name: String,
+    pub owner_id: UserId,
+}
+
+impl From<&Guild> for GuildSnapshot {
+    fn from(guild: &Guild) -> Self {
+        Self {
+            id: guild.id,
+            name: guild.name.clone(),
+            owner_id: guild.owner_id,
+        }
+    }
+}
+
+impl From<&PartialGuild> for GuildSnapshot {
+    fn from(guild: &PartialGuild) -> Self {
+        Self {
+            id: guild.id,
+            name: guild.name.clone(),
+            owner_id: guild.owner_id,
+        }
+    }
+}
+
+/// Fetch the guilds from discord and store them
 pub async fn sync_guilds(
     db_pool: &PgPool,
     guilds: &[GuildId],
@@ -150,99 +619,154 @@ pub async fn sync_guilds(
 ) -> Result<(), sqlx::Error> {
     info!("Synchronizing {} guilds to database...", guilds.len());
 
-    for guild_id in guilds {
-        if let Ok(guild) = guild_id.to_partial_guild(&ctx.http).await {
-            if let Err(e) = sqlx::query(
-                "INSERT INTO chloe_users (snowflake_id) VALUES ($1) ON CONFLICT (snowflake_id) DO NOTHING"
-            )
-            .bind(guild.owner_id.get() as i64)
-            .execute(db_pool).await {
-                error!("Failed to insert/update user {}: {:?}", guild.owner_id, e);
-                continue;
-            }
-
-            if let Ok(user_row) = sqlx::query("SELECT id FROM chloe_users WHERE snowflake_id = $1")
-                .bind(guild.owner_id.get() as i64)
-                .fetch_one(db_pool)
-                .await
-            {
-                let owner_internal_id: String = user_row.get("id");
-
-                match sqlx::query(
-                    r#"
-                    INSERT INTO chloe_guilds (snowflake_id, name, owner_id)
-                    VALUES ($1, $2, $3)
-                    ON CONFLICT (snowflake_id) 
-                    DO UPDATE SET 
-                        name = EXCLUDED.name,
-                        owner_id = EXCLUDED.owner_id,
-                        modified_at = CURRENT_TIMESTAMP
-                    "#,
-                )
-                .bind(guild_id.get() as i64)
-                .bind(&guild.name)
-                .bind(&owner_internal_id)
-                .execute(db_pool)
-                .await
-                {
-                    Ok(_) => {
-                        info!("Synced guild: {} ({})", guild.name, guild_id);
-
-                        // get the guild's internal ID
-                        if let Ok(guild_row) =
-                            sqlx::query("SELECT id FROM chloe_guilds WHERE snowflake_id = $1")
-                                .bind(guild_id.get() as i64)
-                                .fetch_one(db_pool)
-                                .await
-                        {
-                            let guild_internal_id: String = guild_row.get("id");
-
-                            if let Err(e) = sqlx::query(
-                                r#"
-                                INSERT INTO chloe_guild_users (guild_id, user_id, role)
-                                VALUES ($1, $2, 'admin')
-                                ON CONFLICT (guild_id, user_id) 
-                                DO UPDATE SET 
-                                    role = EXCLUDED.role,
-                                    modified_at = CURRENT_TIMESTAMP
-                                "#,
-                            )
-                            .bind(&guild_internal_id)
-                            .bind(&owner_internal_id)
-                            .execute(db_pool)
-                            .await
-                            {
-                                error!(
-                                    "Failed to add owner as admin to guild {}: {:?}",
-                                    guild_id, e
-                                );
-                            } else {
-                                info!("Added guild owner as admin for guild: {}", guild.name);
-                            }
-
-                            // create default settings if they don't exist
-                            if let Err(e) =
-                                create_default_settings(db_pool, &guild_internal_id).await
-                            {
-                                error!(
-                                    "Failed to create default settings for guild {}: {:?}",
-                                    guild_id, e
-                                );
-                            }
-                        }
-                    }
-                    Err(e) => {
-                        error!("Failed to sync guild {}: {:?}", guild_id, e);
-                    }
+    let mut guild_ids = guilds.to_vec();
+    guild_ids.sort_unstable();
+    guild_ids.dedup();
+
+    // serenity queues requests behind discord's rate limits, the bound keeps a big
+    // startup from piling hundreds of them up at once
+    let fetched: Vec<GuildSnapshot> = stream::iter(guild_ids)
+        .map(|guild_id| async move {
+            match guild_id.to_partial_guild(&ctx.http).await {
+                Ok(guild) => Some(GuildSnapshot::from(&guild)),
+                Err(e) => {
+                    error!("Failed to get guild info for {}: {:?}", guild_id, e);
+                    None
                 }
             }
-        } else {
-            error!("Failed to get guild info for {}", guild_id);
-        }
+        })
+        .buffer_unordered(GUILD_FETCH_CONCURRENCY)
+        .filter_map(|guild| async move { guild })
+        .collect()
+        .await;
+
+    let changed = upsert_guilds(db_pool, &fetched).await?;
+    info!(
+        "Guild synchronization complete: {} of {} guilds fetched, {} new or changed",
+        fetched.len(),
+        guilds.len(),
+        changed
+    );
+    Ok(())
+}
+
+/// Startup consistency check. Only guilds joined while chloe was offline are fetched,
+/// the rest are kept current by `guild_create` and `guild_update` as they arrive.
+pub async fn sync_missing_guilds(
+    db_pool: &PgPool,
+    guilds: &[GuildId],
+    ctx: &serenity::prelude::Context,
+) -> Result<usize, sqlx::Error> {
+    let snowflakes: Vec<i64> = guilds.iter().map(|id| id.get() as i64).collect();
+    let known: Vec<i64> = sqlx::query_scalar(
+        "SELECT snowflake_id FROM chloe_guilds WHERE snowflake_id = ANY($1::bigint[])",
+    )
+    .bind(&snowflakes)
+    .fetch_all(db_pool)
+    .await?;
+
+    let missing: Vec<GuildId> = guilds
+        .iter()
+        .filter(|id| !known.contains(&(id.get() as i64)))
+        .copied()
+        .collect();
+    info!(
+        "Guild consistency check: {} of {} guilds missing from the database",
+        missing.len(),
+        guilds.len()
+    );
+
+    if !missing.is_empty() {
+        sync_guilds(db_pool, &missing, ctx).await?;
     }
+    Ok(missing.len())
+}
 
-    info!("Guild synchronization complete");
-    Ok(())
+/// Store the guilds, only touching the ones that are new or whose name or owner changed.
+/// Returns how many that was.
+pub async fn upsert_guilds(db_pool: &PgPool, guilds: &[GuildSnapshot]) -> Result<usize, sqlx::Error> {
+    let mut guilds = guilds.to_vec();
+    guilds.sort_unstable_by_key(|g| g.id);
+    guilds.dedup_by_key(|g| g.id);
+    if guilds.is_empty() {
+        return Ok(0);
+    }
+
+    let guild_snowflakes: Vec<i64> = guilds.iter().map(|g| g.id.get() as i64).collect();
+    let names: Vec<String> = guilds.iter().map(|g| g.name.clone()).collect();
+    let owner_snowflakes: Vec<i64> = guilds.iter().map(|g| g.owner_id.get() as i64).collect();
+
+    let mut tx = db_pool.begin().await?;
+
+    sqlx::query(
+        "INSERT INTO chloe_users (snowflake_id) SELECT DISTINCT * FROM UNNEST($1::bigint[]) ON CONFLICT (snowflake_id) DO NOTHING",
+    )
+    .bind(&owner_snowflakes)
+    .execute(&mut *tx)
+    .await?;
+
+    // unchanged guilds are skipped by the WHERE, so only new and changed rows come back
+    let synced = sqlx::query(
+        r#"
+        INSERT INTO chloe_guilds (snowflake_id, name, owner_id, synced_at)
+        SELECT g.snowflake_id, g.name, u.id, CURRENT_TIMESTAMP
+        FROM UNNEST($1::bigint[], $2::text[], $3::bigint[]) AS g(snowflake_id, name, owner_snowflake)
+        JOIN chloe_users u ON u.snowflake_id = g.owner_snowflake
+        ON CONFLICT (snowflake_id)
+        DO UPDATE SET
+            name = EXCLUDED.name,
+            owner_id = EXCLUDED.owner_id,
+            synced_at = CURRENT_TIMESTAMP,
+            modified_at = CURRENT_TIMESTAMP
+        WHERE chloe_guilds.name IS DISTINCT FROM EXCLUDED.name
+            OR chloe_guilds.owner_id IS DISTINCT FROM EXCLUDED.owner_id
+        RETURNING id, owner_id
+        "#,
+    )
+    .bind(&guild_snowflakes)
+    .bind(&names)
+    .bind(&owner_snowflakes)
+    .fetch_all(&mut *tx)
+    .await?;
+
+    if synced.is_empty() {
+        tx.commit().await?;
+        return Ok(0);
+    }
+
+    let guild_internal_ids: Vec<String> = synced.iter().map(|row| row.get("id")).collect();
+    let owner_internal_ids: Vec<String> = synced.iter().map(|row| row.get("owner_id")).collect();
+
+    // owners are admins of their guild
+    sqlx::query(
+        r#"
+        INSERT INTO chloe_guild_users (guild_id, user_id, role)
+        SELECT guild_id, user_id, 'admin' FROM UNNEST($1::text[], $2::text[]) AS o(guild_id, user_id)
+        ON CONFLICT (guild_id, user_id)
+        DO UPDATE SET
+            role = EXCLUDED.role,
+            modified_at = CURRENT_TIMESTAMP
+        "#,
+    )
+    .bind(&guild_internal_ids)
+    .bind(&owner_internal_ids)
+    .execute(&mut *tx)
+    .await?;
+
+    sqlx::query(
+        r#"
+        INSERT INTO chloe_guilds_settings (guild_id, settings)
+        SELECT guild_id, $2 FROM UNNEST($1::text[]) AS g(guild_id)
+        ON CONFLICT (guild_id) DO NOTHING
+        "#,
+    )
+    .bind(&guild_internal_ids)
+    .bind(default_settings())
+    .execute(&mut *tx)
+    .await?;
+
+    tx.commit().await?;
+    Ok(guild_internal_ids.len())
 }
 
 pub async fn ensure_global_settings(db_pool: &PgPool) -> Result<(), sqlx::Error> {
@@ -272,15 +796,17 @@ pub async fn ensure_global_settings(db_pool: &PgPool) -> Result<(), sqlx::Error>
     Ok(())
 }
 
+fn default_settings() -> serde_json::Value {
+    json!({
+        "ping_reply": false,
+        "llm": false
+    })
+}
+
 pub async fn create_default_settings(
     db_pool: &PgPool,
     guild_internal_id: &str,
 ) -> Result<(), sqlx::Error> {
-    let default_settings = json!({
-        "ping_reply": false,
-        "llm": false
-    });
-
     let existing_settings = sqlx::query("SELECT id FROM chloe_guilds_settings WHERE guild_id = $1")
         .bind(guild_internal_id)
         .fetch_optional(db_pool)
@@ -289,7 +815,7 @@ pub async fn create_default_settings(
     if existing_settings.is_none() {
         sqlx::query("INSERT INTO chloe_guilds_settings (guild_id, settings) VALUES ($1, $2)")
             .bind(guild_internal_id)
-            .bind(&default_settings)
+            .bind(default_settings())
             .execute(db_pool)
             .await?;
 
@@ -0,0 +1,390 @@
+use serde_json::json;
+use serenity::builder::{CreateAllowedMentions, CreateMessage};
+use serenity::http::Http;
+use serenity::model::id::ChannelId;
+use std::collections::{HashMap, VecDeque};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tokio::time::sleep;
+use tracing::{error, info, warn};
+
+/// How often the window is checked against the thresholds
+const CHECK_INTERVAL: Duration = Duration::from_secs(60);
+
+const DEFAULT_WINDOW_MINUTES: u64 = 60;
+
+/// Requests needed in the window before an error rate means anything
+const MIN_REQUESTS_FOR_ERROR_RATE: usize = 10;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum AlertKind {
+    TokenSpend,
+    Cost,
+    ErrorRate,
+    Latency,
+}
+
+/// Limits over a rolling window, from `ALERT_*` environment variables. Only the limits
+/// that are set are checked.
+#[derive(Debug, Clone, PartialEq)]
+pub struct AlertThresholds {
+    pub window: Duration,
+    pub max_tokens: Option<i64>,
+    pub max_cost_usd: Option<f64>,
+    /// Share of failed requests, 0.2 for 20%
+    pub max_error_rate: Option<f64>,
+    pub max_p95_latency: Option<Duration>,
+}
+
+impl AlertThresholds {
+    /// None when no threshold is configured
+    fn from_vars(var: impl Fn(&str) -> Option<String>) -> Option<Self> {
+        let number = |key: &str| var(key).and_then(|value| value.trim().parse::<f64>().ok());
+        let positive = |key: &str| number(key).filter(|&value| value > 0.0);
+
+        let thresholds = Self {
+            window: Duration::from_secs(
+                positive("ALERT_WINDOW_MINUTES")
+                    .map(|minutes| minutes as u64)
+                    .unwrap_or(DEFAULT_WINDOW_MINUTES)
+                    .max(1)
+                    * 60,
+            ),
+            max_tokens: positive("ALERT_MAX_TOKENS").map(|tokens| tokens as i64),
+            max_cost_usd: positive("ALERT_MAX_COST_USD"),
+            max_error_rate: positive("ALERT_MAX_ERROR_RATE").map(|rate| rate.min(1.0)),
+            max_p95_latency: positive("ALERT_MAX_P95_LATENCY_MS")
+                .map(|ms| Duration::from_millis(ms as u64)),
+        };
+        let any = thresholds.max_tokens.is_some()
+            || thresholds.max_cost_usd.is_some()
+            || thresholds.max_error_rate.is_some()
+            || thresholds.max_p95_latency.is_some();
+        any.then_some(thresholds)
+    }
+
+    /// Alerts whose threshold the window crosses, with a line describing each
+    fn crossed(&self, stats: &WindowStats) -> Vec<(AlertKind, String)> {
+        let window = format!("in the last {} minutes", self.window.as_secs() / 60);
+        let mut crossed = Vec::new();
+        if let Some(max) = self.max_tokens.filter(|&max| stats.tokens >= max) {
+            crossed.push((
+                AlertKind::TokenSpend,
+                format!(
+                    "🔥 **token spend**: {} tokens {} (limit {})",
+                    stats.tokens, window, max
+                ),
+            ));
+        }
+        if let Some(max) = self.max_cost_usd.filter(|&max| stats.cost_usd >= max) {
+            crossed.push((
+                AlertKind::Cost,
+                format!(
+                    "💸 **cost**: ${:.2} {} (limit ${:.2})",
+                    stats.cost_usd, window, max
+                ),
+            ));
+        }
+        if let Some(max) = self.max_error_rate {
+            let rate = stats.error_rate();
+            if stats.requests >= MIN_REQUESTS_FOR_ERROR_RATE && rate >= max {
+                crossed.push((
+                    AlertKind::ErrorRate,
+                    format!(
+                        "🚨 **error rate**: {:.0}% of {} requests failed {} (limit {:.0}%)",
+                        rate * 100.0,
+                        stats.requests,
+                        window,
+                        max * 100.0
+                    ),
+                ));
+            }
+        }
+        let slow = self
+            .max_p95_latency
+            .zip(stats.p95_latency)
+            .filter(|(max, p95)| p95 >= max);
+        if let Some((max, p95)) = slow {
+            crossed.push((
+                AlertKind::Latency,
+                format!(
+                    "🐢 **latency**: p95 {}ms {} (limit {}ms)",
+                    p95.as_millis(),
+                    window,
+                    max.as_millis()
+                ),
+            ));
+        }
+        crossed
+    }
+}
+
+/// One provider request as the monitor sees it
+#[derive(Debug, Clone, Copy)]
+struct Sample {
+    at: Instant,
+    tokens: i64,
+    cost_usd: f64,
+    failed: bool,
+    latency: Duration,
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+struct WindowStats {
+    requests: usize,
+    failures: usize,
+    tokens: i64,
+    cost_usd: f64,
+    p95_latency: Option<Duration>,
+}
+
+impl WindowStats {
+    fn from_samples<'a>(samples: impl Iterator<Item = &'a Sample>) -> Self {
+        let mut stats = Self::default();
+        let mut latencies = Vec::new();
+        for sample in samples {
+            stats.requests += 1;
+            stats.failures += sample.failed as usize;
+            stats.tokens += sample.tokens;
+            stats.cost_usd += sample.cost_usd;
+            latencies.push(sample.latency);
+        }
+        latencies.sort();
+        stats.p95_latency = latencies
+            .len()
+            .checked_sub(1)
+            .map(|last| latencies[(last as f64 * 0.95).round() as usize]);
+        stats
+    }
+
+    fn error_rate(&self) -> f64 {
+        if self.requests == 0 {
+            return 0.0;
+        }
+        self.failures as f64 / self.requests as f64
+    }
+}
+
+/// Watches token spend, cost, error rate and latency of provider requests over a rolling
+/// window, and tells the ops channel (`ALERT_CHANNEL_ID`) and/or a webhook
+/// (`ALERT_WEBHOOK_URL`) when a threshold is crossed. Each alert fires at most once per
+/// window.
+pub struct AlertMonitor {
+    thresholds: AlertThresholds,
+    channel_id: Option<ChannelId>,
+    webhook_url: Option<String>,
+    client: reqwest::Client,
+    samples: Mutex<VecDeque<Sample>>,
+    last_fired: Mutex<HashMap<AlertKind, Instant>>,
+}
+
+impl AlertMonitor {
+    /// None unless a threshold and somewhere to send alerts are configured
+    pub fn from_env() -> Option<Self> {
+        let thresholds = AlertThresholds::from_vars(|key| std::env::var(key).ok())?;
+        let channel_id = std::env::var("ALERT_CHANNEL_ID")
+            .ok()
+            .and_then(|id| id.trim().parse().ok())
+            .filter(|&id| id > 0)
+            .map(ChannelId::new);
+        let webhook_url = std::env::var("ALERT_WEBHOOK_URL")
+            .ok()
+            .map(|url| url.trim().to_string())
+            .filter(|url| !url.is_empty());
+        if channel_id.is_none() && webhook_url.is_none() {
+            warn!(
+                event = "alerting_without_destination",
+                "Alert thresholds are set but neither ALERT_CHANNEL_ID nor ALERT_WEBHOOK_URL, alerts are off"
+            );
+            return None;
+        }
+
+        info!(
+            event = "alerting_enabled",
+            window_minutes = thresholds.window.as_secs() / 60,
+            "Usage alerting enabled"
+        );
+        Some(Self {
+            thresholds,
+            channel_id,
+            webhook_url,
+            client: reqwest::Client::new(),
+            samples: Mutex::new(VecDeque::new()),
+            last_fired: Mutex::new(HashMap::new()),
+        })
+    }
+
+    /// Count one provider request, failed ones without tokens
+    pub fn record(&self, tokens: i64, cost_usd: f64, failed: bool, latency: Duration) {
+        let now = Instant::now();
+        let mut samples = self.samples.lock().unwrap_or_else(|e| e.into_inner());
+        samples.push_back(Sample {
+            at: now,
+            tokens,
+            cost_usd,
+            failed,
+            latency,
+        });
+        // requests older than the window never count again
+        while samples
+            .front()
+            .is_some_and(|sample| now.duration_since(sample.at) > self.thresholds.window)
+        {
+            samples.pop_front();
+        }
+    }
+
+    pub async fn start(&self, http: Arc<Http>) {
+        info!(event = "alert_monitor_started", "Starting alert monitor");
+        loop {
+            sleep(CHECK_INTERVAL).await;
+            for message in self.due_alerts(Instant::now()) {
+                self.send(&http, &message).await;
+            }
+        }
+    }
+
+    /// Crossed thresholds that haven't fired within the last window
+    fn due_alerts(&self, now: Instant) -> Vec<String> {
+        let stats = {
+            let samples = self.samples.lock().unwrap_or_else(|e| e.into_inner());
+            WindowStats::from_samples(
+                samples
+                    .iter()
+                    .filter(|sample| now.duration_since(sample.at) <= self.thresholds.window),
+            )
+        };
+
+        let mut last_fired = self.last_fired.lock().unwrap_or_else(|e| e.into_inner());
+        self.thresholds
+            .crossed(&stats)
+            .into_iter()
+            .filter(|(kind, _)| {
+                let due = last_fired
+                    .get(kind)
+                    .is_none_or(|fired| now.duration_since(*fired) >= self.thresholds.window);
+                if due {
+                    last_fired.insert(*kind, now);
+                }
+                due
+            })
+            .map(|(_, message)| message)
+            .collect()
+    }
+
+    async fn send(&self, http: &Http, message: &str) {
+        warn!(event = "usage_alert", alert = %message, "Usage threshold crossed");
+
+        if let Some(channel_id) = self.channel_id {
+            let sent = channel_id
+                .send_message(
+                    http,
+                    CreateMessage::new()
+                        .content(message)
+                        .allowed_mentions(CreateAllowedMentions::new()),
+                )
+                .await;
+            if let Err(e) = sent {
+                error!(
+                    event = "alert_channel_send_failed",
+                    channel_id = %channel_id,
+                    error = ?e,
+                    "Failed to post alert to the ops channel"
+                );
+            }
+        }
+
+        if let Some(webhook_url) = &self.webhook_url {
+            // discord webhooks read `content`, slack-style ones `text`
+            let sent = self
+                .client
+                .post(webhook_url)
+                .json(&json!({ "content": message, "text": message }))
+                .send()
+                .await
+                .and_then(|response| response.error_for_status());
+            if let Err(e) = sent {
+                error!(
+                    event = "alert_webhook_failed",
+                    error = %e,
+                    "Failed to post alert to the webhook"
+                );
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn vars(pairs: &[(&str, &str)]) -> impl Fn(&str) -> Option<String> {
+        let pairs: HashMap<String, String> = pairs
+            .iter()
+            .map(|(key, value)| (key.to_string(), value.to_string()))
+            .collect();
+        move |key| pairs.get(key).cloned()
+    }
+
+    fn sample(tokens: i64, failed: bool, latency_ms: u64) -> Sample {
+        Sample {
+            at: Instant::now(),
+            tokens,
+            cost_usd: tokens as f64 / 1_000_000.0,
+            failed,
+            latency: Duration::from_millis(latency_ms),
+        }
+    }
+
+    #[test]
+    fn test_thresholds_from_vars() {
+        assert_eq!(AlertThresholds::from_vars(vars(&[])), None);
+        assert_eq!(
+            AlertThresholds::from_vars(vars(&[("ALERT_WINDOW_MINUTES", "15")])),
+            None
+        );
+
+        let thresholds = AlertThresholds::from_vars(vars(&[
+            ("ALERT_WINDOW_MINUTES", "15"),
+            ("ALERT_MAX_TOKENS", "1000000"),
+            ("ALERT_MAX_ERROR_RATE", "0.25"),
+            ("ALERT_MAX_P95_LATENCY_MS", "bogus"),
+        ]))
+        .unwrap();
+        assert_eq!(thresholds.window, Duration::from_secs(15 * 60));
+        assert_eq!(thresholds.max_tokens, Some(1_000_000));
+        assert_eq!(thresholds.max_error_rate, Some(0.25));
+        assert_eq!(thresholds.max_p95_latency, None);
+    }
+
+    #[test]
+    fn test_crossed_thresholds() {
+        let thresholds = AlertThresholds {
+            window: Duration::from_secs(3600),
+            max_tokens: Some(10_000),
+            max_cost_usd: None,
+            max_error_rate: Some(0.2),
+            max_p95_latency: Some(Duration::from_secs(10)),
+        };
+
+        let mut samples: Vec<Sample> = (0..9).map(|_| sample(1_000, false, 2_000)).collect();
+        samples.push(sample(0, true, 30_000));
+        samples.push(sample(0, true, 30_000));
+        samples.push(sample(2_000, false, 1_000));
+        let stats = WindowStats::from_samples(samples.iter());
+        assert_eq!(stats.requests, 12);
+        assert_eq!(stats.tokens, 11_000);
+        assert_eq!(stats.p95_latency, Some(Duration::from_secs(30)));
+
+        let kinds: Vec<AlertKind> = thresholds
+            .crossed(&stats)
+            .into_iter()
+            .map(|(kind, _)| kind)
+            .collect();
+        assert_eq!(kinds, vec![AlertKind::TokenSpend, AlertKind::Latency]);
+
+        // too few requests for the error rate to count
+        let stats = WindowStats::from_samples([sample(0, true, 100)].iter());
+        assert!(thresholds.crossed(&stats).is_empty());
+    }
+}
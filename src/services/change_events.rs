@@ -0,0 +1,162 @@
+use chrono::Utc;
+use redis::{AsyncCommands, Client};
+use serde::Serialize;
+use serde_json::{Value, json};
+use tracing::{error, info, warn};
+
+/// Redis pub/sub channel the events are published on
+pub const CHANGE_EVENTS_CHANNEL: &str = "chloe-events";
+
+/// Something the dashboard or audit tooling may want to react to. Guild ids are strings,
+/// snowflakes don't fit in a javascript number.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ChangeEvent {
+    /// A global prompt version became the active one
+    PromptActivated { prompt_id: String },
+    /// A guild saved a prompt of its own, which is active right away
+    GuildPromptActivated {
+        guild_id: String,
+        version: i32,
+        created_by: Option<String>,
+    },
+    /// A guild went back to the global prompt
+    GuildPromptDeactivated { guild_id: String },
+    /// Settings merged into a guild's settings, `changes` holds the new values
+    GuildSettingsUpdated {
+        guild_id: String,
+        keys: Vec<String>,
+        changes: Value,
+    },
+}
+
+impl ChangeEvent {
+    pub fn guild_settings_updated(guild_id: i64, updates: &Value) -> Self {
+        let keys = updates
+            .as_object()
+            .map(|updates| updates.keys().cloned().collect())
+            .unwrap_or_default();
+        ChangeEvent::GuildSettingsUpdated {
+            guild_id: guild_id.to_string(),
+            keys,
+            changes: updates.clone(),
+        }
+    }
+
+    /// What's published, the event with the time it happened
+    pub fn payload(&self) -> Value {
+        let mut payload = json!(self);
+        payload["at"] = json!(Utc::now().to_rfc3339());
+        payload
+    }
+
+    fn name(&self) -> &'static str {
+        match self {
+            ChangeEvent::PromptActivated { .. } => "prompt_activated",
+            ChangeEvent::GuildPromptActivated { .. } => "guild_prompt_activated",
+            ChangeEvent::GuildPromptDeactivated { .. } => "guild_prompt_deactivated",
+            ChangeEvent::GuildSettingsUpdated { .. } => "guild_settings_updated",
+        }
+    }
+}
+
+/// Publishes prompt and settings changes on `chloe-events`, and posts them to
+/// `CHANGE_WEBHOOK_URL` when it's set, so nothing has to poll for them. Publishing never
+/// fails the change itself.
+pub struct ChangeEvents {
+    redis_client: Client,
+    webhook_url: Option<String>,
+    client: reqwest::Client,
+}
+
+impl ChangeEvents {
+    pub fn from_env(redis_client: Client) -> Self {
+        let webhook_url = std::env::var("CHANGE_WEBHOOK_URL")
+            .ok()
+            .map(|url| url.trim().to_string())
+            .filter(|url| !url.is_empty());
+        if webhook_url.is_some() {
+            info!(
+                event = "change_webhook_enabled",
+                "Posting prompt and settings changes to the change webhook"
+            );
+        }
+        Self {
+            redis_client,
+            webhook_url,
+            client: reqwest::Client::new(),
+        }
+    }
+
+    pub async fn publish(&self, event: ChangeEvent) {
+        let payload = event.payload();
+
+        match self.redis_client.get_multiplexed_async_connection().await {
+            Ok(mut conn) => {
+                let published: Result<i64, _> = conn
+                    .publish(CHANGE_EVENTS_CHANNEL, payload.to_string())
+                    .await;
+                if let Err(e) = published {
+                    warn!(
+                        event = "change_event_publish_failed",
+                        change = event.name(),
+                        error = %e,
+                        "Failed to publish change event"
+                    );
+                }
+            }
+            Err(e) => warn!(
+                event = "change_event_publish_failed",
+                change = event.name(),
+                error = %e,
+                "Failed to connect to redis to publish change event"
+            ),
+        }
+
+        // a slow webhook shouldn't hold up the command that made the change
+        if let Some(webhook_url) = self.webhook_url.clone() {
+            let client = self.client.clone();
+            let change = event.name();
+            tokio::spawn(async move {
+                let sent = client
+                    .post(&webhook_url)
+                    .json(&payload)
+                    .send()
+                    .await
+                    .and_then(|response| response.error_for_status());
+                if let Err(e) = sent {
+                    error!(
+                        event = "change_webhook_failed",
+                        change = change,
+                        error = %e,
+                        "Failed to post change event to the webhook"
+                    );
+                }
+            });
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_payload() {
+        let event =
+            ChangeEvent::guild_settings_updated(123, &json!({ "llm": true, "quietHours": null }));
+        let payload = event.payload();
+        assert_eq!(payload["type"], "guild_settings_updated");
+        assert_eq!(payload["guild_id"], "123");
+        assert_eq!(payload["keys"], json!(["llm", "quietHours"]));
+        assert_eq!(payload["changes"]["llm"], true);
+        assert!(payload["at"].is_string());
+
+        let payload = ChangeEvent::PromptActivated {
+            prompt_id: "abc".to_string(),
+        }
+        .payload();
+        assert_eq!(payload["type"], "prompt_activated");
+        assert_eq!(payload["prompt_id"], "abc");
+    }
+}
@@ -0,0 +1,232 @@
+use crate::services::content_safety::HarmCategory;
+use crate::services::provider_recorder::prompt_hash;
+use chrono::{DateTime, Utc};
+use serde_json::Value;
+use serenity::builder::{CreateAllowedMentions, CreateMessage};
+use serenity::http::Http;
+use serenity::model::id::ChannelId;
+use sqlx::{PgPool, Row};
+use tracing::{error, info};
+
+/// Guild setting naming the channel where moderators hear about blocked replies, e.g.
+/// `{"logChannel": "123"}`. Incidents are recorded either way.
+pub const CONTENT_REPORTS_SETTING: &str = "contentReports";
+
+/// Incidents shown by `/contentfilter incidents`
+pub const RECENT_INCIDENTS_LIMIT: i64 = 10;
+
+/// The moderator channel from the `contentReports` setting, if one is set
+pub fn report_channel(setting: Option<&Value>) -> Option<u64> {
+    setting
+        .and_then(|setting| setting.get("logChannel"))
+        .and_then(|value| match value {
+            Value::String(id) => id.trim().parse().ok(),
+            value => value.as_u64(),
+        })
+}
+
+/// A reply the provider's content filter blocked. Only a hash of the prompt is kept,
+/// enough to tell repeats apart without storing what anyone said.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ContentIncident {
+    pub guild_id: Option<u64>,
+    pub channel_id: Option<u64>,
+    pub model: String,
+    /// The provider's reason, e.g. `SAFETY` or `PROHIBITED_CONTENT`
+    pub block_reason: String,
+    /// The provider's harm category, when it said which one
+    pub category: Option<String>,
+    pub prompt_hash: String,
+    pub created_at: Option<DateTime<Utc>>,
+}
+
+impl ContentIncident {
+    pub fn new(
+        guild_id: Option<u64>,
+        channel_id: Option<u64>,
+        model: &str,
+        block_reason: &str,
+        category: Option<&str>,
+        prompt: &str,
+    ) -> Self {
+        Self {
+            guild_id,
+            channel_id,
+            model: model.to_string(),
+            block_reason: block_reason.to_string(),
+            category: category.map(str::to_string),
+            prompt_hash: prompt_hash(prompt),
+            created_at: None,
+        }
+    }
+
+    /// What was blocked, e.g. `hate speech (SAFETY)`
+    pub fn describe(&self) -> String {
+        match self.category.as_deref() {
+            Some(category) => format!(
+                "{} ({})",
+                HarmCategory::from_gemini_name(category)
+                    .map(|category| category.label())
+                    .unwrap_or(category),
+                self.block_reason
+            ),
+            None => self.block_reason.clone(),
+        }
+    }
+
+    /// Enough of the hash to recognize repeats at a glance
+    pub fn short_hash(&self) -> &str {
+        &self.prompt_hash[..self.prompt_hash.len().min(12)]
+    }
+}
+
+pub struct ContentIncidentService {
+    db_pool: PgPool,
+}
+
+impl ContentIncidentService {
+    pub fn new(db_pool: PgPool) -> Self {
+        Self { db_pool }
+    }
+
+    /// Store the incident in the background so the reply to the user never waits on it
+    pub fn record(&self, incident: ContentIncident) {
+        let db_pool = self.db_pool.clone();
+        tokio::spawn(async move {
+            let result = sqlx::query(
+                "INSERT INTO chloe_content_incidents (guild_id, channel_id, model, block_reason, category, prompt_hash)
+                 VALUES ($1, $2, $3, $4, $5, $6)",
+            )
+            .bind(incident.guild_id.map(|id| id as i64))
+            .bind(incident.channel_id.map(|id| id as i64))
+            .bind(&incident.model)
+            .bind(&incident.block_reason)
+            .bind(&incident.category)
+            .bind(&incident.prompt_hash)
+            .execute(&db_pool)
+            .await;
+
+            if let Err(e) = result {
+                error!(
+                    event = "content_incident_record_failed",
+                    guild_id = ?incident.guild_id,
+                    error = ?e,
+                    "Failed to record content filter incident"
+                );
+            }
+        });
+    }
+
+    /// The guild's latest incidents, newest first
+    pub async fn recent(
+        &self,
+        guild_id: u64,
+        limit: i64,
+    ) -> Result<Vec<ContentIncident>, sqlx::Error> {
+        let rows = sqlx::query(
+            "SELECT guild_id, channel_id, model, block_reason, category, prompt_hash, created_at
+             FROM chloe_content_incidents
+             WHERE guild_id = $1
+             ORDER BY created_at DESC
+             LIMIT $2",
+        )
+        .bind(guild_id as i64)
+        .bind(limit)
+        .fetch_all(&self.db_pool)
+        .await?;
+
+        Ok(rows
+            .into_iter()
+            .map(|row| ContentIncident {
+                guild_id: row.get::<Option<i64>, _>("guild_id").map(|id| id as u64),
+                channel_id: row.get::<Option<i64>, _>("channel_id").map(|id| id as u64),
+                model: row.get("model"),
+                block_reason: row.get("block_reason"),
+                category: row.get("category"),
+                prompt_hash: row.get("prompt_hash"),
+                created_at: row.get("created_at"),
+            })
+            .collect())
+    }
+}
+
+/// Tell moderators a reply was blocked, linking the message that asked for it
+pub async fn notify_moderators(
+    http: &Http,
+    log_channel_id: u64,
+    incident: &ContentIncident,
+    message_link: &str,
+) {
+    let channel = incident
+        .channel_id
+        .map(|id| format!(" in <#{}>", id))
+        .unwrap_or_default();
+    let notice = CreateMessage::new()
+        .content(format!(
+            "🚫 the content filter blocked a reply{}: {}, prompt `{}`\n{}",
+            channel,
+            incident.describe(),
+            incident.short_hash(),
+            message_link
+        ))
+        .allowed_mentions(CreateAllowedMentions::new());
+    match ChannelId::new(log_channel_id)
+        .send_message(http, notice)
+        .await
+    {
+        Ok(_) => info!(
+            event = "content_incident_reported",
+            guild_id = ?incident.guild_id,
+            log_channel_id = log_channel_id,
+            "Told moderators about a blocked reply"
+        ),
+        Err(e) => error!(
+            event = "content_incident_report_failed",
+            log_channel_id = log_channel_id,
+            error = ?e,
+            "Failed to notify moderators of a blocked reply"
+        ),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_report_channel() {
+        assert_eq!(
+            report_channel(Some(&json!({"logChannel": "123"}))),
+            Some(123)
+        );
+        assert_eq!(report_channel(Some(&json!({"logChannel": 456}))), Some(456));
+        assert_eq!(report_channel(Some(&json!({"logChannel": "nope"}))), None);
+        assert_eq!(report_channel(None), None);
+    }
+
+    #[test]
+    fn test_incident_describe() {
+        let incident = ContentIncident::new(
+            Some(1),
+            Some(2),
+            "gemini-2.5-flash",
+            "SAFETY",
+            Some("HARM_CATEGORY_HATE_SPEECH"),
+            "hi chloe",
+        );
+        assert_eq!(incident.describe(), "hate speech (SAFETY)");
+        assert_eq!(incident.prompt_hash.len(), 64);
+        assert_eq!(incident.short_hash().len(), 12);
+
+        let incident = ContentIncident::new(
+            None,
+            None,
+            "gemini-2.5-flash",
+            "PROHIBITED_CONTENT",
+            None,
+            "",
+        );
+        assert_eq!(incident.describe(), "PROHIBITED_CONTENT");
+    }
+}
@@ -0,0 +1,237 @@
+use crate::services::gemini_types::SafetySetting;
+use serde_json::{Map, Value};
+
+/// Guild setting with a block threshold per harm category, e.g.
+/// `{"harassment": "medium", "dangerousContent": "high"}`. Categories left out keep chloe's
+/// defaults and none go below the global floor superadmins set. Only gemini takes
+/// thresholds, ollama models have no equivalent and answer unfiltered.
+pub const CONTENT_SAFETY_SETTING: &str = "contentSafety";
+
+/// How likely a harm has to be before the provider blocks the response, weakest first
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum BlockThreshold {
+    None,
+    OnlyHigh,
+    MediumAndAbove,
+    LowAndAbove,
+}
+
+impl BlockThreshold {
+    /// From the short names used in settings, or gemini's own
+    pub fn from_str(s: &str) -> Option<Self> {
+        match s.trim().to_lowercase().as_str() {
+            "none" | "off" | "block_none" => Some(Self::None),
+            "high" | "only_high" | "block_only_high" => Some(Self::OnlyHigh),
+            "medium" | "medium_and_above" | "block_medium_and_above" => Some(Self::MediumAndAbove),
+            "low" | "low_and_above" | "block_low_and_above" => Some(Self::LowAndAbove),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::None => "none",
+            Self::OnlyHigh => "high",
+            Self::MediumAndAbove => "medium",
+            Self::LowAndAbove => "low",
+        }
+    }
+
+    pub fn gemini_name(&self) -> &'static str {
+        match self {
+            Self::None => "BLOCK_NONE",
+            Self::OnlyHigh => "BLOCK_ONLY_HIGH",
+            Self::MediumAndAbove => "BLOCK_MEDIUM_AND_ABOVE",
+            Self::LowAndAbove => "BLOCK_LOW_AND_ABOVE",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HarmCategory {
+    Harassment,
+    HateSpeech,
+    SexuallyExplicit,
+    DangerousContent,
+}
+
+impl HarmCategory {
+    pub const ALL: [Self; 4] = [
+        Self::Harassment,
+        Self::HateSpeech,
+        Self::SexuallyExplicit,
+        Self::DangerousContent,
+    ];
+
+    /// Key in the `contentSafety` setting
+    pub fn key(&self) -> &'static str {
+        match self {
+            Self::Harassment => "harassment",
+            Self::HateSpeech => "hateSpeech",
+            Self::SexuallyExplicit => "sexuallyExplicit",
+            Self::DangerousContent => "dangerousContent",
+        }
+    }
+
+    /// How the category reads to moderators
+    pub fn label(&self) -> &'static str {
+        match self {
+            Self::Harassment => "harassment",
+            Self::HateSpeech => "hate speech",
+            Self::SexuallyExplicit => "sexually explicit",
+            Self::DangerousContent => "dangerous content",
+        }
+    }
+
+    pub fn gemini_name(&self) -> &'static str {
+        match self {
+            Self::Harassment => "HARM_CATEGORY_HARASSMENT",
+            Self::HateSpeech => "HARM_CATEGORY_HATE_SPEECH",
+            Self::SexuallyExplicit => "HARM_CATEGORY_SEXUALLY_EXPLICIT",
+            Self::DangerousContent => "HARM_CATEGORY_DANGEROUS_CONTENT",
+        }
+    }
+
+    pub fn from_gemini_name(name: &str) -> Option<Self> {
+        Self::ALL
+            .into_iter()
+            .find(|category| category.gemini_name() == name)
+    }
+}
+
+/// A block threshold for every harm category
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SafetyThresholds([BlockThreshold; 4]);
+
+impl Default for SafetyThresholds {
+    /// What chloe blocks when nobody configured anything
+    fn default() -> Self {
+        Self([
+            BlockThreshold::None,
+            BlockThreshold::MediumAndAbove,
+            BlockThreshold::None,
+            BlockThreshold::None,
+        ])
+    }
+}
+
+impl SafetyThresholds {
+    /// The global floor until a superadmin sets one: hate speech is always blocked when
+    /// it's clearly hate speech
+    pub fn default_floor() -> Self {
+        Self([
+            BlockThreshold::None,
+            BlockThreshold::OnlyHigh,
+            BlockThreshold::None,
+            BlockThreshold::None,
+        ])
+    }
+
+    pub fn get(&self, category: HarmCategory) -> BlockThreshold {
+        self.0[category as usize]
+    }
+
+    pub fn set(&mut self, category: HarmCategory, threshold: BlockThreshold) {
+        self.0[category as usize] = threshold;
+    }
+
+    /// From the guild setting or the stored floor, anything left out or unreadable
+    /// keeps `base`
+    pub fn from_setting(setting: Option<&Value>, base: Self) -> Self {
+        let mut thresholds = base;
+        for category in HarmCategory::ALL {
+            if let Some(threshold) = setting
+                .and_then(|setting| setting.get(category.key()))
+                .and_then(Value::as_str)
+                .and_then(BlockThreshold::from_str)
+            {
+                thresholds.set(category, threshold);
+            }
+        }
+        thresholds
+    }
+
+    pub fn to_setting(&self) -> Value {
+        let thresholds: Map<String, Value> = HarmCategory::ALL
+            .into_iter()
+            .map(|category| {
+                (
+                    category.key().to_string(),
+                    Value::from(self.get(category).as_str()),
+                )
+            })
+            .collect();
+        Value::Object(thresholds)
+    }
+
+    /// These thresholds, raised to the floor wherever they're weaker
+    pub fn at_least(&self, floor: &Self) -> Self {
+        let mut thresholds = *self;
+        for category in HarmCategory::ALL {
+            thresholds.set(category, self.get(category).max(floor.get(category)));
+        }
+        thresholds
+    }
+
+    pub fn gemini_settings(&self) -> Vec<SafetySetting> {
+        HarmCategory::ALL
+            .into_iter()
+            .map(|category| SafetySetting {
+                category: category.gemini_name().to_string(),
+                threshold: self.get(category).gemini_name().to_string(),
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_thresholds_from_setting() {
+        let guild = SafetyThresholds::from_setting(
+            Some(
+                &json!({ "harassment": "medium", "hateSpeech": "off", "dangerousContent": "nope" }),
+            ),
+            SafetyThresholds::default(),
+        );
+        assert_eq!(
+            guild.get(HarmCategory::Harassment),
+            BlockThreshold::MediumAndAbove
+        );
+        assert_eq!(guild.get(HarmCategory::HateSpeech), BlockThreshold::None);
+        assert_eq!(
+            guild.get(HarmCategory::DangerousContent),
+            BlockThreshold::None
+        );
+        assert_eq!(
+            SafetyThresholds::from_setting(Some(&guild.to_setting()), SafetyThresholds::default()),
+            guild
+        );
+
+        // turning hate speech off still leaves the floor
+        let applied = guild.at_least(&SafetyThresholds::default_floor());
+        assert_eq!(
+            applied.get(HarmCategory::HateSpeech),
+            BlockThreshold::OnlyHigh
+        );
+        assert_eq!(
+            applied.get(HarmCategory::Harassment),
+            BlockThreshold::MediumAndAbove
+        );
+    }
+
+    #[test]
+    fn test_gemini_settings() {
+        let settings = SafetyThresholds::default().gemini_settings();
+        assert_eq!(settings.len(), 4);
+        assert_eq!(settings[1].category, "HARM_CATEGORY_HATE_SPEECH");
+        assert_eq!(settings[1].threshold, "BLOCK_MEDIUM_AND_ABOVE");
+        assert_eq!(
+            HarmCategory::from_gemini_name("HARM_CATEGORY_DANGEROUS_CONTENT"),
+            Some(HarmCategory::DangerousContent)
+        );
+    }
+}
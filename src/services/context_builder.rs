@@ -0,0 +1,980 @@
+use crate::services::conversation_service::ConversationService;
+use crate::services::llm_service::{
+    ConversationContext, ImageData, LinkedMessage, MessageContext, UserInfo,
+};
+use crate::services::saved_conversations::SavedConversation;
+use crate::services::semantic_memory::{MemoryQuery, RetrievedMemory};
+use crate::utils::long_message::{MAX_HISTORY_CHARS, MAX_PROMPT_CHARS, shorten_for_prompt};
+use crate::utils::message_cache::{CachedAttachment, CachedMessage, MessageCache, attachments_of};
+use crate::utils::nickname_cache::NicknameCache;
+use crate::utils::regex_patterns::MESSAGE_LINK_REGEX;
+use crate::utils::{ImageProcessor, MessageSanitizer};
+use serde_json::Value;
+use serenity::builder::GetMessages;
+use serenity::http::Http;
+use serenity::model::channel::{Channel, Message};
+use serenity::model::id::{ChannelId, GuildId, MessageId, UserId};
+use std::collections::HashSet;
+use std::sync::Arc;
+use tracing::info;
+
+/// Upper bound for every count setting, the message cache keeps 50 messages per channel
+const MAX_CONFIGURABLE_MESSAGES: usize = 50;
+
+const MAX_CONFIGURABLE_TOKENS: usize = 100_000;
+
+/// Related older messages and facts retrieved by similarity
+const RETRIEVED_MEMORIES: usize = 5;
+
+/// With retrieval the latest history only needs to carry the flow of the conversation,
+/// what's relevant from further back is retrieved
+const RETRIEVAL_HISTORY_KEEP: usize = 4;
+
+/// Stands in for the text of turns that are only an image or a video
+const IMAGE_PLACEHOLDER: &str = "[image]";
+const VIDEO_PLACEHOLDER: &str = "[video]";
+
+/// Message links followed per message
+const MAX_LINKED_MESSAGES: usize = 2;
+
+/// Fetched around a linked message, itself included
+const LINKED_MESSAGE_WINDOW: u8 = 5;
+
+type BoxError = Box<dyn std::error::Error + Send + Sync>;
+
+pub struct ContextOptions {
+    pub bot_user_id: u64,
+    pub is_random_reply: bool,
+    pub limits: ContextLimits,
+    /// Saved conversation the channel follows on from, its turns come before the channel's
+    pub resumed: Option<SavedConversation>,
+}
+
+/// How much history goes into a context, tunable per guild through the `contextWindow`
+/// setting, e.g. `{"replyChainDepth": 30, "historyMessages": 15, "maxTokens": 12000}`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ContextLimits {
+    /// Deepest reply chain followed back from the current message
+    pub reply_chain_depth: usize,
+    /// Chains shorter than this are supplemented with recent channel history
+    pub supplement_below: usize,
+    /// Messages fetched from the channel when supplementing
+    pub history_fetch: usize,
+    /// Of the fetched history, how many messages are kept
+    pub history_keep: usize,
+    /// Estimated token budget for all context messages together
+    pub max_tokens: usize,
+}
+
+impl Default for ContextLimits {
+    fn default() -> Self {
+        Self {
+            reply_chain_depth: 15,
+            supplement_below: 8,
+            history_fetch: 20,
+            history_keep: 8,
+            max_tokens: 8000,
+        }
+    }
+}
+
+impl ContextLimits {
+    pub fn from_setting(setting: Option<&Value>) -> Self {
+        let defaults = Self::default();
+        let Some(setting) = setting else {
+            return defaults;
+        };
+        let limit = |key: &str, default: usize, max: usize| {
+            setting
+                .get(key)
+                .and_then(|value| value.as_u64())
+                .map(|value| (value as usize).min(max))
+                .unwrap_or(default)
+        };
+
+        Self {
+            reply_chain_depth: limit(
+                "replyChainDepth",
+                defaults.reply_chain_depth,
+                MAX_CONFIGURABLE_MESSAGES,
+            ),
+            supplement_below: limit(
+                "supplementBelow",
+                defaults.supplement_below,
+                MAX_CONFIGURABLE_MESSAGES,
+            ),
+            history_fetch: limit(
+                "historyFetch",
+                defaults.history_fetch,
+                MAX_CONFIGURABLE_MESSAGES,
+            ),
+            history_keep: limit(
+                "historyMessages",
+                defaults.history_keep,
+                MAX_CONFIGURABLE_MESSAGES,
+            ),
+            max_tokens: limit("maxTokens", defaults.max_tokens, MAX_CONFIGURABLE_TOKENS),
+        }
+    }
+}
+
+/// Assembles the conversation context for a message from its reply chain, recent
+/// channel history and the people involved
+pub struct ContextBuilder {
+    image_processor: ImageProcessor,
+    message_cache: Arc<MessageCache>,
+    nicknames: Arc<NicknameCache>,
+    conversations: Arc<ConversationService>,
+}
+
+impl ContextBuilder {
+    pub fn new(
+        message_cache: Arc<MessageCache>,
+        nicknames: Arc<NicknameCache>,
+        conversations: Arc<ConversationService>,
+    ) -> Self {
+        Self {
+            image_processor: ImageProcessor::new(),
+            message_cache,
+            nicknames,
+            conversations,
+        }
+    }
+
+    pub async fn build_context(
+        &self,
+        http: &Arc<Http>,
+        msg: &Message,
+        options: ContextOptions,
+    ) -> ConversationContext {
+        let recent_messages = self.reply_chain_context(http, msg, &options).await;
+
+        info!(
+            event = "reply_chain_context_gathered",
+            user = %msg.author.name,
+            channel_id = %msg.channel_id,
+            message_count = recent_messages.len(),
+            messages = ?recent_messages.iter().map(|m| format!("{}: {}", m.user_display_name, m.content)).collect::<Vec<_>>(),
+            "Gathered reply chain context"
+        );
+
+        let user_display_name = self.nicknames.display_name(http, None, msg).await;
+        let current_images = self.image_processor.process_message_images(msg).await;
+
+        let user_info = gather_user_info(
+            &recent_messages,
+            (msg.author.id.get(), msg.author.bot),
+            &user_display_name,
+            options.bot_user_id,
+        );
+
+        let referenced_message = match &msg.referenced_message {
+            Some(ref_msg) => Some(
+                self.message_context(http, msg, ref_msg, options.bot_user_id)
+                    .await,
+            ),
+            None => None,
+        };
+
+        let relevant_memories = self
+            .relevant_memories(msg, &recent_messages, options.resumed.as_ref())
+            .await;
+        let linked_messages = self.linked_messages(http, msg, options.bot_user_id).await;
+
+        // Sanitize the current message to prevent impersonation
+        let current_message = if msg.content.is_empty() {
+            turn_content("", &attachments_of(msg))
+                .unwrap_or_default()
+                .to_string()
+        } else {
+            let sanitized = MessageSanitizer::sanitize_message(&msg.content, &user_display_name);
+            shorten_for_prompt(&sanitized, MAX_PROMPT_CHARS, msg.id.get())
+        };
+        let message_length = msg.content.chars().count();
+        if message_length > MAX_PROMPT_CHARS {
+            info!(
+                event = "long_message_shortened",
+                user = %msg.author.name,
+                message_id = msg.id.get(),
+                message_length = message_length,
+                "Cut the middle of a long message out of the prompt"
+            );
+        }
+
+        ConversationContext {
+            current_user: user_display_name,
+            current_message,
+            current_images,
+            recent_messages,
+            user_info,
+            referenced_message,
+            is_random_reply: options.is_random_reply,
+            instructions: None,
+            relevant_memories,
+            author_activity: None,
+            linked_messages,
+            persona: None,
+            guild_prompt: None,
+            memory_guild_id: msg
+                .guild_id
+                .map(|id| id.get())
+                .or(options.resumed.and_then(|resumed| resumed.guild_id)),
+        }
+    }
+
+    /// Messages linked in this server that the author can read themselves, each with a
+    /// couple of its neighbors
+    async fn linked_messages(
+        &self,
+        http: &Http,
+        msg: &Message,
+        bot_user_id: u64,
+    ) -> Vec<LinkedMessage> {
+        let Some(guild_id) = msg.guild_id else {
+            return Vec::new();
+        };
+        let mut linked = Vec::new();
+        for (channel_id, message_id) in message_links(&msg.content, guild_id.get()) {
+            match self
+                .linked_message(http, msg, guild_id, channel_id, message_id, bot_user_id)
+                .await
+            {
+                Ok(Some(linked_message)) => linked.push(linked_message),
+                Ok(None) => info!(
+                    event = "linked_message_skipped",
+                    channel_id = channel_id.get(),
+                    message_id = message_id.get(),
+                    "Linked message is gone or the author can't read it"
+                ),
+                Err(e) => info!(
+                    event = "linked_message_fetch_failed",
+                    channel_id = channel_id.get(),
+                    message_id = message_id.get(),
+                    error = ?e,
+                    "Couldn't fetch linked message"
+                ),
+            }
+        }
+        linked
+    }
+
+    async fn linked_message(
+        &self,
+        http: &Http,
+        current_msg: &Message,
+        guild_id: GuildId,
+        channel_id: ChannelId,
+        message_id: MessageId,
+        bot_user_id: u64,
+    ) -> Result<Option<LinkedMessage>, BoxError> {
+        // chloe can see channels the author can't, their content mustn't leak through her
+        if !author_can_read(http, guild_id, channel_id, current_msg.author.id).await? {
+            return Ok(None);
+        }
+
+        let mut messages = channel_id
+            .messages(
+                http,
+                GetMessages::new()
+                    .around(message_id)
+                    .limit(LINKED_MESSAGE_WINDOW),
+            )
+            .await?;
+        messages.sort_by_key(|message| message.id);
+        let Some(position) = messages.iter().position(|message| message.id == message_id) else {
+            return Ok(None);
+        };
+
+        let mut contexts = Vec::new();
+        for message in &messages {
+            contexts.push(
+                self.message_context(http, current_msg, message, bot_user_id)
+                    .await,
+            );
+        }
+        let after = contexts.split_off(position + 1);
+        let message = contexts.remove(position);
+        Ok(Some(LinkedMessage {
+            message,
+            before: contexts,
+            after,
+        }))
+    }
+
+    /// Older messages and the author's facts related to the message, leaving out what's
+    /// already in the context
+    async fn relevant_memories(
+        &self,
+        msg: &Message,
+        recent_messages: &[MessageContext],
+        resumed: Option<&SavedConversation>,
+    ) -> Vec<RetrievedMemory> {
+        let Some(semantic) = self.conversations.semantic_memory() else {
+            return Vec::new();
+        };
+        if msg.content.trim().is_empty() {
+            return Vec::new();
+        }
+
+        // a resumed conversation brings the memories of where it was saved along
+        let query = match resumed.and_then(|resumed| Some((resumed, resumed.saved_through()?))) {
+            Some((resumed, saved_through)) => MemoryQuery {
+                guild_id: resumed.guild_id,
+                channel_id: resumed.source_channel_id,
+                user_id: msg.author.id.get(),
+                before_message_id: saved_through + 1,
+            },
+            None => MemoryQuery {
+                guild_id: msg.guild_id.map(|id| id.get()),
+                channel_id: msg.channel_id.get(),
+                user_id: msg.author.id.get(),
+                before_message_id: msg.id.get(),
+            },
+        };
+        match semantic
+            .search(
+                &query,
+                &msg.content,
+                RETRIEVED_MEMORIES + recent_messages.len(),
+            )
+            .await
+        {
+            Ok(memories) => {
+                let memories: Vec<RetrievedMemory> = memories
+                    .into_iter()
+                    .filter(|memory| {
+                        !recent_messages
+                            .iter()
+                            .any(|recent| recent.content == memory.content)
+                    })
+                    .take(RETRIEVED_MEMORIES)
+                    .collect();
+                info!(
+                    event = "memories_retrieved",
+                    channel_id = %msg.channel_id,
+                    memory_count = memories.len(),
+                    "Retrieved related memories"
+                );
+                memories
+            }
+            Err(e) => {
+                info!(
+                    event = "memory_retrieval_failed",
+                    channel_id = %msg.channel_id,
+                    error = ?e,
+                    "Couldn't retrieve related memories"
+                );
+                Vec::new()
+            }
+        }
+    }
+
+    /// A message's images as the model gets them, attachments and video keyframes
+    pub async fn message_images(&self, msg: &Message) -> Vec<ImageData> {
+        self.image_processor.process_message_images(msg).await
+    }
+
+    async fn message_context(
+        &self,
+        http: &Http,
+        current_msg: &Message,
+        msg: &Message,
+        bot_user_id: u64,
+    ) -> MessageContext {
+        let is_self = msg.author.id.get() == bot_user_id;
+        // other bots keep their own names so their output isn't put in chloe's mouth
+        let user_display_name = if is_self {
+            "Chloe".to_string()
+        } else {
+            self.nicknames
+                .display_name(http, current_msg.guild_id, msg)
+                .await
+        };
+
+        let images = self.image_processor.process_message_images(msg).await;
+
+        // Sanitize message content to prevent impersonation
+        let content = if msg.content.is_empty() {
+            turn_content("", &attachments_of(msg))
+                .unwrap_or_default()
+                .to_string()
+        } else {
+            let sanitized = MessageSanitizer::sanitize_message(&msg.content, &user_display_name);
+            shorten_for_prompt(&sanitized, MAX_HISTORY_CHARS, msg.id.get())
+        };
+
+        MessageContext {
+            user_display_name,
+            user_id: msg.author.id.get(),
+            content,
+            is_bot: msg.author.bot,
+            is_self,
+            channel_id: msg.channel_id.get(),
+            images,
+        }
+    }
+
+    /// The reply chain, supplemented with channel history when short, oldest first
+    async fn reply_chain_context(
+        &self,
+        http: &Arc<Http>,
+        current_msg: &Message,
+        options: &ContextOptions,
+    ) -> Vec<MessageContext> {
+        let limits = &options.limits;
+        let mut reply_chain = Vec::new();
+        let mut msg_to_follow = current_msg.referenced_message.as_ref().map(|m| m.as_ref());
+
+        info!(
+            event = "starting_reply_chain_trace",
+            current_msg_id = current_msg.id.get(),
+            has_referenced_msg = msg_to_follow.is_some(),
+            "Starting to trace reply chain"
+        );
+
+        while let Some(msg) = msg_to_follow {
+            if reply_chain.len() >= limits.reply_chain_depth {
+                break;
+            }
+
+            info!(
+                event = "processing_chain_message",
+                msg_id = msg.id.get(),
+                content = %msg.content,
+                author = %msg.author.name,
+                has_next_ref = msg.referenced_message.is_some(),
+                next_ref_id = msg.referenced_message.as_ref().map(|m| m.id.get()),
+                "Processing message in reply chain"
+            );
+
+            if turn_content(&msg.content, &attachments_of(msg)).is_none() {
+                info!(
+                    event = "skipping_empty_message",
+                    msg_id = msg.id.get(),
+                    "Skipping empty message in chain"
+                );
+                msg_to_follow = msg.referenced_message.as_ref().map(|m| m.as_ref());
+                continue;
+            }
+
+            reply_chain.push(
+                self.message_context(http, current_msg, msg, options.bot_user_id)
+                    .await,
+            );
+
+            // Follow the chain if this message is also a reply
+            if let Some(ref_msg) = &msg.referenced_message {
+                info!(
+                    event = "found_next_reference",
+                    current_msg_id = msg.id.get(),
+                    next_ref_id = ref_msg.id.get(),
+                    "Found next message in chain"
+                );
+                msg_to_follow = Some(ref_msg.as_ref());
+            } else {
+                info!(
+                    event = "chain_end_reached",
+                    current_msg_id = msg.id.get(),
+                    "No more references found, ending chain"
+                );
+                msg_to_follow = None;
+            }
+        }
+
+        let mut history = Vec::new();
+        if reply_chain.len() < limits.supplement_below {
+            info!(
+                event = "supplementing_with_channel_history",
+                chain_length = reply_chain.len(),
+                "Reply chain is short, fetching recent channel history"
+            );
+
+            match self
+                .recent_channel_context(http, current_msg, options)
+                .await
+            {
+                Ok(additional_context) => {
+                    history = additional_context;
+                    info!(
+                        event = "supplemented_context",
+                        new_chain_length = reply_chain.len() + history.len(),
+                        "Successfully supplemented with channel history"
+                    );
+                }
+                Err(e) => {
+                    info!(
+                        event = "failed_to_supplement",
+                        error = ?e,
+                        "Failed to fetch channel history"
+                    );
+                }
+            }
+        }
+
+        let trimmed = fit_token_budget(&mut reply_chain, &mut history, limits.max_tokens);
+        if trimmed > 0 {
+            info!(
+                event = "context_trimmed_to_budget",
+                trimmed_messages = trimmed,
+                max_tokens = limits.max_tokens,
+                "Dropped oldest context messages to fit the token budget"
+            );
+        }
+
+        let context = merge_context(reply_chain, history);
+        info!(
+            event = "reply_chain_complete",
+            chain_length = context.len(),
+            "Completed reply chain tracing"
+        );
+        context
+    }
+
+    async fn recent_channel_context(
+        &self,
+        http: &Arc<Http>,
+        current_msg: &Message,
+        options: &ContextOptions,
+    ) -> Result<Vec<MessageContext>, BoxError> {
+        let limits = &options.limits;
+        let history_keep = if self.conversations.semantic_memory().is_some() {
+            limits.history_keep.min(RETRIEVAL_HISTORY_KEEP)
+        } else {
+            limits.history_keep
+        };
+        let mut context = Vec::new();
+
+        let mut messages = match self
+            .message_cache
+            .recent_before(
+                current_msg.channel_id.get(),
+                current_msg.id.get(),
+                limits.history_fetch,
+            )
+            .await
+        {
+            Some(cached) => {
+                info!(
+                    event = "channel_context_from_cache",
+                    channel_id = current_msg.channel_id.get(),
+                    message_count = cached.len(),
+                    "Assembled channel context from the message cache"
+                );
+                cached
+            }
+            None => match self.stored_turns(current_msg, limits.history_fetch).await {
+                Some(turns) => turns,
+                None => {
+                    self.fetch_recent_messages(
+                        http,
+                        current_msg,
+                        limits.history_fetch,
+                        options.bot_user_id,
+                    )
+                    .await?
+                }
+            },
+        };
+        if let Some(resumed) = &options.resumed {
+            let room = limits.history_fetch.saturating_sub(messages.len());
+            messages.extend(resumed.turns.iter().take(room).cloned());
+        }
+
+        for msg in &messages {
+            let is_self = msg.author_id == options.bot_user_id;
+            if msg.is_bot && !is_self {
+                continue;
+            }
+            let Some(content) = turn_content(&msg.content, &msg.attachments) else {
+                continue;
+            };
+
+            let images = self
+                .image_processor
+                .process_attachments(&msg.attachments)
+                .await;
+
+            // Sanitize message content to prevent impersonation
+            let sanitized_content = if msg.content.is_empty() {
+                content.to_string()
+            } else {
+                let sanitized = MessageSanitizer::sanitize_message(content, &msg.author_name);
+                shorten_for_prompt(&sanitized, MAX_HISTORY_CHARS, msg.id)
+            };
+
+            context.push(MessageContext {
+                user_display_name: msg.author_name.clone(),
+                user_id: msg.author_id,
+                content: sanitized_content,
+                is_bot: msg.is_bot,
+                is_self,
+                channel_id: msg.channel_id,
+                images,
+            });
+
+            if context.len() >= history_keep {
+                break;
+            }
+        }
+
+        Ok(context)
+    }
+
+    /// The channel's remembered conversation, None when nothing was stored yet or the
+    /// database can't be reached
+    async fn stored_turns(
+        &self,
+        current_msg: &Message,
+        limit: usize,
+    ) -> Option<Vec<CachedMessage>> {
+        let channel_id = current_msg.channel_id.get();
+        match self
+            .conversations
+            .recent_turns(channel_id, current_msg.id.get(), limit)
+            .await
+        {
+            Ok(turns) if !turns.is_empty() => {
+                info!(
+                    event = "channel_context_from_conversation",
+                    channel_id = channel_id,
+                    message_count = turns.len(),
+                    "Assembled channel context from stored conversation turns"
+                );
+                Some(turns)
+            }
+            Ok(_) => None,
+            Err(e) => {
+                info!(
+                    event = "conversation_turns_unavailable",
+                    channel_id = channel_id,
+                    error = ?e,
+                    "Couldn't load stored conversation turns"
+                );
+                None
+            }
+        }
+    }
+
+    /// Fetch recent channel history from discord and fill the cache with it
+    async fn fetch_recent_messages(
+        &self,
+        http: &Arc<Http>,
+        current_msg: &Message,
+        limit: usize,
+        bot_user_id: u64,
+    ) -> Result<Vec<CachedMessage>, BoxError> {
+        let messages = current_msg
+            .channel_id
+            .messages(
+                http,
+                serenity::builder::GetMessages::new()
+                    .before(current_msg.id)
+                    .limit(limit as u8),
+            )
+            .await?;
+
+        // turns dropped with /rewind stay out of the context
+        let rewound = self
+            .message_cache
+            .rewound(current_msg.channel_id.get())
+            .await;
+        let mut cached = Vec::new();
+        for msg in messages
+            .iter()
+            .filter(|msg| !rewound.contains(&msg.id.get()))
+        {
+            let user_display_name = if msg.author.id.get() == bot_user_id {
+                "Chloe".to_string()
+            } else if msg.content.is_empty() && msg.attachments.is_empty() {
+                msg.author.display_name().to_string()
+            } else {
+                self.nicknames
+                    .display_name(http, current_msg.guild_id, msg)
+                    .await
+            };
+            let message = CachedMessage::from_message(msg, user_display_name);
+            self.message_cache.store(&message).await;
+            cached.push(message);
+        }
+        self.message_cache
+            .mark_channel_complete(current_msg.channel_id.get())
+            .await;
+
+        Ok(cached)
+    }
+}
+
+/// The text a turn stands for in the context, a placeholder for turns that are only an
+/// image or a video, None for turns with nothing chloe can use
+fn turn_content<'a>(content: &'a str, attachments: &[CachedAttachment]) -> Option<&'a str> {
+    if !content.is_empty() {
+        return Some(content);
+    }
+    let has = |kind: &str| {
+        attachments.iter().any(|attachment| {
+            attachment
+                .content_type
+                .as_deref()
+                .is_some_and(|content_type| content_type.starts_with(kind))
+        })
+    };
+    if has("image/") {
+        Some(IMAGE_PLACEHOLDER)
+    } else if has("video/") {
+        Some(VIDEO_PLACEHOLDER)
+    } else {
+        None
+    }
+}
+
+/// Whether the member can read the channel's history, threads go by their parent channel
+async fn author_can_read(
+    http: &Http,
+    guild_id: GuildId,
+    channel_id: ChannelId,
+    author_id: UserId,
+) -> Result<bool, BoxError> {
+    let Channel::Guild(mut channel) = channel_id.to_channel(http).await? else {
+        return Ok(false);
+    };
+    if let (true, Some(parent_id)) = (channel.thread_metadata.is_some(), channel.parent_id) {
+        let Some(parent) = parent_id.to_channel(http).await?.guild() else {
+            return Ok(false);
+        };
+        channel = parent;
+    }
+    if channel.guild_id != guild_id {
+        return Ok(false);
+    }
+
+    let guild = guild_id.to_partial_guild(http).await?;
+    let member = guild_id.member(http, author_id).await?;
+    let permissions = guild.user_permissions_in(&channel, &member);
+    Ok(permissions.view_channel() && permissions.read_message_history())
+}
+
+/// Channel and message ids of the links in `content` that point into the guild, each once
+fn message_links(content: &str, guild_id: u64) -> Vec<(ChannelId, MessageId)> {
+    let mut links = Vec::new();
+    for captures in MESSAGE_LINK_REGEX.captures_iter(content) {
+        let ids = (
+            captures[1].parse::<u64>(),
+            captures[2].parse::<u64>(),
+            captures[3].parse::<u64>(),
+        );
+        let (Ok(link_guild), Ok(channel_id), Ok(message_id)) = ids else {
+            continue; // DM links
+        };
+        if link_guild != guild_id || channel_id == 0 || message_id == 0 {
+            continue;
+        }
+        let link = (ChannelId::new(channel_id), MessageId::new(message_id));
+        if !links.contains(&link) {
+            links.push(link);
+        }
+        if links.len() >= MAX_LINKED_MESSAGES {
+            break;
+        }
+    }
+    links
+}
+
+/// Drop the oldest supplementary history first, then the oldest of the reply chain,
+/// until the context fits the budget. Both lists are newest first. Returns how many
+/// messages were dropped.
+fn fit_token_budget(
+    reply_chain: &mut Vec<MessageContext>,
+    history: &mut Vec<MessageContext>,
+    max_tokens: usize,
+) -> usize {
+    let mut total: usize = reply_chain
+        .iter()
+        .chain(history.iter())
+        .map(estimate_tokens)
+        .sum();
+    let mut dropped = 0;
+    while total > max_tokens {
+        match history.pop().or_else(|| reply_chain.pop()) {
+            Some(message) => {
+                total -= estimate_tokens(&message);
+                dropped += 1;
+            }
+            None => break,
+        }
+    }
+    dropped
+}
+
+/// Same rough 4 chars per token estimate the llm service uses
+fn estimate_tokens(message: &MessageContext) -> usize {
+    (message.user_display_name.len() + message.content.len()).div_ceil(4)
+}
+
+/// Both lists come newest first, the result is oldest first with the reply chain ahead
+/// of the supplementary history
+fn merge_context(
+    reply_chain: Vec<MessageContext>,
+    history: Vec<MessageContext>,
+) -> Vec<MessageContext> {
+    let mut context = history;
+    context.extend(reply_chain);
+    context.reverse();
+    context
+}
+
+/// Everyone in the conversation once, the author first and chloe last
+fn gather_user_info(
+    recent_messages: &[MessageContext],
+    (author_id, author_is_bot): (u64, bool),
+    author_display_name: &str,
+    bot_user_id: u64,
+) -> Vec<UserInfo> {
+    let mut user_info = Vec::new();
+    let mut seen_users = HashSet::new();
+
+    if seen_users.insert(author_id) {
+        user_info.push(UserInfo {
+            display_name: author_display_name.to_string(),
+            user_id: author_id,
+            is_bot: author_is_bot,
+        });
+    }
+
+    for msg in recent_messages {
+        if seen_users.insert(msg.user_id) {
+            user_info.push(UserInfo {
+                display_name: msg.user_display_name.clone(),
+                user_id: msg.user_id,
+                is_bot: msg.is_bot,
+            });
+        }
+    }
+
+    if seen_users.insert(bot_user_id) {
+        user_info.push(UserInfo {
+            display_name: "Chloe".to_string(),
+            user_id: bot_user_id,
+            is_bot: true,
+        });
+    }
+
+    user_info
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn message(user_id: u64, content: &str) -> MessageContext {
+        MessageContext {
+            user_display_name: format!("user{}", user_id),
+            user_id,
+            content: content.to_string(),
+            is_bot: false,
+            is_self: false,
+            channel_id: 1,
+            images: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn test_attachment_only_turns_get_a_placeholder() {
+        let attachment = |content_type: &str| CachedAttachment {
+            id: 1,
+            url: "https://cdn.discordapp.com/attachments/1/2/file".to_string(),
+            filename: "file".to_string(),
+            content_type: Some(content_type.to_string()),
+            size: 1024,
+        };
+
+        assert_eq!(
+            turn_content("look", &[attachment("image/png")]),
+            Some("look")
+        );
+        assert_eq!(
+            turn_content("", &[attachment("image/png")]),
+            Some("[image]")
+        );
+        assert_eq!(
+            turn_content("", &[attachment("video/mp4")]),
+            Some("[video]")
+        );
+        assert_eq!(turn_content("", &[attachment("application/pdf")]), None);
+        assert_eq!(turn_content("", &[]), None);
+    }
+
+    #[test]
+    fn test_message_links_stay_in_the_guild() {
+        let content = "what about https://discord.com/channels/1/2/3 and \
+            https://ptb.discord.com/channels/1/2/3, not https://discord.com/channels/9/2/4 \
+            or https://discord.com/channels/@me/5/6 but https://discordapp.com/channels/1/7/8";
+
+        assert_eq!(
+            message_links(content, 1),
+            vec![
+                (ChannelId::new(2), MessageId::new(3)),
+                (ChannelId::new(7), MessageId::new(8)),
+            ]
+        );
+        assert!(message_links("no links here", 1).is_empty());
+    }
+
+    #[test]
+    fn test_merge_context_orders_oldest_first() {
+        let chain = vec![message(1, "chain newest"), message(2, "chain oldest")];
+        let history = vec![message(3, "history newest"), message(4, "history oldest")];
+
+        let merged: Vec<String> = merge_context(chain, history)
+            .into_iter()
+            .map(|m| m.content)
+            .collect();
+        assert_eq!(
+            merged,
+            [
+                "chain oldest",
+                "chain newest",
+                "history oldest",
+                "history newest"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_fit_token_budget_drops_oldest_history_first() {
+        let mut chain = vec![message(1, &"a".repeat(40)), message(2, &"b".repeat(40))];
+        let mut history = vec![message(3, &"c".repeat(40)), message(4, &"d".repeat(40))];
+
+        // every message is 12 tokens, so a budget of 30 keeps two
+        assert_eq!(fit_token_budget(&mut chain, &mut history, 30), 2);
+        assert_eq!(chain.len(), 2);
+        assert!(history.is_empty());
+
+        assert_eq!(fit_token_budget(&mut chain, &mut history, 12), 1);
+        assert_eq!(chain[0].user_id, 1);
+    }
+
+    #[test]
+    fn test_context_limits_from_setting() {
+        assert_eq!(ContextLimits::from_setting(None), ContextLimits::default());
+
+        let limits = ContextLimits::from_setting(Some(&serde_json::json!({
+            "replyChainDepth": 30,
+            "historyFetch": 500,
+            "maxTokens": "lots"
+        })));
+        assert_eq!(limits.reply_chain_depth, 30);
+        assert_eq!(limits.history_fetch, MAX_CONFIGURABLE_MESSAGES);
+        assert_eq!(limits.max_tokens, ContextLimits::default().max_tokens);
+    }
+
+    #[test]
+    fn test_gather_user_info_dedupes_and_adds_chloe() {
+        let recent = vec![message(2, "a"), message(1, "b"), message(2, "c")];
+        let users = gather_user_info(&recent, (1, false), "author", 99);
+
+        let ids: Vec<u64> = users.iter().map(|user| user.user_id).collect();
+        assert_eq!(ids, [1, 2, 99]);
+        assert_eq!(users[0].display_name, "author");
+        assert_eq!(users[2].display_name, "Chloe");
+    }
+}
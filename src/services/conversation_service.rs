@@ -0,0 +1,229 @@
+use crate::services::semantic_memory::SemanticMemory;
+use crate::utils::message_cache::CachedMessage;
+use sqlx::{PgPool, Row};
+use std::sync::Arc;
+use std::time::Duration;
+use tracing::{error, info, warn};
+
+/// Turns older than this are deleted
+pub const RETENTION_DAYS: i32 = 30;
+
+const PRUNE_INTERVAL: Duration = Duration::from_secs(60 * 60 * 24);
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TurnRole {
+    User,
+    Assistant,
+}
+
+impl TurnRole {
+    fn as_str(&self) -> &'static str {
+        match self {
+            TurnRole::User => "user",
+            TurnRole::Assistant => "assistant",
+        }
+    }
+}
+
+/// Messages chloe answered and her replies, per channel in `chloe_messages`, so
+/// conversations survive restarts and the redis cache expiring without re-fetching
+/// channel history from discord. Attachments aren't kept, their CDN links expire.
+pub struct ConversationService {
+    db_pool: PgPool,
+    semantic: Option<Arc<SemanticMemory>>,
+}
+
+impl ConversationService {
+    pub fn new(db_pool: PgPool) -> Self {
+        Self {
+            db_pool,
+            semantic: None,
+        }
+    }
+
+    /// Also embed stored turns so they can be retrieved by similarity
+    pub fn with_semantic_memory(mut self, semantic: Option<Arc<SemanticMemory>>) -> Self {
+        self.semantic = semantic;
+        self
+    }
+
+    pub fn semantic_memory(&self) -> Option<&Arc<SemanticMemory>> {
+        self.semantic.as_ref().filter(|semantic| semantic.is_enabled())
+    }
+
+    /// Store a turn in the background, a message is only stored once
+    pub fn record(&self, message: &CachedMessage, guild_id: Option<u64>, role: TurnRole) {
+        let message = message.clone();
+        let db_pool = self.db_pool.clone();
+        let semantic = self.semantic.clone();
+
+        tokio::spawn(async move {
+            let result = sqlx::query(
+                "INSERT INTO chloe_messages (message_id, channel_id, guild_id, author_id, author_name, role, content)
+                 VALUES ($1, $2, $3, $4, $5, $6, $7)
+                 ON CONFLICT (message_id) DO NOTHING",
+            )
+            .bind(message.id as i64)
+            .bind(message.channel_id as i64)
+            .bind(guild_id.map(|id| id as i64))
+            .bind(message.author_id as i64)
+            .bind(&message.author_name)
+            .bind(role.as_str())
+            .bind(&message.content)
+            .execute(&db_pool)
+            .await;
+
+            let stored = match result {
+                Ok(result) => result.rows_affected() > 0,
+                Err(e) => {
+                    error!(
+                        event = "conversation_turn_store_failed",
+                        channel_id = message.channel_id,
+                        error = ?e,
+                        "Failed to store conversation turn"
+                    );
+                    false
+                }
+            };
+
+            if let Some(semantic) = semantic.filter(|_| stored) {
+                let indexed = semantic.index_message(&message, guild_id).await;
+                if let Err(e) = indexed {
+                    warn!(
+                        event = "conversation_turn_embed_failed",
+                        channel_id = message.channel_id,
+                        error = ?e,
+                        "Failed to embed conversation turn"
+                    );
+                }
+            }
+        });
+    }
+
+    /// The channel's last turns before `before_id`, newest first
+    pub async fn recent_turns(
+        &self,
+        channel_id: u64,
+        before_id: u64,
+        limit: usize,
+    ) -> Result<Vec<CachedMessage>, sqlx::Error> {
+        let rows = sqlx::query(
+            "SELECT message_id, author_id, author_name, role, content FROM chloe_messages
+             WHERE channel_id = $1 AND message_id < $2
+             ORDER BY message_id DESC
+             LIMIT $3",
+        )
+        .bind(channel_id as i64)
+        .bind(before_id as i64)
+        .bind(limit as i64)
+        .fetch_all(&self.db_pool)
+        .await?;
+
+        Ok(rows
+            .iter()
+            .map(|row| CachedMessage {
+                id: row.get::<i64, _>("message_id") as u64,
+                channel_id,
+                author_id: row.get::<i64, _>("author_id") as u64,
+                author_name: row.get("author_name"),
+                is_bot: row.get::<&str, _>("role") == TurnRole::Assistant.as_str(),
+                content: row.get("content"),
+                attachments: Vec::new(),
+            })
+            .collect())
+    }
+
+    pub async fn update_content(&self, message_id: u64, content: &str) {
+        let result = sqlx::query("UPDATE chloe_messages SET content = $2 WHERE message_id = $1")
+            .bind(message_id as i64)
+            .bind(content)
+            .execute(&self.db_pool)
+            .await;
+        if let Err(e) = result {
+            error!(
+                event = "conversation_turn_update_failed",
+                message_id = message_id,
+                error = ?e,
+                "Failed to update conversation turn"
+            );
+            return;
+        }
+
+        if let Some(semantic) = &self.semantic {
+            let reindexed = semantic.reindex_message(message_id, content).await;
+            if let Err(e) = reindexed {
+                warn!(
+                    event = "conversation_turn_embed_failed",
+                    message_id = message_id,
+                    error = ?e,
+                    "Failed to re-embed edited conversation turn"
+                );
+            }
+        }
+    }
+
+    /// Deleted messages are forgotten too, their embedding with them
+    pub async fn remove(&self, message_id: u64) {
+        let result = sqlx::query("DELETE FROM chloe_messages WHERE message_id = $1")
+            .bind(message_id as i64)
+            .execute(&self.db_pool)
+            .await;
+        if let Err(e) = result {
+            error!(
+                event = "conversation_turn_delete_failed",
+                message_id = message_id,
+                error = ?e,
+                "Failed to delete conversation turn"
+            );
+        }
+    }
+
+    /// Delete the channel's newest `turns` turns, their embeddings go with them. Returns
+    /// the deleted message ids, newest first.
+    pub async fn rewind(&self, channel_id: u64, turns: usize) -> Result<Vec<u64>, sqlx::Error> {
+        let ids: Vec<i64> = sqlx::query_scalar(
+            "DELETE FROM chloe_messages WHERE message_id IN (
+                 SELECT message_id FROM chloe_messages
+                 WHERE channel_id = $1
+                 ORDER BY message_id DESC
+                 LIMIT $2
+             )
+             RETURNING message_id",
+        )
+        .bind(channel_id as i64)
+        .bind(turns as i64)
+        .fetch_all(&self.db_pool)
+        .await?;
+
+        let mut ids: Vec<u64> = ids.into_iter().map(|id| id as u64).collect();
+        ids.sort_unstable_by(|a, b| b.cmp(a));
+        Ok(ids)
+    }
+
+    /// Delete turns past the retention period once a day
+    pub async fn prune_periodically(&self) {
+        let mut interval = tokio::time::interval(PRUNE_INTERVAL);
+        loop {
+            interval.tick().await;
+            let result = sqlx::query(
+                "DELETE FROM chloe_messages WHERE created_at < CURRENT_TIMESTAMP - make_interval(days => $1)",
+            )
+            .bind(RETENTION_DAYS)
+            .execute(&self.db_pool)
+            .await;
+
+            match result {
+                Ok(result) => info!(
+                    event = "conversation_turns_pruned",
+                    deleted = result.rows_affected(),
+                    "Pruned old conversation turns"
+                ),
+                Err(e) => error!(
+                    event = "conversation_prune_failed",
+                    error = ?e,
+                    "Failed to prune conversation turns"
+                ),
+            }
+        }
+    }
+}
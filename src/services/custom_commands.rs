@@ -0,0 +1,180 @@
+use sqlx::{PgPool, Row};
+use tracing::info;
+
+pub const MAX_NAME_LENGTH: usize = 32;
+
+pub const MAX_DESCRIPTION_LENGTH: usize = 100;
+
+pub const MAX_RESPONSE_LENGTH: usize = 2000;
+
+/// How many custom commands a guild can have, well under discord's 100 guild commands
+pub const MAX_COMMANDS_PER_GUILD: usize = 50;
+
+/// A slash command an admin defined for their guild, answering with fixed text
+#[derive(Debug, Clone, PartialEq)]
+pub struct CustomCommand {
+    pub name: String,
+    pub description: String,
+    pub response: String,
+    /// Answer with an embed instead of plain text
+    pub embed: bool,
+}
+
+impl CustomCommand {
+    /// The description shown in discord's command picker, the start of the response
+    /// when the admin didn't give one
+    pub fn default_description(response: &str) -> String {
+        let line = response.lines().next().unwrap_or_default().trim();
+        if line.is_empty() {
+            return "a command of this server".to_string();
+        }
+        if line.chars().count() <= MAX_DESCRIPTION_LENGTH {
+            return line.to_string();
+        }
+        let mut description: String = line.chars().take(MAX_DESCRIPTION_LENGTH - 1).collect();
+        description.push('…');
+        description
+    }
+}
+
+/// Slash command names discord accepts, lowercased: 1 to 32 letters, digits, `-` or `_`
+pub fn normalize_name(name: &str) -> Option<String> {
+    let name = name.trim().trim_start_matches('/').to_lowercase();
+    let valid = !name.is_empty()
+        && name.chars().count() <= MAX_NAME_LENGTH
+        && name
+            .chars()
+            .all(|c| c.is_alphanumeric() || c == '-' || c == '_');
+    valid.then_some(name)
+}
+
+pub struct CustomCommandService {
+    db_pool: PgPool,
+}
+
+impl CustomCommandService {
+    pub fn new(db_pool: PgPool) -> Self {
+        Self { db_pool }
+    }
+
+    /// Save a command registered with discord as `command_id`, replacing the one with the
+    /// same name
+    pub async fn add(
+        &self,
+        guild_id: i64,
+        command: &CustomCommand,
+        command_id: u64,
+        created_by: &str,
+    ) -> Result<(), sqlx::Error> {
+        let result = sqlx::query(
+            "INSERT INTO chloe_custom_commands (guild_id, name, description, response, embed, command_id, created_by)
+             SELECT g.id, $2, $3, $4, $5, $6, $7 FROM chloe_guilds g WHERE g.snowflake_id = $1
+             ON CONFLICT (guild_id, name) DO UPDATE SET
+                 description = EXCLUDED.description,
+                 response = EXCLUDED.response,
+                 embed = EXCLUDED.embed,
+                 command_id = EXCLUDED.command_id",
+        )
+        .bind(guild_id)
+        .bind(&command.name)
+        .bind(&command.description)
+        .bind(&command.response)
+        .bind(command.embed)
+        .bind(command_id as i64)
+        .bind(created_by)
+        .execute(&self.db_pool)
+        .await?;
+
+        if result.rows_affected() == 0 {
+            return Err(sqlx::Error::RowNotFound); // the guild was never registered
+        }
+        info!(
+            event = "custom_command_added",
+            guild_id = guild_id,
+            name = %command.name,
+            "Added custom command"
+        );
+        Ok(())
+    }
+
+    /// The discord command id of the removed command, None when there was none by that name
+    pub async fn remove(&self, guild_id: i64, name: &str) -> Result<Option<u64>, sqlx::Error> {
+        let removed: Option<Option<i64>> = sqlx::query_scalar(
+            "DELETE FROM chloe_custom_commands c USING chloe_guilds g
+             WHERE c.guild_id = g.id AND g.snowflake_id = $1 AND c.name = $2
+             RETURNING c.command_id",
+        )
+        .bind(guild_id)
+        .bind(name)
+        .fetch_optional(&self.db_pool)
+        .await?;
+
+        Ok(removed.map(|command_id| command_id.unwrap_or_default() as u64))
+    }
+
+    pub async fn list(&self, guild_id: i64) -> Result<Vec<CustomCommand>, sqlx::Error> {
+        let rows = sqlx::query(
+            "SELECT c.name, c.description, c.response, c.embed FROM chloe_custom_commands c
+             JOIN chloe_guilds g ON c.guild_id = g.id
+             WHERE g.snowflake_id = $1
+             ORDER BY c.name",
+        )
+        .bind(guild_id)
+        .fetch_all(&self.db_pool)
+        .await?;
+
+        Ok(rows.iter().map(command_from_row).collect())
+    }
+
+    pub async fn find(
+        &self,
+        guild_id: i64,
+        name: &str,
+    ) -> Result<Option<CustomCommand>, sqlx::Error> {
+        let row = sqlx::query(
+            "SELECT c.name, c.description, c.response, c.embed FROM chloe_custom_commands c
+             JOIN chloe_guilds g ON c.guild_id = g.id
+             WHERE g.snowflake_id = $1 AND c.name = $2",
+        )
+        .bind(guild_id)
+        .bind(name)
+        .fetch_optional(&self.db_pool)
+        .await?;
+
+        Ok(row.as_ref().map(command_from_row))
+    }
+}
+
+fn command_from_row(row: &sqlx::postgres::PgRow) -> CustomCommand {
+    CustomCommand {
+        name: row.get("name"),
+        description: row.get("description"),
+        response: row.get("response"),
+        embed: row.get("embed"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_normalize_name() {
+        assert_eq!(normalize_name(" /Rules "), Some("rules".to_string()));
+        assert_eq!(normalize_name("faq_2-new"), Some("faq_2-new".to_string()));
+        assert_eq!(normalize_name(""), None);
+        assert_eq!(normalize_name("two words"), None);
+        assert_eq!(normalize_name(&"a".repeat(33)), None);
+    }
+
+    #[test]
+    fn test_default_description() {
+        assert_eq!(
+            CustomCommand::default_description("read the rules\nplease"),
+            "read the rules"
+        );
+        let long = CustomCommand::default_description(&"x".repeat(150));
+        assert_eq!(long.chars().count(), MAX_DESCRIPTION_LENGTH);
+        assert!(long.ends_with('…'));
+    }
+}
@@ -0,0 +1,275 @@
+use crate::services::intent_router::IntentRouter;
+use crate::services::model_router::qualified_model;
+use crate::services::response_stats::ProviderHealth;
+use crate::tools::DiscordContext;
+use crate::tools::ToolName;
+use crate::tools::tool_executor::ToolPolicy;
+use serde_json::Value;
+
+/// Guild setting with the degraded mode policy
+pub const DEGRADED_MODE_SETTING: &str = "degradedMode";
+
+/// A provider failing this share of its recent requests puts chloe in degraded mode
+const DEFAULT_MAX_ERROR_RATE: f64 = 0.5;
+
+/// Too few requests say nothing about a provider, one failure isn't an outage
+const MIN_HEALTH_SAMPLES: usize = 10;
+
+/// Why chloe is answering degraded
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DegradedReason {
+    ProviderFailing,
+    BudgetExhausted,
+}
+
+/// From the guild setting `degradedMode`, e.g. `{"canned": true, "tools": false,
+/// "cheapModel": true, "model": "gemini-2.0-flash-lite", "maxErrorRate": 0.5,
+/// "overBudget": false}`. While the provider is failing, greetings and other simple intents
+/// get canned replies, everything else is answered on the cheapest model without tools.
+/// A spent token budget still refuses unless `overBudget` is on. `{"enabled": false}` goes
+/// back to apologizing and refusing.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DegradedPolicy {
+    pub enabled: bool,
+    /// Answer simple intents without the model
+    pub canned: bool,
+    /// Keep tools other than sending the reply
+    pub tools: bool,
+    /// Answer the rest on the cheapest model, or on `model` when set
+    pub cheap_model: bool,
+    pub model: Option<String>,
+    /// Recent error rate at which the provider counts as failing
+    pub max_error_rate: f64,
+    /// Keep answering degraded once the daily token budget is spent
+    pub over_budget: bool,
+}
+
+impl Default for DegradedPolicy {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            canned: true,
+            tools: false,
+            cheap_model: true,
+            model: None,
+            max_error_rate: DEFAULT_MAX_ERROR_RATE,
+            over_budget: false,
+        }
+    }
+}
+
+/// How a message is answered while degraded
+#[derive(Debug, Clone, PartialEq)]
+pub enum DegradedPlan {
+    /// A canned reply, the model isn't called
+    Canned(String),
+    /// The model answers with these limits
+    Limited(DegradedMode),
+    /// Nothing in the policy answers it, it's handled like outside degraded mode
+    Unchanged,
+}
+
+/// Limits on a model answer while degraded
+#[derive(Debug, Clone, PartialEq)]
+pub struct DegradedMode {
+    pub reason: DegradedReason,
+    /// Model answering instead of the routed or pinned one
+    pub model: Option<String>,
+    pub tools: bool,
+}
+
+impl DegradedMode {
+    pub fn apply(&self, discord_context: &mut DiscordContext) {
+        if let Some(model) = &self.model {
+            discord_context.llm_config.model = Some(model.clone());
+        }
+        if !self.tools {
+            // discord_send_message stays on no matter what, an allow list of it is the rest off
+            discord_context.tool_policy = ToolPolicy {
+                allow: vec![ToolName::DiscordSendMessage.as_str().to_string()],
+                deny: Vec::new(),
+            };
+        }
+    }
+}
+
+impl DegradedPolicy {
+    pub fn from_setting(setting: Option<&Value>) -> Self {
+        let defaults = Self::default();
+        let Some(setting) = setting else {
+            return defaults;
+        };
+        let flag = |key: &str, default: bool| {
+            setting
+                .get(key)
+                .and_then(|value| value.as_bool())
+                .unwrap_or(default)
+        };
+        let provider = setting
+            .get("provider")
+            .and_then(|provider| provider.as_str())
+            .unwrap_or("gemini");
+        Self {
+            enabled: flag("enabled", defaults.enabled),
+            canned: flag("canned", defaults.canned),
+            tools: flag("tools", defaults.tools),
+            cheap_model: flag("cheapModel", defaults.cheap_model),
+            model: setting
+                .get("model")
+                .and_then(|model| model.as_str())
+                .and_then(|model| qualified_model(provider, model)),
+            max_error_rate: setting
+                .get("maxErrorRate")
+                .and_then(|rate| rate.as_f64())
+                .filter(|rate| *rate > 0.0 && *rate <= 1.0)
+                .unwrap_or(defaults.max_error_rate),
+            over_budget: flag("overBudget", defaults.over_budget),
+        }
+    }
+
+    /// Whether the provider failed often enough lately to answer degraded
+    pub fn is_failing(&self, health: &ProviderHealth) -> bool {
+        self.enabled
+            && health.requests >= MIN_HEALTH_SAMPLES
+            && health.error_rate() >= self.max_error_rate
+    }
+
+    /// How to answer the message while degraded, `cheapest` is used unless the policy
+    /// names a model
+    pub fn plan(
+        &self,
+        reason: DegradedReason,
+        message: &str,
+        has_attachments: bool,
+        cheapest: &str,
+    ) -> DegradedPlan {
+        // the budget is the guild's to break, not chloe's
+        if !self.enabled || (reason == DegradedReason::BudgetExhausted && !self.over_budget) {
+            return DegradedPlan::Unchanged;
+        }
+        if self.canned
+            && !has_attachments
+            && let Some(reply) = IntentRouter::canned_response(&IntentRouter::classify(message))
+        {
+            return DegradedPlan::Canned(reply);
+        }
+        let model = self
+            .cheap_model
+            .then(|| self.model.clone().unwrap_or_else(|| cheapest.to_string()));
+        // a spent budget is only worth breaking for a cheaper answer
+        if model.is_none() && (reason == DegradedReason::BudgetExhausted || self.tools) {
+            return DegradedPlan::Unchanged;
+        }
+        DegradedPlan::Limited(DegradedMode {
+            reason,
+            model,
+            tools: self.tools,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use std::time::Duration;
+
+    fn health(requests: usize, failures: usize) -> ProviderHealth {
+        ProviderHealth {
+            provider: "gemini".to_string(),
+            requests,
+            failures,
+            median_latency: Duration::ZERO,
+            p95_latency: Duration::ZERO,
+        }
+    }
+
+    #[test]
+    fn test_degraded_policy_from_setting() {
+        assert_eq!(
+            DegradedPolicy::from_setting(None),
+            DegradedPolicy::default()
+        );
+        let policy = DegradedPolicy::from_setting(Some(&json!({
+            "canned": false,
+            "tools": true,
+            "model": "gemini-2.5-flash-lite",
+            "maxErrorRate": 3
+        })));
+        assert!(policy.enabled);
+        assert!(!policy.canned);
+        assert!(policy.tools);
+        assert_eq!(policy.model.as_deref(), Some("gemini-2.5-flash-lite"));
+        assert_eq!(policy.max_error_rate, DEFAULT_MAX_ERROR_RATE);
+        assert!(!DegradedPolicy::from_setting(Some(&json!({ "enabled": false }))).enabled);
+    }
+
+    #[test]
+    fn test_is_failing() {
+        let policy = DegradedPolicy::default();
+        assert!(policy.is_failing(&health(20, 10)));
+        assert!(!policy.is_failing(&health(20, 9)));
+        assert!(!policy.is_failing(&health(4, 4)));
+    }
+
+    #[test]
+    fn test_plan() {
+        let policy = DegradedPolicy::default();
+        let reason = DegradedReason::ProviderFailing;
+        assert!(matches!(
+            policy.plan(reason, "hiii", false, "cheap"),
+            DegradedPlan::Canned(_)
+        ));
+        assert_eq!(
+            policy.plan(reason, "explain monads to me", false, "cheap"),
+            DegradedPlan::Limited(DegradedMode {
+                reason,
+                model: Some("cheap".to_string()),
+                tools: false,
+            })
+        );
+
+        let budget = DegradedReason::BudgetExhausted;
+        assert_eq!(
+            policy.plan(budget, "hiii", false, "cheap"),
+            DegradedPlan::Unchanged
+        );
+        assert_eq!(
+            policy.plan(budget, "explain monads to me", false, "cheap"),
+            DegradedPlan::Unchanged
+        );
+        let over_budget = DegradedPolicy::from_setting(Some(&json!({ "overBudget": true })));
+        assert!(matches!(
+            over_budget.plan(budget, "explain monads to me", false, "cheap"),
+            DegradedPlan::Limited(DegradedMode { model: Some(_), .. })
+        ));
+
+        let no_cheap_model = DegradedPolicy {
+            cheap_model: false,
+            over_budget: true,
+            ..DegradedPolicy::default()
+        };
+        assert_eq!(
+            no_cheap_model.plan(
+                DegradedReason::BudgetExhausted,
+                "explain monads",
+                false,
+                "cheap"
+            ),
+            DegradedPlan::Unchanged
+        );
+        assert!(matches!(
+            no_cheap_model.plan(reason, "explain monads", false, "cheap"),
+            DegradedPlan::Limited(DegradedMode { model: None, .. })
+        ));
+
+        let off = DegradedPolicy {
+            enabled: false,
+            ..DegradedPolicy::default()
+        };
+        assert_eq!(
+            off.plan(reason, "hiii", false, "cheap"),
+            DegradedPlan::Unchanged
+        );
+    }
+}
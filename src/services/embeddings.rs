@@ -0,0 +1,211 @@
+use anyhow::{Context, Result, anyhow};
+use reqwest::Client;
+use serde::Deserialize;
+use serde_json::json;
+use std::sync::Arc;
+
+/// Width of every stored embedding, the `vector` column is declared with it
+pub const EMBEDDING_DIMENSIONS: usize = 768;
+
+const GEMINI_EMBEDDING_MODEL: &str = "text-embedding-004";
+
+/// What a text is embedded for, providers that tell the two apart embed queries and the
+/// documents they should find slightly differently
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum EmbeddingTask {
+    Document,
+    Query,
+}
+
+#[async_trait::async_trait]
+pub trait EmbeddingProvider: Send + Sync {
+    /// Model name for logs
+    fn model(&self) -> &str;
+
+    /// One vector of `EMBEDDING_DIMENSIONS` per text, in order
+    async fn embed(&self, texts: &[String], task: EmbeddingTask) -> Result<Vec<Vec<f32>>>;
+}
+
+/// The embedding provider with credentials in the environment: ollama when
+/// `OLLAMA_URL` and `OLLAMA_EMBED_MODEL` are set (a 768 dimension model such as
+/// `nomic-embed-text`), otherwise gemini
+pub fn configured_embedder(client: &Client) -> Option<Arc<dyn EmbeddingProvider>> {
+    let env = |key: &str| std::env::var(key).ok().filter(|value| !value.is_empty());
+    if let (Some(base_url), Some(model)) = (env("OLLAMA_URL"), env("OLLAMA_EMBED_MODEL")) {
+        return Some(Arc::new(OllamaEmbeddings::new(
+            client.clone(),
+            base_url,
+            model,
+        )));
+    }
+    let api_key = env("GEMINI_API_KEY")?;
+    Some(Arc::new(GeminiEmbeddings::new(client.clone(), api_key)))
+}
+
+/// pgvector's text form of a vector, e.g. `[0.1,-0.2]`, bound as a string and cast in SQL
+pub fn vector_literal(embedding: &[f32]) -> String {
+    let values: Vec<String> = embedding.iter().map(|value| value.to_string()).collect();
+    format!("[{}]", values.join(","))
+}
+
+fn check_dimensions(
+    model: &str,
+    embeddings: Vec<Vec<f32>>,
+    expected: usize,
+) -> Result<Vec<Vec<f32>>> {
+    if embeddings.len() != expected {
+        return Err(anyhow!(
+            "{} returned {} embeddings for {} texts",
+            model,
+            embeddings.len(),
+            expected
+        ));
+    }
+    if let Some(embedding) = embeddings
+        .iter()
+        .find(|embedding| embedding.len() != EMBEDDING_DIMENSIONS)
+    {
+        return Err(anyhow!(
+            "{} embeds into {} dimensions, the memory store needs {}",
+            model,
+            embedding.len(),
+            EMBEDDING_DIMENSIONS
+        ));
+    }
+    Ok(embeddings)
+}
+
+#[derive(Debug, Deserialize)]
+struct GeminiEmbedResponse {
+    #[serde(default)]
+    embeddings: Vec<GeminiEmbedding>,
+}
+
+#[derive(Debug, Deserialize)]
+struct GeminiEmbedding {
+    values: Vec<f32>,
+}
+
+pub struct GeminiEmbeddings {
+    client: Client,
+    api_key: String,
+}
+
+impl GeminiEmbeddings {
+    pub fn new(client: Client, api_key: String) -> Self {
+        Self { client, api_key }
+    }
+}
+
+#[async_trait::async_trait]
+impl EmbeddingProvider for GeminiEmbeddings {
+    fn model(&self) -> &str {
+        GEMINI_EMBEDDING_MODEL
+    }
+
+    async fn embed(&self, texts: &[String], task: EmbeddingTask) -> Result<Vec<Vec<f32>>> {
+        let task_type = match task {
+            EmbeddingTask::Document => "RETRIEVAL_DOCUMENT",
+            EmbeddingTask::Query => "RETRIEVAL_QUERY",
+        };
+        let model = format!("models/{}", GEMINI_EMBEDDING_MODEL);
+        let requests: Vec<_> = texts
+            .iter()
+            .map(|text| {
+                json!({
+                    "model": model,
+                    "content": { "parts": [{ "text": text }] },
+                    "taskType": task_type,
+                })
+            })
+            .collect();
+
+        let response: GeminiEmbedResponse = self
+            .client
+            .post(format!(
+                "https://generativelanguage.googleapis.com/v1beta/{}:batchEmbedContents?key={}",
+                model, self.api_key
+            ))
+            .json(&json!({ "requests": requests }))
+            .send()
+            .await
+            .context("Failed to reach the gemini embedding api")?
+            .error_for_status()
+            .context("Gemini embedding request failed")?
+            .json()
+            .await
+            .context("Failed to parse gemini embeddings")?;
+
+        let embeddings = response
+            .embeddings
+            .into_iter()
+            .map(|embedding| embedding.values)
+            .collect();
+        check_dimensions(GEMINI_EMBEDDING_MODEL, embeddings, texts.len())
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct OllamaEmbedResponse {
+    #[serde(default)]
+    embeddings: Vec<Vec<f32>>,
+}
+
+pub struct OllamaEmbeddings {
+    client: Client,
+    base_url: String,
+    model: String,
+}
+
+impl OllamaEmbeddings {
+    pub fn new(client: Client, base_url: String, model: String) -> Self {
+        Self {
+            client,
+            base_url: base_url.trim_end_matches('/').to_string(),
+            model,
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl EmbeddingProvider for OllamaEmbeddings {
+    fn model(&self) -> &str {
+        &self.model
+    }
+
+    async fn embed(&self, texts: &[String], _task: EmbeddingTask) -> Result<Vec<Vec<f32>>> {
+        let response: OllamaEmbedResponse = self
+            .client
+            .post(format!("{}/api/embed", self.base_url))
+            .json(&json!({ "model": self.model, "input": texts }))
+            .send()
+            .await
+            .context("Failed to reach ollama")?
+            .error_for_status()
+            .context("Ollama embedding request failed")?
+            .json()
+            .await
+            .context("Failed to parse ollama embeddings")?;
+
+        check_dimensions(&self.model, response.embeddings, texts.len())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_vector_literal() {
+        assert_eq!(vector_literal(&[0.5, -1.0, 0.25]), "[0.5,-1,0.25]");
+        assert_eq!(vector_literal(&[]), "[]");
+    }
+
+    #[test]
+    fn test_check_dimensions() {
+        let ok = vec![vec![0.0; EMBEDDING_DIMENSIONS]];
+        assert!(check_dimensions("test", ok, 1).is_ok());
+        assert!(check_dimensions("test", vec![vec![0.0; 3]], 1).is_err());
+        assert!(check_dimensions("test", Vec::new(), 1).is_err());
+    }
+}
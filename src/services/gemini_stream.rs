@@ -0,0 +1,202 @@
+use crate::services::gemini_types::{
+    Candidate, GeminiResponse, PromptFeedback, ResponseContent, ResponsePart,
+};
+
+/// Gemini's streaming endpoint for a generateContent url, answering in server-sent events
+pub fn stream_url(url: &str) -> String {
+    url.replacen(":generateContent?", ":streamGenerateContent?alt=sse&", 1)
+}
+
+/// Splits a server-sent event body into the data of each event. Network chunks can end
+/// mid-line or mid-character, so incomplete lines wait in the buffer for the next chunk.
+#[derive(Debug, Default)]
+pub struct SseParser {
+    buffer: Vec<u8>,
+    data: String,
+}
+
+impl SseParser {
+    /// Feed the next chunk of the body, returning the events it completed
+    pub fn push(&mut self, chunk: &[u8]) -> Vec<String> {
+        self.buffer.extend_from_slice(chunk);
+
+        let mut events = Vec::new();
+        while let Some(end) = self.buffer.iter().position(|&byte| byte == b'\n') {
+            let line: Vec<u8> = self.buffer.drain(..=end).collect();
+            let line = String::from_utf8_lossy(&line);
+            let line = line.trim_end_matches(['\n', '\r']);
+
+            if line.is_empty() {
+                if !self.data.is_empty() {
+                    events.push(std::mem::take(&mut self.data));
+                }
+            } else if let Some(data) = line.strip_prefix("data:") {
+                if !self.data.is_empty() {
+                    self.data.push('\n');
+                }
+                self.data.push_str(data.strip_prefix(' ').unwrap_or(data));
+            }
+            // comments, event names and ids carry nothing gemini uses
+        }
+        events
+    }
+
+    /// Whatever event was still open when the body ended
+    pub fn finish(mut self) -> Option<String> {
+        let mut events = self.push(b"\n\n");
+        events.pop()
+    }
+}
+
+/// Merges streamed chunks back into the single response the rest of the service expects.
+/// Text arrives as deltas, function calls arrive whole in one chunk.
+#[derive(Debug, Default)]
+pub struct ResponseAccumulator {
+    text: String,
+    function_calls: Vec<ResponsePart>,
+    last: Option<GeminiResponse>,
+    prompt_feedback: Option<PromptFeedback>,
+}
+
+impl ResponseAccumulator {
+    pub fn push(&mut self, chunk: GeminiResponse) {
+        let parts = chunk
+            .candidates
+            .iter()
+            .flatten()
+            .take(1)
+            .filter_map(|candidate| candidate.content.as_ref())
+            .filter_map(|content| content.parts.as_ref())
+            .flatten();
+        for part in parts {
+            match part {
+                ResponsePart::Text { text } => self.text.push_str(text),
+                ResponsePart::FunctionCall { .. } => self.function_calls.push(part.clone()),
+            }
+        }
+
+        if self.prompt_feedback.is_none() {
+            self.prompt_feedback = chunk.prompt_feedback.clone();
+        }
+        self.last = Some(chunk);
+    }
+
+    /// Text streamed so far
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+
+    pub fn has_function_call(&self) -> bool {
+        !self.function_calls.is_empty()
+    }
+
+    pub fn finish(self) -> GeminiResponse {
+        let mut parts = Vec::new();
+        if !self.text.is_empty() {
+            parts.push(ResponsePart::Text { text: self.text });
+        }
+        parts.extend(self.function_calls);
+
+        // the last chunk carries the finish reason, safety ratings and the usage totals
+        let last = self.last.unwrap_or(GeminiResponse {
+            candidates: None,
+            prompt_feedback: None,
+            model_version: None,
+            response_id: None,
+            usage_metadata: None,
+        });
+        let candidate = last
+            .candidates
+            .as_ref()
+            .and_then(|candidates| candidates.first());
+        let finish_reason = candidate.and_then(|candidate| candidate.finish_reason.clone());
+        let safety_ratings = candidate.and_then(|candidate| candidate.safety_ratings.clone());
+
+        GeminiResponse {
+            candidates: (!parts.is_empty() || finish_reason.is_some()).then(|| {
+                vec![Candidate {
+                    content: Some(ResponseContent {
+                        parts: Some(parts),
+                        role: Some("model".to_string()),
+                    }),
+                    finish_reason,
+                    index: Some(0),
+                    safety_ratings,
+                }]
+            }),
+            prompt_feedback: self.prompt_feedback,
+            model_version: last.model_version,
+            response_id: last.response_id,
+            usage_metadata: last.usage_metadata,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_sse_parser_handles_split_chunks() {
+        let mut parser = SseParser::default();
+        assert!(parser.push(b"data: {\"a\":").is_empty());
+        assert_eq!(parser.push(b"1}\r\n\r\ndata: {\"b\":2}\n"), [r#"{"a":1}"#]);
+        assert_eq!(parser.push(b"\n: keep-alive\n\n"), [r#"{"b":2}"#]);
+
+        // a multibyte character split across chunks survives
+        let mut parser = SseParser::default();
+        let event = "data: héllo\n\n".as_bytes();
+        assert!(parser.push(&event[..8]).is_empty());
+        assert_eq!(parser.push(&event[8..]), ["héllo"]);
+
+        let mut parser = SseParser::default();
+        parser.push(b"data: tail");
+        assert_eq!(parser.finish().as_deref(), Some("tail"));
+    }
+
+    #[test]
+    fn test_accumulator_merges_text_and_keeps_function_calls() {
+        let chunks = [
+            json!({"candidates": [{"content": {"parts": [{"text": "Let me "}], "role": "model"}}]}),
+            json!({"candidates": [{"content": {"parts": [{"text": "check"}], "role": "model"}}]}),
+            json!({
+                "candidates": [{
+                    "content": {"parts": [{"functionCall": {"name": "web_search", "args": {"query": "rust"}}}], "role": "model"},
+                    "finishReason": "STOP"
+                }],
+                "usageMetadata": {"promptTokenCount": 10, "candidatesTokenCount": 4, "totalTokenCount": 14}
+            }),
+        ];
+
+        let mut accumulator = ResponseAccumulator::default();
+        for chunk in chunks {
+            accumulator.push(serde_json::from_value(chunk).unwrap());
+            if accumulator.text() == "Let me " {
+                assert!(!accumulator.has_function_call());
+            }
+        }
+        assert!(accumulator.has_function_call());
+
+        let response = accumulator.finish();
+        assert_eq!(response.get_text(), Some("Let me check"));
+        assert_eq!(
+            response.get_function_call().map(|call| call.name.as_str()),
+            Some("web_search")
+        );
+        assert_eq!(
+            response
+                .usage_metadata
+                .and_then(|usage| usage.total_token_count),
+            Some(14)
+        );
+    }
+
+    #[test]
+    fn test_stream_url() {
+        assert_eq!(
+            stream_url("https://example.com/v1beta/models/gemini:generateContent?key=abc"),
+            "https://example.com/v1beta/models/gemini:streamGenerateContent?alt=sse&key=abc"
+        );
+    }
+}
@@ -1,3 +1,4 @@
+use crate::services::content_safety::SafetyThresholds;
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
 
@@ -10,6 +11,15 @@ pub struct GeminiRequest {
     pub tools: Option<Vec<Tool>>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub safety_settings: Option<Vec<SafetySetting>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub generation_config: Option<GenerationConfig>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GenerationConfig {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub temperature: Option<f32>,
 }
 
 #[derive(Debug, Clone, Serialize)]
@@ -91,8 +101,48 @@ pub struct GeminiResponse {
 #[serde(rename_all = "camelCase")]
 pub struct Candidate {
     pub content: Option<ResponseContent>,
-    pub finish_reason: Option<String>,
+    pub finish_reason: Option<FinishReason>,
     pub index: Option<i32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub safety_ratings: Option<Vec<SafetyRating>>,
+}
+
+/// Why the model stopped generating. Providers' raw reasons are folded into the cases
+/// chloe reacts to, the rest are kept as they came.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(from = "String", into = "String")]
+pub enum FinishReason {
+    Stop,
+    /// Cut off by the output token limit
+    Length,
+    ToolCalls,
+    /// Stopped by a safety or content filter, with the provider's reason
+    ContentFilter(String),
+    Other(String),
+}
+
+impl From<String> for FinishReason {
+    fn from(reason: String) -> Self {
+        match reason.to_uppercase().as_str() {
+            "STOP" => Self::Stop,
+            "MAX_TOKENS" | "LENGTH" => Self::Length,
+            "TOOL_CALLS" => Self::ToolCalls,
+            "SAFETY" | "RECITATION" | "BLOCKLIST" | "PROHIBITED_CONTENT" | "SPII"
+            | "IMAGE_SAFETY" | "CONTENT_FILTER" => Self::ContentFilter(reason),
+            _ => Self::Other(reason),
+        }
+    }
+}
+
+impl From<FinishReason> for String {
+    fn from(reason: FinishReason) -> Self {
+        match reason {
+            FinishReason::Stop => "STOP".to_string(),
+            FinishReason::Length => "MAX_TOKENS".to_string(),
+            FinishReason::ToolCalls => "TOOL_CALLS".to_string(),
+            FinishReason::ContentFilter(reason) | FinishReason::Other(reason) => reason,
+        }
+    }
 }
 
 #[derive(Debug, Clone, Deserialize, Serialize)]
@@ -126,6 +176,9 @@ pub struct PromptFeedback {
 pub struct SafetyRating {
     pub category: String,
     pub probability: String,
+    /// Set on the rating that made the provider block
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub blocked: Option<bool>,
 }
 
 #[derive(Debug, Clone, Deserialize, Serialize)]
@@ -147,6 +200,7 @@ impl GeminiRequest {
             }],
             tools: None,
             safety_settings: None,
+            generation_config: None,
         }
     }
 
@@ -178,6 +232,48 @@ impl GeminiRequest {
         self
     }
 
+    /// Sample at `temperature`, None keeps the model's default
+    pub fn with_temperature(mut self, temperature: Option<f32>) -> Self {
+        self.generation_config = temperature.map(|temperature| GenerationConfig {
+            temperature: Some(temperature),
+        });
+        self
+    }
+
+    pub fn temperature(&self) -> Option<f32> {
+        self.generation_config.as_ref()?.temperature
+    }
+
+    /// Add a text part at the end of the prompt
+    pub fn with_text(mut self, text: &str) -> Self {
+        if let Some(content) = self.contents.get_mut(0) {
+            content.parts.push(Part::Text {
+                text: text.to_string(),
+            });
+        }
+        self
+    }
+
+    /// Continue a reply the model started but didn't finish, as text without tools
+    pub fn with_continuation(mut self, partial: &str) -> Self {
+        if let Some(content) = self.contents.get_mut(0) {
+            content.parts.push(Part::Text {
+                text: format!(
+                    "## Your reply so far\nIt was cut off by the length limit. Continue it exactly where it stops, without repeating any of it:\n\n{}",
+                    partial
+                ),
+            });
+            // a request carrying tool calls has to keep declaring its tools
+            let has_tool_calls = content.parts.iter().any(|part| {
+                matches!(part, Part::FunctionCall { .. } | Part::FunctionResponse { .. })
+            });
+            if !has_tool_calls {
+                self.tools = None;
+            }
+        }
+        self
+    }
+
     pub fn add_function_call_parts(
         mut self,
         function_call: &FunctionCall,
@@ -225,6 +321,47 @@ impl GeminiResponse {
             })
     }
 
+    /// Every function call in the first candidate, the model may ask for several at once
+    pub fn function_calls_mut(&mut self) -> impl Iterator<Item = &mut FunctionCall> {
+        self.candidates
+            .iter_mut()
+            .flatten()
+            .take(1)
+            .filter_map(|candidate| candidate.content.as_mut())
+            .filter_map(|content| content.parts.as_mut())
+            .flatten()
+            .filter_map(|part| match part {
+                ResponsePart::FunctionCall { function_call } => Some(function_call),
+                _ => None,
+            })
+    }
+
+    /// The provider couldn't turn what the model wrote into a function call
+    pub fn is_malformed_call(&self) -> bool {
+        matches!(
+            self.finish_reason(),
+            Some(FinishReason::Other(reason)) if reason == "MALFORMED_FUNCTION_CALL"
+        )
+    }
+
+    pub fn get_function_calls(&self) -> Vec<&FunctionCall> {
+        self.candidates
+            .as_ref()
+            .and_then(|candidates| candidates.first())
+            .and_then(|candidate| candidate.content.as_ref())
+            .and_then(|content| content.parts.as_ref())
+            .map(|parts| {
+                parts
+                    .iter()
+                    .filter_map(|part| match part {
+                        ResponsePart::FunctionCall { function_call } => Some(function_call),
+                        _ => None,
+                    })
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
     pub fn has_function_call(&self) -> bool {
         self.get_function_call().is_some()
     }
@@ -233,6 +370,48 @@ impl GeminiResponse {
         self.get_text().is_some()
     }
 
+    /// No text and no function calls, what some models send back instead of an answer
+    pub fn is_empty(&self) -> bool {
+        !self.has_function_call() && self.get_text().is_none_or(|text| text.trim().is_empty())
+    }
+
+    pub fn finish_reason(&self) -> Option<&FinishReason> {
+        self.candidates
+            .as_ref()?
+            .first()?
+            .finish_reason
+            .as_ref()
+    }
+
+    /// Add text generated to continue this response, e.g. after it hit the token limit
+    pub fn append_text(&mut self, more: &str, finish_reason: Option<FinishReason>) {
+        let Some(candidate) = self
+            .candidates
+            .as_mut()
+            .and_then(|candidates| candidates.first_mut())
+        else {
+            return;
+        };
+        candidate.finish_reason = finish_reason;
+        let parts = candidate
+            .content
+            .get_or_insert_with(|| ResponseContent {
+                parts: None,
+                role: Some("model".to_string()),
+            })
+            .parts
+            .get_or_insert_with(Vec::new);
+        match parts.iter_mut().find_map(|part| match part {
+            ResponsePart::Text { text } => Some(text),
+            _ => None,
+        }) {
+            Some(text) => text.push_str(more),
+            None => parts.push(ResponsePart::Text {
+                text: more.to_string(),
+            }),
+        }
+    }
+
     pub fn is_blocked(&self) -> bool {
         self.prompt_feedback.as_ref()
             .and_then(|f| f.block_reason.as_ref())
@@ -243,26 +422,115 @@ impl GeminiResponse {
         self.prompt_feedback.as_ref()
             .and_then(|f| f.block_reason.as_deref())
     }
+
+    /// The harm category a block was for, from the prompt feedback or the candidate's
+    /// ratings. The rating marked blocked wins, otherwise the most likely harm.
+    pub fn blocked_category(&self) -> Option<&str> {
+        let prompt_ratings = self
+            .prompt_feedback
+            .as_ref()
+            .and_then(|feedback| feedback.safety_ratings.as_ref());
+        let candidate_ratings = self
+            .candidates
+            .as_ref()
+            .and_then(|candidates| candidates.first())
+            .and_then(|candidate| candidate.safety_ratings.as_ref());
+        let ratings: Vec<&SafetyRating> = prompt_ratings
+            .into_iter()
+            .chain(candidate_ratings)
+            .flatten()
+            .collect();
+
+        let likelihood = |rating: &SafetyRating| match rating.probability.as_str() {
+            "HIGH" => 3,
+            "MEDIUM" => 2,
+            "LOW" => 1,
+            _ => 0,
+        };
+        ratings
+            .iter()
+            .find(|rating| rating.blocked == Some(true))
+            .or_else(|| {
+                ratings
+                    .iter()
+                    .filter(|rating| likelihood(rating) > 1)
+                    .max_by_key(|rating| likelihood(rating))
+            })
+            .map(|rating| rating.category.as_str())
+    }
 }
 
-// Standard safety settings
+// Standard safety settings, for requests without a guild
 pub fn default_safety_settings() -> Vec<SafetySetting> {
-    vec![
-        SafetySetting {
-            category: "HARM_CATEGORY_HARASSMENT".to_string(),
-            threshold: "BLOCK_NONE".to_string(),
-        },
-        SafetySetting {
-            category: "HARM_CATEGORY_HATE_SPEECH".to_string(),
-            threshold: "BLOCK_MEDIUM_AND_ABOVE".to_string(),
-        },
-        SafetySetting {
-            category: "HARM_CATEGORY_SEXUALLY_EXPLICIT".to_string(),
-            threshold: "BLOCK_NONE".to_string(),
-        },
-        SafetySetting {
-            category: "HARM_CATEGORY_DANGEROUS_CONTENT".to_string(),
-            threshold: "BLOCK_NONE".to_string(),
-        },
-    ]
-}
\ No newline at end of file
+    SafetyThresholds::default().gemini_settings()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_finish_reason_from_raw() {
+        let reason = |raw: &str| FinishReason::from(raw.to_string());
+        assert_eq!(reason("STOP"), FinishReason::Stop);
+        assert_eq!(reason("MAX_TOKENS"), FinishReason::Length);
+        assert_eq!(reason("length"), FinishReason::Length);
+        assert_eq!(
+            reason("RECITATION"),
+            FinishReason::ContentFilter("RECITATION".to_string())
+        );
+        assert_eq!(
+            reason("MALFORMED_FUNCTION_CALL"),
+            FinishReason::Other("MALFORMED_FUNCTION_CALL".to_string())
+        );
+        assert_eq!(String::from(FinishReason::Length), "MAX_TOKENS");
+    }
+
+    #[test]
+    fn test_append_text_continues_response() {
+        let mut response: GeminiResponse = serde_json::from_value(json!({
+            "candidates": [{
+                "content": {"parts": [{"text": "the first half"}], "role": "model"},
+                "finishReason": "MAX_TOKENS"
+            }]
+        }))
+        .unwrap();
+        assert_eq!(response.finish_reason(), Some(&FinishReason::Length));
+
+        response.append_text(" and the rest", Some(FinishReason::Stop));
+
+        assert_eq!(response.get_text(), Some("the first half and the rest"));
+        assert_eq!(response.finish_reason(), Some(&FinishReason::Stop));
+    }
+
+    #[test]
+    fn test_blocked_category() {
+        let response: GeminiResponse = serde_json::from_value(json!({
+            "candidates": [{
+                "finishReason": "SAFETY",
+                "safetyRatings": [
+                    {"category": "HARM_CATEGORY_HARASSMENT", "probability": "MEDIUM"},
+                    {"category": "HARM_CATEGORY_DANGEROUS_CONTENT", "probability": "LOW", "blocked": true}
+                ]
+            }]
+        }))
+        .unwrap();
+        assert_eq!(
+            response.blocked_category(),
+            Some("HARM_CATEGORY_DANGEROUS_CONTENT")
+        );
+
+        let response: GeminiResponse = serde_json::from_value(json!({
+            "promptFeedback": {
+                "blockReason": "SAFETY",
+                "safetyRatings": [
+                    {"category": "HARM_CATEGORY_HARASSMENT", "probability": "MEDIUM"},
+                    {"category": "HARM_CATEGORY_HATE_SPEECH", "probability": "HIGH"}
+                ]
+            }
+        }))
+        .unwrap();
+        assert_eq!(response.blocked_category(), Some("HARM_CATEGORY_HATE_SPEECH"));
+    }
+}
@@ -0,0 +1,171 @@
+use crate::services::change_events::{ChangeEvent, ChangeEvents};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::{PgPool, Row, postgres::PgRow};
+use std::sync::Arc;
+use tracing::info;
+
+/// Versions shown by `/prompt history`
+pub const PROMPT_HISTORY_LIMIT: i64 = 10;
+
+/// A version of a guild's own system prompt, used instead of the global one while active
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct GuildPrompt {
+    pub version: i32,
+    pub content: String,
+    pub created_by: Option<String>,
+    pub is_active: bool,
+    pub created_at: DateTime<Utc>,
+}
+
+fn guild_prompt_from_row(row: &PgRow) -> GuildPrompt {
+    GuildPrompt {
+        version: row.get("version"),
+        content: row.get("content"),
+        created_by: row.get("created_by"),
+        is_active: row.get("is_active"),
+        created_at: row.get("created_at"),
+    }
+}
+
+/// Per-guild prompt versions, kept apart from `chloe_prompts` so global versions and
+/// activations never touch a guild's
+pub struct GuildPromptService {
+    db_pool: PgPool,
+    change_events: Option<Arc<ChangeEvents>>,
+}
+
+impl GuildPromptService {
+    pub fn new(db_pool: PgPool) -> Self {
+        Self {
+            db_pool,
+            change_events: None,
+        }
+    }
+
+    /// Also publish every activation and deactivation
+    pub fn with_change_events(mut self, change_events: Arc<ChangeEvents>) -> Self {
+        self.change_events = Some(change_events);
+        self
+    }
+
+    async fn publish(&self, event: ChangeEvent) {
+        if let Some(change_events) = &self.change_events {
+            change_events.publish(event).await;
+        }
+    }
+
+    /// The guild's active prompt, None while it uses the global one
+    pub async fn active(&self, guild_id: u64) -> Result<Option<GuildPrompt>, sqlx::Error> {
+        let row = sqlx::query(
+            "SELECT p.version, p.content, p.created_by, p.is_active, p.created_at
+             FROM chloe_guild_prompts p
+             JOIN chloe_guilds g ON p.guild_id = g.id
+             WHERE g.snowflake_id = $1 AND p.is_active",
+        )
+        .bind(guild_id as i64)
+        .fetch_optional(&self.db_pool)
+        .await?;
+        Ok(row.as_ref().map(guild_prompt_from_row))
+    }
+
+    /// Save the prompt as the guild's next version and make it the active one. None when
+    /// the guild was never registered.
+    pub async fn create_version(
+        &self,
+        guild_id: u64,
+        content: &str,
+        created_by: Option<&str>,
+    ) -> Result<Option<i32>, sqlx::Error> {
+        let mut tx = self.db_pool.begin().await?;
+
+        let Some(guild_uuid) =
+            sqlx::query_scalar::<_, String>("SELECT id FROM chloe_guilds WHERE snowflake_id = $1")
+                .bind(guild_id as i64)
+                .fetch_optional(&mut *tx)
+                .await?
+        else {
+            return Ok(None);
+        };
+
+        sqlx::query("UPDATE chloe_guild_prompts SET is_active = false WHERE guild_id = $1")
+            .bind(&guild_uuid)
+            .execute(&mut *tx)
+            .await?;
+        let version: i32 = sqlx::query_scalar(
+            "INSERT INTO chloe_guild_prompts (guild_id, version, content, created_by, is_active)
+             SELECT $1, COALESCE(MAX(version), 0) + 1, $2, $3, true
+             FROM chloe_guild_prompts WHERE guild_id = $1
+             RETURNING version",
+        )
+        .bind(&guild_uuid)
+        .bind(content)
+        .bind(created_by)
+        .fetch_one(&mut *tx)
+        .await?;
+
+        tx.commit().await?;
+
+        info!(
+            event = "guild_prompt_version_created",
+            guild_id = guild_id,
+            version = version,
+            created_by = created_by.unwrap_or("unknown"),
+            "Guild prompt version created and activated"
+        );
+        self.publish(ChangeEvent::GuildPromptActivated {
+            guild_id: guild_id.to_string(),
+            version,
+            created_by: created_by.map(str::to_string),
+        })
+        .await;
+        Ok(Some(version))
+    }
+
+    /// Go back to the global prompt, the guild's versions are kept. False when none was
+    /// active.
+    pub async fn deactivate(&self, guild_id: u64) -> Result<bool, sqlx::Error> {
+        let result = sqlx::query(
+            "UPDATE chloe_guild_prompts p SET is_active = false
+             FROM chloe_guilds g
+             WHERE p.guild_id = g.id AND g.snowflake_id = $1 AND p.is_active",
+        )
+        .bind(guild_id as i64)
+        .execute(&self.db_pool)
+        .await?;
+
+        if result.rows_affected() > 0 {
+            info!(
+                event = "guild_prompt_deactivated",
+                guild_id = guild_id,
+                "Guild went back to the global prompt"
+            );
+            self.publish(ChangeEvent::GuildPromptDeactivated {
+                guild_id: guild_id.to_string(),
+            })
+            .await;
+        }
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// The guild's latest versions, newest first
+    pub async fn history(
+        &self,
+        guild_id: u64,
+        limit: i64,
+    ) -> Result<Vec<GuildPrompt>, sqlx::Error> {
+        let rows = sqlx::query(
+            "SELECT p.version, p.content, p.created_by, p.is_active, p.created_at
+             FROM chloe_guild_prompts p
+             JOIN chloe_guilds g ON p.guild_id = g.id
+             WHERE g.snowflake_id = $1
+             ORDER BY p.version DESC
+             LIMIT $2",
+        )
+        .bind(guild_id as i64)
+        .bind(limit)
+        .fetch_all(&self.db_pool)
+        .await?;
+        Ok(rows.iter().map(guild_prompt_from_row).collect())
+    }
+}
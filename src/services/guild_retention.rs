@@ -0,0 +1,142 @@
+use chrono::{DateTime, Duration, Utc};
+use sqlx::PgPool;
+use tracing::info;
+
+const DEFAULT_RETENTION_DAYS: i64 = 30;
+
+/// How long a guild's data is kept after chloe is removed, from `GUILD_RETENTION_DAYS`.
+/// Being added back within it picks up where the guild left off.
+pub fn retention_period() -> Duration {
+    let days = std::env::var("GUILD_RETENTION_DAYS")
+        .ok()
+        .and_then(|days| days.trim().parse::<i64>().ok())
+        .filter(|days| *days >= 0)
+        .unwrap_or(DEFAULT_RETENTION_DAYS);
+    Duration::days(days)
+}
+
+/// Mark the guild inactive and stop work queued for it. Scheduled messages of an inactive
+/// guild are skipped rather than removed, so they resume if chloe is added back.
+/// Returns when the guild left, None when it was already inactive or never registered.
+pub async fn mark_left(
+    db_pool: &PgPool,
+    guild_id: u64,
+) -> Result<Option<DateTime<Utc>>, sqlx::Error> {
+    let mut tx = db_pool.begin().await?;
+    let left_at: Option<DateTime<Utc>> = sqlx::query_scalar(
+        "UPDATE chloe_guilds SET left_at = NOW(), modified_at = CURRENT_TIMESTAMP
+         WHERE snowflake_id = $1 AND left_at IS NULL
+         RETURNING left_at",
+    )
+    .bind(guild_id as i64)
+    .fetch_optional(&mut *tx)
+    .await?;
+
+    // nobody there to read them anymore
+    let reminders = sqlx::query(
+        "UPDATE chloe_reminders SET status = 'cancelled' WHERE guild_id = $1 AND status = 'pending'",
+    )
+    .bind(guild_id as i64)
+    .execute(&mut *tx)
+    .await?;
+    tx.commit().await?;
+
+    info!(
+        event = "guild_marked_inactive",
+        guild_id = guild_id,
+        cancelled_reminders = reminders.rows_affected(),
+        "Marked guild inactive"
+    );
+    Ok(left_at)
+}
+
+/// Mark a guild chloe was added back to active again and cancel its pending purge
+pub async fn mark_rejoined(db_pool: &PgPool, guild_id: u64) -> Result<bool, sqlx::Error> {
+    let mut tx = db_pool.begin().await?;
+    let result = sqlx::query(
+        "UPDATE chloe_guilds SET left_at = NULL, modified_at = CURRENT_TIMESTAMP
+         WHERE snowflake_id = $1 AND left_at IS NOT NULL",
+    )
+    .bind(guild_id as i64)
+    .execute(&mut *tx)
+    .await?;
+    sqlx::query(
+        "UPDATE chloe_scheduled_jobs SET status = 'cancelled', modified_at = CURRENT_TIMESTAMP
+         WHERE job_type = 'purge_guild' AND status = 'pending'
+         AND (payload->>'guild_id')::bigint = $1",
+    )
+    .bind(guild_id as i64)
+    .execute(&mut *tx)
+    .await?;
+    tx.commit().await?;
+
+    let rejoined = result.rows_affected() > 0;
+    if rejoined {
+        info!(
+            event = "guild_marked_active",
+            guild_id = guild_id,
+            "Guild is active again"
+        );
+    }
+    Ok(rejoined)
+}
+
+/// Delete the settings, conversations, memories and features of a guild that is still
+/// inactive. The guild row and its usage totals are kept. Returns whether anything was
+/// purged, false when chloe was added back in the meantime.
+pub async fn purge_guild(db_pool: &PgPool, guild_id: u64) -> Result<bool, sqlx::Error> {
+    let mut tx = db_pool.begin().await?;
+    let internal_id: Option<String> = sqlx::query_scalar(
+        "SELECT id FROM chloe_guilds WHERE snowflake_id = $1 AND left_at IS NOT NULL FOR UPDATE",
+    )
+    .bind(guild_id as i64)
+    .fetch_optional(&mut *tx)
+    .await?;
+    let Some(internal_id) = internal_id else {
+        return Ok(false);
+    };
+
+    // embeddings go with the messages and memories they were made from
+    for table in [
+        "chloe_messages",
+        "chloe_reminders",
+        "chloe_saved_conversations",
+    ] {
+        sqlx::query(&format!("DELETE FROM {} WHERE guild_id = $1", table))
+            .bind(guild_id as i64)
+            .execute(&mut *tx)
+            .await?;
+    }
+    for table in [
+        "chloe_user_memories",
+        "chloe_reaction_roles",
+        "chloe_triggers",
+        "chloe_modmail_threads",
+        "chloe_scheduled_messages",
+        "chloe_starboard",
+        "chloe_custom_commands",
+        "chloe_guild_prompts",
+        "chloe_levels",
+        "chloe_level_rewards",
+        "chloe_guild_users",
+        "chloe_guilds_settings",
+    ] {
+        sqlx::query(&format!("DELETE FROM {} WHERE guild_id = $1", table))
+            .bind(&internal_id)
+            .execute(&mut *tx)
+            .await?;
+    }
+    // without an owner the next sync after a rejoin sets the guild up like a new one
+    sqlx::query("UPDATE chloe_guilds SET owner_id = NULL WHERE id = $1")
+        .bind(&internal_id)
+        .execute(&mut *tx)
+        .await?;
+    tx.commit().await?;
+
+    info!(
+        event = "guild_data_purged",
+        guild_id = guild_id,
+        "Purged data of inactive guild"
+    );
+    Ok(true)
+}
@@ -1,3 +1,8 @@
+use crate::services::change_events::{ChangeEvent, ChangeEvents};
+use crate::services::ping_reply::{PING_REPLY_SETTING, PING_RESPONSES_SETTING, PingResponses};
+use crate::services::quiet_hours::QuietHours;
+use crate::services::reply_visibility::{REPLY_VISIBILITY_SETTING, ReplyKind, ReplyVisibility};
+use crate::utils::cache_stats::{CacheSnapshot, CacheStats};
 use serde_json::Value;
 use sqlx::{PgPool, Row};
 use std::collections::HashMap;
@@ -10,6 +15,9 @@ pub struct GuildService {
     db_pool: PgPool,
     settings_cache: Arc<RwLock<HashMap<i64, Value>>>,
     role_cache: Arc<RwLock<HashMap<(i64, i64), String>>>, // (guild_id, user_id) -> role
+    settings_cache_stats: Arc<CacheStats>,
+    role_cache_stats: Arc<CacheStats>,
+    change_events: Option<Arc<ChangeEvents>>,
 }
 
 impl GuildService {
@@ -18,15 +26,26 @@ impl GuildService {
             db_pool,
             settings_cache: Arc::new(RwLock::new(HashMap::new())),
             role_cache: Arc::new(RwLock::new(HashMap::new())),
+            settings_cache_stats: Arc::new(CacheStats::default()),
+            role_cache_stats: Arc::new(CacheStats::default()),
+            change_events: None,
         }
     }
 
+    /// Also publish every settings update
+    pub fn with_change_events(mut self, change_events: Arc<ChangeEvents>) -> Self {
+        self.change_events = Some(change_events);
+        self
+    }
+
     pub async fn get_user_role(&self, guild_id: i64, user_id: i64) -> Option<String> {
         let cache_key = (guild_id, user_id);
 
         {
             let cache = self.role_cache.read().await;
-            if let Some(role) = cache.get(&cache_key) {
+            let cached = cache.get(&cache_key);
+            self.role_cache_stats.record(cached.is_some());
+            if let Some(role) = cached {
                 return Some(role.clone());
             }
         }
@@ -51,7 +70,9 @@ impl GuildService {
         // Check cache first
         {
             let cache = self.settings_cache.read().await;
-            if let Some(settings) = cache.get(&guild_id) {
+            let cached = cache.get(&guild_id);
+            self.settings_cache_stats.record(cached.is_some());
+            if let Some(settings) = cached {
                 return settings.get(key).cloned();
             }
         }
@@ -65,6 +86,122 @@ impl GuildService {
         }
     }
 
+    /// Load every guild's settings into the cache at once, so first messages after a
+    /// restart don't each wait on the database
+    pub async fn preload_settings(&self) -> Result<usize, sqlx::Error> {
+        let rows = sqlx::query(
+            "SELECT g.snowflake_id, gs.settings FROM chloe_guilds_settings gs
+             JOIN chloe_guilds g ON gs.guild_id = g.id",
+        )
+        .fetch_all(&self.db_pool)
+        .await?;
+
+        let mut cache = self.settings_cache.write().await;
+        for row in &rows {
+            cache.insert(row.get("snowflake_id"), row.get("settings"));
+        }
+        Ok(rows.len())
+    }
+
+    /// Merge `updates` into the guild's settings JSON and drop the cached copy
+    pub async fn update_guild_settings(
+        &self,
+        guild_id: i64,
+        updates: &Value,
+    ) -> Result<(), sqlx::Error> {
+        sqlx::query(
+            "UPDATE chloe_guilds_settings gs 
+             SET settings = (gs.settings::jsonb || $2::jsonb)::json, modified_at = CURRENT_TIMESTAMP 
+             FROM chloe_guilds g 
+             WHERE gs.guild_id = g.id AND g.snowflake_id = $1",
+        )
+        .bind(guild_id)
+        .bind(updates)
+        .execute(&self.db_pool)
+        .await?;
+
+        self.settings_cache.write().await.remove(&guild_id);
+        info!(
+            event = "guild_settings_updated",
+            guild_id = guild_id,
+            "Updated guild settings"
+        );
+        if let Some(change_events) = &self.change_events {
+            change_events
+                .publish(ChangeEvent::guild_settings_updated(guild_id, updates))
+                .await;
+        }
+        Ok(())
+    }
+
+    /// Whether chloe may reply in this channel, an empty or missing `llmChannels` means everywhere
+    pub async fn is_llm_channel_allowed(&self, guild_id: i64, channel_id: u64) -> bool {
+        let Some(channels) = self.get_guild_setting(guild_id, "llmChannels").await else {
+            return true;
+        };
+        match channels.as_array() {
+            Some(channels) if !channels.is_empty() => channels
+                .iter()
+                .filter_map(|channel| channel.as_str())
+                .any(|channel| channel == channel_id.to_string()),
+            _ => true,
+        }
+    }
+
+    /// Whether the guild's `quietHours` window is on right now. Anything chloe posts
+    /// unprompted checks this, answers to mentions don't.
+    pub async fn is_quiet_hours(&self, guild_id: i64) -> bool {
+        let setting = self.get_guild_setting(guild_id, "quietHours").await;
+        QuietHours::from_setting(setting.as_ref())
+            .is_some_and(|quiet| quiet.contains(chrono::Utc::now()))
+    }
+
+    /// The reply template for a bare ping from a member with `roles` (highest first),
+    /// None unless the guild turned `ping_reply` on
+    pub async fn get_ping_response(&self, guild_id: i64, roles: &[u64]) -> Option<String> {
+        let enabled = self
+            .get_guild_setting(guild_id, PING_REPLY_SETTING)
+            .await
+            .and_then(|setting| setting.as_bool())
+            .unwrap_or(false);
+        if !enabled {
+            return None;
+        }
+        let setting = self
+            .get_guild_setting(guild_id, PING_RESPONSES_SETTING)
+            .await;
+        Some(
+            PingResponses::from_setting(setting.as_ref())
+                .template_for(roles)
+                .to_string(),
+        )
+    }
+
+    /// Whether command replies of `kind` should only be shown to whoever ran the command
+    pub async fn is_reply_ephemeral(&self, guild_id: i64, kind: ReplyKind) -> bool {
+        let setting = self
+            .get_guild_setting(guild_id, REPLY_VISIBILITY_SETTING)
+            .await;
+        ReplyVisibility::from_setting(setting.as_ref()).is_ephemeral(kind)
+    }
+
+    /// Hits and misses of the settings and role caches since startup
+    pub fn cache_stats(&self) -> [(&'static str, CacheSnapshot); 2] {
+        [
+            ("guild settings", self.settings_cache_stats.snapshot()),
+            ("member roles", self.role_cache_stats.snapshot()),
+        ]
+    }
+
+    /// Drop everything cached for a guild chloe was removed from
+    pub async fn forget_guild(&self, guild_id: i64) {
+        self.settings_cache.write().await.remove(&guild_id);
+        self.role_cache
+            .write()
+            .await
+            .retain(|(cached_guild, _), _| *cached_guild != guild_id);
+    }
+
     pub async fn clear_all_caches(&self) {
         let mut settings_cache = self.settings_cache.write().await;
         let mut role_cache = self.role_cache.write().await;
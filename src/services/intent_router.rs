@@ -0,0 +1,230 @@
+use crate::tools::calculator::{self, format_number};
+use crate::utils::regex_patterns::{ADDRESSING_REGEX, DATE_OR_SCORE_REGEX, MATH_PREFIX_REGEX};
+use chrono::Utc;
+
+const GREETINGS: &[&str] = &[
+    "hi",
+    "hii",
+    "hiii",
+    "hey",
+    "heyy",
+    "heyyy",
+    "hello",
+    "helo",
+    "hiya",
+    "yo",
+    "sup",
+    "wassup",
+    "gm",
+    "good morning",
+    "good afternoon",
+    "good evening",
+    "morning",
+];
+const THANKS: &[&str] = &[
+    "thanks",
+    "thank you",
+    "thx",
+    "ty",
+    "tysm",
+    "thank u",
+    "thanks a lot",
+    "ty ty",
+];
+const TIME_QUESTIONS: &[&str] = &[
+    "what time is it",
+    "whats the time",
+    "what's the time",
+    "time",
+    "what's the date",
+    "whats the date",
+    "what day is it",
+];
+
+const GREETING_REPLIES: &[&str] = &["hiii 💅", "heyyy ✨", "omg hi bestie 💖", "hey hey 💄"];
+const THANKS_REPLIES: &[&str] = &["ofc bestie 💖", "anytime ✨", "np np 💅", "ur welcome 💕"];
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Intent {
+    Greeting,
+    Thanks,
+    Time,
+    Math {
+        expression: String,
+        value: f64,
+    },
+    /// Anything that needs the real model
+    Escalate,
+}
+
+/// Cheap heuristic pass that answers trivial messages without a model call
+pub struct IntentRouter;
+
+impl IntentRouter {
+    pub fn classify(message: &str) -> Intent {
+        let stripped = ADDRESSING_REGEX.replace_all(message, " ");
+        let normalized = stripped
+            .trim()
+            .trim_matches(|c: char| matches!(c, '!' | '?' | '.' | ',' | '~' | ' '))
+            .split_whitespace()
+            .collect::<Vec<_>>()
+            .join(" ")
+            .to_lowercase();
+
+        if normalized.is_empty() {
+            return Intent::Escalate;
+        }
+        if GREETINGS.contains(&normalized.as_str()) {
+            return Intent::Greeting;
+        }
+        if THANKS.contains(&normalized.as_str()) {
+            return Intent::Thanks;
+        }
+        if TIME_QUESTIONS.contains(&normalized.as_str()) {
+            return Intent::Time;
+        }
+
+        let prefixed = MATH_PREFIX_REGEX.is_match(&normalized);
+        let expression = MATH_PREFIX_REGEX
+            .replace(&normalized, "")
+            .trim()
+            .to_string();
+        if !prefixed && !looks_like_math(&expression) {
+            return Intent::Escalate;
+        }
+        match evaluate_expression(&expression) {
+            Some(value) => Intent::Math { expression, value },
+            None => Intent::Escalate,
+        }
+    }
+
+    /// The canned reply for a trivial intent, None when the message should go to the model
+    pub fn canned_response(intent: &Intent) -> Option<String> {
+        match intent {
+            Intent::Greeting => Some(pick(GREETING_REPLIES)),
+            Intent::Thanks => Some(pick(THANKS_REPLIES)),
+            Intent::Time => Some(format!(
+                "it's {} UTC rn ⏰",
+                Utc::now().format("%A %Y-%m-%d %H:%M")
+            )),
+            Intent::Math { expression, value } => {
+                Some(format!("{} = **{}** 🧮", expression, format_number(*value)))
+            }
+            Intent::Escalate => None,
+        }
+    }
+}
+
+fn pick(replies: &[&str]) -> String {
+    replies[rand::random::<usize>() % replies.len()].to_string()
+}
+
+/// Evaluate arithmetic with the calculator tool. Returns None for anything that isn't
+/// arithmetic with at least one operator, so "42" or "2 apples" stay with the model.
+fn evaluate_expression(expression: &str) -> Option<f64> {
+    let expression = times_as_star(expression);
+    let has_operator = expression
+        .chars()
+        .filter(|c| !c.is_whitespace())
+        .skip(1)
+        .any(|c| matches!(c, '+' | '-' | '*' | '/' | '%' | '^'));
+    if !has_operator {
+        return None;
+    }
+    calculator::evaluate(&expression).ok()
+}
+
+/// Without a "what's" in front only spaced out arithmetic counts, "10/10" or "2025-10-15"
+/// is a score or a date more often than a sum
+fn looks_like_math(expression: &str) -> bool {
+    let expression = times_as_star(expression);
+    let spaced_operator = expression
+        .split_whitespace()
+        .any(|token| matches!(token, "+" | "-" | "*" | "/" | "%" | "^"));
+    spaced_operator && !DATE_OR_SCORE_REGEX.is_match(&expression)
+}
+
+/// People write "3 x 4" for multiplication, the calculator only knows `*`
+fn times_as_star(expression: &str) -> String {
+    let chars: Vec<char> = expression.chars().collect();
+    let neighbour = |mut index: usize, forward: bool| loop {
+        index = if forward {
+            index + 1
+        } else {
+            index.checked_sub(1)?
+        };
+        match chars.get(index) {
+            Some(c) if c.is_whitespace() => continue,
+            other => return other.copied(),
+        }
+    };
+    chars
+        .iter()
+        .enumerate()
+        .map(|(index, &c)| {
+            let multiplies = c == 'x'
+                && matches!(neighbour(index, false), Some(c) if c.is_ascii_digit() || c == ')')
+                && matches!(neighbour(index, true), Some(c) if c.is_ascii_digit() || c == '(' || c == '-');
+            if multiplies { '*' } else { c }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_classify_trivial_intents() {
+        assert_eq!(IntentRouter::classify("<@123> hiii!"), Intent::Greeting);
+        assert_eq!(IntentRouter::classify("thank you chloe"), Intent::Thanks);
+        assert_eq!(
+            IntentRouter::classify("chloe what time is it?"),
+            Intent::Time
+        );
+        assert_eq!(
+            IntentRouter::classify("chloe what's 2 + 3 * 4"),
+            Intent::Math {
+                expression: "2 + 3 * 4".to_string(),
+                value: 14.0
+            }
+        );
+    }
+
+    #[test]
+    fn test_classify_escalates_real_questions() {
+        assert_eq!(
+            IntentRouter::classify("hey chloe can you explain monads"),
+            Intent::Escalate
+        );
+        assert_eq!(
+            IntentRouter::classify("what time is it in tokyo"),
+            Intent::Escalate
+        );
+        assert_eq!(IntentRouter::classify("chloe 42"), Intent::Escalate);
+        assert_eq!(IntentRouter::classify("chloe 2025-10-15"), Intent::Escalate);
+        assert_eq!(IntentRouter::classify("<@123> 10/10"), Intent::Escalate);
+        assert_eq!(IntentRouter::classify("chloe 24/7"), Intent::Escalate);
+        assert_eq!(IntentRouter::classify("chloe 10/10 + 1"), Intent::Escalate);
+        assert!(matches!(
+            IntentRouter::classify("chloe 3 x 4"),
+            Intent::Math { value: 12.0, .. }
+        ));
+        assert!(matches!(
+            IntentRouter::classify("what's 10/4"),
+            Intent::Math { value: 2.5, .. }
+        ));
+        assert_eq!(IntentRouter::classify("chloe"), Intent::Escalate);
+    }
+
+    #[test]
+    fn test_evaluate_expression() {
+        assert_eq!(evaluate_expression("(1 + 2) * -3"), Some(-9.0));
+        assert_eq!(evaluate_expression("10 / 4"), Some(2.5));
+        assert_eq!(evaluate_expression("1 / 0"), None);
+        assert_eq!(evaluate_expression("2 + apples"), None);
+        assert_eq!(evaluate_expression("3 x (2 + 1)"), Some(9.0));
+        assert_eq!(evaluate_expression("sqrt(16) + 1"), Some(5.0));
+        assert_eq!(evaluate_expression("sqrt(16)"), None);
+    }
+}
@@ -0,0 +1,372 @@
+use rand::Rng;
+use redis::{AsyncCommands, Client};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use sqlx::{PgPool, Row};
+use tracing::{info, warn};
+
+/// Guild setting turning leveling on
+pub const LEVELING_SETTING: &str = "leveling";
+
+/// A member earns xp for at most one message per this long, so spamming doesn't pay
+const XP_COOLDOWN_SECS: u64 = 60;
+
+const MIN_MESSAGE_XP: i64 = 15;
+const MAX_MESSAGE_XP: i64 = 25;
+
+/// How long a guild's leaderboard is served from redis before it's read again
+const LEADERBOARD_TTL_SECS: u64 = 60;
+
+pub const LEADERBOARD_SIZE: usize = 10;
+
+/// From the guild setting `leveling`, e.g. `{"enabled": true, "announceChannelId": "123"}`.
+/// Off until enabled. Level ups are announced where the member leveled up unless a
+/// channel is set, `"announce": false` keeps them quiet.
+#[derive(Debug, Clone, PartialEq)]
+pub struct LevelingSettings {
+    pub announce: bool,
+    pub announce_channel_id: Option<u64>,
+}
+
+impl LevelingSettings {
+    pub fn from_setting(setting: Option<&Value>) -> Option<Self> {
+        let setting = setting?;
+        if !setting
+            .get("enabled")
+            .and_then(Value::as_bool)
+            .unwrap_or(false)
+        {
+            return None;
+        }
+        let announce_channel_id = setting.get("announceChannelId").and_then(|id| match id {
+            Value::String(id) => id.trim().parse().ok(),
+            value => value.as_u64(),
+        });
+        Some(Self {
+            announce: setting
+                .get("announce")
+                .and_then(Value::as_bool)
+                .unwrap_or(true),
+            announce_channel_id,
+        })
+    }
+}
+
+/// Xp it takes to get from `level` to the next one
+pub fn xp_to_next(level: i32) -> i64 {
+    let level = level as i64;
+    5 * level * level + 50 * level + 100
+}
+
+/// Where a member with `total_xp` stands
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LevelProgress {
+    pub level: i32,
+    /// Xp earned since reaching `level`
+    pub xp_into_level: i64,
+    pub xp_for_next: i64,
+}
+
+pub fn level_progress(total_xp: i64) -> LevelProgress {
+    let mut level = 0;
+    let mut remaining = total_xp.max(0);
+    while remaining >= xp_to_next(level) {
+        remaining -= xp_to_next(level);
+        level += 1;
+    }
+    LevelProgress {
+        level,
+        xp_into_level: remaining,
+        xp_for_next: xp_to_next(level),
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LeaderboardEntry {
+    pub user_id: u64,
+    pub xp: i64,
+    pub level: i32,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Rank {
+    pub xp: i64,
+    pub position: i64,
+    pub message_count: i64,
+    pub progress: LevelProgress,
+}
+
+/// A role handed out on reaching a level
+#[derive(Debug, Clone, PartialEq)]
+pub struct LevelReward {
+    pub level: i32,
+    pub role_id: u64,
+}
+
+/// Xp per member per guild in `chloe_levels`, with the message cooldown and leaderboards
+/// in redis
+pub struct LevelingService {
+    db_pool: PgPool,
+    redis_client: Client,
+}
+
+impl LevelingService {
+    pub fn new(db_pool: PgPool, redis_client: Client) -> Self {
+        Self {
+            db_pool,
+            redis_client,
+        }
+    }
+
+    /// Give a member xp for a message. Returns the level they reached when it went up,
+    /// None while they're on cooldown or didn't level up.
+    pub async fn award(&self, guild_id: u64, user_id: u64) -> Result<Option<i32>, sqlx::Error> {
+        if !self.take_cooldown(guild_id, user_id).await {
+            return Ok(None);
+        }
+
+        let xp = rand::thread_rng().gen_range(MIN_MESSAGE_XP..=MAX_MESSAGE_XP);
+        let row = sqlx::query(
+            "INSERT INTO chloe_levels (guild_id, user_id, xp, level, message_count, last_xp_at)
+             SELECT g.id, $2, $3, 0, 1, NOW() FROM chloe_guilds g WHERE g.snowflake_id = $1
+             ON CONFLICT (guild_id, user_id) DO UPDATE SET
+                 xp = chloe_levels.xp + EXCLUDED.xp,
+                 message_count = chloe_levels.message_count + 1,
+                 last_xp_at = NOW()
+             RETURNING id, xp, level",
+        )
+        .bind(guild_id as i64)
+        .bind(user_id as i64)
+        .bind(xp)
+        .fetch_optional(&self.db_pool)
+        .await?;
+        // the guild was never registered
+        let Some(row) = row else {
+            return Ok(None);
+        };
+
+        let total_xp: i64 = row.get("xp");
+        let level: i32 = row.get("level");
+        let reached = level_progress(total_xp).level;
+        if reached <= level {
+            return Ok(None);
+        }
+        sqlx::query("UPDATE chloe_levels SET level = $2 WHERE id = $1")
+            .bind(row.get::<String, _>("id"))
+            .bind(reached)
+            .execute(&self.db_pool)
+            .await?;
+
+        info!(
+            event = "member_leveled_up",
+            guild_id = guild_id,
+            user_id = user_id,
+            level = reached,
+            "Member leveled up"
+        );
+        Ok(Some(reached))
+    }
+
+    /// Whether the member may earn xp now, starting their cooldown if so. Without redis
+    /// nobody earns xp rather than everybody on every message.
+    async fn take_cooldown(&self, guild_id: u64, user_id: u64) -> bool {
+        let mut conn = match self.redis_client.get_multiplexed_async_connection().await {
+            Ok(conn) => conn,
+            Err(e) => {
+                warn!(
+                    event = "xp_cooldown_unavailable",
+                    error = %e,
+                    "Failed to connect to redis for the xp cooldown"
+                );
+                return false;
+            }
+        };
+        let set: Result<Option<String>, _> = redis::cmd("SET")
+            .arg(cooldown_key(guild_id, user_id))
+            .arg(1)
+            .arg("NX")
+            .arg("EX")
+            .arg(XP_COOLDOWN_SECS)
+            .query_async(&mut conn)
+            .await;
+        matches!(set, Ok(Some(_)))
+    }
+
+    /// A member's xp, level and place on the guild's leaderboard, None before their
+    /// first message counted
+    pub async fn rank(&self, guild_id: u64, user_id: u64) -> Result<Option<Rank>, sqlx::Error> {
+        let row = sqlx::query(
+            "SELECT l.xp, l.message_count,
+                 (SELECT COUNT(*) FROM chloe_levels o WHERE o.guild_id = l.guild_id AND o.xp > l.xp) + 1 AS position
+             FROM chloe_levels l
+             JOIN chloe_guilds g ON l.guild_id = g.id
+             WHERE g.snowflake_id = $1 AND l.user_id = $2",
+        )
+        .bind(guild_id as i64)
+        .bind(user_id as i64)
+        .fetch_optional(&self.db_pool)
+        .await?;
+
+        Ok(row.map(|row| {
+            let xp: i64 = row.get("xp");
+            Rank {
+                xp,
+                position: row.get("position"),
+                message_count: row.get("message_count"),
+                progress: level_progress(xp),
+            }
+        }))
+    }
+
+    /// The guild's top members by xp, from redis when it was read recently
+    pub async fn leaderboard(&self, guild_id: u64) -> Result<Vec<LeaderboardEntry>, sqlx::Error> {
+        let mut conn = self
+            .redis_client
+            .get_multiplexed_async_connection()
+            .await
+            .ok();
+        if let Some(conn) = conn.as_mut()
+            && let Ok(Some(cached)) = conn
+                .get::<_, Option<String>>(leaderboard_key(guild_id))
+                .await
+            && let Ok(entries) = serde_json::from_str(&cached)
+        {
+            return Ok(entries);
+        }
+
+        let rows = sqlx::query(
+            "SELECT l.user_id, l.xp, l.level FROM chloe_levels l
+             JOIN chloe_guilds g ON l.guild_id = g.id
+             WHERE g.snowflake_id = $1
+             ORDER BY l.xp DESC
+             LIMIT $2",
+        )
+        .bind(guild_id as i64)
+        .bind(LEADERBOARD_SIZE as i64)
+        .fetch_all(&self.db_pool)
+        .await?;
+        let entries: Vec<LeaderboardEntry> = rows
+            .iter()
+            .map(|row| LeaderboardEntry {
+                user_id: row.get::<i64, _>("user_id") as u64,
+                xp: row.get("xp"),
+                level: row.get("level"),
+            })
+            .collect();
+
+        if let Some(conn) = conn.as_mut()
+            && let Ok(payload) = serde_json::to_string(&entries)
+        {
+            let _: Result<(), _> = conn
+                .set_ex(leaderboard_key(guild_id), payload, LEADERBOARD_TTL_SECS)
+                .await;
+        }
+        Ok(entries)
+    }
+
+    /// Hand out `role_id` at `level`, replacing the role set for that level before
+    pub async fn set_reward(
+        &self,
+        guild_id: u64,
+        level: i32,
+        role_id: u64,
+    ) -> Result<(), sqlx::Error> {
+        let result = sqlx::query(
+            "INSERT INTO chloe_level_rewards (guild_id, level, role_id)
+             SELECT g.id, $2, $3 FROM chloe_guilds g WHERE g.snowflake_id = $1
+             ON CONFLICT (guild_id, level) DO UPDATE SET role_id = EXCLUDED.role_id",
+        )
+        .bind(guild_id as i64)
+        .bind(level)
+        .bind(role_id as i64)
+        .execute(&self.db_pool)
+        .await?;
+
+        if result.rows_affected() == 0 {
+            return Err(sqlx::Error::RowNotFound); // the guild was never registered
+        }
+        Ok(())
+    }
+
+    /// Whether a reward was set for the level and removed
+    pub async fn remove_reward(&self, guild_id: u64, level: i32) -> Result<bool, sqlx::Error> {
+        let result = sqlx::query(
+            "DELETE FROM chloe_level_rewards r USING chloe_guilds g
+             WHERE r.guild_id = g.id AND g.snowflake_id = $1 AND r.level = $2",
+        )
+        .bind(guild_id as i64)
+        .bind(level)
+        .execute(&self.db_pool)
+        .await?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// The guild's role rewards, lowest level first
+    pub async fn rewards(&self, guild_id: u64) -> Result<Vec<LevelReward>, sqlx::Error> {
+        let rows = sqlx::query(
+            "SELECT r.level, r.role_id FROM chloe_level_rewards r
+             JOIN chloe_guilds g ON r.guild_id = g.id
+             WHERE g.snowflake_id = $1
+             ORDER BY r.level",
+        )
+        .bind(guild_id as i64)
+        .fetch_all(&self.db_pool)
+        .await?;
+
+        Ok(rows
+            .iter()
+            .map(|row| LevelReward {
+                level: row.get("level"),
+                role_id: row.get::<i64, _>("role_id") as u64,
+            })
+            .collect())
+    }
+}
+
+fn cooldown_key(guild_id: u64, user_id: u64) -> String {
+    format!("chloe:xp_cooldown:{}:{}", guild_id, user_id)
+}
+
+fn leaderboard_key(guild_id: u64) -> String {
+    format!("chloe:leaderboard:{}", guild_id)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_level_progress() {
+        assert_eq!(
+            level_progress(0),
+            LevelProgress {
+                level: 0,
+                xp_into_level: 0,
+                xp_for_next: 100
+            }
+        );
+        assert_eq!(level_progress(99).level, 0);
+        assert_eq!(level_progress(100).level, 1);
+        // 100 for level 1, 155 more for level 2
+        let progress = level_progress(300);
+        assert_eq!(progress.level, 2);
+        assert_eq!(progress.xp_into_level, 45);
+        assert_eq!(progress.xp_for_next, 220);
+    }
+
+    #[test]
+    fn test_leveling_settings() {
+        assert_eq!(LevelingSettings::from_setting(None), None);
+        assert_eq!(
+            LevelingSettings::from_setting(Some(&json!({ "enabled": false }))),
+            None
+        );
+        let settings = LevelingSettings::from_setting(Some(
+            &json!({ "enabled": true, "announceChannelId": "42" }),
+        ))
+        .unwrap();
+        assert!(settings.announce);
+        assert_eq!(settings.announce_channel_id, Some(42));
+    }
+}
@@ -1,45 +1,108 @@
+use crate::error::LlmError;
+use crate::services::alerting::AlertMonitor;
+use crate::services::content_incidents::{
+    self, CONTENT_REPORTS_SETTING, ContentIncident, ContentIncidentService,
+};
+use crate::services::degraded_mode::DegradedPolicy;
+use crate::services::gemini_stream::{self, ResponseAccumulator, SseParser};
 use crate::services::gemini_types::{
-    self, FunctionCall, FunctionResponse, FunctionResponseData, GeminiRequest,
-    GeminiResponse,
+    FinishReason, FunctionCall, FunctionResponse, FunctionResponseData, GeminiRequest,
+    GeminiResponse, SafetySetting,
+};
+use crate::services::guild_prompts::GuildPrompt;
+use crate::services::guild_service::GuildService;
+use crate::services::model_router::{self, ModelRouter, TaskType};
+use crate::services::ollama_provider::{self, OllamaProvider};
+use crate::services::prompt_builder::{PromptBuilder, PromptVariables, interpolate};
+use crate::services::provider_recorder::{PersonalDetails, ProviderRecorder};
+use crate::services::provider_timeouts::ProviderTimeouts;
+use crate::services::response_stats::{
+    EmptyResponseCount, EmptyResponseStats, ProviderHealth, ProviderHealthStats,
 };
-use crate::services::prompt_builder::PromptBuilder;
+use crate::services::semantic_memory::RetrievedMemory;
+use crate::services::usage_service::{self, UsageService};
+use crate::services::user_memories::UserMemoryService;
 use crate::settings::Settings;
 use crate::tools::{
-    DiscordAddReactionTool, DiscordContext, DiscordSendMessageTool, ToolCall, ToolName, ToolResult, WebSearchTool,
+    DiscordAddReactionTool, DiscordContext, DiscordSendMessageTool, ImageGenerationTool, RecallFactsTool, RememberFactTool, StreamingReply, Tool, ToolCall, ToolName, ToolResult, WebSearchTool,
+    result_cache::ToolResultCache,
     tool_executor::ToolExecutor,
 };
 use anyhow::{Context, Result};
 use chrono::Utc;
 use reqwest::Client;
+use serde::{Deserialize, Serialize};
 use serde_json::{Value, json};
 use std::{
-    collections::{HashMap, VecDeque},
+    collections::HashMap,
     env,
     sync::Arc,
+    time::Instant,
 };
-use tokio::sync::RwLock;
-use tracing::{error, info};
+use tokio::sync::mpsc;
+use tracing::{error, info, warn};
+use crate::utils::Paginator;
+use crate::utils::cache_stats::CacheSnapshot;
+use crate::utils::rate_limiter::RateLimiterSnapshot;
+use crate::utils::reaction_tracker::ReactionTracker;
+use crate::utils::repetition_guard::RepetitionGuard;
 use crate::utils::regex_patterns::{
     URL_REGEX, IMAGE_URL_REGEX, MENTION_REGEX, EMOTICON_REGEX, ESCAPED_CHAR_REGEX
 };
 
-#[derive(Clone, Debug)]
+const IMAGE_DESCRIPTION_PROMPT: &str = "Describe each attached image in detail for someone who can't see it. \
+Include any visible text verbatim, people, objects, setting and mood. Number the images if there are several.";
+
+/// Tool error fed back to Gemini when a reply repeats one of chloe's recent messages
+const REPETITION_NUDGE: &str = "Not sent: you just said nearly exactly this in the channel. \
+Don't repeat yourself, reply again with something new or phrase it differently.";
+
+/// Added to the prompt when a model answered with nothing at all, before it's asked again
+const EMPTY_RESPONSE_NUDGE: &str = "## Note\nYour previous attempt at this came back empty. \
+Respond to the current message now, using your tools.";
+
+/// Added to the prompt when a tool call's arguments couldn't be read, before it's asked again
+const MALFORMED_CALL_NUDGE: &str = "## Note\nYour previous tool call had arguments that weren't valid JSON. \
+Call the tool again with its arguments as a single JSON object matching its parameters.";
+
+/// How much warmer the retry after an empty response runs, from gemini's default of 1.0
+const EMPTY_RETRY_TEMPERATURE_STEP: f32 = 0.3;
+
+/// How many times a reply cut off by the token limit is continued before it's sent as is
+const MAX_CONTINUATIONS: usize = 2;
+
+/// Remembered facts about the author put in the prompt, older ones are left to recall_facts
+const PROMPT_MEMORY_LIMIT: i64 = 10;
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct MessageContext {
     pub user_display_name: String,
     pub user_id: u64,
     pub content: String,
     pub is_bot: bool,
+    /// Sent by chloe herself, as opposed to another bot in the channel
+    pub is_self: bool,
     pub channel_id: u64,
     pub images: Vec<ImageData>,
 }
 
-#[derive(Clone, Debug)]
+/// A message linked in the current message, with the ones around it
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct LinkedMessage {
+    pub message: MessageContext,
+    /// Oldest first
+    pub before: Vec<MessageContext>,
+    /// Oldest first
+    pub after: Vec<MessageContext>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct ImageData {
     pub base64_data: String,
     pub mime_type: String,
 }
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct ConversationContext {
     pub current_user: String,
     pub current_message: String,
@@ -48,9 +111,30 @@ pub struct ConversationContext {
     pub user_info: Vec<UserInfo>,
     pub referenced_message: Option<MessageContext>,
     pub is_random_reply: bool,
+    /// What a guild's auto-response trigger asks chloe to answer with
+    pub instructions: Option<String>,
+    /// Older messages and facts related to the current message, most similar first
+    pub relevant_memories: Vec<RetrievedMemory>,
+    /// What the author is playing or listening to, when presences are enabled
+    pub author_activity: Option<String>,
+    /// Messages the current message links to
+    pub linked_messages: Vec<LinkedMessage>,
+    /// Prompt overlay of the persona preset the server picked
+    pub persona: Option<String>,
+    /// The server's own system prompt, used instead of the global one
+    pub guild_prompt: Option<GuildPrompt>,
+    /// Guild whose remembered facts apply, where a resumed conversation was saved
+    pub memory_guild_id: Option<u64>,
 }
 
-#[derive(Clone, Debug)]
+/// Which stored prompt an answer was built from, guild and global versions count apart
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PromptVersion {
+    Global(i32),
+    Guild(i32),
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct UserInfo {
     pub display_name: String,
     pub user_id: u64,
@@ -65,34 +149,73 @@ pub struct LlmResponse {
     pub raw_text: String, // original text before cleaning, for reaction processing
 }
 
+/// The request a batch of tool calls came from, what their follow-up is built from
+#[derive(Clone, Copy)]
+struct ToolTurn<'a> {
+    url: &'a str,
+    combined_prompt: &'a str,
+    images: &'a [ImageData],
+    urls: &'a [String],
+    initial_text: Option<&'a str>,
+    discord_context: Option<&'a DiscordContext>,
+    max_calls: usize,
+}
+
 pub struct LlmService {
     client: Client,
+    timeouts: ProviderTimeouts,
     api_key: String,
     settings: Arc<Settings>,
-    conversation_history: Arc<RwLock<std::collections::HashMap<u64, VecDeque<MessageContext>>>>,
     tool_executor: ToolExecutor,
     rate_limiter: Arc<crate::utils::RateLimiter>,
+    model_router: ModelRouter,
+    guild_service: Arc<GuildService>,
+    repetition_guard: RepetitionGuard,
+    recorder: Option<ProviderRecorder>,
+    usage: Option<Arc<UsageService>>,
+    memories: Option<Arc<UserMemoryService>>,
+    ollama: Option<OllamaProvider>,
+    alerts: Option<Arc<AlertMonitor>>,
+    empty_responses: EmptyResponseStats,
+    provider_health: ProviderHealthStats,
+    tool_cache: Option<Arc<ToolResultCache>>,
+    incidents: Option<Arc<ContentIncidentService>>,
 }
 
 impl LlmService {
-    pub fn new(settings: Arc<Settings>) -> Result<Self> {
-        let api_key =
-            env::var("GEMINI_API_KEY").context("GEMINI_API_KEY environment variable not set")?;
+    pub fn new(
+        settings: Arc<Settings>,
+        paginator: Arc<Paginator>,
+        guild_service: Arc<GuildService>,
+        recorder: Option<ProviderRecorder>,
+        usage: Option<Arc<UsageService>>,
+        memories: Option<Arc<UserMemoryService>>,
+        reactions: Arc<ReactionTracker>,
+    ) -> Result<Self> {
+        let timeouts = ProviderTimeouts::gemini();
+        let client = timeouts.client();
+        let ollama = OllamaProvider::from_env();
+
+        // a local ollama server is enough to run without a gemini key
+        let api_key = match env::var("GEMINI_API_KEY") {
+            Ok(api_key) if !api_key.is_empty() => api_key,
+            _ if ollama.is_some() => {
+                warn!(
+                    event = "gemini_api_key_missing",
+                    "GEMINI_API_KEY not set, only ollama models will work"
+                );
+                String::new()
+            }
+            Ok(_) => return Err(anyhow::anyhow!("GEMINI_API_KEY cannot be empty")),
+            Err(e) => return Err(e).context("GEMINI_API_KEY environment variable not set"),
+        };
 
-        if api_key.is_empty() {
-            return Err(anyhow::anyhow!("GEMINI_API_KEY cannot be empty"));
+        let mut tool_executor = default_tool_executor(paginator, reactions);
+        if let Some(memories) = &memories {
+            tool_executor.register_tool(Arc::new(RememberFactTool::new(Arc::clone(memories))));
+            tool_executor.register_tool(Arc::new(RecallFactsTool::new(Arc::clone(memories))));
         }
 
-        let client = Client::new();
-
-        // initialize tool executor with available tools
-        let mut tool_executor = ToolExecutor::new();
-        tool_executor.register_tool(Arc::new(WebSearchTool::new()));
-        tool_executor.register_tool(Arc::new(crate::tools::FetchTool::new()));
-        // tool_executor.register_tool(Arc::new(ImageGenerationTool::new()));
-        tool_executor.register_tool(Arc::new(DiscordSendMessageTool::new()));
-        tool_executor.register_tool(Arc::new(DiscordAddReactionTool::new()));
-
         info!(
             event = "llm_service_initialized",
             tools_count = tool_executor.get_tool_definitions().len(),
@@ -101,19 +224,90 @@ impl LlmService {
 
         Ok(Self {
             client,
+            timeouts,
             api_key,
             settings,
-            conversation_history: Arc::new(RwLock::new(std::collections::HashMap::new())),
             tool_executor,
             rate_limiter: Arc::new(crate::utils::create_llm_rate_limiter()),
+            model_router: ModelRouter::from_env(),
+            guild_service,
+            repetition_guard: RepetitionGuard::new(),
+            recorder,
+            usage,
+            memories,
+            ollama,
+            alerts: None,
+            empty_responses: EmptyResponseStats::default(),
+            provider_health: ProviderHealthStats::default(),
+            tool_cache: None,
+            incidents: None,
         })
     }
 
-    pub async fn prompt_gemini(&self, system_prompt: &str, prompt: &str) -> Result<String> {
-        let url = format!(
-            "https://generativelanguage.googleapis.com/v1beta/models/gemini-2.5-flash-preview-05-20:generateContent?key={}",
-            self.api_key
+    /// Register a tool that needs services the defaults don't have, before the service is shared
+    pub fn with_tool(mut self, tool: Arc<dyn Tool>) -> Self {
+        self.tool_executor.register_tool(tool);
+        self
+    }
+
+    /// Open connections to the providers before the first message arrives, so it doesn't
+    /// pay for DNS and TLS handshakes after a deploy
+    pub async fn warm_up(&self) {
+        let started = Instant::now();
+        if !self.api_key.is_empty() {
+            let url = format!(
+                "https://generativelanguage.googleapis.com/v1beta/models?pageSize=1&key={}",
+                self.api_key
+            );
+            if let Err(e) = self.client.get(&url).send().await {
+                // the url holds the api key, keep it out of the logs
+                warn!(
+                    event = "provider_warmup_failed",
+                    provider = "gemini",
+                    error = %e.without_url(),
+                    "Failed to warm up the Gemini connection"
+                );
+            }
+        }
+        if let Some(ollama) = &self.ollama {
+            let models = ollama.available_models().await;
+            if let Err(e) = models {
+                warn!(
+                    event = "provider_warmup_failed",
+                    provider = "ollama",
+                    error = %e,
+                    "Failed to warm up the ollama connection"
+                );
+            }
+        }
+        info!(
+            event = "providers_warmed_up",
+            elapsed_ms = started.elapsed().as_millis() as u64,
+            "Provider connections warmed up"
         );
+    }
+
+    /// Answer repeated calls to external API tools from the cache
+    pub fn with_tool_cache(mut self, cache: Arc<ToolResultCache>) -> Self {
+        self.tool_executor = self.tool_executor.with_result_cache(Arc::clone(&cache));
+        self.tool_cache = Some(cache);
+        self
+    }
+
+    /// Feed chat requests to the usage alert monitor
+    pub fn with_alerts(mut self, alerts: Option<Arc<AlertMonitor>>) -> Self {
+        self.alerts = alerts;
+        self
+    }
+
+    /// Record replies the content filter blocks
+    pub fn with_content_incidents(mut self, incidents: Arc<ContentIncidentService>) -> Self {
+        self.incidents = Some(incidents);
+        self
+    }
+
+    pub async fn prompt_gemini(&self, system_prompt: &str, prompt: &str) -> Result<String> {
+        let url = self.model_url(&self.model_router.select(TaskType::Chat, None));
 
         let combined_prompt = if system_prompt.is_empty() {
             prompt.to_string()
@@ -124,6 +318,22 @@ impl LlmService {
         self.send_request(&url, &combined_prompt).await
     }
 
+    /// Plain text completion without tools or conversation history, for drafts a person
+    /// reads before anything is sent
+    pub async fn complete_text(&self, system_prompt: &str, prompt: &str) -> Result<String> {
+        let url = self.model_url(&self.model_router.select(TaskType::Chat, None));
+        let request = GeminiRequest::new(&format!("{}\n\n{}", system_prompt, prompt))
+            .with_safety_settings(self.safety_settings(None).await);
+
+        let response = self.generate(&url, &request).await?;
+        self.record_exchange(&url, &request, &response, None).await;
+        response
+            .get_text()
+            .map(|text| text.trim().to_string())
+            .filter(|text| !text.is_empty())
+            .ok_or_else(|| anyhow::anyhow!("Model returned no text"))
+    }
+
     pub async fn prompt_with_context(&self, context: ConversationContext) -> Result<String> {
         let response = self
             .prompt_with_context_and_sender(
@@ -170,9 +380,31 @@ impl LlmService {
         TFut: std::future::Future<Output = ()> + Send,
     {
         let global_settings = self.settings.get_global_settings().await;
+        let (system_prompt, prompt_version) = match &context.guild_prompt {
+            Some(guild_prompt) => (
+                guild_prompt.content.as_str(),
+                PromptVersion::Guild(guild_prompt.version),
+            ),
+            None => (
+                global_settings.prompt.as_str(),
+                PromptVersion::Global(global_settings.prompt_version),
+            ),
+        };
+        // the prompt as it's sent, so the recorder can find it in the request
+        let system_prompt = interpolate(
+            system_prompt,
+            &PromptVariables::from_discord_context(discord_context),
+        );
+        let discord_context = discord_context.map(|ctx| DiscordContext {
+            system_prompt: Some(system_prompt.clone()),
+            prompt_version: Some(prompt_version),
+            personal_details: PersonalDetails::from_context(&context),
+            ..ctx.clone()
+        });
+        let discord_context = discord_context.as_ref();
 
         let enriched_system_prompt = self
-            .enrich_system_prompt_with_context(&global_settings.prompt, &context, discord_context)
+            .enrich_system_prompt_with_context(&system_prompt, &context, discord_context)
             .await;
 
         info!(
@@ -183,17 +415,27 @@ impl LlmService {
             "Processing message with conversation context"
         );
 
-        let url = format!(
-            "https://generativelanguage.googleapis.com/v1beta/models/gemini-2.5-flash-preview-05-20:generateContent?key={}",
-            self.api_key
-        );
-
         let combined_prompt = if enriched_system_prompt.is_empty() {
             context.current_message.clone()
         } else {
             enriched_system_prompt
         };
 
+        let model = self
+            .select_model(&context, &combined_prompt, discord_context)
+            .await;
+        let url = self.model_url(&model);
+
+        // text-only models get descriptions from a vision model instead of the raw images
+        let mut combined_prompt = combined_prompt;
+        let images: &[ImageData] =
+            if !context.current_images.is_empty() && !self.model_router.supports_vision(&model) {
+                combined_prompt.push_str(&self.describe_images(&context.current_images).await);
+                &[]
+            } else {
+                &context.current_images
+            };
+
         // extract urls from the current message for context
         let message_urls = self.extract_urls_from_message(&context.current_message);
 
@@ -201,7 +443,7 @@ impl LlmService {
             .send_request_with_images_urls_and_sender(
                 &url,
                 &combined_prompt,
-                &context.current_images,
+                images,
                 &message_urls,
                 message_sender,
                 typing_starter,
@@ -246,19 +488,527 @@ impl LlmService {
         })
     }
 
+    /// How often each model sent back an empty response since startup
+    pub fn empty_response_counts(&self) -> Vec<(String, EmptyResponseCount)> {
+        self.empty_responses.snapshot()
+    }
+
+    /// Error rate and latency of each provider's recent chat requests
+    pub fn provider_health(&self) -> Vec<ProviderHealth> {
+        self.provider_health.snapshot()
+    }
+
+    /// Model used when neither the guild nor routing picks one
+    pub fn default_model(&self) -> &str {
+        self.model_router.default_model()
+    }
+
+    /// Model answering while chloe is degraded
+    pub fn cheapest_model(&self) -> String {
+        self.model_router.cheapest_model()
+    }
+
+    /// Whether the provider of the guild's pinned model, or of the default model without
+    /// one, has been failing too often for the policy
+    pub fn provider_failing(&self, policy: &DegradedPolicy, pinned_model: Option<&str>) -> bool {
+        let provider = provider_of(pinned_model.unwrap_or(self.default_model()));
+        self.provider_health
+            .snapshot()
+            .iter()
+            .any(|health| health.provider == provider && policy.is_failing(health))
+    }
+
+    /// Gemini safety settings for a request: the guild's thresholds, or chloe's defaults
+    /// without a guild, raised to the global floor
+    async fn safety_settings(
+        &self,
+        discord_context: Option<&DiscordContext>,
+    ) -> Vec<SafetySetting> {
+        let thresholds = discord_context
+            .map(|ctx| ctx.content_safety)
+            .unwrap_or_default();
+        thresholds
+            .at_least(&self.settings.safety_floor().await)
+            .gemini_settings()
+    }
+
+    /// Waits and saturation of the limiter every model request goes through
+    pub async fn rate_limiter_stats(&self) -> RateLimiterSnapshot {
+        self.rate_limiter.snapshot().await
+    }
+
+    /// Hits and misses of the tool result cache, None when it isn't set up
+    pub fn tool_cache_stats(&self) -> Option<CacheSnapshot> {
+        self.tool_cache.as_ref().map(|cache| cache.stats())
+    }
+
+    /// Models pulled on the ollama server, None when ollama isn't configured
+    pub async fn ollama_models(&self) -> Option<Result<Vec<String>>> {
+        Some(self.ollama.as_ref()?.available_models().await)
+    }
+
     async fn enrich_system_prompt_with_context(
         &self,
         base_prompt: &str,
         context: &ConversationContext,
         discord_context: Option<&DiscordContext>,
     ) -> String {
-        let tool_definitions = self.tool_executor.get_tool_definitions();
-        let prompt_builder = PromptBuilder::new(base_prompt.to_string(), tool_definitions);
+        let tool_definitions = self
+            .tool_executor
+            .get_enabled_tool_definitions(discord_context);
+        let remembered_facts = self.remembered_facts(context, discord_context).await;
+        let prompt_builder = PromptBuilder::new(base_prompt.to_string(), tool_definitions)
+            .with_remembered_facts(remembered_facts);
         prompt_builder.build_enriched_prompt(context, discord_context).await
     }
 
-    fn estimate_tokens(&self, text: &str) -> usize {
-        (text.len() as f32 / 4.0).ceil() as usize
+    /// What chloe remembers about the author, empty in DMs that didn't resume a server's
+    /// conversation or without a memory store
+    async fn remembered_facts(
+        &self,
+        context: &ConversationContext,
+        discord_context: Option<&DiscordContext>,
+    ) -> Vec<String> {
+        let (Some(memories), Some(ctx)) = (&self.memories, discord_context) else {
+            return Vec::new();
+        };
+        let Some(guild_id) = context.memory_guild_id else {
+            return Vec::new();
+        };
+        match memories
+            .recall(guild_id, ctx.author_id.get(), PROMPT_MEMORY_LIMIT)
+            .await
+        {
+            Ok(facts) => facts,
+            Err(e) => {
+                warn!(
+                    event = "user_memory_recall_failed",
+                    guild_id = guild_id,
+                    user_id = %ctx.author_id,
+                    error = ?e,
+                    "Failed to load user memories for the prompt"
+                );
+                Vec::new()
+            }
+        }
+    }
+
+    async fn select_model(
+        &self,
+        context: &ConversationContext,
+        combined_prompt: &str,
+        discord_context: Option<&DiscordContext>,
+    ) -> String {
+        let has_images = !context.current_images.is_empty()
+            || context
+                .referenced_message
+                .as_ref()
+                .map(|message| !message.images.is_empty())
+                .unwrap_or(false);
+        // a model pinned with /model wins over routing
+        if let Some(model) = discord_context.and_then(|ctx| ctx.llm_config.model.clone()) {
+            info!(
+                event = "model_selected",
+                model = %model,
+                guild_pinned = true,
+                "Using the guild's pinned model"
+            );
+            return model;
+        }
+
+        let task = self.model_router.classify(
+            &context.current_message,
+            has_images,
+            self.estimate_tokens(combined_prompt),
+        );
+
+        let guild_routes = match discord_context.and_then(|ctx| ctx.guild_id) {
+            Some(guild_id) => {
+                self.guild_service
+                    .get_guild_setting(guild_id.get() as i64, "modelRouting")
+                    .await
+            }
+            None => None,
+        };
+        let model = self.model_router.select(task, guild_routes.as_ref());
+
+        info!(
+            event = "model_selected",
+            task = ?task,
+            model = %model,
+            guild_override = guild_routes.is_some(),
+            "Selected model for request"
+        );
+
+        model
+    }
+
+    /// Describe images with the vision fallback model, formatted as a prompt section
+    async fn describe_images(&self, images: &[ImageData]) -> String {
+        let model = self.model_router.vision_fallback_model();
+        let request = GeminiRequest::new(IMAGE_DESCRIPTION_PROMPT)
+            .with_images(images)
+            .with_safety_settings(self.safety_settings(None).await);
+
+        let description = self
+            .generate(&self.model_url(&model), &request)
+            .await
+            .map(|response| response.get_text().map(|text| text.trim().to_string()));
+
+        let description = match description {
+            Ok(Some(description)) if !description.is_empty() => {
+                info!(
+                    event = "vision_fallback_described",
+                    model = %model,
+                    images_count = images.len(),
+                    description_chars = description.len(),
+                    "Described images for a text-only model"
+                );
+                description
+            }
+            result => {
+                error!(
+                    event = "vision_fallback_failed",
+                    model = %model,
+                    images_count = images.len(),
+                    error = ?result.err(),
+                    "Failed to describe images for a text-only model"
+                );
+                "(the images couldn't be described, let the user know you can't see them right now)"
+                    .to_string()
+            }
+        };
+
+        format!(
+            "\n\n## Attached Images\nThe user attached {} image(s). You can't see them directly, here is a description:\n{}",
+            images.len(),
+            description
+        )
+    }
+
+    fn model_url(&self, model: &str) -> String {
+        model_endpoint(model, &self.api_key)
+    }
+
+    /// Count the exchange's tokens, and hand it to the recorder when recording is enabled
+    async fn record_exchange(
+        &self,
+        url: &str,
+        request: &GeminiRequest,
+        response: &GeminiResponse,
+        discord_context: Option<&DiscordContext>,
+    ) {
+        if let (Some(usage), Some(metadata)) = (&self.usage, &response.usage_metadata) {
+            usage.record(
+                discord_context.and_then(|ctx| ctx.guild_id.map(|id| id.get())),
+                discord_context.map(|ctx| ctx.author_id.get()),
+                model_from_url(url),
+                metadata,
+            );
+        }
+        if let Some(recorder) = &self.recorder {
+            let global_settings = self.settings.get_global_settings().await;
+            let (system_prompt, prompt_version) = match discord_context
+                .and_then(|ctx| Some((ctx.system_prompt.as_deref()?, ctx.prompt_version?)))
+            {
+                Some(prompt) => prompt,
+                None => (
+                    global_settings.prompt.as_str(),
+                    PromptVersion::Global(global_settings.prompt_version),
+                ),
+            };
+            let no_details = PersonalDetails::default();
+            let details = discord_context.map_or(&no_details, |ctx| &ctx.personal_details);
+            recorder.record(
+                model_from_url(url),
+                system_prompt,
+                prompt_version,
+                details,
+                request,
+                response,
+            );
+        }
+    }
+
+    /// Count a chat request towards its provider's health, and report its tokens, cost,
+    /// outcome and latency to the alert monitor
+    fn observe_request(&self, url: &str, started: Instant, response: &Result<GeminiResponse>) {
+        self.provider_health
+            .record(provider_of(url), response.is_err(), started.elapsed());
+
+        let Some(alerts) = &self.alerts else {
+            return;
+        };
+        let usage = response
+            .as_ref()
+            .ok()
+            .and_then(|response| response.usage_metadata.as_ref());
+        let (tokens, cost_usd) = match usage {
+            Some(usage) => {
+                let prompt_tokens = usage.prompt_token_count.unwrap_or(0) as i64;
+                let completion_tokens = usage.candidates_token_count.unwrap_or(0) as i64;
+                let cost =
+                    usage_service::estimated_cost(model_from_url(url), prompt_tokens, completion_tokens);
+                (prompt_tokens + completion_tokens, cost)
+            }
+            None => (0, 0.0),
+        };
+        alerts.record(tokens, cost_usd, response.is_err(), started.elapsed());
+    }
+
+    fn estimate_tokens(&self, text: &str) -> usize {
+        (text.len() as f32 / 4.0).ceil() as usize
+    }
+
+    /// Send a request to the model behind `url`, ollama models included
+    async fn generate(&self, url: &str, request: &GeminiRequest) -> Result<GeminiResponse> {
+        match ollama_provider::model_name(url) {
+            Some(model) => self.ollama_chat(model, request).await,
+            None => self.post_gemini(url, request).await,
+        }
+    }
+
+    /// `generate`, tried once more on the fallback model when the provider times out
+    async fn generate_with_fallback(
+        &self,
+        url: &str,
+        request: &GeminiRequest,
+    ) -> Result<GeminiResponse> {
+        match self.generate(url, request).await {
+            Err(e) if LlmError::is_timeout(&e) => self.retry_on_fallback(url, request, e).await,
+            result => result,
+        }
+    }
+
+    /// Retry a request that timed out on the model's fallback, the timeout stands when
+    /// there's none
+    async fn retry_on_fallback(
+        &self,
+        url: &str,
+        request: &GeminiRequest,
+        error: anyhow::Error,
+    ) -> Result<GeminiResponse> {
+        let Some(fallback) = self.model_router.timeout_fallback(model_from_url(url)) else {
+            return Err(error);
+        };
+
+        warn!(
+            event = "llm_timeout_fallback",
+            model = %model_from_url(url),
+            fallback_model = %fallback,
+            error = %error,
+            "Model timed out, retrying on the fallback model"
+        );
+        self.generate(&self.model_url(&fallback), request).await
+    }
+
+    /// Stream a response from the model behind `url`, one chunk per server-sent event.
+    /// Ollama models, and gemini requests that can't open a stream, arrive as one chunk.
+    /// A stream that times out opening isn't tried again, the timeout is the one chunk.
+    async fn generate_stream(
+        &self,
+        url: &str,
+        request: &GeminiRequest,
+    ) -> mpsc::Receiver<Result<GeminiResponse>> {
+        let (sender, receiver) = mpsc::channel(32);
+
+        let stream = match ollama_provider::model_name(url) {
+            Some(_) => None,
+            None => match self
+                .client
+                .post(gemini_stream::stream_url(url))
+                .json(request)
+                .send()
+                .await
+            {
+                Ok(response) if response.status().is_success() => Some(response),
+                Ok(response) => {
+                    warn!(
+                        event = "gemini_stream_unavailable",
+                        status_code = %response.status(),
+                        "Gemini stream failed to open, falling back to a single response"
+                    );
+                    None
+                }
+                // stream_to_discord retries on the fallback, not on the model that timed out
+                Err(e) if e.is_timeout() => {
+                    let error = self.timeouts.classify(e, "Failed to open Gemini stream");
+                    let _ = sender.send(Err(error)).await;
+                    return receiver;
+                }
+                Err(e) => {
+                    warn!(
+                        event = "gemini_stream_unavailable",
+                        error = %e,
+                        "Gemini stream failed to open, falling back to a single response"
+                    );
+                    None
+                }
+            },
+        };
+
+        let Some(mut response) = stream else {
+            let _ = sender.send(self.generate(url, request).await).await;
+            return receiver;
+        };
+
+        let timeouts = self.timeouts;
+        tokio::spawn(async move {
+            let mut parser = SseParser::default();
+            loop {
+                let (events, done) = match response.chunk().await {
+                    Ok(Some(bytes)) => (parser.push(&bytes), false),
+                    Ok(None) => (std::mem::take(&mut parser).finish().into_iter().collect(), true),
+                    Err(e) => {
+                        let _ = sender
+                            .send(Err(timeouts.classify(e, "Gemini stream was interrupted")))
+                            .await;
+                        return;
+                    }
+                };
+
+                for event in events {
+                    let chunk = serde_json::from_str::<GeminiResponse>(&event)
+                        .context("Failed to parse streamed chunk from Gemini API");
+                    if sender.send(chunk).await.is_err() {
+                        return; // nobody is reading anymore
+                    }
+                }
+                if done {
+                    return;
+                }
+            }
+        });
+        receiver
+    }
+
+    /// Stream a response into a reply edited as text arrives. Text only shows while no
+    /// function call has come in, after one it was a lead-in to the tool. A timeout before
+    /// anything arrived is retried on the fallback model, like outside streaming.
+    async fn stream_to_discord(
+        &self,
+        url: &str,
+        request: &GeminiRequest,
+        reply: &mut StreamingReply,
+    ) -> Result<GeminiResponse> {
+        let mut chunks = self.generate_stream(url, request).await;
+        let mut accumulator = ResponseAccumulator::default();
+        let mut received = false;
+        while let Some(chunk) = chunks.recv().await {
+            let chunk = match chunk {
+                Err(e) if !received && LlmError::is_timeout(&e) => {
+                    return self.retry_on_fallback(url, request, e).await;
+                }
+                chunk => chunk?,
+            };
+            received = true;
+            accumulator.push(chunk);
+            if !accumulator.has_function_call() && !accumulator.text().trim().is_empty() {
+                reply.update(accumulator.text()).await;
+            }
+        }
+
+        info!(
+            event = "gemini_stream_finished",
+            streamed_chars = accumulator.text().len(),
+            reply_started = reply.is_started(),
+            "Finished streaming response"
+        );
+        Ok(accumulator.finish())
+    }
+
+    async fn ollama_chat(&self, model: &str, request: &GeminiRequest) -> Result<GeminiResponse> {
+        self.ollama
+            .as_ref()
+            .context("OLLAMA_URL must be set to use ollama models")?
+            .chat(model, request)
+            .await
+    }
+
+    async fn post_gemini(&self, url: &str, request: &GeminiRequest) -> Result<GeminiResponse> {
+        // Retry logic for transient errors
+        let mut retry_count = 0;
+        let max_retries = 3;
+        let mut last_error = None;
+
+        let response = loop {
+            let response = self
+                .client
+                .post(url)
+                .header("Content-Type", "application/json")
+                .json(request)
+                .send()
+                .await
+                .map_err(|e| self.timeouts.classify(e, "Failed to send request to Gemini API"));
+
+            match response {
+                Ok(resp) => {
+                    if resp.status().is_success() {
+                        break resp;
+                    } else if resp.status() == 500 || resp.status() == 502 || resp.status() == 503 {
+                        // Transient server errors - retry
+                        retry_count += 1;
+                        if retry_count <= max_retries {
+                            let wait_time = std::time::Duration::from_millis(1000 * retry_count);
+                            info!(
+                                event = "gemini_api_retry",
+                                status_code = %resp.status(),
+                                retry_count = retry_count,
+                                wait_ms = wait_time.as_millis(),
+                                "Retrying Gemini API request due to server error"
+                            );
+                            tokio::time::sleep(wait_time).await;
+                            continue;
+                        } else {
+                            break resp;
+                        }
+                    } else {
+                        // Client errors - don't retry
+                        break resp;
+                    }
+                }
+                // a timed out provider gets no more of this message's time, the caller falls back
+                Err(e) if LlmError::is_timeout(&e) => return Err(e),
+                Err(e) => {
+                    last_error = Some(e);
+                    retry_count += 1;
+                    if retry_count <= max_retries {
+                        let wait_time = std::time::Duration::from_millis(1000 * retry_count);
+                        info!(
+                            event = "gemini_api_retry_network",
+                            retry_count = retry_count,
+                            wait_ms = wait_time.as_millis(),
+                            "Retrying Gemini API request due to network error"
+                        );
+                        tokio::time::sleep(wait_time).await;
+                        continue;
+                    } else {
+                        return Err(last_error.unwrap());
+                    }
+                }
+            }
+        };
+
+        if !response.status().is_success() {
+            let status = response.status();
+            let error_text = response.text().await.unwrap_or_default();
+            error!(
+                event = "gemini_api_error",
+                status_code = %status,
+                error_text = %error_text,
+                "Gemini API request failed"
+            );
+            return Err(anyhow::anyhow!(
+                "API request failed with status {}: {}",
+                status,
+                error_text
+            ));
+        }
+
+        response
+            .json()
+            .await
+            .map_err(|e| self.timeouts.classify(e, "Failed to parse JSON response from Gemini API"))
     }
 
     async fn send_request(&self, url: &str, combined_prompt: &str) -> Result<String> {
@@ -336,15 +1086,18 @@ impl LlmService {
         }
 
         // Build typed request
-        let tool_definitions = self.tool_executor.get_tool_definitions();
+        let tool_definitions = self
+            .tool_executor
+            .get_enabled_tool_definitions(discord_context);
         let request = GeminiRequest::new(combined_prompt)
             .with_images(images)
             .with_tools(tool_definitions)
-            .with_safety_settings(gemini_types::default_safety_settings());
+            .with_safety_settings(self.safety_settings(discord_context).await)
+            .with_temperature(discord_context.and_then(|ctx| ctx.llm_config.temperature));
 
         info!(
             event = "gemini_api_request",
-            model = "gemini-2.5-flash-preview-05-20",
+            model = %model_from_url(url),
             prompt_chars = combined_prompt.len(),
             estimated_tokens = self.estimate_tokens(combined_prompt),
             prompt = %self.format_prompt_for_display(combined_prompt),
@@ -366,177 +1119,94 @@ impl LlmService {
             }
         };
 
-        // Retry logic for transient errors
-        let mut retry_count = 0;
-        let max_retries = 3;
-        let mut last_error = None;
-
-        let response = loop {
-            let response = self
-                .client
-                .post(url)
-                .header("Content-Type", "application/json")
-                .json(&request)
-                .send()
-                .await
-                .context("Failed to send request to Gemini API");
-
-            match response {
-                Ok(resp) => {
-                    if resp.status().is_success() {
-                        break resp;
-                    } else if resp.status() == 500 || resp.status() == 502 || resp.status() == 503 {
-                        // Transient server errors - retry
-                        retry_count += 1;
-                        if retry_count <= max_retries {
-                            let wait_time = std::time::Duration::from_millis(1000 * retry_count);
-                            info!(
-                                event = "gemini_api_retry",
-                                status_code = %resp.status(),
-                                retry_count = retry_count,
-                                wait_ms = wait_time.as_millis(),
-                                "Retrying Gemini API request due to server error"
-                            );
-                            tokio::time::sleep(wait_time).await;
-                            continue;
-                        } else {
-                            break resp;
-                        }
-                    } else {
-                        // Client errors - don't retry
-                        break resp;
-                    }
-                }
-                Err(e) => {
-                    last_error = Some(e);
-                    retry_count += 1;
-                    if retry_count <= max_retries {
-                        let wait_time = std::time::Duration::from_millis(1000 * retry_count);
-                        info!(
-                            event = "gemini_api_retry_network",
-                            retry_count = retry_count,
-                            wait_ms = wait_time.as_millis(),
-                            "Retrying Gemini API request due to network error"
-                        );
-                        tokio::time::sleep(wait_time).await;
-                        continue;
-                    } else {
-                        return Err(last_error.unwrap());
-                    }
-                }
-            }
+        // stream into an in-place reply where the guild allows it
+        let mut streaming_reply = discord_context
+            .filter(|ctx| ctx.stream_replies)
+            .map(StreamingReply::new);
+        let started = Instant::now();
+        let response_json = match streaming_reply.as_mut() {
+            Some(reply) => self.stream_to_discord(url, &request, reply).await,
+            None => self.generate_with_fallback(url, &request).await,
         };
-
-        if !response.status().is_success() {
-            let status = response.status();
-            let error_text = response.text().await.unwrap_or_default();
-            error!(
-                event = "gemini_api_error",
-                status_code = %status,
-                error_text = %error_text,
-                "Gemini API request failed"
-            );
-            return Err(anyhow::anyhow!(
-                "API request failed with status {}: {}",
-                status,
-                error_text
-            ));
-        }
-
-        let response_json: GeminiResponse = response
-            .json()
-            .await
-            .context("Failed to parse JSON response from Gemini API")?;
+        self.observe_request(url, started, &response_json);
+        let response_json = response_json?;
+        self.record_exchange(url, &request, &response_json, discord_context)
+            .await;
+        let response_json = self
+            .continue_truncated(url, &request, response_json, discord_context)
+            .await;
+        let response_json = self
+            .repair_tool_calls(url, &request, response_json, discord_context)
+            .await;
+        let response_json = self
+            .recover_empty_response(url, &request, response_json, discord_context)
+            .await;
 
         // Log response structure
         info!(
             event = "gemini_raw_response",
             has_candidates = response_json.candidates.is_some(),
             is_blocked = response_json.is_blocked(),
+            finish_reason = ?response_json.finish_reason(),
             "Raw response from Gemini API"
         );
 
-        // check if the response was blocked for safety reasons
-        if response_json.is_blocked() {
-            let block_reason = response_json.get_block_reason().unwrap_or("UNKNOWN");
-            let safety_message = match block_reason {
-                "SAFETY" => {
-                    "Oh no! I can't respond to that because it might involve harmful content. Let's talk about something else instead! ✨"
-                }
-                "OTHER" => {
-                    "Hmm, I'm not able to respond to that right now. Maybe we could try a different topic? 💭"
-                }
-                _ => {
-                    "Something's preventing me from responding to that. Want to try asking something else? 🤔"
-                }
-            };
-
-            info!(
-                event = "response_blocked_by_safety",
-                block_reason = %block_reason,
-                "Response blocked by Gemini safety filters"
-            );
-
-                // If we have Discord context, send the safety message directly
-                if let Some(discord_ctx) = discord_context {
-                    info!(
-                        event = "sending_safety_message_to_discord",
-                        safety_message = %safety_message,
-                        "Sending safety block message to Discord"
-                    );
-
-                    // Create a tool call to send the safety message
-                    let mut safety_params = std::collections::HashMap::new();
-                    safety_params.insert(
-                        "content".to_string(),
-                        serde_json::Value::String(safety_message.to_string()),
-                    );
-                    safety_params.insert(
-                        "reply_to_original".to_string(),
-                        serde_json::Value::Bool(true),
-                    );
-
-                    let safety_tool_call = crate::tools::ToolCall {
-                        id: format!(
-                            "safety_{}",
-                            chrono::Utc::now().timestamp_nanos_opt().unwrap_or(0)
-                        ),
-                        name: ToolName::DiscordSendMessage.as_str().to_string(),
-                        parameters: safety_params,
-                    };
-
-                    // Execute the Discord message tool directly
-                    let _result = self
-                        .tool_executor
-                        .execute_tool(safety_tool_call, Some(discord_ctx))
-                        .await;
-
-                    // Return empty response since we handled it directly
-                    return Ok(("".to_string(), false));
-                }
-
-                // Fallback for when no Discord context (shouldn't happen in Discord usage)
-                return Ok((safety_message.to_string(), false));
+        // check if the response was blocked for safety reasons, or filtered partway through
+        let filter_reason = match response_json.finish_reason() {
+            Some(FinishReason::ContentFilter(reason)) if !response_json.has_function_call() => {
+                Some(reason.clone())
+            }
+            _ => None,
+        };
+        if response_json.is_blocked() || filter_reason.is_some() {
+            let block_reason = response_json
+                .get_block_reason()
+                .or(filter_reason.as_deref())
+                .unwrap_or("UNKNOWN");
+            self.report_content_incident(
+                url,
+                combined_prompt,
+                &response_json,
+                block_reason,
+                discord_context,
+            )
+            .await;
+            let response = self
+                .reply_with_safety_message(block_reason, discord_context)
+                .await;
+            return Ok((response, false));
         }
 
         // check if the response contains tool calls
         let initial_text = response_json.get_text().unwrap_or("").to_string();
         
-        if let Some(function_call) = response_json.get_function_call() {
-            // Convert FunctionCall to Value for backward compatibility
-            let function_call_value = serde_json::to_value(function_call)
+        if response_json.has_function_call() {
+            // Convert FunctionCalls to Values for backward compatibility
+            let function_call_values = response_json
+                .get_function_calls()
+                .into_iter()
+                .map(serde_json::to_value)
+                .collect::<Result<Vec<_>, _>>()
                 .context("Failed to convert function call to Value")?;
             
+            // text streamed ahead of the call is already showing, it only needs its final edit
+            let streamed = match streaming_reply.as_mut() {
+                Some(reply) if reply.is_started() => reply.finish(&initial_text).await,
+                _ => false,
+            };
+
             // If we have initial text and a message sender, send the initial text immediately
-            if !initial_text.trim().is_empty() && message_sender.is_some() {
-                info!(
-                    event = "sending_initial_response",
-                    text_length = initial_text.len(),
-                    "Sending initial text before tool execution"
-                );
+            if streamed || (!initial_text.trim().is_empty() && message_sender.is_some()) {
+                if !streamed {
+                    info!(
+                        event = "sending_initial_response",
+                        text_length = initial_text.len(),
+                        "Sending initial text before tool execution"
+                    );
 
-                if let Some(sender) = message_sender {
-                    sender(initial_text.clone()).await;
+                    if let Some(sender) = message_sender {
+                        sender(initial_text.clone()).await;
+                    }
                 }
 
                 // Start typing indicator for tool execution
@@ -551,7 +1221,7 @@ impl LlmService {
                         combined_prompt,
                         images,
                         urls,
-                        &function_call_value,
+                        &function_call_values,
                         discord_context,
                     )
                     .await?;
@@ -568,7 +1238,7 @@ impl LlmService {
                         combined_prompt,
                         images,
                         urls,
-                        &function_call_value,
+                        &function_call_values,
                         &initial_text,
                         discord_context,
                     )
@@ -584,6 +1254,15 @@ impl LlmService {
                 "Gemini returned raw text instead of using tools - this violates our tool-only requirement"
             );
 
+            // a streamed reply already shows the text
+            let streamed = match streaming_reply.as_mut() {
+                Some(reply) if reply.is_started() => reply.finish(&initial_text).await,
+                _ => false,
+            };
+            if streamed {
+                return Ok(("".to_string(), false));
+            }
+
             // Autocorrect by sending the raw text via discord_send_message
             if let Some(discord_ctx) = discord_context {
                 error!(
@@ -630,7 +1309,7 @@ impl LlmService {
         combined_prompt: &str,
         images: &[ImageData],
         urls: &[String],
-        function_call: &Value,
+        function_calls: &[Value],
         initial_text: &str,
         discord_context: Option<&DiscordContext>,
     ) -> Result<String> {
@@ -640,7 +1319,7 @@ impl LlmService {
             combined_prompt,
             images,
             urls,
-            function_call,
+            function_calls,
             Some(initial_text),
             discord_context,
             5,
@@ -656,7 +1335,7 @@ impl LlmService {
         combined_prompt: &str,
         images: &[ImageData],
         urls: &[String],
-        function_call: &Value,
+        function_calls: &[Value],
         discord_context: Option<&DiscordContext>,
     ) -> Result<String> {
         // Execute up to 5 tool calls in sequence
@@ -665,7 +1344,7 @@ impl LlmService {
             combined_prompt,
             images,
             urls,
-            function_call,
+            function_calls,
             None,
             discord_context,
             5,
@@ -870,11 +1549,27 @@ impl LlmService {
         combined_prompt: &str,
         images: &[ImageData],
         urls: &[String],
-        function_call: &Value,
+        function_calls: &[Value],
         initial_text: Option<&str>,
         discord_context: Option<&DiscordContext>,
         max_calls: usize,
     ) -> Result<String> {
+        if function_calls.len() > 1 {
+            let turn = ToolTurn {
+                url,
+                combined_prompt,
+                images,
+                urls,
+                initial_text,
+                discord_context,
+                max_calls,
+            };
+            return self.handle_parallel_tool_calls(&turn, function_calls).await;
+        }
+        let function_call = function_calls
+            .first()
+            .context("Missing function call in tool call")?;
+
         // Extract tool name and args
         let function_name = function_call
             .get("name")
@@ -907,66 +1602,335 @@ impl LlmService {
             parameters,
         };
 
-        // Execute the tool
-        let tool_result = self
+        // Catch chloe repeating one of her last messages in the channel before it goes out
+        let outgoing_content = if function_name == ToolName::DiscordSendMessage.as_str() {
+            tool_call
+                .parameters
+                .get("content")
+                .and_then(|c| c.as_str())
+                .map(|c| c.to_string())
+        } else {
+            None
+        };
+        let is_repeat = match (&outgoing_content, discord_context) {
+            (Some(content), Some(ctx)) if max_calls > 1 => {
+                self.repetition_guard
+                    .is_repeat(ctx.channel_id.get(), content)
+                    .await
+            }
+            _ => false,
+        };
+
+        if is_repeat {
+            warn!(
+                event = "repeated_response_blocked",
+                function_name = %function_name,
+                remaining_calls = max_calls - 1,
+                "Generated reply repeats a recent message, asking Gemini to regenerate"
+            );
+
+            let nudge = ToolResult {
+                id: tool_call.id.clone(),
+                success: false,
+                result: String::new(),
+                error: Some(REPETITION_NUDGE.to_string()),
+            };
+            let follow_up_response = self
+                .send_tool_follow_up_request(
+                    url,
+                    combined_prompt,
+                    images,
+                    discord_context,
+                    function_call,
+                    function_name,
+                    &nudge,
+                )
+                .await?;
+
+            return self
+                .process_tool_follow_up_response(
+                    &follow_up_response,
+                    url,
+                    combined_prompt,
+                    images,
+                    urls,
+                    initial_text,
+                    function_name,
+                    &nudge,
+                    discord_context,
+                    max_calls,
+                )
+                .await;
+        }
+
+        // Execute the tool
+        let tool_result = self
+            .tool_executor
+            .execute_tool(tool_call, discord_context)
+            .await;
+
+        match (&outgoing_content, discord_context) {
+            (Some(content), Some(ctx)) if tool_result.success => {
+                self.repetition_guard
+                    .record(ctx.channel_id.get(), content)
+                    .await;
+            }
+            _ => {}
+        }
+
+        // For Discord tools that don't need feedback, return immediately
+        if !self.tool_executor.tool_needs_result_feedback(function_name) {
+            info!(
+                event = "skipping_follow_up_for_discord_tool",
+                function_name = %function_name,
+                tool_success = tool_result.success,
+                "Skipping Gemini follow-up request for Discord tool that doesn't need feedback"
+            );
+
+            return if let Some(initial_text) = initial_text {
+                if !initial_text.trim().is_empty() {
+                    // Combine initial text with tool result for certain tools
+                    let combined = if matches!(
+                        ToolName::from_str(function_name).ok(),
+                        Some(ToolName::DiscordAddReaction | ToolName::DiscordSendMessage)
+                    )
+                    {
+                        format!("{} {}", initial_text.trim(), tool_result.result)
+                    } else {
+                        initial_text.to_string()
+                    };
+                    Ok(self.escape_markdown(&combined))
+                } else {
+                    Ok("".to_string())
+                }
+            } else {
+                // No initial text case - for tool_call_only scenario
+                let final_response = if matches!(
+                    ToolName::from_str(function_name).ok(),
+                    Some(ToolName::DiscordAddReaction | ToolName::DiscordSendMessage)
+                )
+                {
+                    tool_result.result
+                } else {
+                    "".to_string()
+                };
+                Ok(self.escape_markdown(&final_response))
+            };
+        }
+
+        // Build follow-up request for tools that need feedback
+        let follow_up_response = self
+            .send_tool_follow_up_request(
+                url,
+                combined_prompt,
+                images,
+                discord_context,
+                function_call,
+                function_name,
+                &tool_result,
+            )
+            .await?;
+
+        // Process the follow-up response
+        self.process_tool_follow_up_response(
+            &follow_up_response,
+            url,
+            combined_prompt,
+            images,
+            urls,
+            initial_text,
+            function_name,
+            &tool_result,
+            discord_context,
+            max_calls,
+        )
+        .await
+    }
+
+    // Several calls in one model turn run together, their results go back in one follow-up
+    async fn handle_parallel_tool_calls(
+        &self,
+        turn: &ToolTurn<'_>,
+        function_calls: &[Value],
+    ) -> Result<String> {
+        let ToolTurn {
+            url,
+            combined_prompt,
+            images,
+            urls,
+            initial_text,
+            discord_context,
+            max_calls,
+        } = *turn;
+        let typed_calls = function_calls
+            .iter()
+            .map(|call| serde_json::from_value::<FunctionCall>(call.clone()))
+            .collect::<Result<Vec<_>, _>>()
+            .context("Failed to parse function call")?;
+
+        let call_nanos = Utc::now().timestamp_nanos_opt().unwrap_or(0);
+        let tool_calls: Vec<ToolCall> = typed_calls
+            .iter()
+            .enumerate()
+            .map(|(index, call)| ToolCall {
+                id: format!("call_{}_{}", call_nanos, index),
+                name: call.name.clone(),
+                parameters: call
+                    .args
+                    .as_object()
+                    .map(|args| args.clone().into_iter().collect())
+                    .unwrap_or_default(),
+            })
+            .collect();
+
+        info!(
+            event = "parallel_tool_calls_received",
+            function_names = ?tool_calls.iter().map(|call| call.name.as_str()).collect::<Vec<_>>(),
+            has_initial_text = initial_text.is_some(),
+            "Received several tool calls from Gemini"
+        );
+
+        // Repeats are held back with a nudge instead of sent, like in the single call path
+        let mut repeats = Vec::with_capacity(tool_calls.len());
+        for tool_call in &tool_calls {
+            let is_repeat = match (sent_content(tool_call), discord_context) {
+                (Some(content), Some(ctx)) if max_calls > 1 => {
+                    self.repetition_guard
+                        .is_repeat(ctx.channel_id.get(), content)
+                        .await
+                }
+                _ => false,
+            };
+            repeats.push(is_repeat);
+        }
+        let held_back = repeats.iter().filter(|is_repeat| **is_repeat).count();
+        if held_back > 0 {
+            warn!(
+                event = "repeated_response_blocked",
+                held_back = held_back,
+                remaining_calls = max_calls - 1,
+                "Generated reply repeats a recent message, asking Gemini to regenerate"
+            );
+        }
+
+        let to_execute: Vec<ToolCall> = tool_calls
+            .iter()
+            .zip(&repeats)
+            .filter(|(_, is_repeat)| !**is_repeat)
+            .map(|(tool_call, _)| tool_call.clone())
+            .collect();
+        let mut executed = self
             .tool_executor
-            .execute_tool(tool_call, discord_context)
-            .await;
+            .execute_tools_parallel(to_execute, discord_context)
+            .await
+            .into_iter();
+        let tool_results: Vec<ToolResult> = tool_calls
+            .iter()
+            .zip(&repeats)
+            .map(|(tool_call, is_repeat)| match is_repeat {
+                true => ToolResult {
+                    id: tool_call.id.clone(),
+                    success: false,
+                    result: String::new(),
+                    error: Some(REPETITION_NUDGE.to_string()),
+                },
+                false => executed.next().unwrap_or_else(|| ToolResult {
+                    id: tool_call.id.clone(),
+                    success: false,
+                    result: String::new(),
+                    error: Some("Tool didn't run".to_string()),
+                }),
+            })
+            .collect();
 
-        // For Discord tools that don't need feedback, return immediately
-        if !self.tool_executor.tool_needs_result_feedback(function_name) {
+        if let Some(ctx) = discord_context {
+            for (tool_call, tool_result) in tool_calls.iter().zip(&tool_results) {
+                if let (Some(content), true) = (sent_content(tool_call), tool_result.success) {
+                    self.repetition_guard
+                        .record(ctx.channel_id.get(), content)
+                        .await;
+                }
+            }
+        }
+
+        // Discord tools that don't need feedback end the turn, like in the single call path,
+        // unless a repeat was held back and the model has to answer again
+        if held_back == 0
+            && tool_calls
+                .iter()
+                .all(|call| !self.tool_executor.tool_needs_result_feedback(&call.name))
+        {
             info!(
                 event = "skipping_follow_up_for_discord_tool",
-                function_name = %function_name,
-                tool_success = tool_result.success,
-                "Skipping Gemini follow-up request for Discord tool that doesn't need feedback"
+                call_count = tool_calls.len(),
+                "Skipping Gemini follow-up request for Discord tools that don't need feedback"
             );
 
-            return if let Some(initial_text) = initial_text {
-                if !initial_text.trim().is_empty() {
-                    // Combine initial text with tool result for certain tools
-                    let combined = if matches!(
-                        ToolName::from_str(function_name).ok(),
+            let discord_output = tool_calls
+                .iter()
+                .zip(&tool_results)
+                .filter(|(call, _)| {
+                    matches!(
+                        ToolName::from_str(&call.name).ok(),
                         Some(ToolName::DiscordAddReaction | ToolName::DiscordSendMessage)
                     )
-                    {
-                        format!("{} {}", initial_text.trim(), tool_result.result)
-                    } else {
-                        initial_text.to_string()
-                    };
-                    Ok(self.escape_markdown(&combined))
-                } else {
-                    Ok("".to_string())
+                })
+                .map(|(_, result)| result.result.as_str())
+                .filter(|result| !result.is_empty())
+                .collect::<Vec<_>>()
+                .join(" ");
+
+            let final_response = match initial_text {
+                Some(initial_text) if !initial_text.trim().is_empty() => {
+                    format!("{} {}", initial_text.trim(), discord_output)
                 }
-            } else {
-                // No initial text case - for tool_call_only scenario
-                let final_response = if matches!(
-                    ToolName::from_str(function_name).ok(),
-                    Some(ToolName::DiscordAddReaction | ToolName::DiscordSendMessage)
-                )
-                {
-                    tool_result.result
-                } else {
-                    "".to_string()
-                };
-                Ok(self.escape_markdown(&final_response))
+                Some(_) => String::new(),
+                None => discord_output,
             };
+            return Ok(self.escape_markdown(final_response.trim_end()));
         }
 
-        // Build follow-up request for tools that need feedback
-        let follow_up_response = self
-            .send_tool_follow_up_request(
-                url,
-                combined_prompt,
-                images,
-                urls,
-                function_call,
-                function_name,
-                &tool_result,
+        let mut request = GeminiRequest::new(combined_prompt).with_images(images);
+        for ((typed_call, tool_call), tool_result) in
+            typed_calls.iter().zip(&tool_calls).zip(&tool_results)
+        {
+            request = request.add_function_call_parts(
+                typed_call,
+                self.function_response(&tool_call.name, tool_result),
+            );
+        }
+        let request = request
+            .with_tools(
+                self.tool_executor
+                    .get_enabled_tool_definitions(discord_context),
             )
+            .with_safety_settings(self.safety_settings(discord_context).await)
+            .with_temperature(discord_context.and_then(|ctx| ctx.llm_config.temperature));
+
+        info!(
+            event = "sending_follow_up_request",
+            call_count = tool_calls.len(),
+            tool_success = tool_results.iter().all(|result| result.success),
+            "Sending follow-up request with tool results"
+        );
+
+        let follow_up_response = self
+            .send_follow_up_request(url, &request, discord_context)
             .await?;
 
-        // Process the follow-up response
+        // the last call that needed feedback stands in for the batch when combining the reply
+        let (function_name, tool_result) = tool_calls
+            .iter()
+            .zip(&tool_results)
+            .zip(&repeats)
+            .rev()
+            .find(|((call, _), is_repeat)| {
+                **is_repeat || self.tool_executor.tool_needs_result_feedback(&call.name)
+            })
+            .map(|((call, result), _)| (call, result))
+            .map(|(call, result)| (call.name.as_str(), result))
+            .context("Missing tool call that needs feedback")?;
+
         self.process_tool_follow_up_response(
             &follow_up_response,
             url,
@@ -975,7 +1939,7 @@ impl LlmService {
             urls,
             initial_text,
             function_name,
-            &tool_result,
+            tool_result,
             discord_context,
             max_calls,
         )
@@ -988,41 +1952,27 @@ impl LlmService {
         url: &str,
         combined_prompt: &str,
         images: &[ImageData],
-        _urls: &[String],
+        discord_context: Option<&DiscordContext>,
         function_call: &Value,
         function_name: &str,
         tool_result: &ToolResult,
     ) -> Result<GeminiResponse> {
-        // Prepare truncated result for certain tools
-        let truncated_result = self.prepare_tool_result_for_follow_up(function_name, &tool_result.result);
-
-        // Create typed function response
-        let function_response = FunctionResponse {
-            name: function_name.to_string(),
-            response: if tool_result.success {
-                FunctionResponseData {
-                    result: Some(truncated_result),
-                    error: None,
-                }
-            } else {
-                FunctionResponseData {
-                    result: None,
-                    error: Some(tool_result.error.as_deref().unwrap_or("Unknown error").to_string()),
-                }
-            },
-        };
+        let function_response = self.function_response(function_name, tool_result);
 
         // Convert function_call Value to FunctionCall
         let function_call_typed: FunctionCall = serde_json::from_value(function_call.clone())
             .context("Failed to parse function call")?;
 
         // Build typed request
-        let tool_definitions = self.tool_executor.get_tool_definitions();
+        let tool_definitions = self
+            .tool_executor
+            .get_enabled_tool_definitions(discord_context);
         let request = GeminiRequest::new(combined_prompt)
             .with_images(images)
             .add_function_call_parts(&function_call_typed, function_response)
             .with_tools(tool_definitions)
-            .with_safety_settings(gemini_types::default_safety_settings());
+            .with_safety_settings(self.safety_settings(discord_context).await)
+            .with_temperature(discord_context.and_then(|ctx| ctx.llm_config.temperature));
 
         info!(
             event = "sending_follow_up_request",
@@ -1031,36 +1981,378 @@ impl LlmService {
             "Sending follow-up request with tool result"
         );
 
-        // Send the request
-        let response = self
-            .client
-            .post(url)
-            .header("Content-Type", "application/json")
-            .json(&request)
-            .send()
+        self.send_follow_up_request(url, &request, discord_context)
             .await
-            .context("Failed to send follow-up request to Gemini API")?;
+    }
 
-        if !response.status().is_success() {
-            let status = response.status();
-            let error_text = response.text().await.unwrap_or_default();
-            error!(
-                event = "gemini_follow_up_error",
-                status_code = %status,
-                error_text = %error_text,
-                "Gemini API follow-up request failed"
+    // Helper to turn a tool result into the function response fed back to Gemini
+    /// Record a blocked reply and tell the guild's moderators when they asked to hear
+    /// about them
+    async fn report_content_incident(
+        &self,
+        url: &str,
+        combined_prompt: &str,
+        response: &GeminiResponse,
+        block_reason: &str,
+        discord_context: Option<&DiscordContext>,
+    ) {
+        let guild_id = discord_context.and_then(|ctx| ctx.guild_id);
+        let incident = ContentIncident::new(
+            guild_id.map(|id| id.get()),
+            discord_context.map(|ctx| ctx.channel_id.get()),
+            model_from_url(url),
+            block_reason,
+            response.blocked_category(),
+            combined_prompt,
+        );
+        warn!(
+            event = "content_incident",
+            guild_id = ?incident.guild_id,
+            channel_id = ?incident.channel_id,
+            block_reason = %incident.block_reason,
+            category = ?incident.category,
+            prompt_hash = %incident.prompt_hash,
+            "Provider content filter blocked a reply"
+        );
+        if let Some(incidents) = &self.incidents {
+            incidents.record(incident.clone());
+        }
+
+        let (Some(discord_ctx), Some(guild_id)) = (discord_context, guild_id) else {
+            return;
+        };
+        let setting = self
+            .guild_service
+            .get_guild_setting(guild_id.get() as i64, CONTENT_REPORTS_SETTING)
+            .await;
+        if let Some(log_channel_id) = content_incidents::report_channel(setting.as_ref()) {
+            let link = discord_ctx
+                .message_id
+                .link(discord_ctx.channel_id, Some(guild_id));
+            content_incidents::notify_moderators(
+                &discord_ctx.http,
+                log_channel_id,
+                &incident,
+                &link,
+            )
+            .await;
+        }
+    }
+
+    // Helper to tell the user a response was blocked, instead of posting nothing or half of it.
+    // Returns the message when there's no Discord context to send it to.
+    async fn reply_with_safety_message(
+        &self,
+        block_reason: &str,
+        discord_context: Option<&DiscordContext>,
+    ) -> String {
+        let safety_message = match block_reason {
+            "SAFETY" => {
+                "Oh no! I can't respond to that because it might involve harmful content. Let's talk about something else instead! ✨"
+            }
+            "OTHER" => {
+                "Hmm, I'm not able to respond to that right now. Maybe we could try a different topic? 💭"
+            }
+            _ => {
+                "Something's preventing me from responding to that. Want to try asking something else? 🤔"
+            }
+        };
+
+        info!(
+            event = "response_blocked_by_safety",
+            block_reason = %block_reason,
+            "Response blocked by Gemini safety filters"
+        );
+
+        // If we have Discord context, send the safety message directly
+        let Some(discord_ctx) = discord_context else {
+            // Fallback for when no Discord context (shouldn't happen in Discord usage)
+            return safety_message.to_string();
+        };
+
+        info!(
+            event = "sending_safety_message_to_discord",
+            safety_message = %safety_message,
+            "Sending safety block message to Discord"
+        );
+
+        let mut safety_params = HashMap::new();
+        safety_params.insert("content".to_string(), json!(safety_message));
+        safety_params.insert("reply_to_original".to_string(), json!(true));
+
+        let safety_tool_call = ToolCall {
+            id: format!("safety_{}", Utc::now().timestamp_nanos_opt().unwrap_or(0)),
+            name: ToolName::DiscordSendMessage.as_str().to_string(),
+            parameters: safety_params,
+        };
+
+        // Execute the Discord message tool directly, nothing is left to return
+        let _result = self
+            .tool_executor
+            .execute_tool(safety_tool_call, Some(discord_ctx))
+            .await;
+        String::new()
+    }
+
+    /// Ask the model to pick up where a reply cut off by the token limit stops, a couple of
+    /// times at most, so long answers aren't posted half finished
+    async fn continue_truncated(
+        &self,
+        url: &str,
+        request: &GeminiRequest,
+        mut response: GeminiResponse,
+        discord_context: Option<&DiscordContext>,
+    ) -> GeminiResponse {
+        for attempt in 1..=MAX_CONTINUATIONS {
+            if response.finish_reason() != Some(&FinishReason::Length)
+                || response.has_function_call()
+            {
+                break;
+            }
+            let Some(partial) = response.get_text().map(str::to_string) else {
+                break;
+            };
+
+            info!(
+                event = "continuing_truncated_response",
+                model = %model_from_url(url),
+                partial_chars = partial.len(),
+                attempt = attempt,
+                "Response hit the token limit, asking the model to continue it"
             );
-            return Err(anyhow::anyhow!(
-                "Follow-up API request failed with status {}: {}",
-                status,
-                error_text
-            ));
+
+            let continuation_request = request.clone().with_continuation(&partial);
+            match self.generate(url, &continuation_request).await {
+                Ok(continuation) => {
+                    self.record_exchange(url, &continuation_request, &continuation, discord_context)
+                        .await;
+                    let more = continuation.get_text().unwrap_or("").to_string();
+                    response.append_text(&more, continuation.finish_reason().cloned());
+                    if more.is_empty() {
+                        break;
+                    }
+                }
+                Err(e) => {
+                    warn!(
+                        event = "response_continuation_failed",
+                        error = %e,
+                        "Failed to continue a truncated response, keeping what there is"
+                    );
+                    break;
+                }
+            }
+        }
+        response
+    }
+
+    /// Ask once more, with a nudge and a warmer temperature, when the model sent back no
+    /// text and no tool calls. Counted per model either way.
+    async fn recover_empty_response(
+        &self,
+        url: &str,
+        request: &GeminiRequest,
+        response: GeminiResponse,
+        discord_context: Option<&DiscordContext>,
+    ) -> GeminiResponse {
+        if !response.is_empty() || response.is_blocked() {
+            return response;
+        }
+
+        let model = model_from_url(url);
+        warn!(
+            event = "empty_model_response",
+            model = %model,
+            finish_reason = ?response.finish_reason(),
+            "Model returned no content and no tool calls, retrying with a nudge"
+        );
+
+        let temperature = (request.temperature().unwrap_or(1.0) + EMPTY_RETRY_TEMPERATURE_STEP)
+            .min(model_router::MAX_TEMPERATURE);
+        let retry_request = request
+            .clone()
+            .with_text(EMPTY_RESPONSE_NUDGE)
+            .with_temperature(Some(temperature));
+        let retried = match self.generate(url, &retry_request).await {
+            Ok(retried) => {
+                self.record_exchange(url, &retry_request, &retried, discord_context)
+                    .await;
+                Some(retried)
+            }
+            Err(e) => {
+                warn!(
+                    event = "empty_response_retry_failed",
+                    model = %model,
+                    error = %e,
+                    "Retry after an empty response failed"
+                );
+                None
+            }
+        };
+
+        let recovered = retried.as_ref().is_some_and(|retried| !retried.is_empty());
+        let count = self.empty_responses.record(model, recovered);
+        info!(
+            event = "empty_response_retry_finished",
+            model = %model,
+            recovered = recovered,
+            empty_total = count.empty,
+            recovered_total = count.recovered,
+            "Finished retrying an empty response"
+        );
+
+        match retried {
+            Some(retried) if recovered => retried,
+            _ => response,
+        }
+    }
+
+    /// Fix malformed tool call arguments before any tool reads them, asking the model once
+    /// more when they can't be salvaged or the provider couldn't parse the call at all
+    async fn repair_tool_calls(
+        &self,
+        url: &str,
+        request: &GeminiRequest,
+        mut response: GeminiResponse,
+        discord_context: Option<&DiscordContext>,
+    ) -> GeminiResponse {
+        if self.repair_function_calls(&mut response) && !response.is_malformed_call() {
+            return response;
         }
 
+        let model = model_from_url(url);
+        warn!(
+            event = "malformed_tool_call",
+            model = %model,
+            finish_reason = ?response.finish_reason(),
+            "Tool call arguments couldn't be repaired, asking the model again"
+        );
+
+        let retry_request = request.clone().with_text(MALFORMED_CALL_NUDGE);
+        match self.generate(url, &retry_request).await {
+            Ok(mut retried) => {
+                self.record_exchange(url, &retry_request, &retried, discord_context)
+                    .await;
+                let recovered =
+                    self.repair_function_calls(&mut retried) && !retried.is_malformed_call();
+                info!(
+                    event = "malformed_tool_call_retry_finished",
+                    model = %model,
+                    recovered = recovered,
+                    "Finished asking again after a malformed tool call"
+                );
+                if recovered {
+                    return retried;
+                }
+            }
+            Err(e) => warn!(
+                event = "malformed_tool_call_retry_failed",
+                model = %model,
+                error = %e,
+                "Retry after a malformed tool call failed"
+            ),
+        }
         response
-            .json()
-            .await
-            .context("Failed to parse follow-up JSON response from Gemini API")
+    }
+
+    /// Repair every function call's arguments in place, false when one couldn't be
+    fn repair_function_calls(&self, response: &mut GeminiResponse) -> bool {
+        let mut repaired_all = true;
+        for call in response.function_calls_mut() {
+            match self.tool_executor.repair_arguments(&call.name, &call.args) {
+                Some(args) if args != call.args => {
+                    info!(
+                        event = "tool_arguments_repaired",
+                        function_name = %call.name,
+                        "Repaired malformed tool call arguments"
+                    );
+                    call.args = args;
+                }
+                Some(_) => {}
+                None => repaired_all = false,
+            }
+        }
+        repaired_all
+    }
+
+    fn function_response(&self, function_name: &str, tool_result: &ToolResult) -> FunctionResponse {
+        // Prepare truncated result for certain tools
+        let truncated_result = self.prepare_tool_result_for_follow_up(function_name, &tool_result.result);
+
+        FunctionResponse {
+            name: function_name.to_string(),
+            response: if tool_result.success {
+                FunctionResponseData {
+                    result: Some(truncated_result),
+                    error: None,
+                }
+            } else {
+                FunctionResponseData {
+                    result: None,
+                    error: Some(tool_result.error.as_deref().unwrap_or("Unknown error").to_string()),
+                }
+            },
+        }
+    }
+
+    // Helper to send a follow-up request carrying one or more tool results
+    async fn send_follow_up_request(
+        &self,
+        url: &str,
+        request: &GeminiRequest,
+        discord_context: Option<&DiscordContext>,
+    ) -> Result<GeminiResponse> {
+        let started = Instant::now();
+        let response_json: Result<GeminiResponse> = async {
+            match ollama_provider::model_name(url) {
+                Some(model) => self.ollama_chat(model, request).await,
+                None => {
+                    let response = self
+                        .client
+                        .post(url)
+                        .header("Content-Type", "application/json")
+                        .json(request)
+                        .send()
+                        .await
+                        .context("Failed to send follow-up request to Gemini API")?;
+
+                    if !response.status().is_success() {
+                        let status = response.status();
+                        let error_text = response.text().await.unwrap_or_default();
+                        error!(
+                            event = "gemini_follow_up_error",
+                            status_code = %status,
+                            error_text = %error_text,
+                            "Gemini API follow-up request failed"
+                        );
+                        return Err(anyhow::anyhow!(
+                            "Follow-up API request failed with status {}: {}",
+                            status,
+                            error_text
+                        ));
+                    }
+
+                    response
+                        .json()
+                        .await
+                        .context("Failed to parse follow-up JSON response from Gemini API")
+                }
+            }
+        }
+        .await;
+        self.observe_request(url, started, &response_json);
+        let response_json = response_json?;
+        self.record_exchange(url, request, &response_json, discord_context)
+            .await;
+
+        let response_json = self
+            .continue_truncated(url, request, response_json, discord_context)
+            .await;
+        let response_json = self
+            .repair_tool_calls(url, request, response_json, discord_context)
+            .await;
+        Ok(self
+            .recover_empty_response(url, request, response_json, discord_context)
+            .await)
     }
 
     // Helper to process follow-up response
@@ -1085,12 +2377,16 @@ impl LlmService {
         );
 
         // Check if response contains another function call
-        if let Some(next_function_call) = response_json.get_function_call() {
-            let function_call_value = serde_json::to_value(next_function_call)
+        if response_json.has_function_call() {
+            let function_call_values = response_json
+                .get_function_calls()
+                .into_iter()
+                .map(serde_json::to_value)
+                .collect::<Result<Vec<_>, _>>()
                 .context("Failed to convert function call to Value")?;
-            
+
             return self.handle_follow_up_tool_call(
-                &function_call_value,
+                &function_call_values,
                 url,
                 combined_prompt,
                 images,
@@ -1102,6 +2398,24 @@ impl LlmService {
             .await;
         }
 
+        let filter_reason = match response_json.finish_reason() {
+            Some(FinishReason::ContentFilter(reason)) => Some(reason.as_str()),
+            _ => None,
+        };
+        if let Some(block_reason) = response_json.get_block_reason().or(filter_reason) {
+            self.report_content_incident(
+                url,
+                combined_prompt,
+                response_json,
+                block_reason,
+                discord_context,
+            )
+            .await;
+            return Ok(self
+                .reply_with_safety_message(block_reason, discord_context)
+                .await);
+        }
+
         // Check for raw text violation
         if response_json.has_text() && !response_json.has_function_call() {
             if let Some(raw_text) = response_json.get_text() {
@@ -1129,7 +2443,7 @@ impl LlmService {
     // Helper for handling follow-up tool calls
     async fn handle_follow_up_tool_call(
         &self,
-        next_function_call: &Value,
+        next_function_calls: &[Value],
         url: &str,
         combined_prompt: &str,
         images: &[ImageData],
@@ -1138,8 +2452,9 @@ impl LlmService {
         discord_context: Option<&DiscordContext>,
         max_calls: usize,
     ) -> Result<String> {
-        let next_function_name = next_function_call
-            .get("name")
+        let next_function_name = next_function_calls
+            .first()
+            .and_then(|call| call.get("name"))
             .and_then(|n| n.as_str())
             .unwrap_or("unknown");
 
@@ -1147,6 +2462,7 @@ impl LlmService {
             event = "follow_up_function_call_detected",
             follow_up_function = %next_function_name,
             remaining_calls = max_calls - 1,
+            call_count = next_function_calls.len(),
             "Gemini wants to make another tool call"
         );
 
@@ -1168,7 +2484,7 @@ impl LlmService {
             combined_prompt,
             images,
             urls,
-            next_function_call,
+            next_function_calls,
             initial_text,
             discord_context,
             max_calls - 1,
@@ -1247,9 +2563,7 @@ impl LlmService {
 
     // Helper to prepare tool results for follow-up
     fn prepare_tool_result_for_follow_up(&self, function_name: &str, result: &str) -> String {
-        if result.len() > 1000 && result.contains("data:image/") {
-            "Image generated successfully!".to_string()
-        } else if matches!(ToolName::from_str(function_name).ok(), Some(ToolName::WebSearch)) && result.len() > 2000 {
+        if matches!(ToolName::from_str(function_name).ok(), Some(ToolName::WebSearch)) && result.len() > 2000 {
             format!("{}... [truncated for length]", &result[..2000])
         } else {
             result.to_string()
@@ -1307,13 +2621,7 @@ impl LlmService {
         };
 
         // Special handling for certain tools
-        let final_response = if matches!(ToolName::from_str(function_name).ok(), Some(ToolName::GenerateImage)) && tool_result.contains("data:image/") {
-            info!(
-                event = "using_original_tool_result_for_image",
-                "Using original tool result instead of Gemini response for image generation"
-            );
-            tool_result.to_string()
-        } else if matches!(
+        let final_response = if matches!(
             ToolName::from_str(function_name).ok(),
             Some(ToolName::DiscordAddReaction | ToolName::DiscordSendMessage)
         ) {
@@ -1330,3 +2638,67 @@ impl LlmService {
         Ok(self.escape_markdown(&final_response))
     }
 }
+
+/// The tools chloe can call, also used by the eval harness so it scores against the same set
+pub fn default_tool_executor(paginator: Arc<Paginator>, reactions: Arc<ReactionTracker>) -> ToolExecutor {
+    let mut tool_executor = ToolExecutor::new();
+    tool_executor.register_tool(Arc::new(WebSearchTool::new()));
+    tool_executor.register_tool(Arc::new(crate::tools::FetchTool::new()));
+    tool_executor.register_tool(Arc::new(crate::tools::WeatherTool::new()));
+    tool_executor.register_tool(Arc::new(crate::tools::GithubLookupTool::new()));
+    let image_generation = ImageGenerationTool::new();
+    if image_generation.is_configured() {
+        tool_executor.register_tool(Arc::new(image_generation));
+    }
+    let translator = crate::tools::Translator::from_env().map(Arc::new);
+    if let Some(translator) = &translator {
+        tool_executor.register_tool(Arc::new(crate::tools::TranslateTool::new(Arc::clone(translator))));
+    }
+    tool_executor.register_tool(Arc::new(
+        DiscordSendMessageTool::new(Arc::clone(&paginator)).with_translator(translator),
+    ));
+    tool_executor.register_tool(Arc::new(crate::tools::DiscordSendEmbedTool));
+    tool_executor.register_tool(Arc::new(crate::tools::DiscordSendFileTool::new()));
+    tool_executor.register_tool(Arc::new(crate::tools::DiscordCreateThreadTool::new(paginator)));
+    tool_executor.register_tool(Arc::new(DiscordAddReactionTool::new(reactions)));
+    tool_executor
+}
+
+pub fn model_endpoint(model: &str, api_key: &str) -> String {
+    if model.starts_with(ollama_provider::MODEL_PREFIX) {
+        return model.to_string();
+    }
+    format!(
+        "https://generativelanguage.googleapis.com/v1beta/models/{}:generateContent?key={}",
+        model, api_key
+    )
+}
+
+/// Pull the model name back out of a generateContent url for logging
+fn model_from_url(url: &str) -> &str {
+    if ollama_provider::model_name(url).is_some() {
+        return url;
+    }
+    url.split("/models/")
+        .nth(1)
+        .and_then(|rest| rest.split(':').next())
+        .unwrap_or("unknown")
+}
+
+/// Provider behind a model or its url, as provider health counts it
+fn provider_of(model: &str) -> &'static str {
+    if ollama_provider::model_name(model).is_some() {
+        "ollama"
+    } else {
+        "gemini"
+    }
+}
+
+/// What a discord_send_message call would send, None for other tools
+fn sent_content(tool_call: &ToolCall) -> Option<&str> {
+    tool_call
+        .parameters
+        .get("content")
+        .and_then(|c| c.as_str())
+        .filter(|_| tool_call.name == ToolName::DiscordSendMessage.as_str())
+}
@@ -0,0 +1,173 @@
+use serde_json::Value;
+
+/// Guild setting for the message posted when someone joins
+pub const WELCOME_SETTING: &str = "welcome";
+/// Guild setting for the message posted when someone leaves
+pub const FAREWELL_SETTING: &str = "farewell";
+
+const MAX_TEMPLATE_LENGTH: usize = 1000;
+
+/// Instructions for greetings the model writes fresh each time
+pub const GREETING_PROMPT: &str = "You're Chloe, a discord bot, writing a one-off message for a server channel. Reply with only the message, at most 300 characters, no quotes around it. Write {user} where the member should be mentioned.";
+
+/// `DISCORD_MEMBERS=true` subscribes to member joins and leaves for welcome and farewell
+/// messages. The server members intent has to be enabled for the bot in the developer
+/// portal too.
+pub fn member_events_enabled() -> bool {
+    std::env::var("DISCORD_MEMBERS")
+        .map(|value| value == "true" || value == "1")
+        .unwrap_or(false)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GreetingKind {
+    Welcome,
+    Farewell,
+}
+
+impl GreetingKind {
+    pub fn setting(self) -> &'static str {
+        match self {
+            GreetingKind::Welcome => WELCOME_SETTING,
+            GreetingKind::Farewell => FAREWELL_SETTING,
+        }
+    }
+
+    fn default_template(self) -> &'static str {
+        match self {
+            GreetingKind::Welcome => "welcome to {guild}, {user}! 💖 you're member #{member_count}",
+            GreetingKind::Farewell => "{username} left {guild} 💔 we're {member_count} now",
+        }
+    }
+}
+
+/// From the guild settings `welcome` and `farewell`, e.g. `{"channelId": "123",
+/// "message": "hi {user}, welcome to {guild}!", "generate": true}`. Off until a channel
+/// is set. With `generate` the model writes each message fresh in the server's persona,
+/// using the template as the idea and falling back to it when the model fails.
+#[derive(Debug, Clone, PartialEq)]
+pub struct GreetingSettings {
+    pub channel_id: u64,
+    pub template: String,
+    pub generate: bool,
+}
+
+impl GreetingSettings {
+    pub fn from_setting(kind: GreetingKind, setting: Option<&Value>) -> Option<Self> {
+        let setting = setting?;
+        let channel_id = match setting.get("channelId")? {
+            Value::String(id) => id.trim().parse().ok()?,
+            value => value.as_u64()?,
+        };
+        let template = setting
+            .get("message")
+            .and_then(Value::as_str)
+            .map(str::trim)
+            .filter(|template| !template.is_empty())
+            .map(|template| template.chars().take(MAX_TEMPLATE_LENGTH).collect())
+            .unwrap_or_else(|| kind.default_template().to_string());
+        Some(Self {
+            channel_id,
+            template,
+            generate: setting
+                .get("generate")
+                .and_then(Value::as_bool)
+                .unwrap_or(false),
+        })
+    }
+}
+
+/// What greeting templates can refer to
+#[derive(Debug, Clone, PartialEq)]
+pub struct GreetingVars {
+    pub user_id: u64,
+    pub username: String,
+    pub guild: String,
+    pub member_count: u64,
+}
+
+/// Fill in `{user}` (a mention), `{username}`, `{guild}` and `{member_count}`
+pub fn render(template: &str, vars: &GreetingVars) -> String {
+    template
+        .replace("{user}", &format!("<@{}>", vars.user_id))
+        .replace("{username}", &vars.username)
+        .replace("{guild}", &vars.guild)
+        .replace("{member_count}", &vars.member_count.to_string())
+}
+
+/// The request for a freshly written greeting, the reply still goes through [`render`]
+pub fn generation_prompt(kind: GreetingKind, template: &str, vars: &GreetingVars) -> String {
+    let event = match kind {
+        GreetingKind::Welcome => "just joined",
+        GreetingKind::Farewell => "just left",
+    };
+    format!(
+        "{} {} the server {}, which now has {} members. Write a message for the channel about it, in the spirit of: {}",
+        vars.username,
+        event,
+        vars.guild,
+        vars.member_count,
+        render(template, vars).replace(&format!("<@{}>", vars.user_id), "{user}")
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn vars() -> GreetingVars {
+        GreetingVars {
+            user_id: 1,
+            username: "mika".to_string(),
+            guild: "gyaru".to_string(),
+            member_count: 42,
+        }
+    }
+
+    #[test]
+    fn test_greeting_settings() {
+        assert_eq!(
+            GreetingSettings::from_setting(GreetingKind::Welcome, None),
+            None
+        );
+        assert_eq!(
+            GreetingSettings::from_setting(
+                GreetingKind::Welcome,
+                Some(&json!({ "generate": true }))
+            ),
+            None
+        );
+
+        let farewell = GreetingSettings::from_setting(
+            GreetingKind::Farewell,
+            Some(&json!({ "channelId": "7" })),
+        )
+        .unwrap();
+        assert_eq!(farewell.channel_id, 7);
+        assert_eq!(farewell.template, GreetingKind::Farewell.default_template());
+        assert!(!farewell.generate);
+
+        let welcome = GreetingSettings::from_setting(
+            GreetingKind::Welcome,
+            Some(&json!({ "channelId": 7, "message": " hi {user} ", "generate": true })),
+        )
+        .unwrap();
+        assert_eq!(welcome.template, "hi {user}");
+        assert!(welcome.generate);
+    }
+
+    #[test]
+    fn test_render() {
+        assert_eq!(
+            render(
+                "{user} ({username}) joined {guild}, #{member_count}",
+                &vars()
+            ),
+            "<@1> (mika) joined gyaru, #42"
+        );
+        let prompt = generation_prompt(GreetingKind::Welcome, "welcome {user}!", &vars());
+        assert!(prompt.starts_with("mika just joined the server gyaru, which now has 42 members"));
+        assert!(prompt.ends_with("welcome {user}!"));
+    }
+}
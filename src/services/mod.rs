@@ -1,5 +1,44 @@
+pub mod alerting;
+pub mod change_events;
+pub mod content_incidents;
+pub mod content_safety;
+pub mod context_builder;
+pub mod conversation_service;
+pub mod custom_commands;
+pub mod degraded_mode;
+pub mod embeddings;
+pub mod gemini_stream;
 pub mod gemini_types;
+pub mod guild_prompts;
+pub mod guild_retention;
 pub mod guild_service;
+pub mod intent_router;
+pub mod leveling;
 pub mod llm_service;
+pub mod member_greetings;
+pub mod model_router;
+pub mod modmail;
+pub mod ollama_provider;
+pub mod personas;
+pub mod ping_reply;
 pub mod prompt_builder;
+pub mod provider_recorder;
+pub mod provider_timeouts;
+pub mod quiet_hours;
+pub mod reaction_roles;
+pub mod regenerations;
+pub mod reminders;
+pub mod reply_style;
+pub mod reply_visibility;
+pub mod response_stats;
+pub mod safety;
+pub mod saved_conversations;
+pub mod scheduled_messages;
+pub mod scheduler;
+pub mod starboard;
+pub mod summarizer;
+pub mod semantic_memory;
+pub mod triggers;
+pub mod usage_service;
+pub mod user_memories;
 pub mod user_service;
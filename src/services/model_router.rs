@@ -0,0 +1,331 @@
+use crate::services::ollama_provider;
+use crate::services::usage_service;
+use crate::utils::regex_patterns::CODE_HINT_REGEX;
+use serde_json::Value;
+use std::env;
+
+const DEFAULT_MODEL: &str = "gemini-2.5-flash-preview-05-20";
+
+/// Prompts estimated above this many tokens go to the long-context model
+const DEFAULT_LONG_CONTEXT_TOKENS: usize = 30_000;
+
+pub const MAX_TEMPERATURE: f32 = 2.0;
+
+/// Models known to reject image input, extended with `TEXT_ONLY_MODELS`
+const TEXT_ONLY_MODEL_PREFIXES: &[&str] = &["gemma-3-1b", "gemini-1.0-pro"];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TaskType {
+    Chat,
+    Code,
+    Vision,
+    LongContext,
+}
+
+impl TaskType {
+    /// Key used for this task in the `modelRouting` guild setting
+    fn setting_key(&self) -> &'static str {
+        match self {
+            TaskType::Chat => "default",
+            TaskType::Code => "code",
+            TaskType::Vision => "vision",
+            TaskType::LongContext => "longContext",
+        }
+    }
+}
+
+/// Picks a Gemini model per request from the task type. Routes come from the
+/// environment and can be overridden per guild through the `modelRouting` setting,
+/// e.g. `{"code": "gemini-2.5-pro"}`.
+pub struct ModelRouter {
+    default_model: String,
+    code_model: Option<String>,
+    vision_model: Option<String>,
+    long_context_model: Option<String>,
+    fallback_model: Option<String>,
+    cheap_model: Option<String>,
+    long_context_tokens: usize,
+    text_only_models: Vec<String>,
+}
+
+impl ModelRouter {
+    pub fn from_env() -> Self {
+        let model_var = |name: &str| env::var(name).ok().filter(|model| is_valid_model(model));
+
+        Self {
+            default_model: model_var("GEMINI_MODEL")
+                .or_else(ollama_default_model)
+                .unwrap_or_else(|| DEFAULT_MODEL.to_string()),
+            code_model: model_var("GEMINI_CODE_MODEL"),
+            vision_model: model_var("GEMINI_VISION_MODEL"),
+            long_context_model: model_var("GEMINI_LONG_CONTEXT_MODEL"),
+            fallback_model: model_var("GEMINI_FALLBACK_MODEL"),
+            cheap_model: model_var("GEMINI_CHEAP_MODEL"),
+            long_context_tokens: env::var("LONG_CONTEXT_TOKENS")
+                .ok()
+                .and_then(|tokens| tokens.parse().ok())
+                .unwrap_or(DEFAULT_LONG_CONTEXT_TOKENS),
+            text_only_models: env::var("TEXT_ONLY_MODELS")
+                .map(|models| {
+                    models
+                        .split(',')
+                        .map(|model| model.trim().to_string())
+                        .filter(|model| !model.is_empty())
+                        .collect()
+                })
+                .unwrap_or_default(),
+        }
+    }
+
+    pub fn default_model(&self) -> &str {
+        &self.default_model
+    }
+
+    pub fn supports_vision(&self, model: &str) -> bool {
+        !TEXT_ONLY_MODEL_PREFIXES
+            .iter()
+            .any(|prefix| model.starts_with(prefix))
+            && !self
+                .text_only_models
+                .iter()
+                .any(|text_only| text_only == model)
+    }
+
+    /// Model used to describe images when the selected model can't see them
+    pub fn vision_fallback_model(&self) -> String {
+        self.vision_model
+            .clone()
+            .filter(|model| self.supports_vision(model))
+            .unwrap_or_else(|| DEFAULT_MODEL.to_string())
+    }
+
+    /// Model to retry on when `model`'s provider times out, from `GEMINI_FALLBACK_MODEL`
+    pub fn timeout_fallback(&self, model: &str) -> Option<String> {
+        self.fallback_model
+            .clone()
+            .filter(|fallback| fallback != model)
+    }
+
+    /// Model answering while chloe is degraded, `GEMINI_CHEAP_MODEL` or the cheapest priced
+    /// one. A self-hosted default is free already.
+    pub fn cheapest_model(&self) -> String {
+        if let Some(model) = &self.cheap_model {
+            return model.clone();
+        }
+        if ollama_provider::model_name(&self.default_model).is_some() {
+            return self.default_model.clone();
+        }
+        usage_service::cheapest_priced_model().to_string()
+    }
+
+    pub fn classify(&self, message: &str, has_images: bool, estimated_tokens: usize) -> TaskType {
+        if has_images {
+            TaskType::Vision
+        } else if estimated_tokens > self.long_context_tokens {
+            TaskType::LongContext
+        } else if CODE_HINT_REGEX.is_match(message) {
+            TaskType::Code
+        } else {
+            TaskType::Chat
+        }
+    }
+
+    /// Resolve the model for a task, guild overrides first, then configured routes,
+    /// then the guild's or global default
+    pub fn select(&self, task: TaskType, guild_routes: Option<&Value>) -> String {
+        let guild_model = |key: &str| {
+            guild_routes
+                .and_then(|routes| routes.get(key))
+                .and_then(|model| model.as_str())
+                .filter(|model| is_valid_model(model))
+                .map(|model| model.to_string())
+        };
+
+        let configured = match task {
+            TaskType::Chat => None,
+            TaskType::Code => self.code_model.clone(),
+            TaskType::Vision => self.vision_model.clone(),
+            TaskType::LongContext => self.long_context_model.clone(),
+        };
+
+        guild_model(task.setting_key())
+            .or(configured)
+            .or_else(|| guild_model(TaskType::Chat.setting_key()))
+            .unwrap_or_else(|| self.default_model.clone())
+    }
+}
+
+/// A guild's pinned model and temperature from the `llmConfig` setting, e.g.
+/// `{"provider": "ollama", "model": "llama3.1:8b", "temperature": 0.7}`. A pinned model
+/// wins over task routing.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct GuildModelConfig {
+    pub model: Option<String>,
+    pub temperature: Option<f32>,
+}
+
+impl GuildModelConfig {
+    pub fn from_setting(setting: Option<&Value>) -> Self {
+        let Some(setting) = setting else {
+            return Self::default();
+        };
+        let provider = setting
+            .get("provider")
+            .and_then(|provider| provider.as_str())
+            .unwrap_or("gemini");
+        Self {
+            model: setting
+                .get("model")
+                .and_then(|model| model.as_str())
+                .and_then(|model| qualified_model(provider, model)),
+            temperature: setting
+                .get("temperature")
+                .and_then(|temperature| temperature.as_f64())
+                .map(|temperature| (temperature as f32).clamp(0.0, MAX_TEMPERATURE)),
+        }
+    }
+}
+
+/// The endpoint-style name a provider's model is routed under, None for unknown
+/// providers or invalid names
+pub fn qualified_model(provider: &str, model: &str) -> Option<String> {
+    let model = model.trim();
+    let qualified = match provider.to_lowercase().as_str() {
+        "gemini" => model.to_string(),
+        "ollama" => format!(
+            "{}{}",
+            ollama_provider::MODEL_PREFIX,
+            ollama_provider::model_name(model).unwrap_or(model)
+        ),
+        _ => return None,
+    };
+    is_valid_model(&qualified).then_some(qualified)
+}
+
+/// `OLLAMA_MODEL` when running without a gemini key, so self-hosters only need ollama
+fn ollama_default_model() -> Option<String> {
+    if env::var("GEMINI_API_KEY").is_ok_and(|key| !key.is_empty()) {
+        return None;
+    }
+    env::var("OLLAMA_MODEL")
+        .ok()
+        .map(|model| format!("{}{}", ollama_provider::MODEL_PREFIX, model))
+        .filter(|model| is_valid_model(model))
+}
+
+/// Model names end up in the request URL, so only allow the characters Gemini uses.
+/// Ollama names go in the request body and may also carry a tag and namespace.
+fn is_valid_model(model: &str) -> bool {
+    match ollama_provider::model_name(model) {
+        Some(name) => {
+            !name.is_empty()
+                && name
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '.' | '_' | ':' | '/'))
+        }
+        None => {
+            !model.is_empty()
+                && model
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '.' | '_'))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn router() -> ModelRouter {
+        ModelRouter {
+            default_model: "flash".to_string(),
+            code_model: Some("pro".to_string()),
+            vision_model: None,
+            long_context_model: None,
+            fallback_model: Some("flash-lite".to_string()),
+            cheap_model: None,
+            long_context_tokens: 1000,
+            text_only_models: vec!["tiny".to_string()],
+        }
+    }
+
+    #[test]
+    fn test_classify_task_types() {
+        let router = router();
+        assert_eq!(router.classify("look at this", true, 10), TaskType::Vision);
+        assert_eq!(router.classify("hello", false, 5000), TaskType::LongContext);
+        assert_eq!(
+            router.classify("why does this ```fn main() {}``` not compile", false, 10),
+            TaskType::Code
+        );
+        assert_eq!(
+            router.classify("how was your day", false, 10),
+            TaskType::Chat
+        );
+    }
+
+    #[test]
+    fn test_select_prefers_guild_overrides() {
+        let router = router();
+        assert_eq!(router.select(TaskType::Code, None), "pro");
+        assert_eq!(router.select(TaskType::Vision, None), "flash");
+
+        let routes = json!({"default": "lite", "code": "coder", "vision": "bad/model"});
+        assert_eq!(
+            router.select(
+                TaskType::Code,
+                Some(&json!({"code": "ollama:qwen2.5-coder:7b"}))
+            ),
+            "ollama:qwen2.5-coder:7b"
+        );
+        assert_eq!(router.select(TaskType::Code, Some(&routes)), "coder");
+        assert_eq!(router.select(TaskType::Vision, Some(&routes)), "lite");
+        assert_eq!(router.select(TaskType::Chat, Some(&routes)), "lite");
+    }
+
+    #[test]
+    fn test_guild_model_config_from_setting() {
+        let config = GuildModelConfig::from_setting(Some(&json!({
+            "provider": "ollama",
+            "model": "llama3.1:8b",
+            "temperature": 5.0
+        })));
+        assert_eq!(config.model.as_deref(), Some("ollama:llama3.1:8b"));
+        assert_eq!(config.temperature, Some(MAX_TEMPERATURE));
+
+        let config = GuildModelConfig::from_setting(Some(&json!({"model": "gemini-2.5-pro"})));
+        assert_eq!(config.model.as_deref(), Some("gemini-2.5-pro"));
+        assert_eq!(config.temperature, None);
+
+        assert_eq!(qualified_model("gemini", "bad/model"), None);
+        assert_eq!(qualified_model("openai", "gpt-4o"), None);
+        assert_eq!(GuildModelConfig::from_setting(None), GuildModelConfig::default());
+    }
+
+    #[test]
+    fn test_supports_vision() {
+        let router = router();
+        assert!(router.supports_vision("gemini-2.5-flash"));
+        assert!(!router.supports_vision("gemma-3-1b-it"));
+        assert!(!router.supports_vision("tiny"));
+        assert_eq!(router.vision_fallback_model(), DEFAULT_MODEL);
+    }
+
+    #[test]
+    fn test_timeout_fallback() {
+        let router = router();
+        assert_eq!(router.timeout_fallback("flash").as_deref(), Some("flash-lite"));
+        assert_eq!(router.timeout_fallback("flash-lite"), None);
+    }
+
+    #[test]
+    fn test_cheapest_model() {
+        let mut router = router();
+        assert_eq!(router.cheapest_model(), "gemini-2.0-flash-lite");
+        router.default_model = "ollama:llama3.1:8b".to_string();
+        assert_eq!(router.cheapest_model(), "ollama:llama3.1:8b");
+        router.cheap_model = Some("gemma-3-4b-it".to_string());
+        assert_eq!(router.cheapest_model(), "gemma-3-4b-it");
+    }
+}
@@ -0,0 +1,162 @@
+use serde_json::Value;
+use sqlx::{PgPool, Row};
+use tokio::sync::RwLock;
+use tracing::{error, info};
+
+/// Mod messages starting with this stay in the thread instead of being sent to the member
+pub const INTERNAL_NOTE_PREFIX: &str = "//";
+
+/// A member's open conversation with a guild's mods
+#[derive(Debug, Clone, PartialEq)]
+pub struct ModmailThread {
+    pub guild_id: u64,
+    pub user_id: u64,
+    pub thread_id: u64,
+}
+
+/// The channel modmail threads are opened in, from the guild setting
+/// `modmail`, e.g. `{"channelId": "123"}`. None while modmail is off.
+pub fn modmail_channel(setting: Option<&Value>) -> Option<u64> {
+    setting?.get("channelId")?.as_str()?.parse().ok()
+}
+
+/// Whether a mod's message in a thread should reach the member
+pub fn is_relayed(content: &str) -> bool {
+    !content.trim_start().starts_with(INTERNAL_NOTE_PREFIX)
+}
+
+/// Open modmail threads. Every DM and every guild message is checked against them, so the
+/// open ones are kept in memory and reloaded after a change.
+pub struct ModmailService {
+    db_pool: PgPool,
+    open: RwLock<Option<Vec<ModmailThread>>>,
+}
+
+impl ModmailService {
+    pub fn new(db_pool: PgPool) -> Self {
+        Self {
+            db_pool,
+            open: RwLock::new(None),
+        }
+    }
+
+    /// The member's open threads, one per guild they've written to
+    pub async fn threads_for_user(&self, user_id: u64) -> Vec<ModmailThread> {
+        self.find(|thread| thread.user_id == user_id).await
+    }
+
+    /// The open thread behind a channel, if it is one
+    pub async fn thread(&self, thread_id: u64) -> Option<ModmailThread> {
+        self.find(|thread| thread.thread_id == thread_id)
+            .await
+            .into_iter()
+            .next()
+    }
+
+    pub async fn open(&self, thread: &ModmailThread) -> Result<(), sqlx::Error> {
+        let result = sqlx::query(
+            "INSERT INTO chloe_modmail_threads (guild_id, user_id, thread_id)
+             SELECT g.id, $2, $3 FROM chloe_guilds g WHERE g.snowflake_id = $1",
+        )
+        .bind(thread.guild_id as i64)
+        .bind(thread.user_id as i64)
+        .bind(thread.thread_id as i64)
+        .execute(&self.db_pool)
+        .await?;
+
+        if result.rows_affected() == 0 {
+            return Err(sqlx::Error::RowNotFound); // the guild was never registered
+        }
+        *self.open.write().await = None;
+        info!(
+            event = "modmail_thread_opened",
+            guild_id = thread.guild_id,
+            user_id = thread.user_id,
+            thread_id = thread.thread_id,
+            "Opened modmail thread"
+        );
+        Ok(())
+    }
+
+    /// Whether the thread was open and is now closed
+    pub async fn close(&self, thread_id: u64) -> Result<bool, sqlx::Error> {
+        let result = sqlx::query(
+            "UPDATE chloe_modmail_threads SET status = 'closed', closed_at = CURRENT_TIMESTAMP
+             WHERE thread_id = $1 AND status = 'open'",
+        )
+        .bind(thread_id as i64)
+        .execute(&self.db_pool)
+        .await?;
+
+        *self.open.write().await = None;
+        Ok(result.rows_affected() > 0)
+    }
+
+    async fn find(&self, matches: impl Fn(&ModmailThread) -> bool) -> Vec<ModmailThread> {
+        if let Some(open) = self.open.read().await.as_ref() {
+            return open
+                .iter()
+                .filter(|thread| matches(thread))
+                .cloned()
+                .collect();
+        }
+
+        let open = match self.load_open().await {
+            Ok(open) => open,
+            Err(e) => {
+                error!(
+                    event = "modmail_threads_load_failed",
+                    error = ?e,
+                    "Failed to load modmail threads"
+                );
+                return Vec::new();
+            }
+        };
+        let found = open
+            .iter()
+            .filter(|thread| matches(thread))
+            .cloned()
+            .collect();
+        *self.open.write().await = Some(open);
+        found
+    }
+
+    async fn load_open(&self) -> Result<Vec<ModmailThread>, sqlx::Error> {
+        let rows = sqlx::query(
+            "SELECT g.snowflake_id, t.user_id, t.thread_id FROM chloe_modmail_threads t
+             JOIN chloe_guilds g ON t.guild_id = g.id
+             WHERE t.status = 'open'",
+        )
+        .fetch_all(&self.db_pool)
+        .await?;
+
+        Ok(rows
+            .iter()
+            .map(|row| ModmailThread {
+                guild_id: row.get::<i64, _>("snowflake_id") as u64,
+                user_id: row.get::<i64, _>("user_id") as u64,
+                thread_id: row.get::<i64, _>("thread_id") as u64,
+            })
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_modmail_channel() {
+        let setting = json!({"channelId": "123"});
+        assert_eq!(modmail_channel(Some(&setting)), Some(123));
+        assert_eq!(modmail_channel(Some(&json!({}))), None);
+        assert_eq!(modmail_channel(None), None);
+    }
+
+    #[test]
+    fn test_internal_notes_are_not_relayed() {
+        assert!(is_relayed("hi, we're looking into it"));
+        assert!(!is_relayed("  // they were warned last week"));
+    }
+}
@@ -0,0 +1,362 @@
+use crate::services::gemini_types::{
+    Candidate, FinishReason, FunctionCall, GeminiRequest, GeminiResponse, Part, ResponseContent,
+    ResponsePart, UsageMetadata,
+};
+use crate::services::provider_timeouts::ProviderTimeouts;
+use anyhow::{Context, Result};
+use reqwest::Client;
+use serde::{Deserialize, Serialize};
+use serde_json::{Value, json};
+use std::time::{Duration, Instant};
+use tokio::sync::RwLock;
+use tracing::info;
+
+/// Models served by ollama are written `ollama:<name>`, e.g. `ollama:llama3.1:8b`. They have
+/// no per-model url, so the prefixed name doubles as their endpoint.
+pub const MODEL_PREFIX: &str = "ollama:";
+
+/// How long the /api/tags listing is trusted before asking again
+const MODELS_TTL: Duration = Duration::from_secs(60);
+
+/// The ollama model behind an endpoint, None for gemini urls
+pub fn model_name(endpoint: &str) -> Option<&str> {
+    endpoint.strip_prefix(MODEL_PREFIX)
+}
+
+#[derive(Debug, Serialize)]
+struct ChatRequest {
+    model: String,
+    messages: Vec<ChatMessage>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    tools: Vec<Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    options: Option<Value>,
+    stream: bool,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct ChatMessage {
+    role: String,
+    #[serde(default)]
+    content: String,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    images: Vec<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    tool_calls: Vec<ToolCall>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct ToolCall {
+    function: ToolCallFunction,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct ToolCallFunction {
+    name: String,
+    arguments: Value,
+}
+
+#[derive(Debug, Deserialize)]
+struct ChatResponse {
+    model: Option<String>,
+    message: ChatMessage,
+    done_reason: Option<String>,
+    prompt_eval_count: Option<i32>,
+    eval_count: Option<i32>,
+}
+
+#[derive(Debug, Deserialize)]
+struct TagsResponse {
+    models: Vec<TagsModel>,
+}
+
+#[derive(Debug, Deserialize)]
+struct TagsModel {
+    name: String,
+}
+
+/// Chat against a local ollama server, so chloe can be self-hosted without paid keys.
+/// Requests and responses are translated from and to the gemini types the rest of the
+/// service works with.
+pub struct OllamaProvider {
+    client: Client,
+    timeouts: ProviderTimeouts,
+    base_url: String,
+    models: RwLock<Option<(Vec<String>, Instant)>>,
+}
+
+impl OllamaProvider {
+    /// Enabled by `OLLAMA_URL`, e.g. `http://localhost:11434`, with its own timeouts
+    pub fn from_env() -> Option<Self> {
+        let base_url = std::env::var("OLLAMA_URL")
+            .ok()
+            .filter(|url| !url.is_empty())?;
+        let timeouts = ProviderTimeouts::ollama();
+        Some(Self {
+            client: timeouts.client(),
+            timeouts,
+            base_url: base_url.trim_end_matches('/').to_string(),
+            models: RwLock::new(None),
+        })
+    }
+
+    /// Models actually pulled on the server, from /api/tags
+    pub async fn available_models(&self) -> Result<Vec<String>> {
+        let cached = self
+            .models
+            .read()
+            .await
+            .as_ref()
+            .filter(|(_, fetched_at)| fetched_at.elapsed() < MODELS_TTL)
+            .map(|(models, _)| models.clone());
+        if let Some(models) = cached {
+            return Ok(models);
+        }
+
+        let tags: TagsResponse = self
+            .client
+            .get(format!("{}/api/tags", self.base_url))
+            .send()
+            .await
+            .map_err(|e| self.timeouts.classify(e, "Failed to reach ollama"))?
+            .error_for_status()
+            .context("Ollama model listing failed")?
+            .json()
+            .await
+            .context("Failed to parse ollama model listing")?;
+
+        let models: Vec<String> = tags.models.into_iter().map(|model| model.name).collect();
+        info!(
+            event = "ollama_models_listed",
+            models = ?models,
+            "Listed models pulled on ollama"
+        );
+        *self.models.write().await = Some((models.clone(), Instant::now()));
+        Ok(models)
+    }
+
+    pub async fn chat(&self, model: &str, request: &GeminiRequest) -> Result<GeminiResponse> {
+        let available = self.available_models().await?;
+        // `llama3` is pulled as `llama3:latest`
+        let pulled = available
+            .iter()
+            .any(|name| name == model || name.strip_suffix(":latest") == Some(model));
+        if !pulled {
+            return Err(anyhow::anyhow!(
+                "Ollama model {} isn't pulled, available: {}",
+                model,
+                available.join(", ")
+            ));
+        }
+
+        let response = self
+            .client
+            .post(format!("{}/api/chat", self.base_url))
+            .json(&to_chat_request(model, request))
+            .send()
+            .await
+            .map_err(|e| self.timeouts.classify(e, "Failed to send request to ollama"))?;
+
+        if !response.status().is_success() {
+            let status = response.status();
+            let error_text = response.text().await.unwrap_or_default();
+            return Err(anyhow::anyhow!(
+                "Ollama request failed with status {}: {}",
+                status,
+                error_text
+            ));
+        }
+
+        let response: ChatResponse = response
+            .json()
+            .await
+            .map_err(|e| self.timeouts.classify(e, "Failed to parse ollama response"))?;
+        Ok(to_gemini_response(response))
+    }
+}
+
+/// Gemini keeps the whole exchange in one content, a function call and its result are
+/// separate assistant and tool messages for ollama. Ollama has nothing like gemini's
+/// safety settings, so they don't carry over.
+fn to_chat_request(model: &str, request: &GeminiRequest) -> ChatRequest {
+    let mut messages: Vec<ChatMessage> = Vec::new();
+    let mut user = ChatMessage {
+        role: "user".to_string(),
+        ..Default::default()
+    };
+
+    let parts = request.contents.iter().flat_map(|content| &content.parts);
+    for part in parts {
+        match part {
+            Part::Text { text } => {
+                if !user.content.is_empty() {
+                    user.content.push_str("\n\n");
+                }
+                user.content.push_str(text);
+            }
+            Part::InlineData { inline_data } => user.images.push(inline_data.data.clone()),
+            Part::FunctionCall { function_call } => {
+                messages.push(std::mem::replace(
+                    &mut user,
+                    ChatMessage {
+                        role: "user".to_string(),
+                        ..Default::default()
+                    },
+                ));
+                messages.push(ChatMessage {
+                    role: "assistant".to_string(),
+                    tool_calls: vec![ToolCall {
+                        function: ToolCallFunction {
+                            name: function_call.name.clone(),
+                            arguments: function_call.args.clone(),
+                        },
+                    }],
+                    ..Default::default()
+                });
+            }
+            Part::FunctionResponse { function_response } => messages.push(ChatMessage {
+                role: "tool".to_string(),
+                content: serde_json::to_string(&function_response.response).unwrap_or_default(),
+                ..Default::default()
+            }),
+        }
+    }
+    if !user.content.is_empty() || !user.images.is_empty() {
+        messages.push(user);
+    }
+    messages.retain(|message| {
+        message.role != "user" || !message.content.is_empty() || !message.images.is_empty()
+    });
+
+    let tools = request
+        .tools
+        .iter()
+        .flatten()
+        .flat_map(|tool| &tool.function_declarations)
+        .map(|declaration| json!({"type": "function", "function": declaration}))
+        .collect();
+
+    ChatRequest {
+        model: model.to_string(),
+        messages,
+        tools,
+        options: request
+            .temperature()
+            .map(|temperature| json!({"temperature": temperature})),
+        stream: false,
+    }
+}
+
+fn to_gemini_response(response: ChatResponse) -> GeminiResponse {
+    let mut parts = Vec::new();
+    if !response.message.content.trim().is_empty() {
+        parts.push(ResponsePart::Text {
+            text: response.message.content,
+        });
+    }
+    for call in response.message.tool_calls {
+        parts.push(ResponsePart::FunctionCall {
+            function_call: FunctionCall {
+                name: call.function.name,
+                args: call.function.arguments,
+            },
+        });
+    }
+
+    GeminiResponse {
+        candidates: Some(vec![Candidate {
+            content: Some(ResponseContent {
+                parts: Some(parts),
+                role: Some("model".to_string()),
+            }),
+            finish_reason: response.done_reason.map(FinishReason::from),
+            index: Some(0),
+            safety_ratings: None,
+        }]),
+        prompt_feedback: None,
+        model_version: response.model,
+        response_id: None,
+        usage_metadata: Some(UsageMetadata {
+            candidates_token_count: response.eval_count,
+            prompt_token_count: response.prompt_eval_count,
+            total_token_count: match (response.prompt_eval_count, response.eval_count) {
+                (Some(prompt), Some(eval)) => Some(prompt + eval),
+                _ => None,
+            },
+        }),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::services::gemini_types::{FunctionResponse, FunctionResponseData};
+
+    #[test]
+    fn test_to_chat_request_splits_tool_exchange() {
+        let call = FunctionCall {
+            name: "web_search".to_string(),
+            args: json!({"query": "rust"}),
+        };
+        let request = GeminiRequest::new("hi chloe")
+            .add_function_call_parts(
+                &call,
+                FunctionResponse {
+                    name: "web_search".to_string(),
+                    response: FunctionResponseData {
+                        result: Some("results".to_string()),
+                        error: None,
+                    },
+                },
+            )
+            .with_tools(vec![json!({"name": "web_search"})])
+            .with_temperature(Some(0.5));
+
+        let chat = to_chat_request("llama3", &request);
+        let roles: Vec<&str> = chat.messages.iter().map(|m| m.role.as_str()).collect();
+        assert_eq!(roles, ["user", "assistant", "tool"]);
+        assert_eq!(chat.messages[0].content, "hi chloe");
+        assert_eq!(chat.messages[1].tool_calls[0].function.name, "web_search");
+        assert_eq!(chat.messages[2].content, r#"{"result":"results"}"#);
+        assert_eq!(chat.tools[0]["function"]["name"], "web_search");
+        assert_eq!(chat.options, Some(json!({"temperature": 0.5})));
+    }
+
+    #[test]
+    fn test_to_gemini_response_maps_text_and_tool_calls() {
+        let response: ChatResponse = serde_json::from_value(json!({
+            "model": "llama3",
+            "message": {
+                "role": "assistant",
+                "content": "",
+                "tool_calls": [{"function": {"name": "discord_send_message", "arguments": {"content": "hey"}}}]
+            },
+            "done_reason": "stop",
+            "prompt_eval_count": 10,
+            "eval_count": 5
+        }))
+        .unwrap();
+
+        let gemini = to_gemini_response(response);
+        assert!(!gemini.has_text());
+        assert_eq!(
+            gemini.get_function_call().map(|call| call.name.as_str()),
+            Some("discord_send_message")
+        );
+        assert_eq!(
+            gemini
+                .usage_metadata
+                .and_then(|usage| usage.total_token_count),
+            Some(15)
+        );
+    }
+
+    #[test]
+    fn test_model_name() {
+        assert_eq!(model_name("ollama:llama3.1:8b"), Some("llama3.1:8b"));
+        assert_eq!(
+            model_name("https://generativelanguage.googleapis.com/v1beta/models/x"),
+            None
+        );
+    }
+}
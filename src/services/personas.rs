@@ -0,0 +1,226 @@
+use sqlx::{PgPool, Row};
+use tracing::info;
+
+/// Guild setting holding the key of the persona the server picked
+pub const PERSONA_SETTING: &str = "persona";
+
+pub const MAX_KEY_LENGTH: usize = 32;
+pub const MAX_NAME_LENGTH: usize = 100;
+pub const MAX_DESCRIPTION_LENGTH: usize = 200;
+pub const MAX_OVERLAY_LENGTH: usize = 2000;
+
+/// A persona is a prompt overlay added on top of chloe's global prompt in servers that
+/// pick it, so it changes her tone without replacing who she is
+#[derive(Debug, Clone, PartialEq)]
+pub struct Persona {
+    pub key: String,
+    pub name: String,
+    pub description: String,
+    pub overlay: String,
+    pub built_in: bool,
+}
+
+struct BuiltInPersona {
+    key: &'static str,
+    name: &'static str,
+    description: &'static str,
+    overlay: &'static str,
+}
+
+impl BuiltInPersona {
+    fn to_persona(&self) -> Persona {
+        Persona {
+            key: self.key.to_string(),
+            name: self.name.to_string(),
+            description: self.description.to_string(),
+            overlay: self.overlay.to_string(),
+            built_in: true,
+        }
+    }
+}
+
+const BUILT_IN_PERSONAS: &[BuiltInPersona] = &[
+    BuiltInPersona {
+        key: "assistant",
+        name: "helpful assistant",
+        description: "patient and to the point, answers first and jokes later",
+        overlay: "Be a helpful assistant first. Answer the question directly and completely, keep jokes and roasts to a minimum, and ask a short follow-up question when a request is ambiguous instead of guessing.",
+    },
+    BuiltInPersona {
+        key: "gremlin",
+        name: "sarcastic gremlin",
+        description: "chaotic, sarcastic and dramatic, still gets the answer right",
+        overlay: "Be a sarcastic little gremlin. Tease people, be dramatic and unhinged in a playful way and never take anything too seriously, but when someone actually needs an answer give them a correct one between the sass. Never be mean about things people can't change.",
+    },
+    BuiltInPersona {
+        key: "study",
+        name: "study buddy",
+        description: "explains step by step and quizzes you instead of doing your homework",
+        overlay: "Be a study buddy. Explain things step by step with small examples, check understanding with a quick question at the end, and when someone asks for homework answers guide them to the solution instead of handing it over. Keep the mood encouraging.",
+    },
+];
+
+fn built_in(key: &str) -> Option<&'static BuiltInPersona> {
+    BUILT_IN_PERSONAS.iter().find(|persona| persona.key == key)
+}
+
+/// Lowercase a preset key, None when it isn't 1 to 32 of a-z, 0-9, `-` and `_`
+pub fn normalize_key(key: &str) -> Option<String> {
+    let key = key.trim().to_lowercase();
+    let valid = !key.is_empty()
+        && key.len() <= MAX_KEY_LENGTH
+        && key
+            .chars()
+            .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || matches!(c, '-' | '_'));
+    valid.then_some(key)
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum PersonaError {
+    #[error("preset keys are 1 to {MAX_KEY_LENGTH} letters, digits, dashes or underscores")]
+    InvalidKey,
+    #[error("`{0}` is a built-in preset and can't be changed")]
+    BuiltIn(String),
+    #[error("database error: {0}")]
+    Database(#[from] sqlx::Error),
+}
+
+/// The built-in persona presets plus the ones superadmins published to
+/// `chloe_persona_presets`, which every server can pick from
+pub struct PersonaService {
+    db_pool: PgPool,
+}
+
+impl PersonaService {
+    pub fn new(db_pool: PgPool) -> Self {
+        Self { db_pool }
+    }
+
+    /// Built-in presets first, then published ones by key
+    pub async fn list(&self) -> Result<Vec<Persona>, sqlx::Error> {
+        let rows = sqlx::query(
+            "SELECT key, name, description, overlay FROM chloe_persona_presets ORDER BY key",
+        )
+        .fetch_all(&self.db_pool)
+        .await?;
+
+        let mut personas: Vec<Persona> = BUILT_IN_PERSONAS
+            .iter()
+            .map(BuiltInPersona::to_persona)
+            .collect();
+        personas.extend(rows.iter().map(persona_from_row));
+        Ok(personas)
+    }
+
+    pub async fn find(&self, key: &str) -> Result<Option<Persona>, sqlx::Error> {
+        let Some(key) = normalize_key(key) else {
+            return Ok(None);
+        };
+        if let Some(persona) = built_in(&key) {
+            return Ok(Some(persona.to_persona()));
+        }
+        let row = sqlx::query(
+            "SELECT key, name, description, overlay FROM chloe_persona_presets WHERE key = $1",
+        )
+        .bind(&key)
+        .fetch_optional(&self.db_pool)
+        .await?;
+        Ok(row.as_ref().map(persona_from_row))
+    }
+
+    /// Publish a preset to every server, replacing a published one with the same key
+    pub async fn publish(
+        &self,
+        key: &str,
+        name: &str,
+        description: &str,
+        overlay: &str,
+        created_by: u64,
+    ) -> Result<Persona, PersonaError> {
+        let key = normalize_key(key).ok_or(PersonaError::InvalidKey)?;
+        if built_in(&key).is_some() {
+            return Err(PersonaError::BuiltIn(key));
+        }
+
+        sqlx::query(
+            "INSERT INTO chloe_persona_presets (key, name, description, overlay, created_by)
+             VALUES ($1, $2, $3, $4, $5)
+             ON CONFLICT (key) DO UPDATE
+             SET name = EXCLUDED.name, description = EXCLUDED.description,
+                 overlay = EXCLUDED.overlay, created_by = EXCLUDED.created_by,
+                 modified_at = CURRENT_TIMESTAMP",
+        )
+        .bind(&key)
+        .bind(name)
+        .bind(description)
+        .bind(overlay)
+        .bind(created_by as i64)
+        .execute(&self.db_pool)
+        .await?;
+
+        info!(
+            event = "persona_published",
+            key = %key,
+            created_by = created_by,
+            "Published persona preset"
+        );
+        Ok(Persona {
+            key,
+            name: name.to_string(),
+            description: description.to_string(),
+            overlay: overlay.to_string(),
+            built_in: false,
+        })
+    }
+
+    /// Remove a published preset, servers that picked it go back to no persona
+    pub async fn unpublish(&self, key: &str) -> Result<bool, PersonaError> {
+        let key = normalize_key(key).ok_or(PersonaError::InvalidKey)?;
+        if built_in(&key).is_some() {
+            return Err(PersonaError::BuiltIn(key));
+        }
+        let result = sqlx::query("DELETE FROM chloe_persona_presets WHERE key = $1")
+            .bind(&key)
+            .execute(&self.db_pool)
+            .await?;
+        if result.rows_affected() > 0 {
+            info!(event = "persona_unpublished", key = %key, "Removed persona preset");
+        }
+        Ok(result.rows_affected() > 0)
+    }
+}
+
+fn persona_from_row(row: &sqlx::postgres::PgRow) -> Persona {
+    Persona {
+        key: row.get("key"),
+        name: row.get("name"),
+        description: row.get("description"),
+        overlay: row.get("overlay"),
+        built_in: false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_normalize_key() {
+        assert_eq!(normalize_key(" Study "), Some("study".to_string()));
+        assert_eq!(normalize_key("pirate_2"), Some("pirate_2".to_string()));
+        assert_eq!(normalize_key(""), None);
+        assert_eq!(normalize_key("no spaces"), None);
+        assert_eq!(normalize_key(&"a".repeat(MAX_KEY_LENGTH + 1)), None);
+    }
+
+    #[test]
+    fn test_built_in_personas_are_valid() {
+        for persona in BUILT_IN_PERSONAS {
+            assert_eq!(normalize_key(persona.key).as_deref(), Some(persona.key));
+            assert!(persona.name.chars().count() <= MAX_NAME_LENGTH);
+            assert!(persona.description.chars().count() <= MAX_DESCRIPTION_LENGTH);
+            assert!(persona.overlay.chars().count() <= MAX_OVERLAY_LENGTH);
+        }
+        assert!(built_in("gremlin").is_some());
+    }
+}
@@ -0,0 +1,103 @@
+use serde_json::Value;
+use std::collections::HashMap;
+
+/// Guild setting that turns ping replies on
+pub const PING_REPLY_SETTING: &str = "ping_reply";
+/// Guild setting with the reply templates
+pub const PING_RESPONSES_SETTING: &str = "pingResponses";
+
+const DEFAULT_TEMPLATE: &str = "hiii {user}! say my name or ask me something to chat 💅";
+
+/// What chloe says when someone pings her without saying anything else, from the guild
+/// setting `pingResponses`, e.g. `{"default": "hi {user}", "roles": {"123": "hi boss"}}`.
+/// Templates may use `{user}`, `{server}` and `{channel}`.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct PingResponses {
+    pub default: Option<String>,
+    pub roles: HashMap<u64, String>,
+}
+
+impl PingResponses {
+    pub fn from_setting(setting: Option<&Value>) -> Self {
+        let Some(setting) = setting else {
+            return Self::default();
+        };
+        let template = |value: &Value| {
+            value
+                .as_str()
+                .map(str::trim)
+                .filter(|template| !template.is_empty())
+                .map(str::to_string)
+        };
+        let roles = setting
+            .get("roles")
+            .and_then(Value::as_object)
+            .map(|roles| {
+                roles
+                    .iter()
+                    .filter_map(|(role, value)| Some((role.parse().ok()?, template(value)?)))
+                    .collect()
+            })
+            .unwrap_or_default();
+        Self {
+            default: setting.get("default").and_then(template),
+            roles,
+        }
+    }
+
+    /// The template for the first of `roles` that has one, so callers pass the member's
+    /// roles highest first
+    pub fn template_for(&self, roles: &[u64]) -> &str {
+        roles
+            .iter()
+            .find_map(|role| self.roles.get(role))
+            .or(self.default.as_ref())
+            .map(String::as_str)
+            .unwrap_or(DEFAULT_TEMPLATE)
+    }
+}
+
+pub fn render(template: &str, user_id: u64, server: &str, channel_id: u64) -> String {
+    template
+        .replace("{user}", &format!("<@{}>", user_id))
+        .replace("{server}", server)
+        .replace("{channel}", &format!("<#{}>", channel_id))
+}
+
+/// Whether the message is nothing but a mention of `bot_id`
+pub fn is_bare_ping(content: &str, bot_id: u64) -> bool {
+    let content = content.trim();
+    content == format!("<@{}>", bot_id) || content == format!("<@!{}>", bot_id)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_template_for_roles() {
+        let responses = PingResponses::from_setting(Some(&json!({
+            "default": "hey {user}",
+            "roles": {"10": "hi mod {user}", "20": " ", "nope": "ignored"}
+        })));
+        assert_eq!(responses.roles.len(), 1);
+        assert_eq!(responses.template_for(&[30, 10]), "hi mod {user}");
+        assert_eq!(responses.template_for(&[20]), "hey {user}");
+        assert_eq!(
+            PingResponses::from_setting(None).template_for(&[]),
+            DEFAULT_TEMPLATE
+        );
+    }
+
+    #[test]
+    fn test_render_and_bare_ping() {
+        assert_eq!(
+            render("{user} in {channel} on {server}", 1, "gyaru", 2),
+            "<@1> in <#2> on gyaru"
+        );
+        assert!(is_bare_ping(" <@42> ", 42));
+        assert!(is_bare_ping("<@!42>", 42));
+        assert!(!is_bare_ping("<@42> hi", 42));
+    }
+}
@@ -0,0 +1,466 @@
+use crate::services::gemini_types::{GeminiRequest, GeminiResponse};
+use crate::services::llm_service::{ConversationContext, PromptVersion, model_endpoint};
+use crate::services::semantic_memory::MemorySource;
+use crate::settings::Settings;
+use crate::utils::regex_patterns::{EMAIL_REGEX, SNOWFLAKE_REGEX};
+use anyhow::{Context, Result};
+use reqwest::Client;
+use serde_json::{Value, json};
+use sha2::{Digest, Sha256};
+use sqlx::{PgPool, Row, types::Json};
+use std::collections::HashMap;
+use tracing::{error, info, warn};
+
+/// Stands in for the system prompt in recorded requests so replays can swap in another one
+const SYSTEM_PROMPT_PLACEHOLDER: &str = "{{system_prompt}}";
+
+const DEFAULT_REPLAY_LIMIT: i64 = 50;
+
+/// Names and presence in a conversation, which the anonymizer can't tell apart from other
+/// text by their shape
+#[derive(Debug, Clone, Default)]
+pub struct PersonalDetails {
+    /// Display names, usernames and nicknames of the people in the conversation
+    pub names: Vec<String>,
+    pub activity: Option<String>,
+}
+
+impl PersonalDetails {
+    pub fn from_context(context: &ConversationContext) -> Self {
+        let messages = context
+            .recent_messages
+            .iter()
+            .chain(&context.referenced_message)
+            .chain(context.linked_messages.iter().flat_map(|linked| {
+                linked
+                    .before
+                    .iter()
+                    .chain(std::iter::once(&linked.message))
+                    .chain(&linked.after)
+            }))
+            .filter(|msg| !msg.is_self && !msg.is_bot)
+            .map(|msg| msg.user_display_name.clone());
+        let users = context
+            .user_info
+            .iter()
+            .filter(|user| !user.is_bot)
+            .map(|user| user.display_name.clone());
+        let memories = context
+            .relevant_memories
+            .iter()
+            .filter(|memory| memory.source == MemorySource::Message)
+            .map(|memory| memory.author_name.clone());
+
+        let mut names: Vec<String> = std::iter::once(context.current_user.clone())
+            .chain(messages)
+            .chain(users)
+            .chain(memories)
+            .filter(|name| !name.trim().is_empty())
+            .collect();
+        names.sort();
+        names.dedup();
+        Self {
+            names,
+            activity: context.author_activity.clone(),
+        }
+    }
+}
+
+/// Opt-in recorder, enabled with `RECORD_PROVIDER_TRAFFIC=true`, that stores anonymized
+/// Gemini requests and responses as a regression corpus for `chloe replay`
+#[derive(Clone)]
+pub struct ProviderRecorder {
+    db_pool: PgPool,
+}
+
+impl ProviderRecorder {
+    pub fn from_env(db_pool: PgPool) -> Option<Self> {
+        let enabled = std::env::var("RECORD_PROVIDER_TRAFFIC")
+            .map(|value| value == "true" || value == "1")
+            .unwrap_or(false);
+
+        if enabled {
+            info!(
+                event = "provider_recording_enabled",
+                "Recording anonymized provider traffic"
+            );
+        }
+        enabled.then_some(Self { db_pool })
+    }
+
+    /// Anonymize one request/response pair and store it in the background
+    pub fn record(
+        &self,
+        model: &str,
+        system_prompt: &str,
+        prompt_version: PromptVersion,
+        details: &PersonalDetails,
+        request: &GeminiRequest,
+        response: &GeminiResponse,
+    ) {
+        let (request, response) = match (
+            serde_json::to_value(request),
+            serde_json::to_value(response),
+        ) {
+            (Ok(request), Ok(response)) => (request, response),
+            _ => return,
+        };
+
+        let mut anonymizer = Anonymizer::new(system_prompt, details);
+        let request = anonymizer.anonymize(request);
+        let response = anonymizer.anonymize(response);
+        let prompt_hash = prompt_hash(system_prompt);
+        let (prompt_version, guild_prompt) = match prompt_version {
+            PromptVersion::Global(version) => (version, false),
+            PromptVersion::Guild(version) => (version, true),
+        };
+        let model = model.to_string();
+        let db_pool = self.db_pool.clone();
+
+        tokio::spawn(async move {
+            let result = sqlx::query(
+                "INSERT INTO chloe_provider_recordings (model, prompt_hash, prompt_version, guild_prompt, request, response)
+                 VALUES ($1, $2, $3, $4, $5, $6)",
+            )
+            .bind(&model)
+            .bind(&prompt_hash)
+            .bind(prompt_version)
+            .bind(guild_prompt)
+            .bind(Json(&request))
+            .bind(Json(&response))
+            .execute(&db_pool)
+            .await;
+
+            if let Err(e) = result {
+                error!(
+                    event = "provider_recording_failed",
+                    model = %model,
+                    error = ?e,
+                    "Failed to store provider recording"
+                );
+            }
+        });
+    }
+}
+
+/// Hex sha256 of a prompt
+pub(crate) fn prompt_hash(prompt: &str) -> String {
+    Sha256::digest(prompt.as_bytes())
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect()
+}
+
+/// Strips what could identify people from recorded traffic. Snowflakes and names are
+/// swapped for stable fakes within one exchange so mentions and attributions still line
+/// up, presence is dropped and images too.
+struct Anonymizer<'a> {
+    system_prompt: &'a str,
+    /// Longest first, so a name isn't swapped inside a longer one containing it
+    names: Vec<&'a str>,
+    activity: Option<&'a str>,
+    ids: HashMap<String, String>,
+    pseudonyms: HashMap<String, String>,
+}
+
+impl<'a> Anonymizer<'a> {
+    fn new(system_prompt: &'a str, details: &'a PersonalDetails) -> Self {
+        let mut names: Vec<&str> = details.names.iter().map(String::as_str).collect();
+        names.sort_by_key(|name| std::cmp::Reverse(name.chars().count()));
+        Self {
+            system_prompt,
+            names,
+            activity: details
+                .activity
+                .as_deref()
+                .filter(|activity| !activity.is_empty()),
+            ids: HashMap::new(),
+            pseudonyms: HashMap::new(),
+        }
+    }
+
+    fn anonymize(&mut self, value: Value) -> Value {
+        match value {
+            Value::String(text) => Value::String(self.scrub(&text)),
+            Value::Array(items) => Value::Array(
+                items
+                    .into_iter()
+                    .filter(|item| item.get("inline_data").is_none())
+                    .map(|item| self.anonymize(item))
+                    .collect(),
+            ),
+            Value::Object(fields) => Value::Object(
+                fields
+                    .into_iter()
+                    .map(|(key, value)| (key, self.anonymize(value)))
+                    .collect(),
+            ),
+            other => other,
+        }
+    }
+
+    fn scrub(&mut self, text: &str) -> String {
+        let text = if self.system_prompt.is_empty() {
+            text.to_string()
+        } else {
+            text.replace(self.system_prompt, SYSTEM_PROMPT_PLACEHOLDER)
+        };
+        let mut text = EMAIL_REGEX
+            .replace_all(&text, "user@example.com")
+            .into_owned();
+        if let Some(activity) = self.activity {
+            text = text.replace(activity, "something");
+        }
+        for name in &self.names {
+            let next = self.pseudonyms.len() + 1;
+            let pseudonym = self
+                .pseudonyms
+                .entry(name.to_string())
+                .or_insert_with(|| format!("member{}", next));
+            text = replace_word(&text, name, pseudonym);
+        }
+
+        let ids = &mut self.ids;
+        SNOWFLAKE_REGEX
+            .replace_all(&text, |caps: &regex::Captures| {
+                let next = ids.len() as u64 + 1;
+                ids.entry(caps[0].to_string())
+                    .or_insert_with(|| (100_000_000_000_000_000 + next).to_string())
+                    .clone()
+            })
+            .into_owned()
+    }
+}
+
+/// Replace `word` where it isn't part of a longer word, so a member called "Al" leaves
+/// "Also" alone
+fn replace_word(text: &str, word: &str, replacement: &str) -> String {
+    let is_word_char = |c: Option<char>| c.is_some_and(|c| c.is_alphanumeric() || c == '_');
+    let mut out = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(start) = rest.find(word) {
+        let end = start + word.len();
+        let bounded = !is_word_char(rest[..start].chars().next_back())
+            && !is_word_char(rest[end..].chars().next());
+        out.push_str(&rest[..start]);
+        out.push_str(if bounded { replacement } else { word });
+        rest = &rest[end..];
+    }
+    out.push_str(rest);
+    out
+}
+
+fn fill_system_prompt(value: Value, system_prompt: &str) -> Value {
+    match value {
+        Value::String(text) => {
+            Value::String(text.replace(SYSTEM_PROMPT_PLACEHOLDER, system_prompt))
+        }
+        Value::Array(items) => Value::Array(
+            items
+                .into_iter()
+                .map(|item| fill_system_prompt(item, system_prompt))
+                .collect(),
+        ),
+        Value::Object(fields) => Value::Object(
+            fields
+                .into_iter()
+                .map(|(key, value)| (key, fill_system_prompt(value, system_prompt)))
+                .collect(),
+        ),
+        other => other,
+    }
+}
+
+fn summarize(response: &GeminiResponse) -> Value {
+    json!({
+        "text": response.get_text(),
+        "function_call": response.get_function_call(),
+    })
+}
+
+/// Options for `chloe replay [--model <name>] [--prompt-version <n>] [--limit <n>]`
+#[derive(Debug, Default)]
+pub struct ReplayOptions {
+    pub model: Option<String>,
+    pub prompt_version: Option<i32>,
+    pub limit: Option<i64>,
+}
+
+impl ReplayOptions {
+    pub fn from_args(args: &[String]) -> Result<Self> {
+        let mut options = Self::default();
+        let mut args = args.iter();
+        while let Some(flag) = args.next() {
+            let value = args
+                .next()
+                .with_context(|| format!("Missing value for {}", flag))?;
+            match flag.as_str() {
+                "--model" => options.model = Some(value.clone()),
+                "--prompt-version" => {
+                    options.prompt_version =
+                        Some(value.parse().context("--prompt-version must be a number")?)
+                }
+                "--limit" => {
+                    options.limit = Some(value.parse().context("--limit must be a number")?)
+                }
+                _ => anyhow::bail!("Unknown replay option {}", flag),
+            }
+        }
+        Ok(options)
+    }
+}
+
+/// Re-send recorded requests with the chosen prompt and model and print one JSON line
+/// per recording comparing the recorded and replayed answers
+pub async fn replay(db_pool: &PgPool, settings: &Settings, options: ReplayOptions) -> Result<()> {
+    let api_key =
+        std::env::var("GEMINI_API_KEY").context("GEMINI_API_KEY environment variable not set")?;
+
+    let system_prompt = match options.prompt_version {
+        Some(version) => settings
+            .get_prompt_content_by_version(db_pool, version)
+            .await?
+            .with_context(|| format!("No prompt with version {}", version))?,
+        None => {
+            settings.load_from_database(db_pool).await?;
+            settings.get_global_settings().await.prompt
+        }
+    };
+
+    // a guild's own prompt isn't kept anywhere replay can fill it back in from
+    let rows = sqlx::query(
+        "SELECT id, model, prompt_hash, request, response FROM chloe_provider_recordings
+         WHERE NOT guild_prompt ORDER BY created_at DESC LIMIT $1",
+    )
+    .bind(options.limit.unwrap_or(DEFAULT_REPLAY_LIMIT))
+    .fetch_all(db_pool)
+    .await?;
+
+    info!(
+        event = "replay_started",
+        recordings = rows.len(),
+        model = options.model.as_deref().unwrap_or("recorded"),
+        prompt_version = ?options.prompt_version,
+        "Replaying recorded provider traffic"
+    );
+
+    let client = Client::new();
+    let mut changed = 0;
+    for row in &rows {
+        let id: String = row.get("id");
+        let recorded_model: String = row.get("model");
+        let model = options.model.clone().unwrap_or(recorded_model);
+        let request = fill_system_prompt(row.get::<Json<Value>, _>("request").0, &system_prompt);
+        let recorded: GeminiResponse = match serde_json::from_value(
+            row.get::<Json<Value>, _>("response").0,
+        ) {
+            Ok(response) => response,
+            Err(e) => {
+                warn!(event = "replay_recording_invalid", id = %id, error = ?e, "Skipping unreadable recording");
+                continue;
+            }
+        };
+
+        let replayed = client
+            .post(model_endpoint(&model, &api_key))
+            .json(&request)
+            .send()
+            .await
+            .and_then(|response| response.error_for_status());
+        let replayed: Value = match replayed {
+            Ok(response) => match response.json::<GeminiResponse>().await {
+                Ok(response) => summarize(&response),
+                Err(e) => json!({ "error": e.to_string() }),
+            },
+            Err(e) => json!({ "error": e.to_string() }),
+        };
+
+        let recorded = summarize(&recorded);
+        if recorded != replayed {
+            changed += 1;
+        }
+        println!(
+            "{}",
+            json!({
+                "id": id,
+                "model": model,
+                "prompt_hash": row.get::<String, _>("prompt_hash"),
+                "recorded": recorded,
+                "replayed": replayed,
+                "changed": recorded != replayed,
+            })
+        );
+    }
+
+    info!(
+        event = "replay_finished",
+        recordings = rows.len(),
+        changed = changed,
+        "Replay complete"
+    );
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_anonymize_scrubs_ids_and_system_prompt() {
+        let details = PersonalDetails::default();
+        let mut anonymizer = Anonymizer::new("You're Chloe.", &details);
+        let request = json!({
+            "contents": [{"parts": [
+                {"text": "You're Chloe.\n<@123456789012345678> said hi to <@123456789012345678>, mail me at a.b@c.io"},
+                {"inline_data": {"mime_type": "image/png", "data": "aGk="}}
+            ]}]
+        });
+
+        let anonymized = anonymizer.anonymize(request);
+        let parts = anonymized["contents"][0]["parts"].as_array().unwrap();
+        assert_eq!(parts.len(), 1);
+        assert_eq!(
+            parts[0]["text"],
+            "{{system_prompt}}\n<@100000000000000001> said hi to <@100000000000000001>, mail me at user@example.com"
+        );
+
+        let filled = fill_system_prompt(anonymized, "You're Chloe v2.");
+        assert!(
+            filled["contents"][0]["parts"][0]["text"]
+                .as_str()
+                .unwrap()
+                .starts_with("You're Chloe v2.\n")
+        );
+    }
+
+    #[test]
+    fn test_anonymize_swaps_names_for_pseudonyms() {
+        let details = PersonalDetails {
+            names: vec!["Al".to_string(), "sarah 🌸".to_string()],
+            activity: Some("playing Elden Ring".to_string()),
+        };
+        let mut anonymizer = Anonymizer::new("You're Chloe.", &details);
+        let prompt = "You're Chloe.\n\n## Recent Conversation:\nsarah 🌸: Also, ask Al\nChloe: hiii\nAl: <@123456789012345678>\n\n## What sarah 🌸 Is Doing:\nTheir Discord status shows them playing Elden Ring.";
+        let request = json!({ "contents": [{ "parts": [{ "text": prompt }] }] });
+        let response = json!({ "text": "Al and sarah 🌸, hi!" });
+
+        let request = anonymizer.anonymize(request);
+        assert_eq!(
+            request["contents"][0]["parts"][0]["text"],
+            "{{system_prompt}}\n\n## Recent Conversation:\nmember1: Also, ask member2\nChloe: hiii\nmember2: <@100000000000000001>\n\n## What member1 Is Doing:\nTheir Discord status shows them something."
+        );
+        assert_eq!(
+            anonymizer.anonymize(response)["text"],
+            "member2 and member1, hi!"
+        );
+    }
+
+    #[test]
+    fn test_replay_options_from_args() {
+        let args = ["--model", "gemini-2.5-pro", "--limit", "5"].map(String::from);
+        let options = ReplayOptions::from_args(&args).unwrap();
+        assert_eq!(options.model.as_deref(), Some("gemini-2.5-pro"));
+        assert_eq!(options.limit, Some(5));
+        assert!(ReplayOptions::from_args(&["--bogus".to_string(), "1".to_string()]).is_err());
+    }
+}
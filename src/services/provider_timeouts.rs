@@ -0,0 +1,107 @@
+use crate::error::LlmError;
+use reqwest::Client;
+use std::time::Duration;
+
+/// Connect and read timeouts for one model provider. Read is the longest the provider
+/// may go quiet mid-response, which also bounds a stalled stream.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ProviderTimeouts {
+    pub provider: &'static str,
+    pub connect: Duration,
+    pub read: Duration,
+}
+
+impl ProviderTimeouts {
+    /// Gemini's timeouts from `GEMINI_CONNECT_TIMEOUT_SECS` and `GEMINI_READ_TIMEOUT_SECS`
+    pub fn gemini() -> Self {
+        Self::from_vars("gemini", "GEMINI", 5, 60, |key| std::env::var(key).ok())
+    }
+
+    /// Ollama's timeouts from `OLLAMA_CONNECT_TIMEOUT_SECS` and `OLLAMA_READ_TIMEOUT_SECS`,
+    /// local models are given longer to produce their first tokens
+    pub fn ollama() -> Self {
+        Self::from_vars("ollama", "OLLAMA", 5, 180, |key| std::env::var(key).ok())
+    }
+
+    fn from_vars(
+        provider: &'static str,
+        prefix: &str,
+        default_connect_secs: u64,
+        default_read_secs: u64,
+        var: impl Fn(&str) -> Option<String>,
+    ) -> Self {
+        let seconds = |name: &str, default: u64| {
+            let secs = var(&format!("{}_{}_TIMEOUT_SECS", prefix, name))
+                .and_then(|value| value.trim().parse::<f64>().ok())
+                .filter(|&secs| secs > 0.0)
+                .unwrap_or(default as f64);
+            Duration::from_secs_f64(secs)
+        };
+        Self {
+            provider,
+            connect: seconds("CONNECT", default_connect_secs),
+            read: seconds("READ", default_read_secs),
+        }
+    }
+
+    /// A client that gives up on this provider after the configured timeouts
+    pub fn client(&self) -> Client {
+        Client::builder()
+            .connect_timeout(self.connect)
+            .read_timeout(self.read)
+            .build()
+            .unwrap_or_else(|_| Client::new())
+    }
+
+    /// Turn a timed out request into `LlmError::Timeout` so callers can tell it apart
+    pub fn classify(&self, error: reqwest::Error, context: &'static str) -> anyhow::Error {
+        if error.is_timeout() {
+            let after = if error.is_connect() {
+                self.connect
+            } else {
+                self.read
+            };
+            anyhow::Error::new(LlmError::Timeout {
+                provider: self.provider,
+                after,
+            })
+        } else {
+            anyhow::Error::new(error).context(context)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    fn vars(pairs: &[(&str, &str)]) -> impl Fn(&str) -> Option<String> {
+        let pairs: HashMap<String, String> = pairs
+            .iter()
+            .map(|(key, value)| (key.to_string(), value.to_string()))
+            .collect();
+        move |key| pairs.get(key).cloned()
+    }
+
+    #[test]
+    fn test_from_vars() {
+        let defaults = ProviderTimeouts::from_vars("gemini", "GEMINI", 5, 60, vars(&[]));
+        assert_eq!(defaults.connect, Duration::from_secs(5));
+        assert_eq!(defaults.read, Duration::from_secs(60));
+
+        let configured = ProviderTimeouts::from_vars(
+            "ollama",
+            "OLLAMA",
+            5,
+            180,
+            vars(&[
+                ("OLLAMA_CONNECT_TIMEOUT_SECS", "1.5"),
+                ("OLLAMA_READ_TIMEOUT_SECS", "0"),
+                ("GEMINI_READ_TIMEOUT_SECS", "10"),
+            ]),
+        );
+        assert_eq!(configured.connect, Duration::from_millis(1500));
+        assert_eq!(configured.read, Duration::from_secs(180));
+    }
+}
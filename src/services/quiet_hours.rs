@@ -0,0 +1,82 @@
+use chrono::{DateTime, NaiveTime, Utc};
+use chrono_tz::Tz;
+use serde_json::Value;
+
+/// A daily window in which chloe doesn't speak up on her own, from the guild setting
+/// `quietHours`, e.g. `{"start": "23:00", "end": "08:00", "timezone": "Europe/Berlin"}`.
+/// Mentions and replies to her are still answered.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct QuietHours {
+    pub start: NaiveTime,
+    pub end: NaiveTime,
+    pub timezone: Tz,
+}
+
+impl QuietHours {
+    /// None when the setting is missing or malformed, a missing timezone means UTC
+    pub fn from_setting(setting: Option<&Value>) -> Option<Self> {
+        let setting = setting?;
+        let time =
+            |key: &str| NaiveTime::parse_from_str(setting.get(key)?.as_str()?.trim(), "%H:%M").ok();
+        let timezone = match setting.get("timezone").and_then(Value::as_str) {
+            Some(name) => name.trim().parse().ok()?,
+            None => Tz::UTC,
+        };
+        Some(Self {
+            start: time("start")?,
+            end: time("end")?,
+            timezone,
+        })
+    }
+
+    /// Whether `now` falls in the window in the guild's timezone. Windows may cross midnight,
+    /// one that starts and ends at the same time is empty.
+    pub fn contains(&self, now: DateTime<Utc>) -> bool {
+        let local = now.with_timezone(&self.timezone).time();
+        if self.start <= self.end {
+            self.start <= local && local < self.end
+        } else {
+            local >= self.start || local < self.end
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+    use serde_json::json;
+
+    #[test]
+    fn test_from_setting() {
+        let setting = json!({"start": "23:00", "end": "08:00", "timezone": "Europe/Berlin"});
+        let quiet = QuietHours::from_setting(Some(&setting)).unwrap();
+        assert_eq!(quiet.start, NaiveTime::from_hms_opt(23, 0, 0).unwrap());
+        assert_eq!(quiet.timezone, chrono_tz::Europe::Berlin);
+
+        let utc = QuietHours::from_setting(Some(&json!({"start": "1:00", "end": "02:30"})));
+        assert_eq!(utc.map(|quiet| quiet.timezone), Some(Tz::UTC));
+
+        assert!(QuietHours::from_setting(Some(&json!({"start": "23:00"}))).is_none());
+        let bad_zone = json!({"start": "23:00", "end": "08:00", "timezone": "Mars/Olympus"});
+        assert!(QuietHours::from_setting(Some(&bad_zone)).is_none());
+    }
+
+    #[test]
+    fn test_contains_across_midnight_in_timezone() {
+        let setting = json!({"start": "23:00", "end": "08:00", "timezone": "Europe/Berlin"});
+        let quiet = QuietHours::from_setting(Some(&setting)).unwrap();
+
+        // berlin is UTC+2 in summer
+        let at = |hour, minute| Utc.with_ymd_and_hms(2025, 7, 1, hour, minute, 0).unwrap();
+        assert!(quiet.contains(at(21, 0)));
+        assert!(quiet.contains(at(5, 59)));
+        assert!(!quiet.contains(at(6, 0)));
+        assert!(!quiet.contains(at(20, 59)));
+
+        let daytime = json!({"start": "09:00", "end": "17:00"});
+        let quiet = QuietHours::from_setting(Some(&daytime)).unwrap();
+        assert!(quiet.contains(at(9, 0)));
+        assert!(!quiet.contains(at(17, 0)));
+    }
+}
@@ -0,0 +1,200 @@
+use crate::utils::regex_patterns::MESSAGE_LINK_REGEX;
+use serenity::model::channel::ReactionType;
+use sqlx::{PgPool, Row};
+use std::collections::HashMap;
+use tokio::sync::RwLock;
+use tracing::{error, info};
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ReactionRole {
+    pub channel_id: u64,
+    pub message_id: u64,
+    pub emoji: String,
+    pub role_id: u64,
+}
+
+/// How an emoji is stored: custom emojis by id, since they can be renamed, unicode ones
+/// without the variation selector some keyboards add
+pub fn emoji_key(emoji: &ReactionType) -> String {
+    match emoji {
+        ReactionType::Custom { id, .. } => id.to_string(),
+        ReactionType::Unicode(unicode) => unicode.replace('\u{fe0f}', ""),
+        _ => emoji.to_string(),
+    }
+}
+
+/// A message link, or a bare message id in `current_channel`, as (channel id, message id)
+pub fn parse_message_ref(input: &str, current_channel: u64) -> Option<(u64, u64)> {
+    let input = input.trim();
+    if let Some(captures) = MESSAGE_LINK_REGEX.captures(input) {
+        let channel_id = captures.get(2)?.as_str().parse().ok()?;
+        let message_id = captures.get(3)?.as_str().parse().ok()?;
+        return Some((channel_id, message_id));
+    }
+    input
+        .parse()
+        .ok()
+        .map(|message_id| (current_channel, message_id))
+}
+
+/// Emoji to role mappings on designated messages. Every reaction in every guild is checked
+/// against them, so all mappings are kept in memory and reloaded after a change.
+pub struct ReactionRoleService {
+    db_pool: PgPool,
+    by_message: RwLock<Option<HashMap<u64, Vec<ReactionRole>>>>,
+}
+
+impl ReactionRoleService {
+    pub fn new(db_pool: PgPool) -> Self {
+        Self {
+            db_pool,
+            by_message: RwLock::new(None),
+        }
+    }
+
+    /// The role a reaction on a message grants, if any
+    pub async fn role_for(&self, message_id: u64, emoji: &ReactionType) -> Option<u64> {
+        let key = emoji_key(emoji);
+        let find = |mappings: &HashMap<u64, Vec<ReactionRole>>| {
+            mappings
+                .get(&message_id)?
+                .iter()
+                .find(|mapping| mapping.emoji == key)
+                .map(|mapping| mapping.role_id)
+        };
+
+        if let Some(mappings) = self.by_message.read().await.as_ref() {
+            return find(mappings);
+        }
+
+        let mappings = match self.load_all().await {
+            Ok(mappings) => mappings,
+            Err(e) => {
+                error!(
+                    event = "reaction_roles_load_failed",
+                    error = ?e,
+                    "Failed to load reaction roles"
+                );
+                return None;
+            }
+        };
+        let role_id = find(&mappings);
+        *self.by_message.write().await = Some(mappings);
+        role_id
+    }
+
+    /// Map an emoji on a message to a role, replacing the emoji's previous role
+    pub async fn add(
+        &self,
+        guild_id: i64,
+        mapping: &ReactionRole,
+        created_by: &str,
+    ) -> Result<(), sqlx::Error> {
+        let result = sqlx::query(
+            "INSERT INTO chloe_reaction_roles (guild_id, channel_id, message_id, emoji, role_id, created_by)
+             SELECT g.id, $2, $3, $4, $5, $6 FROM chloe_guilds g WHERE g.snowflake_id = $1
+             ON CONFLICT (message_id, emoji) DO UPDATE SET role_id = EXCLUDED.role_id",
+        )
+        .bind(guild_id)
+        .bind(mapping.channel_id as i64)
+        .bind(mapping.message_id as i64)
+        .bind(&mapping.emoji)
+        .bind(mapping.role_id as i64)
+        .bind(created_by)
+        .execute(&self.db_pool)
+        .await?;
+
+        if result.rows_affected() == 0 {
+            return Err(sqlx::Error::RowNotFound); // the guild was never registered
+        }
+        *self.by_message.write().await = None;
+        info!(
+            event = "reaction_role_added",
+            guild_id = guild_id,
+            message_id = mapping.message_id,
+            role_id = mapping.role_id,
+            "Added reaction role"
+        );
+        Ok(())
+    }
+
+    /// Whether a mapping existed and was removed
+    pub async fn remove(&self, message_id: u64, emoji: &str) -> Result<bool, sqlx::Error> {
+        let result =
+            sqlx::query("DELETE FROM chloe_reaction_roles WHERE message_id = $1 AND emoji = $2")
+                .bind(message_id as i64)
+                .bind(emoji)
+                .execute(&self.db_pool)
+                .await?;
+
+        *self.by_message.write().await = None;
+        Ok(result.rows_affected() > 0)
+    }
+
+    pub async fn list(&self, guild_id: i64) -> Result<Vec<ReactionRole>, sqlx::Error> {
+        let rows = sqlx::query(
+            "SELECT rr.channel_id, rr.message_id, rr.emoji, rr.role_id FROM chloe_reaction_roles rr
+             JOIN chloe_guilds g ON rr.guild_id = g.id
+             WHERE g.snowflake_id = $1
+             ORDER BY rr.created_at",
+        )
+        .bind(guild_id)
+        .fetch_all(&self.db_pool)
+        .await?;
+        Ok(rows.iter().map(row_to_mapping).collect())
+    }
+
+    async fn load_all(&self) -> Result<HashMap<u64, Vec<ReactionRole>>, sqlx::Error> {
+        let rows =
+            sqlx::query("SELECT channel_id, message_id, emoji, role_id FROM chloe_reaction_roles")
+                .fetch_all(&self.db_pool)
+                .await?;
+
+        let mut mappings: HashMap<u64, Vec<ReactionRole>> = HashMap::new();
+        for mapping in rows.iter().map(row_to_mapping) {
+            mappings
+                .entry(mapping.message_id)
+                .or_default()
+                .push(mapping);
+        }
+        Ok(mappings)
+    }
+}
+
+fn row_to_mapping(row: &sqlx::postgres::PgRow) -> ReactionRole {
+    ReactionRole {
+        channel_id: row.get::<i64, _>("channel_id") as u64,
+        message_id: row.get::<i64, _>("message_id") as u64,
+        emoji: row.get("emoji"),
+        role_id: row.get::<i64, _>("role_id") as u64,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_emoji_key() {
+        let custom = ReactionType::try_from("<:chloe:600404340292059257>").unwrap();
+        assert_eq!(emoji_key(&custom), "600404340292059257");
+        assert_eq!(
+            emoji_key(&ReactionType::Unicode("❤\u{fe0f}".to_string())),
+            emoji_key(&ReactionType::Unicode("❤".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_parse_message_ref() {
+        assert_eq!(
+            parse_message_ref("https://discord.com/channels/1/22/333", 9),
+            Some((22, 333))
+        );
+        assert_eq!(
+            parse_message_ref("https://ptb.discord.com/channels/1/22/333", 9),
+            Some((22, 333))
+        );
+        assert_eq!(parse_message_ref(" 333 ", 9), Some((9, 333)));
+        assert_eq!(parse_message_ref("not a message", 9), None);
+    }
+}
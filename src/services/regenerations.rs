@@ -0,0 +1,166 @@
+use crate::services::llm_service::ConversationContext;
+use redis::{AsyncCommands, Client};
+use serde::{Deserialize, Serialize};
+use serenity::model::channel::ReactionType;
+use tracing::warn;
+
+/// Reacting with this to one of chloe's replies asks her to answer again
+pub const REGENERATE_EMOJI: &str = "🔁";
+
+/// Answering takes minutes at most, the request only waits that long for its reply
+const PENDING_TTL_SECS: u64 = 10 * 60;
+
+/// How long a reply can be regenerated after it was sent
+const REGENERATION_TTL_SECS: i64 = 24 * 60 * 60;
+
+/// How long after chloe answered an edit to the message still gets her reply updated
+const EDIT_FOLLOW_SECS: u64 = 5 * 60;
+
+/// What chloe was asked, kept so a reply can be answered again from the same context
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RegenerationRequest {
+    pub guild_id: Option<u64>,
+    pub channel_id: u64,
+    /// The message chloe answered
+    pub message_id: u64,
+    pub author_id: u64,
+    pub context: ConversationContext,
+}
+
+impl RegenerationRequest {
+    /// The asker can always regenerate, anyone else has to be an admin of the guild
+    pub fn allows(&self, user_id: u64, is_admin: bool) -> bool {
+        user_id == self.author_id || is_admin
+    }
+}
+
+pub fn is_regenerate_reaction(emoji: &ReactionType) -> bool {
+    matches!(emoji, ReactionType::Unicode(name) if name == REGENERATE_EMOJI)
+}
+
+/// Contexts of chloe's replies in redis, keyed by the reply's message id. The context is
+/// stored under the asked message before answering, and moves to the reply once chloe's
+/// message referencing it comes through the gateway. Which reply answered a message is
+/// kept for a few minutes too, for edits to it.
+pub struct RegenerationStore {
+    redis_client: Client,
+}
+
+impl RegenerationStore {
+    pub fn new(redis_client: Client) -> Self {
+        Self { redis_client }
+    }
+
+    /// Keep the context chloe is about to answer from, until her reply claims it. Image
+    /// data is left out, it can be megabytes a reply and is fetched again to regenerate.
+    pub async fn remember(&self, mut request: RegenerationRequest) {
+        drop_images(&mut request.context);
+        let Ok(payload) = serde_json::to_string(&request) else {
+            return;
+        };
+        let stored: Result<(), String> = async {
+            let mut conn = self
+                .redis_client
+                .get_multiplexed_async_connection()
+                .await
+                .map_err(|e| e.to_string())?;
+            conn.set_ex(pending_key(request.message_id), payload, PENDING_TTL_SECS)
+                .await
+                .map_err(|e| e.to_string())
+        }
+        .await;
+        if let Err(e) = stored {
+            warn!(
+                event = "regeneration_store_failed",
+                message_id = request.message_id,
+                error = %e,
+                "Couldn't keep the context for regenerating the reply"
+            );
+        }
+    }
+
+    /// Hand the asked message's context to chloe's reply. Only her first message replying
+    /// to it claims it, further pages find nothing left.
+    pub async fn claim(&self, message_id: u64, reply_id: u64) {
+        let Ok(mut conn) = self.redis_client.get_multiplexed_async_connection().await else {
+            return;
+        };
+        let claimed: Result<bool, _> = redis::cmd("RENAMENX")
+            .arg(pending_key(message_id))
+            .arg(reply_key(reply_id))
+            .query_async(&mut conn)
+            .await;
+        if let Ok(true) = claimed {
+            let _: Result<(), _> = redis::pipe()
+                .expire(reply_key(reply_id), REGENERATION_TTL_SECS)
+                .set_ex(answered_key(message_id), reply_id, EDIT_FOLLOW_SECS)
+                .query_async(&mut conn)
+                .await;
+        }
+    }
+
+    /// chloe's reply to the message, while it's recent enough for an edit to update it
+    pub async fn recent_reply(&self, message_id: u64) -> Option<u64> {
+        let mut conn = self
+            .redis_client
+            .get_multiplexed_async_connection()
+            .await
+            .ok()?;
+        conn.get(answered_key(message_id)).await.ok()?
+    }
+
+    /// What the reply was answered from, None once it expired or for replies sent before
+    pub async fn find(&self, reply_id: u64) -> Option<RegenerationRequest> {
+        let mut conn = self
+            .redis_client
+            .get_multiplexed_async_connection()
+            .await
+            .ok()?;
+        let payload: Option<String> = conn.get(reply_key(reply_id)).await.ok()?;
+        serde_json::from_str(&payload?).ok()
+    }
+}
+
+fn drop_images(context: &mut ConversationContext) {
+    context.current_images.clear();
+    let linked = context.linked_messages.iter_mut().flat_map(|linked| {
+        std::iter::once(&mut linked.message)
+            .chain(linked.before.iter_mut())
+            .chain(linked.after.iter_mut())
+    });
+    for message in context
+        .recent_messages
+        .iter_mut()
+        .chain(context.referenced_message.iter_mut())
+        .chain(linked)
+    {
+        message.images.clear();
+    }
+}
+
+fn pending_key(message_id: u64) -> String {
+    format!("chloe:regen_pending:{}", message_id)
+}
+
+fn reply_key(reply_id: u64) -> String {
+    format!("chloe:regen:{}", reply_id)
+}
+
+fn answered_key(message_id: u64) -> String {
+    format!("chloe:regen_answered:{}", message_id)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_regenerate_reaction() {
+        assert!(is_regenerate_reaction(&ReactionType::Unicode(
+            "🔁".to_string()
+        )));
+        assert!(!is_regenerate_reaction(&ReactionType::Unicode(
+            "⭐".to_string()
+        )));
+    }
+}
@@ -0,0 +1,352 @@
+use chrono::{DateTime, Duration as ChronoDuration, Utc};
+use serenity::builder::{CreateAllowedMentions, CreateMessage};
+use serenity::http::Http;
+use serenity::model::id::{ChannelId, UserId};
+use sqlx::{PgPool, Row};
+use std::sync::Arc;
+use tokio::time::{Duration, sleep};
+use tracing::{error, info, warn};
+
+/// How often the worker checks for due reminders
+const POLL_INTERVAL: Duration = Duration::from_secs(15);
+
+/// Max reminders sent per tick
+const CLAIM_BATCH_SIZE: i64 = 20;
+
+pub const MAX_PENDING_PER_USER: i64 = 25;
+
+pub const MAX_MESSAGE_LENGTH: usize = 1000;
+
+/// Furthest ahead a reminder can be set
+pub const MAX_DELAY_DAYS: i64 = 365;
+
+/// Characters of a reminder's id shown to users and enough to cancel it
+pub const SHORT_ID_LENGTH: usize = 8;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Reminder {
+    pub id: String,
+    pub guild_id: Option<u64>,
+    pub channel_id: u64,
+    pub user_id: u64,
+    pub message: String,
+    pub remind_at: DateTime<Utc>,
+}
+
+impl Reminder {
+    pub fn short_id(&self) -> &str {
+        &self.id[..self.id.len().min(SHORT_ID_LENGTH)]
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum ReminderError {
+    #[error("already {MAX_PENDING_PER_USER} reminders pending, cancel one first")]
+    TooManyPending,
+    #[error("database error: {0}")]
+    Database(#[from] sqlx::Error),
+}
+
+/// Reminders members asked for in `chloe_reminders`, sent in the channel they were set in
+/// by a background worker once due
+pub struct ReminderService {
+    db_pool: PgPool,
+}
+
+impl ReminderService {
+    pub fn new(db_pool: PgPool) -> Self {
+        Self { db_pool }
+    }
+
+    pub async fn create(
+        &self,
+        guild_id: Option<u64>,
+        channel_id: u64,
+        user_id: u64,
+        message: &str,
+        remind_at: DateTime<Utc>,
+    ) -> Result<Reminder, ReminderError> {
+        let pending: i64 = sqlx::query_scalar(
+            "SELECT COUNT(*) FROM chloe_reminders WHERE user_id = $1 AND status = 'pending'",
+        )
+        .bind(user_id as i64)
+        .fetch_one(&self.db_pool)
+        .await?;
+        if pending >= MAX_PENDING_PER_USER {
+            return Err(ReminderError::TooManyPending);
+        }
+
+        let id: String = sqlx::query_scalar(
+            "INSERT INTO chloe_reminders (guild_id, channel_id, user_id, message, remind_at)
+             VALUES ($1, $2, $3, $4, $5) RETURNING id",
+        )
+        .bind(guild_id.map(|id| id as i64))
+        .bind(channel_id as i64)
+        .bind(user_id as i64)
+        .bind(message)
+        .bind(remind_at)
+        .fetch_one(&self.db_pool)
+        .await?;
+
+        info!(
+            event = "reminder_created",
+            reminder_id = %id,
+            user_id = user_id,
+            remind_at = %remind_at,
+            "Created reminder"
+        );
+        Ok(Reminder {
+            id,
+            guild_id,
+            channel_id,
+            user_id,
+            message: message.to_string(),
+            remind_at,
+        })
+    }
+
+    /// The user's pending reminders, soonest first
+    pub async fn pending_for_user(&self, user_id: u64) -> Result<Vec<Reminder>, sqlx::Error> {
+        let rows = sqlx::query(
+            "SELECT id, guild_id, channel_id, user_id, message, remind_at FROM chloe_reminders
+             WHERE user_id = $1 AND status = 'pending'
+             ORDER BY remind_at",
+        )
+        .bind(user_id as i64)
+        .fetch_all(&self.db_pool)
+        .await?;
+
+        Ok(rows.iter().map(reminder_from_row).collect())
+    }
+
+    /// Cancel one of the user's pending reminders by its id or short id, None when no
+    /// single reminder matches
+    pub async fn cancel(&self, user_id: u64, id: &str) -> Result<Option<Reminder>, sqlx::Error> {
+        let id = id.trim().to_lowercase();
+        if id.len() < 4 || !id.chars().all(|c| c.is_ascii_hexdigit() || c == '-') {
+            return Ok(None);
+        }
+        let matching: Vec<Reminder> = self
+            .pending_for_user(user_id)
+            .await?
+            .into_iter()
+            .filter(|reminder| reminder.id.starts_with(&id))
+            .collect();
+        let [reminder] = matching.as_slice() else {
+            return Ok(None);
+        };
+
+        let result = sqlx::query(
+            "UPDATE chloe_reminders SET status = 'cancelled' WHERE id = $1 AND status = 'pending'",
+        )
+        .bind(&reminder.id)
+        .execute(&self.db_pool)
+        .await?;
+        if result.rows_affected() == 0 {
+            return Ok(None); // sent in the meantime
+        }
+        info!(
+            event = "reminder_cancelled",
+            reminder_id = %reminder.id,
+            user_id = user_id,
+            "Cancelled reminder"
+        );
+        Ok(Some(reminder.clone()))
+    }
+
+    /// Send due reminders until the process exits
+    pub async fn start(&self, http: Arc<Http>) {
+        info!(
+            event = "reminder_worker_started",
+            "Starting reminder worker"
+        );
+        loop {
+            if let Err(e) = self.send_due(&http).await {
+                error!(
+                    event = "reminder_tick_failed",
+                    error = ?e,
+                    "Failed to send due reminders"
+                );
+            }
+            sleep(POLL_INTERVAL).await;
+        }
+    }
+
+    async fn send_due(&self, http: &Http) -> Result<(), sqlx::Error> {
+        let rows = sqlx::query(
+            r#"
+            UPDATE chloe_reminders SET status = 'sent', sent_at = CURRENT_TIMESTAMP
+            WHERE id IN (
+                SELECT id FROM chloe_reminders
+                WHERE status = 'pending' AND remind_at <= NOW()
+                ORDER BY remind_at
+                LIMIT $1
+                FOR UPDATE SKIP LOCKED
+            )
+            RETURNING id, guild_id, channel_id, user_id, message, remind_at
+            "#,
+        )
+        .bind(CLAIM_BATCH_SIZE)
+        .fetch_all(&self.db_pool)
+        .await?;
+
+        for reminder in rows.iter().map(reminder_from_row) {
+            if let Err(e) = deliver(http, &reminder).await {
+                warn!(
+                    event = "reminder_delivery_failed",
+                    reminder_id = %reminder.id,
+                    user_id = reminder.user_id,
+                    error = ?e,
+                    "Failed to deliver reminder"
+                );
+                sqlx::query("UPDATE chloe_reminders SET status = 'failed' WHERE id = $1")
+                    .bind(&reminder.id)
+                    .execute(&self.db_pool)
+                    .await?;
+                continue;
+            }
+            info!(
+                event = "reminder_sent",
+                reminder_id = %reminder.id,
+                user_id = reminder.user_id,
+                "Sent reminder"
+            );
+        }
+        Ok(())
+    }
+}
+
+/// Ping the member in the channel the reminder was set in, or DM them when chloe can't
+/// post there anymore
+async fn deliver(http: &Http, reminder: &Reminder) -> Result<(), serenity::Error> {
+    let content = format!("⏰ <@{}> reminder: {}", reminder.user_id, reminder.message);
+    let message = |content: &str| {
+        CreateMessage::new().content(content).allowed_mentions(
+            CreateAllowedMentions::new().users(vec![UserId::new(reminder.user_id)]),
+        )
+    };
+
+    let sent = ChannelId::new(reminder.channel_id)
+        .send_message(http, message(&content))
+        .await;
+    if sent.is_ok() {
+        return Ok(());
+    }
+    let dm = UserId::new(reminder.user_id)
+        .create_dm_channel(http)
+        .await?;
+    dm.send_message(http, message(&content)).await?;
+    Ok(())
+}
+
+fn reminder_from_row(row: &sqlx::postgres::PgRow) -> Reminder {
+    Reminder {
+        id: row.get("id"),
+        guild_id: row.get::<Option<i64>, _>("guild_id").map(|id| id as u64),
+        channel_id: row.get::<i64, _>("channel_id") as u64,
+        user_id: row.get::<i64, _>("user_id") as u64,
+        message: row.get("message"),
+        remind_at: row.get("remind_at"),
+    }
+}
+
+/// Parse a delay like `2h`, `30 min`, `1d 12h` or `90s`
+pub fn parse_delay(input: &str) -> Option<ChronoDuration> {
+    let input = input.trim().to_lowercase();
+    let input = input.strip_prefix("in ").unwrap_or(&input);
+    let mut total = ChronoDuration::zero();
+    let mut rest = input.trim();
+    if rest.is_empty() {
+        return None;
+    }
+
+    while !rest.is_empty() {
+        let digits = rest
+            .find(|c: char| !c.is_ascii_digit())
+            .unwrap_or(rest.len());
+        let amount: i64 = rest[..digits].parse().ok()?;
+        rest = rest[digits..].trim_start();
+        let unit_len = rest
+            .find(|c: char| !c.is_alphabetic())
+            .unwrap_or(rest.len());
+        let unit = &rest[..unit_len];
+        let step = match unit {
+            "s" | "sec" | "secs" | "second" | "seconds" => ChronoDuration::try_seconds(amount)?,
+            "m" | "min" | "mins" | "minute" | "minutes" => ChronoDuration::try_minutes(amount)?,
+            "h" | "hr" | "hrs" | "hour" | "hours" => ChronoDuration::try_hours(amount)?,
+            "d" | "day" | "days" => ChronoDuration::try_days(amount)?,
+            "w" | "week" | "weeks" => ChronoDuration::try_weeks(amount)?,
+            _ => return None,
+        };
+        total = total.checked_add(&step)?;
+        rest = rest[unit_len..].trim_start_matches([' ', ',']);
+        rest = rest.strip_prefix("and ").unwrap_or(rest);
+    }
+    Some(total)
+}
+
+/// When a reminder set by `when` (a delay or a UTC time) goes off, or why it can't be set
+pub fn resolve_remind_at(when: &str, now: DateTime<Utc>) -> Result<DateTime<Utc>, String> {
+    let remind_at = match parse_delay(when) {
+        Some(delay) => now + delay,
+        None => crate::services::scheduler::parse_schedule_time(when).ok_or_else(|| {
+            format!(
+                "couldn't understand '{}', use a delay like 2h or 1d 30m, or a UTC time like 2025-10-25 18:30",
+                when.trim()
+            )
+        })?,
+    };
+    if remind_at <= now {
+        return Err("that time already passed".to_string());
+    }
+    if remind_at > now + ChronoDuration::days(MAX_DELAY_DAYS) {
+        return Err(format!(
+            "reminders can be set at most {} days ahead",
+            MAX_DELAY_DAYS
+        ));
+    }
+    Ok(remind_at)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_delay() {
+        assert_eq!(parse_delay("2h"), Some(ChronoDuration::hours(2)));
+        assert_eq!(
+            parse_delay("in 30 minutes"),
+            Some(ChronoDuration::minutes(30))
+        );
+        assert_eq!(
+            parse_delay("1d 12h"),
+            Some(ChronoDuration::days(1) + ChronoDuration::hours(12))
+        );
+        assert_eq!(
+            parse_delay("1 hour and 15 min"),
+            Some(ChronoDuration::minutes(75))
+        );
+        assert_eq!(parse_delay("2025-10-25 18:30"), None);
+        assert_eq!(parse_delay("soon"), None);
+        assert_eq!(parse_delay(""), None);
+    }
+
+    #[test]
+    fn test_resolve_remind_at() {
+        let now = "2025-10-25T12:00:00Z".parse::<DateTime<Utc>>().unwrap();
+        assert_eq!(
+            resolve_remind_at("2h", now),
+            Ok(now + ChronoDuration::hours(2))
+        );
+        assert_eq!(
+            resolve_remind_at("2025-10-25 18:30", now),
+            "2025-10-25T18:30:00Z"
+                .parse::<DateTime<Utc>>()
+                .map_err(|e| e.to_string())
+        );
+        assert!(resolve_remind_at("2025-10-24 18:30", now).is_err());
+        assert!(resolve_remind_at("400d", now).is_err());
+        assert!(resolve_remind_at("whenever", now).is_err());
+    }
+}
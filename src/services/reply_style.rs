@@ -0,0 +1,118 @@
+use serde_json::Value;
+
+/// Guild setting tuning how chloe writes without a full persona
+pub const REPLY_STYLE_SETTING: &str = "replyStyle";
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Verbosity {
+    Terse,
+    #[default]
+    Normal,
+    Detailed,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum EmojiLevel {
+    None,
+    Few,
+    #[default]
+    Normal,
+    Lots,
+}
+
+/// From the guild setting `replyStyle`, e.g. `{"verbosity": "terse", "emoji": "none",
+/// "embeds": true}`. Values left out or not understood keep chloe's usual style.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct ReplyStyle {
+    pub verbosity: Verbosity,
+    pub emoji: EmojiLevel,
+    /// Answer with embeds by default instead of plain messages
+    pub embeds: bool,
+}
+
+impl ReplyStyle {
+    pub fn from_setting(setting: Option<&Value>) -> Self {
+        let Some(setting) = setting else {
+            return Self::default();
+        };
+        let text = |key: &str| {
+            setting
+                .get(key)
+                .and_then(Value::as_str)
+                .map(|value| value.trim().to_lowercase())
+        };
+        let verbosity = match text("verbosity").as_deref() {
+            Some("terse") => Verbosity::Terse,
+            Some("detailed") => Verbosity::Detailed,
+            _ => Verbosity::Normal,
+        };
+        let emoji = match text("emoji").as_deref() {
+            Some("none") => EmojiLevel::None,
+            Some("few") => EmojiLevel::Few,
+            Some("lots") => EmojiLevel::Lots,
+            _ => EmojiLevel::Normal,
+        };
+        Self {
+            verbosity,
+            emoji,
+            embeds: setting
+                .get("embeds")
+                .and_then(Value::as_bool)
+                .unwrap_or(false),
+        }
+    }
+
+    /// Instructions for the system prompt, None when the guild keeps the usual style
+    pub fn instructions(&self) -> Option<String> {
+        let mut lines = Vec::new();
+        match self.verbosity {
+            Verbosity::Terse => lines.push(
+                "- Keep replies short: a sentence or two, no preamble or recap. Only go longer when asked for detail.",
+            ),
+            Verbosity::Detailed => lines.push(
+                "- Give thorough answers: explain the why and add examples or steps where they help, still within the length limit.",
+            ),
+            Verbosity::Normal => {}
+        }
+        match self.emoji {
+            EmojiLevel::None => lines.push("- Don't use emoji, not even custom server emoji."),
+            EmojiLevel::Few => lines.push("- Use emoji sparingly, at most one per message."),
+            EmojiLevel::Lots => lines.push("- Use plenty of emoji, the server loves them."),
+            EmojiLevel::Normal => {}
+        }
+        if self.embeds {
+            lines.push(
+                "- Answer with discord_send_embed whenever the reply has any structure (lists, steps, comparisons, facts), keep discord_send_message for short chat.",
+            );
+        }
+        if lines.is_empty() {
+            return None;
+        }
+        Some(lines.join("\n"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_reply_style() {
+        assert_eq!(ReplyStyle::from_setting(None), ReplyStyle::default());
+        assert_eq!(ReplyStyle::default().instructions(), None);
+
+        let setting = json!({"verbosity": "Terse", "emoji": "none", "embeds": true});
+        let style = ReplyStyle::from_setting(Some(&setting));
+        assert_eq!(style.verbosity, Verbosity::Terse);
+        assert_eq!(style.emoji, EmojiLevel::None);
+        assert!(style.embeds);
+        assert_eq!(style.instructions().unwrap().lines().count(), 3);
+
+        let setting = json!({"verbosity": "chatty", "emoji": 3});
+        assert_eq!(
+            ReplyStyle::from_setting(Some(&setting)),
+            ReplyStyle::default()
+        );
+    }
+}
@@ -0,0 +1,82 @@
+use serde_json::Value;
+
+/// Guild setting choosing who sees command replies
+pub const REPLY_VISIBILITY_SETTING: &str = "replyVisibility";
+
+/// Which policy a command reply follows
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ReplyKind {
+    /// Results of commands that change or show server config, ephemeral by default
+    Admin,
+    /// /status, /ping, /rank, /leaderboard and /rewind, public by default
+    Status,
+    /// Replies only the caller should see, like their reminders or a failed command.
+    /// Always ephemeral, guilds can't change it.
+    Private,
+}
+
+impl ReplyKind {
+    fn ephemeral_by_default(self) -> bool {
+        !matches!(self, Self::Status)
+    }
+}
+
+/// Whether replies of each kind are ephemeral, from the guild setting `replyVisibility`,
+/// e.g. `{"admin": "public", "status": "ephemeral"}`. Kinds left out keep their default.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct ReplyVisibility {
+    pub admin: Option<bool>,
+    pub status: Option<bool>,
+}
+
+impl ReplyVisibility {
+    pub fn from_setting(setting: Option<&Value>) -> Self {
+        let Some(setting) = setting else {
+            return Self::default();
+        };
+        let ephemeral = |key: &str| match setting.get(key)?.as_str()?.trim() {
+            "ephemeral" => Some(true),
+            "public" => Some(false),
+            _ => None,
+        };
+        Self {
+            admin: ephemeral("admin"),
+            status: ephemeral("status"),
+        }
+    }
+
+    pub fn is_ephemeral(&self, kind: ReplyKind) -> bool {
+        let configured = match kind {
+            ReplyKind::Admin => self.admin,
+            ReplyKind::Status => self.status,
+            ReplyKind::Private => None,
+        };
+        configured.unwrap_or_else(|| kind.ephemeral_by_default())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_reply_visibility() {
+        let defaults = ReplyVisibility::from_setting(None);
+        assert!(defaults.is_ephemeral(ReplyKind::Admin));
+        assert!(!defaults.is_ephemeral(ReplyKind::Status));
+        assert!(defaults.is_ephemeral(ReplyKind::Private));
+
+        let setting = json!({"admin": "public", "status": "ephemeral"});
+        let visibility = ReplyVisibility::from_setting(Some(&setting));
+        assert!(!visibility.is_ephemeral(ReplyKind::Admin));
+        assert!(visibility.is_ephemeral(ReplyKind::Status));
+        assert!(visibility.is_ephemeral(ReplyKind::Private));
+
+        let setting = json!({"admin": "loud"});
+        assert_eq!(
+            ReplyVisibility::from_setting(Some(&setting)),
+            ReplyVisibility::default()
+        );
+    }
+}
@@ -0,0 +1,156 @@
+use std::collections::{HashMap, VecDeque};
+use std::sync::Mutex;
+use std::time::Duration;
+
+/// Requests per provider the health numbers are taken over
+const PROVIDER_HEALTH_SAMPLES: usize = 100;
+
+/// How often a model answered with no text and no tool calls, and how many of those
+/// the retry recovered
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct EmptyResponseCount {
+    pub empty: u64,
+    pub recovered: u64,
+}
+
+/// Per model empty response counts since startup
+#[derive(Default)]
+pub struct EmptyResponseStats {
+    counts: Mutex<HashMap<String, EmptyResponseCount>>,
+}
+
+impl EmptyResponseStats {
+    pub fn record(&self, model: &str, recovered: bool) -> EmptyResponseCount {
+        let mut counts = self.counts.lock().unwrap_or_else(|e| e.into_inner());
+        let count = counts.entry(model.to_string()).or_default();
+        count.empty += 1;
+        if recovered {
+            count.recovered += 1;
+        }
+        *count
+    }
+
+    /// Every model that has sent an empty response, most empty responses first
+    pub fn snapshot(&self) -> Vec<(String, EmptyResponseCount)> {
+        let counts = self.counts.lock().unwrap_or_else(|e| e.into_inner());
+        let mut snapshot: Vec<_> = counts
+            .iter()
+            .map(|(model, count)| (model.clone(), *count))
+            .collect();
+        snapshot.sort_by(|a, b| b.1.empty.cmp(&a.1.empty).then_with(|| a.0.cmp(&b.0)));
+        snapshot
+    }
+}
+
+/// How a provider did over its most recent requests
+#[derive(Debug, Clone, PartialEq)]
+pub struct ProviderHealth {
+    pub provider: String,
+    pub requests: usize,
+    pub failures: usize,
+    pub median_latency: Duration,
+    pub p95_latency: Duration,
+}
+
+impl ProviderHealth {
+    pub fn error_rate(&self) -> f64 {
+        if self.requests == 0 {
+            return 0.0;
+        }
+        self.failures as f64 / self.requests as f64
+    }
+}
+
+/// The last `PROVIDER_HEALTH_SAMPLES` requests of each provider, failed or not
+#[derive(Default)]
+pub struct ProviderHealthStats {
+    samples: Mutex<HashMap<String, VecDeque<(bool, Duration)>>>,
+}
+
+impl ProviderHealthStats {
+    pub fn record(&self, provider: &str, failed: bool, latency: Duration) {
+        let mut samples = self.samples.lock().unwrap_or_else(|e| e.into_inner());
+        let recent = samples.entry(provider.to_string()).or_default();
+        if recent.len() == PROVIDER_HEALTH_SAMPLES {
+            recent.pop_front();
+        }
+        recent.push_back((failed, latency));
+    }
+
+    /// Every provider that has been sent a request, by name
+    pub fn snapshot(&self) -> Vec<ProviderHealth> {
+        let samples = self.samples.lock().unwrap_or_else(|e| e.into_inner());
+        let mut snapshot: Vec<ProviderHealth> = samples
+            .iter()
+            .map(|(provider, recent)| {
+                let mut latencies: Vec<Duration> =
+                    recent.iter().map(|(_, latency)| *latency).collect();
+                latencies.sort();
+                let percentile = |share: f64| {
+                    latencies
+                        .len()
+                        .checked_sub(1)
+                        .map(|last| latencies[(last as f64 * share).round() as usize])
+                        .unwrap_or_default()
+                };
+                ProviderHealth {
+                    provider: provider.clone(),
+                    requests: recent.len(),
+                    failures: recent.iter().filter(|(failed, _)| *failed).count(),
+                    median_latency: percentile(0.5),
+                    p95_latency: percentile(0.95),
+                }
+            })
+            .collect();
+        snapshot.sort_by(|a, b| a.provider.cmp(&b.provider));
+        snapshot
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_counts_per_model() {
+        let stats = EmptyResponseStats::default();
+        stats.record("gemini-2.5-flash", true);
+        stats.record("ollama:llama3.1", false);
+        let count = stats.record("ollama:llama3.1", true);
+
+        assert_eq!(
+            count,
+            EmptyResponseCount {
+                empty: 2,
+                recovered: 1
+            }
+        );
+        let models: Vec<_> = stats
+            .snapshot()
+            .into_iter()
+            .map(|(model, _)| model)
+            .collect();
+        assert_eq!(models, ["ollama:llama3.1", "gemini-2.5-flash"]);
+    }
+
+    #[test]
+    fn test_provider_health_keeps_recent_requests() {
+        let stats = ProviderHealthStats::default();
+        stats.record("ollama", true, Duration::from_millis(900));
+        for ms in 1..=PROVIDER_HEALTH_SAMPLES as u64 {
+            stats.record("gemini", ms % 10 == 0, Duration::from_millis(ms * 10));
+        }
+        // pushes out the first sample
+        stats.record("gemini", false, Duration::from_millis(5));
+
+        let snapshot = stats.snapshot();
+        assert_eq!(snapshot.len(), 2);
+        let gemini = &snapshot[0];
+        assert_eq!(gemini.provider, "gemini");
+        assert_eq!(gemini.requests, PROVIDER_HEALTH_SAMPLES);
+        assert_eq!(gemini.failures, 10);
+        assert_eq!(gemini.error_rate(), 0.1);
+        assert_eq!(gemini.p95_latency, Duration::from_millis(950));
+        assert_eq!(snapshot[1].error_rate(), 1.0);
+    }
+}
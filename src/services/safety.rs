@@ -0,0 +1,142 @@
+use crate::utils::regex_patterns::CRISIS_REGEX;
+use serde_json::Value;
+use serenity::builder::{CreateAllowedMentions, CreateMessage};
+use serenity::http::Http;
+use serenity::model::channel::Message;
+use serenity::model::id::ChannelId;
+use tracing::{error, info};
+
+/// Guild setting configuring crisis responses
+pub const SAFETY_SETTING: &str = "safety";
+
+/// Server-specific resources are kept short so the vetted ones stay visible
+const MAX_EXTRA_RESOURCES_LENGTH: usize = 500;
+
+/// Vetted resources sent instead of a model reply, in plain words rather than chloe's voice
+const CRISIS_RESPONSE: &str = "It sounds like you're going through something really painful right now, and you don't have to handle it alone. Please reach out to people who can help:
+• **US & Canada**: call or text **988** (Suicide & Crisis Lifeline)
+• **UK & Ireland**: call **116 123** (Samaritans)
+• **Anywhere else**: find a free, confidential helpline at https://findahelpline.com
+If you're in immediate danger, please call your local emergency number.";
+
+/// From the guild setting `safety`, e.g. `{"enabled": true, "logChannel": "123",
+/// "resources": "our mods are here too, DM any of them"}`. Crisis responses are on
+/// unless a guild turns them off.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SafetySettings {
+    pub enabled: bool,
+    /// Private channel where moderators are told a crisis response went out
+    pub log_channel_id: Option<u64>,
+    /// Added below the vetted resources, e.g. a local helpline or the server's support
+    pub extra_resources: Option<String>,
+}
+
+impl SafetySettings {
+    pub fn from_setting(setting: Option<&Value>) -> Self {
+        let field = |key: &str| setting.and_then(|setting| setting.get(key));
+        Self {
+            enabled: field("enabled").and_then(Value::as_bool).unwrap_or(true),
+            log_channel_id: field("logChannel").and_then(|value| match value {
+                Value::String(id) => id.trim().parse().ok(),
+                value => value.as_u64(),
+            }),
+            extra_resources: field("resources")
+                .and_then(Value::as_str)
+                .map(str::trim)
+                .filter(|resources| !resources.is_empty())
+                .map(|resources| resources.chars().take(MAX_EXTRA_RESOURCES_LENGTH).collect()),
+        }
+    }
+
+    pub fn response(&self) -> String {
+        match &self.extra_resources {
+            Some(extra) => format!("{}\n\n{}", CRISIS_RESPONSE, extra),
+            None => CRISIS_RESPONSE.to_string(),
+        }
+    }
+}
+
+/// Whether a message reads like someone talking about hurting themselves
+pub fn is_crisis_message(content: &str) -> bool {
+    CRISIS_REGEX.is_match(content)
+}
+
+/// Reply with the crisis resources and let moderators know privately. The message
+/// content stays out of logs, moderators get a link to it instead.
+pub async fn respond_to_crisis(http: &Http, msg: &Message, settings: &SafetySettings) {
+    let reply = CreateMessage::new()
+        .content(settings.response())
+        .reference_message(msg)
+        .allowed_mentions(CreateAllowedMentions::new());
+    if let Err(e) = msg.channel_id.send_message(http, reply).await {
+        error!(
+            event = "crisis_response_send_failed",
+            channel_id = %msg.channel_id,
+            error = ?e,
+            "Failed to send crisis resources"
+        );
+    }
+    info!(
+        event = "crisis_response_sent",
+        guild_id = ?msg.guild_id,
+        channel_id = %msg.channel_id,
+        "Sent crisis resources instead of a reply"
+    );
+
+    let Some(log_channel_id) = settings.log_channel_id else {
+        return;
+    };
+    let notice = CreateMessage::new()
+        .content(format!(
+            "🛟 sent crisis resources to <@{}> in <#{}>, you may want to check in on them: {}",
+            msg.author.id,
+            msg.channel_id,
+            msg.link()
+        ))
+        .allowed_mentions(CreateAllowedMentions::new());
+    if let Err(e) = ChannelId::new(log_channel_id)
+        .send_message(http, notice)
+        .await
+    {
+        error!(
+            event = "crisis_log_send_failed",
+            log_channel_id = log_channel_id,
+            error = ?e,
+            "Failed to notify moderators of a crisis response"
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_is_crisis_message() {
+        assert!(is_crisis_message("i honestly want to die"));
+        assert!(is_crisis_message("I've been thinking about suicide a lot"));
+        assert!(is_crisis_message("i don’t want to be alive anymore"));
+        assert!(is_crisis_message("been self-harming again"));
+
+        assert!(!is_crisis_message("this boss fight is killing me lol"));
+        assert!(!is_crisis_message("chloe what's the weather tomorrow"));
+    }
+
+    #[test]
+    fn test_from_setting() {
+        let defaults = SafetySettings::from_setting(None);
+        assert!(defaults.enabled);
+        assert_eq!(defaults.response(), CRISIS_RESPONSE);
+
+        let setting = json!({
+            "enabled": false,
+            "logChannel": "123456789",
+            "resources": "  DM any mod, we're here  "
+        });
+        let settings = SafetySettings::from_setting(Some(&setting));
+        assert!(!settings.enabled);
+        assert_eq!(settings.log_channel_id, Some(123456789));
+        assert!(settings.response().ends_with("\n\nDM any mod, we're here"));
+    }
+}
@@ -0,0 +1,294 @@
+use crate::utils::message_cache::CachedMessage;
+use chrono::{DateTime, Utc};
+use sqlx::types::Json;
+use sqlx::{PgPool, Row, postgres::PgRow};
+use tracing::info;
+
+/// Turns kept when a conversation is saved, as many as the message cache holds per channel
+pub const SAVED_TURNS_LIMIT: usize = 50;
+
+/// Saved conversations each member can keep
+pub const MAX_SAVED_CONVERSATIONS: i64 = 25;
+
+const MAX_NAME_CHARS: usize = 64;
+
+/// The name as it's stored, e.g. `Project X` becomes `project-x`
+pub fn normalize_name(name: &str) -> Result<String, &'static str> {
+    let name = name
+        .trim()
+        .to_lowercase()
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join("-");
+    if name.is_empty() {
+        return Err("the name can't be empty");
+    }
+    if name.chars().count() > MAX_NAME_CHARS {
+        return Err("keep the name under 64 characters");
+    }
+    if !name
+        .chars()
+        .all(|c| c.is_alphanumeric() || c == '-' || c == '_')
+    {
+        return Err("names can only have letters, numbers, dashes and underscores");
+    }
+    Ok(name)
+}
+
+/// A channel's conversation a member saved under a name, to pick it up again elsewhere
+#[derive(Debug, Clone)]
+pub struct SavedConversation {
+    pub name: String,
+    pub user_id: u64,
+    /// Where it was saved, memories are recalled from this guild after resuming
+    pub guild_id: Option<u64>,
+    pub source_channel_id: u64,
+    /// Newest first, like the channel's stored turns
+    pub turns: Vec<CachedMessage>,
+    /// Channel it was last resumed in
+    pub resumed_in: Option<u64>,
+    pub created_at: DateTime<Utc>,
+}
+
+impl SavedConversation {
+    /// The newest saved turn, memories of the source channel stop there
+    pub fn saved_through(&self) -> Option<u64> {
+        self.turns.first().map(|turn| turn.id)
+    }
+
+    /// Where the turns can be shown without reaching people who can't read the channel
+    /// they came from: DMs, that channel and its threads. `guild_id` is None in DMs.
+    pub fn can_resume_in(
+        &self,
+        guild_id: Option<u64>,
+        channel_id: u64,
+        thread_parent_id: Option<u64>,
+    ) -> bool {
+        if self.guild_id.is_none() || guild_id.is_none() {
+            return true;
+        }
+        channel_id == self.source_channel_id || thread_parent_id == Some(self.source_channel_id)
+    }
+}
+
+fn saved_conversation_from_row(row: &PgRow) -> SavedConversation {
+    SavedConversation {
+        name: row.get("name"),
+        user_id: row.get::<i64, _>("user_id") as u64,
+        guild_id: row.get::<Option<i64>, _>("guild_id").map(|id| id as u64),
+        source_channel_id: row.get::<i64, _>("source_channel_id") as u64,
+        turns: row.get::<Json<Vec<CachedMessage>>, _>("turns").0,
+        resumed_in: row.get::<Option<i64>, _>("resumed_in").map(|id| id as u64),
+        created_at: row.get("created_at"),
+    }
+}
+
+/// Conversations members saved with `/conversation save`, in `chloe_saved_conversations`.
+/// A channel follows on from at most one of them, its turns come before the channel's own
+/// in the context.
+pub struct SavedConversationService {
+    db_pool: PgPool,
+}
+
+impl SavedConversationService {
+    pub fn new(db_pool: PgPool) -> Self {
+        Self { db_pool }
+    }
+
+    /// Save the turns under the member's name, replacing what was saved under it before.
+    /// False when the member already has as many saved as they can keep.
+    pub async fn save(
+        &self,
+        user_id: u64,
+        name: &str,
+        guild_id: Option<u64>,
+        channel_id: u64,
+        turns: &[CachedMessage],
+    ) -> Result<bool, sqlx::Error> {
+        let saved: i64 = sqlx::query_scalar(
+            "SELECT COUNT(*) FROM chloe_saved_conversations WHERE user_id = $1 AND name <> $2",
+        )
+        .bind(user_id as i64)
+        .bind(name)
+        .fetch_one(&self.db_pool)
+        .await?;
+        if saved >= MAX_SAVED_CONVERSATIONS {
+            return Ok(false);
+        }
+
+        sqlx::query(
+            "INSERT INTO chloe_saved_conversations (user_id, name, guild_id, source_channel_id, turns)
+             VALUES ($1, $2, $3, $4, $5)
+             ON CONFLICT (user_id, name) DO UPDATE SET
+                 guild_id = EXCLUDED.guild_id,
+                 source_channel_id = EXCLUDED.source_channel_id,
+                 turns = EXCLUDED.turns,
+                 created_at = NOW()",
+        )
+        .bind(user_id as i64)
+        .bind(name)
+        .bind(guild_id.map(|id| id as i64))
+        .bind(channel_id as i64)
+        .bind(Json(turns))
+        .execute(&self.db_pool)
+        .await?;
+
+        info!(
+            event = "conversation_saved",
+            user_id = user_id,
+            name = name,
+            channel_id = channel_id,
+            turns = turns.len(),
+            "Conversation saved"
+        );
+        Ok(true)
+    }
+
+    pub async fn find(
+        &self,
+        user_id: u64,
+        name: &str,
+    ) -> Result<Option<SavedConversation>, sqlx::Error> {
+        let row = sqlx::query(
+            "SELECT name, user_id, guild_id, source_channel_id, turns, resumed_in, created_at
+             FROM chloe_saved_conversations
+             WHERE user_id = $1 AND name = $2",
+        )
+        .bind(user_id as i64)
+        .bind(name)
+        .fetch_optional(&self.db_pool)
+        .await?;
+        Ok(row.as_ref().map(saved_conversation_from_row))
+    }
+
+    /// Make the channel follow on from the saved conversation instead of whatever it
+    /// followed before
+    pub async fn resume(
+        &self,
+        user_id: u64,
+        name: &str,
+        channel_id: u64,
+    ) -> Result<(), sqlx::Error> {
+        let mut tx = self.db_pool.begin().await?;
+        sqlx::query("UPDATE chloe_saved_conversations SET resumed_in = NULL WHERE resumed_in = $1")
+            .bind(channel_id as i64)
+            .execute(&mut *tx)
+            .await?;
+        sqlx::query(
+            "UPDATE chloe_saved_conversations SET resumed_in = $3 WHERE user_id = $1 AND name = $2",
+        )
+        .bind(user_id as i64)
+        .bind(name)
+        .bind(channel_id as i64)
+        .execute(&mut *tx)
+        .await?;
+        tx.commit().await?;
+
+        info!(
+            event = "conversation_resumed",
+            user_id = user_id,
+            name = name,
+            channel_id = channel_id,
+            "Saved conversation resumed"
+        );
+        Ok(())
+    }
+
+    /// The saved conversation the channel follows on from, if any
+    pub async fn resumed_in(
+        &self,
+        channel_id: u64,
+    ) -> Result<Option<SavedConversation>, sqlx::Error> {
+        let row = sqlx::query(
+            "SELECT name, user_id, guild_id, source_channel_id, turns, resumed_in, created_at
+             FROM chloe_saved_conversations
+             WHERE resumed_in = $1",
+        )
+        .bind(channel_id as i64)
+        .fetch_optional(&self.db_pool)
+        .await?;
+        Ok(row.as_ref().map(saved_conversation_from_row))
+    }
+
+    /// The member's saved conversations, newest first
+    pub async fn list(&self, user_id: u64) -> Result<Vec<SavedConversation>, sqlx::Error> {
+        let rows = sqlx::query(
+            "SELECT name, user_id, guild_id, source_channel_id, turns, resumed_in, created_at
+             FROM chloe_saved_conversations
+             WHERE user_id = $1
+             ORDER BY created_at DESC",
+        )
+        .bind(user_id as i64)
+        .fetch_all(&self.db_pool)
+        .await?;
+        Ok(rows.iter().map(saved_conversation_from_row).collect())
+    }
+
+    /// Drop rewound turns from every saved conversation holding them
+    pub async fn forget_turns(&self, message_ids: &[u64]) -> Result<(), sqlx::Error> {
+        let message_ids: Vec<i64> = message_ids.iter().map(|id| *id as i64).collect();
+        sqlx::query(
+            "UPDATE chloe_saved_conversations SET turns = COALESCE(
+                 (SELECT jsonb_agg(turn ORDER BY position)
+                  FROM jsonb_array_elements(turns) WITH ORDINALITY AS saved(turn, position)
+                  WHERE NOT (turn->>'id')::bigint = ANY($1)),
+                 '[]'::jsonb)
+             WHERE EXISTS (
+                 SELECT 1 FROM jsonb_array_elements(turns) AS saved(turn)
+                 WHERE (turn->>'id')::bigint = ANY($1))",
+        )
+        .bind(&message_ids)
+        .execute(&self.db_pool)
+        .await?;
+        Ok(())
+    }
+
+    /// False when the member had nothing saved under the name
+    pub async fn delete(&self, user_id: u64, name: &str) -> Result<bool, sqlx::Error> {
+        let result =
+            sqlx::query("DELETE FROM chloe_saved_conversations WHERE user_id = $1 AND name = $2")
+                .bind(user_id as i64)
+                .bind(name)
+                .execute(&self.db_pool)
+                .await?;
+        Ok(result.rows_affected() > 0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_normalize_name() {
+        assert_eq!(normalize_name("  Project X "), Ok("project-x".to_string()));
+        assert_eq!(normalize_name("bug_hunt-2"), Ok("bug_hunt-2".to_string()));
+        assert!(normalize_name("   ").is_err());
+        assert!(normalize_name("what?").is_err());
+        assert!(normalize_name(&"a".repeat(65)).is_err());
+    }
+
+    #[test]
+    fn test_can_resume_in() {
+        let saved = SavedConversation {
+            name: "project-x".to_string(),
+            user_id: 1,
+            guild_id: Some(10),
+            source_channel_id: 100,
+            turns: Vec::new(),
+            resumed_in: None,
+            created_at: Utc::now(),
+        };
+        assert!(saved.can_resume_in(None, 5, None));
+        assert!(saved.can_resume_in(Some(10), 100, None));
+        assert!(saved.can_resume_in(Some(10), 101, Some(100)));
+        assert!(!saved.can_resume_in(Some(10), 102, None));
+        assert!(!saved.can_resume_in(Some(11), 102, None));
+
+        let from_dms = SavedConversation {
+            guild_id: None,
+            ..saved
+        };
+        assert!(from_dms.can_resume_in(Some(10), 102, None));
+    }
+}
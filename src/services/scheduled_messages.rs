@@ -0,0 +1,312 @@
+use crate::utils::cron::{CronError, CronSchedule};
+use chrono::{DateTime, Utc};
+use chrono_tz::Tz;
+use serenity::builder::{CreateAllowedMentions, CreateMessage};
+use serenity::http::Http;
+use serenity::model::id::ChannelId;
+use sqlx::{PgPool, Row};
+use std::sync::Arc;
+use tokio::time::{Duration, sleep};
+use tracing::{error, info, warn};
+
+/// How often the worker checks for due messages
+const POLL_INTERVAL: Duration = Duration::from_secs(30);
+
+/// Max messages posted per tick
+const CLAIM_BATCH_SIZE: i64 = 20;
+
+pub const MAX_SCHEDULES_PER_GUILD: i64 = 25;
+
+pub const MAX_CONTENT_LENGTH: usize = 2000;
+
+/// Characters of a schedule's id shown to admins and enough to remove it
+pub const SHORT_ID_LENGTH: usize = 8;
+
+/// A message an admin set up to be posted on a cron schedule, like a weekly meeting
+/// reminder
+#[derive(Debug, Clone, PartialEq)]
+pub struct ScheduledMessage {
+    pub id: String,
+    pub channel_id: u64,
+    pub cron: String,
+    pub timezone: Tz,
+    pub content: String,
+    pub next_run_at: DateTime<Utc>,
+    /// Turned off when chloe can no longer post in the channel
+    pub enabled: bool,
+}
+
+impl ScheduledMessage {
+    pub fn short_id(&self) -> &str {
+        &self.id[..self.id.len().min(SHORT_ID_LENGTH)]
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum ScheduleError {
+    #[error("{0}")]
+    InvalidCron(#[from] CronError),
+    #[error("'{0}' isn't a timezone I know, use one like Europe/Berlin")]
+    UnknownTimezone(String),
+    #[error("that schedule never runs")]
+    NeverRuns,
+    #[error("already {MAX_SCHEDULES_PER_GUILD} scheduled messages, remove one first")]
+    TooMany,
+    #[error("database error: {0}")]
+    Database(#[from] sqlx::Error),
+}
+
+/// Recurring announcements in `chloe_scheduled_messages`, posted by a background worker.
+/// Runs missed while chloe was down are posted once when she's back, not once per miss.
+pub struct ScheduledMessageService {
+    db_pool: PgPool,
+}
+
+impl ScheduledMessageService {
+    pub fn new(db_pool: PgPool) -> Self {
+        Self { db_pool }
+    }
+
+    /// Validate and store a schedule, `timezone` is an IANA name and UTC when left out
+    pub async fn create(
+        &self,
+        guild_id: u64,
+        channel_id: u64,
+        cron: &str,
+        timezone: Option<&str>,
+        content: &str,
+        created_by: u64,
+    ) -> Result<ScheduledMessage, ScheduleError> {
+        let schedule = CronSchedule::parse(cron)?;
+        let timezone = match timezone.map(str::trim).filter(|name| !name.is_empty()) {
+            Some(name) => name
+                .parse::<Tz>()
+                .map_err(|_| ScheduleError::UnknownTimezone(name.to_string()))?,
+            None => Tz::UTC,
+        };
+        let next_run_at = schedule
+            .next_after(Utc::now(), timezone)
+            .ok_or(ScheduleError::NeverRuns)?;
+
+        let existing: i64 = sqlx::query_scalar(
+            "SELECT COUNT(*) FROM chloe_scheduled_messages s
+             JOIN chloe_guilds g ON s.guild_id = g.id
+             WHERE g.snowflake_id = $1",
+        )
+        .bind(guild_id as i64)
+        .fetch_one(&self.db_pool)
+        .await?;
+        if existing >= MAX_SCHEDULES_PER_GUILD {
+            return Err(ScheduleError::TooMany);
+        }
+
+        let cron = cron.trim().to_string();
+        let id: String = sqlx::query_scalar(
+            "INSERT INTO chloe_scheduled_messages (guild_id, channel_id, cron, timezone, content, next_run_at, created_by)
+             SELECT g.id, $2, $3, $4, $5, $6, $7 FROM chloe_guilds g WHERE g.snowflake_id = $1
+             RETURNING id",
+        )
+        .bind(guild_id as i64)
+        .bind(channel_id as i64)
+        .bind(&cron)
+        .bind(timezone.name())
+        .bind(content)
+        .bind(next_run_at)
+        .bind(created_by as i64)
+        .fetch_optional(&self.db_pool)
+        .await?
+        .ok_or(sqlx::Error::RowNotFound)?; // the guild was never registered
+
+        info!(
+            event = "scheduled_message_created",
+            schedule_id = %id,
+            guild_id = guild_id,
+            channel_id = channel_id,
+            cron = %cron,
+            next_run_at = %next_run_at,
+            "Created scheduled message"
+        );
+        Ok(ScheduledMessage {
+            id,
+            channel_id,
+            cron,
+            timezone,
+            content: content.to_string(),
+            next_run_at,
+            enabled: true,
+        })
+    }
+
+    /// The guild's schedules, next to run first
+    pub async fn list(&self, guild_id: u64) -> Result<Vec<ScheduledMessage>, sqlx::Error> {
+        let rows = sqlx::query(
+            "SELECT s.id, s.channel_id, s.cron, s.timezone, s.content, s.next_run_at, s.enabled
+             FROM chloe_scheduled_messages s
+             JOIN chloe_guilds g ON s.guild_id = g.id
+             WHERE g.snowflake_id = $1
+             ORDER BY s.next_run_at",
+        )
+        .bind(guild_id as i64)
+        .fetch_all(&self.db_pool)
+        .await?;
+
+        Ok(rows.iter().map(scheduled_message_from_row).collect())
+    }
+
+    /// Remove one of the guild's schedules by its id or short id, None when no single
+    /// schedule matches
+    pub async fn remove(
+        &self,
+        guild_id: u64,
+        id: &str,
+    ) -> Result<Option<ScheduledMessage>, sqlx::Error> {
+        let id = id.trim().to_lowercase();
+        if id.len() < 4 || !id.chars().all(|c| c.is_ascii_hexdigit() || c == '-') {
+            return Ok(None);
+        }
+        let matching: Vec<ScheduledMessage> = self
+            .list(guild_id)
+            .await?
+            .into_iter()
+            .filter(|schedule| schedule.id.starts_with(&id))
+            .collect();
+        let [schedule] = matching.as_slice() else {
+            return Ok(None);
+        };
+
+        let result = sqlx::query("DELETE FROM chloe_scheduled_messages WHERE id = $1")
+            .bind(&schedule.id)
+            .execute(&self.db_pool)
+            .await?;
+        if result.rows_affected() == 0 {
+            return Ok(None);
+        }
+        info!(
+            event = "scheduled_message_removed",
+            schedule_id = %schedule.id,
+            guild_id = guild_id,
+            "Removed scheduled message"
+        );
+        Ok(Some(schedule.clone()))
+    }
+
+    /// Post due messages until the process exits
+    pub async fn start(&self, http: Arc<Http>) {
+        info!(
+            event = "scheduled_message_worker_started",
+            "Starting scheduled message worker"
+        );
+        loop {
+            if let Err(e) = self.post_due(&http).await {
+                error!(
+                    event = "scheduled_message_tick_failed",
+                    error = ?e,
+                    "Failed to post due scheduled messages"
+                );
+            }
+            sleep(POLL_INTERVAL).await;
+        }
+    }
+
+    async fn post_due(&self, http: &Http) -> Result<(), sqlx::Error> {
+        let now = Utc::now();
+        let mut tx = self.db_pool.begin().await?;
+        let rows = sqlx::query(
+            "SELECT id, channel_id, cron, timezone, content, next_run_at, enabled FROM chloe_scheduled_messages
+             WHERE enabled AND next_run_at <= $1
+             AND NOT EXISTS (SELECT 1 FROM chloe_guilds g WHERE g.id = guild_id AND g.left_at IS NOT NULL)
+             ORDER BY next_run_at
+             LIMIT $2
+             FOR UPDATE SKIP LOCKED",
+        )
+        .bind(now)
+        .bind(CLAIM_BATCH_SIZE)
+        .fetch_all(&mut *tx)
+        .await?;
+        let due: Vec<ScheduledMessage> = rows.iter().map(scheduled_message_from_row).collect();
+
+        // moved on before posting, so a crash mid-tick skips a run rather than repeating it
+        for schedule in &due {
+            let next_run_at = CronSchedule::parse(&schedule.cron)
+                .ok()
+                .and_then(|cron| cron.next_after(now, schedule.timezone));
+            sqlx::query(
+                "UPDATE chloe_scheduled_messages
+                 SET next_run_at = COALESCE($2, next_run_at), enabled = $3, last_run_at = $4
+                 WHERE id = $1",
+            )
+            .bind(&schedule.id)
+            .bind(next_run_at)
+            .bind(next_run_at.is_some())
+            .bind(now)
+            .execute(&mut *tx)
+            .await?;
+        }
+        tx.commit().await?;
+
+        for schedule in due {
+            let sent = ChannelId::new(schedule.channel_id)
+                .send_message(
+                    http,
+                    CreateMessage::new()
+                        .content(&schedule.content)
+                        .allowed_mentions(
+                            CreateAllowedMentions::new().all_users(true).all_roles(true),
+                        ),
+                )
+                .await;
+            match sent {
+                Ok(_) => info!(
+                    event = "scheduled_message_posted",
+                    schedule_id = %schedule.id,
+                    channel_id = schedule.channel_id,
+                    "Posted scheduled message"
+                ),
+                Err(e) if is_gone(&e) => {
+                    warn!(
+                        event = "scheduled_message_disabled",
+                        schedule_id = %schedule.id,
+                        channel_id = schedule.channel_id,
+                        error = ?e,
+                        "Channel is gone or off limits, disabling scheduled message"
+                    );
+                    sqlx::query(
+                        "UPDATE chloe_scheduled_messages SET enabled = false WHERE id = $1",
+                    )
+                    .bind(&schedule.id)
+                    .execute(&self.db_pool)
+                    .await?;
+                }
+                Err(e) => warn!(
+                    event = "scheduled_message_post_failed",
+                    schedule_id = %schedule.id,
+                    channel_id = schedule.channel_id,
+                    error = ?e,
+                    "Failed to post scheduled message"
+                ),
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Deleted channels and ones chloe can't post in anymore won't come back by retrying
+fn is_gone(error: &serenity::Error) -> bool {
+    matches!(
+        error,
+        serenity::Error::Http(serenity::http::HttpError::UnsuccessfulRequest(response))
+            if matches!(response.status_code.as_u16(), 403 | 404)
+    )
+}
+
+fn scheduled_message_from_row(row: &sqlx::postgres::PgRow) -> ScheduledMessage {
+    ScheduledMessage {
+        id: row.get("id"),
+        channel_id: row.get::<i64, _>("channel_id") as u64,
+        cron: row.get("cron"),
+        timezone: row.get::<String, _>("timezone").parse().unwrap_or(Tz::UTC),
+        content: row.get("content"),
+        next_run_at: row.get("next_run_at"),
+        enabled: row.get("enabled"),
+    }
+}
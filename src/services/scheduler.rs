@@ -0,0 +1,342 @@
+use crate::services::guild_retention;
+use crate::settings::Settings;
+use chrono::{DateTime, NaiveDate, NaiveDateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::types::Json;
+use sqlx::{PgPool, Row};
+use tokio::time::{Duration, sleep};
+use tracing::{error, info, warn};
+
+/// How often the worker checks for due jobs
+const POLL_INTERVAL: Duration = Duration::from_secs(30);
+
+/// Max jobs claimed per tick so one backlog can't starve the rest of the bot
+const CLAIM_BATCH_SIZE: i64 = 20;
+
+/// Work the scheduler knows how to run, stored as the job's JSON payload
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ScheduledJob {
+    /// Activate a prompt version. When `revert_at` is set, whatever prompt was active
+    /// right before is re-activated at that time.
+    ActivatePrompt {
+        prompt_id: String,
+        version: i32,
+        revert_at: Option<DateTime<Utc>>,
+    },
+    /// Delete the data of a guild chloe was removed from, skipped if she was added back
+    PurgeGuild { guild_id: u64 },
+}
+
+impl ScheduledJob {
+    pub const ACTIVATE_PROMPT: &'static str = "activate_prompt";
+    pub const PURGE_GUILD: &'static str = "purge_guild";
+
+    pub fn job_type(&self) -> &'static str {
+        match self {
+            ScheduledJob::ActivatePrompt { .. } => Self::ACTIVATE_PROMPT,
+            ScheduledJob::PurgeGuild { .. } => Self::PURGE_GUILD,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct ScheduledJobRecord {
+    pub id: String,
+    pub job: ScheduledJob,
+    pub run_at: DateTime<Utc>,
+    pub created_by: Option<String>,
+}
+
+pub struct Scheduler {
+    db_pool: PgPool,
+    settings: Settings,
+}
+
+impl Scheduler {
+    pub fn new(db_pool: PgPool, settings: Settings) -> Self {
+        Self { db_pool, settings }
+    }
+
+    pub async fn schedule(
+        &self,
+        job: ScheduledJob,
+        run_at: DateTime<Utc>,
+        created_by: Option<&str>,
+    ) -> Result<String, sqlx::Error> {
+        let job_id = sqlx::query_scalar::<_, String>(
+            "INSERT INTO chloe_scheduled_jobs (job_type, payload, run_at, created_by) VALUES ($1, $2, $3, $4) RETURNING id"
+        )
+        .bind(job.job_type())
+        .bind(Json(&job))
+        .bind(run_at)
+        .bind(created_by)
+        .fetch_one(&self.db_pool)
+        .await?;
+
+        info!(
+            event = "job_scheduled",
+            job_id = %job_id,
+            job_type = job.job_type(),
+            run_at = %run_at,
+            created_by = created_by.unwrap_or("unknown"),
+            "Scheduled job"
+        );
+
+        Ok(job_id)
+    }
+
+    /// Cancel a pending job, returns false if it doesn't exist or already ran
+    pub async fn cancel(&self, job_id: &str) -> Result<bool, sqlx::Error> {
+        let result = sqlx::query(
+            "UPDATE chloe_scheduled_jobs SET status = 'cancelled', modified_at = CURRENT_TIMESTAMP WHERE id = $1 AND status = 'pending'",
+        )
+        .bind(job_id)
+        .execute(&self.db_pool)
+        .await?;
+
+        let cancelled = result.rows_affected() > 0;
+        info!(
+            event = "job_cancel_requested",
+            job_id = %job_id,
+            cancelled = cancelled,
+            "Cancel requested for scheduled job"
+        );
+
+        Ok(cancelled)
+    }
+
+    pub async fn pending_jobs(
+        &self,
+        job_type: &str,
+    ) -> Result<Vec<ScheduledJobRecord>, sqlx::Error> {
+        let rows = sqlx::query(
+            "SELECT id, payload, run_at, created_by FROM chloe_scheduled_jobs WHERE status = 'pending' AND job_type = $1 ORDER BY run_at",
+        )
+        .bind(job_type)
+        .fetch_all(&self.db_pool)
+        .await?;
+
+        Ok(rows
+            .into_iter()
+            .filter_map(|row| {
+                let Json(job) = row.try_get::<Json<ScheduledJob>, _>("payload").ok()?;
+                Some(ScheduledJobRecord {
+                    id: row.get("id"),
+                    job,
+                    run_at: row.get("run_at"),
+                    created_by: row.get("created_by"),
+                })
+            })
+            .collect())
+    }
+
+    pub async fn start(&self) {
+        info!(event = "scheduler_started", "Starting job scheduler");
+
+        // jobs left running by a crash get another go, every job type is safe to re-run
+        if let Err(e) = sqlx::query(
+            "UPDATE chloe_scheduled_jobs SET status = 'pending' WHERE status = 'running'",
+        )
+        .execute(&self.db_pool)
+        .await
+        {
+            error!(
+                event = "scheduler_recovery_failed",
+                error = ?e,
+                "Failed to requeue interrupted jobs"
+            );
+        }
+
+        loop {
+            if let Err(e) = self.run_due_jobs().await {
+                error!(
+                    event = "scheduler_tick_failed",
+                    error = ?e,
+                    "Failed to run due jobs"
+                );
+            }
+            sleep(POLL_INTERVAL).await;
+        }
+    }
+
+    async fn run_due_jobs(&self) -> Result<(), sqlx::Error> {
+        let rows = sqlx::query(
+            r#"
+            UPDATE chloe_scheduled_jobs SET status = 'running', modified_at = CURRENT_TIMESTAMP
+            WHERE id IN (
+                SELECT id FROM chloe_scheduled_jobs
+                WHERE status = 'pending' AND run_at <= NOW()
+                ORDER BY run_at
+                LIMIT $1
+                FOR UPDATE SKIP LOCKED
+            )
+            RETURNING id, payload
+            "#,
+        )
+        .bind(CLAIM_BATCH_SIZE)
+        .fetch_all(&self.db_pool)
+        .await?;
+
+        for row in rows {
+            let job_id: String = row.get("id");
+            let result = match row.try_get::<Json<ScheduledJob>, _>("payload") {
+                Ok(Json(job)) => self.execute(&job_id, job).await,
+                Err(e) => Err(format!("Invalid job payload: {}", e)),
+            };
+
+            let (status, last_error) = match &result {
+                Ok(()) => ("completed", None),
+                Err(e) => ("failed", Some(e.as_str())),
+            };
+
+            if let Err(error) = &result {
+                warn!(
+                    event = "scheduled_job_failed",
+                    job_id = %job_id,
+                    error = %error,
+                    "Scheduled job failed"
+                );
+            }
+
+            sqlx::query(
+                "UPDATE chloe_scheduled_jobs SET status = $1, last_error = $2, modified_at = CURRENT_TIMESTAMP WHERE id = $3",
+            )
+            .bind(status)
+            .bind(last_error)
+            .bind(&job_id)
+            .execute(&self.db_pool)
+            .await?;
+        }
+
+        Ok(())
+    }
+
+    async fn execute(&self, job_id: &str, job: ScheduledJob) -> Result<(), String> {
+        info!(
+            event = "scheduled_job_running",
+            job_id = %job_id,
+            job_type = job.job_type(),
+            "Running scheduled job"
+        );
+
+        match job {
+            ScheduledJob::ActivatePrompt {
+                prompt_id,
+                version,
+                revert_at,
+            } => {
+                self.activate_prompt(job_id, &prompt_id, version, revert_at)
+                    .await
+            }
+            ScheduledJob::PurgeGuild { guild_id } => {
+                guild_retention::purge_guild(&self.db_pool, guild_id)
+                    .await
+                    .map(|_| ())
+                    .map_err(|e| format!("Failed to purge guild data: {}", e))
+            }
+        }
+    }
+
+    async fn activate_prompt(
+        &self,
+        job_id: &str,
+        prompt_id: &str,
+        version: i32,
+        revert_at: Option<DateTime<Utc>>,
+    ) -> Result<(), String> {
+        let previous = self
+            .settings
+            .get_active_prompt(&self.db_pool)
+            .await
+            .map_err(|e| format!("Failed to look up active prompt: {}", e))?;
+
+        self.settings
+            .activate_prompt_version(&self.db_pool, prompt_id)
+            .await
+            .map_err(|e| format!("Failed to activate prompt: {}", e))?;
+
+        info!(
+            event = "scheduled_prompt_activated",
+            job_id = %job_id,
+            prompt_id = %prompt_id,
+            version = version,
+            "Activated scheduled prompt"
+        );
+
+        match (revert_at, previous) {
+            (Some(revert_at), Some((previous_id, previous_version)))
+                if previous_id != prompt_id =>
+            {
+                self.schedule(
+                    ScheduledJob::ActivatePrompt {
+                        prompt_id: previous_id,
+                        version: previous_version,
+                        revert_at: None,
+                    },
+                    revert_at,
+                    Some("scheduler"),
+                )
+                .await
+                .map_err(|e| format!("Failed to schedule prompt reversion: {}", e))?;
+            }
+            _ => {}
+        }
+
+        Ok(())
+    }
+}
+
+/// Parse a schedule time given in UTC as RFC 3339, `YYYY-MM-DD HH:MM` or `YYYY-MM-DD`
+pub fn parse_schedule_time(input: &str) -> Option<DateTime<Utc>> {
+    let input = input.trim();
+
+    if let Ok(time) = DateTime::parse_from_rfc3339(input) {
+        return Some(time.with_timezone(&Utc));
+    }
+    if let Ok(time) = NaiveDateTime::parse_from_str(input, "%Y-%m-%d %H:%M") {
+        return Some(time.and_utc());
+    }
+    NaiveDate::parse_from_str(input, "%Y-%m-%d")
+        .ok()
+        .and_then(|date| date.and_hms_opt(0, 0, 0))
+        .map(|time| time.and_utc())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_schedule_time_formats() {
+        let expected = "2025-10-25T18:30:00Z".parse::<DateTime<Utc>>().unwrap();
+        assert_eq!(parse_schedule_time("2025-10-25 18:30"), Some(expected));
+        assert_eq!(
+            parse_schedule_time("2025-10-25T20:30:00+02:00"),
+            Some(expected)
+        );
+        assert_eq!(
+            parse_schedule_time("2025-10-25"),
+            "2025-10-25T00:00:00Z".parse::<DateTime<Utc>>().ok()
+        );
+        assert_eq!(parse_schedule_time("next tuesday"), None);
+    }
+
+    #[test]
+    fn test_job_payload_roundtrip() {
+        let job = ScheduledJob::ActivatePrompt {
+            prompt_id: "abc".to_string(),
+            version: 3,
+            revert_at: None,
+        };
+        let payload = serde_json::to_value(&job).unwrap();
+        assert_eq!(payload["type"], "activate_prompt");
+        assert!(matches!(
+            serde_json::from_value::<ScheduledJob>(payload).unwrap(),
+            ScheduledJob::ActivatePrompt { version: 3, .. }
+        ));
+
+        let payload = serde_json::to_value(ScheduledJob::PurgeGuild { guild_id: 42 }).unwrap();
+        assert_eq!(payload, serde_json::json!({ "type": "purge_guild", "guild_id": 42 }));
+    }
+}
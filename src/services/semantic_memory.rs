@@ -0,0 +1,228 @@
+use crate::services::embeddings::{EmbeddingProvider, EmbeddingTask, vector_literal};
+use crate::utils::message_cache::CachedMessage;
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use sqlx::{PgPool, Row};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use tracing::{info, warn};
+
+/// Matches less similar than this aren't worth the prompt space
+pub const MIN_SIMILARITY: f64 = 0.6;
+
+/// Shorter messages ("lol", "ok") carry no meaning worth retrieving
+const MIN_INDEXED_CHARS: usize = 12;
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum MemorySource {
+    /// An older message in the channel
+    Message,
+    /// A fact the user asked chloe to remember
+    Fact,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RetrievedMemory {
+    pub source: MemorySource,
+    pub author_name: String,
+    pub content: String,
+    pub similarity: f64,
+}
+
+/// Who and where the memories are retrieved for
+#[derive(Debug, Clone, Copy)]
+pub struct MemoryQuery {
+    pub guild_id: Option<u64>,
+    pub channel_id: u64,
+    pub user_id: u64,
+    /// Only messages older than this are considered, newer ones are in the context anyway
+    pub before_message_id: u64,
+}
+
+/// Embeddings of stored conversation turns and user facts in `chloe_memory_embeddings`
+/// (pgvector), so the context can pull in what's related to the current message instead
+/// of only the latest history. Rows go away with the turn or fact they were made from.
+/// Everything is a no-op until `initialize` found pgvector.
+pub struct SemanticMemory {
+    db_pool: PgPool,
+    embedder: Arc<dyn EmbeddingProvider>,
+    enabled: AtomicBool,
+}
+
+impl SemanticMemory {
+    pub fn new(db_pool: PgPool, embedder: Arc<dyn EmbeddingProvider>) -> Self {
+        Self {
+            db_pool,
+            embedder,
+            enabled: AtomicBool::new(false),
+        }
+    }
+
+    /// Set up the embeddings table, call once the other tables exist
+    pub async fn initialize(&self) {
+        match crate::schema::initialize_semantic_memory(&self.db_pool).await {
+            Ok(()) => {
+                self.enabled.store(true, Ordering::Relaxed);
+                info!(
+                    event = "semantic_memory_enabled",
+                    model = self.embedder.model(),
+                    "Semantic memory enabled"
+                );
+            }
+            Err(e) => warn!(
+                event = "semantic_memory_unavailable",
+                error = ?e,
+                "pgvector isn't available, running without semantic memory"
+            ),
+        }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.enabled.load(Ordering::Relaxed)
+    }
+
+    /// Embed a stored conversation turn, call once it is in `chloe_messages`
+    pub async fn index_message(
+        &self,
+        message: &CachedMessage,
+        guild_id: Option<u64>,
+    ) -> Result<()> {
+        if !self.is_enabled() || !is_worth_indexing(&message.content) {
+            return Ok(());
+        }
+        let embedding = self
+            .embed(&message.content, EmbeddingTask::Document)
+            .await?;
+        sqlx::query(
+            "INSERT INTO chloe_memory_embeddings (message_id, channel_id, guild_id, user_id, author_name, content, embedding)
+             VALUES ($1, $2, $3, $4, $5, $6, $7::vector)
+             ON CONFLICT (message_id) DO NOTHING",
+        )
+        .bind(message.id as i64)
+        .bind(message.channel_id as i64)
+        .bind(guild_id.map(|id| id as i64))
+        .bind(message.author_id as i64)
+        .bind(&message.author_name)
+        .bind(&message.content)
+        .bind(embedding)
+        .execute(&self.db_pool)
+        .await?;
+        Ok(())
+    }
+
+    /// Re-embed an edited turn
+    pub async fn reindex_message(&self, message_id: u64, content: &str) -> Result<()> {
+        if !self.is_enabled() {
+            return Ok(());
+        }
+        if !is_worth_indexing(content) {
+            sqlx::query("DELETE FROM chloe_memory_embeddings WHERE message_id = $1")
+                .bind(message_id as i64)
+                .execute(&self.db_pool)
+                .await?;
+            return Ok(());
+        }
+        let embedding = self.embed(content, EmbeddingTask::Document).await?;
+        sqlx::query(
+            "UPDATE chloe_memory_embeddings SET content = $2, embedding = $3::vector WHERE message_id = $1",
+        )
+        .bind(message_id as i64)
+        .bind(content)
+        .bind(embedding)
+        .execute(&self.db_pool)
+        .await?;
+        Ok(())
+    }
+
+    /// Embed a fact from `chloe_user_memories`
+    pub async fn index_fact(
+        &self,
+        memory_id: &str,
+        guild_id: u64,
+        user_id: u64,
+        fact: &str,
+    ) -> Result<()> {
+        if !self.is_enabled() {
+            return Ok(());
+        }
+        let embedding = self.embed(fact, EmbeddingTask::Document).await?;
+        sqlx::query(
+            "INSERT INTO chloe_memory_embeddings (memory_id, guild_id, user_id, author_name, content, embedding)
+             VALUES ($1, $2, $3, '', $4, $5::vector)
+             ON CONFLICT (memory_id) DO NOTHING",
+        )
+        .bind(memory_id)
+        .bind(guild_id as i64)
+        .bind(user_id as i64)
+        .bind(fact)
+        .bind(embedding)
+        .execute(&self.db_pool)
+        .await?;
+        Ok(())
+    }
+
+    /// Older messages of the channel and the user's facts closest to `text`, most similar first
+    pub async fn search(
+        &self,
+        query: &MemoryQuery,
+        text: &str,
+        limit: usize,
+    ) -> Result<Vec<RetrievedMemory>> {
+        if !self.is_enabled() {
+            return Ok(Vec::new());
+        }
+        let embedding = self.embed(text, EmbeddingTask::Query).await?;
+        let rows = sqlx::query(
+            "SELECT memory_id IS NOT NULL AS is_fact, author_name, content,
+                    1 - (embedding <=> $1::vector) AS similarity
+             FROM chloe_memory_embeddings
+             WHERE (message_id IS NOT NULL AND channel_id = $2 AND message_id < $3)
+                OR (memory_id IS NOT NULL AND guild_id = $4 AND user_id = $5)
+             ORDER BY embedding <=> $1::vector
+             LIMIT $6",
+        )
+        .bind(embedding)
+        .bind(query.channel_id as i64)
+        .bind(query.before_message_id as i64)
+        .bind(query.guild_id.map(|id| id as i64))
+        .bind(query.user_id as i64)
+        .bind(limit as i64)
+        .fetch_all(&self.db_pool)
+        .await?;
+
+        Ok(rows
+            .iter()
+            .map(|row| RetrievedMemory {
+                source: if row.get("is_fact") {
+                    MemorySource::Fact
+                } else {
+                    MemorySource::Message
+                },
+                author_name: row.get("author_name"),
+                content: row.get("content"),
+                similarity: row.get("similarity"),
+            })
+            .filter(|memory| memory.similarity >= MIN_SIMILARITY)
+            .collect())
+    }
+
+    async fn embed(&self, text: &str, task: EmbeddingTask) -> Result<String> {
+        let embeddings = self.embedder.embed(&[text.to_string()], task).await?;
+        Ok(vector_literal(&embeddings[0]))
+    }
+}
+
+fn is_worth_indexing(content: &str) -> bool {
+    content.trim().chars().count() >= MIN_INDEXED_CHARS
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_short_messages_are_not_indexed() {
+        assert!(!is_worth_indexing("  lol ok  "));
+        assert!(is_worth_indexing("I main jungle in ranked"));
+    }
+}
@@ -0,0 +1,152 @@
+use crate::services::reaction_roles::emoji_key;
+use serde_json::Value;
+use serenity::model::channel::ReactionType;
+use sqlx::PgPool;
+use tracing::info;
+
+/// Guild setting configuring the starboard
+pub const STARBOARD_SETTING: &str = "starboard";
+
+const DEFAULT_EMOJI: &str = "⭐";
+
+const DEFAULT_THRESHOLD: u64 = 3;
+
+/// From the guild setting `starboard`, e.g. `{"channelId": "123", "emoji": "⭐",
+/// "threshold": 5}`. The starboard is off until a channel is set.
+#[derive(Debug, Clone, PartialEq)]
+pub struct StarboardSettings {
+    pub channel_id: u64,
+    /// Stored as [`emoji_key`] gives it, so custom emojis match after a rename
+    pub emoji: String,
+    pub threshold: u64,
+}
+
+impl StarboardSettings {
+    pub fn from_setting(setting: Option<&Value>) -> Option<Self> {
+        let setting = setting?;
+        let channel_id = match setting.get("channelId")? {
+            Value::String(id) => id.trim().parse().ok()?,
+            value => value.as_u64()?,
+        };
+        let emoji = setting
+            .get("emoji")
+            .and_then(Value::as_str)
+            .map(str::trim)
+            .filter(|emoji| !emoji.is_empty())
+            .and_then(|emoji| ReactionType::try_from(emoji).ok())
+            .map(|emoji| emoji_key(&emoji))
+            .unwrap_or_else(|| DEFAULT_EMOJI.to_string());
+        let threshold = setting
+            .get("threshold")
+            .and_then(Value::as_u64)
+            .unwrap_or(DEFAULT_THRESHOLD)
+            .max(1);
+        Some(Self {
+            channel_id,
+            emoji,
+            threshold,
+        })
+    }
+
+    pub fn matches(&self, emoji: &ReactionType) -> bool {
+        emoji_key(emoji) == self.emoji
+    }
+}
+
+/// Messages already reposted to a starboard, in `chloe_starboard`, so each goes up once
+pub struct StarboardService {
+    db_pool: PgPool,
+}
+
+impl StarboardService {
+    pub fn new(db_pool: PgPool) -> Self {
+        Self { db_pool }
+    }
+
+    /// Reserve a message for the starboard, false when it's already there or being posted
+    pub async fn claim(
+        &self,
+        guild_id: u64,
+        channel_id: u64,
+        message_id: u64,
+    ) -> Result<bool, sqlx::Error> {
+        let result = sqlx::query(
+            "INSERT INTO chloe_starboard (guild_id, channel_id, message_id)
+             SELECT g.id, $2, $3 FROM chloe_guilds g WHERE g.snowflake_id = $1
+             ON CONFLICT (message_id) DO NOTHING",
+        )
+        .bind(guild_id as i64)
+        .bind(channel_id as i64)
+        .bind(message_id as i64)
+        .execute(&self.db_pool)
+        .await?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// Remember where a claimed message was reposted
+    pub async fn posted(
+        &self,
+        message_id: u64,
+        starboard_message_id: u64,
+    ) -> Result<(), sqlx::Error> {
+        sqlx::query("UPDATE chloe_starboard SET starboard_message_id = $2 WHERE message_id = $1")
+            .bind(message_id as i64)
+            .bind(starboard_message_id as i64)
+            .execute(&self.db_pool)
+            .await?;
+        info!(
+            event = "starboard_message_posted",
+            message_id = message_id,
+            starboard_message_id = starboard_message_id,
+            "Reposted message to starboard"
+        );
+        Ok(())
+    }
+
+    /// Give up a claim whose repost failed, so the next reaction tries again
+    pub async fn release(&self, message_id: u64) -> Result<(), sqlx::Error> {
+        sqlx::query(
+            "DELETE FROM chloe_starboard WHERE message_id = $1 AND starboard_message_id IS NULL",
+        )
+        .bind(message_id as i64)
+        .execute(&self.db_pool)
+        .await?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_starboard_settings() {
+        assert_eq!(StarboardSettings::from_setting(None), None);
+        assert_eq!(
+            StarboardSettings::from_setting(Some(&json!({ "emoji": "🔥" }))),
+            None
+        );
+
+        let defaults = StarboardSettings::from_setting(Some(&json!({ "channelId": "42" })));
+        assert_eq!(
+            defaults,
+            Some(StarboardSettings {
+                channel_id: 42,
+                emoji: "⭐".to_string(),
+                threshold: 3,
+            })
+        );
+
+        let custom = StarboardSettings::from_setting(Some(&json!({
+            "channelId": 42,
+            "emoji": "<:chloe:600404340292059257>",
+            "threshold": 0
+        })))
+        .unwrap();
+        assert_eq!(custom.emoji, "600404340292059257");
+        assert_eq!(custom.threshold, 1);
+        assert!(custom.matches(&ReactionType::try_from("<:renamed:600404340292059257>").unwrap()));
+        assert!(!custom.matches(&ReactionType::Unicode("⭐".to_string())));
+    }
+}
@@ -0,0 +1,232 @@
+use crate::services::llm_service::LlmService;
+use crate::services::reminders::parse_delay;
+use chrono::{DateTime, Utc};
+use serenity::builder::GetMessages;
+use serenity::http::Http;
+use serenity::model::channel::Message;
+use serenity::model::id::{ChannelId, MessageId};
+use tracing::info;
+
+/// Messages summarized when neither a count nor a time is given
+pub const DEFAULT_MESSAGE_COUNT: usize = 100;
+
+/// Most messages one summary reads, fetched 100 at a time from discord
+pub const MAX_MESSAGE_COUNT: usize = 500;
+
+/// Transcript characters per request, roughly 6k tokens so a chunk fits next to the
+/// prompt of any model chloe routes to
+pub const MAX_CHUNK_CHARS: usize = 24_000;
+
+/// A single message is cut after this many characters, pasted logs say little in a recap
+const MAX_LINE_CHARS: usize = 1000;
+
+/// Instructions for the recap, the model fills these sections in
+pub const RECAP_PROMPT: &str = "You're Chloe, recapping a discord conversation for someone who missed it. Reply in the conversation's language using exactly these sections, leaving out any that would be empty:\n**tl;dr** one or two sentences\n**what happened** a few bullet points, in order\n**key decisions** bullet points of what was agreed on\n**action items** bullet points of who does what, with the person's name\n**open questions** bullet points of what's still unanswered\nOnly use what's in the transcript, don't invent names or details. Stay under 3500 characters.";
+
+/// Instructions for the notes taken on each part of a transcript too long for one request
+const CHUNK_PROMPT: &str = "You're taking notes on one part of a longer discord conversation so it can be recapped later. List what was discussed, decisions, action items with who owns them, and open questions as short bullet points. Keep names. Only use what's in the transcript.";
+
+/// Which messages to summarize
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum HistoryRange {
+    /// The last this many messages
+    Count(usize),
+    /// Everything posted after this time, up to [`MAX_MESSAGE_COUNT`]
+    Since(DateTime<Utc>),
+}
+
+impl HistoryRange {
+    /// From the command or tool options: a count, or a delay like `2h` back from `now`
+    pub fn from_options(
+        count: Option<usize>,
+        since: Option<&str>,
+        now: DateTime<Utc>,
+    ) -> Result<Self, String> {
+        if let Some(since) = since.map(str::trim).filter(|since| !since.is_empty()) {
+            let delay = parse_delay(since).ok_or_else(|| {
+                format!(
+                    "couldn't understand '{}', use a time span like 2h or 1d 12h",
+                    since
+                )
+            })?;
+            return Ok(Self::Since(now - delay));
+        }
+        Ok(Self::Count(
+            count
+                .unwrap_or(DEFAULT_MESSAGE_COUNT)
+                .clamp(1, MAX_MESSAGE_COUNT),
+        ))
+    }
+}
+
+/// The channel's messages in `range` before `before` (or the latest), oldest first
+pub async fn fetch_history(
+    http: &Http,
+    channel_id: ChannelId,
+    before: Option<MessageId>,
+    range: HistoryRange,
+) -> serenity::Result<Vec<Message>> {
+    let wanted = match range {
+        HistoryRange::Count(count) => count,
+        HistoryRange::Since(_) => MAX_MESSAGE_COUNT,
+    };
+    let mut messages: Vec<Message> = Vec::new();
+    let mut before = before;
+    while messages.len() < wanted {
+        let limit = (wanted - messages.len()).min(100) as u8;
+        let mut request = GetMessages::new().limit(limit);
+        if let Some(before) = before {
+            request = request.before(before);
+        }
+        // newest first
+        let page = channel_id.messages(http, request).await?;
+        let page_len = page.len();
+        let mut reached_start = false;
+        for message in page {
+            if let HistoryRange::Since(since) = range
+                && *message.timestamp < since
+            {
+                reached_start = true;
+                break;
+            }
+            messages.push(message);
+        }
+        if reached_start || page_len < limit as usize {
+            break;
+        }
+        before = messages.last().map(|message| message.id);
+    }
+    messages.reverse();
+    Ok(messages)
+}
+
+/// One transcript line per message worth reading, like `[14:02] mika: hi`
+pub fn transcript(messages: &[Message]) -> Vec<String> {
+    messages
+        .iter()
+        .filter_map(|message| {
+            let mut content = message.content.trim().to_string();
+            for attachment in &message.attachments {
+                content.push_str(&format!(" [attachment: {}]", attachment.filename));
+            }
+            let content = content.trim();
+            if content.is_empty() {
+                return None;
+            }
+            let author = if message.author.bot {
+                format!("{} (bot)", message.author.name)
+            } else {
+                message.author.name.clone()
+            };
+            Some(transcript_line(
+                &message.timestamp.format("%H:%M").to_string(),
+                &author,
+                content,
+            ))
+        })
+        .collect()
+}
+
+fn transcript_line(time: &str, author: &str, content: &str) -> String {
+    let content = content.replace('\n', " ⏎ ");
+    let content = if content.chars().count() > MAX_LINE_CHARS {
+        let mut cut: String = content.chars().take(MAX_LINE_CHARS).collect();
+        cut.push('…');
+        cut
+    } else {
+        content
+    };
+    format!("[{}] {}: {}", time, author, content)
+}
+
+/// Split transcript lines into chunks of at most `max_chars`, never splitting a line
+pub fn chunk_transcript(lines: &[String], max_chars: usize) -> Vec<String> {
+    let mut chunks = Vec::new();
+    let mut current = String::new();
+    for line in lines {
+        if !current.is_empty() && current.len() + line.len() + 1 > max_chars {
+            chunks.push(std::mem::take(&mut current));
+        }
+        if !current.is_empty() {
+            current.push('\n');
+        }
+        current.push_str(line);
+    }
+    if !current.is_empty() {
+        chunks.push(current);
+    }
+    chunks
+}
+
+/// A recap of the transcript. Transcripts over one chunk get notes taken per chunk
+/// first, the recap is written from the notes.
+pub async fn summarize(llm_service: &LlmService, lines: &[String]) -> anyhow::Result<String> {
+    let chunks = chunk_transcript(lines, MAX_CHUNK_CHARS);
+    if chunks.len() <= 1 {
+        let transcript = chunks.into_iter().next().unwrap_or_default();
+        return llm_service.complete_text(RECAP_PROMPT, &transcript).await;
+    }
+
+    let chunk_count = chunks.len();
+    let mut notes = Vec::with_capacity(chunk_count);
+    for (index, chunk) in chunks.iter().enumerate() {
+        let part = llm_service.complete_text(CHUNK_PROMPT, chunk).await?;
+        notes.push(format!(
+            "Notes on part {} of {}:\n{}",
+            index + 1,
+            chunk_count,
+            part
+        ));
+    }
+    info!(
+        event = "summary_chunked",
+        chunks = chunk_count,
+        lines = lines.len(),
+        "Summarized a long transcript in parts"
+    );
+    llm_service
+        .complete_text(RECAP_PROMPT, &notes.join("\n\n"))
+        .await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Duration;
+
+    #[test]
+    fn test_history_range() {
+        let now = Utc::now();
+        assert_eq!(
+            HistoryRange::from_options(None, None, now),
+            Ok(HistoryRange::Count(DEFAULT_MESSAGE_COUNT))
+        );
+        assert_eq!(
+            HistoryRange::from_options(Some(9000), None, now),
+            Ok(HistoryRange::Count(MAX_MESSAGE_COUNT))
+        );
+        assert_eq!(
+            HistoryRange::from_options(Some(20), Some("2h"), now),
+            Ok(HistoryRange::Since(now - Duration::hours(2)))
+        );
+        assert!(HistoryRange::from_options(None, Some("yesterday-ish"), now).is_err());
+    }
+
+    #[test]
+    fn test_chunk_transcript() {
+        let lines: Vec<String> = (0..10).map(|i| format!("[12:0{}] mika: hi", i)).collect();
+        // each line is 16 characters, three fit with their newlines
+        let chunks = chunk_transcript(&lines, 60);
+        assert_eq!(chunks.len(), 4);
+        assert_eq!(chunks[0].lines().count(), 3);
+        assert!(chunks.iter().all(|chunk| chunk.len() <= 60));
+        assert!(chunk_transcript(&[], 60).is_empty());
+
+        let line = transcript_line("12:00", "mika", &"x".repeat(2000));
+        assert!(line.ends_with('…'));
+        assert_eq!(
+            transcript_line("12:00", "mika", "a\nb"),
+            "[12:00] mika: a ⏎ b"
+        );
+    }
+}
@@ -0,0 +1,244 @@
+use regex::{Regex, RegexBuilder};
+use sqlx::{PgPool, Row};
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::sync::{Mutex, RwLock};
+use tracing::{error, info};
+
+pub const MAX_PATTERN_LENGTH: usize = 200;
+
+pub const MAX_RESPONSE_LENGTH: usize = 2000;
+
+/// How long a trigger stays quiet in a channel after firing, so a busy support channel
+/// doesn't get the same answer to every message
+const TRIGGER_COOLDOWN: Duration = Duration::from_secs(30);
+
+/// An admin defined auto-response, answered with canned text or by the model following
+/// `response` as instructions
+#[derive(Debug, Clone, PartialEq)]
+pub struct Trigger {
+    pub pattern: String,
+    pub is_regex: bool,
+    pub response: String,
+    pub use_llm: bool,
+    /// Only fires in this channel when set
+    pub channel_id: Option<u64>,
+}
+
+impl Trigger {
+    /// Keywords match case-insensitively as whole words, regexes anywhere in the message
+    pub fn matcher(&self) -> Result<Regex, regex::Error> {
+        let pattern = if self.is_regex {
+            self.pattern.clone()
+        } else {
+            format!(r"(?:^|\W){}(?:\W|$)", regex::escape(self.pattern.trim()))
+        };
+        RegexBuilder::new(&pattern)
+            .case_insensitive(true)
+            .size_limit(1 << 20)
+            .build()
+    }
+}
+
+struct CompiledTrigger {
+    trigger: Trigger,
+    matcher: Regex,
+}
+
+/// Per guild triggers, compiled once and reloaded whenever a guild's triggers change
+pub struct TriggerService {
+    db_pool: PgPool,
+    by_guild: RwLock<HashMap<i64, Arc<Vec<CompiledTrigger>>>>,
+    last_fired: Mutex<HashMap<(i64, String, u64), Instant>>,
+}
+
+impl TriggerService {
+    pub fn new(db_pool: PgPool) -> Self {
+        Self {
+            db_pool,
+            by_guild: RwLock::new(HashMap::new()),
+            last_fired: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// The first trigger matching a message, unless it fired in the channel recently
+    pub async fn find_match(
+        &self,
+        guild_id: i64,
+        channel_id: u64,
+        content: &str,
+    ) -> Option<Trigger> {
+        let triggers = self.compiled(guild_id).await;
+        let trigger = triggers
+            .iter()
+            .find(|compiled| {
+                compiled
+                    .trigger
+                    .channel_id
+                    .is_none_or(|only| only == channel_id)
+                    && compiled.matcher.is_match(content)
+            })
+            .map(|compiled| compiled.trigger.clone())?;
+
+        let mut last_fired = self.last_fired.lock().await;
+        let now = Instant::now();
+        last_fired.retain(|_, fired_at| now.duration_since(*fired_at) < TRIGGER_COOLDOWN);
+        let key = (guild_id, trigger.pattern.clone(), channel_id);
+        if last_fired.contains_key(&key) {
+            return None;
+        }
+        last_fired.insert(key, now);
+        Some(trigger)
+    }
+
+    /// Add a trigger, or replace the one with the same pattern
+    pub async fn add(
+        &self,
+        guild_id: i64,
+        trigger: &Trigger,
+        created_by: &str,
+    ) -> Result<(), sqlx::Error> {
+        let result = sqlx::query(
+            "INSERT INTO chloe_triggers (guild_id, pattern, is_regex, response, use_llm, channel_id, created_by)
+             SELECT g.id, $2, $3, $4, $5, $6, $7 FROM chloe_guilds g WHERE g.snowflake_id = $1
+             ON CONFLICT (guild_id, pattern) DO UPDATE SET
+                 is_regex = EXCLUDED.is_regex,
+                 response = EXCLUDED.response,
+                 use_llm = EXCLUDED.use_llm,
+                 channel_id = EXCLUDED.channel_id",
+        )
+        .bind(guild_id)
+        .bind(&trigger.pattern)
+        .bind(trigger.is_regex)
+        .bind(&trigger.response)
+        .bind(trigger.use_llm)
+        .bind(trigger.channel_id.map(|id| id as i64))
+        .bind(created_by)
+        .execute(&self.db_pool)
+        .await?;
+
+        if result.rows_affected() == 0 {
+            return Err(sqlx::Error::RowNotFound); // the guild was never registered
+        }
+        self.by_guild.write().await.remove(&guild_id);
+        info!(
+            event = "trigger_added",
+            guild_id = guild_id,
+            pattern = %trigger.pattern,
+            use_llm = trigger.use_llm,
+            "Added trigger"
+        );
+        Ok(())
+    }
+
+    /// Whether a trigger with this pattern existed and was removed
+    pub async fn remove(&self, guild_id: i64, pattern: &str) -> Result<bool, sqlx::Error> {
+        let result = sqlx::query(
+            "DELETE FROM chloe_triggers t USING chloe_guilds g
+             WHERE t.guild_id = g.id AND g.snowflake_id = $1 AND t.pattern = $2",
+        )
+        .bind(guild_id)
+        .bind(pattern)
+        .execute(&self.db_pool)
+        .await?;
+
+        self.by_guild.write().await.remove(&guild_id);
+        Ok(result.rows_affected() > 0)
+    }
+
+    pub async fn list(&self, guild_id: i64) -> Result<Vec<Trigger>, sqlx::Error> {
+        let rows = sqlx::query(
+            "SELECT t.pattern, t.is_regex, t.response, t.use_llm, t.channel_id FROM chloe_triggers t
+             JOIN chloe_guilds g ON t.guild_id = g.id
+             WHERE g.snowflake_id = $1
+             ORDER BY t.created_at",
+        )
+        .bind(guild_id)
+        .fetch_all(&self.db_pool)
+        .await?;
+
+        Ok(rows
+            .iter()
+            .map(|row| Trigger {
+                pattern: row.get("pattern"),
+                is_regex: row.get("is_regex"),
+                response: row.get("response"),
+                use_llm: row.get("use_llm"),
+                channel_id: row.get::<Option<i64>, _>("channel_id").map(|id| id as u64),
+            })
+            .collect())
+    }
+
+    async fn compiled(&self, guild_id: i64) -> Arc<Vec<CompiledTrigger>> {
+        if let Some(triggers) = self.by_guild.read().await.get(&guild_id) {
+            return Arc::clone(triggers);
+        }
+
+        let triggers = match self.list(guild_id).await {
+            Ok(triggers) => triggers,
+            Err(e) => {
+                error!(
+                    event = "triggers_load_failed",
+                    guild_id = guild_id,
+                    error = ?e,
+                    "Failed to load triggers"
+                );
+                return Arc::new(Vec::new());
+            }
+        };
+        // patterns are validated when added, one that no longer compiles is skipped
+        let compiled: Arc<Vec<CompiledTrigger>> = Arc::new(
+            triggers
+                .into_iter()
+                .filter_map(|trigger| {
+                    let matcher = trigger.matcher().ok()?;
+                    Some(CompiledTrigger { trigger, matcher })
+                })
+                .collect(),
+        );
+        self.by_guild
+            .write()
+            .await
+            .insert(guild_id, Arc::clone(&compiled));
+        compiled
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn trigger(pattern: &str, is_regex: bool) -> Trigger {
+        Trigger {
+            pattern: pattern.to_string(),
+            is_regex,
+            response: "see #faq".to_string(),
+            use_llm: false,
+            channel_id: None,
+        }
+    }
+
+    #[test]
+    fn test_keyword_matches_whole_words() {
+        let matcher = trigger("refund", false).matcher().unwrap();
+        assert!(matcher.is_match("how do I get a Refund?"));
+        assert!(matcher.is_match("refund"));
+        assert!(!matcher.is_match("refunded already"));
+
+        // keywords are literal text, not patterns
+        let matcher = trigger("c++", false).matcher().unwrap();
+        assert!(matcher.is_match("anyone know c++"));
+        assert!(!matcher.is_match("anyone know c"));
+    }
+
+    #[test]
+    fn test_regex_trigger() {
+        let matcher = trigger(r"how (do|can) i (install|set ?up)", true)
+            .matcher()
+            .unwrap();
+        assert!(matcher.is_match("hey, How can I setup the bot"));
+        assert!(!matcher.is_match("I installed it"));
+        assert!(trigger("(unclosed", true).matcher().is_err());
+    }
+}
@@ -0,0 +1,495 @@
+use crate::services::gemini_types::UsageMetadata;
+use crate::services::ollama_provider;
+use chrono::NaiveDate;
+use serde_json::{Value, json};
+use sqlx::{PgPool, Row};
+use tracing::error;
+
+/// List prices in USD per million prompt and completion tokens. Self-hosted ollama models
+/// and unknown models count as free.
+const MODEL_PRICES: &[(&str, f64, f64)] = &[
+    ("gemini-2.5-pro", 1.25, 10.0),
+    ("gemini-2.5-flash-lite", 0.10, 0.40),
+    ("gemini-2.5-flash", 0.30, 2.50),
+    ("gemini-2.0-flash-lite", 0.075, 0.30),
+    ("gemini-2.0-flash", 0.10, 0.40),
+];
+
+/// Token totals over some period
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct UsageTotals {
+    pub prompt_tokens: i64,
+    pub completion_tokens: i64,
+    pub requests: i64,
+    pub cost_usd: f64,
+}
+
+impl UsageTotals {
+    fn add(&mut self, model: &str, prompt_tokens: i64, completion_tokens: i64, requests: i64) {
+        self.prompt_tokens += prompt_tokens;
+        self.completion_tokens += completion_tokens;
+        self.requests += requests;
+        self.cost_usd += estimated_cost(model, prompt_tokens, completion_tokens);
+    }
+}
+
+/// What a request would cost at list price, matching the longest known model prefix
+/// so dated variants like `gemini-2.5-flash-preview-05-20` are priced too
+pub fn estimated_cost(model: &str, prompt_tokens: i64, completion_tokens: i64) -> f64 {
+    if ollama_provider::model_name(model).is_some() {
+        return 0.0;
+    }
+    MODEL_PRICES
+        .iter()
+        .filter(|(prefix, _, _)| model.starts_with(prefix))
+        .max_by_key(|(prefix, _, _)| prefix.len())
+        .map(|(_, prompt_price, completion_price)| {
+            (prompt_tokens as f64 * prompt_price + completion_tokens as f64 * completion_price)
+                / 1_000_000.0
+        })
+        .unwrap_or(0.0)
+}
+
+/// The priced model with the lowest list price, what chloe falls back to when she has to
+/// answer cheaply
+pub fn cheapest_priced_model() -> &'static str {
+    MODEL_PRICES
+        .iter()
+        .min_by(|a, b| (a.1 + a.2).total_cmp(&(b.1 + b.2)))
+        .map(|(model, _, _)| *model)
+        .unwrap_or("gemini-2.0-flash-lite")
+}
+
+/// Longest range one export covers
+pub const MAX_EXPORT_DAYS: i64 = 366;
+
+/// One day of one user's usage of one model, as exported for billing
+#[derive(Debug, Clone, PartialEq)]
+pub struct UsageRow {
+    pub day: NaiveDate,
+    /// 0 when the request had no user
+    pub user_id: u64,
+    pub model: String,
+    pub prompt_tokens: i64,
+    pub completion_tokens: i64,
+    pub requests: i64,
+}
+
+impl UsageRow {
+    pub fn cost_usd(&self) -> f64 {
+        estimated_cost(&self.model, self.prompt_tokens, self.completion_tokens)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ExportFormat {
+    Csv,
+    Json,
+}
+
+impl ExportFormat {
+    pub fn from_str(s: &str) -> Option<Self> {
+        match s.trim().to_lowercase().as_str() {
+            "csv" => Some(Self::Csv),
+            "json" => Some(Self::Json),
+            _ => None,
+        }
+    }
+
+    pub fn extension(&self) -> &'static str {
+        match self {
+            Self::Csv => "csv",
+            Self::Json => "json",
+        }
+    }
+
+    /// The rows as a file in this format
+    pub fn render(&self, rows: &[UsageRow]) -> String {
+        match self {
+            Self::Csv => usage_csv(rows),
+            Self::Json => usage_json(rows).to_string(),
+        }
+    }
+}
+
+/// Days from `from` through `to` as `YYYY-MM-DD`, at most `MAX_EXPORT_DAYS` of them
+pub fn parse_date_range(from: &str, to: &str) -> Result<(NaiveDate, NaiveDate), String> {
+    let parse = |date: &str| {
+        NaiveDate::parse_from_str(date.trim(), "%Y-%m-%d")
+            .map_err(|_| format!("`{}` isn't a date like 2025-10-01", date.trim()))
+    };
+    let (from, to) = (parse(from)?, parse(to)?);
+    if from > to {
+        return Err("the range ends before it starts".to_string());
+    }
+    if (to - from).num_days() >= MAX_EXPORT_DAYS {
+        return Err(format!(
+            "exports cover at most {} days at a time",
+            MAX_EXPORT_DAYS
+        ));
+    }
+    Ok((from, to))
+}
+
+pub fn usage_csv(rows: &[UsageRow]) -> String {
+    let mut csv = String::from(
+        "day,user_id,model,prompt_tokens,completion_tokens,requests,estimated_cost_usd\n",
+    );
+    for row in rows {
+        csv.push_str(&format!(
+            "{},{},{},{},{},{},{:.6}\n",
+            row.day,
+            row.user_id,
+            csv_field(&row.model),
+            row.prompt_tokens,
+            row.completion_tokens,
+            row.requests,
+            row.cost_usd()
+        ));
+    }
+    csv
+}
+
+pub fn usage_json(rows: &[UsageRow]) -> Value {
+    Value::Array(
+        rows.iter()
+            .map(|row| {
+                json!({
+                    "day": row.day.to_string(),
+                    // as a string, javascript can't hold a snowflake in a number
+                    "user_id": row.user_id.to_string(),
+                    "model": row.model,
+                    "prompt_tokens": row.prompt_tokens,
+                    "completion_tokens": row.completion_tokens,
+                    "requests": row.requests,
+                    "estimated_cost_usd": row.cost_usd(),
+                })
+            })
+            .collect(),
+    )
+}
+
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+/// Daily token caps from the guild setting `tokenBudget`, e.g.
+/// `{"guildDaily": 2000000, "userDaily": 50000}`. Days run midnight to midnight UTC.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct TokenBudget {
+    pub guild_daily: Option<i64>,
+    pub user_daily: Option<i64>,
+}
+
+/// Whose budget ran out
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum BudgetScope {
+    Guild,
+    User,
+}
+
+impl TokenBudget {
+    pub fn from_setting(setting: Option<&Value>) -> Self {
+        let Some(setting) = setting else {
+            return Self::default();
+        };
+        let cap = |key: &str| {
+            setting
+                .get(key)
+                .and_then(Value::as_i64)
+                .filter(|&cap| cap > 0)
+        };
+        Self {
+            guild_daily: cap("guildDaily"),
+            user_daily: cap("userDaily"),
+        }
+    }
+
+    pub fn is_unlimited(&self) -> bool {
+        self.guild_daily.is_none() && self.user_daily.is_none()
+    }
+
+    /// The budget already used up by today's tokens, the guild's checked first
+    pub fn exceeded(&self, guild_tokens: i64, user_tokens: i64) -> Option<BudgetScope> {
+        if self.guild_daily.is_some_and(|cap| guild_tokens >= cap) {
+            return Some(BudgetScope::Guild);
+        }
+        if self.user_daily.is_some_and(|cap| user_tokens >= cap) {
+            return Some(BudgetScope::User);
+        }
+        None
+    }
+}
+
+/// Daily token counts per guild, user and model in `chloe_usage`, for billing and for
+/// spotting anyone burning through the quota. Guild and user are discord snowflakes,
+/// 0 when a request had none (DMs, background jobs).
+#[derive(Clone)]
+pub struct UsageService {
+    db_pool: PgPool,
+}
+
+impl UsageService {
+    pub fn new(db_pool: PgPool) -> Self {
+        Self { db_pool }
+    }
+
+    /// Add one response's token counts to today's row, in the background so replies
+    /// never wait on it
+    pub fn record(
+        &self,
+        guild_id: Option<u64>,
+        user_id: Option<u64>,
+        model: &str,
+        usage: &UsageMetadata,
+    ) {
+        let prompt_tokens = usage.prompt_token_count.unwrap_or(0) as i64;
+        let completion_tokens = usage.candidates_token_count.unwrap_or(0) as i64;
+        let guild_id = guild_id.unwrap_or(0) as i64;
+        let user_id = user_id.unwrap_or(0) as i64;
+        let model = model.to_string();
+        let db_pool = self.db_pool.clone();
+
+        tokio::spawn(async move {
+            let result = sqlx::query(
+                "INSERT INTO chloe_usage (guild_id, user_id, model, day, prompt_tokens, completion_tokens, requests)
+                 VALUES ($1, $2, $3, (CURRENT_TIMESTAMP AT TIME ZONE 'UTC')::date, $4, $5, 1)
+                 ON CONFLICT (guild_id, user_id, model, day) DO UPDATE SET
+                     prompt_tokens = chloe_usage.prompt_tokens + EXCLUDED.prompt_tokens,
+                     completion_tokens = chloe_usage.completion_tokens + EXCLUDED.completion_tokens,
+                     requests = chloe_usage.requests + 1",
+            )
+            .bind(guild_id)
+            .bind(user_id)
+            .bind(&model)
+            .bind(prompt_tokens)
+            .bind(completion_tokens)
+            .execute(&db_pool)
+            .await;
+
+            if let Err(e) = result {
+                error!(
+                    event = "usage_record_failed",
+                    guild_id = guild_id,
+                    model = %model,
+                    error = ?e,
+                    "Failed to record token usage"
+                );
+            }
+        });
+    }
+
+    /// Tokens the guild, and the user within it, have used so far today (UTC)
+    pub async fn tokens_today(
+        &self,
+        guild_id: u64,
+        user_id: u64,
+    ) -> Result<(i64, i64), sqlx::Error> {
+        let row = sqlx::query(
+            "SELECT COALESCE(SUM(prompt_tokens + completion_tokens), 0)::BIGINT AS guild_tokens,
+                    COALESCE(SUM(prompt_tokens + completion_tokens) FILTER (WHERE user_id = $2), 0)::BIGINT AS user_tokens
+             FROM chloe_usage
+             WHERE guild_id = $1 AND day = (CURRENT_TIMESTAMP AT TIME ZONE 'UTC')::date",
+        )
+        .bind(guild_id as i64)
+        .bind(user_id as i64)
+        .fetch_one(&self.db_pool)
+        .await?;
+        Ok((row.get("guild_tokens"), row.get("user_tokens")))
+    }
+
+    /// A guild's usage over the last `days` days, today included
+    pub async fn guild_usage(&self, guild_id: u64, days: i32) -> Result<UsageTotals, sqlx::Error> {
+        let rows = sqlx::query(
+            "SELECT model, SUM(prompt_tokens)::BIGINT AS prompt_tokens,
+                    SUM(completion_tokens)::BIGINT AS completion_tokens, SUM(requests)::BIGINT AS requests
+             FROM chloe_usage
+             WHERE guild_id = $1 AND day > (CURRENT_TIMESTAMP AT TIME ZONE 'UTC')::date - $2
+             GROUP BY model",
+        )
+        .bind(guild_id as i64)
+        .bind(days)
+        .fetch_all(&self.db_pool)
+        .await?;
+        Ok(sum_rows(&rows))
+    }
+
+    /// A user's usage across every guild over the last `days` days
+    pub async fn user_usage(&self, user_id: u64, days: i32) -> Result<UsageTotals, sqlx::Error> {
+        let rows = sqlx::query(
+            "SELECT model, SUM(prompt_tokens)::BIGINT AS prompt_tokens,
+                    SUM(completion_tokens)::BIGINT AS completion_tokens, SUM(requests)::BIGINT AS requests
+             FROM chloe_usage
+             WHERE user_id = $1 AND day > (CURRENT_TIMESTAMP AT TIME ZONE 'UTC')::date - $2
+             GROUP BY model",
+        )
+        .bind(user_id as i64)
+        .bind(days)
+        .fetch_all(&self.db_pool)
+        .await?;
+        Ok(sum_rows(&rows))
+    }
+
+    /// Every usage row of a guild from `from` through `to`, oldest first
+    pub async fn guild_rows(
+        &self,
+        guild_id: u64,
+        from: NaiveDate,
+        to: NaiveDate,
+    ) -> Result<Vec<UsageRow>, sqlx::Error> {
+        let rows = sqlx::query(
+            "SELECT day, user_id, model, prompt_tokens, completion_tokens, requests
+             FROM chloe_usage
+             WHERE guild_id = $1 AND day BETWEEN $2 AND $3
+             ORDER BY day, user_id, model",
+        )
+        .bind(guild_id as i64)
+        .bind(from)
+        .bind(to)
+        .fetch_all(&self.db_pool)
+        .await?;
+
+        Ok(rows
+            .iter()
+            .map(|row| UsageRow {
+                day: row.get("day"),
+                user_id: row.get::<i64, _>("user_id") as u64,
+                model: row.get("model"),
+                prompt_tokens: row.get("prompt_tokens"),
+                completion_tokens: row.get("completion_tokens"),
+                requests: row.get("requests"),
+            })
+            .collect())
+    }
+
+    /// Heaviest users of a guild over the last `days` days, by total tokens
+    pub async fn top_users(
+        &self,
+        guild_id: u64,
+        days: i32,
+        limit: i64,
+    ) -> Result<Vec<(u64, UsageTotals)>, sqlx::Error> {
+        let rows = sqlx::query(
+            "SELECT user_id, model, SUM(prompt_tokens)::BIGINT AS prompt_tokens,
+                    SUM(completion_tokens)::BIGINT AS completion_tokens, SUM(requests)::BIGINT AS requests
+             FROM chloe_usage
+             WHERE guild_id = $1 AND user_id <> 0 AND day > (CURRENT_TIMESTAMP AT TIME ZONE 'UTC')::date - $2
+             GROUP BY user_id, model",
+        )
+        .bind(guild_id as i64)
+        .bind(days)
+        .fetch_all(&self.db_pool)
+        .await?;
+
+        let mut by_user: Vec<(u64, UsageTotals)> = Vec::new();
+        for row in &rows {
+            let user_id = row.get::<i64, _>("user_id") as u64;
+            let index = match by_user.iter().position(|(id, _)| *id == user_id) {
+                Some(index) => index,
+                None => {
+                    by_user.push((user_id, UsageTotals::default()));
+                    by_user.len() - 1
+                }
+            };
+            add_row(&mut by_user[index].1, row);
+        }
+        by_user.sort_by_key(|(_, totals)| {
+            std::cmp::Reverse(totals.prompt_tokens + totals.completion_tokens)
+        });
+        by_user.truncate(limit.max(0) as usize);
+        Ok(by_user)
+    }
+}
+
+fn sum_rows(rows: &[sqlx::postgres::PgRow]) -> UsageTotals {
+    let mut totals = UsageTotals::default();
+    for row in rows {
+        add_row(&mut totals, row);
+    }
+    totals
+}
+
+fn add_row(totals: &mut UsageTotals, row: &sqlx::postgres::PgRow) {
+    totals.add(
+        row.get::<&str, _>("model"),
+        row.get("prompt_tokens"),
+        row.get("completion_tokens"),
+        row.get("requests"),
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_token_budget() {
+        let setting = serde_json::json!({"guildDaily": 1000, "userDaily": 0});
+        let budget = TokenBudget::from_setting(Some(&setting));
+        // a zero cap means no cap rather than locking everyone out
+        assert_eq!(budget.user_daily, None);
+        assert_eq!(budget.exceeded(999, 5000), None);
+        assert_eq!(budget.exceeded(1000, 0), Some(BudgetScope::Guild));
+
+        let setting = serde_json::json!({"guildDaily": 1000, "userDaily": 100});
+        let budget = TokenBudget::from_setting(Some(&setting));
+        assert_eq!(budget.exceeded(100, 100), Some(BudgetScope::User));
+        assert!(TokenBudget::from_setting(None).is_unlimited());
+    }
+
+    #[test]
+    fn test_estimated_cost() {
+        assert_eq!(estimated_cost("gemini-2.5-pro", 1_000_000, 0), 1.25);
+        // the longest prefix wins, flash-lite isn't priced as flash
+        assert_eq!(estimated_cost("gemini-2.5-flash-lite", 0, 1_000_000), 0.40);
+        assert_eq!(
+            estimated_cost("gemini-2.5-flash-preview-05-20", 1_000_000, 1_000_000),
+            2.80
+        );
+        assert_eq!(
+            estimated_cost("ollama:llama3.1:8b", 1_000_000, 1_000_000),
+            0.0
+        );
+        assert_eq!(estimated_cost("some-new-model", 1_000_000, 1_000_000), 0.0);
+        assert_eq!(cheapest_priced_model(), "gemini-2.0-flash-lite");
+    }
+
+    #[test]
+    fn test_usage_export() {
+        let rows = vec![UsageRow {
+            day: NaiveDate::from_ymd_opt(2025, 10, 1).unwrap(),
+            user_id: 175928847299117063,
+            model: "gemini-2.5-pro".to_string(),
+            prompt_tokens: 1_000_000,
+            completion_tokens: 0,
+            requests: 3,
+        }];
+        assert_eq!(
+            usage_csv(&rows),
+            "day,user_id,model,prompt_tokens,completion_tokens,requests,estimated_cost_usd\n\
+             2025-10-01,175928847299117063,gemini-2.5-pro,1000000,0,3,1.250000\n"
+        );
+        let json = usage_json(&rows);
+        assert_eq!(json[0]["user_id"], "175928847299117063");
+        assert_eq!(json[0]["estimated_cost_usd"], 1.25);
+        assert_eq!(csv_field("a,\"b\""), "\"a,\"\"b\"\"\"");
+    }
+
+    #[test]
+    fn test_parse_date_range() {
+        assert_eq!(
+            parse_date_range("2025-10-01", " 2025-10-31 "),
+            Ok((
+                NaiveDate::from_ymd_opt(2025, 10, 1).unwrap(),
+                NaiveDate::from_ymd_opt(2025, 10, 31).unwrap()
+            ))
+        );
+        assert!(parse_date_range("2025-10-31", "2025-10-01").is_err());
+        assert!(parse_date_range("yesterday", "2025-10-01").is_err());
+        assert!(parse_date_range("2024-01-01", "2025-10-01").is_err());
+    }
+}
@@ -0,0 +1,145 @@
+use crate::services::semantic_memory::SemanticMemory;
+use sqlx::{PgPool, Row};
+use std::sync::Arc;
+use tracing::{info, warn};
+
+/// Longest fact chloe will store, anything longer is a summary she should shorten
+pub const MAX_FACT_LENGTH: usize = 300;
+
+/// Facts kept per user and guild, the oldest are forgotten past this
+pub const MAX_FACTS_PER_USER: i64 = 50;
+
+/// Short facts members told chloe about themselves, per guild in `chloe_user_memories`,
+/// so she still knows them in later conversations
+pub struct UserMemoryService {
+    db_pool: PgPool,
+    semantic: Option<Arc<SemanticMemory>>,
+}
+
+impl UserMemoryService {
+    pub fn new(db_pool: PgPool) -> Self {
+        Self {
+            db_pool,
+            semantic: None,
+        }
+    }
+
+    /// Also embed stored facts so they can be retrieved by similarity
+    pub fn with_semantic_memory(mut self, semantic: Option<Arc<SemanticMemory>>) -> Self {
+        self.semantic = semantic;
+        self
+    }
+
+    /// Store a fact, remembering the same one again only refreshes it
+    pub async fn remember(
+        &self,
+        guild_id: u64,
+        user_id: u64,
+        fact: &str,
+    ) -> Result<(), sqlx::Error> {
+        let result = sqlx::query(
+            "INSERT INTO chloe_user_memories (guild_id, user_id, fact)
+             SELECT g.id, $2, $3 FROM chloe_guilds g WHERE g.snowflake_id = $1
+             ON CONFLICT (guild_id, user_id, fact) DO UPDATE SET created_at = CURRENT_TIMESTAMP
+             RETURNING id",
+        )
+        .bind(guild_id as i64)
+        .bind(user_id as i64)
+        .bind(fact)
+        .fetch_optional(&self.db_pool)
+        .await?;
+
+        let Some(row) = result else {
+            return Err(sqlx::Error::RowNotFound); // the guild was never registered
+        };
+        if let Some(semantic) = self.semantic.clone() {
+            let memory_id: String = row.get("id");
+            let fact = fact.to_string();
+            tokio::spawn(async move {
+                let indexed = semantic
+                    .index_fact(&memory_id, guild_id, user_id, &fact)
+                    .await;
+                if let Err(e) = indexed {
+                    warn!(
+                        event = "user_memory_embed_failed",
+                        guild_id = guild_id,
+                        user_id = user_id,
+                        error = ?e,
+                        "Failed to embed user memory"
+                    );
+                }
+            });
+        }
+
+        sqlx::query(
+            "DELETE FROM chloe_user_memories WHERE id IN (
+                 SELECT m.id FROM chloe_user_memories m
+                 JOIN chloe_guilds g ON m.guild_id = g.id
+                 WHERE g.snowflake_id = $1 AND m.user_id = $2
+                 ORDER BY m.created_at DESC
+                 OFFSET $3
+             )",
+        )
+        .bind(guild_id as i64)
+        .bind(user_id as i64)
+        .bind(MAX_FACTS_PER_USER)
+        .execute(&self.db_pool)
+        .await?;
+
+        info!(
+            event = "user_memory_stored",
+            guild_id = guild_id,
+            user_id = user_id,
+            "Stored user memory"
+        );
+        Ok(())
+    }
+
+    /// The user's facts in this guild, newest first
+    pub async fn recall(
+        &self,
+        guild_id: u64,
+        user_id: u64,
+        limit: i64,
+    ) -> Result<Vec<String>, sqlx::Error> {
+        let rows = sqlx::query(
+            "SELECT m.fact FROM chloe_user_memories m
+             JOIN chloe_guilds g ON m.guild_id = g.id
+             WHERE g.snowflake_id = $1 AND m.user_id = $2
+             ORDER BY m.created_at DESC
+             LIMIT $3",
+        )
+        .bind(guild_id as i64)
+        .bind(user_id as i64)
+        .bind(limit)
+        .fetch_all(&self.db_pool)
+        .await?;
+
+        Ok(rows.iter().map(|row| row.get("fact")).collect())
+    }
+}
+
+/// Collapse whitespace so the same fact worded with different spacing is stored once.
+/// None when there's nothing to store or it's too long.
+pub fn normalize_fact(fact: &str) -> Option<String> {
+    let fact = fact.split_whitespace().collect::<Vec<_>>().join(" ");
+    if fact.is_empty() || fact.chars().count() > MAX_FACT_LENGTH {
+        return None;
+    }
+    Some(fact)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_normalize_fact() {
+        assert_eq!(
+            normalize_fact("  my birthday is\n march 3rd "),
+            Some("my birthday is march 3rd".to_string())
+        );
+        assert_eq!(normalize_fact("   "), None);
+        assert_eq!(normalize_fact(&"a".repeat(MAX_FACT_LENGTH + 1)), None);
+    }
+}
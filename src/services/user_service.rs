@@ -551,4 +551,141 @@ impl UserService {
 
         Ok(Some(auth_info))
     }
+
+    /// Grant or revoke superadmin, recording who did it in the audit table. `actor` is
+    /// None for the `SUPERADMIN_IDS` bootstrap. Returns false when nothing changed.
+    pub async fn set_superadmin(
+        &self,
+        user_snowflake_id: i64,
+        superadmin: bool,
+        actor: Option<i64>,
+    ) -> Result<bool, sqlx::Error> {
+        let mut tx = self.db_pool.begin().await?;
+
+        let changed = if superadmin {
+            sqlx::query(
+                r#"
+                INSERT INTO chloe_users (snowflake_id, superadmin)
+                VALUES ($1, true)
+                ON CONFLICT (snowflake_id)
+                DO UPDATE SET
+                    superadmin = true,
+                    modified_at = CURRENT_TIMESTAMP
+                WHERE chloe_users.superadmin = false
+                "#,
+            )
+            .bind(user_snowflake_id)
+            .execute(&mut *tx)
+            .await?
+        } else {
+            sqlx::query(
+                "UPDATE chloe_users SET superadmin = false, modified_at = CURRENT_TIMESTAMP WHERE snowflake_id = $1 AND superadmin = true",
+            )
+            .bind(user_snowflake_id)
+            .execute(&mut *tx)
+            .await?
+        }
+        .rows_affected()
+            > 0;
+
+        if changed {
+            sqlx::query(
+                "INSERT INTO chloe_superadmin_audit (user_id, actor_id, action) VALUES ($1, $2, $3)",
+            )
+            .bind(user_snowflake_id)
+            .bind(actor)
+            .bind(if superadmin { "promote" } else { "demote" })
+            .execute(&mut *tx)
+            .await?;
+        }
+        tx.commit().await?;
+
+        info!(
+            event = "superadmin_changed",
+            user_snowflake_id = user_snowflake_id,
+            superadmin = superadmin,
+            actor = ?actor,
+            changed = changed,
+            "Superadmin status set"
+        );
+
+        Ok(changed)
+    }
+
+    /// Whether the user opted into chatting with chloe in DMs
+    pub async fn dm_chat_enabled(&self, user_snowflake_id: i64) -> Result<bool, sqlx::Error> {
+        let enabled = sqlx::query_scalar::<_, bool>(
+            "SELECT dm_chat FROM chloe_users WHERE snowflake_id = $1",
+        )
+        .bind(user_snowflake_id)
+        .fetch_optional(&self.db_pool)
+        .await?;
+        Ok(enabled.unwrap_or(false))
+    }
+
+    /// Opt the user in or out of chatting with chloe in DMs
+    pub async fn set_dm_chat(
+        &self,
+        user_snowflake_id: i64,
+        enabled: bool,
+    ) -> Result<(), sqlx::Error> {
+        sqlx::query(
+            r#"
+            INSERT INTO chloe_users (snowflake_id, dm_chat)
+            VALUES ($1, $2)
+            ON CONFLICT (snowflake_id)
+            DO UPDATE SET
+                dm_chat = EXCLUDED.dm_chat,
+                modified_at = CURRENT_TIMESTAMP
+            "#,
+        )
+        .bind(user_snowflake_id)
+        .bind(enabled)
+        .execute(&self.db_pool)
+        .await?;
+
+        info!(
+            event = "dm_chat_changed",
+            user_snowflake_id = user_snowflake_id,
+            enabled = enabled,
+            "DM chat opt-in set"
+        );
+        Ok(())
+    }
+
+    /// Promote everyone listed in `SUPERADMIN_IDS`, so a fresh deploy has someone who can
+    /// use `/admin`. Nobody is demoted for being left out of the list.
+    pub async fn bootstrap_superadmins(&self) -> Result<usize, sqlx::Error> {
+        let ids = parse_user_ids(&std::env::var("SUPERADMIN_IDS").unwrap_or_default());
+        let mut promoted = 0;
+        for id in ids {
+            if self.set_superadmin(id, true, None).await? {
+                promoted += 1;
+            }
+        }
+        Ok(promoted)
+    }
+}
+
+/// Comma or whitespace separated user ids, skipping anything that isn't one
+fn parse_user_ids(value: &str) -> Vec<i64> {
+    value
+        .split(|c: char| c == ',' || c.is_whitespace())
+        .filter_map(|id| id.trim().parse::<u64>().ok())
+        .map(|id| id as i64)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_user_ids() {
+        assert_eq!(
+            parse_user_ids("123, 456 nope\n789,"),
+            vec![123, 456, 789]
+        );
+        assert!(parse_user_ids("").is_empty());
+    }
 }
@@ -1,16 +1,27 @@
+use crate::services::change_events::{ChangeEvent, ChangeEvents};
+use crate::services::content_safety::SafetyThresholds;
 use sqlx::{PgPool, Row};
 use std::sync::Arc;
 use tokio::sync::RwLock;
-use tracing::info;
+use tokio::time::{Duration, interval};
+use tracing::{info, warn};
+
+/// How often each instance checks whether another one activated a different prompt
+const PROMPT_WATCH_INTERVAL: Duration = Duration::from_secs(15);
 
 #[derive(Clone)]
 pub struct Settings {
     global_data: Arc<RwLock<GlobalSettings>>,
+    change_events: Option<Arc<ChangeEvents>>,
 }
 
 #[derive(Clone, Debug)]
 pub struct GlobalSettings {
     pub prompt: String,
+    /// Version of the active prompt in chloe_prompts, 0 for the built-in default
+    pub prompt_version: i32,
+    /// Block thresholds no guild's content safety settings can go below
+    pub safety_floor: SafetyThresholds,
 }
 
 impl Settings {
@@ -18,10 +29,19 @@ impl Settings {
         Self {
             global_data: Arc::new(RwLock::new(GlobalSettings {
                 prompt: "You're Chloe, a discord bot.".to_string(),
+                prompt_version: 0,
+                safety_floor: SafetyThresholds::default_floor(),
             })),
+            change_events: None,
         }
     }
 
+    /// Also publish every prompt activation
+    pub fn with_change_events(mut self, change_events: Arc<ChangeEvents>) -> Self {
+        self.change_events = Some(change_events);
+        self
+    }
+
     pub async fn load_from_database(&self, db_pool: &PgPool) -> Result<(), sqlx::Error> {
         info!(
             event = "settings_load_started",
@@ -50,7 +70,7 @@ impl Settings {
         );
 
         if let Ok(row) = sqlx::query(
-            "SELECT p.content as prompt FROM chloe_settings s 
+            "SELECT p.content as prompt, p.version, s.safety_floor FROM chloe_settings s 
              JOIN chloe_prompts p ON s.prompt_id = p.id 
              WHERE s.id = 1",
         )
@@ -58,10 +78,16 @@ impl Settings {
         .await
         {
             let prompt: String = row.get("prompt");
+            let prompt_version: i32 = row.get("version");
+            let safety_floor = SafetyThresholds::from_setting(
+                row.get::<Option<serde_json::Value>, _>("safety_floor").as_ref(),
+                SafetyThresholds::default_floor(),
+            );
 
             info!(
                 event = "global_settings_db_loaded",
                 prompt_length = prompt.len(),
+                prompt_version = prompt_version,
                 "Loaded prompt from database, acquiring write lock"
             );
 
@@ -74,9 +100,12 @@ impl Settings {
             {
                 Ok(mut data) => {
                     data.prompt = prompt.clone();
+                    data.prompt_version = prompt_version;
+                    data.safety_floor = safety_floor;
                     info!(
                         event = "global_settings_loaded",
                         prompt_length = prompt.len(),
+                        prompt_version = prompt_version,
                         "Global settings loaded from database"
                     );
                 }
@@ -100,6 +129,76 @@ impl Settings {
         Ok(())
     }
 
+    pub async fn prompt_version(&self) -> i32 {
+        self.global_data.read().await.prompt_version
+    }
+
+    pub async fn safety_floor(&self) -> SafetyThresholds {
+        self.global_data.read().await.safety_floor
+    }
+
+    /// Store a new global content safety floor, taking effect here right away and on
+    /// other instances the next time they reload settings
+    pub async fn set_safety_floor(
+        &self,
+        db_pool: &PgPool,
+        floor: SafetyThresholds,
+    ) -> Result<(), sqlx::Error> {
+        sqlx::query(
+            "UPDATE chloe_settings SET safety_floor = $1, modified_at = CURRENT_TIMESTAMP WHERE id = 1",
+        )
+        .bind(floor.to_setting())
+        .execute(db_pool)
+        .await?;
+        self.global_data.write().await.safety_floor = floor;
+
+        info!(
+            event = "safety_floor_changed",
+            floor = %floor.to_setting(),
+            "Global content safety floor set"
+        );
+        Ok(())
+    }
+
+    /// Poll the active prompt version and reload when it changes, so activations made
+    /// by another instance or straight in the database take effect here too
+    pub async fn watch_active_prompt(&self, db_pool: PgPool) {
+        let mut ticker = interval(PROMPT_WATCH_INTERVAL);
+        loop {
+            ticker.tick().await;
+
+            let active_version = match self.get_active_prompt(&db_pool).await {
+                Ok(Some((_, version))) => version,
+                Ok(None) => continue,
+                Err(e) => {
+                    warn!(
+                        event = "prompt_watch_failed",
+                        error = ?e,
+                        "Failed to check the active prompt version"
+                    );
+                    continue;
+                }
+            };
+
+            let loaded_version = self.prompt_version().await;
+            if active_version != loaded_version {
+                info!(
+                    event = "prompt_hot_reload",
+                    old_version = loaded_version,
+                    new_version = active_version,
+                    "Active prompt changed, reloading"
+                );
+                if let Err(e) = self.reload_global_settings(&db_pool).await {
+                    warn!(
+                        event = "prompt_hot_reload_failed",
+                        error = ?e,
+                        "Failed to reload the active prompt"
+                    );
+                }
+            }
+        }
+    }
+
     pub async fn reload_global_settings(&self, db_pool: &PgPool) -> Result<(), sqlx::Error> {
         info!(
             event = "global_settings_reload_started",
@@ -148,6 +247,44 @@ impl Settings {
         Ok(prompt_id)
     }
 
+    /// Id and version of the currently active prompt
+    pub async fn get_active_prompt(
+        &self,
+        db_pool: &PgPool,
+    ) -> Result<Option<(String, i32)>, sqlx::Error> {
+        let row = sqlx::query(
+            "SELECT p.id, p.version FROM chloe_settings s 
+             JOIN chloe_prompts p ON s.prompt_id = p.id 
+             WHERE s.id = 1",
+        )
+        .fetch_optional(db_pool)
+        .await?;
+
+        Ok(row.map(|row| (row.get("id"), row.get("version"))))
+    }
+
+    pub async fn find_prompt_by_version(
+        &self,
+        db_pool: &PgPool,
+        version: i32,
+    ) -> Result<Option<String>, sqlx::Error> {
+        sqlx::query_scalar("SELECT id FROM chloe_prompts WHERE version = $1")
+            .bind(version)
+            .fetch_optional(db_pool)
+            .await
+    }
+
+    pub async fn get_prompt_content_by_version(
+        &self,
+        db_pool: &PgPool,
+        version: i32,
+    ) -> Result<Option<String>, sqlx::Error> {
+        sqlx::query_scalar("SELECT content FROM chloe_prompts WHERE version = $1")
+            .bind(version)
+            .fetch_optional(db_pool)
+            .await
+    }
+
     pub async fn activate_prompt_version(
         &self,
         db_pool: &PgPool,
@@ -189,6 +326,13 @@ impl Settings {
             prompt_id = %prompt_id,
             "Prompt version activated and settings reloaded"
         );
+        if let Some(change_events) = &self.change_events {
+            change_events
+                .publish(ChangeEvent::PromptActivated {
+                    prompt_id: prompt_id.to_string(),
+                })
+                .await;
+        }
 
         Ok(())
     }
@@ -0,0 +1,231 @@
+use serde_json::{Map, Number, Value};
+
+/// Parse JSON the way models tend to get it wrong: wrapped in a code fence, with single
+/// quotes, bare keys, trailing commas, python's True/None or closing brackets left off
+pub fn lenient_json(text: &str) -> Option<Value> {
+    let text = strip_code_fence(text.trim());
+    if let Ok(value) = serde_json::from_str(text) {
+        return Some(value);
+    }
+    let start = text.find(['{', '['])?;
+    serde_json::from_str(&normalize(&text[start..])).ok()
+}
+
+/// A call's arguments as the object its tool expects, None when they can't be salvaged.
+/// Arguments sent as a JSON string are parsed, and each argument the schema types is
+/// coerced to that type when it came as something else, e.g. `"3"` for an integer or
+/// an array sent as a JSON string.
+pub fn repair_arguments(args: &Value, schema: &Value) -> Option<Value> {
+    let mut args = match args {
+        Value::Object(args) => args.clone(),
+        Value::String(text) => match lenient_json(text)? {
+            Value::Object(args) => args,
+            _ => return None,
+        },
+        Value::Null => Map::new(),
+        _ => return None,
+    };
+
+    if let Some(properties) = schema.get("properties").and_then(Value::as_object) {
+        for (key, value) in args.iter_mut() {
+            let expected = properties
+                .get(key)
+                .and_then(|property| property.get("type"))
+                .and_then(Value::as_str);
+            if let Some(coerced) = expected.and_then(|expected| coerce(value, expected)) {
+                *value = coerced;
+            }
+        }
+    }
+    Some(Value::Object(args))
+}
+
+/// The value as the schema type, None when it already is one or can't become one
+fn coerce(value: &Value, expected: &str) -> Option<Value> {
+    match (expected, value) {
+        ("object", Value::String(text)) => lenient_json(text).filter(Value::is_object),
+        ("array", Value::String(text)) => lenient_json(text).filter(Value::is_array),
+        ("array", Value::Object(_)) => Some(Value::Array(vec![value.clone()])),
+        ("integer", Value::String(text)) => text.trim().parse::<i64>().ok().map(Value::from),
+        ("integer", Value::Number(number)) if number.as_i64().is_none() => number
+            .as_f64()
+            .filter(|number| number.fract() == 0.0)
+            .map(|number| Value::from(number as i64)),
+        ("number", Value::String(text)) => text
+            .trim()
+            .parse::<f64>()
+            .ok()
+            .and_then(Number::from_f64)
+            .map(Value::Number),
+        ("boolean", Value::String(text)) => match text.trim().to_lowercase().as_str() {
+            "true" | "yes" => Some(Value::Bool(true)),
+            "false" | "no" => Some(Value::Bool(false)),
+            _ => None,
+        },
+        ("string", Value::Number(_) | Value::Bool(_)) => Some(Value::String(value.to_string())),
+        _ => None,
+    }
+}
+
+fn strip_code_fence(text: &str) -> &str {
+    let Some(inner) = text.strip_prefix("```") else {
+        return text;
+    };
+    // drop the language tag on the opening line
+    let inner = inner.split_once('\n').map_or(inner, |(_, rest)| rest);
+    inner.trim_end().trim_end_matches("```").trim()
+}
+
+/// Rewrite almost-JSON into JSON, one character at a time so quotes inside strings are
+/// left alone
+fn normalize(text: &str) -> String {
+    let chars: Vec<char> = text.chars().collect();
+    let mut out = String::with_capacity(text.len());
+    let mut closers = Vec::new();
+    let mut i = 0;
+
+    while i < chars.len() {
+        let c = chars[i];
+        match c {
+            '"' | '\'' => {
+                out.push('"');
+                i += 1;
+                while i < chars.len() && chars[i] != c {
+                    match chars[i] {
+                        '\\' if i + 1 < chars.len() => {
+                            if c == '\'' && chars[i + 1] == '\'' {
+                                out.push('\'');
+                            } else {
+                                out.push('\\');
+                                out.push(chars[i + 1]);
+                            }
+                            i += 1;
+                        }
+                        // only reachable inside single quotes
+                        '"' => out.push_str("\\\""),
+                        '\n' => out.push_str("\\n"),
+                        other => out.push(other),
+                    }
+                    i += 1;
+                }
+                out.push('"');
+                i += 1;
+            }
+            '{' | '[' => {
+                closers.push(if c == '{' { '}' } else { ']' });
+                out.push(c);
+                i += 1;
+            }
+            '}' | ']' => {
+                drop_trailing_comma(&mut out);
+                closers.pop();
+                out.push(c);
+                i += 1;
+            }
+            c if c.is_ascii_digit() || c == '-' => {
+                while i < chars.len()
+                    && (chars[i].is_ascii_alphanumeric() || matches!(chars[i], '.' | '-' | '+'))
+                {
+                    out.push(chars[i]);
+                    i += 1;
+                }
+            }
+            c if c.is_alphabetic() || c == '_' => {
+                let start = i;
+                while i < chars.len() && (chars[i].is_alphanumeric() || chars[i] == '_') {
+                    i += 1;
+                }
+                let word: String = chars[start..i].iter().collect();
+                let is_key = chars[i..]
+                    .iter()
+                    .find(|c| !c.is_whitespace())
+                    .is_some_and(|&c| c == ':');
+                match word.as_str() {
+                    _ if is_key => out.push_str(&format!("\"{}\"", word)),
+                    "true" | "True" => out.push_str("true"),
+                    "false" | "False" => out.push_str("false"),
+                    "null" | "None" => out.push_str("null"),
+                    _ => out.push_str(&format!("\"{}\"", word)),
+                }
+            }
+            _ => {
+                out.push(c);
+                i += 1;
+            }
+        }
+    }
+
+    drop_trailing_comma(&mut out);
+    while let Some(closer) = closers.pop() {
+        out.push(closer);
+    }
+    out
+}
+
+fn drop_trailing_comma(out: &mut String) {
+    let trimmed = out.trim_end().len();
+    if out[..trimmed].ends_with(',') {
+        out.truncate(trimmed - 1);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_lenient_json() {
+        assert_eq!(
+            lenient_json("```json\n{\"query\": \"rust\",}\n```"),
+            Some(json!({"query": "rust"}))
+        );
+        assert_eq!(
+            lenient_json("{'content': 'she said \"hi\"', silent: True, reply_to: None"),
+            Some(json!({"content": "she said \"hi\"", "silent": true, "reply_to": null}))
+        );
+        assert_eq!(
+            lenient_json(
+                "sure! {\"fields\": [{\"name\": \"a\", \"value\": \"b\"},], \"n\": -1.5e2}"
+            ),
+            Some(json!({"fields": [{"name": "a", "value": "b"}], "n": -150.0}))
+        );
+        assert_eq!(lenient_json("not json at all"), None);
+    }
+
+    #[test]
+    fn test_repair_arguments() {
+        let schema = json!({
+            "type": "object",
+            "properties": {
+                "duration_minutes": {"type": "integer"},
+                "fields": {"type": "array"},
+                "silent": {"type": "boolean"},
+                "user_id": {"type": "string"}
+            }
+        });
+
+        let repaired = repair_arguments(
+            &json!(
+                "{\"duration_minutes\": \"10\", \"fields\": \"[{'name': 'a'}]\", \"silent\": \"true\", \"user_id\": 123,}"
+            ),
+            &schema,
+        );
+        assert_eq!(
+            repaired,
+            Some(json!({
+                "duration_minutes": 10,
+                "fields": [{"name": "a"}],
+                "silent": true,
+                "user_id": "123"
+            }))
+        );
+
+        // already fine, nothing changes
+        let args = json!({"duration_minutes": 5, "other": "x"});
+        assert_eq!(repair_arguments(&args, &schema), Some(args));
+        assert_eq!(repair_arguments(&Value::Null, &schema), Some(json!({})));
+        assert_eq!(repair_arguments(&json!("[1, 2]"), &schema), None);
+        assert_eq!(repair_arguments(&json!("garbage"), &schema), None);
+    }
+}
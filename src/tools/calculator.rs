@@ -2,6 +2,9 @@ use super::Tool;
 use serde_json::{Value, json};
 use std::collections::HashMap;
 
+/// Longest expression evaluated, also bounds how deep the parser recurses
+const MAX_EXPRESSION_LENGTH: usize = 500;
+
 pub struct CalculatorTool;
 
 #[async_trait::async_trait]
@@ -11,7 +14,7 @@ impl Tool for CalculatorTool {
     }
 
     fn description(&self) -> &str {
-        "Perform mathematical calculations. Supports basic arithmetic operations."
+        "Evaluate a mathematical expression. Supports + - * / % ^, parentheses, the constants pi and e, and the functions sqrt, cbrt, abs, exp, ln, log (base 10, or log(x, base)), log2, sin, cos, tan, asin, acos, atan, floor, ceil, round, min and max. Angles are in radians."
     }
 
     fn parameters_schema(&self) -> Value {
@@ -20,7 +23,7 @@ impl Tool for CalculatorTool {
             "properties": {
                 "expression": {
                     "type": "string",
-                    "description": "The mathematical expression to evaluate (e.g., '2 + 2', '10 * 5')"
+                    "description": "The mathematical expression to evaluate (e.g., '2 + 2', '(3 + 4) * 2^3', 'sqrt(2) * sin(pi / 4)')"
                 }
             },
             "required": ["expression"]
@@ -37,53 +40,378 @@ impl Tool for CalculatorTool {
             .and_then(|v| v.as_str())
             .ok_or("Missing or invalid 'expression' parameter")?;
 
-        // Simple calculator - in a real implementation you'd use a proper math parser
-        match expression.trim() {
-            expr if expr.contains(" + ") => {
-                let parts: Vec<&str> = expr.split(" + ").collect();
-                if parts.len() == 2 {
-                    let a: f64 = parts[0].parse().map_err(|_| "Invalid number")?;
-                    let b: f64 = parts[1].parse().map_err(|_| "Invalid number")?;
-                    Ok(format!("{} + {} = {}", a, b, a + b))
-                } else {
-                    Err("Invalid addition expression".to_string())
+        let result = evaluate(expression)?;
+        Ok(format!("{} = {}", expression.trim(), format_number(result)))
+    }
+}
+
+/// Evaluate an arithmetic expression with the usual precedence: `^` binds tightest and
+/// to the right, then unary minus, then `* / %`, then `+ -`
+pub fn evaluate(expression: &str) -> Result<f64, String> {
+    if expression.len() > MAX_EXPRESSION_LENGTH {
+        return Err(format!(
+            "Expression is too long, keep it under {} characters",
+            MAX_EXPRESSION_LENGTH
+        ));
+    }
+    let tokens = tokenize(expression)?;
+    if tokens.is_empty() {
+        return Err("Empty expression".to_string());
+    }
+
+    let mut parser = Parser {
+        tokens,
+        position: 0,
+    };
+    let value = parser.expression()?;
+    if let Some(token) = parser.peek() {
+        return Err(format!("Unexpected {}", token));
+    }
+    if !value.is_finite() {
+        return Err("The result isn't a finite number".to_string());
+    }
+    Ok(value)
+}
+
+/// Up to 10 decimals without trailing zeros
+pub fn format_number(value: f64) -> String {
+    if value.fract() == 0.0 && value.abs() < 1e15 {
+        return format!("{}", value as i64);
+    }
+    let formatted = format!("{:.10}", value);
+    formatted
+        .trim_end_matches('0')
+        .trim_end_matches('.')
+        .to_string()
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Number(f64),
+    Identifier(String),
+    Operator(char),
+    LeftParen,
+    RightParen,
+    Comma,
+}
+
+impl std::fmt::Display for Token {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Token::Number(value) => write!(f, "number {}", value),
+            Token::Identifier(name) => write!(f, "'{}'", name),
+            Token::Operator(op) => write!(f, "'{}'", op),
+            Token::LeftParen => write!(f, "'('"),
+            Token::RightParen => write!(f, "')'"),
+            Token::Comma => write!(f, "','"),
+        }
+    }
+}
+
+fn tokenize(expression: &str) -> Result<Vec<Token>, String> {
+    let mut tokens = Vec::new();
+    let mut chars = expression.chars().peekable();
+
+    while let Some(&c) = chars.peek() {
+        match c {
+            c if c.is_whitespace() => {
+                chars.next();
+            }
+            '0'..='9' | '.' => {
+                let mut number = String::new();
+                while let Some(&c) = chars.peek() {
+                    // exponent notation like 1e-3
+                    let sign_after_exponent =
+                        (c == '-' || c == '+') && number.ends_with(['e', 'E']);
+                    if c.is_ascii_digit() || c == '.' || c == 'e' || c == 'E' || sign_after_exponent
+                    {
+                        number.push(c);
+                        chars.next();
+                    } else {
+                        break;
+                    }
                 }
+                let value = number
+                    .parse()
+                    .map_err(|_| format!("Invalid number '{}'", number))?;
+                tokens.push(Token::Number(value));
             }
-            expr if expr.contains(" - ") => {
-                let parts: Vec<&str> = expr.split(" - ").collect();
-                if parts.len() == 2 {
-                    let a: f64 = parts[0].parse().map_err(|_| "Invalid number")?;
-                    let b: f64 = parts[1].parse().map_err(|_| "Invalid number")?;
-                    Ok(format!("{} - {} = {}", a, b, a - b))
-                } else {
-                    Err("Invalid subtraction expression".to_string())
+            c if c.is_alphabetic() || c == '_' => {
+                let mut name = String::new();
+                while let Some(&c) = chars.peek() {
+                    if c.is_alphanumeric() || c == '_' {
+                        name.push(c);
+                        chars.next();
+                    } else {
+                        break;
+                    }
                 }
+                tokens.push(Token::Identifier(name.to_lowercase()));
             }
-            expr if expr.contains(" * ") => {
-                let parts: Vec<&str> = expr.split(" * ").collect();
-                if parts.len() == 2 {
-                    let a: f64 = parts[0].parse().map_err(|_| "Invalid number")?;
-                    let b: f64 = parts[1].parse().map_err(|_| "Invalid number")?;
-                    Ok(format!("{} * {} = {}", a, b, a * b))
+            '*' => {
+                chars.next();
+                // python style 2 ** 3
+                if chars.peek() == Some(&'*') {
+                    chars.next();
+                    tokens.push(Token::Operator('^'));
                 } else {
-                    Err("Invalid multiplication expression".to_string())
+                    tokens.push(Token::Operator('*'));
                 }
             }
-            expr if expr.contains(" / ") => {
-                let parts: Vec<&str> = expr.split(" / ").collect();
-                if parts.len() == 2 {
-                    let a: f64 = parts[0].parse().map_err(|_| "Invalid number")?;
-                    let b: f64 = parts[1].parse().map_err(|_| "Invalid number")?;
-                    if b == 0.0 {
-                        Err("Division by zero".to_string())
-                    } else {
-                        Ok(format!("{} / {} = {}", a, b, a / b))
-                    }
-                } else {
-                    Err("Invalid division expression".to_string())
+            '+' | '-' | '/' | '%' | '^' => {
+                chars.next();
+                tokens.push(Token::Operator(c));
+            }
+            '×' => {
+                chars.next();
+                tokens.push(Token::Operator('*'));
+            }
+            '÷' => {
+                chars.next();
+                tokens.push(Token::Operator('/'));
+            }
+            '(' => {
+                chars.next();
+                tokens.push(Token::LeftParen);
+            }
+            ')' => {
+                chars.next();
+                tokens.push(Token::RightParen);
+            }
+            ',' => {
+                chars.next();
+                tokens.push(Token::Comma);
+            }
+            _ => return Err(format!("Unexpected character '{}'", c)),
+        }
+    }
+    Ok(tokens)
+}
+
+struct Parser {
+    tokens: Vec<Token>,
+    position: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.position)
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.position).cloned();
+        self.position += 1;
+        token
+    }
+
+    fn expect(&mut self, expected: Token) -> Result<(), String> {
+        match self.next() {
+            Some(token) if token == expected => Ok(()),
+            Some(token) => Err(format!("Expected {} but found {}", expected, token)),
+            None => Err(format!("Expected {} at the end", expected)),
+        }
+    }
+
+    /// expression := term (('+' | '-') term)*
+    fn expression(&mut self) -> Result<f64, String> {
+        let mut value = self.term()?;
+        while let Some(Token::Operator(op @ ('+' | '-'))) = self.peek().cloned() {
+            self.next();
+            let rhs = self.term()?;
+            value = if op == '+' { value + rhs } else { value - rhs };
+        }
+        Ok(value)
+    }
+
+    /// term := unary (('*' | '/' | '%') unary)*
+    fn term(&mut self) -> Result<f64, String> {
+        let mut value = self.unary()?;
+        while let Some(Token::Operator(op @ ('*' | '/' | '%'))) = self.peek().cloned() {
+            self.next();
+            let rhs = self.unary()?;
+            if op != '*' && rhs == 0.0 {
+                return Err("Division by zero".to_string());
+            }
+            value = match op {
+                '*' => value * rhs,
+                '/' => value / rhs,
+                _ => value % rhs,
+            };
+        }
+        Ok(value)
+    }
+
+    /// unary := ('-' | '+') unary | power
+    fn unary(&mut self) -> Result<f64, String> {
+        match self.peek() {
+            Some(Token::Operator('-')) => {
+                self.next();
+                Ok(-self.unary()?)
+            }
+            Some(Token::Operator('+')) => {
+                self.next();
+                self.unary()
+            }
+            _ => self.power(),
+        }
+    }
+
+    /// power := primary ('^' unary)?, so 2^3^2 is 2^9 and 2^-1 is 0.5
+    fn power(&mut self) -> Result<f64, String> {
+        let base = self.primary()?;
+        if let Some(Token::Operator('^')) = self.peek() {
+            self.next();
+            let exponent = self.unary()?;
+            return Ok(base.powf(exponent));
+        }
+        Ok(base)
+    }
+
+    /// primary := number | constant | function '(' arguments ')' | '(' expression ')'
+    fn primary(&mut self) -> Result<f64, String> {
+        match self.next() {
+            Some(Token::Number(value)) => Ok(value),
+            Some(Token::LeftParen) => {
+                let value = self.expression()?;
+                self.expect(Token::RightParen)?;
+                Ok(value)
+            }
+            Some(Token::Identifier(name)) => {
+                if self.peek() != Some(&Token::LeftParen) {
+                    return constant(&name);
                 }
+                self.next();
+                let mut arguments = vec![self.expression()?];
+                while self.peek() == Some(&Token::Comma) {
+                    self.next();
+                    arguments.push(self.expression()?);
+                }
+                self.expect(Token::RightParen)?;
+                call(&name, &arguments)
             }
-            _ => Err("Unsupported expression. Use format like '2 + 2', '10 * 5', etc.".to_string()),
+            Some(token) => Err(format!("Unexpected {}", token)),
+            None => Err("Unexpected end of expression".to_string()),
         }
     }
 }
+
+fn constant(name: &str) -> Result<f64, String> {
+    match name {
+        "pi" | "π" => Ok(std::f64::consts::PI),
+        "e" => Ok(std::f64::consts::E),
+        "tau" => Ok(std::f64::consts::TAU),
+        _ => Err(format!("Unknown constant '{}'", name)),
+    }
+}
+
+fn call(name: &str, arguments: &[f64]) -> Result<f64, String> {
+    let unary = |f: fn(f64) -> f64| match arguments {
+        [x] => Ok(f(*x)),
+        _ => Err(format!("{} takes exactly one argument", name)),
+    };
+    match name {
+        "sqrt" => match arguments {
+            [x] if *x < 0.0 => Err("Square root of a negative number".to_string()),
+            _ => unary(f64::sqrt),
+        },
+        "cbrt" => unary(f64::cbrt),
+        "abs" => unary(f64::abs),
+        "exp" => unary(f64::exp),
+        "ln" => unary(f64::ln),
+        "log" => match arguments {
+            [x] => Ok(x.log10()),
+            [x, base] => Ok(x.log(*base)),
+            _ => Err("log takes one or two arguments".to_string()),
+        },
+        "log2" => unary(f64::log2),
+        "log10" => unary(f64::log10),
+        "sin" => unary(f64::sin),
+        "cos" => unary(f64::cos),
+        "tan" => unary(f64::tan),
+        "asin" => unary(f64::asin),
+        "acos" => unary(f64::acos),
+        "atan" => unary(f64::atan),
+        "floor" => unary(f64::floor),
+        "ceil" => unary(f64::ceil),
+        "round" => unary(f64::round),
+        "min" => arguments
+            .iter()
+            .copied()
+            .reduce(f64::min)
+            .ok_or_else(|| "min needs an argument".to_string()),
+        "max" => arguments
+            .iter()
+            .copied()
+            .reduce(f64::max)
+            .ok_or_else(|| "max needs an argument".to_string()),
+        _ => Err(format!("Unknown function '{}'", name)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_close(expression: &str, expected: f64) {
+        let value = evaluate(expression).unwrap();
+        assert!(
+            (value - expected).abs() < 1e-9,
+            "{} = {}, expected {}",
+            expression,
+            value,
+            expected
+        );
+    }
+
+    #[test]
+    fn test_precedence_and_parentheses() {
+        assert_close("2 + 3 * 4", 14.0);
+        assert_close("(2 + 3) * 4", 20.0);
+        assert_close("10 - 4 - 3", 3.0);
+        assert_close("2*3+4/2", 8.0);
+        assert_close("7 % 4", 3.0);
+        assert_close("((1))", 1.0);
+    }
+
+    #[test]
+    fn test_exponents_and_unary_minus() {
+        assert_close("2^3^2", 512.0);
+        assert_close("2 ** 10", 1024.0);
+        assert_close("-2^2", -4.0);
+        assert_close("(-2)^2", 4.0);
+        assert_close("2^-1", 0.5);
+        assert_close("--3", 3.0);
+        assert_close("1.5e3 + 1e-3", 1500.001);
+    }
+
+    #[test]
+    fn test_functions_and_constants() {
+        assert_close("sqrt(16)", 4.0);
+        assert_close("sin(pi / 2)", 1.0);
+        assert_close("log(1000)", 3.0);
+        assert_close("log(8, 2)", 3.0);
+        assert_close("ln(e)", 1.0);
+        assert_close("max(1, 5, 3) - min(2, -1)", 6.0);
+        assert_close("SQRT(2) * sqrt(2)", 2.0);
+    }
+
+    #[test]
+    fn test_errors() {
+        assert!(evaluate("").is_err());
+        assert!(evaluate("1 / 0").is_err());
+        assert!(evaluate("(1 + 2").is_err());
+        assert!(evaluate("1 +").is_err());
+        assert!(evaluate("2 3").is_err());
+        assert!(evaluate("foo(1)").is_err());
+        assert!(evaluate("sqrt(-1)").is_err());
+        assert!(evaluate("1 & 2").is_err());
+        assert!(evaluate("10^1000").is_err());
+    }
+
+    #[test]
+    fn test_format_number() {
+        assert_eq!(format_number(4.0), "4");
+        assert_eq!(format_number(0.1 + 0.2), "0.3");
+        assert_eq!(format_number(-2.5), "-2.5");
+    }
+}
@@ -0,0 +1,158 @@
+use super::DiscordContext;
+use serenity::builder::{
+    CreateActionRow, CreateButton, CreateInteractionResponse, CreateInteractionResponseMessage,
+    CreateMessage, EditMessage,
+};
+use serenity::model::Permissions;
+use serenity::model::application::{ButtonStyle, ComponentInteraction};
+use serenity::model::channel::ReactionType;
+use tokio::time::{Duration, Instant};
+use tracing::{info, warn};
+
+/// How long an authorized user has to confirm a destructive action
+pub const CONFIRMATION_TIMEOUT: Duration = Duration::from_secs(60);
+
+const CONFIRM_ID: &str = "chloe_confirm";
+const CANCEL_ID: &str = "chloe_cancel";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConfirmationOutcome {
+    Confirmed,
+    Cancelled,
+    TimedOut,
+}
+
+/// Post a preview with ✅/❌ buttons and wait until the invoking user (or a member
+/// with Manage Server) confirms or cancels, or the timeout runs out
+pub async fn request_confirmation(
+    discord_ctx: &DiscordContext,
+    tool_name: &str,
+    preview: &str,
+) -> Result<ConfirmationOutcome, String> {
+    let buttons = CreateActionRow::Buttons(vec![
+        CreateButton::new(CONFIRM_ID)
+            .label("confirm")
+            .style(ButtonStyle::Success)
+            .emoji(ReactionType::Unicode("✅".to_string())),
+        CreateButton::new(CANCEL_ID)
+            .label("cancel")
+            .style(ButtonStyle::Danger)
+            .emoji(ReactionType::Unicode("❌".to_string())),
+    ]);
+
+    let mut message = discord_ctx
+        .channel_id
+        .send_message(
+            &discord_ctx.http,
+            CreateMessage::new()
+                .content(format!(
+                    "⚠️ **confirm action** (`{}`)\n{}\n\n-# expires in {}s",
+                    tool_name,
+                    preview,
+                    CONFIRMATION_TIMEOUT.as_secs()
+                ))
+                .reference_message((discord_ctx.channel_id, discord_ctx.message_id))
+                .components(vec![buttons]),
+        )
+        .await
+        .map_err(|e| format!("Failed to send confirmation prompt: {}", e))?;
+
+    info!(
+        event = "tool_confirmation_requested",
+        tool_name = %tool_name,
+        confirmation_message_id = message.id.get(),
+        "Waiting for user confirmation"
+    );
+
+    let deadline = Instant::now() + CONFIRMATION_TIMEOUT;
+
+    loop {
+        let remaining = deadline.saturating_duration_since(Instant::now());
+        let Some(interaction) = message
+            .await_component_interaction(&discord_ctx.shard)
+            .timeout(remaining)
+            .await
+        else {
+            if let Err(e) = message
+                .edit(
+                    &discord_ctx.http,
+                    EditMessage::new()
+                        .content(format!("⌛ **timed out** (`{}`)\n{}", tool_name, preview))
+                        .components(vec![]),
+                )
+                .await
+            {
+                warn!(
+                    event = "tool_confirmation_edit_failed",
+                    error = ?e,
+                    "Failed to mark confirmation prompt as expired"
+                );
+            }
+            return Ok(ConfirmationOutcome::TimedOut);
+        };
+
+        if !is_authorized(discord_ctx, &interaction) {
+            let _ = interaction
+                .create_response(
+                    &discord_ctx.http,
+                    CreateInteractionResponse::Message(
+                        CreateInteractionResponseMessage::new()
+                            .content("only the person who asked (or a server manager) can confirm this")
+                            .ephemeral(true),
+                    ),
+                )
+                .await;
+            continue;
+        }
+
+        let (outcome, status) = if interaction.data.custom_id == CONFIRM_ID {
+            (ConfirmationOutcome::Confirmed, "✅ **confirmed**")
+        } else {
+            (ConfirmationOutcome::Cancelled, "❌ **cancelled**")
+        };
+
+        info!(
+            event = "tool_confirmation_resolved",
+            tool_name = %tool_name,
+            outcome = ?outcome,
+            resolved_by = interaction.user.id.get(),
+            "Confirmation prompt resolved"
+        );
+
+        if let Err(e) = interaction
+            .create_response(
+                &discord_ctx.http,
+                CreateInteractionResponse::UpdateMessage(
+                    CreateInteractionResponseMessage::new()
+                        .content(format!(
+                            "{} by <@{}> (`{}`)\n{}",
+                            status,
+                            interaction.user.id,
+                            tool_name,
+                            preview
+                        ))
+                        .components(vec![]),
+                ),
+            )
+            .await
+        {
+            warn!(
+                event = "tool_confirmation_edit_failed",
+                error = ?e,
+                "Failed to update confirmation prompt"
+            );
+        }
+
+        return Ok(outcome);
+    }
+}
+
+fn is_authorized(discord_ctx: &DiscordContext, interaction: &ComponentInteraction) -> bool {
+    interaction.user.id == discord_ctx.author_id
+        || interaction
+            .member
+            .as_ref()
+            .and_then(|member| member.permissions)
+            .map(|permissions| permissions.contains(Permissions::MANAGE_GUILD))
+            .unwrap_or(false)
+}
@@ -0,0 +1,393 @@
+use super::{DiscordContext, Tool};
+use serde_json::{Value, json};
+use serenity::builder::{CreateEmbed, CreateEmbedFooter, CreateMessage};
+use serenity::model::Permissions;
+use std::collections::HashMap;
+
+// discord's embed limits
+const TITLE_LIMIT: usize = 256;
+const DESCRIPTION_LIMIT: usize = 4096;
+const MAX_FIELDS: usize = 25;
+const FIELD_NAME_LIMIT: usize = 256;
+const FIELD_VALUE_LIMIT: usize = 1024;
+const FOOTER_LIMIT: usize = 2048;
+/// All text of an embed together
+const TOTAL_LIMIT: usize = 6000;
+
+/// chloe's pink, used when the model doesn't pick a color
+const DEFAULT_COLOR: u32 = 0xff69b4;
+
+#[derive(Debug, Clone, PartialEq)]
+struct EmbedField {
+    name: String,
+    value: String,
+    inline: bool,
+}
+
+/// An embed as the model asked for it, cut down to discord's limits
+#[derive(Debug, Clone, PartialEq)]
+struct EmbedContent {
+    title: Option<String>,
+    url: Option<String>,
+    description: Option<String>,
+    fields: Vec<EmbedField>,
+    color: u32,
+    image_url: Option<String>,
+    thumbnail_url: Option<String>,
+    footer: Option<String>,
+}
+
+impl EmbedContent {
+    fn from_parameters(parameters: &HashMap<String, Value>) -> Result<Self, String> {
+        let text = |key: &str, limit: usize| {
+            parameters
+                .get(key)
+                .and_then(|v| v.as_str())
+                .map(str::trim)
+                .filter(|value| !value.is_empty())
+                .map(|value| truncate(value, limit))
+        };
+        let link = |key: &str| {
+            parameters
+                .get(key)
+                .and_then(|v| v.as_str())
+                .map(str::trim)
+                .filter(|value| is_http_url(value))
+                .map(str::to_string)
+        };
+
+        let fields = parameters
+            .get("fields")
+            .and_then(|v| v.as_array())
+            .map(|fields| {
+                fields
+                    .iter()
+                    .filter_map(|field| {
+                        let name = field.get("name")?.as_str()?.trim();
+                        let value = field.get("value")?.as_str()?.trim();
+                        if name.is_empty() || value.is_empty() {
+                            return None;
+                        }
+                        Some(EmbedField {
+                            name: truncate(name, FIELD_NAME_LIMIT),
+                            value: truncate(value, FIELD_VALUE_LIMIT),
+                            inline: field
+                                .get("inline")
+                                .and_then(|v| v.as_bool())
+                                .unwrap_or(false),
+                        })
+                    })
+                    .take(MAX_FIELDS)
+                    .collect()
+            })
+            .unwrap_or_default();
+
+        let mut embed = Self {
+            title: text("title", TITLE_LIMIT),
+            url: link("url"),
+            description: text("description", DESCRIPTION_LIMIT),
+            fields,
+            color: parameters
+                .get("color")
+                .and_then(|v| v.as_str())
+                .and_then(parse_color)
+                .unwrap_or(DEFAULT_COLOR),
+            image_url: link("image_url"),
+            thumbnail_url: link("thumbnail_url"),
+            footer: text("footer", FOOTER_LIMIT),
+        };
+        if embed.title.is_none() && embed.description.is_none() && embed.fields.is_empty() {
+            return Err("An embed needs a title, a description or fields".to_string());
+        }
+        embed.fit_total_limit();
+        Ok(embed)
+    }
+
+    fn text_length(&self) -> usize {
+        let length = |value: &Option<String>| value.as_ref().map_or(0, |v| v.chars().count());
+        length(&self.title)
+            + length(&self.description)
+            + length(&self.footer)
+            + self
+                .fields
+                .iter()
+                .map(|field| field.name.chars().count() + field.value.chars().count())
+                .sum::<usize>()
+    }
+
+    /// Drop trailing fields, then shorten the description, until the embed fits
+    fn fit_total_limit(&mut self) {
+        while self.text_length() > TOTAL_LIMIT && self.fields.len() > 1 {
+            self.fields.pop();
+        }
+        let excess = self.text_length().saturating_sub(TOTAL_LIMIT);
+        if excess == 0 {
+            return;
+        }
+        if let Some(description) = &self.description {
+            let keep = description.chars().count().saturating_sub(excess);
+            self.description = Some(truncate(description, keep.max(1)));
+        }
+    }
+
+    fn to_embed(&self) -> CreateEmbed {
+        let mut embed = CreateEmbed::new().color(self.color);
+        if let Some(title) = &self.title {
+            embed = embed.title(title);
+        }
+        if let Some(url) = &self.url {
+            embed = embed.url(url);
+        }
+        if let Some(description) = &self.description {
+            embed = embed.description(description);
+        }
+        for field in &self.fields {
+            embed = embed.field(&field.name, &field.value, field.inline);
+        }
+        if let Some(image_url) = &self.image_url {
+            embed = embed.image(image_url);
+        }
+        if let Some(thumbnail_url) = &self.thumbnail_url {
+            embed = embed.thumbnail(thumbnail_url);
+        }
+        if let Some(footer) = &self.footer {
+            embed = embed.footer(CreateEmbedFooter::new(footer));
+        }
+        embed
+    }
+
+    /// The embed as a plain message, for channels where chloe can't embed
+    fn to_text(&self) -> String {
+        let mut lines = Vec::new();
+        if let Some(title) = &self.title {
+            lines.push(format!("**{}**", title));
+        }
+        if let Some(description) = &self.description {
+            lines.push(description.clone());
+        }
+        for field in &self.fields {
+            lines.push(format!("**{}**: {}", field.name, field.value));
+        }
+        if let Some(image_url) = &self.image_url {
+            lines.push(image_url.clone());
+        }
+        if let Some(footer) = &self.footer {
+            lines.push(format!("-# {}", footer));
+        }
+        truncate(&lines.join("\n"), 2000)
+    }
+}
+
+fn truncate(value: &str, limit: usize) -> String {
+    if value.chars().count() <= limit {
+        return value.to_string();
+    }
+    let mut truncated: String = value.chars().take(limit.saturating_sub(1)).collect();
+    truncated.push('…');
+    truncated
+}
+
+fn is_http_url(value: &str) -> bool {
+    value.starts_with("https://") || value.starts_with("http://")
+}
+
+/// `#ff69b4`, `ff69b4` or `0xff69b4`
+fn parse_color(value: &str) -> Option<u32> {
+    let hex = value.trim();
+    let hex = hex
+        .strip_prefix('#')
+        .or_else(|| hex.strip_prefix("0x"))
+        .unwrap_or(hex);
+    if hex.len() != 6 {
+        return None;
+    }
+    u32::from_str_radix(hex, 16).ok()
+}
+
+/// Rich answers as a discord embed, for result cards, lists of facts and status panels
+pub struct DiscordSendEmbedTool;
+
+#[async_trait::async_trait]
+impl Tool for DiscordSendEmbedTool {
+    fn name(&self) -> &str {
+        "discord_send_embed"
+    }
+
+    fn description(&self) -> &str {
+        "Send a Discord embed instead of a plain message. Use it only for structured answers that read better as a card, like search results, comparisons, stats or a status overview. For normal chat use discord_send_message."
+    }
+
+    fn parameters_schema(&self) -> Value {
+        json!({
+            "type": "object",
+            "properties": {
+                "title": {
+                    "type": "string",
+                    "description": format!("Embed title (max {} characters)", TITLE_LIMIT)
+                },
+                "url": {
+                    "type": "string",
+                    "description": "Link the title points to"
+                },
+                "description": {
+                    "type": "string",
+                    "description": format!("Main text, Discord markdown works (max {} characters)", DESCRIPTION_LIMIT)
+                },
+                "fields": {
+                    "type": "array",
+                    "description": format!("Up to {} name/value pairs", MAX_FIELDS),
+                    "items": {
+                        "type": "object",
+                        "properties": {
+                            "name": {"type": "string"},
+                            "value": {"type": "string"},
+                            "inline": {"type": "boolean", "description": "Show next to other inline fields"}
+                        },
+                        "required": ["name", "value"]
+                    }
+                },
+                "color": {
+                    "type": "string",
+                    "description": "Hex color of the side bar, e.g. '#ff69b4'"
+                },
+                "image_url": {
+                    "type": "string",
+                    "description": "Large image shown at the bottom"
+                },
+                "thumbnail_url": {
+                    "type": "string",
+                    "description": "Small image shown at the top right"
+                },
+                "footer": {
+                    "type": "string",
+                    "description": "Small text at the bottom, e.g. sources"
+                },
+                "reply_to_original": {
+                    "type": "boolean",
+                    "description": "Whether to reply to the original message (true) or send as a standalone message (false). Default is true.",
+                    "default": true
+                }
+            },
+            "required": []
+        })
+    }
+
+    fn needs_discord_context(&self) -> bool {
+        true
+    }
+
+    fn needs_result_feedback(&self) -> bool {
+        false // sent like discord_send_message, there's nothing to follow up on
+    }
+
+    async fn execute(
+        &self,
+        parameters: HashMap<String, Value>,
+        discord_context: Option<&DiscordContext>,
+    ) -> Result<String, String> {
+        let embed = EmbedContent::from_parameters(&parameters)?;
+        let discord_ctx = discord_context.ok_or("Discord context is required for this tool")?;
+
+        let reply_to_original = parameters
+            .get("reply_to_original")
+            .and_then(|v| v.as_bool())
+            .unwrap_or(true)
+            && discord_ctx.bot_has_permission(Permissions::READ_MESSAGE_HISTORY);
+        let can_embed = discord_ctx.bot_has_permission(Permissions::EMBED_LINKS);
+
+        let mut message = if can_embed {
+            CreateMessage::new().embed(embed.to_embed())
+        } else {
+            CreateMessage::new().content(embed.to_text())
+        };
+        if reply_to_original {
+            message = message.reference_message((discord_ctx.channel_id, discord_ctx.message_id));
+        }
+
+        discord_ctx
+            .channel_id
+            .send_message(&discord_ctx.http, message)
+            .await
+            .map_err(|e| format!("Failed to send Discord embed: {}", e))?;
+
+        Ok(format!(
+            "Successfully sent {}: '{}' (reply_to_original: {})",
+            if can_embed { "embed" } else { "embed as text" },
+            embed
+                .title
+                .as_deref()
+                .or(embed.description.as_deref())
+                .unwrap_or_default()
+                .chars()
+                .take(50)
+                .collect::<String>(),
+            reply_to_original
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parameters(value: Value) -> HashMap<String, Value> {
+        serde_json::from_value(value).unwrap()
+    }
+
+    #[test]
+    fn test_embed_from_parameters() {
+        let embed = EmbedContent::from_parameters(&parameters(json!({
+            "title": "  Rust 1.87  ",
+            "url": "javascript:alert(1)",
+            "fields": [
+                {"name": "Released", "value": "2025-05-15", "inline": true},
+                {"name": "", "value": "dropped"}
+            ],
+            "color": "#00ff00",
+            "image_url": "https://example.com/ferris.png"
+        })))
+        .unwrap();
+
+        assert_eq!(embed.title.as_deref(), Some("Rust 1.87"));
+        assert_eq!(embed.url, None);
+        assert_eq!(
+            embed.fields,
+            vec![EmbedField {
+                name: "Released".to_string(),
+                value: "2025-05-15".to_string(),
+                inline: true,
+            }]
+        );
+        assert_eq!(embed.color, 0x00ff00);
+        assert_eq!(
+            embed.image_url.as_deref(),
+            Some("https://example.com/ferris.png")
+        );
+        assert!(EmbedContent::from_parameters(&parameters(json!({"color": "#fff"}))).is_err());
+    }
+
+    #[test]
+    fn test_embed_fits_discord_limits() {
+        let field = json!({"name": "n".repeat(300), "value": "v".repeat(2000)});
+        let embed = EmbedContent::from_parameters(&parameters(json!({
+            "description": "d".repeat(5000),
+            "fields": vec![field; 30],
+        })))
+        .unwrap();
+
+        assert_eq!(embed.fields.len(), 1);
+        assert_eq!(embed.fields[0].value.chars().count(), FIELD_VALUE_LIMIT);
+        assert_eq!(
+            embed.description.as_ref().map(|d| d.chars().count()),
+            Some(DESCRIPTION_LIMIT)
+        );
+        assert!(embed.text_length() <= TOTAL_LIMIT);
+    }
+
+    #[test]
+    fn test_parse_color() {
+        assert_eq!(parse_color("#ff69b4"), Some(0xff69b4));
+        assert_eq!(parse_color("0x00FF00"), Some(0x00ff00));
+        assert_eq!(parse_color("pink"), None);
+    }
+}
@@ -0,0 +1,320 @@
+use super::fetch_policy::{self, USER_AGENT};
+use super::{DiscordContext, Tool};
+use base64::Engine;
+use serde_json::{Value, json};
+use serenity::builder::{CreateAttachment, CreateMessage};
+use serenity::model::Permissions;
+use std::collections::HashMap;
+use std::time::Duration;
+use tracing::info;
+
+/// Discord's upload limit for servers without boosts
+pub const MAX_FILE_BYTES: usize = 10 * 1024 * 1024;
+
+/// Attachments discord allows on one message
+pub const MAX_FILES_PER_MESSAGE: usize = 10;
+
+const DOWNLOAD_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// A file ready to be uploaded
+#[derive(Debug, Clone, PartialEq)]
+pub struct FileUpload {
+    pub filename: String,
+    pub bytes: Vec<u8>,
+}
+
+impl FileUpload {
+    /// Decode a data url (`data:image/png;base64,...`) or plain base64
+    pub fn from_base64(data: &str, filename: &str) -> Result<Self, String> {
+        let (mime_type, encoded) = match data.trim().strip_prefix("data:") {
+            Some(data_url) => {
+                let (header, encoded) = data_url
+                    .split_once(',')
+                    .ok_or("Malformed data URL, expected data:<type>;base64,<data>")?;
+                let mime_type = header
+                    .strip_suffix(";base64")
+                    .ok_or("Only base64 data URLs are supported")?;
+                (Some(mime_type), encoded)
+            }
+            None => (None, data.trim()),
+        };
+        let bytes = base64::engine::general_purpose::STANDARD
+            .decode(encoded)
+            .map_err(|e| format!("Invalid base64 data: {}", e))?;
+        Ok(Self {
+            filename: with_extension(filename, mime_type),
+            bytes,
+        })
+    }
+}
+
+/// A safe attachment name, with an extension matching the mime type when it has none
+fn with_extension(filename: &str, mime_type: Option<&str>) -> String {
+    let mut name: String = filename
+        .trim()
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || matches!(c, '.' | '-' | '_') {
+                c
+            } else {
+                '_'
+            }
+        })
+        .take(100)
+        .collect();
+    if name.trim_matches(['.', '_']).is_empty() {
+        name = "file".to_string();
+    }
+    if !name.contains('.') {
+        let extension = match mime_type {
+            Some("image/png") => "png",
+            Some("image/jpeg") => "jpg",
+            Some("image/gif") => "gif",
+            Some("image/webp") => "webp",
+            Some("application/pdf") => "pdf",
+            Some(mime) if mime.starts_with("text/") => "txt",
+            _ => "bin",
+        };
+        name = format!("{}.{}", name, extension);
+    }
+    name
+}
+
+/// Upload files to the channel the conversation is in, as one message
+pub async fn send_files(
+    discord_ctx: &DiscordContext,
+    files: Vec<FileUpload>,
+    content: Option<&str>,
+    reply_to_original: bool,
+) -> Result<String, String> {
+    if !discord_ctx.bot_has_permission(Permissions::ATTACH_FILES) {
+        return Err("I can't attach files in this channel".to_string());
+    }
+    if files.is_empty() || files.len() > MAX_FILES_PER_MESSAGE {
+        return Err(format!(
+            "Send 1 to {} files at a time",
+            MAX_FILES_PER_MESSAGE
+        ));
+    }
+    if let Some(file) = files.iter().find(|file| file.bytes.len() > MAX_FILE_BYTES) {
+        return Err(format!(
+            "{} is {} MB, Discord only takes files up to {} MB",
+            file.filename,
+            file.bytes.len() / (1024 * 1024),
+            MAX_FILE_BYTES / (1024 * 1024)
+        ));
+    }
+
+    let filenames: Vec<String> = files.iter().map(|file| file.filename.clone()).collect();
+    let mut message = CreateMessage::new().add_files(
+        files
+            .into_iter()
+            .map(|file| CreateAttachment::bytes(file.bytes, file.filename)),
+    );
+    if let Some(content) = content.map(str::trim).filter(|content| !content.is_empty()) {
+        message = message.content(content);
+    }
+    if reply_to_original && discord_ctx.bot_has_permission(Permissions::READ_MESSAGE_HISTORY) {
+        message = message.reference_message((discord_ctx.channel_id, discord_ctx.message_id));
+    }
+
+    discord_ctx
+        .channel_id
+        .send_message(&discord_ctx.http, message)
+        .await
+        .map_err(|e| format!("Failed to upload to Discord: {}", e))?;
+
+    info!(
+        event = "files_sent",
+        channel_id = %discord_ctx.channel_id,
+        file_count = filenames.len(),
+        "Sent files to Discord"
+    );
+    Ok(format!("Successfully sent {}", filenames.join(", ")))
+}
+
+/// Post a file from a url or base64 data as an attachment
+pub struct DiscordSendFileTool {
+    client: reqwest::Client,
+}
+
+impl DiscordSendFileTool {
+    pub fn new() -> Self {
+        let client = reqwest::Client::builder()
+            .timeout(DOWNLOAD_TIMEOUT)
+            .user_agent(USER_AGENT)
+            .build()
+            .unwrap_or_default();
+        Self { client }
+    }
+
+    async fn download(
+        &self,
+        url: &str,
+        filename: Option<&str>,
+        blocklist: &[String],
+    ) -> Result<FileUpload, String> {
+        let parsed = reqwest::Url::parse(url).map_err(|e| format!("Invalid URL: {}", e))?;
+        if !matches!(parsed.scheme(), "http" | "https") {
+            return Err("Only http and https URLs can be sent".to_string());
+        }
+        let host = parsed.host_str().ok_or("URL has no host")?;
+        if fetch_policy::is_blocked(host, blocklist) {
+            return Err(format!("Fetching {} is blocked in this server", host));
+        }
+
+        let response = self
+            .client
+            .get(parsed.clone())
+            .send()
+            .await
+            .and_then(|response| response.error_for_status())
+            .map_err(|e| format!("Failed to download {}: {}", url, e))?;
+        if response
+            .content_length()
+            .is_some_and(|length| length as usize > MAX_FILE_BYTES)
+        {
+            return Err(format!(
+                "{} is larger than Discord's {} MB limit",
+                url,
+                MAX_FILE_BYTES / (1024 * 1024)
+            ));
+        }
+        let mime_type = response
+            .headers()
+            .get(reqwest::header::CONTENT_TYPE)
+            .and_then(|value| value.to_str().ok())
+            .map(|value| value.split(';').next().unwrap_or(value).trim().to_string());
+        let bytes = response
+            .bytes()
+            .await
+            .map_err(|e| format!("Failed to download {}: {}", url, e))?;
+
+        let name = filename.unwrap_or_else(|| {
+            parsed
+                .path_segments()
+                .and_then(|mut segments| segments.next_back())
+                .filter(|segment| !segment.is_empty())
+                .unwrap_or("file")
+        });
+        Ok(FileUpload {
+            filename: with_extension(name, mime_type.as_deref()),
+            bytes: bytes.to_vec(),
+        })
+    }
+}
+
+impl Default for DiscordSendFileTool {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[async_trait::async_trait]
+impl Tool for DiscordSendFileTool {
+    fn name(&self) -> &str {
+        "discord_send_file"
+    }
+
+    fn description(&self) -> &str {
+        "Upload a file to the channel as an attachment, from a URL or base64 data. Use it to share images, documents or generated text files instead of pasting links or data."
+    }
+
+    fn parameters_schema(&self) -> Value {
+        json!({
+            "type": "object",
+            "properties": {
+                "url": {
+                    "type": "string",
+                    "description": "URL of the file to download and upload"
+                },
+                "data": {
+                    "type": "string",
+                    "description": "The file as base64 or a data URL, when there's no URL"
+                },
+                "filename": {
+                    "type": "string",
+                    "description": "Name of the attachment, e.g. 'notes.txt'"
+                },
+                "content": {
+                    "type": "string",
+                    "description": "Optional message sent with the file"
+                },
+                "reply_to_original": {
+                    "type": "boolean",
+                    "description": "Whether to reply to the original message (true) or send as a standalone message (false). Default is true.",
+                    "default": true
+                }
+            },
+            "required": []
+        })
+    }
+
+    fn needs_discord_context(&self) -> bool {
+        true
+    }
+
+    fn timeout(&self) -> Duration {
+        DOWNLOAD_TIMEOUT * 2 // the download, then the upload to discord
+    }
+
+    async fn execute(
+        &self,
+        parameters: HashMap<String, Value>,
+        discord_context: Option<&DiscordContext>,
+    ) -> Result<String, String> {
+        let string = |key: &str| {
+            parameters
+                .get(key)
+                .and_then(|v| v.as_str())
+                .map(str::trim)
+                .filter(|value| !value.is_empty())
+        };
+        let discord_ctx = discord_context.ok_or("Discord context is required for this tool")?;
+        let blocklist = discord_ctx.fetch_blocklist.as_slice();
+
+        let file = match (string("url"), string("data")) {
+            (Some(url), _) => self.download(url, string("filename"), blocklist).await?,
+            (None, Some(data)) => {
+                FileUpload::from_base64(data, string("filename").unwrap_or("file"))?
+            }
+            (None, None) => return Err("Provide either 'url' or 'data'".to_string()),
+        };
+        let reply_to_original = parameters
+            .get("reply_to_original")
+            .and_then(|v| v.as_bool())
+            .unwrap_or(true);
+
+        send_files(
+            discord_ctx,
+            vec![file],
+            string("content"),
+            reply_to_original,
+        )
+        .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_file_from_base64() {
+        let file = FileUpload::from_base64("data:image/png;base64,aGVsbG8=", "chloe").unwrap();
+        assert_eq!(file.filename, "chloe.png");
+        assert_eq!(file.bytes, b"hello");
+
+        let file = FileUpload::from_base64("aGVsbG8=", "notes.txt").unwrap();
+        assert_eq!(file.filename, "notes.txt");
+        assert!(FileUpload::from_base64("data:text/plain,hello", "notes").is_err());
+        assert!(FileUpload::from_base64("not base64!", "file").is_err());
+    }
+
+    #[test]
+    fn test_filenames_are_sanitized() {
+        assert_eq!(with_extension("../../etc/passwd", None), ".._.._etc_passwd");
+        assert_eq!(with_extension("my cat", Some("image/jpeg")), "my_cat.jpg");
+        assert_eq!(with_extension("", Some("image/webp")), "file.webp");
+    }
+}
@@ -1,16 +1,333 @@
 use super::Tool;
+use super::translate::Translator;
 use serde_json::{Value, json};
 use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use serenity::builder::{CreateMessage, EditMessage, GetMessages};
+use serenity::model::channel::{Channel, Message};
+use serenity::model::id::{ChannelId, MessageId};
+use crate::utils::pagination::{MAX_PAGE_CHARS, Paginator};
 use crate::utils::regex_patterns::{MENTION_REGEX as DISCORD_MENTION_REGEX, URL_REGEX, EMOTICON_REGEX};
 
-pub struct DiscordSendMessageTool;
+/// Discord rejects plain messages longer than this
+const DISCORD_MESSAGE_LIMIT: usize = 2000;
+
+/// Discord rate limits message edits, so a streamed reply is edited at most this often
+const STREAM_EDIT_INTERVAL: Duration = Duration::from_secs(1);
+
+/// A reply edited in place while the model streams its text, so users see the answer
+/// as it's generated instead of waiting for all of it
+pub struct StreamingReply {
+    http: Arc<serenity::http::Http>,
+    channel_id: serenity::model::id::ChannelId,
+    reply_to: Option<serenity::model::id::MessageId>,
+    message: Option<Message>,
+    shown: String,
+    last_edit: Option<Instant>,
+}
+
+impl StreamingReply {
+    pub fn new(discord_ctx: &super::DiscordContext) -> Self {
+        Self {
+            http: Arc::clone(&discord_ctx.http),
+            channel_id: discord_ctx.channel_id,
+            reply_to: discord_ctx
+                .bot_has_permission(serenity::model::Permissions::READ_MESSAGE_HISTORY)
+                .then_some(discord_ctx.message_id),
+            message: None,
+            shown: String::new(),
+            last_edit: None,
+        }
+    }
+
+    /// Whether any of the reply has been posted yet
+    pub fn is_started(&self) -> bool {
+        self.message.is_some()
+    }
+
+    /// Show the text streamed so far, throttled to one edit per interval
+    pub async fn update(&mut self, text: &str) {
+        if self
+            .last_edit
+            .is_some_and(|last_edit| last_edit.elapsed() < STREAM_EDIT_INTERVAL)
+        {
+            return;
+        }
+
+        // cut long previews short, finish decides how the whole text goes out
+        let mut preview = DiscordSendMessageTool::escape_markdown_chars(text.trim());
+        if preview.chars().count() > DISCORD_MESSAGE_LIMIT {
+            preview = preview.chars().take(DISCORD_MESSAGE_LIMIT - 1).collect();
+            preview.push('…');
+        }
+        if let Err(e) = self.show(preview).await {
+            tracing::warn!(
+                event = "streaming_reply_update_failed",
+                error = %e,
+                "Failed to update streamed reply"
+            );
+        }
+        self.last_edit = Some(Instant::now());
+    }
+
+    /// Show the complete text. Returns false when it doesn't fit in one message, after
+    /// removing the preview so it can go out paginated instead.
+    pub async fn finish(&mut self, text: &str) -> bool {
+        let content = DiscordSendMessageTool::escape_markdown_chars(text.trim());
+        if content.chars().count() > DISCORD_MESSAGE_LIMIT {
+            if let Some(message) = self.message.take() {
+                let _ = message.delete(&self.http).await;
+            }
+            return false;
+        }
+        match self.show(content).await {
+            Ok(()) => true,
+            Err(e) => {
+                tracing::warn!(
+                    event = "streaming_reply_finish_failed",
+                    error = %e,
+                    "Failed to finish streamed reply"
+                );
+                false
+            }
+        }
+    }
+
+    async fn show(&mut self, content: String) -> serenity::Result<()> {
+        if content.is_empty() || content == self.shown {
+            return Ok(());
+        }
+        match &mut self.message {
+            Some(message) => {
+                message
+                    .edit(&self.http, EditMessage::new().content(&content))
+                    .await?
+            }
+            None => {
+                let mut builder = CreateMessage::new().content(&content);
+                if let Some(reply_to) = self.reply_to {
+                    builder = builder.reference_message((self.channel_id, reply_to));
+                }
+                self.message = Some(self.channel_id.send_message(&self.http, builder).await?);
+            }
+        }
+        self.shown = content;
+        Ok(())
+    }
+}
+
+/// Where discord_send_message posts
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MessageTarget {
+    /// A reply to the message being answered
+    Reply,
+    /// A plain message in the channel or thread of the message being answered
+    Channel,
+    /// A plain message in the parent channel, when answering in a thread
+    Parent,
+    /// Inside the thread started on the message being answered, e.g. by discord_create_thread
+    Thread,
+}
+
+impl MessageTarget {
+    pub fn from_str(target: &str) -> Option<Self> {
+        match target.trim().to_lowercase().as_str() {
+            "reply" => Some(Self::Reply),
+            "channel" | "standalone" => Some(Self::Channel),
+            "parent" => Some(Self::Parent),
+            "thread" => Some(Self::Thread),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Reply => "reply",
+            Self::Channel => "channel",
+            Self::Parent => "parent",
+            Self::Thread => "thread",
+        }
+    }
+
+    /// The target the model asked for, `reply_to_original` is the older way of choosing
+    /// between a reply and a plain message
+    fn requested(parameters: &HashMap<String, Value>) -> Option<Self> {
+        if let Some(target) = parameters
+            .get("target")
+            .and_then(|v| v.as_str())
+            .and_then(Self::from_str)
+        {
+            return Some(target);
+        }
+        parameters
+            .get("reply_to_original")
+            .and_then(|v| v.as_bool())
+            .map(|reply| if reply { Self::Reply } else { Self::Channel })
+    }
+
+    /// Without an explicit choice a reply is only used where it helps: in a thread where
+    /// nobody has posted since the message being answered it's just noise
+    fn default_for(in_thread: bool, answered_is_latest: bool) -> Self {
+        if in_thread && answered_is_latest {
+            Self::Channel
+        } else {
+            Self::Reply
+        }
+    }
+}
+
+pub struct DiscordSendMessageTool {
+    paginator: Arc<Paginator>,
+    translator: Option<Arc<Translator>>,
+}
 
 impl DiscordSendMessageTool {
-    pub fn new() -> Self {
-        Self
+    pub fn new(paginator: Arc<Paginator>) -> Self {
+        Self {
+            paginator,
+            translator: None,
+        }
+    }
+
+    /// Translate replies in guilds with a `replyLanguage`
+    pub fn with_translator(mut self, translator: Option<Arc<Translator>>) -> Self {
+        self.translator = translator;
+        self
+    }
+
+    /// The reply in the guild's language, or unchanged when there's none or it fails
+    async fn translate_reply(&self, content: &str, discord_ctx: &super::DiscordContext) -> String {
+        let (Some(translator), Some(language)) = (&self.translator, &discord_ctx.reply_language)
+        else {
+            return content.to_string();
+        };
+        match translator.translate(content, language, None).await {
+            Ok(translation) if translation.detected_source.as_deref() == Some(language.as_str()) => {
+                content.to_string()
+            }
+            Ok(translation) => translation.text,
+            Err(e) => {
+                tracing::warn!(
+                    event = "reply_translation_failed",
+                    language = %language,
+                    error = %e,
+                    "Failed to translate reply, sending it untranslated"
+                );
+                content.to_string()
+            }
+        }
     }
 
-    fn escape_markdown_chars(text: &str) -> String {
+    /// Where the message goes and what it replies to
+    async fn destination(
+        &self,
+        parameters: &HashMap<String, Value>,
+        discord_ctx: &super::DiscordContext,
+    ) -> (MessageTarget, ChannelId, Option<MessageId>) {
+        let target = match MessageTarget::requested(parameters) {
+            Some(target) => target,
+            None if discord_ctx.is_thread() => {
+                MessageTarget::default_for(true, Self::is_latest_message(discord_ctx).await)
+            }
+            None => MessageTarget::Reply,
+        };
+
+        // replies need read message history, fall back to a standalone message without it
+        let can_reply =
+            discord_ctx.bot_has_permission(serenity::model::Permissions::READ_MESSAGE_HISTORY);
+        let reply = can_reply.then_some(discord_ctx.message_id);
+        match target {
+            MessageTarget::Reply => (target, discord_ctx.channel_id, reply),
+            MessageTarget::Channel => (target, discord_ctx.channel_id, None),
+            MessageTarget::Parent => match discord_ctx.thread_parent_id {
+                Some(parent_id) => (target, parent_id, None),
+                None => (MessageTarget::Channel, discord_ctx.channel_id, None),
+            },
+            MessageTarget::Thread => match Self::thread_on_message(discord_ctx).await {
+                Some(thread_id) => (target, thread_id, None),
+                None => (MessageTarget::Reply, discord_ctx.channel_id, reply),
+            },
+        }
+    }
+
+    /// Edit the earlier reply into the new answer. Answers too long for one message go out
+    /// as new pages and the earlier reply is deleted.
+    async fn replace_reply(
+        &self,
+        discord_ctx: &super::DiscordContext,
+        replaced: MessageId,
+        content: &str,
+    ) -> Result<String, String> {
+        let preview = content.chars().take(50).collect::<String>();
+        if content.chars().count() > DISCORD_MESSAGE_LIMIT {
+            let pages = Paginator::split_pages(content, MAX_PAGE_CHARS);
+            let page_count = pages.len();
+            let reply_to = discord_ctx
+                .bot_has_permission(serenity::model::Permissions::READ_MESSAGE_HISTORY)
+                .then_some(discord_ctx.message_id);
+            self.paginator
+                .send_pages(&discord_ctx.http, discord_ctx.channel_id, reply_to, None, pages)
+                .await?;
+            if let Err(e) = discord_ctx
+                .channel_id
+                .delete_message(&discord_ctx.http, replaced)
+                .await
+            {
+                tracing::warn!(
+                    event = "replaced_reply_delete_failed",
+                    message_id = %replaced,
+                    error = %e,
+                    "Couldn't delete the reply a long regenerated answer replaced"
+                );
+            }
+            return Ok(format!(
+                "Successfully replaced the earlier reply with {} embed page(s): '{}'",
+                page_count, preview
+            ));
+        }
+
+        // the earlier reply may have been paginated, its pages and buttons go away
+        let edit = EditMessage::new()
+            .content(content)
+            .embeds(Vec::new())
+            .components(Vec::new());
+        match discord_ctx
+            .channel_id
+            .edit_message(&discord_ctx.http, replaced, edit)
+            .await
+        {
+            Ok(_) => Ok(format!(
+                "Successfully replaced the earlier reply with: '{}'",
+                preview
+            )),
+            Err(e) => Err(format!("Failed to edit Discord message: {}", e)),
+        }
+    }
+
+    /// Whether nothing was posted after the message being answered
+    async fn is_latest_message(discord_ctx: &super::DiscordContext) -> bool {
+        discord_ctx
+            .channel_id
+            .messages(
+                &discord_ctx.http,
+                GetMessages::new().after(discord_ctx.message_id).limit(1),
+            )
+            .await
+            .map(|newer| newer.is_empty())
+            .unwrap_or(false)
+    }
+
+    /// The thread started on the message being answered, it shares the message's id
+    async fn thread_on_message(discord_ctx: &super::DiscordContext) -> Option<ChannelId> {
+        let thread_id = ChannelId::new(discord_ctx.message_id.get());
+        match thread_id.to_channel(&discord_ctx.http).await {
+            Ok(Channel::Guild(thread)) if thread.thread_metadata.is_some() => Some(thread.id),
+            _ => None,
+        }
+    }
+
+    pub(crate) fn escape_markdown_chars(text: &str) -> String {
         // First, convert literal \n to actual newlines
         let text_with_newlines = text.replace("\\n", "\n");
         
@@ -96,10 +413,14 @@ impl Tool for DiscordSendMessageTool {
                     "type": "string",
                     "description": "The message content to send to Discord. Be natural, conversational, and helpful. Use Discord markdown formatting if needed."
                 },
+                "target": {
+                    "type": "string",
+                    "enum": ["reply", "channel", "parent", "thread"],
+                    "description": "Where to post. reply: reply to the message you're answering. channel: a plain message in the same channel or thread, for announcements or when replying would be noise. parent: from a thread, post in its parent channel, e.g. a summary of what the thread decided. thread: inside the thread you started on the message earlier. When left out you reply, except in a thread where nobody wrote since the message you're answering."
+                },
                 "reply_to_original": {
                     "type": "boolean",
-                    "description": "Whether to reply to the original message (true) or send as a standalone message (false). Default is true.",
-                    "default": true
+                    "description": "Older way to choose between target reply (true) and channel (false), ignored when target is set."
                 }
             },
             "required": ["content"]
@@ -160,6 +481,10 @@ impl Tool for DiscordSendMessageTool {
             );
         }
 
+        let discord_ctx = discord_context.ok_or("Discord context is required for this tool")?;
+        let content_to_use = self.translate_reply(content_to_use, discord_ctx).await;
+        let content_to_use = content_to_use.as_str();
+
         // Escape markdown characters to prevent formatting issues
         let content = Self::escape_markdown_chars(content_to_use);
 
@@ -175,35 +500,79 @@ impl Tool for DiscordSendMessageTool {
             );
         }
 
-        let reply_to_original = parameters
-            .get("reply_to_original")
-            .and_then(|v| v.as_bool())
-            .unwrap_or(true);
+        // a regenerated answer takes the place of the reply it was asked for
+        if let Some(replaced) = discord_ctx.edit_message {
+            return self.replace_reply(discord_ctx, replaced, &content).await;
+        }
 
-        let discord_ctx = discord_context.ok_or("Discord context is required for this tool")?;
+        let (target, channel_id, reply_to) = self.destination(&parameters, discord_ctx).await;
 
-        // Send the message directly
-        use serenity::builder::CreateMessage;
+        // Long answers go out as paginated embeds instead of failing the length check
+        if content.chars().count() > DISCORD_MESSAGE_LIMIT {
+            let pages = Paginator::split_pages(&content, MAX_PAGE_CHARS);
+            let page_count = pages.len();
+            self.paginator
+                .send_pages(&discord_ctx.http, channel_id, reply_to, None, pages)
+                .await?;
+
+            return Ok(format!(
+                "Successfully sent long message as {} embed page(s): '{}' (target: {})",
+                page_count,
+                content.chars().take(50).collect::<String>(),
+                target.as_str()
+            ));
+        }
 
+        // Send the message directly
         let mut message_builder = CreateMessage::new().content(&content);
 
         // Add reply reference if requested
-        if reply_to_original {
-            message_builder =
-                message_builder.reference_message((discord_ctx.channel_id, discord_ctx.message_id));
+        if let Some(reply_to) = reply_to {
+            message_builder = message_builder.reference_message((channel_id, reply_to));
         }
 
-        match discord_ctx
-            .channel_id
+        match channel_id
             .send_message(&discord_ctx.http, message_builder)
             .await
         {
             Ok(_) => Ok(format!(
-                "Successfully sent message: '{}' (reply_to_original: {})",
+                "Successfully sent message: '{}' (target: {})",
                 content.chars().take(50).collect::<String>(),
-                reply_to_original
+                target.as_str()
             )),
             Err(e) => Err(format!("Failed to send Discord message: {}", e)),
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_message_target() {
+        let parameters = |pairs: Value| -> HashMap<String, Value> {
+            serde_json::from_value(pairs).unwrap()
+        };
+        assert_eq!(
+            MessageTarget::requested(&parameters(json!({ "target": "parent" }))),
+            Some(MessageTarget::Parent)
+        );
+        assert_eq!(
+            MessageTarget::requested(&parameters(json!({ "reply_to_original": false }))),
+            Some(MessageTarget::Channel)
+        );
+        // target wins over the older flag
+        assert_eq!(
+            MessageTarget::requested(&parameters(
+                json!({ "target": "thread", "reply_to_original": true })
+            )),
+            Some(MessageTarget::Thread)
+        );
+        assert_eq!(MessageTarget::requested(&parameters(json!({}))), None);
+
+        assert_eq!(MessageTarget::default_for(false, true), MessageTarget::Reply);
+        assert_eq!(MessageTarget::default_for(true, false), MessageTarget::Reply);
+        assert_eq!(MessageTarget::default_for(true, true), MessageTarget::Channel);
+    }
+}
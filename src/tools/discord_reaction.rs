@@ -1,12 +1,79 @@
 use super::Tool;
+use crate::utils::reaction_tracker::{MAX_REACTIONS_PER_MESSAGE, ReactionClaim, ReactionTracker};
 use serde_json::{Value, json};
 use std::collections::HashMap;
+use std::sync::Arc;
 
-pub struct DiscordAddReactionTool;
+/// A guild's `emojiAliases` setting mapping friendly names to its custom emojis,
+/// e.g. `{"pog": "guildpog"}`. Aliases are matched case-insensitively.
+pub fn emoji_aliases(setting: Option<&Value>) -> HashMap<String, String> {
+    setting
+        .and_then(|v| v.as_object())
+        .map(|aliases| {
+            aliases
+                .iter()
+                .filter_map(|(alias, emoji)| {
+                    let alias = alias.trim().trim_matches(':').to_lowercase();
+                    let emoji = emoji.as_str()?.trim().trim_matches(':').to_string();
+                    (!alias.is_empty() && !emoji.is_empty()).then_some((alias, emoji))
+                })
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// The guild's custom emoji by name or alias, None when it has none such or outside a guild
+async fn find_custom_emoji(
+    discord_ctx: &super::DiscordContext,
+    emoji_name: &str,
+) -> Result<Option<serenity::model::channel::ReactionType>, String> {
+    if discord_ctx.guild_id.is_none() {
+        return Ok(None);
+    }
+    let guild_emojis = match discord_ctx.guild_emojis().await {
+        Ok(emojis) => emojis,
+        Err(e) => return Err(format!("Failed to fetch guild emojis: {}", e)),
+    };
+
+    // Find the emoji by name, then by the guild's alias for it
+    let aliased = discord_ctx.emoji_aliases.get(&emoji_name.to_lowercase());
+    let custom_emoji = guild_emojis
+        .iter()
+        .find(|emoji| emoji.name == emoji_name)
+        .or_else(|| aliased.and_then(|name| guild_emojis.iter().find(|emoji| &emoji.name == name)));
+    Ok(custom_emoji.map(
+        |custom_emoji| serenity::model::channel::ReactionType::Custom {
+            animated: custom_emoji.animated,
+            id: custom_emoji.id,
+            name: Some(custom_emoji.name.clone()),
+        },
+    ))
+}
+
+/// Common Unicode alternatives for custom emojis a guild doesn't have
+fn unicode_suggestion(emoji_name: &str) -> &'static str {
+    match emoji_name.to_lowercase().as_str() {
+        "poggers" | "pog" => "😮",
+        "kekw" | "lul" | "lol" => "😂",
+        "sadge" | "sad" => "😢",
+        "pepehands" => "😭",
+        "monkas" | "nervous" => "😰",
+        "thumbsup" | "up" => "👍",
+        "thumbsdown" | "down" => "👎",
+        "heart" | "love" => "❤️",
+        "fire" => "🔥",
+        "100" | "perfect" => "💯",
+        _ => "👍", // Default fallback
+    }
+}
+
+pub struct DiscordAddReactionTool {
+    reactions: Arc<ReactionTracker>,
+}
 
 impl DiscordAddReactionTool {
-    pub fn new() -> Self {
-        Self
+    pub fn new(reactions: Arc<ReactionTracker>) -> Self {
+        Self { reactions }
     }
 }
 
@@ -53,69 +120,99 @@ impl Tool for DiscordAddReactionTool {
 
         let discord_ctx = discord_context.ok_or("Discord context is required for this tool")?;
 
+        if !discord_ctx.bot_has_permission(serenity::model::Permissions::ADD_REACTIONS) {
+            return Err("Missing permission to add reactions in this channel".to_string());
+        }
+
         // Parse emoji - either Unicode or custom guild emoji
+        let mut substituted = None;
         let reaction_type = if emoji_str.starts_with(':') && emoji_str.ends_with(':') {
             // Custom guild emoji format :name:
             let emoji_name = &emoji_str[1..emoji_str.len() - 1];
 
-            // Get guild emojis to find the custom emoji
-            if let Some(guild_id) = discord_ctx.guild_id {
-                let guild_emojis = match guild_id.emojis(&discord_ctx.http).await {
-                    Ok(emojis) => emojis,
-                    Err(e) => return Err(format!("Failed to fetch guild emojis: {}", e)),
-                };
-
-                // Find the emoji by name
-                if let Some(custom_emoji) =
-                    guild_emojis.iter().find(|emoji| emoji.name == emoji_name)
-                {
-                    serenity::model::channel::ReactionType::Custom {
-                        animated: custom_emoji.animated,
-                        id: custom_emoji.id,
-                        name: Some(custom_emoji.name.clone()),
-                    }
-                } else {
-                    // Suggest common Unicode alternatives for failed custom emojis
-                    let unicode_suggestion = match emoji_name.to_lowercase().as_str() {
-                        "poggers" | "pog" => "😮",
-                        "kekw" | "lul" | "lol" => "😂",
-                        "sadge" | "sad" => "😢",
-                        "pepehands" => "😭",
-                        "monkas" | "nervous" => "😰",
-                        "thumbsup" | "up" => "👍",
-                        "thumbsdown" | "down" => "👎",
-                        "heart" | "love" => "❤️",
-                        "fire" => "🔥",
-                        "100" | "perfect" => "💯",
-                        _ => "👍", // Default fallback
-                    };
-
+            match find_custom_emoji(discord_ctx, emoji_name).await? {
+                Some(reaction_type) => reaction_type,
+                None if discord_ctx.reaction_fallback => {
+                    // react with the closest unicode emoji rather than failing the tool call
+                    let suggestion = unicode_suggestion(emoji_name);
+                    substituted = Some(suggestion);
+                    serenity::model::channel::ReactionType::Unicode(suggestion.to_string())
+                }
+                None if discord_ctx.guild_id.is_none() => {
+                    return Err("Cannot use custom emoji outside of guild context".to_string());
+                }
+                None => {
                     // Return a helpful error with the Unicode suggestion
                     return Err(format!(
                         "Custom emoji '{}' not found in guild. Try using Unicode emoji '{}' instead, or check the Available Custom Emojis section for valid options.",
-                        emoji_name, unicode_suggestion
+                        emoji_name,
+                        unicode_suggestion(emoji_name)
                     ));
                 }
-            } else {
-                return Err("Cannot use custom emoji outside of guild context".to_string());
             }
         } else {
             // Unicode emoji
             serenity::model::channel::ReactionType::Unicode(emoji_str.to_string())
         };
 
+        // retries in the tool loop would otherwise stack up reactions
+        let message_id = discord_ctx.message_id.get();
+        let reaction_key = reaction_type.to_string();
+        match self.reactions.claim(message_id, &reaction_key).await {
+            ReactionClaim::Allowed => {}
+            ReactionClaim::Duplicate => {
+                return Ok(format!("Already reacted with {}", emoji_str));
+            }
+            ReactionClaim::LimitReached => {
+                return Err(format!(
+                    "Already added {} reactions to this message, don't add more",
+                    MAX_REACTIONS_PER_MESSAGE
+                ));
+            }
+        }
+
         // Add the reaction directly
-        match discord_ctx
+        let added = discord_ctx
             .http
             .create_reaction(
                 discord_ctx.channel_id,
                 discord_ctx.message_id,
                 &reaction_type,
             )
-            .await
-        {
-            Ok(_) => Ok(format!("Successfully added reaction: {}", emoji_str)),
+            .await;
+        if added.is_err() {
+            self.reactions.release(message_id, &reaction_key).await;
+        }
+        match added {
+            Ok(_) => Ok(match substituted {
+                Some(suggestion) => format!(
+                    "Custom emoji {} isn't available here, reacted with {} instead",
+                    emoji_str, suggestion
+                ),
+                None => format!("Successfully added reaction: {}", emoji_str),
+            }),
             Err(e) => Err(format!("Failed to add Discord reaction: {}", e)),
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_emoji_aliases() {
+        let setting = json!({"Pog": ":guildpog:", "kekw": "guildkek", "": "x", "sad": 3});
+        let aliases = emoji_aliases(Some(&setting));
+        assert_eq!(aliases.len(), 2);
+        assert_eq!(aliases.get("pog").map(String::as_str), Some("guildpog"));
+        assert_eq!(aliases.get("kekw").map(String::as_str), Some("guildkek"));
+        assert!(emoji_aliases(None).is_empty());
+    }
+
+    #[test]
+    fn test_unicode_suggestion() {
+        assert_eq!(unicode_suggestion("KEKW"), "😂");
+        assert_eq!(unicode_suggestion("somethingelse"), "👍");
+    }
+}
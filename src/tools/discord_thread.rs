@@ -0,0 +1,171 @@
+use super::discord_message::DiscordSendMessageTool;
+use super::{DiscordContext, Tool};
+use crate::utils::pagination::{MAX_PAGE_CHARS, Paginator};
+use serde_json::{Value, json};
+use serenity::builder::{CreateMessage, CreateThread};
+use serenity::model::Permissions;
+use serenity::model::channel::AutoArchiveDuration;
+use std::collections::HashMap;
+use std::sync::Arc;
+use tracing::info;
+
+/// Discord's limit for channel and thread names
+const THREAD_NAME_LIMIT: usize = 100;
+
+const DISCORD_MESSAGE_LIMIT: usize = 2000;
+
+/// A thread name discord accepts: one line, at most 100 characters
+fn thread_name(name: &str) -> Option<String> {
+    let name = name.split_whitespace().collect::<Vec<_>>().join(" ");
+    if name.is_empty() {
+        return None;
+    }
+    if name.chars().count() <= THREAD_NAME_LIMIT {
+        return Some(name);
+    }
+    let mut truncated: String = name.chars().take(THREAD_NAME_LIMIT - 1).collect();
+    truncated.push('…');
+    Some(truncated)
+}
+
+/// Start a thread on the message chloe is answering and reply in it, so long back and
+/// forths don't flood the channel
+pub struct DiscordCreateThreadTool {
+    paginator: Arc<Paginator>,
+}
+
+impl DiscordCreateThreadTool {
+    pub fn new(paginator: Arc<Paginator>) -> Self {
+        Self { paginator }
+    }
+}
+
+#[async_trait::async_trait]
+impl Tool for DiscordCreateThreadTool {
+    fn name(&self) -> &str {
+        "discord_create_thread"
+    }
+
+    fn description(&self) -> &str {
+        "Start a thread on the message you're answering and post your reply in it. Use it when a conversation turns into a long back-and-forth (debugging, planning, a long story) that would flood the channel, or when someone asks to take it to a thread. Don't use it for short answers."
+    }
+
+    fn parameters_schema(&self) -> Value {
+        json!({
+            "type": "object",
+            "properties": {
+                "name": {
+                    "type": "string",
+                    "description": format!("Short thread title describing the topic (max {} characters)", THREAD_NAME_LIMIT)
+                },
+                "content": {
+                    "type": "string",
+                    "description": "Your reply, posted as the first message in the thread"
+                }
+            },
+            "required": ["name", "content"]
+        })
+    }
+
+    fn needs_discord_context(&self) -> bool {
+        true
+    }
+
+    fn needs_result_feedback(&self) -> bool {
+        false // the reply went out in the thread, like discord_send_message
+    }
+
+    async fn execute(
+        &self,
+        parameters: HashMap<String, Value>,
+        discord_context: Option<&DiscordContext>,
+    ) -> Result<String, String> {
+        let name = parameters
+            .get("name")
+            .and_then(|v| v.as_str())
+            .and_then(thread_name)
+            .ok_or("Missing or invalid 'name' parameter")?;
+        let content = parameters
+            .get("content")
+            .and_then(|v| v.as_str())
+            .map(str::trim)
+            .filter(|content| !content.is_empty())
+            .ok_or("Missing or invalid 'content' parameter")?;
+        let discord_ctx = discord_context.ok_or("Discord context is required for this tool")?;
+
+        if discord_ctx.guild_id.is_none() {
+            return Err("Threads only exist in servers".to_string());
+        }
+        if discord_ctx.is_thread() {
+            return Err(
+                "This conversation is already in a thread, answer with discord_send_message"
+                    .to_string(),
+            );
+        }
+        if !discord_ctx.bot_has_permission(
+            Permissions::CREATE_PUBLIC_THREADS | Permissions::SEND_MESSAGES_IN_THREADS,
+        ) {
+            return Err("I can't create threads in this channel".to_string());
+        }
+
+        let thread = discord_ctx
+            .channel_id
+            .create_thread_from_message(
+                &discord_ctx.http,
+                discord_ctx.message_id,
+                CreateThread::new(&name).auto_archive_duration(AutoArchiveDuration::OneDay),
+            )
+            .await
+            .map_err(|e| format!("Failed to create thread: {}", e))?;
+        info!(
+            event = "thread_created",
+            channel_id = %discord_ctx.channel_id,
+            thread_id = %thread.id,
+            "Moved a conversation into a thread"
+        );
+
+        let content = DiscordSendMessageTool::escape_markdown_chars(content);
+        if content.chars().count() > DISCORD_MESSAGE_LIMIT {
+            self.paginator
+                .send_pages(
+                    &discord_ctx.http,
+                    thread.id,
+                    None,
+                    None,
+                    Paginator::split_pages(&content, MAX_PAGE_CHARS),
+                )
+                .await?;
+        } else {
+            thread
+                .id
+                .send_message(&discord_ctx.http, CreateMessage::new().content(&content))
+                .await
+                .map_err(|e| format!("Created the thread but failed to post in it: {}", e))?;
+        }
+
+        Ok(format!(
+            "Successfully created thread '{}' (<#{}>) and replied in it: '{}'",
+            name,
+            thread.id,
+            content.chars().take(50).collect::<String>()
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_thread_name() {
+        assert_eq!(
+            thread_name("  rust\nlifetimes   help "),
+            Some("rust lifetimes help".to_string())
+        );
+        assert_eq!(thread_name(" \n "), None);
+
+        let long = thread_name(&"a".repeat(150)).unwrap();
+        assert_eq!(long.chars().count(), THREAD_NAME_LIMIT);
+        assert!(long.ends_with('…'));
+    }
+}
@@ -1,14 +1,54 @@
 use super::Tool;
+use super::fetch_policy::{self, FetchPolicy};
+use crate::utils::fetched_content::{self, ContentKind};
+use crate::utils::readability;
 use reqwest;
 use serde_json::{Value, json};
 use std::collections::HashMap;
+use std::time::Duration;
 use tracing::info;
 
-pub struct FetchTool;
+/// Larger bodies are cut off before they reach the model
+const MAX_CONTENT_CHARS: usize = 50000;
+
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(10);
+const DEFAULT_TIMEOUT_SECS: u64 = 30;
+const MAX_TIMEOUT_SECS: u64 = 60;
+
+pub struct FetchTool {
+    // one pooled client, so repeat fetches to a site reuse its connections
+    client: reqwest::Client,
+    policy: FetchPolicy,
+}
 
 impl FetchTool {
     pub fn new() -> Self {
-        Self
+        let client = reqwest::Client::builder()
+            .connect_timeout(CONNECT_TIMEOUT)
+            .timeout(Duration::from_secs(DEFAULT_TIMEOUT_SECS))
+            .user_agent(fetch_policy::USER_AGENT)
+            .build()
+            .unwrap_or_default();
+        Self {
+            policy: FetchPolicy::from_env(client.clone()),
+            client,
+        }
+    }
+}
+
+/// Say what went wrong in terms the model can relay, rather than reqwest's error chain
+fn describe_error(url: &str, timeout_secs: u64, error: &reqwest::Error) -> String {
+    if error.is_timeout() {
+        format!("Timed out after {}s fetching {}", timeout_secs, url)
+    } else if error.is_connect() {
+        format!(
+            "Couldn't connect to {}, the site may be down or unreachable",
+            url
+        )
+    } else if error.is_redirect() {
+        format!("Too many redirects fetching {}", url)
+    } else {
+        format!("Failed to fetch URL: {}", error)
     }
 }
 
@@ -19,7 +59,7 @@ impl Tool for FetchTool {
     }
 
     fn description(&self) -> &str {
-        "Fetch content from a URL and return the response. Supports GET requests to retrieve web pages, APIs, and other HTTP resources."
+        "Fetch content from a URL and return the response. Supports GET requests to retrieve web pages, APIs, and other HTTP resources. Web pages come back as their readable article text with title and author, JSON is pretty-printed and shortened, RSS/Atom feeds are listed as entries, binary files only report their type and size, and a CSS selector narrows a page down to just the matching elements."
     }
 
     fn parameters_schema(&self) -> Value {
@@ -29,12 +69,27 @@ impl Tool for FetchTool {
                 "url": {
                     "type": "string",
                     "description": "The URL to fetch content from"
+                },
+                "selector": {
+                    "type": "string",
+                    "description": "Optional CSS selector, e.g. 'table.results' or '#comments'. Web pages then return only the text of matching elements instead of the article."
+                },
+                "timeout_seconds": {
+                    "type": "integer",
+                    "description": "How long to wait for the site, 1-60 seconds. Default is 30.",
+                    "minimum": 1,
+                    "maximum": 60
                 }
             },
             "required": ["url"]
         })
     }
 
+    fn timeout(&self) -> Duration {
+        // the longest fetch the model can ask for, plus the robots.txt lookup
+        Duration::from_secs(MAX_TIMEOUT_SECS + 15)
+    }
+
     fn needs_discord_context(&self) -> bool {
         false
     }
@@ -46,32 +101,56 @@ impl Tool for FetchTool {
     async fn execute(
         &self,
         parameters: HashMap<String, Value>,
-        _discord_context: Option<&super::DiscordContext>,
+        discord_context: Option<&super::DiscordContext>,
     ) -> Result<String, String> {
         let url = parameters
             .get("url")
             .and_then(|v| v.as_str())
             .ok_or("Missing or invalid 'url' parameter")?;
 
+        let selector = parameters
+            .get("selector")
+            .and_then(|v| v.as_str())
+            .map(str::trim)
+            .filter(|selector| !selector.is_empty());
+        let timeout_secs = parameters
+            .get("timeout_seconds")
+            .and_then(|v| v.as_u64())
+            .unwrap_or(DEFAULT_TIMEOUT_SECS)
+            .clamp(1, MAX_TIMEOUT_SECS);
+
+        let parsed_url = reqwest::Url::parse(url).map_err(|e| format!("Invalid URL: {}", e))?;
+        let blocklist = discord_context
+            .map(|ctx| ctx.fetch_blocklist.as_slice())
+            .unwrap_or_default();
+        self.policy.check(&parsed_url, blocklist).await?;
+
         info!(
             event = "fetch_tool_executing",
             url = %url,
             "Fetching content from URL"
         );
 
-        // Build the request
-        let client = reqwest::Client::builder()
-            .timeout(std::time::Duration::from_secs(30))
-            .user_agent("Mozilla/5.0 (compatible; ChloeBot/1.0)")
-            .build()
-            .map_err(|e| format!("Failed to create HTTP client: {}", e))?;
-
         // Execute the request
-        let response = client
+        let response = self
+            .client
             .get(url)
+            .timeout(Duration::from_secs(timeout_secs))
             .send()
             .await
-            .map_err(|e| format!("Failed to fetch URL: {}", e))?;
+            .map_err(|e| describe_error(url, timeout_secs, &e))?;
+
+        // a redirect can land somewhere the guild blocked
+        if let Some(host) = response
+            .url()
+            .host_str()
+            .filter(|host| fetch_policy::is_blocked(host, blocklist))
+        {
+            return Err(format!(
+                "{} redirected to {}, which is blocked in this server",
+                url, host
+            ));
+        }
 
         let status = response.status();
         let headers = response.headers().clone();
@@ -87,39 +166,89 @@ impl Tool for FetchTool {
         let content_type = headers
             .get("content-type")
             .and_then(|v| v.to_str().ok())
-            .unwrap_or("unknown");
+            .map(|v| v.to_string());
+        let declared_kind = ContentKind::from_content_type(content_type.as_deref());
+
+        // Binary bodies are described, never downloaded
+        if declared_kind == Some(ContentKind::Binary) {
+            let disposition = headers
+                .get("content-disposition")
+                .and_then(|v| v.to_str().ok());
+            return Ok(format!(
+                "Status: {}\n{}",
+                status,
+                fetched_content::binary_summary(
+                    url,
+                    content_type.as_deref(),
+                    response.content_length(),
+                    disposition,
+                )
+            ));
+        }
 
         // Read the response body
-        let body = response
-            .text()
-            .await
-            .map_err(|e| format!("Failed to read response body: {}", e))?;
+        let bytes = response.bytes().await.map_err(|e| {
+            if e.is_timeout() {
+                describe_error(url, timeout_secs, &e)
+            } else {
+                format!("Failed to read response body: {}", e)
+            }
+        })?;
+
+        let kind = declared_kind.unwrap_or_else(|| ContentKind::sniff(&bytes));
+        if kind == ContentKind::Binary {
+            return Ok(format!(
+                "Status: {}\n{}",
+                status,
+                fetched_content::binary_summary(
+                    url,
+                    content_type.as_deref(),
+                    Some(bytes.len() as u64),
+                    None,
+                )
+            ));
+        }
+
+        let body = fetched_content::decode_body(&bytes, content_type.as_deref());
+        let content = match selector {
+            Some(selector) if status.is_success() && kind == ContentKind::Html => {
+                let selection = readability::extract_selection(&body, selector)?;
+                if selection.text.is_empty() {
+                    return Err(format!(
+                        "No elements on {} matched selector '{}'",
+                        url, selector
+                    ));
+                }
+                selection.to_model_text()
+            }
+            _ if status.is_success() => fetched_content::format_text(kind, body),
+            _ => body,
+        };
+        let content_type = content_type.as_deref().unwrap_or("unknown");
+        let length = content.chars().count();
 
         // Format the result
         let result = if status.is_success() {
-            if body.len() > 50000 {
+            if length > MAX_CONTENT_CHARS {
                 // Truncate very large responses
                 format!(
-                    "Status: {}\nContent-Type: {}\nContent-Length: {} bytes\n\nContent (truncated to 50KB):\n{}...\n\n[Content truncated. Original size: {} bytes]",
+                    "Status: {}\nContent-Type: {}\nContent-Length: {} chars\n\nContent (truncated):\n{}...\n\n[Content truncated. Original size: {} chars]",
                     status,
                     content_type,
-                    body.len(),
-                    &body[..50000],
-                    body.len()
+                    length,
+                    content.chars().take(MAX_CONTENT_CHARS).collect::<String>(),
+                    length
                 )
             } else {
                 format!(
-                    "Status: {}\nContent-Type: {}\nContent-Length: {} bytes\n\nContent:\n{}",
-                    status,
-                    content_type,
-                    body.len(),
-                    body
+                    "Status: {}\nContent-Type: {}\nContent-Length: {} chars\n\nContent:\n{}",
+                    status, content_type, length, content
                 )
             }
         } else {
             format!(
                 "Error: HTTP {}\nContent-Type: {}\n\nResponse:\n{}",
-                status, content_type, body
+                status, content_type, content
             )
         };
 
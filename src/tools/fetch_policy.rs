@@ -0,0 +1,202 @@
+use super::web_search::domain_matches;
+use crate::utils::robots::RobotsRules;
+use reqwest::{Client, Url};
+use std::collections::{HashMap, VecDeque};
+use std::time::{Duration, Instant};
+use tokio::sync::{Mutex, RwLock};
+use tracing::{info, warn};
+
+/// The product token chloe's fetches identify as, matched against robots.txt groups
+pub const USER_AGENT: &str = "Mozilla/5.0 (compatible; ChloeBot/1.0)";
+
+/// Requests per domain per minute unless `FETCH_DOMAIN_LIMIT` says otherwise
+const DEFAULT_DOMAIN_LIMIT: usize = 10;
+
+const RATE_WINDOW: Duration = Duration::from_secs(60);
+
+/// How long a robots.txt is trusted before it's fetched again
+const ROBOTS_TTL: Duration = Duration::from_secs(60 * 60);
+
+/// Politeness checks run before every fetch, so chloe can't be used to hammer a site:
+/// guild blocklists, robots.txt and a per-domain request cap shared by all guilds
+pub struct FetchPolicy {
+    client: Client,
+    respect_robots: bool,
+    domain_limit: usize,
+    robots: RwLock<HashMap<String, (RobotsRules, Instant)>>,
+    requests: Mutex<HashMap<String, VecDeque<Instant>>>,
+}
+
+impl FetchPolicy {
+    /// `FETCH_RESPECT_ROBOTS=false` skips robots.txt, `FETCH_DOMAIN_LIMIT` sets the
+    /// requests allowed per domain each minute
+    pub fn from_env(client: Client) -> Self {
+        let respect_robots = std::env::var("FETCH_RESPECT_ROBOTS")
+            .map(|value| !matches!(value.to_lowercase().as_str(), "false" | "0" | "no"))
+            .unwrap_or(true);
+        let domain_limit = std::env::var("FETCH_DOMAIN_LIMIT")
+            .ok()
+            .and_then(|value| value.parse().ok())
+            .filter(|&limit| limit > 0)
+            .unwrap_or(DEFAULT_DOMAIN_LIMIT);
+        Self::new(client, respect_robots, domain_limit)
+    }
+
+    pub fn new(client: Client, respect_robots: bool, domain_limit: usize) -> Self {
+        Self {
+            client,
+            respect_robots,
+            domain_limit,
+            robots: RwLock::new(HashMap::new()),
+            requests: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Err with the reason to give the model when `url` must not be fetched
+    pub async fn check(&self, url: &Url, blocklist: &[String]) -> Result<(), String> {
+        let host = url
+            .host_str()
+            .ok_or("URL has no host")?
+            .trim_start_matches("www.")
+            .to_lowercase();
+
+        if is_blocked(&host, blocklist) {
+            info!(event = "fetch_domain_blocked", domain = %host, "Fetch blocked by guild blocklist");
+            return Err(format!("Fetching {} is blocked in this server", host));
+        }
+
+        if self.respect_robots {
+            let mut path = url.path().to_string();
+            if let Some(query) = url.query() {
+                path.push('?');
+                path.push_str(query);
+            }
+            if !self.robots_for(url).await.is_allowed(&path) {
+                info!(event = "fetch_disallowed_by_robots", url = %url, "Fetch disallowed by robots.txt");
+                return Err(format!("{} is disallowed by {}'s robots.txt", url, host));
+            }
+        }
+
+        let mut requests = self.requests.lock().await;
+        let recent = requests.entry(host.clone()).or_default();
+        match take_slot(recent, Instant::now(), self.domain_limit) {
+            Ok(()) => Ok(()),
+            Err(retry_in) => {
+                warn!(
+                    event = "fetch_domain_rate_limited",
+                    domain = %host,
+                    limit = self.domain_limit,
+                    "Too many fetches to one domain"
+                );
+                Err(format!(
+                    "Too many requests to {} right now, try again in {}s",
+                    host,
+                    retry_in.as_secs().max(1)
+                ))
+            }
+        }
+    }
+
+    async fn robots_for(&self, url: &Url) -> RobotsRules {
+        let origin = url.origin().ascii_serialization();
+        let cached = self
+            .robots
+            .read()
+            .await
+            .get(&origin)
+            .filter(|(_, fetched_at)| fetched_at.elapsed() < ROBOTS_TTL)
+            .map(|(rules, _)| rules.clone());
+        if let Some(rules) = cached {
+            return rules;
+        }
+
+        let rules = self.fetch_robots(&origin).await;
+        self.robots
+            .write()
+            .await
+            .insert(origin, (rules.clone(), Instant::now()));
+        rules
+    }
+
+    /// A missing or unreachable robots.txt allows everything
+    async fn fetch_robots(&self, origin: &str) -> RobotsRules {
+        let response = self
+            .client
+            .get(format!("{}/robots.txt", origin))
+            .header("User-Agent", USER_AGENT)
+            .timeout(Duration::from_secs(10))
+            .send()
+            .await;
+
+        match response {
+            Ok(response) if response.status().is_success() => match response.text().await {
+                Ok(body) => RobotsRules::parse(&body, USER_AGENT),
+                Err(_) => RobotsRules::allow_all(),
+            },
+            Ok(_) => RobotsRules::allow_all(),
+            Err(e) => {
+                warn!(
+                    event = "robots_fetch_failed",
+                    origin = %origin,
+                    error = %e,
+                    "Couldn't read robots.txt, allowing the fetch"
+                );
+                RobotsRules::allow_all()
+            }
+        }
+    }
+}
+
+/// Whether a host is on a guild's blocklist, subdomains included
+pub fn is_blocked(host: &str, blocklist: &[String]) -> bool {
+    blocklist
+        .iter()
+        .any(|blocked| domain_matches(host, blocked))
+}
+
+/// Record a request in the domain's window, or Err with how long until one frees up
+fn take_slot(recent: &mut VecDeque<Instant>, now: Instant, limit: usize) -> Result<(), Duration> {
+    while recent
+        .front()
+        .is_some_and(|&sent| now.duration_since(sent) >= RATE_WINDOW)
+    {
+        recent.pop_front();
+    }
+    if recent.len() >= limit {
+        let oldest = recent.front().copied().unwrap_or(now);
+        return Err(RATE_WINDOW.saturating_sub(now.duration_since(oldest)));
+    }
+    recent.push_back(now);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_take_slot_caps_requests_per_window() {
+        let start = Instant::now();
+        let mut recent = VecDeque::new();
+        assert!(take_slot(&mut recent, start, 2).is_ok());
+        assert!(take_slot(&mut recent, start + Duration::from_secs(10), 2).is_ok());
+
+        let retry_in = take_slot(&mut recent, start + Duration::from_secs(20), 2).unwrap_err();
+        assert_eq!(retry_in, Duration::from_secs(40));
+
+        // the first request has left the window
+        assert!(take_slot(&mut recent, start + Duration::from_secs(61), 2).is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_check_applies_guild_blocklist() {
+        let policy = FetchPolicy::new(Client::new(), false, 10);
+        let blocklist = vec!["example.com".to_string()];
+
+        let url = Url::parse("https://news.example.com/story").unwrap();
+        assert!(policy.check(&url, &blocklist).await.is_err());
+
+        let url = Url::parse("https://example.org/").unwrap();
+        assert!(policy.check(&url, &blocklist).await.is_ok());
+    }
+}
@@ -0,0 +1,113 @@
+use super::{DiscordContext, Tool};
+use crate::utils::MessageSanitizer;
+use crate::utils::long_message::MAX_PROMPT_CHARS;
+use crate::utils::message_cache::MessageCache;
+use serde_json::{Value, json};
+use serenity::model::id::MessageId;
+use std::collections::HashMap;
+use std::sync::Arc;
+
+/// Reads the parts of a long message that were cut from the prompt, from the message
+/// cache or else from discord. Only messages in the channel chloe was asked in.
+pub struct ReadFullMessageTool {
+    message_cache: Arc<MessageCache>,
+}
+
+impl ReadFullMessageTool {
+    pub fn new(message_cache: Arc<MessageCache>) -> Self {
+        Self { message_cache }
+    }
+
+    /// The message's author and full text
+    async fn load(
+        &self,
+        discord_ctx: &DiscordContext,
+        message_id: u64,
+    ) -> Result<(String, String), String> {
+        if let Some(cached) = self.message_cache.get(message_id).await
+            && cached.channel_id == discord_ctx.channel_id.get()
+        {
+            return Ok((cached.author_name, cached.content));
+        }
+        let msg = discord_ctx
+            .channel_id
+            .message(&discord_ctx.http, MessageId::new(message_id))
+            .await
+            .map_err(|_| format!("There's no message {} in this channel", message_id))?;
+        Ok((msg.author.name, msg.content))
+    }
+}
+
+#[async_trait::async_trait]
+impl Tool for ReadFullMessageTool {
+    fn name(&self) -> &str {
+        "read_full_message"
+    }
+
+    fn description(&self) -> &str {
+        "Read the part of a long message that was left out of the conversation, e.g. the middle of pasted logs. Use it when the left out part matters for your answer."
+    }
+
+    fn parameters_schema(&self) -> Value {
+        json!({
+            "type": "object",
+            "properties": {
+                "message_id": {
+                    "type": "string",
+                    "description": "The message_id from the note where the message was cut"
+                },
+                "offset": {
+                    "type": "integer",
+                    "description": "Character to start reading at, 0 for the beginning. Use the offset from the previous result to keep reading."
+                }
+            },
+            "required": ["message_id"]
+        })
+    }
+
+    fn needs_discord_context(&self) -> bool {
+        true // messages are only read from the channel chloe was asked in
+    }
+
+    async fn execute(
+        &self,
+        parameters: HashMap<String, Value>,
+        discord_context: Option<&DiscordContext>,
+    ) -> Result<String, String> {
+        let message_id = parameters
+            .get("message_id")
+            .and_then(|value| match value {
+                Value::String(id) => id.trim().parse::<u64>().ok(),
+                value => value.as_u64(),
+            })
+            .filter(|id| *id > 0)
+            .ok_or("Missing or invalid 'message_id' parameter")?;
+        let offset = parameters
+            .get("offset")
+            .and_then(Value::as_u64)
+            .unwrap_or(0) as usize;
+        let discord_ctx = discord_context.ok_or("Discord context is required for this tool")?;
+
+        let (author, content) = self.load(discord_ctx, message_id).await?;
+        let content = MessageSanitizer::sanitize_message(&content, &author);
+        let total = content.chars().count();
+        if offset >= total {
+            return Err(format!("The message is only {} characters long", total));
+        }
+        let end = (offset + MAX_PROMPT_CHARS).min(total);
+        let part: String = content.chars().skip(offset).take(end - offset).collect();
+
+        let mut result = format!(
+            "Characters {} to {} of {} from {}'s message:\n{}",
+            offset, end, total, author, part
+        );
+        if end < total {
+            result.push_str(&format!(
+                "\n[{} more characters, call again with offset {} to keep reading]",
+                total - end,
+                end
+            ));
+        }
+        Ok(result)
+    }
+}
@@ -0,0 +1,516 @@
+use super::{DiscordContext, Tool};
+use serde::Deserialize;
+use serde_json::{Value, json};
+use std::collections::HashMap;
+
+const API_URL: &str = "https://api.github.com";
+const USER_AGENT: &str = "chloe-discord-bot";
+
+/// Issue and PR bodies are cut down to this before they reach the model
+const MAX_BODY_CHARS: usize = 1500;
+/// File contents are cut down to this
+const MAX_FILE_CHARS: usize = 8000;
+
+/// What the model asked to look up, from a pasted link or a repo plus number or path
+#[derive(Debug, Clone, PartialEq)]
+enum GithubTarget {
+    Repo {
+        owner: String,
+        repo: String,
+    },
+    /// Issues and pull requests share numbers, the API tells them apart
+    Issue {
+        owner: String,
+        repo: String,
+        number: u64,
+    },
+    File {
+        owner: String,
+        repo: String,
+        git_ref: Option<String>,
+        path: String,
+    },
+}
+
+impl GithubTarget {
+    /// Parse `github.com` links to repos, issues, pull requests and files
+    fn from_url(url: &str) -> Option<Self> {
+        let rest = url
+            .trim()
+            .trim_start_matches("https://")
+            .trim_start_matches("http://")
+            .trim_start_matches("www.")
+            .strip_prefix("github.com/")?;
+        let rest = rest.split(['?', '#']).next().unwrap_or_default();
+        let parts: Vec<&str> = rest.split('/').filter(|part| !part.is_empty()).collect();
+        let (owner, repo) = (
+            parts.first()?.to_string(),
+            parts.get(1)?.trim_end_matches(".git").to_string(),
+        );
+        match parts.get(2..).unwrap_or_default() {
+            [] => Some(Self::Repo { owner, repo }),
+            ["issues" | "pull", number, ..] => Some(Self::Issue {
+                owner,
+                repo,
+                number: number.parse().ok()?,
+            }),
+            ["blob", git_ref, path @ ..] if !path.is_empty() => Some(Self::File {
+                owner,
+                repo,
+                git_ref: Some(git_ref.to_string()),
+                path: path.join("/"),
+            }),
+            _ => Some(Self::Repo { owner, repo }),
+        }
+    }
+
+    fn from_parameters(parameters: &HashMap<String, Value>) -> Result<Self, String> {
+        let text = |key: &str| {
+            parameters
+                .get(key)
+                .and_then(|v| v.as_str())
+                .map(str::trim)
+                .filter(|value| !value.is_empty())
+        };
+        if let Some(url) = text("url") {
+            return Self::from_url(url).ok_or_else(|| format!("'{}' isn't a GitHub link", url));
+        }
+
+        let repo =
+            text("repo").ok_or("Provide either a GitHub 'url' or a 'repo' like owner/name")?;
+        let (owner, repo) = repo
+            .split_once('/')
+            .filter(|(owner, repo)| !owner.is_empty() && !repo.is_empty())
+            .ok_or("'repo' must look like owner/name")?;
+        let (owner, repo) = (owner.to_string(), repo.to_string());
+
+        if let Some(number) = parameters.get("number").and_then(|v| v.as_u64()) {
+            return Ok(Self::Issue {
+                owner,
+                repo,
+                number,
+            });
+        }
+        if let Some(path) = text("path") {
+            return Ok(Self::File {
+                owner,
+                repo,
+                git_ref: text("ref").map(str::to_string),
+                path: path.trim_start_matches('/').to_string(),
+            });
+        }
+        Ok(Self::Repo { owner, repo })
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct Repository {
+    full_name: String,
+    description: Option<String>,
+    html_url: String,
+    stargazers_count: u64,
+    forks_count: u64,
+    open_issues_count: u64,
+    language: Option<String>,
+    license: Option<License>,
+    default_branch: String,
+    #[serde(default)]
+    topics: Vec<String>,
+    archived: bool,
+    pushed_at: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct License {
+    spdx_id: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Issue {
+    title: String,
+    html_url: String,
+    state: String,
+    user: Option<Account>,
+    #[serde(default)]
+    labels: Vec<Label>,
+    comments: u64,
+    created_at: String,
+    body: Option<String>,
+    /// Only set when the issue is a pull request
+    pull_request: Option<Value>,
+}
+
+#[derive(Debug, Deserialize)]
+struct PullRequest {
+    merged: bool,
+    draft: Option<bool>,
+    additions: u64,
+    deletions: u64,
+    changed_files: u64,
+    base: Branch,
+    head: Branch,
+}
+
+#[derive(Debug, Deserialize)]
+struct Account {
+    login: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct Label {
+    name: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct Branch {
+    label: String,
+}
+
+/// Repository metadata, issue and pull request summaries and file contents from the
+/// GitHub REST API. `GITHUB_TOKEN` is optional, it raises the rate limit and lets chloe
+/// see private repos the token can.
+pub struct GithubLookupTool {
+    client: reqwest::Client,
+    token: Option<String>,
+}
+
+impl GithubLookupTool {
+    pub fn new() -> Self {
+        Self {
+            client: reqwest::Client::new(),
+            token: std::env::var("GITHUB_TOKEN")
+                .ok()
+                .filter(|token| !token.trim().is_empty()),
+        }
+    }
+
+    fn request(&self, path: &str, accept: &str) -> reqwest::RequestBuilder {
+        let mut request = self
+            .client
+            .get(format!("{}{}", API_URL, path))
+            .header("User-Agent", USER_AGENT)
+            .header("Accept", accept)
+            .header("X-GitHub-Api-Version", "2022-11-28");
+        if let Some(token) = &self.token {
+            request = request.bearer_auth(token);
+        }
+        request
+    }
+
+    async fn get_json<T: serde::de::DeserializeOwned>(&self, path: &str) -> Result<T, String> {
+        let response = self.send(path, "application/vnd.github+json").await?;
+        response
+            .json()
+            .await
+            .map_err(|e| format!("Failed to parse GitHub response: {}", e))
+    }
+
+    async fn send(&self, path: &str, accept: &str) -> Result<reqwest::Response, String> {
+        let response = self
+            .request(path, accept)
+            .send()
+            .await
+            .map_err(|e| format!("Failed to reach GitHub: {}", e))?;
+
+        let status = response.status();
+        if status.is_success() {
+            return Ok(response);
+        }
+        match status.as_u16() {
+            404 => Err("Not found on GitHub, it may not exist or may be private".to_string()),
+            403 | 429 => Err("GitHub rate limited the lookup, try again later".to_string()),
+            _ => {
+                let error_text = response.text().await.unwrap_or_default();
+                Err(format!(
+                    "GitHub request failed with status {}: {}",
+                    status, error_text
+                ))
+            }
+        }
+    }
+
+    async fn lookup(&self, target: &GithubTarget) -> Result<String, String> {
+        match target {
+            GithubTarget::Repo { owner, repo } => {
+                let repository: Repository =
+                    self.get_json(&format!("/repos/{}/{}", owner, repo)).await?;
+                Ok(format_repository(&repository))
+            }
+            GithubTarget::Issue {
+                owner,
+                repo,
+                number,
+            } => {
+                let issue: Issue = self
+                    .get_json(&format!("/repos/{}/{}/issues/{}", owner, repo, number))
+                    .await?;
+                let pull = match issue.pull_request {
+                    Some(_) => Some(
+                        self.get_json::<PullRequest>(&format!(
+                            "/repos/{}/{}/pulls/{}",
+                            owner, repo, number
+                        ))
+                        .await?,
+                    ),
+                    None => None,
+                };
+                Ok(format_issue(
+                    &format!("{}/{}#{}", owner, repo, number),
+                    &issue,
+                    pull.as_ref(),
+                ))
+            }
+            GithubTarget::File {
+                owner,
+                repo,
+                git_ref,
+                path,
+            } => {
+                let mut url = format!("/repos/{}/{}/contents/{}", owner, repo, path);
+                if let Some(git_ref) = git_ref {
+                    url.push_str(&format!("?ref={}", git_ref));
+                }
+                let content = self
+                    .send(&url, "application/vnd.github.raw+json")
+                    .await?
+                    .text()
+                    .await
+                    .map_err(|e| format!("Failed to read file from GitHub: {}", e))?;
+                Ok(format_file(
+                    &format!("{}/{}/{}", owner, repo, path),
+                    git_ref.as_deref(),
+                    &content,
+                ))
+            }
+        }
+    }
+}
+
+impl Default for GithubLookupTool {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+fn truncate(text: &str, max_chars: usize) -> String {
+    if text.chars().count() <= max_chars {
+        return text.to_string();
+    }
+    let mut truncated: String = text.chars().take(max_chars).collect();
+    truncated.push_str("\n[truncated]");
+    truncated
+}
+
+fn format_repository(repository: &Repository) -> String {
+    let mut summary = format!(
+        "Repository {} ({})\n",
+        repository.full_name, repository.html_url
+    );
+    if let Some(description) = repository.description.as_deref().filter(|d| !d.is_empty()) {
+        summary.push_str(&format!("Description: {}\n", description));
+    }
+    summary.push_str(&format!(
+        "Stars: {}, forks: {}, open issues and PRs: {}\n",
+        repository.stargazers_count, repository.forks_count, repository.open_issues_count
+    ));
+    if let Some(language) = &repository.language {
+        summary.push_str(&format!("Language: {}\n", language));
+    }
+    if let Some(license) = repository
+        .license
+        .as_ref()
+        .and_then(|l| l.spdx_id.as_deref())
+    {
+        summary.push_str(&format!("License: {}\n", license));
+    }
+    if !repository.topics.is_empty() {
+        summary.push_str(&format!("Topics: {}\n", repository.topics.join(", ")));
+    }
+    summary.push_str(&format!("Default branch: {}\n", repository.default_branch));
+    if let Some(pushed_at) = &repository.pushed_at {
+        summary.push_str(&format!("Last push: {}\n", pushed_at));
+    }
+    if repository.archived {
+        summary.push_str("This repository is archived.\n");
+    }
+    summary
+}
+
+fn format_issue(reference: &str, issue: &Issue, pull: Option<&PullRequest>) -> String {
+    let kind = if issue.pull_request.is_some() {
+        "Pull request"
+    } else {
+        "Issue"
+    };
+    let state = match pull {
+        Some(pull) if pull.merged => "merged".to_string(),
+        Some(pull) if pull.draft.unwrap_or(false) && issue.state == "open" => "draft".to_string(),
+        _ => issue.state.clone(),
+    };
+    let mut summary = format!(
+        "{} {}: {} ({})\nState: {}, opened by {} at {}, {} comments\n",
+        kind,
+        reference,
+        issue.title,
+        issue.html_url,
+        state,
+        issue
+            .user
+            .as_ref()
+            .map(|user| user.login.as_str())
+            .unwrap_or("unknown"),
+        issue.created_at,
+        issue.comments
+    );
+    if !issue.labels.is_empty() {
+        let labels: Vec<&str> = issue
+            .labels
+            .iter()
+            .map(|label| label.name.as_str())
+            .collect();
+        summary.push_str(&format!("Labels: {}\n", labels.join(", ")));
+    }
+    if let Some(pull) = pull {
+        summary.push_str(&format!(
+            "Merging {} into {}: {} files changed, +{} -{}\n",
+            pull.head.label, pull.base.label, pull.changed_files, pull.additions, pull.deletions
+        ));
+    }
+    if let Some(body) = issue
+        .body
+        .as_deref()
+        .map(str::trim)
+        .filter(|b| !b.is_empty())
+    {
+        summary.push_str(&format!("\n{}\n", truncate(body, MAX_BODY_CHARS)));
+    }
+    summary
+}
+
+fn format_file(reference: &str, git_ref: Option<&str>, content: &str) -> String {
+    format!(
+        "File {} at {}:\n{}",
+        reference,
+        git_ref.unwrap_or("the default branch"),
+        truncate(content, MAX_FILE_CHARS)
+    )
+}
+
+#[async_trait::async_trait]
+impl Tool for GithubLookupTool {
+    fn name(&self) -> &str {
+        "github_lookup"
+    }
+
+    fn description(&self) -> &str {
+        "Look up a GitHub repository, issue, pull request or file. Use it when someone pastes a GitHub link or asks about a repo, issue or PR, instead of guessing what it says."
+    }
+
+    fn parameters_schema(&self) -> Value {
+        json!({
+            "type": "object",
+            "properties": {
+                "url": {
+                    "type": "string",
+                    "description": "A github.com link to a repository, issue, pull request or file"
+                },
+                "repo": {
+                    "type": "string",
+                    "description": "Repository as owner/name, when there's no link"
+                },
+                "number": {
+                    "type": "integer",
+                    "description": "Issue or pull request number in repo"
+                },
+                "path": {
+                    "type": "string",
+                    "description": "File path in repo, e.g. src/main.rs"
+                },
+                "ref": {
+                    "type": "string",
+                    "description": "Branch, tag or commit for path, default branch if left out"
+                }
+            }
+        })
+    }
+
+    async fn execute(
+        &self,
+        parameters: HashMap<String, Value>,
+        _discord_context: Option<&DiscordContext>,
+    ) -> Result<String, String> {
+        let target = GithubTarget::from_parameters(&parameters)?;
+        self.lookup(&target).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_target_from_url() {
+        assert_eq!(
+            GithubTarget::from_url("https://github.com/serenity-rs/poise"),
+            Some(GithubTarget::Repo {
+                owner: "serenity-rs".to_string(),
+                repo: "poise".to_string(),
+            })
+        );
+        assert_eq!(
+            GithubTarget::from_url("github.com/serenity-rs/serenity/pull/2871/files"),
+            Some(GithubTarget::Issue {
+                owner: "serenity-rs".to_string(),
+                repo: "serenity".to_string(),
+                number: 2871,
+            })
+        );
+        assert_eq!(
+            GithubTarget::from_url(
+                "https://github.com/tokio-rs/tokio/blob/master/tokio/src/lib.rs#L10"
+            ),
+            Some(GithubTarget::File {
+                owner: "tokio-rs".to_string(),
+                repo: "tokio".to_string(),
+                git_ref: Some("master".to_string()),
+                path: "tokio/src/lib.rs".to_string(),
+            })
+        );
+        assert_eq!(GithubTarget::from_url("https://gitlab.com/a/b"), None);
+    }
+
+    #[test]
+    fn test_format_issue() {
+        let issue: Issue = serde_json::from_value(json!({
+            "title": "Panic on startup",
+            "html_url": "https://github.com/a/b/pull/7",
+            "state": "closed",
+            "user": {"login": "gyaru"},
+            "labels": [{"name": "bug"}],
+            "comments": 2,
+            "created_at": "2025-10-25T14:00:00Z",
+            "body": "It panics.",
+            "pull_request": {}
+        }))
+        .unwrap();
+        let pull: PullRequest = serde_json::from_value(json!({
+            "merged": true,
+            "draft": false,
+            "additions": 10,
+            "deletions": 3,
+            "changed_files": 2,
+            "base": {"label": "a:main"},
+            "head": {"label": "gyaru:fix"}
+        }))
+        .unwrap();
+
+        assert_eq!(
+            format_issue("a/b#7", &issue, Some(&pull)),
+            "Pull request a/b#7: Panic on startup (https://github.com/a/b/pull/7)\n\
+             State: merged, opened by gyaru at 2025-10-25T14:00:00Z, 2 comments\n\
+             Labels: bug\n\
+             Merging gyaru:fix into a:main: 2 files changed, +10 -3\n\
+             \nIt panics.\n"
+        );
+    }
+}
@@ -1,6 +1,8 @@
 use super::Tool;
+use super::discord_file::{self, FileUpload};
 use serde_json::{Value, json};
 use std::collections::HashMap;
+use std::time::Duration;
 
 pub struct ImageGenerationTool {
     client: reqwest::Client,
@@ -16,6 +18,11 @@ impl ImageGenerationTool {
             api_key,
         }
     }
+
+    /// Imagen needs a gemini key, without one the tool isn't offered at all
+    pub fn is_configured(&self) -> bool {
+        self.api_key.as_deref().is_some_and(|key| !key.is_empty())
+    }
 }
 
 #[async_trait::async_trait]
@@ -41,10 +48,18 @@ impl Tool for ImageGenerationTool {
         })
     }
 
+    fn needs_discord_context(&self) -> bool {
+        true // the images are uploaded to the channel
+    }
+
+    fn timeout(&self) -> Duration {
+        Duration::from_secs(90) // imagen takes a while, then the images are uploaded
+    }
+
     async fn execute(
         &self,
         parameters: HashMap<String, Value>,
-        _discord_context: Option<&super::DiscordContext>,
+        discord_context: Option<&super::DiscordContext>,
     ) -> Result<String, String> {
         let prompt = parameters
             .get("prompt")
@@ -93,26 +108,67 @@ impl Tool for ImageGenerationTool {
             .await
             .map_err(|e| format!("Failed to parse Imagen API response: {}", e))?;
 
-        // Extract the base64 image data from the response
-        if let Some(predictions) = response_json.get("predictions").and_then(|p| p.as_array()) {
-            if let Some(prediction) = predictions.get(0) {
-                if let Some(base64_data) = prediction
-                    .get("bytesBase64Encoded")
-                    .and_then(|d| d.as_str())
-                {
-                    let mime_type = prediction
-                        .get("mimeType")
-                        .and_then(|m| m.as_str())
-                        .unwrap_or("image/png");
-
-                    // Create a data URL for the image
-                    let image_url = format!("data:{};base64,{}", mime_type, base64_data);
-
-                    return Ok(format!("{}", image_url));
-                }
-            }
-        }
+        let files = generated_images(&response_json)?;
+        let count = files.len();
+        let discord_ctx = discord_context.ok_or("Discord context is required for this tool")?;
+        discord_file::send_files(discord_ctx, files, None, true).await?;
+
+        Ok(format!(
+            "Generated {} image(s) for '{}' and sent them to the channel, don't send them again",
+            count, prompt
+        ))
+    }
+}
+
+/// The images of an Imagen response as attachments
+fn generated_images(response_json: &Value) -> Result<Vec<FileUpload>, String> {
+    let predictions = response_json
+        .get("predictions")
+        .and_then(|p| p.as_array())
+        .ok_or("Failed to extract image data from Imagen API response")?;
+
+    let mut files = Vec::new();
+    for (i, prediction) in predictions.iter().enumerate() {
+        let Some(base64_data) = prediction
+            .get("bytesBase64Encoded")
+            .and_then(|d| d.as_str())
+        else {
+            continue; // filtered by imagen's safety checks
+        };
+        let mime_type = prediction
+            .get("mimeType")
+            .and_then(|m| m.as_str())
+            .unwrap_or("image/png");
+        files.push(FileUpload::from_base64(
+            &format!("data:{};base64,{}", mime_type, base64_data),
+            &format!("chloe_{}", i + 1),
+        )?);
+    }
+    if files.is_empty() {
+        return Err(
+            "Imagen didn't return any images, the prompt may have been filtered".to_string(),
+        );
+    }
+    Ok(files)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_generated_images_become_attachments() {
+        let response = json!({
+            "predictions": [
+                {"bytesBase64Encoded": "aGVsbG8=", "mimeType": "image/png"},
+                {"raiFilteredReason": "filtered"},
+                {"bytesBase64Encoded": "aGk=", "mimeType": "image/jpeg"}
+            ]
+        });
 
-        Err("Failed to extract image data from Imagen API response".to_string())
+        let files = generated_images(&response).unwrap();
+        let names: Vec<&str> = files.iter().map(|file| file.filename.as_str()).collect();
+        assert_eq!(names, vec!["chloe_1.png", "chloe_3.jpg"]);
+        assert!(generated_images(&json!({"predictions": []})).is_err());
     }
 }
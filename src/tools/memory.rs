@@ -0,0 +1,169 @@
+use super::{DiscordContext, Tool};
+use crate::services::user_memories::{
+    MAX_FACT_LENGTH, MAX_FACTS_PER_USER, UserMemoryService, normalize_fact,
+};
+use serde_json::{Value, json};
+use std::collections::HashMap;
+use std::sync::Arc;
+use tracing::error;
+
+/// The guild and user a memory belongs to, facts are only ever about the person talking
+fn memory_owner(discord_context: Option<&DiscordContext>) -> Result<(u64, u64), String> {
+    let discord_ctx = discord_context.ok_or("Discord context is required for this tool")?;
+    let guild_id = discord_ctx
+        .guild_id
+        .ok_or("Memories are only kept in servers, not in DMs")?;
+    Ok((guild_id.get(), discord_ctx.author_id.get()))
+}
+
+pub struct RememberFactTool {
+    memories: Arc<UserMemoryService>,
+}
+
+impl RememberFactTool {
+    pub fn new(memories: Arc<UserMemoryService>) -> Self {
+        Self { memories }
+    }
+}
+
+#[async_trait::async_trait]
+impl Tool for RememberFactTool {
+    fn name(&self) -> &str {
+        "remember_fact"
+    }
+
+    fn description(&self) -> &str {
+        "Remember a lasting fact the user tells you about themselves (birthday, favorite game, main role, pronouns, pets...) for future conversations. Only store facts about the user you're talking to that they shared willingly, never secrets, passwords or things said about other people."
+    }
+
+    fn parameters_schema(&self) -> Value {
+        json!({
+            "type": "object",
+            "properties": {
+                "fact": {
+                    "type": "string",
+                    "description": format!("The fact in one short sentence from your point of view, e.g. 'their birthday is March 3rd' (max {} characters)", MAX_FACT_LENGTH)
+                }
+            },
+            "required": ["fact"]
+        })
+    }
+
+    fn needs_discord_context(&self) -> bool {
+        true // facts belong to the message author
+    }
+
+    async fn execute(
+        &self,
+        parameters: HashMap<String, Value>,
+        discord_context: Option<&DiscordContext>,
+    ) -> Result<String, String> {
+        let fact = parameters
+            .get("fact")
+            .and_then(|v| v.as_str())
+            .ok_or("Missing or invalid 'fact' parameter")?;
+        let fact = normalize_fact(fact).ok_or(format!(
+            "The fact must be between 1 and {} characters",
+            MAX_FACT_LENGTH
+        ))?;
+        let (guild_id, user_id) = memory_owner(discord_context)?;
+
+        match self.memories.remember(guild_id, user_id, &fact).await {
+            Ok(()) => Ok(format!("Remembered: {}", fact)),
+            Err(e) => {
+                error!(
+                    event = "user_memory_store_failed",
+                    guild_id = guild_id,
+                    user_id = user_id,
+                    error = ?e,
+                    "Failed to store user memory"
+                );
+                Err("Couldn't save that right now".to_string())
+            }
+        }
+    }
+}
+
+pub struct RecallFactsTool {
+    memories: Arc<UserMemoryService>,
+}
+
+impl RecallFactsTool {
+    pub fn new(memories: Arc<UserMemoryService>) -> Self {
+        Self { memories }
+    }
+}
+
+#[async_trait::async_trait]
+impl Tool for RecallFactsTool {
+    fn name(&self) -> &str {
+        "recall_facts"
+    }
+
+    fn description(&self) -> &str {
+        "Look up the facts you remembered about the user you're talking to, optionally only those mentioning a word. The most recent ones are already in the prompt, use this when the user asks what you know about them or you need an older one."
+    }
+
+    fn parameters_schema(&self) -> Value {
+        json!({
+            "type": "object",
+            "properties": {
+                "query": {
+                    "type": "string",
+                    "description": "Optional word to filter facts by, e.g. 'birthday'"
+                }
+            },
+            "required": []
+        })
+    }
+
+    fn needs_discord_context(&self) -> bool {
+        true // facts belong to the message author
+    }
+
+    async fn execute(
+        &self,
+        parameters: HashMap<String, Value>,
+        discord_context: Option<&DiscordContext>,
+    ) -> Result<String, String> {
+        let query = parameters
+            .get("query")
+            .and_then(|v| v.as_str())
+            .map(|query| query.trim().to_lowercase())
+            .filter(|query| !query.is_empty());
+        let (guild_id, user_id) = memory_owner(discord_context)?;
+
+        let facts = match self
+            .memories
+            .recall(guild_id, user_id, MAX_FACTS_PER_USER)
+            .await
+        {
+            Ok(facts) => facts,
+            Err(e) => {
+                error!(
+                    event = "user_memory_recall_failed",
+                    guild_id = guild_id,
+                    user_id = user_id,
+                    error = ?e,
+                    "Failed to recall user memories"
+                );
+                return Err("Couldn't look that up right now".to_string());
+            }
+        };
+
+        let matching: Vec<String> = facts
+            .into_iter()
+            .filter(|fact| match &query {
+                Some(query) => fact.to_lowercase().contains(query),
+                None => true,
+            })
+            .collect();
+        if matching.is_empty() {
+            return Ok("You don't remember anything like that about this user".to_string());
+        }
+        Ok(format!(
+            "What you remember about this user:\n- {}",
+            matching.join("\n- ")
+        ))
+    }
+}
@@ -1,27 +1,65 @@
 // Individual tool modules
 pub mod calculator;
+pub mod discord_embed;
+pub mod discord_file;
 pub mod discord_message;
 pub mod discord_reaction;
+pub mod discord_thread;
 pub mod fetch;
+pub mod fetch_policy;
+pub mod full_message;
+pub mod github;
 pub mod image_generation;
+pub mod memory;
+pub mod moderation;
+pub mod reminders;
+pub mod search_backend;
+pub mod summarize;
 pub mod time;
+pub mod translate;
+pub mod weather;
 pub mod web_search;
 
 // Core tool infrastructure
+pub mod argument_repair;
+pub mod confirmation;
+pub mod result_cache;
 pub mod tool_executor;
 pub mod tool_names;
 
 // Re-export all tools for easy access
-pub use discord_message::DiscordSendMessageTool;
+pub use discord_embed::DiscordSendEmbedTool;
+pub use discord_file::DiscordSendFileTool;
+pub use discord_message::{DiscordSendMessageTool, StreamingReply};
 pub use discord_reaction::DiscordAddReactionTool;
+pub use discord_thread::DiscordCreateThreadTool;
 pub use fetch::FetchTool;
+pub use full_message::ReadFullMessageTool;
+pub use github::GithubLookupTool;
 pub use image_generation::ImageGenerationTool;
-pub use web_search::WebSearchTool;
+pub use memory::{RecallFactsTool, RememberFactTool};
+pub use moderation::{DiscordDeleteMessageTool, DiscordKickUserTool, DiscordTimeoutUserTool};
+pub use reminders::{CancelReminderTool, ListRemindersTool, SetReminderTool};
+pub use summarize::SummarizeChannelTool;
+pub use translate::{TranslateTool, Translator};
+pub use weather::WeatherTool;
+pub use web_search::{SearchDomainPolicy, WebSearchTool};
+pub use tool_executor::ToolPolicy;
 pub use tool_names::ToolName;
 
+use crate::services::content_safety::{CONTENT_SAFETY_SETTING, SafetyThresholds};
+use crate::services::guild_service::GuildService;
+use crate::services::llm_service::PromptVersion;
+use crate::services::provider_recorder::PersonalDetails;
+use crate::services::model_router::GuildModelConfig;
+use crate::services::reply_style::{REPLY_STYLE_SETTING, ReplyStyle};
 use serde_json::Value;
 use std::collections::HashMap;
 use std::sync::Arc;
+use std::time::Duration;
+
+/// How long a tool may run before it's cancelled, unless it asks for longer
+pub const DEFAULT_TOOL_TIMEOUT: Duration = Duration::from_secs(30);
 
 #[derive(Debug, Clone)]
 pub struct ToolCall {
@@ -45,12 +83,262 @@ pub struct ToolResult {
     pub error: Option<String>,
 }
 
+impl ToolResult {
+    /// Result of a call cancelled for running past its tool's timeout, worded so the
+    /// model moves on instead of calling it again
+    pub fn timed_out(id: String, tool_name: &str, after: Duration) -> Self {
+        Self {
+            id,
+            success: false,
+            result: String::new(),
+            error: Some(format!(
+                "Tool '{}' timed out after {}s and was cancelled. Don't retry it with the same \
+                 parameters, answer with what you have or tell the user it took too long.",
+                tool_name,
+                after.as_secs()
+            )),
+        }
+    }
+}
+
 #[derive(Clone)]
 pub struct DiscordContext {
     pub http: Arc<serenity::http::Http>,
+    pub shard: serenity::gateway::ShardMessenger, // needed to await component interactions
     pub channel_id: serenity::model::id::ChannelId,
+    pub thread_parent_id: Option<serenity::model::id::ChannelId>, // set when channel_id is a thread
     pub message_id: serenity::model::id::MessageId,
     pub guild_id: Option<serenity::model::id::GuildId>,
+    pub author_id: serenity::model::id::UserId,
+    pub member_roles: Vec<serenity::model::id::RoleId>, // invoking member's discord roles
+    pub channel_nsfw: bool,
+    pub guild_name: Option<String>, // None in DMs or when the guild isn't cached
+    pub channel_name: Option<String>,
+    pub bot_name: String, // chloe's nickname in the guild, or her own display name
+    pub bot_permissions: Option<serenity::model::Permissions>, // None when the guild isn't cached
+    pub user_role: Option<String>, // invoking user's chloe role (member/admin)
+    pub search_domains: SearchDomainPolicy, // guild allow/deny lists for web_search
+    pub search_backend: Option<String>, // guild's preferred web_search backend
+    pub stream_replies: bool, // edit replies in place while the model generates them
+    pub reply_language: Option<String>, // guild language chloe's replies are translated into
+    pub fetch_blocklist: Vec<String>, // guild domains the fetch tool refuses
+    pub tool_policy: ToolPolicy, // guild allow/deny lists for tools
+    pub llm_config: GuildModelConfig, // guild's pinned model and temperature
+    pub emoji_aliases: HashMap<String, String>, // guild alias -> custom emoji name
+    pub reaction_fallback: bool, // react with a unicode stand-in when a custom emoji is missing
+    pub reply_style: ReplyStyle, // guild's verbosity, emoji and embed preferences
+    pub content_safety: SafetyThresholds, // guild's block thresholds, before the global floor
+    pub cached_emojis: Option<Vec<serenity::model::guild::Emoji>>, // None when the guild isn't cached
+    pub edit_message: Option<serenity::model::id::MessageId>, // earlier reply a regenerated answer replaces
+    pub system_prompt: Option<String>, // prompt the answer is built from, placeholders filled in
+    pub prompt_version: Option<PromptVersion>, // which stored prompt that is
+    pub personal_details: PersonalDetails, // names and presence the recorder scrubs
+}
+
+impl DiscordContext {
+    /// Build the tool context for a message, resolving member, channel and permission
+    /// info from the cache up front so tools don't need extra HTTP calls
+    pub async fn from_message(
+        ctx: &serenity::prelude::Context,
+        msg: &serenity::model::channel::Message,
+        guild_service: &GuildService,
+    ) -> Self {
+        let member_roles = msg
+            .member
+            .as_ref()
+            .map(|member| member.roles.clone())
+            .unwrap_or_default();
+
+        let (channel_nsfw, bot_permissions) = match msg.guild_id {
+            Some(guild_id) => Self::resolve_channel_info(ctx, guild_id, msg.channel_id),
+            None => (false, None),
+        };
+        let (guild_name, channel_name, bot_name) = Self::resolve_names(ctx, msg);
+        let thread_parent_id = msg
+            .guild_id
+            .and_then(|guild_id| Self::resolve_thread_parent(ctx, guild_id, msg.channel_id));
+        let cached_emojis = msg.guild_id.and_then(|guild_id| {
+            ctx.cache
+                .guild(guild_id)
+                .map(|guild| guild.emojis.values().cloned().collect())
+        });
+
+        let user_role = match msg.guild_id {
+            Some(guild_id) => {
+                guild_service
+                    .get_user_role(guild_id.get() as i64, msg.author.id.get() as i64)
+                    .await
+            }
+            None => None,
+        };
+
+        // guild settings the tools consult, resolved once per message
+        let guild_setting = |key: &'static str| async move {
+            match msg.guild_id {
+                Some(guild_id) => guild_service.get_guild_setting(guild_id.get() as i64, key).await,
+                None => None,
+            }
+        };
+        let search_domains =
+            SearchDomainPolicy::from_setting(guild_setting("searchDomains").await.as_ref());
+        let search_backend = guild_setting("searchBackend")
+            .await
+            .and_then(|value| value.as_str().map(|name| name.to_lowercase()));
+        let reply_language = guild_setting("replyLanguage").await.and_then(|value| {
+            value
+                .as_str()
+                .map(str::trim)
+                .filter(|language| !language.is_empty())
+                .map(str::to_lowercase)
+        });
+        // a streamed reply would show the text before it's translated
+        let stream_replies = reply_language.is_none()
+            && guild_setting("streamReplies")
+                .await
+                .and_then(|value| value.as_bool())
+                .unwrap_or(true);
+        let fetch_blocklist =
+            web_search::string_list(guild_setting("fetchBlockedDomains").await.as_ref());
+        let tool_policy = ToolPolicy::from_setting(guild_setting("toolPolicy").await.as_ref());
+        let llm_config = GuildModelConfig::from_setting(guild_setting("llmConfig").await.as_ref());
+        let emoji_aliases =
+            discord_reaction::emoji_aliases(guild_setting("emojiAliases").await.as_ref());
+        let reaction_fallback = guild_setting("reactionFallback")
+            .await
+            .and_then(|value| value.as_bool())
+            .unwrap_or(true);
+        let reply_style =
+            ReplyStyle::from_setting(guild_setting(REPLY_STYLE_SETTING).await.as_ref());
+        let content_safety = SafetyThresholds::from_setting(
+            guild_setting(CONTENT_SAFETY_SETTING).await.as_ref(),
+            SafetyThresholds::default(),
+        );
+
+        Self {
+            http: Arc::clone(&ctx.http),
+            shard: ctx.shard.clone(),
+            channel_id: msg.channel_id,
+            thread_parent_id,
+            message_id: msg.id,
+            guild_id: msg.guild_id,
+            author_id: msg.author.id,
+            member_roles,
+            channel_nsfw,
+            guild_name,
+            channel_name,
+            bot_name,
+            bot_permissions,
+            user_role,
+            search_domains,
+            search_backend,
+            stream_replies,
+            reply_language,
+            fetch_blocklist,
+            tool_policy,
+            llm_config,
+            emoji_aliases,
+            reaction_fallback,
+            reply_style,
+            content_safety,
+            cached_emojis,
+            edit_message: None,
+            system_prompt: None,
+            prompt_version: None,
+            personal_details: PersonalDetails::default(),
+        }
+    }
+
+    fn resolve_channel_info(
+        ctx: &serenity::prelude::Context,
+        guild_id: serenity::model::id::GuildId,
+        channel_id: serenity::model::id::ChannelId,
+    ) -> (bool, Option<serenity::model::Permissions>) {
+        let bot_user_id = ctx.cache.current_user().id;
+        let Some(guild) = ctx.cache.guild(guild_id) else {
+            return (false, None);
+        };
+
+        // threads inherit the nsfw flag and permission overwrites from their parent
+        let channel = guild
+            .channels
+            .get(&channel_id)
+            .or_else(|| guild.threads.iter().find(|thread| thread.id == channel_id))
+            .and_then(|channel| match (channel.thread_metadata, channel.parent_id) {
+                (Some(_), Some(parent_id)) => guild.channels.get(&parent_id),
+                _ => Some(channel),
+            });
+
+        let Some(channel) = channel else {
+            return (false, None);
+        };
+
+        let bot_permissions = guild
+            .members
+            .get(&bot_user_id)
+            .map(|member| guild.user_permissions_in(channel, member));
+
+        (channel.nsfw, bot_permissions)
+    }
+
+    fn resolve_names(
+        ctx: &serenity::prelude::Context,
+        msg: &serenity::model::channel::Message,
+    ) -> (Option<String>, Option<String>, String) {
+        let own_name = || ctx.cache.current_user().display_name().to_string();
+        let Some(guild) = msg.guild_id.and_then(|guild_id| ctx.cache.guild(guild_id)) else {
+            return (None, None, own_name());
+        };
+
+        let channel_name = guild
+            .channels
+            .get(&msg.channel_id)
+            .or_else(|| guild.threads.iter().find(|thread| thread.id == msg.channel_id))
+            .map(|channel| channel.name.clone());
+        let bot_name = guild
+            .members
+            .get(&ctx.cache.current_user().id)
+            .map(|member| member.display_name().to_string());
+        (
+            Some(guild.name.clone()),
+            channel_name,
+            bot_name.unwrap_or_else(own_name),
+        )
+    }
+
+    fn resolve_thread_parent(
+        ctx: &serenity::prelude::Context,
+        guild_id: serenity::model::id::GuildId,
+        channel_id: serenity::model::id::ChannelId,
+    ) -> Option<serenity::model::id::ChannelId> {
+        let guild = ctx.cache.guild(guild_id)?;
+        guild
+            .threads
+            .iter()
+            .find(|thread| thread.id == channel_id)
+            .and_then(|thread| thread.parent_id)
+    }
+
+    /// The guild's custom emojis, from the cache when possible so the prompt and
+    /// reactions don't each cost an HTTP call
+    pub async fn guild_emojis(&self) -> serenity::Result<Vec<serenity::model::guild::Emoji>> {
+        match (&self.cached_emojis, self.guild_id) {
+            (Some(emojis), _) => Ok(emojis.clone()),
+            (None, Some(guild_id)) => guild_id.emojis(&self.http).await,
+            (None, None) => Ok(Vec::new()),
+        }
+    }
+
+    /// Whether the conversation is happening in a thread
+    pub fn is_thread(&self) -> bool {
+        self.thread_parent_id.is_some()
+    }
+
+    pub fn bot_has_permission(&self, permission: serenity::model::Permissions) -> bool {
+        // assume the permission is present when the guild isn't cached and let discord decide
+        self.bot_permissions
+            .map(|permissions| permissions.contains(permission))
+            .unwrap_or(true)
+    }
 }
 
 #[async_trait::async_trait]
@@ -64,6 +352,21 @@ pub trait Tool: Send + Sync {
     fn needs_result_feedback(&self) -> bool {
         true // Default: most tools need their results fed back to Gemini
     }
+    fn requires_confirmation(&self) -> bool {
+        false // Default: run immediately, destructive tools opt in to a confirmation step
+    }
+    fn timeout(&self) -> Duration {
+        DEFAULT_TOOL_TIMEOUT // confirmation waits don't count, only execute does
+    }
+    /// Describe what `execute` would do with these parameters, shown to the user before
+    /// confirmation. Must not have side effects.
+    async fn preview(
+        &self,
+        parameters: &HashMap<String, Value>,
+        _discord_context: Option<&DiscordContext>,
+    ) -> Result<String, String> {
+        Ok(format!("run `{}` with {:?}", self.name(), parameters))
+    }
     async fn execute(
         &self,
         parameters: HashMap<String, Value>,
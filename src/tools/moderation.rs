@@ -0,0 +1,481 @@
+use super::{DiscordContext, Tool};
+use crate::services::guild_service::GuildService;
+use chrono::Utc;
+use serde_json::{Value, json};
+use serenity::builder::EditMember;
+use serenity::model::Permissions;
+use serenity::model::Timestamp;
+use serenity::model::id::{GuildId, MessageId, UserId};
+use std::collections::HashMap;
+use std::sync::Arc;
+use tracing::info;
+
+/// Discord doesn't allow timeouts longer than 28 days
+const MAX_TIMEOUT_MINUTES: u64 = 28 * 24 * 60;
+
+/// Discord caps audit log reasons at 512 characters
+const REASON_LIMIT: usize = 512;
+
+/// A user or message id as the model passes it, a plain snowflake or a `<@id>` mention
+fn parse_snowflake(value: &str) -> Option<u64> {
+    let value = value.trim();
+    let value = value
+        .strip_prefix("<@")
+        .and_then(|rest| rest.strip_suffix('>'))
+        .map(|rest| rest.trim_start_matches('!'))
+        .unwrap_or(value);
+    value.parse().ok().filter(|id| *id > 0)
+}
+
+fn reason(parameters: &HashMap<String, Value>) -> Option<String> {
+    parameters
+        .get("reason")
+        .and_then(|v| v.as_str())
+        .map(str::trim)
+        .filter(|reason| !reason.is_empty())
+        .map(|reason| reason.chars().take(REASON_LIMIT).collect())
+}
+
+fn describe_reason(reason: Option<&str>) -> String {
+    reason
+        .map(|reason| format!(" (reason: {})", reason))
+        .unwrap_or_default()
+}
+
+/// Moderation only runs for chloe admins of the guild, and only when chloe herself has
+/// the discord permission it takes
+async fn authorize(
+    guild_service: &GuildService,
+    discord_ctx: &DiscordContext,
+    permission: Permissions,
+) -> Result<GuildId, String> {
+    let guild_id = discord_ctx
+        .guild_id
+        .ok_or("Moderation only works in servers")?;
+    if !guild_service
+        .is_user_admin(guild_id.get() as i64, discord_ctx.author_id.get() as i64)
+        .await
+    {
+        return Err(
+            "Only server admins can ask for moderation actions, refuse politely".to_string(),
+        );
+    }
+    if !discord_ctx.bot_has_permission(permission) {
+        return Err(format!(
+            "I'm missing the {} permission in this server",
+            permission
+        ));
+    }
+    Ok(guild_id)
+}
+
+/// The member a moderation action targets, with their display name for previews
+async fn target_member(
+    guild_service: &GuildService,
+    discord_ctx: &DiscordContext,
+    guild_id: GuildId,
+    parameters: &HashMap<String, Value>,
+) -> Result<(UserId, String), String> {
+    let user_id = parameters
+        .get("user_id")
+        .and_then(|v| v.as_str())
+        .and_then(parse_snowflake)
+        .map(UserId::new)
+        .ok_or("Missing or invalid 'user_id' parameter")?;
+    if user_id == discord_ctx.author_id {
+        return Err("Admins can't use moderation actions on themselves".to_string());
+    }
+    if guild_service
+        .is_user_admin(guild_id.get() as i64, user_id.get() as i64)
+        .await
+    {
+        return Err("That member is a server admin and can't be moderated by me".to_string());
+    }
+    let member = guild_id
+        .member(&discord_ctx.http, user_id)
+        .await
+        .map_err(|_| format!("<@{}> isn't a member of this server", user_id))?;
+    Ok((user_id, member.display_name().to_string()))
+}
+
+fn timeout_minutes(parameters: &HashMap<String, Value>) -> Result<u64, String> {
+    let minutes = parameters
+        .get("duration_minutes")
+        .and_then(|v| v.as_u64())
+        .ok_or("Missing or invalid 'duration_minutes' parameter")?;
+    if minutes > MAX_TIMEOUT_MINUTES {
+        return Err(format!(
+            "Timeouts can last at most {} minutes (28 days)",
+            MAX_TIMEOUT_MINUTES
+        ));
+    }
+    Ok(minutes)
+}
+
+/// Time a member out, or lift their timeout with a duration of 0
+pub struct DiscordTimeoutUserTool {
+    guild_service: Arc<GuildService>,
+}
+
+impl DiscordTimeoutUserTool {
+    pub fn new(guild_service: Arc<GuildService>) -> Self {
+        Self { guild_service }
+    }
+}
+
+#[async_trait::async_trait]
+impl Tool for DiscordTimeoutUserTool {
+    fn name(&self) -> &str {
+        "discord_timeout_user"
+    }
+
+    fn description(&self) -> &str {
+        "Time out a server member so they can't chat for a while, or lift a timeout with duration_minutes 0. Only for server admins asking you to moderate; the admin confirms with a button before it happens."
+    }
+
+    fn parameters_schema(&self) -> Value {
+        json!({
+            "type": "object",
+            "properties": {
+                "user_id": {
+                    "type": "string",
+                    "description": "Discord user ID of the member"
+                },
+                "duration_minutes": {
+                    "type": "integer",
+                    "description": format!("How long the timeout lasts, 0 lifts it (max {})", MAX_TIMEOUT_MINUTES)
+                },
+                "reason": {
+                    "type": "string",
+                    "description": "Reason shown in the audit log"
+                }
+            },
+            "required": ["user_id", "duration_minutes"]
+        })
+    }
+
+    fn needs_discord_context(&self) -> bool {
+        true
+    }
+
+    fn requires_confirmation(&self) -> bool {
+        true
+    }
+
+    async fn preview(
+        &self,
+        parameters: &HashMap<String, Value>,
+        discord_context: Option<&DiscordContext>,
+    ) -> Result<String, String> {
+        let discord_ctx = discord_context.ok_or("Discord context is required for this tool")?;
+        let guild_id = authorize(
+            &self.guild_service,
+            discord_ctx,
+            Permissions::MODERATE_MEMBERS,
+        )
+        .await?;
+        let (user_id, name) =
+            target_member(&self.guild_service, discord_ctx, guild_id, parameters).await?;
+        let reason = reason(parameters);
+        Ok(match timeout_minutes(parameters)? {
+            0 => format!(
+                "lift the timeout of **{}** (`{}`){}",
+                name,
+                user_id,
+                describe_reason(reason.as_deref())
+            ),
+            minutes => format!(
+                "time out **{}** (`{}`) for {} minutes{}",
+                name,
+                user_id,
+                minutes,
+                describe_reason(reason.as_deref())
+            ),
+        })
+    }
+
+    async fn execute(
+        &self,
+        parameters: HashMap<String, Value>,
+        discord_context: Option<&DiscordContext>,
+    ) -> Result<String, String> {
+        let discord_ctx = discord_context.ok_or("Discord context is required for this tool")?;
+        let guild_id = authorize(
+            &self.guild_service,
+            discord_ctx,
+            Permissions::MODERATE_MEMBERS,
+        )
+        .await?;
+        let (user_id, name) =
+            target_member(&self.guild_service, discord_ctx, guild_id, &parameters).await?;
+        let minutes = timeout_minutes(&parameters)?;
+        let reason = reason(&parameters);
+
+        let mut edit = if minutes == 0 {
+            EditMember::new().enable_communication()
+        } else {
+            let until = Utc::now().timestamp() + minutes as i64 * 60;
+            let until = Timestamp::from_unix_timestamp(until)
+                .map_err(|e| format!("Invalid timeout end: {}", e))?;
+            EditMember::new().disable_communication_until_datetime(until)
+        };
+        if let Some(reason) = reason.as_deref() {
+            edit = edit.audit_log_reason(reason);
+        }
+        guild_id
+            .edit_member(&discord_ctx.http, user_id, edit)
+            .await
+            .map_err(|e| format!("Failed to time out {}: {}", name, e))?;
+
+        info!(
+            event = "member_timed_out",
+            guild_id = %guild_id,
+            user_id = %user_id,
+            moderator_id = %discord_ctx.author_id,
+            minutes = minutes,
+            "Timed out a member on an admin's request"
+        );
+        Ok(if minutes == 0 {
+            format!("Lifted the timeout of {}", name)
+        } else {
+            format!("Timed out {} for {} minutes", name, minutes)
+        })
+    }
+}
+
+/// Delete a message in the channel the conversation is in
+pub struct DiscordDeleteMessageTool {
+    guild_service: Arc<GuildService>,
+}
+
+impl DiscordDeleteMessageTool {
+    pub fn new(guild_service: Arc<GuildService>) -> Self {
+        Self { guild_service }
+    }
+}
+
+fn message_id(parameters: &HashMap<String, Value>) -> Result<MessageId, String> {
+    parameters
+        .get("message_id")
+        .and_then(|v| v.as_str())
+        .and_then(parse_snowflake)
+        .map(MessageId::new)
+        .ok_or_else(|| "Missing or invalid 'message_id' parameter".to_string())
+}
+
+#[async_trait::async_trait]
+impl Tool for DiscordDeleteMessageTool {
+    fn name(&self) -> &str {
+        "discord_delete_message"
+    }
+
+    fn description(&self) -> &str {
+        "Delete a message in this channel by its ID, e.g. spam or a message breaking the rules. Only for server admins asking you to moderate; the admin confirms with a button before it happens."
+    }
+
+    fn parameters_schema(&self) -> Value {
+        json!({
+            "type": "object",
+            "properties": {
+                "message_id": {
+                    "type": "string",
+                    "description": "ID of the message to delete, it must be in this channel"
+                },
+                "reason": {
+                    "type": "string",
+                    "description": "Reason shown in the audit log"
+                }
+            },
+            "required": ["message_id"]
+        })
+    }
+
+    fn needs_discord_context(&self) -> bool {
+        true
+    }
+
+    fn requires_confirmation(&self) -> bool {
+        true
+    }
+
+    async fn preview(
+        &self,
+        parameters: &HashMap<String, Value>,
+        discord_context: Option<&DiscordContext>,
+    ) -> Result<String, String> {
+        let discord_ctx = discord_context.ok_or("Discord context is required for this tool")?;
+        authorize(
+            &self.guild_service,
+            discord_ctx,
+            Permissions::MANAGE_MESSAGES,
+        )
+        .await?;
+        let message_id = message_id(parameters)?;
+        let message = discord_ctx
+            .channel_id
+            .message(&discord_ctx.http, message_id)
+            .await
+            .map_err(|_| "There's no message with that ID in this channel".to_string())?;
+        let excerpt: String = message.content.chars().take(200).collect();
+        Ok(format!(
+            "delete this message by **{}**{}:\n> {}",
+            message.author.name,
+            describe_reason(reason(parameters).as_deref()),
+            excerpt.replace('\n', "\n> ")
+        ))
+    }
+
+    async fn execute(
+        &self,
+        parameters: HashMap<String, Value>,
+        discord_context: Option<&DiscordContext>,
+    ) -> Result<String, String> {
+        let discord_ctx = discord_context.ok_or("Discord context is required for this tool")?;
+        let guild_id = authorize(
+            &self.guild_service,
+            discord_ctx,
+            Permissions::MANAGE_MESSAGES,
+        )
+        .await?;
+        let message_id = message_id(&parameters)?;
+        let reason = reason(&parameters);
+
+        discord_ctx
+            .http
+            .delete_message(discord_ctx.channel_id, message_id, reason.as_deref())
+            .await
+            .map_err(|e| format!("Failed to delete the message: {}", e))?;
+
+        info!(
+            event = "message_deleted_by_moderation",
+            guild_id = %guild_id,
+            channel_id = %discord_ctx.channel_id,
+            message_id = %message_id,
+            moderator_id = %discord_ctx.author_id,
+            "Deleted a message on an admin's request"
+        );
+        Ok("Deleted the message".to_string())
+    }
+}
+
+/// Kick a member from the server
+pub struct DiscordKickUserTool {
+    guild_service: Arc<GuildService>,
+}
+
+impl DiscordKickUserTool {
+    pub fn new(guild_service: Arc<GuildService>) -> Self {
+        Self { guild_service }
+    }
+}
+
+#[async_trait::async_trait]
+impl Tool for DiscordKickUserTool {
+    fn name(&self) -> &str {
+        "discord_kick_user"
+    }
+
+    fn description(&self) -> &str {
+        "Kick a member from the server, they can rejoin with an invite. Only for server admins asking you to moderate; the admin confirms with a button before it happens."
+    }
+
+    fn parameters_schema(&self) -> Value {
+        json!({
+            "type": "object",
+            "properties": {
+                "user_id": {
+                    "type": "string",
+                    "description": "Discord user ID of the member"
+                },
+                "reason": {
+                    "type": "string",
+                    "description": "Reason shown in the audit log"
+                }
+            },
+            "required": ["user_id"]
+        })
+    }
+
+    fn needs_discord_context(&self) -> bool {
+        true
+    }
+
+    fn requires_confirmation(&self) -> bool {
+        true
+    }
+
+    async fn preview(
+        &self,
+        parameters: &HashMap<String, Value>,
+        discord_context: Option<&DiscordContext>,
+    ) -> Result<String, String> {
+        let discord_ctx = discord_context.ok_or("Discord context is required for this tool")?;
+        let guild_id =
+            authorize(&self.guild_service, discord_ctx, Permissions::KICK_MEMBERS).await?;
+        let (user_id, name) =
+            target_member(&self.guild_service, discord_ctx, guild_id, parameters).await?;
+        Ok(format!(
+            "kick **{}** (`{}`) from the server{}",
+            name,
+            user_id,
+            describe_reason(reason(parameters).as_deref())
+        ))
+    }
+
+    async fn execute(
+        &self,
+        parameters: HashMap<String, Value>,
+        discord_context: Option<&DiscordContext>,
+    ) -> Result<String, String> {
+        let discord_ctx = discord_context.ok_or("Discord context is required for this tool")?;
+        let guild_id =
+            authorize(&self.guild_service, discord_ctx, Permissions::KICK_MEMBERS).await?;
+        let (user_id, name) =
+            target_member(&self.guild_service, discord_ctx, guild_id, &parameters).await?;
+        let reason = reason(&parameters);
+
+        let kicked = match reason.as_deref() {
+            Some(reason) => {
+                guild_id
+                    .kick_with_reason(&discord_ctx.http, user_id, reason)
+                    .await
+            }
+            None => guild_id.kick(&discord_ctx.http, user_id).await,
+        };
+        kicked.map_err(|e| format!("Failed to kick {}: {}", name, e))?;
+
+        info!(
+            event = "member_kicked",
+            guild_id = %guild_id,
+            user_id = %user_id,
+            moderator_id = %discord_ctx.author_id,
+            "Kicked a member on an admin's request"
+        );
+        Ok(format!("Kicked {} from the server", name))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_snowflake() {
+        assert_eq!(parse_snowflake("123456789"), Some(123456789));
+        assert_eq!(parse_snowflake(" <@123456789> "), Some(123456789));
+        assert_eq!(parse_snowflake("<@!123456789>"), Some(123456789));
+        assert_eq!(parse_snowflake("<#123456789>"), None);
+        assert_eq!(parse_snowflake("0"), None);
+        assert_eq!(parse_snowflake("someone"), None);
+    }
+
+    #[test]
+    fn test_timeout_minutes() {
+        let parameters =
+            |minutes: u64| HashMap::from([("duration_minutes".to_string(), json!(minutes))]);
+        assert_eq!(timeout_minutes(&parameters(0)), Ok(0));
+        assert_eq!(timeout_minutes(&parameters(60)), Ok(60));
+        assert!(timeout_minutes(&parameters(MAX_TIMEOUT_MINUTES + 1)).is_err());
+        assert!(timeout_minutes(&HashMap::new()).is_err());
+    }
+}
@@ -0,0 +1,230 @@
+use super::{DiscordContext, Tool};
+use crate::services::reminders::{
+    MAX_MESSAGE_LENGTH, Reminder, ReminderError, ReminderService, resolve_remind_at,
+};
+use chrono::Utc;
+use serde_json::{Value, json};
+use std::collections::HashMap;
+use std::sync::Arc;
+use tracing::error;
+
+/// How a reminder is listed to the model, with the id it can cancel it by
+fn describe(reminder: &Reminder) -> String {
+    format!(
+        "[{}] {} at {}",
+        reminder.short_id(),
+        reminder.message,
+        reminder.remind_at.format("%Y-%m-%d %H:%M UTC")
+    )
+}
+
+fn database_error(e: impl std::fmt::Debug, user_id: u64) -> String {
+    error!(
+        event = "reminder_tool_failed",
+        user_id = user_id,
+        error = ?e,
+        "Reminder tool failed"
+    );
+    "Couldn't reach the reminder store right now".to_string()
+}
+
+pub struct SetReminderTool {
+    reminders: Arc<ReminderService>,
+}
+
+impl SetReminderTool {
+    pub fn new(reminders: Arc<ReminderService>) -> Self {
+        Self { reminders }
+    }
+}
+
+#[async_trait::async_trait]
+impl Tool for SetReminderTool {
+    fn name(&self) -> &str {
+        "set_reminder"
+    }
+
+    fn description(&self) -> &str {
+        "Set a reminder for the user you're talking to, you will ping them in this channel when it's due. Use it when they ask to be reminded of something."
+    }
+
+    fn parameters_schema(&self) -> Value {
+        json!({
+            "type": "object",
+            "properties": {
+                "when": {
+                    "type": "string",
+                    "description": "A delay like '2h', '30m' or '1d 6h', or an absolute UTC time like '2025-10-25 18:30'. Convert times the user gives in their timezone to UTC."
+                },
+                "message": {
+                    "type": "string",
+                    "description": format!("What to remind them of, as you'd say it to them (max {} characters)", MAX_MESSAGE_LENGTH)
+                }
+            },
+            "required": ["when", "message"]
+        })
+    }
+
+    fn needs_discord_context(&self) -> bool {
+        true // reminders go back to the author's channel
+    }
+
+    async fn execute(
+        &self,
+        parameters: HashMap<String, Value>,
+        discord_context: Option<&DiscordContext>,
+    ) -> Result<String, String> {
+        let string = |key: &str| {
+            parameters
+                .get(key)
+                .and_then(|v| v.as_str())
+                .map(str::trim)
+                .filter(|value| !value.is_empty())
+                .ok_or(format!("Missing or invalid '{}' parameter", key))
+        };
+        let when = string("when")?;
+        let message = string("message")?;
+        if message.chars().count() > MAX_MESSAGE_LENGTH {
+            return Err(format!(
+                "The message must be at most {} characters",
+                MAX_MESSAGE_LENGTH
+            ));
+        }
+        let discord_ctx = discord_context.ok_or("Discord context is required for this tool")?;
+        let remind_at = resolve_remind_at(when, Utc::now())?;
+
+        let user_id = discord_ctx.author_id.get();
+        let reminder = self
+            .reminders
+            .create(
+                discord_ctx.guild_id.map(|id| id.get()),
+                discord_ctx.channel_id.get(),
+                user_id,
+                message,
+                remind_at,
+            )
+            .await
+            .map_err(|e| match e {
+                ReminderError::TooManyPending => e.to_string(),
+                e => database_error(e, user_id),
+            })?;
+        Ok(format!("Reminder set: {}", describe(&reminder)))
+    }
+}
+
+pub struct ListRemindersTool {
+    reminders: Arc<ReminderService>,
+}
+
+impl ListRemindersTool {
+    pub fn new(reminders: Arc<ReminderService>) -> Self {
+        Self { reminders }
+    }
+}
+
+#[async_trait::async_trait]
+impl Tool for ListRemindersTool {
+    fn name(&self) -> &str {
+        "list_reminders"
+    }
+
+    fn description(&self) -> &str {
+        "List the pending reminders of the user you're talking to, with the ids to cancel them by"
+    }
+
+    fn parameters_schema(&self) -> Value {
+        json!({
+            "type": "object",
+            "properties": {},
+            "required": []
+        })
+    }
+
+    fn needs_discord_context(&self) -> bool {
+        true // only the author's reminders are listed
+    }
+
+    async fn execute(
+        &self,
+        _parameters: HashMap<String, Value>,
+        discord_context: Option<&DiscordContext>,
+    ) -> Result<String, String> {
+        let discord_ctx = discord_context.ok_or("Discord context is required for this tool")?;
+        let user_id = discord_ctx.author_id.get();
+        let reminders = self
+            .reminders
+            .pending_for_user(user_id)
+            .await
+            .map_err(|e| database_error(e, user_id))?;
+
+        if reminders.is_empty() {
+            return Ok("The user has no pending reminders".to_string());
+        }
+        let lines: Vec<String> = reminders.iter().map(describe).collect();
+        Ok(format!("Pending reminders:\n- {}", lines.join("\n- ")))
+    }
+}
+
+pub struct CancelReminderTool {
+    reminders: Arc<ReminderService>,
+}
+
+impl CancelReminderTool {
+    pub fn new(reminders: Arc<ReminderService>) -> Self {
+        Self { reminders }
+    }
+}
+
+#[async_trait::async_trait]
+impl Tool for CancelReminderTool {
+    fn name(&self) -> &str {
+        "cancel_reminder"
+    }
+
+    fn description(&self) -> &str {
+        "Cancel one of the user's pending reminders by the id shown by list_reminders or set_reminder"
+    }
+
+    fn parameters_schema(&self) -> Value {
+        json!({
+            "type": "object",
+            "properties": {
+                "id": {
+                    "type": "string",
+                    "description": "The reminder's id, e.g. '3f2a9c1b'"
+                }
+            },
+            "required": ["id"]
+        })
+    }
+
+    fn needs_discord_context(&self) -> bool {
+        true // only the author's reminders can be cancelled
+    }
+
+    async fn execute(
+        &self,
+        parameters: HashMap<String, Value>,
+        discord_context: Option<&DiscordContext>,
+    ) -> Result<String, String> {
+        let id = parameters
+            .get("id")
+            .and_then(|v| v.as_str())
+            .ok_or("Missing or invalid 'id' parameter")?;
+        let discord_ctx = discord_context.ok_or("Discord context is required for this tool")?;
+        let user_id = discord_ctx.author_id.get();
+
+        match self
+            .reminders
+            .cancel(user_id, id)
+            .await
+            .map_err(|e| database_error(e, user_id))?
+        {
+            Some(reminder) => Ok(format!("Cancelled reminder: {}", describe(&reminder))),
+            None => Err(format!(
+                "No pending reminder of this user has the id '{}', use list_reminders to find it",
+                id.trim()
+            )),
+        }
+    }
+}
@@ -0,0 +1,232 @@
+use super::{DiscordContext, Tool, ToolName};
+use crate::utils::cache_stats::{CacheSnapshot, CacheStats};
+use redis::{AsyncCommands, Client};
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+use std::collections::{BTreeMap, HashMap};
+use std::sync::Arc;
+use std::time::Duration;
+use tracing::{info, warn};
+
+/// Default time a result is reused, for the tools worth caching
+const DEFAULT_TTLS: &[(ToolName, u64)] = &[
+    (ToolName::WebSearch, 600),
+    (ToolName::Fetch, 300),
+    (ToolName::GithubLookup, 300),
+];
+
+/// Recent results of tools that call external APIs, in redis so instances share them.
+/// TTLs are per tool and can be changed with `TOOL_CACHE_<TOOL>_SECS`, e.g.
+/// `TOOL_CACHE_FETCH_SECS=60`, where 0 turns caching off for that tool.
+pub struct ToolResultCache {
+    redis_client: Client,
+    ttls: HashMap<&'static str, Duration>,
+    stats: CacheStats,
+}
+
+impl ToolResultCache {
+    pub fn from_env(redis_client: Client) -> Self {
+        Self::from_vars(redis_client, |key| std::env::var(key).ok())
+    }
+
+    fn from_vars(redis_client: Client, var: impl Fn(&str) -> Option<String>) -> Self {
+        let ttls = DEFAULT_TTLS
+            .iter()
+            .filter_map(|(tool, default_secs)| {
+                let key = format!("TOOL_CACHE_{}_SECS", tool.as_str().to_uppercase());
+                let secs = var(&key)
+                    .and_then(|value| value.trim().parse::<u64>().ok())
+                    .unwrap_or(*default_secs);
+                (secs > 0).then(|| (tool.as_str(), Duration::from_secs(secs)))
+            })
+            .collect();
+        Self {
+            redis_client,
+            ttls,
+            stats: CacheStats::default(),
+        }
+    }
+
+    pub fn ttl(&self, tool_name: &str) -> Option<Duration> {
+        self.ttls.get(tool_name).copied()
+    }
+
+    pub fn stats(&self) -> CacheSnapshot {
+        self.stats.snapshot()
+    }
+
+    async fn get(&self, key: &str) -> Option<String> {
+        let mut conn = self
+            .redis_client
+            .get_multiplexed_async_connection()
+            .await
+            .ok()?;
+        conn.get::<_, Option<String>>(key).await.ok().flatten()
+    }
+
+    async fn set(&self, key: &str, result: &str, ttl: Duration) {
+        let stored = async {
+            let mut conn = self.redis_client.get_multiplexed_async_connection().await?;
+            conn.set_ex::<_, _, ()>(key, result, ttl.as_secs()).await
+        }
+        .await;
+        if let Err(e) = stored {
+            warn!(
+                event = "tool_cache_store_failed",
+                key = %key,
+                error = %e,
+                "Failed to cache tool result"
+            );
+        }
+    }
+}
+
+/// Wraps a tool so identical calls within its TTL are answered from the cache. Only
+/// successful results are kept, and each guild gets its own entries since guild
+/// settings like search domains shape the results.
+pub struct CachedTool {
+    inner: Arc<dyn Tool>,
+    cache: Arc<ToolResultCache>,
+    ttl: Duration,
+}
+
+impl CachedTool {
+    pub fn new(inner: Arc<dyn Tool>, cache: Arc<ToolResultCache>, ttl: Duration) -> Self {
+        Self { inner, cache, ttl }
+    }
+}
+
+#[async_trait::async_trait]
+impl Tool for CachedTool {
+    fn name(&self) -> &str {
+        self.inner.name()
+    }
+    fn description(&self) -> &str {
+        self.inner.description()
+    }
+    fn parameters_schema(&self) -> Value {
+        self.inner.parameters_schema()
+    }
+    fn needs_discord_context(&self) -> bool {
+        self.inner.needs_discord_context()
+    }
+    fn needs_result_feedback(&self) -> bool {
+        self.inner.needs_result_feedback()
+    }
+    fn requires_confirmation(&self) -> bool {
+        self.inner.requires_confirmation()
+    }
+    fn timeout(&self) -> Duration {
+        self.inner.timeout()
+    }
+    async fn preview(
+        &self,
+        parameters: &HashMap<String, Value>,
+        discord_context: Option<&DiscordContext>,
+    ) -> Result<String, String> {
+        self.inner.preview(parameters, discord_context).await
+    }
+
+    async fn execute(
+        &self,
+        parameters: HashMap<String, Value>,
+        discord_context: Option<&DiscordContext>,
+    ) -> Result<String, String> {
+        let guild_id = discord_context.and_then(|ctx| ctx.guild_id.map(|id| id.get()));
+        let key = cache_key(self.name(), guild_id, &parameters);
+
+        let cached = self.cache.get(&key).await;
+        self.cache.stats.record(cached.is_some());
+        if let Some(result) = cached {
+            info!(
+                event = "tool_cache_hit",
+                tool_name = %self.name(),
+                result_length = result.len(),
+                "Answered tool call from the cache"
+            );
+            return Ok(result);
+        }
+
+        let result = self.inner.execute(parameters, discord_context).await?;
+        self.cache.set(&key, &result, self.ttl).await;
+        Ok(result)
+    }
+}
+
+/// Same tool, guild and parameters give the same key, whatever order the model wrote
+/// the parameters in and whatever whitespace it left around strings
+fn cache_key(tool_name: &str, guild_id: Option<u64>, parameters: &HashMap<String, Value>) -> String {
+    let normalized: BTreeMap<&str, Value> = parameters
+        .iter()
+        .map(|(name, value)| (name.as_str(), normalize(value)))
+        .collect();
+    let digest: String = Sha256::digest(serde_json::to_string(&normalized).unwrap_or_default())
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect();
+    format!(
+        "chloe:tool_cache:{}:{}:{}",
+        tool_name,
+        guild_id.unwrap_or(0),
+        digest
+    )
+}
+
+fn normalize(value: &Value) -> Value {
+    match value {
+        Value::String(text) => Value::String(text.trim().to_string()),
+        Value::Array(items) => Value::Array(items.iter().map(normalize).collect()),
+        // serde_json's map is ordered, so objects serialize with sorted keys already
+        Value::Object(fields) => Value::Object(
+            fields
+                .iter()
+                .map(|(name, value)| (name.clone(), normalize(value)))
+                .collect(),
+        ),
+        other => other.clone(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn params(value: Value) -> HashMap<String, Value> {
+        serde_json::from_value(value).unwrap()
+    }
+
+    #[test]
+    fn test_cache_key_ignores_order_and_whitespace() {
+        let a = cache_key(
+            "web_search",
+            Some(1),
+            &params(json!({"query": " rust async ", "num_results": 5})),
+        );
+        let b = cache_key(
+            "web_search",
+            Some(1),
+            &params(json!({"num_results": 5, "query": "rust async"})),
+        );
+        assert_eq!(a, b);
+        assert!(a.starts_with("chloe:tool_cache:web_search:1:"));
+
+        let other_guild = cache_key(
+            "web_search",
+            Some(2),
+            &params(json!({"query": "rust async", "num_results": 5})),
+        );
+        assert_ne!(a, other_guild);
+    }
+
+    #[test]
+    fn test_ttls_from_vars() {
+        let client = Client::open("redis://127.0.0.1").unwrap();
+        let cache = ToolResultCache::from_vars(client, |key| {
+            (key == "TOOL_CACHE_FETCH_SECS").then(|| "0".to_string())
+        });
+        assert_eq!(cache.ttl("web_search"), Some(Duration::from_secs(600)));
+        assert_eq!(cache.ttl("fetch"), None);
+        assert_eq!(cache.ttl("calculator"), None);
+    }
+}
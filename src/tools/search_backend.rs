@@ -0,0 +1,463 @@
+use chrono::NaiveDate;
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+
+/// A web search as the model asked for it, after the guild's domain policy was applied
+#[derive(Debug, Clone, PartialEq)]
+pub struct SearchQuery {
+    pub query: String,
+    pub num_results: u32,
+    /// `keyword` or `neural`, only exa tells the two apart
+    pub search_type: String,
+    pub category: Option<String>,
+    pub include_domains: Vec<String>,
+    pub exclude_domains: Vec<String>,
+    pub start_published_date: Option<NaiveDate>,
+    pub end_published_date: Option<NaiveDate>,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct SearchResult {
+    pub title: String,
+    pub url: String,
+    pub author: Option<String>,
+    pub published_date: Option<String>,
+    pub score: Option<f64>,
+    pub snippet: Option<String>,
+}
+
+#[derive(Debug, Default)]
+pub struct SearchResponse {
+    pub results: Vec<SearchResult>,
+    /// The query the backend actually ran, when it rewrote ours
+    pub refined_query: Option<String>,
+}
+
+#[async_trait::async_trait]
+pub trait SearchBackend: Send + Sync {
+    /// Name used by the `SEARCH_BACKEND` env var and the `searchBackend` guild setting
+    fn name(&self) -> &'static str;
+
+    async fn search(&self, query: &SearchQuery) -> Result<SearchResponse, String>;
+}
+
+/// Every backend with credentials in the environment. The one named by `SEARCH_BACKEND`
+/// comes first, otherwise exa, brave, then searxng.
+pub fn configured_backends(client: &reqwest::Client) -> Vec<Arc<dyn SearchBackend>> {
+    let mut backends: Vec<Arc<dyn SearchBackend>> = Vec::new();
+    if let Ok(api_key) = std::env::var("EXA_KEY") {
+        backends.push(Arc::new(ExaBackend::new(client.clone(), api_key)));
+    }
+    if let Ok(api_key) = std::env::var("BRAVE_SEARCH_KEY") {
+        backends.push(Arc::new(BraveBackend::new(client.clone(), api_key)));
+    }
+    if let Ok(base_url) = std::env::var("SEARXNG_URL") {
+        backends.push(Arc::new(SearxngBackend::new(client.clone(), base_url)));
+    }
+
+    let preferred = std::env::var("SEARCH_BACKEND")
+        .unwrap_or_default()
+        .to_lowercase();
+    if let Some(index) = backends
+        .iter()
+        .position(|backend| backend.name() == preferred)
+    {
+        let backend = backends.remove(index);
+        backends.insert(0, backend);
+    }
+    backends
+}
+
+/// Query operators restricting a keyword search to domains, for backends without
+/// native domain filters
+fn site_operators(query: &SearchQuery) -> String {
+    let mut q = query.query.clone();
+    match query.include_domains.as_slice() {
+        [] => {}
+        [domain] => q.push_str(&format!(" site:{}", domain)),
+        domains => q.push_str(&format!(
+            " ({})",
+            domains
+                .iter()
+                .map(|domain| format!("site:{}", domain))
+                .collect::<Vec<_>>()
+                .join(" OR ")
+        )),
+    }
+    for domain in &query.exclude_domains {
+        q.push_str(&format!(" -site:{}", domain));
+    }
+    q
+}
+
+async fn send_json<T: serde::de::DeserializeOwned>(
+    backend: &str,
+    request: reqwest::RequestBuilder,
+) -> Result<T, String> {
+    let response = request
+        .send()
+        .await
+        .map_err(|e| format!("Failed to send request to {}: {}", backend, e))?;
+
+    if !response.status().is_success() {
+        let status = response.status();
+        let error_text = response.text().await.unwrap_or_default();
+        return Err(format!(
+            "{} request failed with status {}: {}",
+            backend, status, error_text
+        ));
+    }
+
+    response
+        .json()
+        .await
+        .map_err(|e| format!("Failed to parse {} response: {}", backend, e))
+}
+
+#[derive(Debug, Serialize)]
+struct ExaSearchRequest {
+    query: String,
+    #[serde(rename = "numResults")]
+    num_results: u32,
+    #[serde(rename = "includeDomains")]
+    include_domains: Option<Vec<String>>,
+    #[serde(rename = "excludeDomains")]
+    exclude_domains: Option<Vec<String>>,
+    #[serde(rename = "startCrawlDate")]
+    start_crawl_date: Option<String>,
+    #[serde(rename = "endCrawlDate")]
+    end_crawl_date: Option<String>,
+    #[serde(rename = "startPublishedDate")]
+    start_published_date: Option<String>,
+    #[serde(rename = "endPublishedDate")]
+    end_published_date: Option<String>,
+    #[serde(rename = "useAutoprompt")]
+    use_autoprompt: Option<bool>,
+    r#type: Option<String>,
+    category: Option<String>,
+}
+
+impl ExaSearchRequest {
+    fn from_query(query: &SearchQuery) -> Self {
+        // exa rejects requests with both lists, and the tool already removed denied
+        // domains from the includes
+        let (include_domains, exclude_domains) = if query.include_domains.is_empty() {
+            (
+                None,
+                (!query.exclude_domains.is_empty()).then(|| query.exclude_domains.clone()),
+            )
+        } else {
+            (Some(query.include_domains.clone()), None)
+        };
+
+        Self {
+            query: query.query.clone(),
+            num_results: query.num_results,
+            include_domains,
+            exclude_domains,
+            start_crawl_date: None,
+            end_crawl_date: None,
+            start_published_date: query
+                .start_published_date
+                .map(|date| format!("{}T00:00:00.000Z", date)),
+            end_published_date: query
+                .end_published_date
+                .map(|date| format!("{}T23:59:59.999Z", date)),
+            use_autoprompt: Some(true),
+            r#type: Some(query.search_type.clone()),
+            category: query.category.clone(),
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct ExaSearchResponse {
+    results: Vec<ExaResult>,
+    #[serde(rename = "autopromptString")]
+    autoprompt_string: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ExaResult {
+    url: String,
+    title: String,
+    score: Option<f64>,
+    #[serde(rename = "publishedDate")]
+    published_date: Option<String>,
+    author: Option<String>,
+    text: Option<String>,
+}
+
+pub struct ExaBackend {
+    client: reqwest::Client,
+    api_key: String,
+}
+
+impl ExaBackend {
+    pub fn new(client: reqwest::Client, api_key: String) -> Self {
+        Self { client, api_key }
+    }
+}
+
+#[async_trait::async_trait]
+impl SearchBackend for ExaBackend {
+    fn name(&self) -> &'static str {
+        "exa"
+    }
+
+    async fn search(&self, query: &SearchQuery) -> Result<SearchResponse, String> {
+        let request = self
+            .client
+            .post("https://api.exa.ai/search")
+            .header("accept", "application/json")
+            .header("content-type", "application/json")
+            .header("x-api-key", &self.api_key)
+            .json(&ExaSearchRequest::from_query(query));
+        let response: ExaSearchResponse = send_json("Exa API", request).await?;
+
+        Ok(SearchResponse {
+            results: response
+                .results
+                .into_iter()
+                .map(|result| SearchResult {
+                    title: result.title,
+                    url: result.url,
+                    author: result.author,
+                    published_date: result.published_date,
+                    score: result.score,
+                    snippet: result.text,
+                })
+                .collect(),
+            refined_query: response.autoprompt_string,
+        })
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct BraveSearchResponse {
+    web: Option<BraveWebResults>,
+}
+
+#[derive(Debug, Deserialize)]
+struct BraveWebResults {
+    results: Vec<BraveResult>,
+}
+
+#[derive(Debug, Deserialize)]
+struct BraveResult {
+    title: String,
+    url: String,
+    description: Option<String>,
+    page_age: Option<String>,
+}
+
+pub struct BraveBackend {
+    client: reqwest::Client,
+    api_key: String,
+}
+
+impl BraveBackend {
+    pub fn new(client: reqwest::Client, api_key: String) -> Self {
+        Self { client, api_key }
+    }
+}
+
+/// Brave's `freshness` range, open ends padded since it needs both dates
+fn brave_freshness(query: &SearchQuery) -> Option<String> {
+    if query.start_published_date.is_none() && query.end_published_date.is_none() {
+        return None;
+    }
+    let start = query
+        .start_published_date
+        .unwrap_or(NaiveDate::from_ymd_opt(1970, 1, 1).unwrap_or_default());
+    let end = query
+        .end_published_date
+        .unwrap_or_else(|| chrono::Utc::now().date_naive());
+    Some(format!("{}to{}", start, end))
+}
+
+#[async_trait::async_trait]
+impl SearchBackend for BraveBackend {
+    fn name(&self) -> &'static str {
+        "brave"
+    }
+
+    async fn search(&self, query: &SearchQuery) -> Result<SearchResponse, String> {
+        let mut params = vec![
+            ("q", site_operators(query)),
+            ("count", query.num_results.to_string()),
+        ];
+        if let Some(freshness) = brave_freshness(query) {
+            params.push(("freshness", freshness));
+        }
+
+        let request = self
+            .client
+            .get("https://api.search.brave.com/res/v1/web/search")
+            .header("accept", "application/json")
+            .header("x-subscription-token", &self.api_key)
+            .query(&params);
+        let response: BraveSearchResponse = send_json("Brave Search API", request).await?;
+
+        Ok(SearchResponse {
+            results: response
+                .web
+                .map(|web| web.results)
+                .unwrap_or_default()
+                .into_iter()
+                .map(|result| SearchResult {
+                    title: result.title,
+                    url: result.url,
+                    published_date: result.page_age,
+                    snippet: result.description,
+                    ..Default::default()
+                })
+                .collect(),
+            refined_query: None,
+        })
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct SearxngResponse {
+    results: Vec<SearxngResult>,
+}
+
+#[derive(Debug, Deserialize)]
+struct SearxngResult {
+    title: String,
+    url: String,
+    content: Option<String>,
+    #[serde(rename = "publishedDate")]
+    published_date: Option<String>,
+    score: Option<f64>,
+}
+
+/// A self-hosted SearxNG instance, which needs `json` enabled in its `search.formats`
+pub struct SearxngBackend {
+    client: reqwest::Client,
+    base_url: String,
+}
+
+impl SearxngBackend {
+    pub fn new(client: reqwest::Client, base_url: String) -> Self {
+        Self {
+            client,
+            base_url: base_url.trim_end_matches('/').to_string(),
+        }
+    }
+}
+
+/// SearxNG only filters by coarse ranges, so take the smallest one covering the start date
+fn searxng_time_range(query: &SearchQuery) -> Option<&'static str> {
+    let days = (chrono::Utc::now().date_naive() - query.start_published_date?).num_days();
+    Some(match days {
+        ..=1 => "day",
+        2..=7 => "week",
+        8..=31 => "month",
+        _ => "year",
+    })
+}
+
+#[async_trait::async_trait]
+impl SearchBackend for SearxngBackend {
+    fn name(&self) -> &'static str {
+        "searxng"
+    }
+
+    async fn search(&self, query: &SearchQuery) -> Result<SearchResponse, String> {
+        let mut params = vec![("q", site_operators(query)), ("format", "json".to_string())];
+        if query.category.as_deref() == Some("news") {
+            params.push(("categories", "news".to_string()));
+        }
+        if let Some(time_range) = searxng_time_range(query) {
+            params.push(("time_range", time_range.to_string()));
+        }
+
+        let request = self
+            .client
+            .get(format!("{}/search", self.base_url))
+            .query(&params);
+        let response: SearxngResponse = send_json("SearxNG", request).await?;
+
+        Ok(SearchResponse {
+            results: response
+                .results
+                .into_iter()
+                .take(query.num_results as usize)
+                .map(|result| SearchResult {
+                    title: result.title,
+                    url: result.url,
+                    published_date: result.published_date,
+                    score: result.score,
+                    snippet: result.content,
+                    ..Default::default()
+                })
+                .collect(),
+            refined_query: None,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn query() -> SearchQuery {
+        SearchQuery {
+            query: "rust".to_string(),
+            num_results: 5,
+            search_type: "keyword".to_string(),
+            category: None,
+            include_domains: Vec::new(),
+            exclude_domains: Vec::new(),
+            start_published_date: None,
+            end_published_date: None,
+        }
+    }
+
+    #[test]
+    fn test_site_operators() {
+        let mut q = query();
+        q.include_domains = vec!["a.com".to_string(), "b.com".to_string()];
+        q.exclude_domains = vec!["c.com".to_string()];
+        assert_eq!(
+            site_operators(&q),
+            "rust (site:a.com OR site:b.com) -site:c.com"
+        );
+
+        q.include_domains.truncate(1);
+        q.exclude_domains.clear();
+        assert_eq!(site_operators(&q), "rust site:a.com");
+    }
+
+    #[test]
+    fn test_exa_request_sends_one_domain_list() {
+        let mut q = query();
+        q.exclude_domains = vec!["c.com".to_string()];
+        q.start_published_date = NaiveDate::from_ymd_opt(2024, 1, 31);
+        let request = ExaSearchRequest::from_query(&q);
+        assert_eq!(request.exclude_domains, Some(vec!["c.com".to_string()]));
+        assert_eq!(
+            request.start_published_date.as_deref(),
+            Some("2024-01-31T00:00:00.000Z")
+        );
+
+        q.include_domains = vec!["a.com".to_string()];
+        let request = ExaSearchRequest::from_query(&q);
+        assert_eq!(request.include_domains, Some(vec!["a.com".to_string()]));
+        assert!(request.exclude_domains.is_none());
+    }
+
+    #[test]
+    fn test_brave_freshness_pads_open_ranges() {
+        let mut q = query();
+        assert_eq!(brave_freshness(&q), None);
+
+        q.end_published_date = NaiveDate::from_ymd_opt(2024, 6, 1);
+        assert_eq!(
+            brave_freshness(&q).as_deref(),
+            Some("1970-01-01to2024-06-01")
+        );
+    }
+}
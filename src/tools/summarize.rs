@@ -0,0 +1,117 @@
+use super::{DiscordContext, Tool};
+use crate::services::summarizer::{self, HistoryRange, MAX_CHUNK_CHARS, MAX_MESSAGE_COUNT};
+use chrono::Utc;
+use serde_json::{Value, json};
+use serenity::model::Permissions;
+use std::collections::HashMap;
+
+/// Reads the recent history of the channel chloe was asked in, in parts that fit the
+/// context, so the model can recap it
+pub struct SummarizeChannelTool;
+
+impl SummarizeChannelTool {
+    pub fn new() -> Self {
+        Self
+    }
+}
+
+impl Default for SummarizeChannelTool {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[async_trait::async_trait]
+impl Tool for SummarizeChannelTool {
+    fn name(&self) -> &str {
+        "summarize_channel"
+    }
+
+    fn description(&self) -> &str {
+        "Read this channel's recent messages to recap them, e.g. when someone asks what they missed or for a summary of a discussion. Returns the transcript with instructions for the recap."
+    }
+
+    fn parameters_schema(&self) -> Value {
+        json!({
+            "type": "object",
+            "properties": {
+                "count": {
+                    "type": "integer",
+                    "description": format!("How many of the latest messages to read, 100 by default, at most {}", MAX_MESSAGE_COUNT)
+                },
+                "since": {
+                    "type": "string",
+                    "description": "Read everything from this long ago instead of a count, like 2h or 1d 12h"
+                },
+                "part": {
+                    "type": "integer",
+                    "description": "Which part of a long transcript to read, starting at 1. Use the next part from the previous result to keep reading."
+                }
+            }
+        })
+    }
+
+    fn needs_discord_context(&self) -> bool {
+        true // only the channel chloe was asked in is read
+    }
+
+    async fn execute(
+        &self,
+        parameters: HashMap<String, Value>,
+        discord_context: Option<&DiscordContext>,
+    ) -> Result<String, String> {
+        let discord_ctx = discord_context.ok_or("Discord context is required for this tool")?;
+        if !discord_ctx.bot_has_permission(Permissions::READ_MESSAGE_HISTORY) {
+            return Err("I can't read this channel's history, tell the user I'm missing the Read Message History permission".to_string());
+        }
+
+        let count = parameters
+            .get("count")
+            .and_then(Value::as_u64)
+            .map(|count| count as usize);
+        let since = parameters.get("since").and_then(Value::as_str);
+        let range = HistoryRange::from_options(count, since, Utc::now())?;
+        let part = parameters
+            .get("part")
+            .and_then(Value::as_u64)
+            .unwrap_or(1)
+            .max(1) as usize;
+
+        let messages = summarizer::fetch_history(
+            &discord_ctx.http,
+            discord_ctx.channel_id,
+            Some(discord_ctx.message_id),
+            range,
+        )
+        .await
+        .map_err(|e| format!("Failed to read the channel: {}", e))?;
+        let lines = summarizer::transcript(&messages);
+        if lines.is_empty() {
+            return Ok("There are no messages to summarize in that range".to_string());
+        }
+
+        let chunks = summarizer::chunk_transcript(&lines, MAX_CHUNK_CHARS);
+        let Some(chunk) = chunks.get(part - 1) else {
+            return Err(format!("The transcript only has {} part(s)", chunks.len()));
+        };
+        let mut result = format!(
+            "Transcript of {} messages, part {} of {}:\n{}",
+            lines.len(),
+            part,
+            chunks.len(),
+            chunk
+        );
+        if part < chunks.len() {
+            result.push_str(&format!(
+                "\n\n[Take notes on this part, then call summarize_channel again with the same range and part {} before writing the recap]",
+                part + 1
+            ));
+        } else {
+            result.push_str(&format!(
+                "\n\n[That's everything. Write the recap following these instructions]\n{}",
+                summarizer::RECAP_PROMPT
+            ));
+        }
+        Ok(result)
+    }
+}
@@ -1,9 +1,40 @@
-use super::{DiscordContext, Tool, ToolCall, ToolResult};
+use super::argument_repair;
+use super::confirmation::{self, CONFIRMATION_TIMEOUT, ConfirmationOutcome};
+use super::result_cache::{CachedTool, ToolResultCache};
+use super::web_search::string_list;
+use super::{DiscordContext, Tool, ToolCall, ToolName, ToolResult};
+use futures::future::join_all;
 use serde_json::Value;
 use std::collections::HashMap;
 use std::sync::Arc;
 use tracing::{error, info};
 
+/// Guild allow/deny lists for tools, an empty allow list means every tool
+#[derive(Debug, Clone, Default)]
+pub struct ToolPolicy {
+    pub allow: Vec<String>,
+    pub deny: Vec<String>,
+}
+
+impl ToolPolicy {
+    pub fn from_setting(setting: Option<&Value>) -> Self {
+        let tools = |key: &str| setting.map(|s| string_list(s.get(key))).unwrap_or_default();
+        Self {
+            allow: tools("allow"),
+            deny: tools("deny"),
+        }
+    }
+
+    pub fn is_enabled(&self, name: &str) -> bool {
+        // chloe replies through discord_send_message, so it can't be turned off
+        if name == ToolName::DiscordSendMessage.as_str() {
+            return true;
+        }
+        let listed = |tools: &[String]| tools.iter().any(|tool| tool == name);
+        !listed(&self.deny) && (self.allow.is_empty() || listed(&self.allow))
+    }
+}
+
 pub struct ToolExecutor {
     tools: HashMap<String, Arc<dyn Tool>>,
 }
@@ -20,6 +51,27 @@ impl ToolExecutor {
         self.tools.insert(name, tool);
     }
 
+    /// Put every tool the cache has a TTL for behind it
+    pub fn with_result_cache(mut self, cache: Arc<ToolResultCache>) -> Self {
+        for tool in self.tools.values_mut() {
+            if let Some(ttl) = cache.ttl(tool.name()) {
+                *tool = Arc::new(CachedTool::new(Arc::clone(tool), Arc::clone(&cache), ttl));
+            }
+        }
+        self
+    }
+
+    /// A call's arguments repaired against its tool's schema, None when they can't be
+    /// salvaged
+    pub fn repair_arguments(&self, name: &str, args: &Value) -> Option<Value> {
+        let schema = self
+            .tools
+            .get(name)
+            .map(|tool| tool.parameters_schema())
+            .unwrap_or(Value::Null);
+        argument_repair::repair_arguments(args, &schema)
+    }
+
     pub fn get_tool_definitions(&self) -> Vec<Value> {
         self.tools
             .values()
@@ -33,6 +85,25 @@ impl ToolExecutor {
             .collect()
     }
 
+    /// Definitions of the tools the guild has enabled, what the model gets to see
+    pub fn get_enabled_tool_definitions(
+        &self,
+        discord_context: Option<&DiscordContext>,
+    ) -> Vec<Value> {
+        let Some(discord_ctx) = discord_context else {
+            return self.get_tool_definitions();
+        };
+        self.get_tool_definitions()
+            .into_iter()
+            .filter(|definition| {
+                definition
+                    .get("name")
+                    .and_then(|name| name.as_str())
+                    .is_some_and(|name| discord_ctx.tool_policy.is_enabled(name))
+            })
+            .collect()
+    }
+
     pub async fn execute_tool(
         &self,
         tool_call: ToolCall,
@@ -52,9 +123,33 @@ impl ToolExecutor {
             tool_name = %tool_call.name,
             tool_id = %tool_call.id,
             parameters = ?tool_call.parameters,
+            user_id = discord_context.map(|ctx| ctx.author_id.get()),
+            user_role = ?discord_context.and_then(|ctx| ctx.user_role.as_deref()),
+            member_roles = ?discord_context.map(|ctx| &ctx.member_roles),
             "Starting tool execution"
         );
 
+        if let Some(discord_ctx) = discord_context
+            && !discord_ctx.tool_policy.is_enabled(&tool_call.name)
+        {
+            info!(
+                event = "tool_execution_disabled",
+                tool_name = %tool_call.name,
+                tool_id = %tool_call.id,
+                guild_id = ?discord_ctx.guild_id.map(|id| id.get()),
+                "Tool is disabled in this guild"
+            );
+            return ToolResult {
+                id: tool_call.id,
+                success: false,
+                result: String::new(),
+                error: Some(format!(
+                    "Tool '{}' is disabled in this server",
+                    tool_call.name
+                )),
+            };
+        }
+
         let result = match self.tools.get(&tool_call.name) {
             Some(tool) => {
                 // Check if this tool needs Discord context
@@ -72,10 +167,46 @@ impl ToolExecutor {
                     }
                     discord_context
                 } else {
-                    None // Don't pass Discord context for tools that don't need it
+                    discord_context // optional for the rest, e.g. guild search and fetch policies
+                };
+
+                let confirmation = if tool.requires_confirmation() {
+                    self.confirm_tool_call(tool.as_ref(), &tool_call, discord_context)
+                        .await
+                } else {
+                    Ok(())
                 };
 
-                match tool.execute(tool_call.parameters, context_to_pass).await {
+                if let Err(error) = confirmation {
+                    info!(
+                        event = "tool_execution_not_confirmed",
+                        tool_name = %tool_call.name,
+                        tool_id = %tool_call.id,
+                        reason = %error,
+                        "Tool execution skipped without confirmation"
+                    );
+                    return ToolResult {
+                        id: tool_call.id,
+                        success: false,
+                        result: String::new(),
+                        error: Some(error),
+                    };
+                }
+
+                let timeout = tool.timeout();
+                let execution = tool.execute(tool_call.parameters, context_to_pass);
+                let Ok(outcome) = tokio::time::timeout(timeout, execution).await else {
+                    error!(
+                        event = "tool_execution_timeout",
+                        tool_name = %tool_call.name,
+                        tool_id = %tool_call.id,
+                        timeout_secs = timeout.as_secs(),
+                        "Tool execution timed out and was cancelled"
+                    );
+                    return ToolResult::timed_out(tool_call.id, &tool_call.name, timeout);
+                };
+
+                match outcome {
                     Ok(result) => {
                         info!(
                             event = "tool_execution_success",
@@ -127,6 +258,74 @@ impl ToolExecutor {
         result
     }
 
+    /// Run the tool calls of one model turn, independent tools concurrently and the
+    /// discord side ones one after another, results come back in call order
+    pub async fn execute_tools_parallel(
+        &self,
+        tool_calls: Vec<ToolCall>,
+        discord_context: Option<&DiscordContext>,
+    ) -> Vec<ToolResult> {
+        let (ordered, independent): (Vec<_>, Vec<_>) = tool_calls
+            .into_iter()
+            .enumerate()
+            .partition(|(_, tool_call)| {
+                tool_call
+                    .tool_name()
+                    .is_ok_and(|tool_name| tool_name.runs_in_order())
+            });
+
+        info!(
+            event = "parallel_tool_execution_start",
+            concurrent = independent.len(),
+            ordered = ordered.len(),
+            "Starting tool batch"
+        );
+
+        let concurrent = join_all(independent.into_iter().map(|(index, tool_call)| async move {
+            (index, self.execute_tool(tool_call, discord_context).await)
+        }));
+        let sequential = async {
+            let mut results = Vec::with_capacity(ordered.len());
+            for (index, tool_call) in ordered {
+                results.push((index, self.execute_tool(tool_call, discord_context).await));
+            }
+            results
+        };
+        let (concurrent, sequential) = tokio::join!(concurrent, sequential);
+
+        let mut results: Vec<_> = concurrent.into_iter().chain(sequential).collect();
+        results.sort_by_key(|(index, _)| *index);
+        results.into_iter().map(|(_, result)| result).collect()
+    }
+
+    // Preview a destructive tool call and wait for an authorized user to confirm it
+    async fn confirm_tool_call(
+        &self,
+        tool: &dyn Tool,
+        tool_call: &ToolCall,
+        discord_context: Option<&DiscordContext>,
+    ) -> Result<(), String> {
+        let discord_ctx = discord_context.ok_or_else(|| {
+            format!(
+                "Tool '{}' requires confirmation but no Discord context was provided",
+                tool_call.name
+            )
+        })?;
+
+        let preview = tool.preview(&tool_call.parameters, Some(discord_ctx)).await?;
+
+        match confirmation::request_confirmation(discord_ctx, &tool_call.name, &preview).await? {
+            ConfirmationOutcome::Confirmed => Ok(()),
+            ConfirmationOutcome::Cancelled => {
+                Err("The user cancelled this action. Don't retry it.".to_string())
+            }
+            ConfirmationOutcome::TimedOut => Err(format!(
+                "Nobody confirmed this action within {}s, so it was not performed",
+                CONFIRMATION_TIMEOUT.as_secs()
+            )),
+        }
+    }
+
     pub fn has_tool(&self, name: &str) -> bool {
         self.tools.contains_key(name)
     }
@@ -138,3 +337,153 @@ impl ToolExecutor {
             .unwrap_or(true) // Default to true if tool not found
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use std::time::{Duration, Instant};
+
+    struct SlowTool(&'static str);
+
+    #[async_trait::async_trait]
+    impl Tool for SlowTool {
+        fn name(&self) -> &str {
+            self.0
+        }
+        fn description(&self) -> &str {
+            "sleeps, then echoes its name"
+        }
+        fn parameters_schema(&self) -> Value {
+            json!({"type": "object", "properties": {}})
+        }
+        async fn execute(
+            &self,
+            _parameters: HashMap<String, Value>,
+            _discord_context: Option<&DiscordContext>,
+        ) -> Result<String, String> {
+            tokio::time::sleep(Duration::from_millis(100)).await;
+            Ok(self.0.to_string())
+        }
+    }
+
+    struct HangingTool;
+
+    #[async_trait::async_trait]
+    impl Tool for HangingTool {
+        fn name(&self) -> &str {
+            "fetch"
+        }
+        fn description(&self) -> &str {
+            "never finishes"
+        }
+        fn parameters_schema(&self) -> Value {
+            json!({"type": "object", "properties": {}})
+        }
+        fn timeout(&self) -> Duration {
+            Duration::from_millis(20)
+        }
+        async fn execute(
+            &self,
+            _parameters: HashMap<String, Value>,
+            _discord_context: Option<&DiscordContext>,
+        ) -> Result<String, String> {
+            std::future::pending().await
+        }
+    }
+
+    fn call(name: &str) -> ToolCall {
+        ToolCall {
+            id: format!("call_{}", name),
+            name: name.to_string(),
+            parameters: HashMap::new(),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_execute_tools_parallel_runs_independent_tools_together() {
+        let mut executor = ToolExecutor::new();
+        for name in ["web_search", "fetch", "get_weather"] {
+            executor.register_tool(Arc::new(SlowTool(name)));
+        }
+
+        let started = Instant::now();
+        let results = executor
+            .execute_tools_parallel(
+                vec![call("web_search"), call("fetch"), call("get_weather")],
+                None,
+            )
+            .await;
+
+        assert!(started.elapsed() < Duration::from_millis(250));
+        let ids: Vec<_> = results.iter().map(|result| result.id.as_str()).collect();
+        assert_eq!(ids, ["call_web_search", "call_fetch", "call_get_weather"]);
+        assert!(results.iter().all(|result| result.success));
+    }
+
+    #[tokio::test]
+    async fn test_execute_tools_parallel_keeps_discord_tools_in_order() {
+        let mut executor = ToolExecutor::new();
+        for name in ["discord_add_reaction", "discord_send_embed", "web_search"] {
+            executor.register_tool(Arc::new(SlowTool(name)));
+        }
+
+        let started = Instant::now();
+        let results = executor
+            .execute_tools_parallel(
+                vec![
+                    call("discord_add_reaction"),
+                    call("web_search"),
+                    call("discord_send_embed"),
+                ],
+                None,
+            )
+            .await;
+
+        // the two discord tools run back to back, web_search alongside them
+        let elapsed = started.elapsed();
+        assert!(elapsed >= Duration::from_millis(200) && elapsed < Duration::from_millis(300));
+        let results: Vec<_> = results.iter().map(|result| result.result.as_str()).collect();
+        assert_eq!(results, ["discord_add_reaction", "web_search", "discord_send_embed"]);
+    }
+
+    #[test]
+    fn test_tool_policy_allow_and_deny() {
+        let everything = ToolPolicy::from_setting(None);
+        assert!(everything.is_enabled("fetch"));
+
+        let denied = ToolPolicy::from_setting(Some(&json!({"deny": ["Fetch", "generate_image"]})));
+        assert!(!denied.is_enabled("fetch"));
+        assert!(!denied.is_enabled("generate_image"));
+        assert!(denied.is_enabled("web_search"));
+
+        let allowed = ToolPolicy::from_setting(Some(&json!({
+            "allow": ["web_search", "fetch"],
+            "deny": ["fetch"]
+        })));
+        assert!(allowed.is_enabled("web_search"));
+        assert!(!allowed.is_enabled("fetch"));
+        assert!(!allowed.is_enabled("calculator"));
+    }
+
+    #[test]
+    fn test_tool_policy_keeps_send_message() {
+        let policy = ToolPolicy::from_setting(Some(&json!({
+            "allow": ["web_search"],
+            "deny": ["discord_send_message"]
+        })));
+        assert!(policy.is_enabled("discord_send_message"));
+    }
+
+    #[tokio::test]
+    async fn test_execute_tool_times_out() {
+        let mut executor = ToolExecutor::new();
+        executor.register_tool(Arc::new(HangingTool));
+
+        let result = executor.execute_tool(call("fetch"), None).await;
+
+        assert!(!result.success);
+        assert_eq!(result.id, "call_fetch");
+        assert!(result.error.unwrap().contains("timed out"));
+    }
+}
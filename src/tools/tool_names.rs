@@ -9,8 +9,20 @@ pub enum ToolName {
     Fetch,
     #[serde(rename = "discord_send_message")]
     DiscordSendMessage,
+    #[serde(rename = "discord_send_embed")]
+    DiscordSendEmbed,
+    #[serde(rename = "discord_send_file")]
+    DiscordSendFile,
+    #[serde(rename = "discord_create_thread")]
+    DiscordCreateThread,
     #[serde(rename = "discord_add_reaction")]
     DiscordAddReaction,
+    #[serde(rename = "discord_timeout_user")]
+    DiscordTimeoutUser,
+    #[serde(rename = "discord_delete_message")]
+    DiscordDeleteMessage,
+    #[serde(rename = "discord_kick_user")]
+    DiscordKickUser,
     #[serde(rename = "generate_image")]
     GenerateImage,
     #[serde(rename = "playwright_web_content")]
@@ -18,6 +30,16 @@ pub enum ToolName {
     #[serde(rename = "get_time")]
     GetTime,
     Calculator,
+    RememberFact,
+    RecallFacts,
+    SetReminder,
+    ListReminders,
+    CancelReminder,
+    GetWeather,
+    Translate,
+    GithubLookup,
+    ReadFullMessage,
+    SummarizeChannel,
 }
 
 impl ToolName {
@@ -26,11 +48,27 @@ impl ToolName {
             "web_search" => Ok(Self::WebSearch),
             "fetch" => Ok(Self::Fetch),
             "discord_send_message" => Ok(Self::DiscordSendMessage),
+            "discord_send_embed" => Ok(Self::DiscordSendEmbed),
+            "discord_send_file" => Ok(Self::DiscordSendFile),
+            "discord_create_thread" => Ok(Self::DiscordCreateThread),
             "discord_add_reaction" => Ok(Self::DiscordAddReaction),
+            "discord_timeout_user" => Ok(Self::DiscordTimeoutUser),
+            "discord_delete_message" => Ok(Self::DiscordDeleteMessage),
+            "discord_kick_user" => Ok(Self::DiscordKickUser),
             "generate_image" => Ok(Self::GenerateImage),
             "playwright_web_content" => Ok(Self::PlaywrightWebContent),
             "get_time" => Ok(Self::GetTime),
             "calculator" => Ok(Self::Calculator),
+            "remember_fact" => Ok(Self::RememberFact),
+            "recall_facts" => Ok(Self::RecallFacts),
+            "set_reminder" => Ok(Self::SetReminder),
+            "list_reminders" => Ok(Self::ListReminders),
+            "cancel_reminder" => Ok(Self::CancelReminder),
+            "get_weather" => Ok(Self::GetWeather),
+            "translate" => Ok(Self::Translate),
+            "github_lookup" => Ok(Self::GithubLookup),
+            "read_full_message" => Ok(Self::ReadFullMessage),
+            "summarize_channel" => Ok(Self::SummarizeChannel),
             _ => Err(anyhow!("Unknown tool name: {}", s)),
         }
     }
@@ -40,20 +78,55 @@ impl ToolName {
             Self::WebSearch => "web_search",
             Self::Fetch => "fetch",
             Self::DiscordSendMessage => "discord_send_message",
+            Self::DiscordSendEmbed => "discord_send_embed",
+            Self::DiscordSendFile => "discord_send_file",
+            Self::DiscordCreateThread => "discord_create_thread",
             Self::DiscordAddReaction => "discord_add_reaction",
+            Self::DiscordTimeoutUser => "discord_timeout_user",
+            Self::DiscordDeleteMessage => "discord_delete_message",
+            Self::DiscordKickUser => "discord_kick_user",
             Self::GenerateImage => "generate_image",
             Self::PlaywrightWebContent => "playwright_web_content",
             Self::GetTime => "get_time",
             Self::Calculator => "calculator",
+            Self::RememberFact => "remember_fact",
+            Self::RecallFacts => "recall_facts",
+            Self::SetReminder => "set_reminder",
+            Self::ListReminders => "list_reminders",
+            Self::CancelReminder => "cancel_reminder",
+            Self::GetWeather => "get_weather",
+            Self::Translate => "translate",
+            Self::GithubLookup => "github_lookup",
+            Self::ReadFullMessage => "read_full_message",
+            Self::SummarizeChannel => "summarize_channel",
         }
     }
 
     pub fn needs_result_feedback(&self) -> bool {
         match self {
-            Self::DiscordSendMessage | Self::DiscordAddReaction => false,
+            Self::DiscordSendMessage
+            | Self::DiscordSendEmbed
+            | Self::DiscordCreateThread
+            | Self::DiscordAddReaction => false,
             _ => true,
         }
     }
+
+    /// Tools with visible effects in discord, these run in the order the model asked for
+    pub fn runs_in_order(&self) -> bool {
+        matches!(
+            self,
+            Self::DiscordSendMessage
+                | Self::DiscordSendEmbed
+                | Self::DiscordSendFile
+                | Self::DiscordCreateThread
+                | Self::DiscordAddReaction
+                | Self::DiscordTimeoutUser
+                | Self::DiscordDeleteMessage
+                | Self::DiscordKickUser
+                | Self::GenerateImage
+        )
+    }
 }
 
 impl fmt::Display for ToolName {
@@ -0,0 +1,339 @@
+use super::{DiscordContext, Tool};
+use serde::Deserialize;
+use serde_json::{Value, json};
+use std::collections::HashMap;
+use std::sync::Arc;
+
+/// Longest text sent for translation, DeepL bills per character
+const MAX_TEXT_CHARS: usize = 5000;
+
+/// Where translations come from, picked by `TRANSLATE_BACKEND` (`deepl` or
+/// `libretranslate`). Without it DeepL is used when `DEEPL_API_KEY` is set, otherwise
+/// LibreTranslate when `LIBRETRANSLATE_URL` is.
+#[derive(Debug, Clone, PartialEq)]
+pub enum TranslationBackend {
+    DeepL {
+        api_key: String,
+        base_url: String,
+    },
+    LibreTranslate {
+        base_url: String,
+        api_key: Option<String>,
+    },
+}
+
+impl TranslationBackend {
+    fn from_vars(var: impl Fn(&str) -> Option<String>) -> Option<Self> {
+        let var = |key: &str| {
+            var(key)
+                .map(|value| value.trim().to_string())
+                .filter(|value| !value.is_empty())
+        };
+        let deepl = || {
+            let api_key = var("DEEPL_API_KEY")?;
+            // free plan keys end in :fx and only work against the free endpoint
+            let base_url = if api_key.ends_with(":fx") {
+                "https://api-free.deepl.com"
+            } else {
+                "https://api.deepl.com"
+            };
+            Some(Self::DeepL {
+                api_key,
+                base_url: base_url.to_string(),
+            })
+        };
+        let libretranslate = || {
+            Some(Self::LibreTranslate {
+                base_url: var("LIBRETRANSLATE_URL")?.trim_end_matches('/').to_string(),
+                api_key: var("LIBRETRANSLATE_API_KEY"),
+            })
+        };
+
+        match var("TRANSLATE_BACKEND")
+            .map(|name| name.to_lowercase())
+            .as_deref()
+        {
+            Some("deepl") => deepl(),
+            Some("libretranslate") => libretranslate(),
+            _ => deepl().or_else(libretranslate),
+        }
+    }
+
+    fn name(&self) -> &'static str {
+        match self {
+            Self::DeepL { .. } => "DeepL",
+            Self::LibreTranslate { .. } => "LibreTranslate",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Translation {
+    pub text: String,
+    /// The source language the backend detected, when it wasn't given one
+    pub detected_source: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct DeepLResponse {
+    translations: Vec<DeepLTranslation>,
+}
+
+#[derive(Debug, Deserialize)]
+struct DeepLTranslation {
+    detected_source_language: Option<String>,
+    text: String,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct LibreTranslateResponse {
+    translated_text: String,
+    detected_language: Option<LibreDetectedLanguage>,
+}
+
+#[derive(Debug, Deserialize)]
+struct LibreDetectedLanguage {
+    language: String,
+}
+
+/// Translates text with the configured backend, shared by the `translate` tool and the
+/// guild `replyLanguage` setting
+pub struct Translator {
+    client: reqwest::Client,
+    backend: TranslationBackend,
+}
+
+impl Translator {
+    /// None when no backend is configured
+    pub fn from_env() -> Option<Self> {
+        TranslationBackend::from_vars(|key| std::env::var(key).ok()).map(|backend| Self {
+            client: reqwest::Client::new(),
+            backend,
+        })
+    }
+
+    /// Translate into `target`, detecting the source language unless `source` is given.
+    /// Languages are ISO 639-1 codes like `de`, optionally with a region like `pt-BR`.
+    pub async fn translate(
+        &self,
+        text: &str,
+        target: &str,
+        source: Option<&str>,
+    ) -> Result<Translation, String> {
+        match &self.backend {
+            TranslationBackend::DeepL { api_key, base_url } => {
+                let mut body = json!({
+                    "text": [text],
+                    "target_lang": deepl_target(target),
+                });
+                if let Some(source) = source {
+                    // DeepL source languages never carry a region
+                    body["source_lang"] = json!(base_language(source).to_uppercase());
+                }
+                let request = self
+                    .client
+                    .post(format!("{}/v2/translate", base_url))
+                    .header("Authorization", format!("DeepL-Auth-Key {}", api_key))
+                    .json(&body);
+                let response: DeepLResponse = send_json(self.backend.name(), request).await?;
+                let translation = response
+                    .translations
+                    .into_iter()
+                    .next()
+                    .ok_or("DeepL returned no translation")?;
+                Ok(Translation {
+                    text: translation.text,
+                    detected_source: translation
+                        .detected_source_language
+                        .map(|language| language.to_lowercase()),
+                })
+            }
+            TranslationBackend::LibreTranslate { base_url, api_key } => {
+                let mut body = json!({
+                    "q": text,
+                    "source": source.map(base_language).unwrap_or("auto"),
+                    "target": base_language(target),
+                    "format": "text",
+                });
+                if let Some(api_key) = api_key {
+                    body["api_key"] = json!(api_key);
+                }
+                let request = self
+                    .client
+                    .post(format!("{}/translate", base_url))
+                    .json(&body);
+                let response: LibreTranslateResponse =
+                    send_json(self.backend.name(), request).await?;
+                Ok(Translation {
+                    text: response.translated_text,
+                    detected_source: response.detected_language.map(|detected| detected.language),
+                })
+            }
+        }
+    }
+}
+
+/// `pt-BR` -> `pt`, LibreTranslate and DeepL source languages have no regions
+fn base_language(language: &str) -> &str {
+    language.trim().split(['-', '_']).next().unwrap_or_default()
+}
+
+/// DeepL wants uppercase targets and no longer accepts plain English or Portuguese
+fn deepl_target(language: &str) -> String {
+    match language.trim().to_uppercase().replace('_', "-").as_str() {
+        "EN" => "EN-US".to_string(),
+        "PT" => "PT-BR".to_string(),
+        other => other.to_string(),
+    }
+}
+
+async fn send_json<T: serde::de::DeserializeOwned>(
+    backend: &str,
+    request: reqwest::RequestBuilder,
+) -> Result<T, String> {
+    let response = request
+        .send()
+        .await
+        .map_err(|e| format!("Failed to reach {}: {}", backend, e))?;
+
+    if !response.status().is_success() {
+        let status = response.status();
+        let error_text = response.text().await.unwrap_or_default();
+        return Err(format!(
+            "{} request failed with status {}: {}",
+            backend, status, error_text
+        ));
+    }
+
+    response
+        .json()
+        .await
+        .map_err(|e| format!("Failed to parse {} response: {}", backend, e))
+}
+
+pub struct TranslateTool {
+    translator: Arc<Translator>,
+}
+
+impl TranslateTool {
+    pub fn new(translator: Arc<Translator>) -> Self {
+        Self { translator }
+    }
+}
+
+#[async_trait::async_trait]
+impl Tool for TranslateTool {
+    fn name(&self) -> &str {
+        "translate"
+    }
+
+    fn description(&self) -> &str {
+        "Translate text into another language, detecting the source language automatically. Use it when someone asks for a translation or what a message in another language says."
+    }
+
+    fn parameters_schema(&self) -> Value {
+        json!({
+            "type": "object",
+            "properties": {
+                "text": {
+                    "type": "string",
+                    "description": "The text to translate"
+                },
+                "target_language": {
+                    "type": "string",
+                    "description": "ISO 639-1 code of the language to translate into, e.g. 'de', 'ja' or 'pt-BR'"
+                },
+                "source_language": {
+                    "type": "string",
+                    "description": "ISO 639-1 code of the text's language, leave out to detect it"
+                }
+            },
+            "required": ["text", "target_language"]
+        })
+    }
+
+    async fn execute(
+        &self,
+        parameters: HashMap<String, Value>,
+        _discord_context: Option<&DiscordContext>,
+    ) -> Result<String, String> {
+        let text = parameters
+            .get("text")
+            .and_then(|v| v.as_str())
+            .map(str::trim)
+            .filter(|text| !text.is_empty())
+            .ok_or("Missing or invalid 'text' parameter")?;
+        if text.chars().count() > MAX_TEXT_CHARS {
+            return Err(format!(
+                "Text is too long to translate, the limit is {} characters",
+                MAX_TEXT_CHARS
+            ));
+        }
+        let target = parameters
+            .get("target_language")
+            .and_then(|v| v.as_str())
+            .map(str::trim)
+            .filter(|target| !target.is_empty())
+            .ok_or("Missing or invalid 'target_language' parameter")?;
+        let source = parameters
+            .get("source_language")
+            .and_then(|v| v.as_str())
+            .map(str::trim)
+            .filter(|source| !source.is_empty());
+
+        let translation = self.translator.translate(text, target, source).await?;
+        let from = source
+            .map(str::to_string)
+            .or(translation.detected_source)
+            .unwrap_or_else(|| "unknown".to_string());
+        Ok(format!(
+            "Translation from {} to {}:\n{}",
+            from, target, translation.text
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn vars(pairs: &[(&str, &str)]) -> impl Fn(&str) -> Option<String> {
+        let pairs: HashMap<String, String> = pairs
+            .iter()
+            .map(|(key, value)| (key.to_string(), value.to_string()))
+            .collect();
+        move |key| pairs.get(key).cloned()
+    }
+
+    #[test]
+    fn test_backend_from_vars() {
+        assert_eq!(TranslationBackend::from_vars(vars(&[])), None);
+        assert_eq!(
+            TranslationBackend::from_vars(vars(&[("DEEPL_API_KEY", "abc:fx")])),
+            Some(TranslationBackend::DeepL {
+                api_key: "abc:fx".to_string(),
+                base_url: "https://api-free.deepl.com".to_string(),
+            })
+        );
+        assert_eq!(
+            TranslationBackend::from_vars(vars(&[
+                ("TRANSLATE_BACKEND", "LibreTranslate"),
+                ("DEEPL_API_KEY", "abc"),
+                ("LIBRETRANSLATE_URL", "http://localhost:5000/"),
+            ])),
+            Some(TranslationBackend::LibreTranslate {
+                base_url: "http://localhost:5000".to_string(),
+                api_key: None,
+            })
+        );
+    }
+
+    #[test]
+    fn test_language_codes() {
+        assert_eq!(deepl_target("en"), "EN-US");
+        assert_eq!(deepl_target("pt_br"), "PT-BR");
+        assert_eq!(deepl_target("de"), "DE");
+        assert_eq!(base_language("pt-BR"), "pt");
+    }
+}
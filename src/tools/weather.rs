@@ -0,0 +1,362 @@
+use super::Tool;
+use serde::Deserialize;
+use serde_json::{Value, json};
+use std::collections::HashMap;
+
+const GEOCODING_URL: &str = "https://geocoding-api.open-meteo.com/v1/search";
+const FORECAST_URL: &str = "https://api.open-meteo.com/v1/forecast";
+
+/// Days of forecast included after the current conditions
+const MAX_FORECAST_DAYS: u64 = 7;
+const DEFAULT_FORECAST_DAYS: u64 = 3;
+
+#[derive(Debug, Deserialize)]
+struct GeocodingResponse {
+    #[serde(default)]
+    results: Vec<Place>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Place {
+    name: String,
+    latitude: f64,
+    longitude: f64,
+    country: Option<String>,
+    /// State or region
+    admin1: Option<String>,
+}
+
+impl Place {
+    fn label(&self) -> String {
+        [
+            Some(self.name.as_str()),
+            self.admin1.as_deref(),
+            self.country.as_deref(),
+        ]
+        .into_iter()
+        .flatten()
+        .filter(|part| !part.is_empty())
+        .collect::<Vec<_>>()
+        .join(", ")
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct ForecastResponse {
+    timezone: String,
+    current: CurrentWeather,
+    daily: DailyForecast,
+}
+
+#[derive(Debug, Deserialize)]
+struct CurrentWeather {
+    time: String,
+    temperature_2m: f64,
+    apparent_temperature: f64,
+    relative_humidity_2m: f64,
+    precipitation: f64,
+    weather_code: u8,
+    wind_speed_10m: f64,
+}
+
+#[derive(Debug, Deserialize)]
+struct DailyForecast {
+    time: Vec<String>,
+    weather_code: Vec<u8>,
+    temperature_2m_max: Vec<f64>,
+    temperature_2m_min: Vec<f64>,
+    precipitation_probability_max: Vec<Option<f64>>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Units {
+    Metric,
+    Imperial,
+}
+
+impl Units {
+    fn temperature(self) -> &'static str {
+        match self {
+            Units::Metric => "°C",
+            Units::Imperial => "°F",
+        }
+    }
+
+    fn wind_speed(self) -> &'static str {
+        match self {
+            Units::Metric => "km/h",
+            Units::Imperial => "mph",
+        }
+    }
+
+    fn precipitation(self) -> &'static str {
+        match self {
+            Units::Metric => "mm",
+            Units::Imperial => "in",
+        }
+    }
+}
+
+/// Current weather and a short forecast from Open-Meteo, which needs no API key
+pub struct WeatherTool {
+    client: reqwest::Client,
+}
+
+impl WeatherTool {
+    pub fn new() -> Self {
+        Self {
+            client: reqwest::Client::new(),
+        }
+    }
+
+    async fn geocode(&self, location: &str) -> Result<Option<Place>, String> {
+        let response: GeocodingResponse = send_json(self.client.get(GEOCODING_URL).query(&[
+            ("name", location),
+            ("count", "1"),
+            ("language", "en"),
+        ]))
+        .await?;
+        Ok(response.results.into_iter().next())
+    }
+
+    async fn forecast(
+        &self,
+        place: &Place,
+        units: Units,
+        days: u64,
+    ) -> Result<ForecastResponse, String> {
+        let mut params = vec![
+            ("latitude", place.latitude.to_string()),
+            ("longitude", place.longitude.to_string()),
+            (
+                "current",
+                "temperature_2m,apparent_temperature,relative_humidity_2m,precipitation,weather_code,wind_speed_10m"
+                    .to_string(),
+            ),
+            (
+                "daily",
+                "weather_code,temperature_2m_max,temperature_2m_min,precipitation_probability_max"
+                    .to_string(),
+            ),
+            ("timezone", "auto".to_string()),
+            ("forecast_days", days.to_string()),
+        ];
+        if units == Units::Imperial {
+            params.push(("temperature_unit", "fahrenheit".to_string()));
+            params.push(("wind_speed_unit", "mph".to_string()));
+            params.push(("precipitation_unit", "inch".to_string()));
+        }
+        send_json(self.client.get(FORECAST_URL).query(&params)).await
+    }
+}
+
+impl Default for WeatherTool {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+async fn send_json<T: serde::de::DeserializeOwned>(
+    request: reqwest::RequestBuilder,
+) -> Result<T, String> {
+    let response = request
+        .send()
+        .await
+        .map_err(|e| format!("Failed to reach Open-Meteo: {}", e))?;
+
+    if !response.status().is_success() {
+        let status = response.status();
+        let error_text = response.text().await.unwrap_or_default();
+        return Err(format!(
+            "Open-Meteo request failed with status {}: {}",
+            status, error_text
+        ));
+    }
+
+    response
+        .json()
+        .await
+        .map_err(|e| format!("Failed to parse Open-Meteo response: {}", e))
+}
+
+/// WMO weather interpretation codes as Open-Meteo reports them
+fn describe_weather_code(code: u8) -> &'static str {
+    match code {
+        0 => "clear sky",
+        1 => "mainly clear",
+        2 => "partly cloudy",
+        3 => "overcast",
+        45 | 48 => "fog",
+        51 | 53 | 55 => "drizzle",
+        56 | 57 => "freezing drizzle",
+        61 => "light rain",
+        63 => "rain",
+        65 => "heavy rain",
+        66 | 67 => "freezing rain",
+        71 => "light snow",
+        73 => "snow",
+        75 => "heavy snow",
+        77 => "snow grains",
+        80..=82 => "rain showers",
+        85 | 86 => "snow showers",
+        95 => "thunderstorm",
+        96 | 99 => "thunderstorm with hail",
+        _ => "unknown conditions",
+    }
+}
+
+fn format_report(place: &Place, forecast: &ForecastResponse, units: Units) -> String {
+    let current = &forecast.current;
+    let temperature = units.temperature();
+    let mut report = format!(
+        "Weather in {} (local time {}, {}):\nNow: {}, {:.0}{} (feels like {:.0}{}), humidity {:.0}%, wind {:.0} {}, precipitation {} {}\n",
+        place.label(),
+        current.time.replace('T', " "),
+        forecast.timezone,
+        describe_weather_code(current.weather_code),
+        current.temperature_2m,
+        temperature,
+        current.apparent_temperature,
+        temperature,
+        current.relative_humidity_2m,
+        current.wind_speed_10m,
+        units.wind_speed(),
+        current.precipitation,
+        units.precipitation(),
+    );
+
+    let daily = &forecast.daily;
+    for (i, date) in daily.time.iter().enumerate() {
+        let (Some(code), Some(max), Some(min)) = (
+            daily.weather_code.get(i),
+            daily.temperature_2m_max.get(i),
+            daily.temperature_2m_min.get(i),
+        ) else {
+            break;
+        };
+        let rain_chance = daily
+            .precipitation_probability_max
+            .get(i)
+            .copied()
+            .flatten()
+            .map(|chance| format!(", {:.0}% chance of precipitation", chance))
+            .unwrap_or_default();
+        report.push_str(&format!(
+            "{}: {}, {:.0}{} to {:.0}{}{}\n",
+            date,
+            describe_weather_code(*code),
+            min,
+            temperature,
+            max,
+            temperature,
+            rain_chance
+        ));
+    }
+    report
+}
+
+#[async_trait::async_trait]
+impl Tool for WeatherTool {
+    fn name(&self) -> &str {
+        "get_weather"
+    }
+
+    fn description(&self) -> &str {
+        "Get the current weather and a short forecast for a place. Use it whenever someone asks about the weather instead of guessing."
+    }
+
+    fn parameters_schema(&self) -> Value {
+        json!({
+            "type": "object",
+            "properties": {
+                "location": {
+                    "type": "string",
+                    "description": "City or place name, e.g. 'Berlin' or 'Austin'"
+                },
+                "units": {
+                    "type": "string",
+                    "enum": ["metric", "imperial"],
+                    "description": "metric (°C, km/h) unless the user prefers fahrenheit"
+                },
+                "days": {
+                    "type": "integer",
+                    "description": format!("Days of forecast to include (1-{}, default {})", MAX_FORECAST_DAYS, DEFAULT_FORECAST_DAYS)
+                }
+            },
+            "required": ["location"]
+        })
+    }
+
+    async fn execute(
+        &self,
+        parameters: HashMap<String, Value>,
+        _discord_context: Option<&super::DiscordContext>,
+    ) -> Result<String, String> {
+        let location = parameters
+            .get("location")
+            .and_then(|v| v.as_str())
+            .map(str::trim)
+            .filter(|location| !location.is_empty())
+            .ok_or("Missing or invalid 'location' parameter")?;
+        let units = match parameters.get("units").and_then(|v| v.as_str()) {
+            Some("imperial") => Units::Imperial,
+            _ => Units::Metric,
+        };
+        let days = parameters
+            .get("days")
+            .and_then(|v| v.as_u64())
+            .unwrap_or(DEFAULT_FORECAST_DAYS)
+            .clamp(1, MAX_FORECAST_DAYS);
+
+        let place = self
+            .geocode(location)
+            .await?
+            .ok_or_else(|| format!("Couldn't find a place called '{}'", location))?;
+        let forecast = self.forecast(&place, units, days).await?;
+        Ok(format_report(&place, &forecast, units))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_format_report() {
+        let place = Place {
+            name: "Berlin".to_string(),
+            latitude: 52.52,
+            longitude: 13.41,
+            country: Some("Germany".to_string()),
+            admin1: Some("Land Berlin".to_string()),
+        };
+        let forecast: ForecastResponse = serde_json::from_value(json!({
+            "timezone": "Europe/Berlin",
+            "current": {
+                "time": "2025-10-25T14:00",
+                "temperature_2m": 11.6,
+                "apparent_temperature": 9.2,
+                "relative_humidity_2m": 71.0,
+                "precipitation": 0.0,
+                "weather_code": 3,
+                "wind_speed_10m": 14.8
+            },
+            "daily": {
+                "time": ["2025-10-25", "2025-10-26"],
+                "weather_code": [3, 61],
+                "temperature_2m_max": [12.4, 10.1],
+                "temperature_2m_min": [6.8, 5.9],
+                "precipitation_probability_max": [10.0, null]
+            }
+        }))
+        .unwrap();
+
+        assert_eq!(
+            format_report(&place, &forecast, Units::Metric),
+            "Weather in Berlin, Land Berlin, Germany (local time 2025-10-25 14:00, Europe/Berlin):\n\
+             Now: overcast, 12°C (feels like 9°C), humidity 71%, wind 15 km/h, precipitation 0 mm\n\
+             2025-10-25: overcast, 7°C to 12°C, 10% chance of precipitation\n\
+             2025-10-26: light rain, 6°C to 10°C\n"
+        );
+    }
+}
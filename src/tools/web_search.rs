@@ -1,68 +1,174 @@
 use super::Tool;
-use serde::{Deserialize, Serialize};
+use super::search_backend::{self, SearchBackend, SearchQuery};
+use chrono::NaiveDate;
 use serde_json::{Value, json};
 use std::collections::HashMap;
+use std::sync::Arc;
 
-#[derive(Debug, Serialize)]
-struct ExaSearchRequest {
-    query: String,
-    #[serde(rename = "numResults")]
-    num_results: u32,
-    #[serde(rename = "includeDomains")]
-    include_domains: Option<Vec<String>>,
-    #[serde(rename = "excludeDomains")]
-    exclude_domains: Option<Vec<String>>,
-    #[serde(rename = "startCrawlDate")]
-    start_crawl_date: Option<String>,
-    #[serde(rename = "endCrawlDate")]
-    end_crawl_date: Option<String>,
-    #[serde(rename = "startPublishedDate")]
-    start_published_date: Option<String>,
-    #[serde(rename = "endPublishedDate")]
-    end_published_date: Option<String>,
-    #[serde(rename = "useAutoprompt")]
-    use_autoprompt: Option<bool>,
-    r#type: Option<String>,
-    category: Option<String>,
+const DEFAULT_NUM_RESULTS: u32 = 5;
+const MAX_NUM_RESULTS: u32 = 10;
+
+const SEARCH_TYPES: [&str; 2] = ["keyword", "neural"];
+
+const CATEGORIES: [&str; 9] = [
+    "company",
+    "research paper",
+    "news",
+    "pdf",
+    "github",
+    "tweet",
+    "personal site",
+    "linkedin profile",
+    "financial report",
+];
+
+/// A guild's `searchDomains` setting, e.g. `{"allow": ["wikipedia.org"], "deny": ["reddit.com"]}`.
+/// A non-empty allow list confines every search to those domains.
+#[derive(Debug, Clone, Default)]
+pub struct SearchDomainPolicy {
+    pub allow: Vec<String>,
+    pub deny: Vec<String>,
+}
+
+impl SearchDomainPolicy {
+    pub fn from_setting(setting: Option<&Value>) -> Self {
+        let domains = |key: &str| setting.map(|s| string_list(s.get(key))).unwrap_or_default();
+        Self {
+            allow: domains("allow"),
+            deny: domains("deny"),
+        }
+    }
+
+    fn is_denied(&self, url_or_domain: &str) -> bool {
+        self.deny
+            .iter()
+            .any(|denied| domain_matches(url_or_domain, denied))
+    }
+}
+
+pub(super) fn string_list(value: Option<&Value>) -> Vec<String> {
+    value
+        .and_then(|v| v.as_array())
+        .map(|items| {
+            items
+                .iter()
+                .filter_map(|item| item.as_str())
+                .map(|item| item.trim().to_lowercase())
+                .filter(|item| !item.is_empty())
+                .collect()
+        })
+        .unwrap_or_default()
 }
 
-#[derive(Debug, Deserialize)]
-struct ExaSearchResponse {
-    results: Vec<ExaResult>,
-    #[serde(rename = "autopromptString")]
-    autoprompt_string: Option<String>,
+/// Whether a url or domain is `domain` or one of its subdomains
+pub(super) fn domain_matches(url_or_domain: &str, domain: &str) -> bool {
+    let host = url_or_domain
+        .split("://")
+        .last()
+        .unwrap_or_default()
+        .split(['/', '?', '#'])
+        .next()
+        .unwrap_or_default()
+        .trim_start_matches("www.")
+        .to_lowercase();
+    let domain = domain.trim_start_matches("www.");
+    host == domain || host.ends_with(&format!(".{}", domain))
 }
 
-#[derive(Debug, Deserialize)]
-struct ExaResult {
-    id: String,
-    url: String,
-    title: String,
-    score: Option<f64>,
-    #[serde(rename = "publishedDate")]
-    published_date: Option<String>,
-    author: Option<String>,
-    text: Option<String>,
+fn parse_date(parameters: &HashMap<String, Value>, key: &str) -> Result<Option<NaiveDate>, String> {
+    let Some(value) = parameters.get(key).and_then(|v| v.as_str()) else {
+        return Ok(None);
+    };
+    NaiveDate::parse_from_str(value, "%Y-%m-%d")
+        .map(Some)
+        .map_err(|_| format!("Invalid '{}', expected YYYY-MM-DD", key))
+}
+
+fn build_query(
+    query: &str,
+    parameters: &HashMap<String, Value>,
+    policy: &SearchDomainPolicy,
+) -> Result<SearchQuery, String> {
+    let num_results = parameters
+        .get("num_results")
+        .and_then(|v| v.as_u64())
+        .map(|n| (n as u32).clamp(1, MAX_NUM_RESULTS))
+        .unwrap_or(DEFAULT_NUM_RESULTS);
+
+    let search_type = match parameters.get("type").and_then(|v| v.as_str()) {
+        Some(search_type) if SEARCH_TYPES.contains(&search_type) => search_type,
+        Some(other) => {
+            return Err(format!(
+                "Invalid 'type' {}, expected keyword or neural",
+                other
+            ));
+        }
+        None => "keyword",
+    };
+
+    let category = match parameters.get("category").and_then(|v| v.as_str()) {
+        Some(category) if CATEGORIES.contains(&category) => Some(category.to_string()),
+        Some(other) => return Err(format!("Invalid 'category' {}", other)),
+        None => None,
+    };
+
+    let mut include_domains: Vec<String> = string_list(parameters.get("include_domains"))
+        .into_iter()
+        .filter(|domain| !policy.is_denied(domain))
+        .collect();
+    if !policy.allow.is_empty() {
+        include_domains.retain(|domain| {
+            policy
+                .allow
+                .iter()
+                .any(|allowed| domain_matches(domain, allowed))
+        });
+        if include_domains.is_empty() {
+            include_domains = policy.allow.clone();
+        }
+    }
+
+    let mut exclude_domains = string_list(parameters.get("exclude_domains"));
+    for denied in &policy.deny {
+        if !exclude_domains.contains(denied) {
+            exclude_domains.push(denied.clone());
+        }
+    }
+
+    Ok(SearchQuery {
+        query: query.to_string(),
+        num_results,
+        search_type: search_type.to_string(),
+        category,
+        include_domains,
+        exclude_domains,
+        start_published_date: parse_date(parameters, "start_published_date")?,
+        end_published_date: parse_date(parameters, "end_published_date")?,
+    })
 }
 
 pub struct WebSearchTool {
-    client: reqwest::Client,
-    api_key: Option<String>,
+    /// Preferred backend first, guilds can pick another through `searchBackend`
+    backends: Vec<Arc<dyn SearchBackend>>,
 }
 
 impl WebSearchTool {
     pub fn new() -> Self {
-        let api_key = std::env::var("EXA_KEY").ok();
-        let has_key = api_key.is_some();
+        let backends = search_backend::configured_backends(&reqwest::Client::new());
 
-        if !has_key {
-            eprintln!("Warning: EXA_KEY environment variable not set. Web search will not work.");
+        if backends.is_empty() {
+            eprintln!(
+                "Warning: none of EXA_KEY, BRAVE_SEARCH_KEY or SEARXNG_URL set. Web search will not work."
+            );
         }
 
-        Self {
-            client: reqwest::Client::new(),
-            api_key,
-        }
+        Self { backends }
+    }
+
+    fn backend_for(&self, guild_choice: Option<&str>) -> Option<&Arc<dyn SearchBackend>> {
+        guild_choice
+            .and_then(|name| self.backends.iter().find(|backend| backend.name() == name))
+            .or_else(|| self.backends.first())
     }
 }
 
@@ -73,7 +179,7 @@ impl Tool for WebSearchTool {
     }
 
     fn description(&self) -> &str {
-        "Search the web for current information using Exa AI's neural search. Returns relevant results with titles, URLs, authors, published dates, and content previews. MUST be used whenever users ask you to search for, find, or look up anything including: music, videos, news, products, people, places, current events, or any other information that would benefit from web search."
+        "Search the web for current information. Returns relevant results with titles, URLs, authors, published dates, and content previews. MUST be used whenever users ask you to search for, find, or look up anything including: music, videos, news, products, people, places, current events, or any other information that would benefit from web search."
     }
 
     fn parameters_schema(&self) -> Value {
@@ -83,6 +189,38 @@ impl Tool for WebSearchTool {
                 "query": {
                     "type": "string",
                     "description": "The search query"
+                },
+                "num_results": {
+                    "type": "integer",
+                    "description": "How many results to return, 1 to 10 (default 5). Use more for broad or research questions"
+                },
+                "type": {
+                    "type": "string",
+                    "enum": SEARCH_TYPES,
+                    "description": "keyword (default) for exact names, titles and terms, neural for open-ended or descriptive queries"
+                },
+                "category": {
+                    "type": "string",
+                    "enum": CATEGORIES,
+                    "description": "Only return results of this kind"
+                },
+                "include_domains": {
+                    "type": "array",
+                    "items": {"type": "string"},
+                    "description": "Only search these domains, e.g. [\"youtube.com\"]"
+                },
+                "exclude_domains": {
+                    "type": "array",
+                    "items": {"type": "string"},
+                    "description": "Never return results from these domains"
+                },
+                "start_published_date": {
+                    "type": "string",
+                    "description": "Only results published on or after this date, YYYY-MM-DD"
+                },
+                "end_published_date": {
+                    "type": "string",
+                    "description": "Only results published on or before this date, YYYY-MM-DD"
                 }
             },
             "required": ["query"]
@@ -92,56 +230,34 @@ impl Tool for WebSearchTool {
     async fn execute(
         &self,
         parameters: HashMap<String, Value>,
-        _discord_context: Option<&super::DiscordContext>,
+        discord_context: Option<&super::DiscordContext>,
     ) -> Result<String, String> {
         let query = parameters
             .get("query")
             .and_then(|v| v.as_str())
             .ok_or("Missing or invalid 'query' parameter")?;
 
-        let api_key = self
-            .api_key
-            .as_ref()
-            .ok_or("EXA_KEY environment variable not set")?;
-
-        let search_request = ExaSearchRequest {
-            query: query.to_string(),
-            num_results: 5,
-            include_domains: None,
-            exclude_domains: None,
-            start_crawl_date: None,
-            end_crawl_date: None,
-            start_published_date: None,
-            end_published_date: None,
-            use_autoprompt: Some(true),
-            r#type: Some("keyword".to_string()),
-            category: None,
-        };
-
-        let response = self
-            .client
-            .post("https://api.exa.ai/search")
-            .header("accept", "application/json")
-            .header("content-type", "application/json")
-            .header("x-api-key", api_key)
-            .json(&search_request)
-            .send()
-            .await
-            .map_err(|e| format!("Failed to send request to Exa API: {}", e))?;
-
-        if !response.status().is_success() {
-            let status = response.status();
-            let error_text = response.text().await.unwrap_or_default();
-            return Err(format!(
-                "Exa API request failed with status {}: {}",
-                status, error_text
-            ));
-        }
+        let backend = self
+            .backend_for(discord_context.and_then(|context| context.search_backend.as_deref()))
+            .ok_or("No web search backend configured")?;
+
+        let default_policy = SearchDomainPolicy::default();
+        let policy = discord_context
+            .map(|context| &context.search_domains)
+            .unwrap_or(&default_policy);
+        let search_query = build_query(query, &parameters, policy)?;
 
-        let search_response: ExaSearchResponse = response
-            .json()
-            .await
-            .map_err(|e| format!("Failed to parse Exa API response: {}", e))?;
+        let mut search_response = backend.search(&search_query).await?;
+
+        // site: operators are only a hint to most engines, so enforce the domains here
+        search_response.results.retain(|result| {
+            !policy.is_denied(&result.url)
+                && (search_query.include_domains.is_empty()
+                    || search_query
+                        .include_domains
+                        .iter()
+                        .any(|domain| domain_matches(&result.url, domain)))
+        });
 
         if search_response.results.is_empty() {
             return Ok(format!("No search results found for query: '{}'", query));
@@ -149,8 +265,8 @@ impl Tool for WebSearchTool {
 
         let mut result_text = format!("Search results for '{}':\n\n", query);
 
-        if let Some(autoprompt) = &search_response.autoprompt_string {
-            result_text.push_str(&format!("Refined query: {}\n\n", autoprompt));
+        if let Some(refined_query) = &search_response.refined_query {
+            result_text.push_str(&format!("Refined query: {}\n\n", refined_query));
         }
 
         for (i, result) in search_response.results.iter().enumerate() {
@@ -169,9 +285,9 @@ impl Tool for WebSearchTool {
                 result_text.push_str(&format!("   Relevance: {:.2}\n", score));
             }
 
-            if let Some(text) = &result.text {
-                let snippet = if text.len() > 200 {
-                    format!("{}...", &text[..200])
+            if let Some(text) = &result.snippet {
+                let snippet = if text.chars().count() > 200 {
+                    format!("{}...", text.chars().take(200).collect::<String>())
                 } else {
                     text.clone()
                 };
@@ -184,3 +300,77 @@ impl Tool for WebSearchTool {
         Ok(result_text)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn params(value: Value) -> HashMap<String, Value> {
+        serde_json::from_value(value).unwrap()
+    }
+
+    #[test]
+    fn test_build_query_reads_model_parameters() {
+        let request = build_query(
+            "rust",
+            &params(json!({
+                "num_results": 50,
+                "type": "neural",
+                "category": "github",
+                "start_published_date": "2024-01-31"
+            })),
+            &SearchDomainPolicy::default(),
+        )
+        .unwrap();
+
+        assert_eq!(request.num_results, MAX_NUM_RESULTS);
+        assert_eq!(request.search_type, "neural");
+        assert_eq!(request.category.as_deref(), Some("github"));
+        assert_eq!(
+            request.start_published_date,
+            NaiveDate::from_ymd_opt(2024, 1, 31)
+        );
+        assert!(
+            build_query(
+                "rust",
+                &params(json!({"type": "fuzzy"})),
+                &Default::default()
+            )
+            .is_err()
+        );
+        assert!(
+            build_query(
+                "rust",
+                &params(json!({"end_published_date": "yesterday"})),
+                &Default::default()
+            )
+            .is_err()
+        );
+    }
+
+    #[test]
+    fn test_build_query_applies_guild_domain_policy() {
+        let policy = SearchDomainPolicy::from_setting(Some(&json!({
+            "allow": ["wikipedia.org", "github.com"],
+            "deny": ["reddit.com"]
+        })));
+
+        let request = build_query(
+            "rust",
+            &params(json!({"include_domains": ["en.wikipedia.org", "reddit.com", "evil.com"]})),
+            &policy,
+        )
+        .unwrap();
+        assert_eq!(request.include_domains, ["en.wikipedia.org"]);
+        assert_eq!(request.exclude_domains, ["reddit.com"]);
+
+        let request = build_query("rust", &params(json!({})), &policy).unwrap();
+        assert_eq!(request.include_domains, policy.allow);
+
+        let deny_only = SearchDomainPolicy::from_setting(Some(&json!({"deny": ["Reddit.com"]})));
+        let request = build_query("rust", &params(json!({})), &deny_only).unwrap();
+        assert_eq!(request.exclude_domains, ["reddit.com"]);
+        assert!(deny_only.is_denied("https://old.reddit.com/r/rust"));
+        assert!(!deny_only.is_denied("https://notreddit.com"));
+    }
+}
@@ -0,0 +1,61 @@
+use chrono::{DateTime, Utc};
+use once_cell::sync::Lazy;
+
+pub const VERSION: &str = env!("CARGO_PKG_VERSION");
+
+/// Short commit the binary was built from, `-dirty` when it had uncommitted changes
+pub const GIT_SHA: &str = env!("CHLOE_GIT_SHA");
+
+static STARTED_AT: Lazy<DateTime<Utc>> = Lazy::new(Utc::now);
+
+/// Note the process start, call first thing in main so uptime counts from there
+pub fn mark_started() {
+    Lazy::force(&STARTED_AT);
+}
+
+pub fn started_at() -> DateTime<Utc> {
+    *STARTED_AT
+}
+
+pub fn built_at() -> Option<DateTime<Utc>> {
+    DateTime::from_timestamp(env!("CHLOE_BUILT_AT").parse().ok()?, 0)
+}
+
+pub fn uptime() -> chrono::Duration {
+    Utc::now() - started_at()
+}
+
+/// e.g. `v0.1.0 (3f9a1c2b7d)`
+pub fn version_line() -> String {
+    format!("v{} ({})", VERSION, GIT_SHA)
+}
+
+/// e.g. `3d 4h 12m`, leading zero units left out
+pub fn format_uptime(uptime: chrono::Duration) -> String {
+    let minutes = uptime.num_minutes().max(0);
+    let (days, hours, minutes) = (minutes / 1440, minutes / 60 % 24, minutes % 60);
+    if days > 0 {
+        format!("{}d {}h {}m", days, hours, minutes)
+    } else if hours > 0 {
+        format!("{}h {}m", hours, minutes)
+    } else {
+        format!("{}m", minutes)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Duration;
+
+    #[test]
+    fn test_format_uptime() {
+        assert_eq!(format_uptime(Duration::seconds(59)), "0m");
+        assert_eq!(format_uptime(Duration::minutes(61)), "1h 1m");
+        assert_eq!(
+            format_uptime(Duration::days(3) + Duration::minutes(12)),
+            "3d 0h 12m"
+        );
+        assert_eq!(format_uptime(Duration::seconds(-5)), "0m");
+    }
+}
@@ -0,0 +1,55 @@
+use std::sync::atomic::{AtomicU64, Ordering};
+
+/// Hit and miss counts of a cache since startup
+#[derive(Default)]
+pub struct CacheStats {
+    hits: AtomicU64,
+    misses: AtomicU64,
+}
+
+impl CacheStats {
+    pub fn record(&self, hit: bool) {
+        let counter = if hit { &self.hits } else { &self.misses };
+        counter.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn snapshot(&self) -> CacheSnapshot {
+        CacheSnapshot {
+            hits: self.hits.load(Ordering::Relaxed),
+            misses: self.misses.load(Ordering::Relaxed),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct CacheSnapshot {
+    pub hits: u64,
+    pub misses: u64,
+}
+
+impl CacheSnapshot {
+    /// Share of lookups answered from the cache, None before the first lookup
+    pub fn hit_rate(&self) -> Option<f64> {
+        let lookups = self.hits + self.misses;
+        (lookups > 0).then(|| self.hits as f64 / lookups as f64)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_hit_rate() {
+        let stats = CacheStats::default();
+        assert_eq!(stats.snapshot().hit_rate(), None);
+
+        stats.record(true);
+        stats.record(true);
+        stats.record(true);
+        stats.record(false);
+        let snapshot = stats.snapshot();
+        assert_eq!(snapshot, CacheSnapshot { hits: 3, misses: 1 });
+        assert_eq!(snapshot.hit_rate(), Some(0.75));
+    }
+}
@@ -0,0 +1,165 @@
+const FENCE: &str = "```";
+
+/// A piece of a discord message, either prose or a fenced code block
+#[derive(Debug, Clone, PartialEq)]
+pub enum Segment<'a> {
+    Text(&'a str),
+    Code(CodeBlock<'a>),
+}
+
+/// A fenced code block, `language` is the fence's info string when it has one
+#[derive(Debug, Clone, PartialEq)]
+pub struct CodeBlock<'a> {
+    pub language: Option<&'a str>,
+    pub body: &'a str,
+    raw: &'a str,
+}
+
+impl CodeBlock<'_> {
+    /// The block exactly as written, with a guessed language added to the fence when it
+    /// had none so the model reads it as the right kind of code
+    pub fn annotated(&self) -> String {
+        if self.language.is_some() {
+            return self.raw.to_string();
+        }
+        match guess_language(self.body) {
+            Some(language) => format!(
+                "{}{}\n{}{}",
+                FENCE,
+                language,
+                self.body.strip_prefix('\n').unwrap_or(self.body),
+                FENCE
+            ),
+            None => self.raw.to_string(),
+        }
+    }
+}
+
+/// Split `content` into prose and fenced code blocks the way discord renders them. An
+/// unclosed fence stays prose, like in the client.
+pub fn split(content: &str) -> Vec<Segment<'_>> {
+    let mut segments = Vec::new();
+    let mut rest = content;
+    while let Some(open) = rest.find(FENCE) {
+        let inner_start = open + FENCE.len();
+        let Some(close) = rest[inner_start..].find(FENCE) else {
+            break;
+        };
+        let inner_end = inner_start + close;
+        let end = inner_end + FENCE.len();
+
+        if open > 0 {
+            segments.push(Segment::Text(&rest[..open]));
+        }
+        let (language, body) = split_info_string(&rest[inner_start..inner_end]);
+        segments.push(Segment::Code(CodeBlock {
+            language,
+            body,
+            raw: &rest[open..end],
+        }));
+        rest = &rest[end..];
+    }
+    if !rest.is_empty() {
+        segments.push(Segment::Text(rest));
+    }
+    segments
+}
+
+/// Discord only takes the first line as the language when it's a single word with more
+/// lines after it, otherwise everything between the fences is code
+fn split_info_string(inner: &str) -> (Option<&str>, &str) {
+    if let Some((first, body)) = inner.split_once('\n') {
+        let first = first.trim();
+        if !first.is_empty()
+            && first
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || "+#-_.".contains(c))
+        {
+            return (Some(first), body);
+        }
+    }
+    (None, inner)
+}
+
+/// A rough guess from telltale keywords, None when nothing stands out
+pub fn guess_language(code: &str) -> Option<&'static str> {
+    let code = code.trim();
+    if code.is_empty() {
+        return None;
+    }
+    if (code.starts_with('{') || code.starts_with('['))
+        && serde_json::from_str::<serde_json::Value>(code).is_ok()
+    {
+        return Some("json");
+    }
+    if code.starts_with("#!/bin/") || code.starts_with("$ ") {
+        return Some("sh");
+    }
+
+    let has = |needles: &[&str]| needles.iter().any(|needle| code.contains(needle));
+    if has(&[
+        "fn main(",
+        "let mut ",
+        "impl ",
+        "pub fn ",
+        "use std::",
+        "#[derive(",
+    ]) {
+        Some("rust")
+    } else if has(&["package main", "func ", ":= "]) {
+        Some("go")
+    } else if has(&["def ", "elif ", "import numpy", "print(", "self."]) && !code.contains(';') {
+        Some("python")
+    } else if has(&["console.log", "function ", "=> {", "const ", "require("]) {
+        Some("javascript")
+    } else if has(&["#include <", "std::cout", "int main("]) {
+        Some("cpp")
+    } else if has(&["public static void", "System.out."]) {
+        Some("java")
+    } else {
+        let upper = code.to_uppercase();
+        let sql = ["SELECT ", "INSERT INTO ", "UPDATE ", "CREATE TABLE "]
+            .iter()
+            .any(|keyword| upper.starts_with(keyword));
+        sql.then_some("sql")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_split() {
+        let message = "look:\n```rust\nlet a: u8 = 1;\n```\nand `inline` ```x``` end";
+        let segments = split(message);
+        assert_eq!(segments.len(), 5);
+        assert_eq!(segments[0], Segment::Text("look:\n"));
+        let Segment::Code(block) = &segments[1] else {
+            panic!("expected a code block");
+        };
+        assert_eq!(block.language, Some("rust"));
+        assert_eq!(block.body, "let a: u8 = 1;\n");
+        assert_eq!(segments[4], Segment::Text(" end"));
+
+        // an unclosed fence isn't code
+        assert_eq!(split("```oops"), vec![Segment::Text("```oops")]);
+    }
+
+    #[test]
+    fn test_annotated() {
+        let Segment::Code(block) = &split("```\nfn main() {}\n```")[0] else {
+            panic!("expected a code block");
+        };
+        assert_eq!(block.annotated(), "```rust\nfn main() {}\n```");
+
+        let Segment::Code(block) = &split("```py\nx = 1\n```")[0] else {
+            panic!("expected a code block");
+        };
+        assert_eq!(block.annotated(), "```py\nx = 1\n```");
+
+        assert_eq!(guess_language("{\"a\": 1}"), Some("json"));
+        assert_eq!(guess_language("select * from users"), Some("sql"));
+        assert_eq!(guess_language("hello there"), None);
+    }
+}
@@ -0,0 +1,245 @@
+use chrono::{DateTime, Datelike, Duration, NaiveDate, TimeZone, Utc};
+use chrono_tz::Tz;
+
+/// How far ahead the next run is looked for, long enough for schedules like `0 0 29 2 1`
+/// (a leap day that's also a monday)
+const MAX_LOOKAHEAD_DAYS: i64 = 366 * 30;
+
+const MONTH_NAMES: &[&str] = &[
+    "jan", "feb", "mar", "apr", "may", "jun", "jul", "aug", "sep", "oct", "nov", "dec",
+];
+const WEEKDAY_NAMES: &[&str] = &["sun", "mon", "tue", "wed", "thu", "fri", "sat"];
+
+#[derive(Debug, Clone, PartialEq, thiserror::Error)]
+pub enum CronError {
+    #[error("expected 5 fields (minute hour day month weekday) but got {0}")]
+    FieldCount(usize),
+    #[error("'{value}' isn't a valid {field}")]
+    InvalidField { field: &'static str, value: String },
+}
+
+/// A standard five field cron expression (`minute hour day month weekday`) with `*`,
+/// lists, ranges, steps, month and weekday names and the `@daily` style shorthands.
+/// Each field is a bitmask of the values it allows.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CronSchedule {
+    minutes: u64,
+    hours: u64,
+    days: u64,
+    months: u64,
+    /// Sunday is 0, 7 is accepted for it too
+    weekdays: u64,
+    /// Like cron, a day matching either the day or the weekday runs when both are given
+    either_day: bool,
+}
+
+impl CronSchedule {
+    pub fn parse(expression: &str) -> Result<Self, CronError> {
+        let expression = match expression.trim().to_lowercase().as_str() {
+            "@hourly" => "0 * * * *".to_string(),
+            "@daily" | "@midnight" => "0 0 * * *".to_string(),
+            "@weekly" => "0 0 * * 0".to_string(),
+            "@monthly" => "0 0 1 * *".to_string(),
+            "@yearly" | "@annually" => "0 0 1 1 *".to_string(),
+            other => other.to_string(),
+        };
+        let fields: Vec<&str> = expression.split_whitespace().collect();
+        let [minute, hour, day, month, weekday] = fields.as_slice() else {
+            return Err(CronError::FieldCount(fields.len()));
+        };
+
+        let mut weekdays = parse_field(weekday, "weekday", 0, 7, WEEKDAY_NAMES, 0)?;
+        if weekdays & (1 << 7) != 0 {
+            weekdays = (weekdays | 1) & !(1 << 7);
+        }
+        Ok(Self {
+            minutes: parse_field(minute, "minute", 0, 59, &[], 0)?,
+            hours: parse_field(hour, "hour", 0, 23, &[], 0)?,
+            days: parse_field(day, "day", 1, 31, &[], 0)?,
+            months: parse_field(month, "month", 1, 12, MONTH_NAMES, 1)?,
+            weekdays,
+            either_day: !day.starts_with('*') && !weekday.starts_with('*'),
+        })
+    }
+
+    /// The first time after `after` the schedule runs, reading the expression as local time
+    /// in `timezone`. Times skipped by a DST change don't run, repeated ones run once.
+    pub fn next_after(&self, after: DateTime<Utc>, timezone: Tz) -> Option<DateTime<Utc>> {
+        let start = after.with_timezone(&timezone).date_naive();
+        (0..MAX_LOOKAHEAD_DAYS)
+            .filter_map(|offset| start.checked_add_signed(Duration::days(offset)))
+            .filter(|date| self.runs_on(*date))
+            .find_map(|date| {
+                set_bits(self.hours, 23).find_map(|hour| {
+                    set_bits(self.minutes, 59).find_map(|minute| {
+                        let local = date.and_hms_opt(hour, minute, 0)?;
+                        let run_at = timezone
+                            .from_local_datetime(&local)
+                            .earliest()?
+                            .with_timezone(&Utc);
+                        (run_at > after).then_some(run_at)
+                    })
+                })
+            })
+    }
+
+    fn runs_on(&self, date: NaiveDate) -> bool {
+        if !has_bit(self.months, date.month()) {
+            return false;
+        }
+        let day = has_bit(self.days, date.day());
+        let weekday = has_bit(self.weekdays, date.weekday().num_days_from_sunday());
+        if self.either_day {
+            day || weekday
+        } else {
+            day && weekday
+        }
+    }
+}
+
+fn has_bit(mask: u64, value: u32) -> bool {
+    mask & (1 << value) != 0
+}
+
+fn set_bits(mask: u64, max: u32) -> impl Iterator<Item = u32> {
+    (0..=max).filter(move |value| has_bit(mask, *value))
+}
+
+/// Parse one field into a bitmask, `names[i]` stands for `first_name + i`
+fn parse_field(
+    text: &str,
+    field: &'static str,
+    min: u32,
+    max: u32,
+    names: &[&str],
+    first_name: u32,
+) -> Result<u64, CronError> {
+    let invalid = || CronError::InvalidField {
+        field,
+        value: text.to_string(),
+    };
+    let value = |part: &str| -> Result<u32, CronError> {
+        let value = match names.iter().position(|name| *name == part) {
+            Some(index) => index as u32 + first_name,
+            None => part.parse().map_err(|_| invalid())?,
+        };
+        if (min..=max).contains(&value) {
+            Ok(value)
+        } else {
+            Err(invalid())
+        }
+    };
+
+    let mut mask = 0u64;
+    for part in text.split(',') {
+        let (range, step) = match part.split_once('/') {
+            Some((range, step)) => (range, step.parse::<u32>().map_err(|_| invalid())?),
+            None => (part, 1),
+        };
+        if step == 0 {
+            return Err(invalid());
+        }
+        let (start, end) = match range.split_once('-') {
+            _ if range == "*" => (min, max),
+            Some((start, end)) => (value(start)?, value(end)?),
+            // `5/15` runs from 5 to the end of the range
+            None if part.contains('/') => (value(range)?, max),
+            None => {
+                let single = value(range)?;
+                (single, single)
+            }
+        };
+        if start > end {
+            return Err(invalid());
+        }
+        for value in (start..=end).step_by(step as usize) {
+            mask |= 1 << value;
+        }
+    }
+    Ok(mask)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn utc(text: &str) -> DateTime<Utc> {
+        text.parse().unwrap()
+    }
+
+    #[test]
+    fn test_parse() {
+        assert!(CronSchedule::parse("*/15 9-17 * * mon-fri").is_ok());
+        assert_eq!(
+            CronSchedule::parse("@daily"),
+            CronSchedule::parse("0 0 * * *")
+        );
+        assert_eq!(
+            CronSchedule::parse("0 0 * * 7"),
+            CronSchedule::parse("0 0 * * SUN")
+        );
+        assert_eq!(
+            CronSchedule::parse("0 9 * *"),
+            Err(CronError::FieldCount(4))
+        );
+        assert!(CronSchedule::parse("60 * * * *").is_err());
+        assert!(CronSchedule::parse("0 17-9 * * *").is_err());
+        assert!(CronSchedule::parse("*/0 * * * *").is_err());
+        assert!(CronSchedule::parse("0 0 * foo *").is_err());
+    }
+
+    #[test]
+    fn test_next_after() {
+        // 2025-10-24 is a friday
+        let weekdays = CronSchedule::parse("30 9 * * 1-5").unwrap();
+        assert_eq!(
+            weekdays.next_after(utc("2025-10-24T08:00:00Z"), Tz::UTC),
+            Some(utc("2025-10-24T09:30:00Z"))
+        );
+        assert_eq!(
+            weekdays.next_after(utc("2025-10-24T09:30:00Z"), Tz::UTC),
+            Some(utc("2025-10-27T09:30:00Z"))
+        );
+
+        let steps = CronSchedule::parse("5/20 * * * *").unwrap();
+        assert_eq!(
+            steps.next_after(utc("2025-10-24T08:46:00Z"), Tz::UTC),
+            Some(utc("2025-10-24T09:05:00Z"))
+        );
+
+        // the 1st or any monday
+        let either = CronSchedule::parse("0 12 1 * mon").unwrap();
+        assert_eq!(
+            either.next_after(utc("2025-10-24T00:00:00Z"), Tz::UTC),
+            Some(utc("2025-10-27T12:00:00Z"))
+        );
+
+        let leap_day = CronSchedule::parse("0 0 29 feb *").unwrap();
+        assert_eq!(
+            leap_day.next_after(utc("2025-10-24T00:00:00Z"), Tz::UTC),
+            Some(utc("2028-02-29T00:00:00Z"))
+        );
+    }
+
+    #[test]
+    fn test_next_after_in_timezone() {
+        let schedule = CronSchedule::parse("0 9 * * *").unwrap();
+        let berlin = chrono_tz::Europe::Berlin;
+        // UTC+2 before the switch back to winter time on 2025-10-26, UTC+1 after
+        assert_eq!(
+            schedule.next_after(utc("2025-10-25T06:00:00Z"), berlin),
+            Some(utc("2025-10-25T07:00:00Z"))
+        );
+        assert_eq!(
+            schedule.next_after(utc("2025-10-25T07:00:00Z"), berlin),
+            Some(utc("2025-10-26T08:00:00Z"))
+        );
+
+        // 02:30 doesn't exist on the day clocks go forward
+        let skipped = CronSchedule::parse("30 2 * * *").unwrap();
+        assert_eq!(
+            skipped.next_after(utc("2025-03-29T02:00:00Z"), berlin),
+            Some(utc("2025-03-31T00:30:00Z"))
+        );
+    }
+}
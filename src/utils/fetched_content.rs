@@ -0,0 +1,469 @@
+use crate::utils::readability;
+use crate::utils::regex_patterns::CHARSET_REGEX;
+use encoding_rs::{Encoding, UTF_8, WINDOWS_1252};
+use quick_xml::events::Event;
+use quick_xml::{Reader, XmlVersion};
+use serde_json::Value;
+
+/// JSON arrays and objects are cut to this many entries before pretty-printing
+const MAX_JSON_ENTRIES: usize = 25;
+const MAX_JSON_DEPTH: usize = 6;
+const MAX_JSON_STRING_CHARS: usize = 500;
+
+const MAX_FEED_ITEMS: usize = 20;
+const MAX_FEED_SUMMARY_CHARS: usize = 300;
+
+/// How far into a body to look for a meta charset or xml declaration
+const CHARSET_SNIFF_BYTES: usize = 1024;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ContentKind {
+    Html,
+    Json,
+    Xml,
+    Text,
+    Binary,
+}
+
+/// The mime type without parameters, lowercased
+fn essence(content_type: &str) -> String {
+    content_type
+        .split(';')
+        .next()
+        .unwrap_or_default()
+        .trim()
+        .to_lowercase()
+}
+
+impl ContentKind {
+    /// None when the header is missing or too vague and the body has to be sniffed
+    pub fn from_content_type(content_type: Option<&str>) -> Option<Self> {
+        let mime = essence(content_type?);
+        let kind = match mime.as_str() {
+            "" | "application/octet-stream" | "binary/octet-stream" => return None,
+            "text/html" | "application/xhtml+xml" => ContentKind::Html,
+            "application/json" | "text/json" => ContentKind::Json,
+            "application/xml" | "text/xml" => ContentKind::Xml,
+            _ if mime.ends_with("+json") => ContentKind::Json,
+            _ if mime.ends_with("+xml") && mime != "image/svg+xml" => ContentKind::Xml,
+            _ if mime.starts_with("text/") => ContentKind::Text,
+            "application/javascript"
+            | "application/x-javascript"
+            | "application/x-yaml"
+            | "application/yaml"
+            | "application/toml"
+            | "application/x-sh" => ContentKind::Text,
+            _ => ContentKind::Binary,
+        };
+        Some(kind)
+    }
+
+    /// Best guess from the first bytes of a body served without a usable content type
+    pub fn sniff(body: &[u8]) -> Self {
+        let head = &body[..body.len().min(CHARSET_SNIFF_BYTES)];
+        if head.contains(&0) {
+            return ContentKind::Binary;
+        }
+        let text = String::from_utf8_lossy(head);
+        let trimmed = text.trim_start_matches('\u{feff}').trim_start();
+        let lower = trimmed.to_lowercase();
+        if lower.starts_with("<!doctype html") || lower.starts_with("<html") {
+            ContentKind::Html
+        } else if lower.starts_with("<?xml")
+            || lower.starts_with("<rss")
+            || lower.starts_with("<feed")
+        {
+            ContentKind::Xml
+        } else if trimmed.starts_with('{') || trimmed.starts_with('[') {
+            ContentKind::Json
+        } else if text
+            .chars()
+            .filter(|c| *c == char::REPLACEMENT_CHARACTER)
+            .count()
+            > head.len() / 10
+        {
+            // mostly undecodable even as utf-8, not text in any useful sense
+            ContentKind::Binary
+        } else {
+            ContentKind::Text
+        }
+    }
+}
+
+/// Decode a body using the content type's charset, a BOM, a meta charset or xml
+/// declaration, then utf-8, falling back to windows-1252 for legacy pages
+pub fn decode_body(body: &[u8], content_type: Option<&str>) -> String {
+    let declared = content_type
+        .and_then(|content_type| CHARSET_REGEX.captures(content_type))
+        .and_then(|captures| Encoding::for_label(captures[1].as_bytes()));
+    let sniffed = || {
+        let head = String::from_utf8_lossy(&body[..body.len().min(CHARSET_SNIFF_BYTES)]);
+        CHARSET_REGEX
+            .captures(&head)
+            .and_then(|captures| Encoding::for_label(captures[1].as_bytes()))
+    };
+
+    let encoding = declared
+        .or_else(|| Encoding::for_bom(body).map(|(encoding, _)| encoding))
+        .or_else(sniffed)
+        .unwrap_or(if std::str::from_utf8(body).is_ok() {
+            UTF_8
+        } else {
+            WINDOWS_1252
+        });
+
+    encoding.decode(body).0.into_owned()
+}
+
+/// The text handed to the model for a decoded body of this kind
+pub fn format_text(kind: ContentKind, text: String) -> String {
+    match kind {
+        ContentKind::Html => readability::extract_article(&text).to_model_text(),
+        ContentKind::Json => format_json(&text).unwrap_or(text),
+        ContentKind::Xml => format_feed(&text).unwrap_or(text),
+        ContentKind::Text | ContentKind::Binary => text,
+    }
+}
+
+/// Pretty-printed JSON with long arrays, objects and strings cut short, so one big
+/// response doesn't crowd out everything else. None when the body isn't valid JSON.
+pub fn format_json(text: &str) -> Option<String> {
+    let value: Value = serde_json::from_str(text).ok()?;
+    serde_json::to_string_pretty(&truncate_json(value, 0)).ok()
+}
+
+fn truncate_json(value: Value, depth: usize) -> Value {
+    match value {
+        Value::Array(items) if depth >= MAX_JSON_DEPTH => {
+            Value::String(format!("[{} items]", items.len()))
+        }
+        Value::Object(entries) if depth >= MAX_JSON_DEPTH => {
+            Value::String(format!("{{{} keys}}", entries.len()))
+        }
+        Value::Array(items) => {
+            let total = items.len();
+            let mut kept: Vec<Value> = items
+                .into_iter()
+                .take(MAX_JSON_ENTRIES)
+                .map(|item| truncate_json(item, depth + 1))
+                .collect();
+            if total > MAX_JSON_ENTRIES {
+                kept.push(Value::String(format!(
+                    "... {} more items",
+                    total - MAX_JSON_ENTRIES
+                )));
+            }
+            Value::Array(kept)
+        }
+        Value::Object(entries) => {
+            let total = entries.len();
+            let mut kept: serde_json::Map<String, Value> = entries
+                .into_iter()
+                .take(MAX_JSON_ENTRIES)
+                .map(|(key, item)| (key, truncate_json(item, depth + 1)))
+                .collect();
+            if total > MAX_JSON_ENTRIES {
+                kept.insert(
+                    "...".to_string(),
+                    Value::String(format!("{} more keys", total - MAX_JSON_ENTRIES)),
+                );
+            }
+            Value::Object(kept)
+        }
+        Value::String(text) if text.chars().count() > MAX_JSON_STRING_CHARS => {
+            Value::String(format!(
+                "{}...",
+                text.chars().take(MAX_JSON_STRING_CHARS).collect::<String>()
+            ))
+        }
+        other => other,
+    }
+}
+
+#[derive(Debug, Default)]
+struct FeedItem {
+    title: String,
+    link: String,
+    published: String,
+    summary: String,
+}
+
+/// RSS and Atom feeds as a numbered list of entries. None for other XML, which is
+/// returned as is.
+pub fn format_feed(xml: &str) -> Option<String> {
+    let mut reader = Reader::from_str(xml);
+
+    let mut is_feed = false;
+    let mut feed_title = String::new();
+    let mut items: Vec<FeedItem> = Vec::new();
+    let mut in_item = false;
+    let mut field: Option<String> = None;
+
+    loop {
+        let event = reader.read_event().ok()?;
+        match event {
+            Event::Start(start) | Event::Empty(start) => {
+                let name = start.local_name().as_ref().to_lowercase();
+                match name.as_str() {
+                    "rss" | "feed" | "rdf" => is_feed = true,
+                    "item" | "entry" => {
+                        in_item = true;
+                        items.push(FeedItem::default());
+                    }
+                    // atom links live in the href attribute
+                    "link" if in_item => {
+                        let href = start
+                            .try_get_attribute("href")
+                            .ok()
+                            .flatten()
+                            .and_then(|attr| attr.normalized_value(XmlVersion::Implicit1_0).ok())
+                            .map(|href| href.into_owned());
+                        let item = items.last_mut().filter(|item| item.link.is_empty());
+                        if let (Some(href), Some(item)) = (href, item) {
+                            item.link = href;
+                        }
+                    }
+                    _ => {}
+                }
+                field = Some(name);
+            }
+            Event::End(end) => {
+                let name = end.local_name().as_ref().to_lowercase();
+                if name == "item" || name == "entry" {
+                    in_item = false;
+                }
+                field = None;
+            }
+            Event::Text(text) => {
+                push_feed_text(
+                    &field,
+                    in_item,
+                    &mut feed_title,
+                    &mut items,
+                    &text.xml10_content(),
+                );
+            }
+            Event::CData(data) => {
+                push_feed_text(
+                    &field,
+                    in_item,
+                    &mut feed_title,
+                    &mut items,
+                    &data.xml10_content(),
+                );
+            }
+            Event::GeneralRef(reference) => {
+                let escaped = format!("&{};", reference.xml10_content());
+                let resolved = quick_xml::escape::unescape(&escaped)
+                    .map(|text| text.into_owned())
+                    .unwrap_or(escaped);
+                push_feed_text(&field, in_item, &mut feed_title, &mut items, &resolved);
+            }
+            Event::Eof => break,
+            _ => {}
+        }
+    }
+
+    if !is_feed {
+        return None;
+    }
+
+    let mut out = String::new();
+    let feed_title = collapse(&feed_title);
+    if !feed_title.is_empty() {
+        out.push_str(&format!("Feed: {}\n\n", feed_title));
+    }
+    for (i, item) in items.iter().take(MAX_FEED_ITEMS).enumerate() {
+        out.push_str(&format!("{}. **{}**\n", i + 1, collapse(&item.title)));
+        if !item.link.is_empty() {
+            out.push_str(&format!("   Link: {}\n", collapse(&item.link)));
+        }
+        if !item.published.is_empty() {
+            out.push_str(&format!("   Published: {}\n", collapse(&item.published)));
+        }
+        // summaries are usually escaped html
+        let summary = readability::extract_article(&item.summary).text;
+        if !summary.is_empty() {
+            let summary = summary.replace('\n', " ");
+            let short: String = summary.chars().take(MAX_FEED_SUMMARY_CHARS).collect();
+            let ellipsis = if short.len() < summary.len() {
+                "..."
+            } else {
+                ""
+            };
+            out.push_str(&format!("   {}{}\n", short, ellipsis));
+        }
+        out.push('\n');
+    }
+    if items.len() > MAX_FEED_ITEMS {
+        out.push_str(&format!(
+            "[{} more entries]\n",
+            items.len() - MAX_FEED_ITEMS
+        ));
+    }
+    Some(out)
+}
+
+fn collapse(text: &str) -> String {
+    text.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+fn push_feed_text(
+    field: &Option<String>,
+    in_item: bool,
+    feed_title: &mut String,
+    items: &mut [FeedItem],
+    text: &str,
+) {
+    let Some(field) = field.as_deref() else {
+        return;
+    };
+    // the channel title comes before any entries
+    let before_items = items.is_empty();
+    let target = match (in_item, items.last_mut()) {
+        (true, Some(item)) => match field {
+            "title" => &mut item.title,
+            "link" => &mut item.link,
+            "pubdate" | "published" | "updated" | "date" => &mut item.published,
+            "description" | "summary" | "content" | "encoded" => &mut item.summary,
+            _ => return,
+        },
+        (false, _) if field == "title" && before_items => feed_title,
+        _ => return,
+    };
+    target.push_str(text);
+}
+
+/// What the model gets for binary content instead of its bytes
+pub fn binary_summary(
+    url: &str,
+    content_type: Option<&str>,
+    content_length: Option<u64>,
+    content_disposition: Option<&str>,
+) -> String {
+    let filename = content_disposition
+        .and_then(|disposition| disposition.split("filename=").nth(1))
+        .map(|name| {
+            name.trim_matches(|c| c == '"' || c == '\'' || c == ';')
+                .to_string()
+        })
+        .or_else(|| {
+            url.split(['?', '#'])
+                .next()
+                .and_then(|path| path.rsplit('/').next())
+                .filter(|name| !name.is_empty() && name.contains('.'))
+                .map(|name| name.to_string())
+        });
+
+    let mut out = format!(
+        "Binary content, not shown.\nContent-Type: {}\n",
+        content_type.unwrap_or("unknown")
+    );
+    if let Some(length) = content_length {
+        out.push_str(&format!("Size: {} bytes\n", length));
+    }
+    if let Some(filename) = filename {
+        out.push_str(&format!("Filename: {}\n", filename));
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_content_kind_from_header_and_sniffing() {
+        assert_eq!(
+            ContentKind::from_content_type(Some("text/html; charset=utf-8")),
+            Some(ContentKind::Html)
+        );
+        assert_eq!(
+            ContentKind::from_content_type(Some("application/ld+json")),
+            Some(ContentKind::Json)
+        );
+        assert_eq!(
+            ContentKind::from_content_type(Some("application/rss+xml")),
+            Some(ContentKind::Xml)
+        );
+        assert_eq!(
+            ContentKind::from_content_type(Some("image/png")),
+            Some(ContentKind::Binary)
+        );
+        assert_eq!(
+            ContentKind::from_content_type(Some("application/octet-stream")),
+            None
+        );
+
+        assert_eq!(ContentKind::sniff(b"  {\"a\": 1}"), ContentKind::Json);
+        assert_eq!(
+            ContentKind::sniff(b"<!DOCTYPE html><html>"),
+            ContentKind::Html
+        );
+        assert_eq!(
+            ContentKind::sniff(b"\x89PNG\r\n\x1a\n\0\0"),
+            ContentKind::Binary
+        );
+    }
+
+    #[test]
+    fn test_decode_body_honours_charsets() {
+        // "café" in latin-1
+        let latin1 = b"caf\xe9";
+        assert_eq!(
+            decode_body(latin1, Some("text/plain; charset=iso-8859-1")),
+            "café"
+        );
+        assert_eq!(decode_body(latin1, Some("text/plain")), "café");
+
+        let page = b"<html><head><meta charset=\"windows-1251\"></head>\xcf\xf0\xe8\xe2\xe5\xf2";
+        assert!(decode_body(page, Some("text/html")).ends_with("Привет"));
+        assert_eq!(decode_body("naïve".as_bytes(), None), "naïve");
+    }
+
+    #[test]
+    fn test_format_json_truncates_long_arrays() {
+        let items: Vec<u32> = (0..40).collect();
+        let text = serde_json::json!({ "items": items }).to_string();
+        let formatted = format_json(&text).unwrap();
+        assert!(formatted.contains("\"... 15 more items\""));
+        assert!(formatted.contains("\n"));
+        assert_eq!(format_json("not json"), None);
+    }
+
+    #[test]
+    fn test_format_feed_lists_rss_and_atom_entries() {
+        let rss = r#"<?xml version="1.0"?><rss><channel><title>News &amp; Stuff</title>
+            <item><title>First</title><link>https://example.com/1</link>
+            <pubDate>Mon, 01 Jan 2024 00:00:00 GMT</pubDate>
+            <description><![CDATA[<p>Hello <b>world</b></p>]]></description></item>
+            </channel></rss>"#;
+        let formatted = format_feed(rss).unwrap();
+        assert!(formatted.starts_with("Feed: News & Stuff"));
+        assert!(formatted.contains("1. **First**"));
+        assert!(formatted.contains("Link: https://example.com/1"));
+        assert!(formatted.contains("Hello world"));
+
+        let atom = r#"<feed xmlns="http://www.w3.org/2005/Atom"><title>Blog</title>
+            <entry><title>Post</title><link href="https://example.com/post"/></entry></feed>"#;
+        assert!(
+            format_feed(atom)
+                .unwrap()
+                .contains("Link: https://example.com/post")
+        );
+
+        assert_eq!(format_feed("<config><key>value</key></config>"), None);
+    }
+
+    #[test]
+    fn test_binary_summary_never_includes_bytes() {
+        let summary = binary_summary(
+            "https://example.com/files/report.pdf?download=1",
+            Some("application/pdf"),
+            Some(1024),
+            None,
+        );
+        assert!(summary.contains("Size: 1024 bytes"));
+        assert!(summary.contains("Filename: report.pdf"));
+    }
+}
@@ -0,0 +1,60 @@
+use std::collections::HashMap;
+use std::sync::Mutex;
+use tokio::task::AbortHandle;
+
+/// Answers being generated, keyed by the message they answer, so an edit to that message
+/// can stop the answer to what it said before
+#[derive(Default)]
+pub struct InFlight {
+    tasks: Mutex<HashMap<u64, AbortHandle>>,
+}
+
+impl InFlight {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Remember the task answering the message, forgetting the ones that finished
+    pub fn track(&self, message_id: u64, task: AbortHandle) {
+        let mut tasks = self.tasks.lock().unwrap_or_else(|e| e.into_inner());
+        tasks.retain(|_, task| !task.is_finished());
+        tasks.insert(message_id, task);
+    }
+
+    /// Stop the answer to the message. False when it already finished or never started.
+    pub fn cancel(&self, message_id: u64) -> bool {
+        let task = self
+            .tasks
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .remove(&message_id);
+        match task {
+            Some(task) if !task.is_finished() => {
+                task.abort();
+                true
+            }
+            _ => false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_cancel() {
+        let in_flight = InFlight::new();
+        let running = tokio::spawn(std::future::pending::<()>());
+        in_flight.track(1, running.abort_handle());
+        assert!(in_flight.cancel(1));
+        assert!(running.await.unwrap_err().is_cancelled());
+        assert!(!in_flight.cancel(1));
+
+        let done = tokio::spawn(async {});
+        let handle = done.abort_handle();
+        done.await.unwrap();
+        in_flight.track(2, handle);
+        assert!(!in_flight.cancel(2));
+    }
+}
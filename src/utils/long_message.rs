@@ -0,0 +1,51 @@
+/// Longest the message being answered goes into a prompt as is, a wall of pasted logs
+/// past this is cut in the middle
+pub const MAX_PROMPT_CHARS: usize = 6000;
+
+/// History turns only carry the flow of the conversation, they're cut sooner
+pub const MAX_HISTORY_CHARS: usize = 1500;
+
+/// Share of the kept text taken from the start, the rest comes from the end where logs
+/// usually have the error
+const HEAD_SHARE: f64 = 0.65;
+
+/// `content` with its middle replaced by a note telling the model how much was left out
+/// and how to read it, unchanged when it fits in `max_chars`
+pub fn shorten_for_prompt(content: &str, max_chars: usize, message_id: u64) -> String {
+    let total = content.chars().count();
+    if total <= max_chars {
+        return content.to_string();
+    }
+    let head_chars = (max_chars as f64 * HEAD_SHARE) as usize;
+    let tail_chars = max_chars - head_chars;
+    let head: String = content.chars().take(head_chars).collect();
+    let tail: String = content.chars().skip(total - tail_chars).collect();
+    format!(
+        "{}\n\n[… {} characters of this message left out, call read_full_message with message_id {} to read them …]\n\n{}",
+        head.trim_end(),
+        total - head_chars - tail_chars,
+        message_id,
+        tail.trim_start()
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_shorten_for_prompt() {
+        assert_eq!(shorten_for_prompt("short", 10, 1), "short");
+
+        let log: String = (0..100).map(|line| format!("line {:03}\n", line)).collect();
+        let shortened = shorten_for_prompt(&log, 100, 42);
+        assert!(shortened.starts_with("line 000\n"));
+        assert!(shortened.ends_with("line 099\n"));
+        assert!(shortened.contains("800 characters of this message left out"));
+        assert!(shortened.contains("message_id 42"));
+
+        // counts characters, not bytes
+        let emoji = "💅".repeat(20);
+        assert!(shorten_for_prompt(&emoji, 10, 1).contains("10 characters"));
+    }
+}
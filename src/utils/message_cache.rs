@@ -0,0 +1,273 @@
+use once_cell::sync::Lazy;
+use redis::{AsyncCommands, Client};
+use serde::{Deserialize, Serialize};
+use serenity::model::channel::Message;
+use std::collections::HashSet;
+use tracing::warn;
+
+/// How long cached messages and channel indexes live in redis
+const MESSAGE_TTL_SECS: u64 = 60 * 60 * 6;
+
+/// How long rewound message ids are remembered, as long as their turns would have been kept
+const REWOUND_TTL_SECS: u64 = 60 * 60 * 24 * 30;
+
+/// Newest messages kept in each channel index
+const MESSAGES_PER_CHANNEL: isize = 50;
+
+/// Identifies this process. A channel only counts as complete for the process that
+/// filled it, since messages sent while chloe was down never reached the cache.
+static INSTANCE_ID: Lazy<String> = Lazy::new(|| format!("{:016x}", rand::random::<u64>()));
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CachedAttachment {
+    pub id: u64,
+    pub url: String,
+    pub filename: String,
+    pub content_type: Option<String>,
+    pub size: u32,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CachedMessage {
+    pub id: u64,
+    pub channel_id: u64,
+    pub author_id: u64,
+    pub author_name: String,
+    pub is_bot: bool,
+    pub content: String,
+    pub attachments: Vec<CachedAttachment>,
+}
+
+impl CachedMessage {
+    pub fn from_message(msg: &Message, author_name: String) -> Self {
+        Self {
+            id: msg.id.get(),
+            channel_id: msg.channel_id.get(),
+            author_id: msg.author.id.get(),
+            author_name,
+            is_bot: msg.author.bot,
+            content: msg.content.clone(),
+            attachments: attachments_of(msg),
+        }
+    }
+}
+
+pub fn attachments_of(msg: &Message) -> Vec<CachedAttachment> {
+    msg.attachments
+        .iter()
+        .map(|attachment| CachedAttachment {
+            id: attachment.id.get(),
+            url: attachment.url.clone(),
+            filename: attachment.filename.clone(),
+            content_type: attachment.content_type.clone(),
+            size: attachment.size,
+        })
+        .collect()
+}
+
+/// Recently seen messages in redis, keyed by message id with a per-channel index, so
+/// context assembly doesn't re-fetch channel history on every mention
+pub struct MessageCache {
+    redis_client: Client,
+}
+
+impl MessageCache {
+    pub fn new(redis_client: Client) -> Self {
+        Self { redis_client }
+    }
+
+    pub async fn store(&self, message: &CachedMessage) {
+        if let Err(e) = self.try_store(message).await {
+            warn!(
+                event = "message_cache_store_failed",
+                message_id = message.id,
+                error = %e,
+                "Failed to cache message"
+            );
+        }
+    }
+
+    async fn try_store(&self, message: &CachedMessage) -> Result<(), String> {
+        let payload = serde_json::to_string(message)
+            .map_err(|e| format!("Failed to serialize message: {}", e))?;
+        let mut conn = self
+            .redis_client
+            .get_multiplexed_async_connection()
+            .await
+            .map_err(|e| format!("Failed to connect to redis: {}", e))?;
+
+        let index = channel_key(message.channel_id);
+        redis::pipe()
+            .set_ex(message_key(message.id), payload, MESSAGE_TTL_SECS)
+            .ignore()
+            .zadd(&index, message.id, snowflake_ms(message.id))
+            .ignore()
+            .zremrangebyrank(&index, 0, -(MESSAGES_PER_CHANNEL + 1))
+            .ignore()
+            .expire(&index, MESSAGE_TTL_SECS as i64)
+            .ignore()
+            .query_async::<()>(&mut conn)
+            .await
+            .map_err(|e| format!("Failed to cache message: {}", e))
+    }
+
+    /// Record that the cache holds this channel's full recent history, after a fetch
+    pub async fn mark_channel_complete(&self, channel_id: u64) {
+        let Ok(mut conn) = self.redis_client.get_multiplexed_async_connection().await else {
+            return;
+        };
+        let _: Result<(), _> = conn
+            .set_ex(
+                complete_key(channel_id),
+                INSTANCE_ID.as_str(),
+                MESSAGE_TTL_SECS,
+            )
+            .await;
+    }
+
+    /// Newest cached messages before `before_id`, newest first. None when the cache
+    /// can't vouch for the channel's history and it has to be fetched instead.
+    pub async fn recent_before(
+        &self,
+        channel_id: u64,
+        before_id: u64,
+        limit: usize,
+    ) -> Option<Vec<CachedMessage>> {
+        let mut conn = self
+            .redis_client
+            .get_multiplexed_async_connection()
+            .await
+            .ok()?;
+
+        let filled_by: Option<String> = conn.get(complete_key(channel_id)).await.ok()?;
+        if filled_by.as_deref() != Some(INSTANCE_ID.as_str()) {
+            return None;
+        }
+
+        let ids: Vec<u64> = conn
+            .zrevrangebyscore_limit(
+                channel_key(channel_id),
+                format!("({}", snowflake_ms(before_id)),
+                "-inf",
+                0,
+                limit as isize,
+            )
+            .await
+            .ok()?;
+        if ids.is_empty() {
+            return Some(Vec::new());
+        }
+
+        let keys: Vec<String> = ids.iter().map(|id| message_key(*id)).collect();
+        let payloads: Vec<Option<String>> = conn.mget(keys).await.ok()?;
+        Some(
+            payloads
+                .into_iter()
+                .flatten()
+                .filter_map(|payload| serde_json::from_str(&payload).ok())
+                .collect(),
+        )
+    }
+
+    pub async fn get(&self, message_id: u64) -> Option<CachedMessage> {
+        let mut conn = self
+            .redis_client
+            .get_multiplexed_async_connection()
+            .await
+            .ok()?;
+        let payload: Option<String> = conn.get(message_key(message_id)).await.ok()?;
+        serde_json::from_str(&payload?).ok()
+    }
+
+    pub async fn update_content(&self, message_id: u64, content: &str) {
+        let Ok(mut conn) = self.redis_client.get_multiplexed_async_connection().await else {
+            return;
+        };
+        let Ok(Some(payload)) = conn.get::<_, Option<String>>(message_key(message_id)).await else {
+            return;
+        };
+        if let Ok(mut message) = serde_json::from_str::<CachedMessage>(&payload) {
+            message.content = content.to_string();
+            self.store(&message).await;
+        }
+    }
+
+    pub async fn remove(&self, channel_id: u64, message_id: u64) {
+        let Ok(mut conn) = self.redis_client.get_multiplexed_async_connection().await else {
+            return;
+        };
+        let _: Result<(), _> = redis::pipe()
+            .del(message_key(message_id))
+            .ignore()
+            .zrem(channel_key(channel_id), message_id)
+            .ignore()
+            .query_async(&mut conn)
+            .await;
+    }
+
+    /// Drop rewound messages and remember them, so refilling the cache from discord
+    /// doesn't bring them back
+    pub async fn forget(&self, channel_id: u64, message_ids: &[u64]) {
+        if message_ids.is_empty() {
+            return;
+        }
+        let Ok(mut conn) = self.redis_client.get_multiplexed_async_connection().await else {
+            return;
+        };
+        let mut pipe = redis::pipe();
+        for message_id in message_ids {
+            pipe.del(message_key(*message_id)).ignore();
+        }
+        let _: Result<(), _> = pipe
+            .zrem(channel_key(channel_id), message_ids)
+            .ignore()
+            .sadd(rewound_key(channel_id), message_ids)
+            .ignore()
+            .expire(rewound_key(channel_id), REWOUND_TTL_SECS as i64)
+            .ignore()
+            .query_async(&mut conn)
+            .await;
+    }
+
+    /// Ids of the channel's messages dropped by a rewind
+    pub async fn rewound(&self, channel_id: u64) -> HashSet<u64> {
+        let Ok(mut conn) = self.redis_client.get_multiplexed_async_connection().await else {
+            return HashSet::new();
+        };
+        conn.smembers(rewound_key(channel_id))
+            .await
+            .unwrap_or_default()
+    }
+}
+
+/// Milliseconds since the discord epoch, exact as an f64 score unlike the raw snowflake
+fn snowflake_ms(id: u64) -> u64 {
+    id >> 22
+}
+
+fn message_key(message_id: u64) -> String {
+    format!("chloe:message:{}", message_id)
+}
+
+fn channel_key(channel_id: u64) -> String {
+    format!("chloe:channel_messages:{}", channel_id)
+}
+
+fn complete_key(channel_id: u64) -> String {
+    format!("chloe:channel_messages_complete:{}", channel_id)
+}
+
+fn rewound_key(channel_id: u64) -> String {
+    format!("chloe:channel_rewound:{}", channel_id)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_snowflake_ms_orders_like_ids() {
+        assert_eq!(snowflake_ms(1 << 22), 1);
+        assert!(snowflake_ms(175928847299117063) < snowflake_ms(1_100_000_000_000_000_000));
+    }
+}
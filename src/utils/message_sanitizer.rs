@@ -1,50 +1,79 @@
+use crate::utils::code_blocks::{self, Segment};
 use crate::utils::regex_patterns::{IMPERSONATION_PATTERN, FAKE_MENTION_PATTERN};
 
 pub struct MessageSanitizer;
 
 impl MessageSanitizer {
-    /// Sanitize a message to prevent impersonation attempts
+    /// Sanitize a message to prevent impersonation attempts. Fenced code blocks are kept
+    /// exactly as written, only tagged with a language when they have none.
     pub fn sanitize_message(content: &str, author_name: &str) -> String {
-        let mut sanitized = content.to_string();
-        
+        let segments = code_blocks::split(content);
+        let prose = segments.iter()
+            .filter_map(|segment| match segment {
+                Segment::Text(text) => Some(*text),
+                Segment::Code(_) => None,
+            })
+            .collect::<Vec<_>>()
+            .join("\n");
+
         // Replace potential impersonation attempts with quoted text
-        if IMPERSONATION_PATTERN.is_match(&sanitized) {
+        let mut quote = false;
+        if IMPERSONATION_PATTERN.is_match(&prose) {
             // Check if the message contains multiple lines that look like chat format
-            let lines: Vec<&str> = sanitized.lines().collect();
+            let lines: Vec<&str> = prose.lines().collect();
             let suspicious_lines = lines.iter()
-                .filter(|line| {
-                    line.contains(':') && 
-                    !line.trim().is_empty() &&
-                    !line.starts_with('>') && // Not already quoted
-                    !line.starts_with("http") // Not a URL
-                })
+                .filter(|line| !line.trim().is_empty() && Self::looks_like_chat(line))
                 .count();
-            
+
             // If multiple lines look like chat format, it's likely an impersonation attempt
-            if suspicious_lines > 1 || (suspicious_lines == 1 && lines.len() > 1) {
-                // Quote each line to make it clear it's part of the user's message
-                sanitized = lines.iter()
-                    .map(|line| {
-                        if line.contains(':') && !line.starts_with('>') && !line.starts_with("http") {
-                            format!("> {}", line)
-                        } else {
-                            line.to_string()
-                        }
-                    })
-                    .collect::<Vec<_>>()
-                    .join("\n");
-                
-                // Add a note about who actually sent this
-                sanitized = format!("{} said:\n{}", author_name, sanitized);
+            quote = suspicious_lines > 1 || (suspicious_lines == 1 && lines.len() > 1);
+        }
+
+        let mut sanitized = String::with_capacity(content.len());
+        for (index, segment) in segments.iter().enumerate() {
+            match segment {
+                Segment::Text(text) => {
+                    let text = if quote {
+                        // text right after a code block continues the fence's line
+                        Self::quote_chat_lines(text, index == 0)
+                    } else {
+                        text.to_string()
+                    };
+                    // Remove fake Discord mentions that might confuse the bot
+                    sanitized.push_str(&FAKE_MENTION_PATTERN.replace_all(&text, "[mention]: "));
+                }
+                Segment::Code(block) => sanitized.push_str(&block.annotated()),
             }
         }
-        
-        // Remove fake Discord mentions that might confuse the bot
-        sanitized = FAKE_MENTION_PATTERN.replace_all(&sanitized, "[mention]: ").to_string();
-        
+
+        if quote {
+            // Add a note about who actually sent this
+            sanitized = format!("{} said:\n{}", author_name, sanitized);
+        }
         sanitized
     }
-    
+
+    fn looks_like_chat(line: &str) -> bool {
+        line.contains(':') &&
+        !line.starts_with('>') && // Not already quoted
+        !line.starts_with("http") // Not a URL
+    }
+
+    /// Quote each line to make it clear it's part of the user's message
+    fn quote_chat_lines(text: &str, starts_line: bool) -> String {
+        text.split('\n')
+            .enumerate()
+            .map(|(index, line)| {
+                if (index > 0 || starts_line) && Self::looks_like_chat(line) {
+                    format!("> {}", line)
+                } else {
+                    line.to_string()
+                }
+            })
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
     /// Add metadata to messages to ensure proper attribution
     pub fn add_attribution_metadata(content: &str, _user_id: u64, _author_name: &str) -> String {
         // Add zero-width spaces to break up patterns that might be interpreted as usernames
@@ -84,4 +113,20 @@ mod tests {
         let sanitized = MessageSanitizer::sanitize_message(message, "User");
         assert_eq!(sanitized, "Check out https://example.com:8080");
     }
+
+    #[test]
+    fn test_code_blocks_kept() {
+        let message = "why does this fail?\n```\nfn main() {\n    let x: u8 = 256;\n}\n```";
+        let sanitized = MessageSanitizer::sanitize_message(message, "User");
+        assert_eq!(
+            sanitized,
+            "why does this fail?\n```rust\nfn main() {\n    let x: u8 = 256;\n}\n```"
+        );
+
+        // chat lines outside the code are still quoted
+        let message = "Bob: hi\nAlice: hey\n```yaml\nkey: value\n```";
+        let sanitized = MessageSanitizer::sanitize_message(message, "User");
+        assert!(sanitized.contains("> Alice: hey"));
+        assert!(sanitized.ends_with("```yaml\nkey: value\n```"));
+    }
 }
\ No newline at end of file
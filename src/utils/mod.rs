@@ -1,8 +1,27 @@
+pub mod build_info;
+pub mod cache_stats;
+pub mod code_blocks;
+pub mod cron;
+pub mod fetched_content;
 pub mod image_processor;
+pub mod in_flight;
+pub mod long_message;
+pub mod message_cache;
 pub mod message_sanitizer;
+pub mod nickname_cache;
+pub mod pagination;
+pub mod presence;
 pub mod rate_limiter;
+pub mod reaction_tracker;
+pub mod readability;
 pub mod regex_patterns;
+pub mod repetition_guard;
+pub mod robots;
+pub mod video_frames;
 
 pub use image_processor::ImageProcessor;
 pub use message_sanitizer::MessageSanitizer;
-pub use rate_limiter::{RateLimiter, create_llm_rate_limiter, create_api_rate_limiter};
+pub use pagination::Paginator;
+pub use rate_limiter::{
+    RateLimiter, create_api_rate_limiter, create_dm_rate_limiter, create_llm_rate_limiter,
+};
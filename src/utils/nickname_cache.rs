@@ -0,0 +1,76 @@
+use serenity::http::Http;
+use serenity::model::channel::Message;
+use serenity::model::id::GuildId;
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+use tokio::sync::RwLock;
+
+/// Entries are refetched after this even without a member update, since those events
+/// only arrive with the GUILD_MEMBERS intent
+const NICKNAME_TTL: Duration = Duration::from_secs(30 * 60);
+
+/// Guild display names keyed by (guild, user), so assembling a 20 message context
+/// doesn't cost a member lookup per message
+pub struct NicknameCache {
+    names: RwLock<HashMap<(u64, u64), (String, Instant)>>,
+}
+
+impl NicknameCache {
+    pub fn new() -> Self {
+        Self {
+            names: RwLock::new(HashMap::new()),
+        }
+    }
+
+    /// The author's name in the guild. Gateway messages carry the member and refresh
+    /// the cache for free, fetched history falls back to the cache and then discord.
+    pub async fn display_name(
+        &self,
+        http: &Http,
+        guild_id: Option<GuildId>,
+        msg: &Message,
+    ) -> String {
+        let fallback = msg.author.display_name().to_string();
+        let Some(guild_id) = guild_id.or(msg.guild_id) else {
+            return fallback;
+        };
+        let key = (guild_id.get(), msg.author.id.get());
+
+        if let Some(member) = &msg.member {
+            let name = member.nick.clone().unwrap_or(fallback);
+            self.update(key.0, key.1, name.clone()).await;
+            return name;
+        }
+
+        let cached = self
+            .names
+            .read()
+            .await
+            .get(&key)
+            .filter(|(_, cached_at)| cached_at.elapsed() < NICKNAME_TTL)
+            .map(|(name, _)| name.clone());
+        if let Some(name) = cached {
+            return name;
+        }
+
+        let name = guild_id
+            .member(http, msg.author.id)
+            .await
+            .ok()
+            .and_then(|member| member.nick)
+            .unwrap_or(fallback);
+        self.update(key.0, key.1, name.clone()).await;
+        name
+    }
+
+    pub async fn update(&self, guild_id: u64, user_id: u64, name: String) {
+        self.names
+            .write()
+            .await
+            .insert((guild_id, user_id), (name, Instant::now()));
+    }
+
+    pub async fn invalidate(&self, guild_id: u64, user_id: u64) {
+        self.names.write().await.remove(&(guild_id, user_id));
+    }
+}
@@ -0,0 +1,269 @@
+use redis::{AsyncCommands, Client};
+use serde::{Deserialize, Serialize};
+use serenity::builder::{
+    CreateActionRow, CreateButton, CreateEmbed, CreateEmbedFooter, CreateInteractionResponse,
+    CreateInteractionResponseMessage, CreateMessage,
+};
+use serenity::http::Http;
+use serenity::model::application::ComponentInteraction;
+use serenity::model::channel::ReactionType;
+use serenity::model::id::{ChannelId, MessageId};
+use tracing::{error, info};
+
+/// Discord caps embed descriptions at 4096 characters, leave room for escaping
+pub const MAX_PAGE_CHARS: usize = 3900;
+
+/// How long page state is kept in redis before the buttons stop working
+const PAGE_STATE_TTL_SECS: u64 = 60 * 60 * 6;
+
+const PAGE_BUTTON_PREFIX: &str = "chloe_page";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct PaginationState {
+    title: Option<String>,
+    pages: Vec<String>,
+}
+
+/// Embed pagination with ◀ ▶ buttons. Page state lives in redis so the buttons keep
+/// working across restarts until the state expires.
+pub struct Paginator {
+    redis_client: Client,
+}
+
+impl Paginator {
+    pub fn new(redis_client: Client) -> Self {
+        Self { redis_client }
+    }
+
+    pub fn owns_interaction(interaction: &ComponentInteraction) -> bool {
+        interaction
+            .data
+            .custom_id
+            .starts_with(&format!("{}:", PAGE_BUTTON_PREFIX))
+    }
+
+    /// Split text into pages on line boundaries, hard-splitting lines that are too long
+    pub fn split_pages(text: &str, max_chars: usize) -> Vec<String> {
+        let mut pages = Vec::new();
+        let mut current = String::new();
+        let mut current_chars = 0;
+
+        for line in text.lines() {
+            let line_chars = line.chars().count();
+
+            if line_chars > max_chars {
+                if !current.is_empty() {
+                    pages.push(std::mem::take(&mut current));
+                    current_chars = 0;
+                }
+                let chars: Vec<char> = line.chars().collect();
+                for chunk in chars.chunks(max_chars) {
+                    pages.push(chunk.iter().collect());
+                }
+                continue;
+            }
+
+            // +1 for the newline joining this line to the page
+            if current_chars + line_chars + 1 > max_chars && !current.is_empty() {
+                pages.push(std::mem::take(&mut current));
+                current_chars = 0;
+            }
+
+            if !current.is_empty() {
+                current.push('\n');
+                current_chars += 1;
+            }
+            current.push_str(line);
+            current_chars += line_chars;
+        }
+
+        if !current.trim().is_empty() {
+            pages.push(current);
+        }
+
+        pages
+    }
+
+    /// Send pages as an embed, with navigation buttons when there's more than one page
+    pub async fn send_pages(
+        &self,
+        http: &Http,
+        channel_id: ChannelId,
+        reply_to: Option<MessageId>,
+        title: Option<&str>,
+        pages: Vec<String>,
+    ) -> Result<(), String> {
+        let page_count = pages.len();
+        let (embed, components, pagination_id) = self.first_page(title, pages).await?;
+        let mut message = CreateMessage::new().embed(embed).components(components);
+        if let Some(message_id) = reply_to {
+            message = message.reference_message((channel_id, message_id));
+        }
+
+        channel_id
+            .send_message(http, message)
+            .await
+            .map_err(|e| format!("Failed to send paginated message: {}", e))?;
+
+        info!(
+            event = "paginated_message_sent",
+            pagination_id = %pagination_id,
+            page_count = page_count,
+            "Sent paginated embed"
+        );
+
+        Ok(())
+    }
+
+    /// The first page as an embed with its buttons and the pagination id, for replies sent
+    /// some other way than `send_pages`, like command responses
+    pub async fn first_page(
+        &self,
+        title: Option<&str>,
+        pages: Vec<String>,
+    ) -> Result<(CreateEmbed, Vec<CreateActionRow>, String), String> {
+        if pages.is_empty() {
+            return Err("Nothing to paginate".to_string());
+        }
+
+        let state = PaginationState {
+            title: title.map(|t| t.to_string()),
+            pages,
+        };
+        let pagination_id = chrono::Utc::now()
+            .timestamp_nanos_opt()
+            .unwrap_or(0)
+            .to_string();
+
+        if state.pages.len() > 1 {
+            self.store_state(&pagination_id, &state).await?;
+        }
+
+        let (embed, components) = render_page(&state, &pagination_id, 0);
+        Ok((embed, components, pagination_id))
+    }
+
+    /// Flip to the page encoded in the button's custom id
+    pub async fn handle_interaction(&self, http: &Http, interaction: &ComponentInteraction) {
+        let mut parts = interaction.data.custom_id.splitn(3, ':').skip(1);
+        let (Some(pagination_id), Some(page)) = (
+            parts.next(),
+            parts.next().and_then(|p| p.parse::<usize>().ok()),
+        ) else {
+            return;
+        };
+
+        let response = match self.load_state(pagination_id).await {
+            Some(state) => {
+                let page = page.min(state.pages.len().saturating_sub(1));
+                let (embed, components) = render_page(&state, pagination_id, page);
+                CreateInteractionResponse::UpdateMessage(
+                    CreateInteractionResponseMessage::new()
+                        .embed(embed)
+                        .components(components),
+                )
+            }
+            None => CreateInteractionResponse::Message(
+                CreateInteractionResponseMessage::new()
+                    .content("these pages expired, ask me again 💭")
+                    .ephemeral(true),
+            ),
+        };
+
+        if let Err(e) = interaction.create_response(http, response).await {
+            error!(
+                event = "pagination_response_failed",
+                pagination_id = %pagination_id,
+                error = ?e,
+                "Failed to update paginated message"
+            );
+        }
+    }
+
+    async fn store_state(&self, pagination_id: &str, state: &PaginationState) -> Result<(), String> {
+        let payload = serde_json::to_string(state)
+            .map_err(|e| format!("Failed to serialize pagination state: {}", e))?;
+        let mut conn = self
+            .redis_client
+            .get_multiplexed_async_connection()
+            .await
+            .map_err(|e| format!("Failed to connect to redis: {}", e))?;
+        conn.set_ex::<_, _, ()>(state_key(pagination_id), payload, PAGE_STATE_TTL_SECS)
+            .await
+            .map_err(|e| format!("Failed to store pagination state: {}", e))
+    }
+
+    async fn load_state(&self, pagination_id: &str) -> Option<PaginationState> {
+        let mut conn = self.redis_client.get_multiplexed_async_connection().await.ok()?;
+        let payload: Option<String> = conn.get(state_key(pagination_id)).await.ok()?;
+        serde_json::from_str(&payload?).ok()
+    }
+}
+
+fn state_key(pagination_id: &str) -> String {
+    format!("chloe:pagination:{}", pagination_id)
+}
+
+fn render_page(
+    state: &PaginationState,
+    pagination_id: &str,
+    page: usize,
+) -> (CreateEmbed, Vec<CreateActionRow>) {
+    let page_count = state.pages.len();
+    let mut embed = CreateEmbed::new().description(&state.pages[page]);
+    if let Some(title) = &state.title {
+        embed = embed.title(title);
+    }
+
+    if page_count <= 1 {
+        return (embed, vec![]);
+    }
+
+    embed = embed.footer(CreateEmbedFooter::new(format!(
+        "page {}/{}",
+        page + 1,
+        page_count
+    )));
+
+    let previous = CreateButton::new(format!(
+        "{}:{}:{}",
+        PAGE_BUTTON_PREFIX,
+        pagination_id,
+        page.saturating_sub(1)
+    ))
+    .emoji(ReactionType::Unicode("◀️".to_string()))
+    .disabled(page == 0);
+    let next = CreateButton::new(format!(
+        "{}:{}:{}",
+        PAGE_BUTTON_PREFIX,
+        pagination_id,
+        page + 1
+    ))
+    .emoji(ReactionType::Unicode("▶️".to_string()))
+    .disabled(page + 1 >= page_count);
+
+    (embed, vec![CreateActionRow::Buttons(vec![previous, next])])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_split_pages_on_lines() {
+        let pages = Paginator::split_pages("aaaa\nbbbb\ncccc", 9);
+        assert_eq!(pages, vec!["aaaa\nbbbb", "cccc"]);
+    }
+
+    #[test]
+    fn test_split_pages_hard_splits_long_lines() {
+        let pages = Paginator::split_pages("abcdefghij", 4);
+        assert_eq!(pages, vec!["abcd", "efgh", "ij"]);
+    }
+
+    #[test]
+    fn test_split_pages_counts_chars_not_bytes() {
+        let pages = Paginator::split_pages("💅💅💅", 3);
+        assert_eq!(pages, vec!["💅💅💅"]);
+    }
+}
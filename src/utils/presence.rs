@@ -0,0 +1,83 @@
+use serenity::model::gateway::{Activity, ActivityType};
+
+/// `DISCORD_PRESENCES=true` subscribes to presence updates so chloe knows what members
+/// are playing or listening to. The presence intent has to be enabled for the bot in the
+/// developer portal too.
+pub fn presences_enabled() -> bool {
+    std::env::var("DISCORD_PRESENCES")
+        .map(|value| value == "true" || value == "1")
+        .unwrap_or(false)
+}
+
+/// What a member is doing right now, e.g. `playing Hades II` or `listening to Espresso
+/// by Sabrina Carpenter on Spotify`, None when their presence shows nothing
+pub fn describe_activities(activities: &[Activity]) -> Option<String> {
+    let described: Vec<String> = activities.iter().filter_map(describe_activity).collect();
+    (!described.is_empty()).then(|| described.join(", "))
+}
+
+fn describe_activity(activity: &Activity) -> Option<String> {
+    let described = match activity.kind {
+        // spotify puts the track in details and the artists in state
+        ActivityType::Listening if activity.name == "Spotify" => match detail(&activity.details) {
+            Some(track) => match detail(&activity.state) {
+                Some(artists) => format!("listening to {} by {} on Spotify", track, artists),
+                None => format!("listening to {} on Spotify", track),
+            },
+            None => "listening to Spotify".to_string(),
+        },
+        ActivityType::Listening => format!("listening to {}", activity.name),
+        ActivityType::Playing => match detail(&activity.details) {
+            Some(details) => format!("playing {} ({})", activity.name, details),
+            None => format!("playing {}", activity.name),
+        },
+        ActivityType::Streaming => match detail(&activity.details) {
+            Some(title) => format!("streaming {} ({})", activity.name, title),
+            None => format!("streaming {}", activity.name),
+        },
+        ActivityType::Watching => format!("watching {}", activity.name),
+        ActivityType::Competing => format!("competing in {}", activity.name),
+        ActivityType::Custom => format!("custom status \"{}\"", detail(&activity.state)?),
+        _ => return None,
+    };
+    Some(described)
+}
+
+fn detail(value: &Option<String>) -> Option<&str> {
+    value.as_deref().filter(|value| !value.is_empty())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn activity(value: serde_json::Value) -> Activity {
+        let mut value = value;
+        value["created_at"] = json!(0);
+        serde_json::from_value(value).unwrap()
+    }
+
+    #[test]
+    fn test_describe_activities() {
+        let spotify = activity(json!({
+            "type": 2,
+            "name": "Spotify",
+            "details": "Espresso",
+            "state": "Sabrina Carpenter"
+        }));
+        let game = activity(json!({"type": 0, "name": "Hades II"}));
+        let status = activity(json!({"type": 4, "name": "Custom Status", "state": ""}));
+
+        assert_eq!(
+            describe_activities(&[game, spotify]),
+            Some(
+                "playing Hades II, listening to Espresso by Sabrina Carpenter on Spotify"
+                    .to_string()
+            )
+        );
+        // an empty custom status shows nothing
+        assert_eq!(describe_activities(&[status]), None);
+        assert_eq!(describe_activities(&[]), None);
+    }
+}
@@ -1,84 +1,373 @@
+use std::collections::HashMap;
 use std::sync::Arc;
-use tokio::sync::Semaphore;
+use tokio::sync::{Mutex, Semaphore};
 use tokio::time::{Duration, Instant};
-use std::collections::HashMap;
-use tokio::sync::Mutex;
 
-/// Rate limiter for API calls
+/// Buckets kept before idle, full ones are dropped
+const PRUNE_ABOVE_KEYS: usize = 1024;
+
+/// Keys listed in a snapshot, busiest first
+const SNAPSHOT_KEYS: usize = 5;
+
+/// Rate limiter for API calls, a token bucket per key under a shared concurrency limit.
+/// A key can spend `burst` requests at once, then gets `refill_per_sec` back every second.
 pub struct RateLimiter {
     /// Semaphore for concurrent request limiting
     semaphore: Arc<Semaphore>,
-    /// Track last request time per key
-    last_request: Arc<Mutex<HashMap<String, Instant>>>,
-    /// Minimum time between requests (per key)
-    min_interval: Duration,
+    max_concurrent: usize,
+    state: Mutex<LimiterState>,
+    burst: f64,
+    refill_per_sec: f64,
+}
+
+#[derive(Default)]
+struct LimiterState {
+    /// Token bucket per key
+    buckets: HashMap<String, Bucket>,
+    /// Kept apart from the buckets so pruning idle keys doesn't lose them
+    totals: LimiterCounts,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Bucket {
+    /// Goes negative while requests wait for tokens that haven't refilled yet, so waiters
+    /// are served in the order they arrived
+    tokens: f64,
+    updated: Instant,
+    counts: LimiterCounts,
+}
+
+impl Bucket {
+    fn refill(&mut self, now: Instant, burst: f64, refill_per_sec: f64) {
+        let elapsed = now.duration_since(self.updated).as_secs_f64();
+        self.tokens = (self.tokens + elapsed * refill_per_sec).min(burst);
+        self.updated = now;
+    }
+}
+
+/// Why a permit wasn't handed out
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum RateLimitRejection {
+    /// Every concurrency slot is taken, by anyone
+    Concurrency,
+    /// The key spent its burst, its next token is back after `retry_after`
+    Interval { retry_after: Duration },
+}
+
+/// What a key, or the whole limiter, went through since startup
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct LimiterCounts {
+    pub acquired: u64,
+    /// Permits that had to wait for a slot or a token first
+    pub waited: u64,
+    pub total_wait: Duration,
+    pub rejected_concurrency: u64,
+    pub rejected_interval: u64,
+}
+
+impl LimiterCounts {
+    /// Average wait of the permits that waited, None when none did
+    pub fn average_wait(&self) -> Option<Duration> {
+        (self.waited > 0).then(|| self.total_wait / self.waited as u32)
+    }
+
+    pub fn rejected(&self) -> u64 {
+        self.rejected_concurrency + self.rejected_interval
+    }
+
+    fn record_acquired(&mut self, wait: Duration) {
+        self.acquired += 1;
+        if !wait.is_zero() {
+            self.waited += 1;
+            self.total_wait += wait;
+        }
+    }
+
+    fn record_rejected(&mut self, rejection: RateLimitRejection) {
+        match rejection {
+            RateLimitRejection::Concurrency => self.rejected_concurrency += 1,
+            RateLimitRejection::Interval { .. } => self.rejected_interval += 1,
+        }
+    }
+}
+
+/// One key's share of the limiter
+#[derive(Debug, Clone, PartialEq)]
+pub struct KeyUsage {
+    pub key: String,
+    /// How much of the burst is spent, 1.0 once requests wait or are turned away
+    pub saturation: f64,
+    pub counts: LimiterCounts,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct RateLimiterSnapshot {
+    /// Permits held right now
+    pub in_flight: usize,
+    pub max_concurrent: usize,
+    pub totals: LimiterCounts,
+    /// The busiest keys, most saturated first
+    pub keys: Vec<KeyUsage>,
 }
 
 impl RateLimiter {
-    pub fn new(max_concurrent: usize, min_interval_ms: u64) -> Self {
+    pub fn new(max_concurrent: usize, burst: u32, refill_per_sec: f64) -> Self {
         Self {
             semaphore: Arc::new(Semaphore::new(max_concurrent)),
-            last_request: Arc::new(Mutex::new(HashMap::new())),
-            min_interval: Duration::from_millis(min_interval_ms),
+            max_concurrent,
+            state: Mutex::new(LimiterState::default()),
+            burst: burst.max(1) as f64,
+            refill_per_sec: refill_per_sec.max(f64::EPSILON),
         }
     }
 
-    /// Acquire a permit for making a request
+    /// Acquire a permit for making a request, waiting for the key's bucket to refill when
+    /// its burst is spent
     pub async fn acquire(&self, key: String) -> Result<RateLimitPermit, String> {
-        // First, acquire semaphore permit for concurrent limiting
-        let permit = self.semaphore.clone().acquire_owned().await
-            .map_err(|_| "Failed to acquire semaphore permit".to_string())?;
+        // First, acquire semaphore permit for concurrent limiting, timing it only when
+        // every slot is taken
+        let (permit, waited) = match self.semaphore.clone().try_acquire_owned() {
+            Ok(permit) => (permit, Duration::ZERO),
+            Err(_) => {
+                let started = Instant::now();
+                let permit = self
+                    .semaphore
+                    .clone()
+                    .acquire_owned()
+                    .await
+                    .map_err(|_| "Failed to acquire semaphore permit".to_string())?;
+                (permit, started.elapsed())
+            }
+        };
+
+        let wait = self.take_token(key, waited).await;
+        if !wait.is_zero() {
+            tokio::time::sleep(wait).await;
+        }
+
+        Ok(RateLimitPermit { _permit: permit })
+    }
+
+    /// A permit only if one is free right now, or why not, without waiting for a
+    /// concurrency slot or a token
+    pub async fn try_acquire(&self, key: String) -> Result<RateLimitPermit, RateLimitRejection> {
+        let permit = self.semaphore.clone().try_acquire_owned().ok();
 
-        // Then check rate limit per key
-        let mut last_request_map = self.last_request.lock().await;
         let now = Instant::now();
-        
-        if let Some(&last_time) = last_request_map.get(&key) {
-            let elapsed = now.duration_since(last_time);
-            if elapsed < self.min_interval {
-                let wait_time = self.min_interval - elapsed;
-                drop(last_request_map); // Release lock during sleep
-                tokio::time::sleep(wait_time).await;
-                
-                // Re-acquire lock and update time
-                let mut last_request_map = self.last_request.lock().await;
-                last_request_map.insert(key.clone(), Instant::now());
-            } else {
-                last_request_map.insert(key.clone(), now);
+        let mut state = self.state.lock().await;
+        let LimiterState { buckets, totals } = &mut *state;
+        let bucket = self.bucket(buckets, key, now);
+        let rejection = match permit {
+            None => RateLimitRejection::Concurrency,
+            Some(_) if bucket.tokens < 1.0 => RateLimitRejection::Interval {
+                retry_after: Duration::from_secs_f64((1.0 - bucket.tokens) / self.refill_per_sec),
+            },
+            Some(permit) => {
+                bucket.tokens -= 1.0;
+                bucket.counts.record_acquired(Duration::ZERO);
+                totals.record_acquired(Duration::ZERO);
+                return Ok(RateLimitPermit { _permit: permit });
             }
-        } else {
-            last_request_map.insert(key.clone(), now);
+        };
+        bucket.counts.record_rejected(rejection);
+        totals.record_rejected(rejection);
+        Err(rejection)
+    }
+
+    /// Take a token from the key's bucket, returning how long until it's actually there.
+    /// `waited` is how long the request already waited for a concurrency slot.
+    async fn take_token(&self, key: String, waited: Duration) -> Duration {
+        let now = Instant::now();
+        let mut state = self.state.lock().await;
+        let LimiterState { buckets, totals } = &mut *state;
+        if buckets.len() > PRUNE_ABOVE_KEYS {
+            buckets.retain(|_, bucket| {
+                bucket.refill(now, self.burst, self.refill_per_sec);
+                bucket.tokens < self.burst
+            });
         }
 
-        Ok(RateLimitPermit {
-            _permit: permit,
-            key,
-            last_request: self.last_request.clone(),
-        })
+        let bucket = self.bucket(buckets, key, now);
+        bucket.tokens -= 1.0;
+        let wait = if bucket.tokens >= 0.0 {
+            Duration::ZERO
+        } else {
+            Duration::from_secs_f64(-bucket.tokens / self.refill_per_sec)
+        };
+        bucket.counts.record_acquired(waited + wait);
+        totals.record_acquired(waited + wait);
+        wait
+    }
+
+    /// The key's bucket, refilled up to `now`
+    fn bucket<'a>(
+        &self,
+        buckets: &'a mut HashMap<String, Bucket>,
+        key: String,
+        now: Instant,
+    ) -> &'a mut Bucket {
+        let bucket = buckets.entry(key).or_insert(Bucket {
+            tokens: self.burst,
+            updated: now,
+            counts: LimiterCounts::default(),
+        });
+        bucket.refill(now, self.burst, self.refill_per_sec);
+        bucket
+    }
+
+    /// Counts since startup and how busy the limiter and its busiest keys are now
+    pub async fn snapshot(&self) -> RateLimiterSnapshot {
+        let now = Instant::now();
+        let mut state = self.state.lock().await;
+        let totals = state.totals;
+        let mut keys: Vec<KeyUsage> = state
+            .buckets
+            .iter_mut()
+            .map(|(key, bucket)| {
+                bucket.refill(now, self.burst, self.refill_per_sec);
+                KeyUsage {
+                    key: key.clone(),
+                    saturation: ((self.burst - bucket.tokens) / self.burst).clamp(0.0, 1.0),
+                    counts: bucket.counts,
+                }
+            })
+            .collect();
+        drop(state);
+
+        keys.sort_by(|a, b| {
+            b.saturation
+                .total_cmp(&a.saturation)
+                .then(b.counts.acquired.cmp(&a.counts.acquired))
+        });
+        keys.truncate(SNAPSHOT_KEYS);
+        RateLimiterSnapshot {
+            in_flight: self.max_concurrent - self.semaphore.available_permits(),
+            max_concurrent: self.max_concurrent,
+            totals,
+            keys,
+        }
     }
 }
 
-/// RAII guard for rate limit permit
+/// RAII guard for rate limit permit, holds a concurrency slot until dropped
 pub struct RateLimitPermit {
     _permit: tokio::sync::OwnedSemaphorePermit,
-    key: String,
-    last_request: Arc<Mutex<HashMap<String, Instant>>>,
-}
-
-impl Drop for RateLimitPermit {
-    fn drop(&mut self) {
-        // Could update last request time here if needed
-    }
 }
 
 /// Global rate limiter for LLM API calls
 pub fn create_llm_rate_limiter() -> RateLimiter {
-    // Allow 5 concurrent requests, with minimum 200ms between requests per user
-    RateLimiter::new(5, 200)
+    // Allow 5 concurrent requests, bursts of 3 per channel refilling 5 a second
+    RateLimiter::new(5, 3, 5.0)
+}
+
+/// Rate limiter for chatting with chloe in DMs, where there's no guild budget to stop a
+/// user. Messages over the limit are turned away rather than queued.
+pub fn create_dm_rate_limiter() -> RateLimiter {
+    // Allow 5 concurrent DM chats, bursts of 5 per user refilling one every 12 seconds
+    RateLimiter::new(5, 5, 1.0 / 12.0)
 }
 
 /// Global rate limiter for external API calls (web search, etc.)
 pub fn create_api_rate_limiter() -> RateLimiter {
-    // Allow 10 concurrent requests, with minimum 100ms between requests
-    RateLimiter::new(10, 100)
-}
\ No newline at end of file
+    // Allow 10 concurrent requests, bursts of 5 per key refilling 10 a second
+    RateLimiter::new(10, 5, 10.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test(start_paused = true)]
+    async fn test_burst_then_refill() {
+        // 50ms per token
+        let limiter = RateLimiter::new(10, 3, 20.0);
+        let started = Instant::now();
+        for _ in 0..3 {
+            limiter.acquire("a".to_string()).await.unwrap();
+        }
+        assert_eq!(started.elapsed(), Duration::ZERO);
+
+        limiter.acquire("a".to_string()).await.unwrap();
+        assert!(started.elapsed() >= Duration::from_millis(50));
+
+        // other keys have their own bucket
+        let other = Instant::now();
+        limiter.acquire("b".to_string()).await.unwrap();
+        assert_eq!(other.elapsed(), Duration::ZERO);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_contention() {
+        // 20ms per token, six requests at once spend the burst of 2 and wait for 4 more
+        let limiter = Arc::new(RateLimiter::new(10, 2, 50.0));
+        let started = Instant::now();
+        let tasks: Vec<_> = (0..6)
+            .map(|_| {
+                let limiter = Arc::clone(&limiter);
+                tokio::spawn(async move {
+                    limiter.acquire("shared".to_string()).await.unwrap();
+                    started.elapsed()
+                })
+            })
+            .collect();
+        let mut finished = Vec::new();
+        for task in tasks {
+            finished.push(task.await.unwrap());
+        }
+        finished.sort();
+
+        assert_eq!(finished[1], Duration::ZERO);
+        assert!(finished[5] >= Duration::from_millis(80));
+        // reserved tokens keep the waiters spaced out rather than all waking at once
+        assert!(finished[5] - finished[2] >= Duration::from_millis(40));
+
+        let snapshot = limiter.snapshot().await;
+        assert_eq!(snapshot.totals.acquired, 6);
+        assert_eq!(snapshot.totals.waited, 4);
+        assert!(snapshot.totals.average_wait().unwrap() >= Duration::from_millis(50));
+        assert_eq!(snapshot.keys[0].key, "shared");
+    }
+
+    #[tokio::test]
+    async fn test_try_acquire_rejects() {
+        let limiter = RateLimiter::new(10, 2, 1.0);
+        assert!(limiter.try_acquire("a".to_string()).await.is_ok());
+        assert!(limiter.try_acquire("a".to_string()).await.is_ok());
+        match limiter.try_acquire("a".to_string()).await {
+            Err(RateLimitRejection::Interval { retry_after }) => {
+                assert!(retry_after > Duration::from_millis(900));
+                assert!(retry_after <= Duration::from_secs(1));
+            }
+            _ => panic!("expected the spent bucket to turn the request away"),
+        }
+        assert!(limiter.try_acquire("b".to_string()).await.is_ok());
+
+        let busy = RateLimiter::new(1, 10, 1.0);
+        let _held = busy.try_acquire("a".to_string()).await.ok().unwrap();
+        assert!(matches!(
+            busy.try_acquire("b".to_string()).await,
+            Err(RateLimitRejection::Concurrency)
+        ));
+
+        let snapshot = limiter.snapshot().await;
+        assert_eq!(snapshot.totals.acquired, 3);
+        assert_eq!(snapshot.totals.rejected_interval, 1);
+        assert_eq!(snapshot.keys[0].key, "a");
+        assert!(snapshot.keys[0].saturation > 0.99);
+        let busy = busy.snapshot().await;
+        assert_eq!(busy.in_flight, 1);
+        assert_eq!(busy.totals.rejected_concurrency, 1);
+    }
+
+    #[tokio::test]
+    async fn test_concurrency_limit() {
+        let limiter = RateLimiter::new(1, 10, 100.0);
+        let held = limiter.acquire("a".to_string()).await.unwrap();
+        let waiting =
+            tokio::time::timeout(Duration::from_millis(20), limiter.acquire("b".to_string())).await;
+        assert!(waiting.is_err());
+
+        drop(held);
+        assert!(limiter.acquire("b".to_string()).await.is_ok());
+    }
+}
@@ -0,0 +1,120 @@
+use redis::Client;
+use tracing::warn;
+
+/// Most reactions chloe adds to a single message
+pub const MAX_REACTIONS_PER_MESSAGE: usize = 3;
+
+/// Tool loops finish within minutes, the tracking only has to outlive them
+const TRACKING_TTL_SECS: i64 = 60 * 60;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ReactionClaim {
+    Allowed,
+    /// chloe already reacted with this emoji
+    Duplicate,
+    /// chloe already placed `MAX_REACTIONS_PER_MESSAGE` reactions
+    LimitReached,
+}
+
+/// The reactions chloe placed per message in redis, so retries in the tool loop can't
+/// pile up emojis on a message
+pub struct ReactionTracker {
+    redis_client: Client,
+}
+
+impl ReactionTracker {
+    pub fn new(redis_client: Client) -> Self {
+        Self { redis_client }
+    }
+
+    /// Reserve a reaction before adding it. Reactions are allowed when redis is
+    /// unreachable, discord still de-duplicates the same emoji.
+    pub async fn claim(&self, message_id: u64, emoji: &str) -> ReactionClaim {
+        match self.try_claim(message_id, emoji).await {
+            Ok(claim) => claim,
+            Err(e) => {
+                warn!(
+                    event = "reaction_tracking_failed",
+                    message_id = message_id,
+                    error = %e,
+                    "Failed to track reaction"
+                );
+                ReactionClaim::Allowed
+            }
+        }
+    }
+
+    /// Give a claim back when adding the reaction failed, so it can be tried again
+    pub async fn release(&self, message_id: u64, emoji: &str) {
+        let Ok(mut conn) = self.redis_client.get_multiplexed_async_connection().await else {
+            return;
+        };
+        let _: Result<(), _> = redis::cmd("SREM")
+            .arg(reactions_key(message_id))
+            .arg(emoji)
+            .query_async(&mut conn)
+            .await;
+    }
+
+    async fn try_claim(&self, message_id: u64, emoji: &str) -> Result<ReactionClaim, String> {
+        let mut conn = self
+            .redis_client
+            .get_multiplexed_async_connection()
+            .await
+            .map_err(|e| format!("Failed to connect to redis: {}", e))?;
+
+        let key = reactions_key(message_id);
+        let (added, placed): (usize, usize) = redis::pipe()
+            .sadd(&key, emoji)
+            .scard(&key)
+            .expire(&key, TRACKING_TTL_SECS)
+            .ignore()
+            .query_async(&mut conn)
+            .await
+            .map_err(|e| format!("Failed to track reaction: {}", e))?;
+
+        let claim = claim_outcome(added > 0, placed);
+        if claim == ReactionClaim::LimitReached {
+            let _: Result<(), _> = redis::cmd("SREM")
+                .arg(&key)
+                .arg(emoji)
+                .query_async(&mut conn)
+                .await;
+        }
+        Ok(claim)
+    }
+}
+
+/// `placed` counts the reaction being claimed when it was newly added
+fn claim_outcome(newly_added: bool, placed: usize) -> ReactionClaim {
+    if !newly_added {
+        ReactionClaim::Duplicate
+    } else if placed > MAX_REACTIONS_PER_MESSAGE {
+        ReactionClaim::LimitReached
+    } else {
+        ReactionClaim::Allowed
+    }
+}
+
+fn reactions_key(message_id: u64) -> String {
+    format!("chloe:reactions:{}", message_id)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_claim_outcome() {
+        assert_eq!(claim_outcome(true, 1), ReactionClaim::Allowed);
+        assert_eq!(
+            claim_outcome(true, MAX_REACTIONS_PER_MESSAGE),
+            ReactionClaim::Allowed
+        );
+        assert_eq!(
+            claim_outcome(true, MAX_REACTIONS_PER_MESSAGE + 1),
+            ReactionClaim::LimitReached
+        );
+        assert_eq!(claim_outcome(false, 2), ReactionClaim::Duplicate);
+    }
+}
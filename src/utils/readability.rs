@@ -0,0 +1,319 @@
+use scraper::{ElementRef, Html, Node, Selector};
+
+/// Elements that never hold article text
+const SKIPPED_TAGS: [&str; 13] = [
+    "script", "style", "noscript", "nav", "header", "footer", "aside", "form", "iframe", "svg",
+    "button", "template", "dialog",
+];
+
+/// class/id fragments marking page chrome rather than content
+const UNLIKELY_FRAGMENTS: [&str; 14] = [
+    "comment",
+    "sidebar",
+    "footer",
+    "menu",
+    "share",
+    "social",
+    "promo",
+    "related",
+    "cookie",
+    "banner",
+    "newsletter",
+    "subscribe",
+    "breadcrumb",
+    "advert",
+];
+
+const BLOCK_TAGS: [&str; 15] = [
+    "p",
+    "div",
+    "section",
+    "article",
+    "main",
+    "li",
+    "blockquote",
+    "pre",
+    "table",
+    "tr",
+    "ul",
+    "ol",
+    "figure",
+    "figcaption",
+    "br",
+];
+
+const HEADING_TAGS: [&str; 6] = ["h1", "h2", "h3", "h4", "h5", "h6"];
+
+/// Below this much text the best candidate is probably wrong, so use the whole body
+const MIN_ARTICLE_CHARS: usize = 200;
+
+/// Paragraphs shorter than this don't count towards a candidate's score
+const MIN_PARAGRAPH_CHARS: usize = 25;
+
+#[derive(Debug, Default, PartialEq)]
+pub struct Article {
+    pub title: Option<String>,
+    pub byline: Option<String>,
+    pub text: String,
+}
+
+impl Article {
+    /// Title and byline as a header over the text, the way the fetch tool returns it
+    pub fn to_model_text(&self) -> String {
+        let mut out = String::new();
+        if let Some(title) = &self.title {
+            out.push_str(&format!("Title: {}\n", title));
+        }
+        if let Some(byline) = &self.byline {
+            out.push_str(&format!("By: {}\n", byline));
+        }
+        if !out.is_empty() {
+            out.push('\n');
+        }
+        out.push_str(&self.text);
+        out
+    }
+}
+
+/// Readability-style extraction: score block elements by the paragraphs they hold, take
+/// the best one and flatten it to text, skipping navigation, ads and other chrome
+pub fn extract_article(html: &str) -> Article {
+    let document = Html::parse_document(html);
+
+    let title = page_title(&document);
+    let byline = meta_content(&document, r#"meta[name="author"]"#)
+        .or_else(|| meta_content(&document, r#"meta[property="article:author"]"#))
+        .or_else(|| first_text(&document, r#"[rel="author"], .byline, .author"#));
+
+    let candidate = best_candidate(&document);
+    let mut text = candidate.map(element_text).unwrap_or_default();
+    if text.chars().count() < MIN_ARTICLE_CHARS {
+        let body = selector("body")
+            .and_then(|body| document.select(&body).next())
+            .unwrap_or_else(|| document.root_element());
+        let body_text = element_text(body);
+        if body_text.len() > text.len() {
+            text = body_text;
+        }
+    }
+
+    Article {
+        title,
+        byline,
+        text,
+    }
+}
+
+/// Only the text of the elements matching a CSS selector, for when just part of a page
+/// matters. Err when the selector doesn't parse.
+pub fn extract_selection(html: &str, css: &str) -> Result<Article, String> {
+    let scoped =
+        Selector::parse(css).map_err(|e| format!("Invalid CSS selector '{}': {}", css, e))?;
+    let document = Html::parse_document(html);
+
+    let text = document
+        .select(&scoped)
+        .map(element_text)
+        .filter(|text| !text.is_empty())
+        .collect::<Vec<_>>()
+        .join("\n\n");
+
+    Ok(Article {
+        title: page_title(&document),
+        byline: None,
+        text,
+    })
+}
+
+fn page_title(document: &Html) -> Option<String> {
+    meta_content(document, r#"meta[property="og:title"]"#)
+        .or_else(|| first_text(document, "title"))
+        .or_else(|| first_text(document, "h1"))
+}
+
+fn selector(css: &str) -> Option<Selector> {
+    Selector::parse(css).ok()
+}
+
+fn meta_content(document: &Html, css: &str) -> Option<String> {
+    let selector = selector(css)?;
+    document
+        .select(&selector)
+        .filter_map(|meta| meta.value().attr("content"))
+        .map(collapse_whitespace)
+        .find(|content| !content.is_empty())
+}
+
+fn first_text(document: &Html, css: &str) -> Option<String> {
+    let selector = selector(css)?;
+    document
+        .select(&selector)
+        .map(|element| collapse_whitespace(&element.text().collect::<String>()))
+        .find(|text| !text.is_empty())
+}
+
+fn is_unlikely(element: &ElementRef) -> bool {
+    let value = element.value();
+    if SKIPPED_TAGS.contains(&value.name()) {
+        return true;
+    }
+    let marker = format!(
+        "{} {}",
+        value.attr("class").unwrap_or_default(),
+        value.attr("id").unwrap_or_default()
+    )
+    .to_lowercase();
+    UNLIKELY_FRAGMENTS
+        .iter()
+        .any(|fragment| marker.contains(fragment))
+}
+
+/// Whether the element or any ancestor is page chrome
+fn inside_unlikely(element: &ElementRef) -> bool {
+    std::iter::successors(Some(*element), |el| el.parent().and_then(ElementRef::wrap))
+        .any(|el| is_unlikely(&el))
+}
+
+fn best_candidate<'a>(document: &'a Html) -> Option<ElementRef<'a>> {
+    let paragraphs = selector("p, pre, td")?;
+    let mut scores: Vec<(ElementRef<'a>, f64)> = Vec::new();
+    let mut add_score = |element: ElementRef<'a>, score: f64| match scores
+        .iter_mut()
+        .find(|(candidate, _)| *candidate == element)
+    {
+        Some((_, total)) => *total += score,
+        None => scores.push((element, score)),
+    };
+
+    for paragraph in document.select(&paragraphs) {
+        if inside_unlikely(&paragraph) {
+            continue;
+        }
+        let text = collapse_whitespace(&paragraph.text().collect::<String>());
+        let length = text.chars().count();
+        if length < MIN_PARAGRAPH_CHARS {
+            continue;
+        }
+
+        // one point per paragraph, plus commas and length as signs of prose
+        let score = 1.0 + text.matches(',').count() as f64 + (length / 100).min(3) as f64;
+        let mut ancestors = paragraph.ancestors().filter_map(ElementRef::wrap);
+        if let Some(parent) = ancestors.next() {
+            add_score(parent, score);
+        }
+        if let Some(grandparent) = ancestors.next() {
+            add_score(grandparent, score / 2.0);
+        }
+    }
+
+    scores
+        .into_iter()
+        .max_by(|a, b| a.1.total_cmp(&b.1))
+        .map(|(element, _)| element)
+}
+
+/// Flatten an element to text with a line per block, headings and list items marked
+fn element_text(element: ElementRef) -> String {
+    let mut out = String::new();
+    push_text(element, &mut out);
+
+    let mut lines: Vec<String> = Vec::new();
+    for line in out.lines().map(collapse_whitespace) {
+        if !line.is_empty() {
+            lines.push(line);
+        }
+    }
+    lines.join("\n")
+}
+
+fn push_text(element: ElementRef, out: &mut String) {
+    for child in element.children() {
+        match child.value() {
+            Node::Text(text) => out.push_str(text),
+            Node::Element(_) => {
+                let Some(child) = ElementRef::wrap(child) else {
+                    continue;
+                };
+                if is_unlikely(&child) {
+                    continue;
+                }
+                let name = child.value().name();
+                let is_heading = HEADING_TAGS.contains(&name);
+                match name {
+                    _ if is_heading => out.push_str("\n## "),
+                    "li" => out.push_str("\n- "),
+                    _ if BLOCK_TAGS.contains(&name) => out.push('\n'),
+                    _ => {}
+                }
+                push_text(child, out);
+                if is_heading || BLOCK_TAGS.contains(&name) {
+                    out.push('\n');
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+fn collapse_whitespace(text: &str) -> String {
+    text.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PAGE: &str = r#"<html><head>
+        <title>Fallback title</title>
+        <meta property="og:title" content="Cats are great">
+        <meta name="author" content="Jane Doe">
+        </head><body>
+        <nav><a href="/">Home</a> <a href="/about">About us and other links</a></nav>
+        <div class="sidebar"><p>Subscribe to our newsletter, it is very good, honestly, really.</p></div>
+        <div id="content">
+            <h2>Why cats</h2>
+            <p>Cats are independent, curious and, despite their reputation, very affectionate animals.</p>
+            <p>They sleep for most of the day, which leaves plenty of time for knocking things off tables.</p>
+            <ul><li>Soft</li><li>Loud at 4am</li></ul>
+        </div>
+        <footer><p>Copyright 2024, all rights reserved, no really, all of them.</p></footer>
+        <script>var tracking = "please ignore me";</script>
+        </body></html>"#;
+
+    #[test]
+    fn test_extract_article_keeps_content_and_drops_chrome() {
+        let article = extract_article(PAGE);
+        assert_eq!(article.title.as_deref(), Some("Cats are great"));
+        assert_eq!(article.byline.as_deref(), Some("Jane Doe"));
+
+        assert!(article.text.contains("## Why cats"));
+        assert!(article.text.contains("- Loud at 4am"));
+        assert!(article.text.contains("knocking things off tables"));
+        for chrome in ["newsletter", "Copyright", "About us", "tracking"] {
+            assert!(!article.text.contains(chrome), "kept {}", chrome);
+        }
+    }
+
+    #[test]
+    fn test_extract_selection_scopes_to_matches() {
+        let article = extract_selection(PAGE, "li").unwrap();
+        assert_eq!(article.title.as_deref(), Some("Cats are great"));
+        assert_eq!(article.text, "Soft\n\nLoud at 4am");
+
+        assert!(
+            extract_selection(PAGE, ".nothing-here")
+                .unwrap()
+                .text
+                .is_empty()
+        );
+        assert!(extract_selection(PAGE, "p[").is_err());
+    }
+
+    #[test]
+    fn test_extract_article_falls_back_to_body_text() {
+        let article = extract_article("<html><body><span>just a short note</span></body></html>");
+        assert_eq!(article.title, None);
+        assert_eq!(article.text, "just a short note");
+        assert_eq!(article.to_model_text(), "just a short note");
+    }
+}
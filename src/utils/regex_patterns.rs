@@ -48,6 +48,15 @@ pub static ESCAPED_CHAR_REGEX: Lazy<Regex> = Lazy::new(|| {
         })
 });
 
+// Discord message link, capturing guild, channel and message ids
+pub static MESSAGE_LINK_REGEX: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"https://(?:(?:ptb|canary)\.)?discord(?:app)?\.com/channels/(\d+|@me)/(\d+)/(\d+)")
+        .unwrap_or_else(|e| {
+            error!("Failed to compile MESSAGE_LINK_REGEX: {}", e);
+            Regex::new(r"^$").unwrap()
+        })
+});
+
 // Impersonation pattern
 pub static IMPERSONATION_PATTERN: Lazy<Regex> = Lazy::new(|| {
     Regex::new(r"(?m)^([A-Za-z0-9_\-\.]+\s*:\s*.+)$")
@@ -84,6 +93,78 @@ pub static GUILD_EMOJI_REGEX: Lazy<Regex> = Lazy::new(|| {
         })
 });
 
+// Bot mentions and chloe's name, stripped before intent classification
+pub static ADDRESSING_REGEX: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"(?i)<@!?\d+>|\bchloe\b")
+        .unwrap_or_else(|e| {
+            error!("Failed to compile ADDRESSING_REGEX: {}", e);
+            Regex::new(r"^$").unwrap()
+        })
+});
+
+// Leading "what's"/"calc" in front of an arithmetic question
+pub static MATH_PREFIX_REGEX: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"^(what'?s|what is|calc|calculate)\s+")
+        .unwrap_or_else(|e| {
+            error!("Failed to compile MATH_PREFIX_REGEX: {}", e);
+            Regex::new(r"^$").unwrap()
+        })
+});
+
+// Dates and scores like 2025-10-15, 10/10 or 24/7, which only look like arithmetic
+pub static DATE_OR_SCORE_REGEX: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"\d+[-/]\d+")
+        .unwrap_or_else(|e| {
+            error!("Failed to compile DATE_OR_SCORE_REGEX: {}", e);
+            Regex::new(r"^$").unwrap()
+        })
+});
+
+// Hints that a message is about code, used for model routing
+pub static CODE_HINT_REGEX: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"(?i)```|\b(code|coding|function|compile[sd]?|compiler|stack ?trace|regex|sql|rust|python|javascript|typescript|golang|c\+\+|debug|segfault|refactor)\b|\b(fn|def|func)\s+\w+\s*\(")
+        .unwrap_or_else(|e| {
+            error!("Failed to compile CODE_HINT_REGEX: {}", e);
+            Regex::new(r"^$").unwrap()
+        })
+});
+
+// Discord snowflake ids, scrubbed from recorded provider traffic
+pub static SNOWFLAKE_REGEX: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"\b\d{17,20}\b")
+        .unwrap_or_else(|e| {
+            error!("Failed to compile SNOWFLAKE_REGEX: {}", e);
+            Regex::new(r"^$").unwrap()
+        })
+});
+
+// Email addresses, scrubbed from recorded provider traffic
+pub static EMAIL_REGEX: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"[A-Za-z0-9._%+\-]+@[A-Za-z0-9.\-]+\.[A-Za-z]{2,}")
+        .unwrap_or_else(|e| {
+            error!("Failed to compile EMAIL_REGEX: {}", e);
+            Regex::new(r"^$").unwrap()
+        })
+});
+
+// charset= in a content type or meta tag, encoding= in an xml declaration
+pub static CHARSET_REGEX: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r#"(?i)(?:charset|encoding)\s*=\s*["']?([A-Za-z0-9_\-:.]+)"#)
+        .unwrap_or_else(|e| {
+            error!("Failed to compile CHARSET_REGEX: {}", e);
+            Regex::new(r"^$").unwrap()
+        })
+});
+
+// First-person self-harm and suicide phrases that get crisis resources instead of a reply
+pub static CRISIS_REGEX: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"(?i)\b(?:(?:kill|killing|harm|harming)\s+my\s?self|(?:want|wanna|ready)\s+(?:to\s+)?die|end(?:ing)?\s+(?:my\s+(?:own\s+)?life|it\s+all)|take\s+my\s+(?:own\s+)?life|suicidal|(?:commit|committing|thinking\s+(?:about|of))\s+suicide|self[\s-]?harm(?:ing)?|(?:don['’]?t|do\s+not)\s+want\s+to\s+(?:live|be\s+alive|exist)|no\s+reason\s+to\s+live|better\s+off\s+dead)\b")
+        .unwrap_or_else(|e| {
+            error!("Failed to compile CRISIS_REGEX: {}", e);
+            Regex::new(r"^$").unwrap()
+        })
+});
+
 #[cfg(test)]
 mod tests {
     use super::*;
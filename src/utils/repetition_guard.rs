@@ -0,0 +1,104 @@
+use std::collections::{HashMap, HashSet, VecDeque};
+use tokio::sync::RwLock;
+
+/// How many of chloe's recent messages are remembered per channel
+const HISTORY_PER_CHANNEL: usize = 5;
+
+/// Short replies like "lol" or "hiii" are fine to repeat
+const MIN_WORDS: usize = 4;
+
+/// Word-set overlap above which two messages count as the same
+const SIMILARITY_THRESHOLD: f64 = 0.9;
+
+/// Remembers what chloe recently sent in each channel so near-verbatim repeats can
+/// be caught before they go out
+pub struct RepetitionGuard {
+    recent: RwLock<HashMap<u64, VecDeque<HashSet<String>>>>,
+}
+
+impl RepetitionGuard {
+    pub fn new() -> Self {
+        Self {
+            recent: RwLock::new(HashMap::new()),
+        }
+    }
+
+    pub async fn is_repeat(&self, channel_id: u64, content: &str) -> bool {
+        let words = normalize(content);
+        if words.len() < MIN_WORDS {
+            return false;
+        }
+
+        let recent = self.recent.read().await;
+        recent
+            .get(&channel_id)
+            .map(|messages| {
+                messages
+                    .iter()
+                    .any(|previous| similarity(&words, previous) >= SIMILARITY_THRESHOLD)
+            })
+            .unwrap_or(false)
+    }
+
+    pub async fn record(&self, channel_id: u64, content: &str) {
+        let mut recent = self.recent.write().await;
+        let messages = recent.entry(channel_id).or_default();
+        messages.push_back(normalize(content));
+        if messages.len() > HISTORY_PER_CHANNEL {
+            messages.pop_front();
+        }
+    }
+}
+
+fn normalize(text: &str) -> HashSet<String> {
+    text.to_lowercase()
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .map(|word| word.to_string())
+        .collect()
+}
+
+/// Jaccard similarity of the two word sets
+fn similarity(a: &HashSet<String>, b: &HashSet<String>) -> f64 {
+    let union = a.union(b).count();
+    if union == 0 {
+        return 0.0;
+    }
+    a.intersection(b).count() as f64 / union as f64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_detects_near_verbatim_repeats() {
+        let guard = RepetitionGuard::new();
+        guard
+            .record(1, "omg bestie that outfit is SO cute, slay 💅")
+            .await;
+
+        assert!(
+            guard
+                .is_repeat(1, "OMG bestie, that outfit is so cute!! slay")
+                .await
+        );
+        assert!(
+            !guard
+                .is_repeat(1, "honestly the rain today is kinda cozy")
+                .await
+        );
+        assert!(
+            !guard
+                .is_repeat(2, "omg bestie that outfit is so cute slay")
+                .await
+        );
+    }
+
+    #[tokio::test]
+    async fn test_short_messages_are_not_guarded() {
+        let guard = RepetitionGuard::new();
+        guard.record(1, "lol same").await;
+        assert!(!guard.is_repeat(1, "lol same").await);
+    }
+}
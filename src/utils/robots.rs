@@ -0,0 +1,141 @@
+/// The allow/disallow rules a robots.txt sets for one user agent
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct RobotsRules {
+    // (allow, path pattern)
+    rules: Vec<(bool, String)>,
+}
+
+impl RobotsRules {
+    /// No robots.txt, or one that couldn't be read, allows everything
+    pub fn allow_all() -> Self {
+        Self::default()
+    }
+
+    /// Rules of the groups naming `user_agent`, or of the `*` group when none do
+    pub fn parse(body: &str, user_agent: &str) -> Self {
+        let user_agent = user_agent.to_lowercase();
+        let mut specific = Vec::new();
+        let mut wildcard = Vec::new();
+        let mut found_specific = false;
+
+        let mut in_agent_lines = false;
+        let (mut group_specific, mut group_wildcard) = (false, false);
+        for line in body.lines() {
+            let line = line.split('#').next().unwrap_or_default().trim();
+            let Some((key, value)) = line.split_once(':') else {
+                continue;
+            };
+            let (key, value) = (key.trim().to_lowercase(), value.trim());
+
+            if key == "user-agent" {
+                // consecutive user-agent lines share one group
+                if !in_agent_lines {
+                    (group_specific, group_wildcard) = (false, false);
+                }
+                in_agent_lines = true;
+                let agent = value.to_lowercase();
+                if agent == "*" {
+                    group_wildcard = true;
+                } else if !agent.is_empty() && user_agent.contains(&agent) {
+                    group_specific = true;
+                    found_specific = true;
+                }
+                continue;
+            }
+            in_agent_lines = false;
+
+            let allow = match key.as_str() {
+                "allow" => true,
+                "disallow" => false,
+                _ => continue,
+            };
+            // an empty disallow allows everything, which is already the default
+            if value.is_empty() {
+                continue;
+            }
+            if group_specific {
+                specific.push((allow, value.to_string()));
+            }
+            if group_wildcard {
+                wildcard.push((allow, value.to_string()));
+            }
+        }
+
+        Self {
+            rules: if found_specific { specific } else { wildcard },
+        }
+    }
+
+    /// The longest matching rule decides, allow wins a tie
+    pub fn is_allowed(&self, path: &str) -> bool {
+        self.rules
+            .iter()
+            .filter(|(_, pattern)| pattern_matches(pattern, path))
+            .max_by_key(|(allow, pattern)| (pattern.len(), *allow))
+            .map(|(allow, _)| *allow)
+            .unwrap_or(true)
+    }
+}
+
+/// robots.txt patterns match path prefixes, `*` matches any run of characters and a
+/// trailing `$` anchors the end
+fn pattern_matches(pattern: &str, path: &str) -> bool {
+    let (pattern, anchored) = match pattern.strip_suffix('$') {
+        Some(pattern) => (pattern, true),
+        None => (pattern, false),
+    };
+
+    let mut pieces = pattern.split('*');
+    let Some(mut rest) = path.strip_prefix(pieces.next().unwrap_or_default()) else {
+        return false;
+    };
+    let pieces: Vec<&str> = pieces.collect();
+    for (i, piece) in pieces.iter().enumerate() {
+        if anchored && i == pieces.len() - 1 {
+            return rest.ends_with(piece);
+        }
+        match rest.find(piece) {
+            Some(start) => rest = &rest[start + piece.len()..],
+            None => return false,
+        }
+    }
+    !anchored || rest.is_empty()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ROBOTS: &str = "
+        # everyone
+        User-agent: *
+        Disallow: /private/
+        Allow: /private/public-page
+        Disallow: /*.pdf$
+
+        User-agent: GoogleBot
+        User-agent: ChloeBot
+        Disallow: /no-chloe
+    ";
+
+    #[test]
+    fn test_parse_prefers_named_group() {
+        let rules = RobotsRules::parse(ROBOTS, "ChloeBot/1.0");
+        assert!(!rules.is_allowed("/no-chloe/page"));
+        assert!(rules.is_allowed("/private/secret"));
+
+        let rules = RobotsRules::parse(ROBOTS, "OtherBot");
+        assert!(rules.is_allowed("/no-chloe"));
+        assert!(!rules.is_allowed("/private/secret"));
+    }
+
+    #[test]
+    fn test_longest_match_and_wildcards() {
+        let rules = RobotsRules::parse(ROBOTS, "OtherBot");
+        assert!(rules.is_allowed("/private/public-page"));
+        assert!(!rules.is_allowed("/files/report.pdf"));
+        assert!(rules.is_allowed("/files/report.pdf?download=1"));
+        assert!(rules.is_allowed("/"));
+        assert!(RobotsRules::allow_all().is_allowed("/anything"));
+    }
+}
@@ -0,0 +1,151 @@
+use crate::services::llm_service::ImageData;
+use std::path::Path;
+use std::process::Stdio;
+use tokio::process::Command;
+use tokio::time::{Duration, timeout};
+use tracing::info;
+
+/// Skip videos bigger than this, discord's free upload cap
+pub const MAX_VIDEO_BYTES: u32 = 25 * 1024 * 1024;
+
+/// Only short clips are sampled, longer videos need more than a few frames to make sense
+const MAX_VIDEO_SECS: f64 = 90.0;
+
+const FRAME_COUNT: usize = 4;
+
+/// Per ffmpeg/ffprobe invocation
+const PROCESS_TIMEOUT: Duration = Duration::from_secs(15);
+
+/// Extract a few evenly spaced keyframes from a short video as jpegs, using the
+/// ffmpeg/ffprobe binaries on PATH
+pub async fn sample_frames(video: &[u8]) -> Result<Vec<ImageData>, String> {
+    let path = std::env::temp_dir().join(format!(
+        "chloe_video_{}",
+        chrono::Utc::now().timestamp_nanos_opt().unwrap_or(0)
+    ));
+    tokio::fs::write(&path, video)
+        .await
+        .map_err(|e| format!("Failed to write video to disk: {}", e))?;
+
+    let result = sample_frames_from_file(&path).await;
+    let _ = tokio::fs::remove_file(&path).await;
+    result
+}
+
+async fn sample_frames_from_file(path: &Path) -> Result<Vec<ImageData>, String> {
+    let duration = probe_duration(path).await?;
+    if duration > MAX_VIDEO_SECS {
+        return Err(format!(
+            "Video is {:.0}s long, only clips up to {:.0}s are sampled",
+            duration, MAX_VIDEO_SECS
+        ));
+    }
+
+    let mut frames = Vec::new();
+    for timestamp in frame_timestamps(duration, FRAME_COUNT) {
+        let jpeg = run(Command::new("ffmpeg").args([
+            "-v",
+            "error",
+            "-ss",
+            &format!("{:.2}", timestamp),
+            "-i",
+            &path.to_string_lossy(),
+            "-frames:v",
+            "1",
+            "-vf",
+            "scale='min(1024,iw)':-2",
+            "-f",
+            "image2pipe",
+            "-vcodec",
+            "mjpeg",
+            "-",
+        ]))
+        .await?;
+
+        if !jpeg.is_empty() {
+            frames.push(ImageData {
+                base64_data: base64::Engine::encode(
+                    &base64::engine::general_purpose::STANDARD,
+                    &jpeg,
+                ),
+                mime_type: "image/jpeg".to_string(),
+            });
+        }
+    }
+
+    info!(
+        event = "video_frames_sampled",
+        duration_secs = duration,
+        frame_count = frames.len(),
+        "Sampled frames from video attachment"
+    );
+
+    Ok(frames)
+}
+
+async fn probe_duration(path: &Path) -> Result<f64, String> {
+    let output = run(Command::new("ffprobe").args([
+        "-v",
+        "error",
+        "-show_entries",
+        "format=duration",
+        "-of",
+        "default=noprint_wrappers=1:nokey=1",
+        &path.to_string_lossy(),
+    ]))
+    .await?;
+
+    String::from_utf8_lossy(&output)
+        .trim()
+        .parse::<f64>()
+        .map_err(|_| "Could not read video duration".to_string())
+}
+
+async fn run(command: &mut Command) -> Result<Vec<u8>, String> {
+    let child = command
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .kill_on_drop(true)
+        .spawn()
+        .map_err(|e| {
+            format!(
+                "Failed to start {:?}: {}",
+                command.as_std().get_program(),
+                e
+            )
+        })?;
+
+    let output = timeout(PROCESS_TIMEOUT, child.wait_with_output())
+        .await
+        .map_err(|_| "Video processing timed out".to_string())?
+        .map_err(|e| format!("Video processing failed: {}", e))?;
+
+    if !output.status.success() {
+        return Err(format!(
+            "Video processing failed: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+
+    Ok(output.stdout)
+}
+
+/// Timestamps at the middle of `count` equal slices, so the first and last frames
+/// aren't black fade-ins
+fn frame_timestamps(duration: f64, count: usize) -> Vec<f64> {
+    (0..count)
+        .map(|i| duration * (i as f64 + 0.5) / count as f64)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_frame_timestamps_are_evenly_spaced() {
+        assert_eq!(frame_timestamps(8.0, 4), vec![1.0, 3.0, 5.0, 7.0]);
+        assert_eq!(frame_timestamps(2.0, 1), vec![1.0]);
+    }
+}